@@ -0,0 +1,35 @@
+-- 百科页面修订历史：每次草稿通过、直接提交或回滚时，为每个变化的页面记录一条不可变修订
+-- 页面被删除后修订依然保留（wiki_id 不设外键），用于整体回滚时恢复页面
+CREATE TABLE wiki_revisions (
+    id               bigserial PRIMARY KEY,
+    wiki_id          bigint NOT NULL,
+    mod_id           bigint NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+    wiki_cache_id    bigint REFERENCES wiki_cache(id) ON DELETE SET NULL,
+    revision_number  integer NOT NULL,
+    -- initial / create / edit / delete / rollback
+    action           varchar(16) NOT NULL,
+    author_id        bigint REFERENCES users(id) ON DELETE SET NULL,
+    reviewer_id      bigint REFERENCES users(id) ON DELETE SET NULL,
+    message          varchar(500) NOT NULL DEFAULT '',
+    parent_wiki_id   bigint NOT NULL,
+    sort_order       integer NOT NULL,
+    title            varchar(255) NOT NULL,
+    slug             varchar(255) NOT NULL,
+    body             varchar(65536) NOT NULL,
+    featured         boolean NOT NULL,
+    created          timestamptz NOT NULL DEFAULT NOW(),
+    UNIQUE (wiki_id, revision_number)
+);
+
+CREATE INDEX idx_wiki_revisions_mod_created
+    ON wiki_revisions (mod_id, created DESC);
+
+-- 为已发布的页面补录初始版本，作为之后对比与回滚的基线
+INSERT INTO wiki_revisions (
+    wiki_id, mod_id, revision_number, action, parent_wiki_id,
+    sort_order, title, slug, body, featured, created
+)
+SELECT id, mod_id, 1, 'initial', parent_wiki_id,
+       sort_order, title, slug, body, featured, updated
+FROM wikis
+WHERE draft = FALSE;
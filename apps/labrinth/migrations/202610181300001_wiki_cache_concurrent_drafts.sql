@@ -0,0 +1,9 @@
+-- 百科并发编辑：每个用户各自持有草稿，通过时与当前内容做三方合并
+-- base_revisions: 开始编辑时各页面的最新修订 id（{"wiki_id": revision_id}）
+-- conflicts: 最近一次合并失败时的冲突列表，供提交者处理
+ALTER TABLE wiki_cache
+    ADD COLUMN base_revisions jsonb NOT NULL DEFAULT '{}'::jsonb,
+    ADD COLUMN conflicts jsonb NOT NULL DEFAULT '[]'::jsonb;
+
+CREATE INDEX IF NOT EXISTS idx_wiki_cache_mod_status
+    ON wiki_cache (mod_id, status);
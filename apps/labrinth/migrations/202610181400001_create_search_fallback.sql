@@ -0,0 +1,39 @@
+-- 未部署 MeiliSearch 时使用的数据库搜索后端（SEARCH_BACKEND=postgres）
+-- search_vector 由应用按中日韩单字与双字切分后直接写入，不依赖数据库的分词配置
+
+-- 项目搜索文档：与 MeiliSearch 索引一致，每个版本一条，按 project_id 去重
+CREATE TABLE search_projects (
+    version_id     varchar(64) PRIMARY KEY,
+    project_id     varchar(64) NOT NULL,
+    document       jsonb NOT NULL,
+    search_vector  tsvector NOT NULL,
+    downloads      integer NOT NULL,
+    follows        integer NOT NULL,
+    date_created   timestamptz NOT NULL,
+    date_modified  timestamptz NOT NULL
+);
+
+CREATE INDEX idx_search_projects_project_id ON search_projects (project_id);
+CREATE INDEX idx_search_projects_search_vector
+    ON search_projects USING gin (search_vector);
+CREATE INDEX idx_search_projects_document
+    ON search_projects USING gin (document jsonb_path_ops);
+
+-- 站内内容搜索文档：百科、论坛帖子与回复、问题与评论
+CREATE TABLE search_content (
+    id                  varchar(128) PRIMARY KEY,
+    content_type        varchar(32) NOT NULL,
+    content_id          varchar(64) NOT NULL,
+    parent_id           varchar(64),
+    project_id          varchar(64),
+    document            jsonb NOT NULL,
+    search_vector       tsvector NOT NULL,
+    modified_timestamp  bigint NOT NULL
+);
+
+CREATE INDEX idx_search_content_type_content
+    ON search_content (content_type, content_id);
+CREATE INDEX idx_search_content_parent ON search_content (parent_id);
+CREATE INDEX idx_search_content_project ON search_content (project_id);
+CREATE INDEX idx_search_content_search_vector
+    ON search_content USING gin (search_vector);
@@ -0,0 +1,8 @@
+-- 待更新搜索索引的项目：项目或版本变更时写入，由后台任务逐批重建这些项目的文档
+-- 不引用 mods，项目删除后仍需处理以移除索引中的文档
+CREATE TABLE search_index_queue (
+    project_id  bigint PRIMARY KEY,
+    queued_at   timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_search_index_queue_queued_at ON search_index_queue (queued_at);
@@ -0,0 +1,2 @@
+-- 地图板块的结构文件与存档解析出的尺寸、方块统计、游戏版本等信息
+ALTER TABLE files ADD COLUMN structure_metadata jsonb NULL;
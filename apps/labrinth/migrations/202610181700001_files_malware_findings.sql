@@ -0,0 +1,2 @@
+-- jar 文件的可疑代码扫描结果；NULL 表示上传时尚未扫描，由审核队列补扫
+ALTER TABLE files ADD COLUMN malware_findings jsonb NULL;
@@ -0,0 +1,15 @@
+-- 1. 已计入下载数的分析事件，事件队列重放时避免重复计数；一周后清理
+CREATE TABLE analytics_processed_events (
+    event_id        text PRIMARY KEY,
+    processed_at    timestamptz NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_analytics_processed_events_time
+    ON analytics_processed_events (processed_at);
+
+-- 2. 激励事件的队列事件 ID（旧数据为 NULL）
+ALTER TABLE incentive_download_events
+    ADD COLUMN event_id text NULL;
+
+CREATE UNIQUE INDEX idx_incentive_events_event_id
+    ON incentive_download_events (event_id);
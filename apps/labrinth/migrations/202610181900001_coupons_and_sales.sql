@@ -0,0 +1,79 @@
+-- 1. 优惠券：归属于项目或组织（组织优惠券适用于组织下的所有付费项目）
+CREATE TABLE coupons (
+    id              BIGINT PRIMARY KEY,
+    code            VARCHAR(32) NOT NULL,
+    project_id      BIGINT REFERENCES mods(id) ON DELETE CASCADE,
+    organization_id BIGINT REFERENCES organizations(id) ON DELETE CASCADE,
+    discount_type   VARCHAR(16) NOT NULL,
+    discount_value  DECIMAL(10, 2) NOT NULL,
+    max_uses        INTEGER,                      -- 总使用次数上限，NULL 表示不限
+    per_user_limit  INTEGER,                      -- 每个用户的使用次数上限，NULL 表示不限
+    starts_at       TIMESTAMPTZ,
+    ends_at         TIMESTAMPTZ,
+    enabled         BOOLEAN DEFAULT TRUE NOT NULL,
+    created_by      BIGINT NOT NULL REFERENCES users(id),
+    created_at      TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    CONSTRAINT check_coupons_owner
+        CHECK ((project_id IS NULL) <> (organization_id IS NULL)),
+    CONSTRAINT check_coupons_discount_type_valid
+        CHECK (discount_type IN ('percent', 'fixed')),
+    CONSTRAINT check_coupons_discount_value
+        CHECK (discount_value > 0 AND (discount_type <> 'percent' OR discount_value < 100)),
+    CONSTRAINT check_coupons_limits
+        CHECK ((max_uses IS NULL OR max_uses > 0) AND (per_user_limit IS NULL OR per_user_limit > 0)),
+    CONSTRAINT check_coupons_window
+        CHECK (starts_at IS NULL OR ends_at IS NULL OR ends_at > starts_at)
+);
+
+-- 优惠码在同一项目/组织内唯一，不区分大小写
+CREATE UNIQUE INDEX idx_coupons_project_code
+    ON coupons (project_id, UPPER(code)) WHERE project_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_coupons_organization_code
+    ON coupons (organization_id, UPPER(code)) WHERE organization_id IS NOT NULL;
+
+COMMENT ON TABLE coupons IS '优惠券，按百分比或固定金额减免付费项目的价格';
+COMMENT ON COLUMN coupons.discount_value IS '百分比减免时为减免的百分比（1-99），固定减免时为减免金额（元）';
+
+-- 限定可使用优惠券的用户；没有记录表示所有用户可用
+CREATE TABLE coupon_allowed_users (
+    coupon_id       BIGINT NOT NULL REFERENCES coupons(id) ON DELETE CASCADE,
+    user_id         BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (coupon_id, user_id)
+);
+
+-- 2. 项目定价的限时促销窗口
+CREATE TABLE project_sales (
+    id              BIGINT PRIMARY KEY,
+    project_id      BIGINT NOT NULL REFERENCES project_pricing(project_id) ON DELETE CASCADE,
+    sale_price      DECIMAL(10, 2) NOT NULL CHECK (sale_price > 0),
+    starts_at       TIMESTAMPTZ NOT NULL,
+    ends_at         TIMESTAMPTZ NOT NULL,
+    created_at      TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    CONSTRAINT check_project_sales_window CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX idx_project_sales_project ON project_sales (project_id, ends_at);
+
+COMMENT ON TABLE project_sales IS '项目限时促销，促销期间按 sale_price 收费';
+
+-- 3. 订单记录原价与优惠，amount 为实际支付金额
+ALTER TABLE payment_orders
+    ADD COLUMN original_amount DECIMAL(10, 2),
+    ADD COLUMN discount_amount DECIMAL(10, 2) DEFAULT 0 NOT NULL,
+    ADD COLUMN coupon_id BIGINT REFERENCES coupons(id) ON DELETE SET NULL,
+    ADD COLUMN sale_id BIGINT REFERENCES project_sales(id) ON DELETE SET NULL;
+
+UPDATE payment_orders SET original_amount = amount;
+
+ALTER TABLE payment_orders
+    ALTER COLUMN original_amount SET NOT NULL,
+    ADD CONSTRAINT check_payment_orders_discount_consistency
+        CHECK (discount_amount >= 0 AND amount = original_amount - discount_amount);
+
+CREATE INDEX idx_payment_orders_coupon_id
+    ON payment_orders (coupon_id) WHERE coupon_id IS NOT NULL;
+
+COMMENT ON COLUMN payment_orders.original_amount IS '订单原价（项目定价）';
+COMMENT ON COLUMN payment_orders.discount_amount IS '促销与优惠券减免的金额，amount = original_amount - discount_amount';
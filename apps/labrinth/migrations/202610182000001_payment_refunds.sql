@@ -0,0 +1,46 @@
+-- 1. 项目的退款政策
+ALTER TABLE project_pricing
+    ADD COLUMN refund_window_days INTEGER DEFAULT 7 NOT NULL,
+    ADD COLUMN refund_policy TEXT,
+    ADD CONSTRAINT check_project_pricing_refund_window
+        CHECK (refund_window_days >= 0 AND refund_window_days <= 90);
+
+COMMENT ON COLUMN project_pricing.refund_window_days IS '支付后可申请退款的天数（0-90），0 表示不接受退款申请';
+COMMENT ON COLUMN project_pricing.refund_policy IS '展示给买家的退款说明';
+
+-- 2. 退款记录：买家申请、作者或管理员审核，以及支付平台发起的退款（拒付）
+CREATE TABLE payment_refunds (
+    id                  BIGINT PRIMARY KEY,
+    order_id            BIGINT NOT NULL REFERENCES payment_orders(id) ON DELETE CASCADE,
+    user_id             BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project_id          BIGINT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+    seller_id           BIGINT NOT NULL REFERENCES users(id),
+    amount              DECIMAL(10, 2) NOT NULL CHECK (amount > 0),
+    reason              TEXT NOT NULL,
+    status              VARCHAR(20) DEFAULT 'pending' NOT NULL,
+    source              VARCHAR(20) NOT NULL,
+    thread_id           BIGINT REFERENCES threads(id) ON DELETE SET NULL,
+    reviewer_id         BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    review_note         TEXT,
+    external_refund_no  VARCHAR(64),
+    clawback_amount     DECIMAL(10, 2) DEFAULT 0 NOT NULL,
+    created_at          TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    reviewed_at         TIMESTAMPTZ,
+    completed_at        TIMESTAMPTZ,
+
+    CONSTRAINT check_payment_refunds_status_valid
+        CHECK (status IN ('pending', 'rejected', 'cancelled', 'refunded')),
+    CONSTRAINT check_payment_refunds_source_valid
+        CHECK (source IN ('buyer', 'chargeback'))
+);
+
+-- 每个订单只能有一条未取消的退款记录
+CREATE UNIQUE INDEX idx_payment_refunds_order
+    ON payment_refunds (order_id) WHERE status <> 'cancelled';
+CREATE INDEX idx_payment_refunds_user ON payment_refunds (user_id, created_at DESC);
+CREATE INDEX idx_payment_refunds_project ON payment_refunds (project_id, created_at DESC);
+CREATE INDEX idx_payment_refunds_pending
+    ON payment_refunds (created_at) WHERE status = 'pending';
+
+COMMENT ON TABLE payment_refunds IS '订单退款记录，退款完成后订单状态为 refunded、购买授权被撤销';
+COMMENT ON COLUMN payment_refunds.clawback_amount IS '由平台垫付退款时从创作者收益中扣回的金额';
@@ -0,0 +1,31 @@
+-- 1. 付费项目许可证：每条购买记录对应一个许可证，用于签发离线可验证的许可令牌
+CREATE TABLE project_licenses (
+    id               BIGINT PRIMARY KEY,
+    purchase_id      BIGINT NOT NULL REFERENCES user_purchases(id) ON DELETE CASCADE,
+    user_id          BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project_id       BIGINT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+    max_activations  INTEGER NOT NULL CHECK (max_activations > 0),
+    created_at       TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    revoked_at       TIMESTAMPTZ,
+    revoke_reason    TEXT
+);
+
+CREATE UNIQUE INDEX idx_project_licenses_purchase ON project_licenses (purchase_id);
+CREATE INDEX idx_project_licenses_user ON project_licenses (user_id);
+CREATE INDEX idx_project_licenses_project ON project_licenses (project_id);
+
+COMMENT ON TABLE project_licenses IS '付费项目许可证，令牌中的过期时间取自对应购买记录';
+COMMENT ON COLUMN project_licenses.max_activations IS '允许同时激活的服务器实例数量';
+
+-- 2. 许可证激活记录：每个服务器实例首次在线验证时登记
+CREATE TABLE project_license_activations (
+    license_id     BIGINT NOT NULL REFERENCES project_licenses(id) ON DELETE CASCADE,
+    instance_id    VARCHAR(128) NOT NULL,
+    first_seen_at  TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_seen_at   TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_ip        VARCHAR(64),
+
+    PRIMARY KEY (license_id, instance_id)
+);
+
+COMMENT ON TABLE project_license_activations IS '许可证在各服务器实例上的激活记录';
@@ -0,0 +1,75 @@
+-- 1. 项目捆绑包：由用户或组织以组合价格出售多个付费项目
+CREATE TABLE project_bundles (
+    id               BIGINT PRIMARY KEY,
+    user_id          BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    organization_id  BIGINT REFERENCES organizations(id) ON DELETE CASCADE,
+    name             VARCHAR(64) NOT NULL,
+    description      TEXT DEFAULT '' NOT NULL,
+    price            DECIMAL(10, 2) NOT NULL CHECK (price > 0),
+    active           BOOLEAN DEFAULT TRUE NOT NULL,
+    created_by       BIGINT NOT NULL REFERENCES users(id),
+    created_at       TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    updated_at       TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    CONSTRAINT check_project_bundles_owner
+        CHECK ((user_id IS NULL) <> (organization_id IS NULL))
+);
+
+CREATE INDEX idx_project_bundles_user ON project_bundles (user_id) WHERE user_id IS NOT NULL;
+CREATE INDEX idx_project_bundles_organization ON project_bundles (organization_id) WHERE organization_id IS NOT NULL;
+
+COMMENT ON TABLE project_bundles IS '项目捆绑包，买家已拥有的项目按原价比例从捆绑包价格中扣除';
+
+CREATE TABLE project_bundle_items (
+    bundle_id   BIGINT NOT NULL REFERENCES project_bundles(id) ON DELETE CASCADE,
+    project_id  BIGINT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (bundle_id, project_id)
+);
+
+CREATE INDEX idx_project_bundle_items_project ON project_bundle_items (project_id);
+
+-- 2. 订单明细：每个订单包含一个或多个项目，收入按项目拆分
+CREATE TABLE payment_order_items (
+    order_id         BIGINT NOT NULL REFERENCES payment_orders(id) ON DELETE CASCADE,
+    project_id       BIGINT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+    original_amount  DECIMAL(10, 2) NOT NULL,
+    amount           DECIMAL(10, 2) NOT NULL,
+    platform_fee     DECIMAL(10, 2) NOT NULL,
+    seller_amount    DECIMAL(10, 2) NOT NULL,
+    validity_days    INTEGER,
+    sale_id          BIGINT REFERENCES project_sales(id) ON DELETE SET NULL,
+
+    PRIMARY KEY (order_id, project_id),
+    CONSTRAINT check_payment_order_items_amount
+        CHECK (amount >= 0 AND amount <= original_amount),
+    CONSTRAINT check_payment_order_items_seller_amount
+        CHECK (seller_amount = amount - platform_fee AND platform_fee >= 0)
+);
+
+CREATE INDEX idx_payment_order_items_project ON payment_order_items (project_id);
+
+-- 已有订单均为单项目订单
+INSERT INTO payment_order_items (
+    order_id, project_id, original_amount, amount, platform_fee,
+    seller_amount, validity_days, sale_id
+)
+SELECT id, project_id, original_amount, amount, amount - seller_amount,
+       seller_amount, validity_days, sale_id
+FROM payment_orders;
+
+ALTER TABLE payment_orders
+    ADD COLUMN bundle_id BIGINT REFERENCES project_bundles(id) ON DELETE SET NULL,
+    ADD COLUMN item_count INTEGER DEFAULT 1 NOT NULL CHECK (item_count >= 1);
+
+COMMENT ON COLUMN payment_orders.project_id IS '订单的主项目，多项目订单为第一个项目，完整明细见 payment_order_items';
+
+-- 待支付订单唯一约束只针对单项目订单
+DROP INDEX idx_payment_orders_pending_unique;
+CREATE UNIQUE INDEX idx_payment_orders_pending_unique
+    ON payment_orders (user_id, project_id)
+    WHERE status = 'pending' AND item_count = 1;
+
+-- 3. 多项目订单的每个项目都以同一订单号创建购买记录
+ALTER TABLE user_purchases DROP CONSTRAINT user_purchases_order_no_key;
+CREATE INDEX idx_user_purchases_order_no ON user_purchases (order_no) WHERE order_no IS NOT NULL;
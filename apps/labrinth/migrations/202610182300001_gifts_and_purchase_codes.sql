@@ -0,0 +1,41 @@
+-- 1. 赠送订单：买家付款，购买记录授予受赠用户
+ALTER TABLE payment_orders
+    ADD COLUMN gift_recipient_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT check_payment_orders_gift_recipient
+        CHECK (gift_recipient_id IS NULL OR gift_recipient_id <> user_id);
+
+CREATE INDEX idx_payment_orders_gift_recipient
+    ON payment_orders (gift_recipient_id) WHERE gift_recipient_id IS NOT NULL;
+
+COMMENT ON COLUMN payment_orders.gift_recipient_id IS '赠送订单的受赠用户，支付成功后购买记录授予该用户';
+
+-- 买家可以同时为自己和他人购买同一项目
+DROP INDEX idx_payment_orders_pending_unique;
+CREATE UNIQUE INDEX idx_payment_orders_pending_unique
+    ON payment_orders (user_id, project_id, COALESCE(gift_recipient_id, 0))
+    WHERE status = 'pending' AND item_count = 1;
+
+-- 2. 兑换码批次：作者为测试者、抽奖等生成的一次性兑换码
+CREATE TABLE purchase_code_batches (
+    id          BIGINT PRIMARY KEY,
+    project_id  BIGINT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+    name        VARCHAR(64) NOT NULL,
+    code_count  INTEGER NOT NULL CHECK (code_count > 0),
+    expires_at  TIMESTAMPTZ,
+    revoked     BOOLEAN DEFAULT FALSE NOT NULL,
+    created_by  BIGINT NOT NULL REFERENCES users(id),
+    created_at  TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_purchase_code_batches_project ON purchase_code_batches (project_id);
+
+COMMENT ON TABLE purchase_code_batches IS '兑换码批次，兑换后按项目正常有效期创建购买记录';
+
+CREATE TABLE purchase_codes (
+    code         VARCHAR(32) PRIMARY KEY,
+    batch_id     BIGINT NOT NULL REFERENCES purchase_code_batches(id) ON DELETE CASCADE,
+    redeemed_by  BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    redeemed_at  TIMESTAMPTZ
+);
+
+CREATE INDEX idx_purchase_codes_batch ON purchase_codes (batch_id);
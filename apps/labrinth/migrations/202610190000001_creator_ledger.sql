@@ -0,0 +1,195 @@
+-- 1. 复式记账：每笔资金变动是一条分录，分录下的明细借贷相抵
+CREATE TABLE ledger_entries (
+    id           BIGSERIAL PRIMARY KEY,
+    kind         VARCHAR(32) NOT NULL,
+    -- 关联的业务单号：订单号、提现 ID、激励事件 ID 等
+    reference    VARCHAR(64),
+    description  TEXT DEFAULT '' NOT NULL,
+    -- 冲正分录指向被冲正的原分录
+    reversal_of  BIGINT REFERENCES ledger_entries(id),
+    created_at   TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_ledger_entries_reference ON ledger_entries (kind, reference);
+CREATE INDEX idx_ledger_entries_created ON ledger_entries (created_at);
+CREATE UNIQUE INDEX idx_ledger_entries_reversal ON ledger_entries (reversal_of) WHERE reversal_of IS NOT NULL;
+
+COMMENT ON TABLE ledger_entries IS '复式记账分录，创作者余额由分录明细汇总得出';
+
+CREATE TABLE ledger_lines (
+    id            BIGSERIAL PRIMARY KEY,
+    entry_id      BIGINT NOT NULL REFERENCES ledger_entries(id) ON DELETE CASCADE,
+    account       VARCHAR(32) NOT NULL,
+    -- 创作者相关科目的所属用户，平台科目为空
+    user_id       BIGINT REFERENCES users(id),
+    project_id    BIGINT REFERENCES mods(id) ON DELETE SET NULL,
+    -- 借方为正，贷方为负
+    amount        NUMERIC(96, 48) NOT NULL CHECK (amount <> 0),
+    -- 收益可提现的时间，早于该时间计入待结算余额
+    available_at  TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_ledger_lines_entry ON ledger_lines (entry_id);
+CREATE INDEX idx_ledger_lines_user_account ON ledger_lines (user_id, account) WHERE user_id IS NOT NULL;
+CREATE INDEX idx_ledger_lines_project ON ledger_lines (project_id) WHERE project_id IS NOT NULL;
+
+-- 事务提交时校验每条分录借贷平衡
+CREATE FUNCTION check_ledger_entry_balanced() RETURNS TRIGGER AS $$
+BEGIN
+    IF (SELECT SUM(amount) FROM ledger_lines WHERE entry_id = NEW.entry_id) <> 0 THEN
+        RAISE EXCEPTION '分录 % 借贷不平衡', NEW.entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER ledger_lines_balanced
+    AFTER INSERT OR UPDATE ON ledger_lines
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_ledger_entry_balanced();
+
+-- 2. 对账差异：每日对账任务发现的问题，由管理员处理
+CREATE TABLE ledger_reconciliation_issues (
+    id               BIGSERIAL PRIMARY KEY,
+    kind             VARCHAR(32) NOT NULL,
+    reference        VARCHAR(64) NOT NULL,
+    user_id          BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    expected         NUMERIC(96, 48),
+    actual           NUMERIC(96, 48),
+    detail           TEXT DEFAULT '' NOT NULL,
+    first_seen_at    TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_seen_at     TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    resolved_at      TIMESTAMPTZ,
+    resolved_by      BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    resolution_note  TEXT
+);
+
+CREATE UNIQUE INDEX idx_ledger_reconciliation_issues_open
+    ON ledger_reconciliation_issues (kind, reference) WHERE resolved_at IS NULL;
+
+COMMENT ON TABLE ledger_reconciliation_issues IS '账本与云账户、支付回调记录对账发现的差异';
+
+-- 3. 迁移历史数据
+DO $$
+DECLARE
+    opening_id    BIGINT;
+    new_entry_id  BIGINT;
+    p             RECORD;
+    o             RECORD;
+BEGIN
+    -- 历史收益（广告分成、激励结算、退款扣回）作为期初余额
+    INSERT INTO ledger_entries (kind, description)
+    VALUES ('opening_balance', '历史收益迁移')
+    RETURNING id INTO opening_id;
+
+    INSERT INTO ledger_lines (entry_id, account, user_id, project_id, amount, available_at)
+    SELECT opening_id, 'creator_payable', user_id, mod_id, -SUM(amount), date_available
+    FROM payouts_values
+    GROUP BY user_id, mod_id, date_available
+    HAVING SUM(amount) <> 0;
+
+    INSERT INTO ledger_lines (entry_id, account, amount)
+    SELECT opening_id, 'opening_balance', -SUM(amount)
+    FROM ledger_lines
+    WHERE ledger_lines.entry_id = opening_id
+    HAVING SUM(amount) <> 0;
+
+    -- 已占用余额的提现：云账户通道服务费从提现金额内扣，其他通道额外扣除
+    FOR p IN
+        SELECT id, user_id, amount, COALESCE(fee, 0) AS fee, method, created
+        FROM payouts
+        WHERE status IN ('success', 'in-transit')
+    LOOP
+        IF p.method = 'yunzhanghu_alipay' THEN
+            p.amount := p.amount - p.fee;
+        END IF;
+
+        IF p.amount <> 0 THEN
+            INSERT INTO ledger_entries (kind, reference, description, created_at)
+            VALUES ('withdrawal', p.id::text, '提现', p.created)
+            RETURNING id INTO new_entry_id;
+
+            INSERT INTO ledger_lines (entry_id, account, user_id, amount, available_at)
+            VALUES (new_entry_id, 'creator_payable', p.user_id, p.amount, p.created),
+                   (new_entry_id, 'payout_clearing', NULL, -p.amount, p.created);
+        END IF;
+
+        IF p.fee <> 0 THEN
+            INSERT INTO ledger_entries (kind, reference, description, created_at)
+            VALUES ('service_fee', p.id::text, '提现服务费', p.created)
+            RETURNING id INTO new_entry_id;
+
+            INSERT INTO ledger_lines (entry_id, account, user_id, amount, available_at)
+            VALUES (new_entry_id, 'creator_payable', p.user_id, p.fee, p.created),
+                   (new_entry_id, 'withdrawal_fees', NULL, -p.fee, p.created);
+        END IF;
+    END LOOP;
+
+    -- 已支付的订单：买家款项直接结算到卖家商户，平台服务费从中计提
+    FOR o IN
+        SELECT id, order_no, seller_id, amount, platform_fee, status,
+               COALESCE(paid_at, created_at) AS paid_at
+        FROM payment_orders
+        WHERE status IN ('paid', 'refunded') AND amount > 0
+    LOOP
+        INSERT INTO ledger_entries (kind, reference, description, created_at)
+        VALUES ('sale', o.order_no, '项目销售', o.paid_at)
+        RETURNING id INTO new_entry_id;
+
+        INSERT INTO ledger_lines (entry_id, account, user_id, project_id, amount, available_at)
+        SELECT new_entry_id, 'creator_sales', o.seller_id, project_id, -amount, o.paid_at
+        FROM payment_order_items
+        WHERE order_id = o.id AND amount > 0;
+
+        INSERT INTO ledger_lines (entry_id, account, amount, available_at)
+        VALUES (new_entry_id, 'payment_clearing', o.amount, o.paid_at);
+
+        IF COALESCE(o.platform_fee, 0) > 0 THEN
+            INSERT INTO ledger_entries (kind, reference, description, created_at)
+            VALUES ('platform_fee', o.order_no, '平台服务费', o.paid_at)
+            RETURNING id INTO new_entry_id;
+
+            INSERT INTO ledger_lines (entry_id, account, user_id, project_id, amount, available_at)
+            SELECT new_entry_id, 'creator_sales', o.seller_id, project_id, platform_fee, o.paid_at
+            FROM payment_order_items
+            WHERE order_id = o.id AND platform_fee > 0;
+
+            INSERT INTO ledger_lines (entry_id, account, amount, available_at)
+            VALUES (new_entry_id, 'platform_revenue', -o.platform_fee, o.paid_at);
+        END IF;
+    END LOOP;
+
+    -- 已退款的订单：平台垫付的退款记入垫付科目（扣回部分已计入期初余额），
+    -- 其余由卖家商户原路退回
+    FOR o IN
+        SELECT po.id, po.order_no, po.seller_id, po.amount,
+               COALESCE(po.platform_fee, 0) AS platform_fee,
+               COALESCE(r.clawback_amount, 0) AS clawback_amount,
+               COALESCE(r.completed_at, po.paid_at, po.created_at) AS refunded_at
+        FROM payment_orders po
+        LEFT JOIN payment_refunds r ON r.order_id = po.id AND r.status = 'refunded'
+        WHERE po.status = 'refunded' AND po.amount > 0
+    LOOP
+        INSERT INTO ledger_entries (kind, reference, description, created_at)
+        VALUES ('refund', o.order_no, '订单退款', o.refunded_at)
+        RETURNING id INTO new_entry_id;
+
+        INSERT INTO ledger_lines (entry_id, account, amount, available_at)
+        VALUES (new_entry_id, 'payment_clearing', -o.amount, o.refunded_at);
+
+        IF o.clawback_amount > 0 THEN
+            INSERT INTO ledger_lines (entry_id, account, amount, available_at)
+            VALUES (new_entry_id, 'refund_advance', o.amount, o.refunded_at);
+        ELSE
+            INSERT INTO ledger_lines (entry_id, account, user_id, project_id, amount, available_at)
+            SELECT new_entry_id, 'creator_sales', o.seller_id, project_id, seller_amount, o.refunded_at
+            FROM payment_order_items
+            WHERE order_id = o.id AND seller_amount > 0;
+
+            IF o.platform_fee > 0 THEN
+                INSERT INTO ledger_lines (entry_id, account, amount, available_at)
+                VALUES (new_entry_id, 'platform_revenue', o.platform_fee, o.refunded_at);
+            END IF;
+        END IF;
+    END LOOP;
+END $$;
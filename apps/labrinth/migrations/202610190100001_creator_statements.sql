@@ -0,0 +1,14 @@
+-- 创作者月度收益对账单：生成后归档，历史对账单不随账本后续变动而改变
+CREATE TABLE creator_statements (
+    user_id       BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 对账月份，取当月第一天（北京时间）
+    period        DATE NOT NULL,
+    data          JSONB NOT NULL,
+    generated_at  TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (user_id, period),
+    CHECK (EXTRACT(DAY FROM period) = 1)
+);
+
+CREATE INDEX idx_creator_statements_period ON creator_statements (period);
+
+COMMENT ON TABLE creator_statements IS '创作者月度收益对账单归档，仅管理员重新生成时覆盖';
@@ -0,0 +1,25 @@
+-- 用户私信：会话已读位置、私信隐私设置与用户屏蔽
+
+-- 每个成员在会话中最后一次阅读的时间，用于计算未读数
+ALTER TABLE threads_members ADD COLUMN last_read_at timestamptz NULL;
+
+CREATE INDEX threads_members_user ON threads_members (user_id);
+CREATE INDEX threads_messages_thread_created ON threads_messages (thread_id, created);
+
+-- 私信隐私设置：everyone 所有人 / followers 仅关注者 / nobody 不接收新会话
+CREATE TABLE user_dm_settings (
+    user_id bigint PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    privacy varchar(32) NOT NULL DEFAULT 'everyone',
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- 用户屏蔽：被屏蔽者无法向屏蔽者发起或继续私信
+CREATE TABLE user_blocks (
+    user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    blocked_user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, blocked_user_id),
+    CHECK (user_id <> blocked_user_id)
+);
+
+CREATE INDEX user_blocks_blocked ON user_blocks (blocked_user_id);
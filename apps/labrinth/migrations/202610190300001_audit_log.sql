@@ -0,0 +1,39 @@
+-- 统一的特权操作审计日志：只追加，按 id 顺序构成哈希链
+CREATE TABLE audit_log (
+    id              bigserial PRIMARY KEY,
+    -- 操作者；管理员密钥或系统任务触发时为空。不设外键，避免删除用户时改写日志
+    actor_user_id   bigint,
+    action          text NOT NULL,
+    target_type     text NOT NULL,
+    target_id       bigint,
+    before          jsonb,
+    after           jsonb,
+    reason          text,
+    ip              text,
+    created_at      timestamptz NOT NULL,
+    -- 上一条日志的 hash，首条为空
+    prev_hash       text,
+    -- sha256(prev_hash + 本条内容)，十六进制
+    hash            text NOT NULL
+);
+
+CREATE INDEX idx_audit_log_action_time ON audit_log (action, created_at DESC);
+CREATE INDEX idx_audit_log_target ON audit_log (target_type, target_id);
+CREATE INDEX idx_audit_log_actor ON audit_log (actor_user_id, created_at DESC);
+CREATE INDEX idx_audit_log_created ON audit_log (created_at DESC);
+
+CREATE FUNCTION audit_log_reject_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log 只允许追加，不能修改或删除';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_change();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_reject_change();
+
+COMMENT ON TABLE audit_log IS '封禁、审核、提现、管理员密钥接口等特权操作的统一审计日志';
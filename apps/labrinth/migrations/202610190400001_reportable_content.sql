@@ -0,0 +1,33 @@
+-- 举报支持论坛回复、Issue 评论、百科页面与讨论串消息
+
+-- 内容 ID 不加外键：内容被删除后举报与快照仍需保留
+ALTER TABLE reports
+    ADD COLUMN post_id bigint NULL,
+    ADD COLUMN issue_comment_id bigint NULL,
+    ADD COLUMN wiki_id bigint NULL,
+    ADD COLUMN thread_message_id bigint NULL,
+    -- 被举报内容的作者，用于直接封禁
+    ADD COLUMN content_author_id bigint NULL,
+    -- 举报时的内容快照
+    ADD COLUMN content_snapshot jsonb NULL;
+
+-- 统一的举报对象，用于聚合同一内容的多条举报
+ALTER TABLE reports
+    ADD COLUMN item_type varchar(32) GENERATED ALWAYS AS (
+        CASE
+            WHEN mod_id IS NOT NULL THEN 'project'
+            WHEN version_id IS NOT NULL THEN 'version'
+            WHEN user_id IS NOT NULL THEN 'user'
+            WHEN post_id IS NOT NULL THEN 'forum-post'
+            WHEN issue_comment_id IS NOT NULL THEN 'issue-comment'
+            WHEN wiki_id IS NOT NULL THEN 'wiki-page'
+            WHEN thread_message_id IS NOT NULL THEN 'thread-message'
+            ELSE 'unknown'
+        END
+    ) STORED,
+    ADD COLUMN item_id bigint GENERATED ALWAYS AS (
+        COALESCE(mod_id, version_id, user_id, post_id, issue_comment_id, wiki_id, thread_message_id)
+    ) STORED;
+
+CREATE INDEX reports_open_item ON reports (item_type, item_id) WHERE closed = FALSE;
+CREATE INDEX reports_content_author ON reports (content_author_id) WHERE content_author_id IS NOT NULL;
@@ -0,0 +1,24 @@
+-- 异步邮件发送队列
+
+CREATE TABLE email_queue (
+    id bigserial PRIMARY KEY,
+    to_address varchar(320) NOT NULL,
+    -- 模板名与实际使用的语言，便于排查
+    template varchar(64) NOT NULL,
+    locale varchar(16) NOT NULL,
+    subject text NOT NULL,
+    html_body text NOT NULL,
+    -- pending / sending / sent / failed
+    status varchar(16) NOT NULL DEFAULT 'pending',
+    attempts integer NOT NULL DEFAULT 0,
+    next_attempt_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- 发送进程领取时间，超时未完成的邮件会被重新领取
+    locked_at timestamptz NULL,
+    last_error text NULL,
+    created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sent_at timestamptz NULL
+);
+
+CREATE INDEX email_queue_pending ON email_queue (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX email_queue_sending ON email_queue (locked_at) WHERE status = 'sending';
+CREATE INDEX email_queue_finished ON email_queue (created_at) WHERE status IN ('sent', 'failed');
@@ -0,0 +1,31 @@
+-- 通知偏好与邮件投递
+
+-- 按通知类型选择投递方式：in_app 仅站内 / email 立即发送邮件 /
+-- daily、weekly 加入每日或每周摘要邮件 / off 不接收；未设置时为 in_app
+CREATE TABLE notification_preferences (
+    user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    notification_type varchar(64) NOT NULL,
+    channel varchar(16) NOT NULL,
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, notification_type)
+);
+
+-- 等待加入摘要邮件的通知（daily / weekly），摘要发送后清空
+ALTER TABLE notifications ADD COLUMN digest varchar(16) NULL;
+
+CREATE INDEX notifications_pending_digest ON notifications (user_id, digest) WHERE digest IS NOT NULL;
+
+-- 每个用户各频率摘要的上次发送时间
+CREATE TABLE notification_digests (
+    user_id bigint NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    frequency varchar(16) NOT NULL,
+    last_sent_at timestamptz NOT NULL,
+    PRIMARY KEY (user_id, frequency)
+);
+
+-- 邮件中一键退订链接使用的令牌，无需登录
+CREATE TABLE notification_unsubscribe_tokens (
+    user_id bigint PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    token varchar(64) NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,7 @@
+-- 百科草稿按页面划分：每份草稿只包含一个页面（删除页面时连同其子页面），
+-- 同一用户可以同时编辑多个页面。wiki_id 为空的是此前的整站草稿
+ALTER TABLE wiki_cache ADD COLUMN wiki_id bigint;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_wiki_cache_user_page_active
+    ON wiki_cache (mod_id, user_id, wiki_id)
+    WHERE wiki_id IS NOT NULL AND status IN ('draft', 'review');
@@ -0,0 +1,7 @@
+-- 退款审核通过后、支付平台确认前的中间状态，避免重复调用支付平台退款
+ALTER TABLE payment_refunds
+    DROP CONSTRAINT check_payment_refunds_status_valid,
+    ADD CONSTRAINT check_payment_refunds_status_valid
+        CHECK (status IN ('pending', 'processing', 'rejected', 'cancelled', 'refunded'));
+
+COMMENT ON COLUMN payment_refunds.status IS 'pending 待审核，processing 已通过审核、等待支付平台退款，rejected/cancelled/refunded 为终态';
@@ -1,9 +1,12 @@
-use lettre::message::Mailbox;
-use lettre::message::header::ContentType;
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{Address, Message, SmtpTransport, Transport};
+use crate::database::models::email_queue_item::EmailQueueItemBuilder;
+use actix_web::HttpRequest;
 use thiserror::Error;
 
+pub mod template;
+pub mod transport;
+
+pub use template::{DEFAULT_LOCALE, EmailTemplate};
+
 #[derive(Error, Debug)]
 pub enum MailError {
     #[error("环境错误")]
@@ -14,68 +17,106 @@ pub enum MailError {
     Address(#[from] lettre::address::AddressError),
     #[error("SMTP 错误: {0}")]
     Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("邮件模板错误: {0}")]
+    Template(String),
+    #[error("邮件配置错误: {0}")]
+    Config(String),
+    #[error("邮件队列数据库错误: {0}")]
+    Database(#[from] crate::database::models::DatabaseError),
+    #[error("邮件写入错误: {0}")]
+    Io(#[from] std::io::Error),
 }
 
-pub fn send_email_raw(
+/// 渲染邮件并加入发送队列
+///
+/// 邮件由后台任务异步发送，失败时按指数退避重试。传入事务时邮件与
+/// 业务数据一同提交，事务回滚则邮件不会发出。
+pub async fn send_email<'a, E>(
+    exec: E,
     to: String,
-    subject: String,
-    body: String,
-) -> Result<(), MailError> {
-    let from_name =
-        dotenvy::var("SMTP_FROM_NAME").unwrap_or_else(|_| "BBSMC".to_string());
-    let from_user = dotenvy::var("SMTP_FROM_USER")
-        .unwrap_or_else(|_| "noreply".to_string());
-    let from_domain = dotenvy::var("SMTP_FROM_DOMAIN")?;
+    template: EmailTemplate,
+    locale: &str,
+    variables: &[(&str, &str)],
+) -> Result<(), MailError>
+where
+    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+{
+    prepare_email(to, template, locale, variables)?
+        .insert(exec)
+        .await?;
 
-    let email = Message::builder()
-        .from(Mailbox::new(
-            Some(from_name),
-            Address::new(&from_user, &from_domain)?,
-        ))
-        .to(to.parse()?)
-        .subject(subject)
-        .header(ContentType::TEXT_HTML)
-        .body(body)?;
+    Ok(())
+}
 
-    let username = dotenvy::var("SMTP_USERNAME")?;
-    let password = dotenvy::var("SMTP_PASSWORD")?;
-    let host = dotenvy::var("SMTP_HOST")?;
-    let creds = Credentials::new(username, password);
+/// 校验地址并渲染邮件，返回待入队的邮件
+pub fn prepare_email(
+    to: String,
+    template: EmailTemplate,
+    locale: &str,
+    variables: &[(&str, &str)],
+) -> Result<EmailQueueItemBuilder, MailError> {
+    // 入队前校验地址，避免无效地址在队列中反复重试
+    to.parse::<lettre::Address>()?;
 
-    let mailer = SmtpTransport::relay(&host)?
-        .port(465)
-        .credentials(creds)
-        .build();
+    let email = template::render(template, locale, variables)?;
 
-    mailer.send(&email)?;
+    Ok(EmailQueueItemBuilder {
+        to_address: to,
+        template: template.as_str().to_string(),
+        locale: email.locale.to_string(),
+        subject: email.subject,
+        html_body: email.html,
+    })
+}
 
-    Ok(())
+/// 根据请求的 Accept-Language 选择邮件语言
+pub fn locale_from_request(req: &HttpRequest) -> &'static str {
+    req.headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|x| x.to_str().ok())
+        .map(parse_accept_language)
+        .unwrap_or(DEFAULT_LOCALE)
 }
 
-pub fn send_email(
-    to: String,
-    email_title: &str,
-    email_description: &str,
-    line_two: &str,
-    button_info: Option<(&str, &str)>,
-) -> Result<(), MailError> {
-    let mut email = if button_info.is_some() {
-        include_str!("button_notif.html")
-    } else {
-        include_str!("auth_notif.html")
-    }
-    .replace("{{ email_title }}", email_title)
-    .replace("{{ email_description }}", email_description)
-    .replace("{{ line_one }}", email_description)
-    .replace("{{ line_two }}", line_two);
+fn parse_accept_language(header: &str) -> &'static str {
+    let mut languages = header
+        .split(',')
+        .filter_map(|x| {
+            let mut parts = x.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|x| x.trim().strip_prefix("q="))
+                .and_then(|x| x.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag, quality))
+        })
+        .collect::<Vec<_>>();
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
 
-    if let Some((button_title, button_link)) = button_info {
-        email = email
-            .replace("{{ button_title }}", button_title)
-            .replace("{{ button_link }}", button_link);
-    }
+    languages
+        .into_iter()
+        .find_map(|(tag, _)| {
+            let primary = tag.split('-').next().unwrap_or(tag);
+            template::LOCALES.iter().copied().find(|x| {
+                x.eq_ignore_ascii_case(tag)
+                    || x.split('-')
+                        .next()
+                        .is_some_and(|x| x.eq_ignore_ascii_case(primary))
+            })
+        })
+        .unwrap_or(DEFAULT_LOCALE)
+}
 
-    send_email_raw(to, email_title.to_string(), email)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    #[test]
+    fn accept_language_picks_best_supported_locale() {
+        assert_eq!(parse_accept_language("en-US,en;q=0.9"), "en");
+        assert_eq!(parse_accept_language("zh-TW;q=0.8,en;q=0.5"), "zh-CN");
+        assert_eq!(parse_accept_language("fr-FR, en;q=0.1"), "en");
+        assert_eq!(parse_accept_language("fr-FR"), DEFAULT_LOCALE);
+        assert_eq!(parse_accept_language(""), DEFAULT_LOCALE);
+    }
 }
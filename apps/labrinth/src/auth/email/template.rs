@@ -0,0 +1,403 @@
+//! 邮件模板
+//!
+//! 每个模板按语言提供标题、两段正文和可选的按钮文字，文本中的
+//! `{{ name }}` 在渲染时替换为变量值（正文中会做 HTML 转义，名称以 `_html`
+//! 结尾的变量除外，由调用方负责转义），再填入 `auth_notif.html` /
+//! `button_notif.html` 布局。按钮链接取自 `link` 变量。缺少对应语言的模板时
+//! 使用默认语言。
+
+use super::MailError;
+
+/// 默认语言，也是所有模板都必须提供的语言
+pub const DEFAULT_LOCALE: &str = "zh-CN";
+
+/// 支持的语言
+pub const LOCALES: &[&str] = &[DEFAULT_LOCALE, "en"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmailTemplate {
+    AuthMethodAdded,
+    AuthMethodRemoved,
+    PhoneChanged,
+    TwoFactorEnabled,
+    TwoFactorRemoved,
+    ResetPassword,
+    PasswordChanged,
+    PasswordRemoved,
+    EmailChanged,
+    VerifyEmail,
+    WelcomeVerifyEmail,
+    IssueCreated,
+    IssueStateChanged,
+    IssueReply,
+    PaymentFailed,
+    Notification,
+    NotificationDigest,
+}
+
+impl EmailTemplate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailTemplate::AuthMethodAdded => "auth_method_added",
+            EmailTemplate::AuthMethodRemoved => "auth_method_removed",
+            EmailTemplate::PhoneChanged => "phone_changed",
+            EmailTemplate::TwoFactorEnabled => "two_factor_enabled",
+            EmailTemplate::TwoFactorRemoved => "two_factor_removed",
+            EmailTemplate::ResetPassword => "reset_password",
+            EmailTemplate::PasswordChanged => "password_changed",
+            EmailTemplate::PasswordRemoved => "password_removed",
+            EmailTemplate::EmailChanged => "email_changed",
+            EmailTemplate::VerifyEmail => "verify_email",
+            EmailTemplate::WelcomeVerifyEmail => "welcome_verify_email",
+            EmailTemplate::IssueCreated => "issue_created",
+            EmailTemplate::IssueStateChanged => "issue_state_changed",
+            EmailTemplate::IssueReply => "issue_reply",
+            EmailTemplate::PaymentFailed => "payment_failed",
+            EmailTemplate::Notification => "notification",
+            EmailTemplate::NotificationDigest => "notification_digest",
+        }
+    }
+}
+
+/// 单个语言的模板文本
+struct TemplateText {
+    subject: &'static str,
+    line_one: &'static str,
+    line_two: &'static str,
+    button: Option<&'static str>,
+}
+
+const CONTACT_ZH: &str =
+    "如果不是您进行的更改，请立即通过电子邮件 (support@bbsmc.net) 联系我们。";
+const CONTACT_EN: &str = "If you did not make this change, please contact us immediately at support@bbsmc.net.";
+
+fn text(template: EmailTemplate, locale: &str) -> Option<TemplateText> {
+    use EmailTemplate::*;
+
+    let text = match (template, locale) {
+        (AuthMethodAdded, "zh-CN") => TemplateText {
+            subject: "已添加身份验证方法",
+            line_one: "您现在可以使用 {{ provider }} 身份验证提供程序登录 BBSMC。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (AuthMethodAdded, "en") => TemplateText {
+            subject: "Authentication method added",
+            line_one: "You can now sign in to BBSMC with {{ provider }}.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (AuthMethodRemoved, "zh-CN") => TemplateText {
+            subject: "身份验证方法已移除",
+            line_one: "您现在无法使用 {{ provider }} 身份验证提供程序登录 BBSMC。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (AuthMethodRemoved, "en") => TemplateText {
+            subject: "Authentication method removed",
+            line_one: "You can no longer sign in to BBSMC with {{ provider }}.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (PhoneChanged, "zh-CN") => TemplateText {
+            subject: "手机号已绑定",
+            line_one: "您的账户手机号已更新为 {{ phone_number }}。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (PhoneChanged, "en") => TemplateText {
+            subject: "Phone number updated",
+            line_one: "The phone number of your account has been updated to {{ phone_number }}.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (TwoFactorEnabled, "zh-CN") => TemplateText {
+            subject: "已启用双因素身份验证",
+            line_one: "登录 BBSMC 时，您现在可以在输入常用的电子邮件地址和密码后，输入由身份验证应用生成的代码。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (TwoFactorEnabled, "en") => TemplateText {
+            subject: "Two-factor authentication enabled",
+            line_one: "When signing in to BBSMC, you will now enter a code from your authenticator app after your email and password.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (TwoFactorRemoved, "zh-CN") => TemplateText {
+            subject: "双因素身份验证已移除",
+            line_one: "登录 BBSMC 时，您不再需要双因素身份验证即可访问。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (TwoFactorRemoved, "en") => TemplateText {
+            subject: "Two-factor authentication removed",
+            line_one: "Two-factor authentication is no longer required when signing in to BBSMC.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (ResetPassword, "zh-CN") => TemplateText {
+            subject: "重置您的密码",
+            line_one: "请访问以下链接以重置您的密码。如果按钮无法使用，您可以复制链接并将其粘贴到浏览器中。",
+            line_two: "如果您没有请求重置密码，您可以放心忽略此邮件。",
+            button: Some("重置密码"),
+        },
+        (ResetPassword, "en") => TemplateText {
+            subject: "Reset your password",
+            line_one: "Please visit the link below to reset your password. If the button does not work, copy the link into your browser.",
+            line_two: "If you did not request a password reset, you can safely ignore this email.",
+            button: Some("Reset password"),
+        },
+        (PasswordChanged, "zh-CN") => TemplateText {
+            subject: "密码修改完成",
+            line_one: "您的账户密码已修改完成。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (PasswordChanged, "en") => TemplateText {
+            subject: "Password changed",
+            line_one: "The password of your account has been changed.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (PasswordRemoved, "zh-CN") => TemplateText {
+            subject: "密码已删除",
+            line_one: "您的账户密码已删除。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (PasswordRemoved, "en") => TemplateText {
+            subject: "Password removed",
+            line_one: "The password of your account has been removed.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (EmailChanged, "zh-CN") => TemplateText {
+            subject: "邮箱已更改",
+            line_one: "您的账户邮箱已更新为 {{ email }}。",
+            line_two: CONTACT_ZH,
+            button: None,
+        },
+        (EmailChanged, "en") => TemplateText {
+            subject: "Email address changed",
+            line_one: "The email address of your account has been updated to {{ email }}.",
+            line_two: CONTACT_EN,
+            button: None,
+        },
+        (VerifyEmail, "zh-CN") => TemplateText {
+            subject: "验证您的邮箱",
+            line_one: "我们需要验证您的邮箱地址。",
+            line_two: "请点击下面的链接以验证您的邮箱。如果按钮无法使用，您可以复制链接并粘贴到浏览器中。该链接将在 24 小时后失效。",
+            button: Some("验证邮箱"),
+        },
+        (VerifyEmail, "en") => TemplateText {
+            subject: "Verify your email",
+            line_one: "We need to verify your email address.",
+            line_two: "Please click the link below to verify your email. If the button does not work, copy the link into your browser. The link expires in 24 hours.",
+            button: Some("Verify email"),
+        },
+        (WelcomeVerifyEmail, "zh-CN") => TemplateText {
+            subject: "验证您的邮箱",
+            line_one: "欢迎加入 BBSMC 资源社区, {{ username }}!",
+            line_two: "请点击下面的链接以验证您的邮箱。如果按钮无法使用，您可以复制链接并粘贴到浏览器中。该链接将在 24 小时后失效。",
+            button: Some("验证邮箱"),
+        },
+        (WelcomeVerifyEmail, "en") => TemplateText {
+            subject: "Verify your email",
+            line_one: "Welcome to BBSMC, {{ username }}!",
+            line_two: "Please click the link below to verify your email. If the button does not work, copy the link into your browser. The link expires in 24 hours.",
+            button: Some("Verify email"),
+        },
+        (IssueCreated, "zh-CN") => TemplateText {
+            subject: "新问题通知",
+            line_one: "{{ username }} 在 {{ project }} 创建了新问题：{{ title }}",
+            line_two: "{{ body }}",
+            button: Some("查看问题"),
+        },
+        (IssueStateChanged, "zh-CN") => TemplateText {
+            subject: "问题状态更新通知",
+            line_one: "{{ username }} 更新了 {{ title }} 的问题状态：{{ state }}",
+            line_two: "",
+            button: Some("查看问题"),
+        },
+        (IssueReply, "zh-CN") => TemplateText {
+            subject: "问题收到新的回复通知",
+            line_one: "{{ username }} 在 {{ project }} 回复了你的消息：{{ body }}",
+            line_two: "{{ body }}",
+            button: Some("查看问题"),
+        },
+        (PaymentFailed, "zh-CN") => TemplateText {
+            subject: "BBSMC 支付失败通知",
+            line_one: "我们尝试从您的绑定银行卡扣款 {{ amount }} 未成功。",
+            line_two: "请点击下方链接更新您的支付方式，或联系银行卡发卡机构。如果按钮无法使用，您可以复制链接并粘贴到浏览器中。",
+            button: Some("更新账单设置"),
+        },
+        (Notification, "zh-CN") => TemplateText {
+            subject: "{{ title }}",
+            line_one: "{{ text }}",
+            line_two: "不想再收到此类邮件？<a href=\"{{ unsubscribe_link }}\">退订</a>",
+            button: Some("查看详情"),
+        },
+        (NotificationDigest, "zh-CN") => TemplateText {
+            subject: "您有 {{ count }} 条未读通知",
+            line_one: "以下是您{{ period }}收到的未读通知：",
+            line_two: "{{ items_html }}<br>不想再收到摘要邮件？<a href=\"{{ unsubscribe_link }}\">退订</a>",
+            button: Some("查看全部通知"),
+        },
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+/// 选择实际使用的语言：模板没有该语言的文本时退回默认语言
+pub fn resolve_locale(template: EmailTemplate, locale: &str) -> &'static str {
+    LOCALES
+        .iter()
+        .find(|x| x.eq_ignore_ascii_case(locale))
+        .filter(|x| text(template, x).is_some())
+        .copied()
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+pub struct RenderedEmail {
+    pub locale: &'static str,
+    pub subject: String,
+    pub html: String,
+}
+
+/// 渲染模板，变量缺失时返回错误
+pub fn render(
+    template: EmailTemplate,
+    locale: &str,
+    variables: &[(&str, &str)],
+) -> Result<RenderedEmail, MailError> {
+    let locale = resolve_locale(template, locale);
+    let text = text(template, locale).ok_or_else(|| {
+        MailError::Template(format!("模板 {} 不存在", template.as_str()))
+    })?;
+
+    let subject = fill(text.subject, variables, false)?;
+    let line_one = fill(text.line_one, variables, true)?;
+    let line_two = fill(text.line_two, variables, true)?;
+
+    let layout = if text.button.is_some() {
+        include_str!("button_notif.html")
+    } else {
+        include_str!("auth_notif.html")
+    };
+
+    let mut html = layout
+        .replace("{{ locale }}", locale)
+        .replace("{{ email_title }}", &escape_html(&subject))
+        .replace("{{ email_description }}", &line_one)
+        .replace("{{ line_one }}", &line_one)
+        .replace("{{ line_two }}", &line_two);
+
+    if let Some(button) = text.button {
+        let link = variable(variables, "link").ok_or_else(|| {
+            MailError::Template(format!(
+                "模板 {} 缺少变量 link",
+                template.as_str()
+            ))
+        })?;
+        html = html
+            .replace("{{ button_title }}", button)
+            .replace("{{ button_link }}", &escape_html(link));
+    }
+
+    Ok(RenderedEmail {
+        locale,
+        subject,
+        html,
+    })
+}
+
+fn variable<'a>(variables: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    variables.iter().find(|x| x.0 == name).map(|x| x.1)
+}
+
+/// 替换文本中的 `{{ name }}`
+fn fill(
+    text: &str,
+    variables: &[(&str, &str)],
+    escape: bool,
+) -> Result<String, MailError> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}").ok_or_else(|| {
+            MailError::Template(format!("模板文本格式错误: {text}"))
+        })? + start;
+        let name = rest[start + 2..end].trim();
+        let value = variable(variables, name).ok_or_else(|| {
+            MailError::Template(format!("缺少模板变量 {name}"))
+        })?;
+
+        result.push_str(&rest[..start]);
+        if escape && !name.ends_with("_html") {
+            result.push_str(&escape_html(value));
+        } else {
+            result.push_str(value);
+        }
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+pub fn escape_html(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_and_escapes_variables() {
+        let email = render(
+            EmailTemplate::EmailChanged,
+            "zh-CN",
+            &[("email", "<a@b.c>")],
+        )
+        .unwrap();
+
+        assert_eq!(email.subject, "邮箱已更改");
+        assert!(email.html.contains("您的账户邮箱已更新为 &lt;a@b.c&gt;。"));
+        assert!(!email.html.contains("{{"));
+    }
+
+    #[test]
+    fn falls_back_to_default_locale() {
+        let email = render(
+            EmailTemplate::PaymentFailed,
+            "en",
+            &[("amount", "¥10.00"), ("link", "https://bbsmc.net/billing")],
+        )
+        .unwrap();
+        assert_eq!(email.locale, DEFAULT_LOCALE);
+
+        let email = render(EmailTemplate::TwoFactorRemoved, "EN", &[]).unwrap();
+        assert_eq!(email.locale, "en");
+        assert!(email.html.contains(r#"lang="en""#));
+    }
+
+    #[test]
+    fn missing_variable_is_an_error() {
+        assert!(render(EmailTemplate::ResetPassword, "zh-CN", &[]).is_err());
+        assert!(render(EmailTemplate::PhoneChanged, "zh-CN", &[]).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! 邮件发送方式
+//!
+//! `EMAIL_TRANSPORT=smtp`（默认）通过 SMTP 发送；`EMAIL_TRANSPORT=file`
+//! 将邮件以 Maildir 格式写入 `EMAIL_FILE_DIR`，用于本地开发与测试。
+
+use super::MailError;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{
+    Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, message: Message) -> Result<(), MailError>;
+}
+
+/// 按环境变量创建发送方式
+pub fn transport_from_env() -> Result<Arc<dyn EmailTransport>, MailError> {
+    let transport =
+        dotenvy::var("EMAIL_TRANSPORT").unwrap_or_else(|_| "smtp".to_string());
+
+    match transport.as_str() {
+        "file" => {
+            let dir = dotenvy::var("EMAIL_FILE_DIR")
+                .unwrap_or_else(|_| "emails".to_string());
+            Ok(Arc::new(MaildirTransport::new(dir)))
+        }
+        "smtp" => Ok(Arc::new(SmtpEmailTransport::from_env()?)),
+        other => Err(MailError::Config(format!(
+            "未知的邮件发送方式 EMAIL_TRANSPORT={other}"
+        ))),
+    }
+}
+
+/// 构造发件人为 `SMTP_FROM_NAME <SMTP_FROM_USER@SMTP_FROM_DOMAIN>` 的 HTML 邮件
+pub fn build_message(
+    to: &str,
+    subject: &str,
+    html_body: String,
+) -> Result<Message, MailError> {
+    let from_name =
+        dotenvy::var("SMTP_FROM_NAME").unwrap_or_else(|_| "BBSMC".to_string());
+    let from_user = dotenvy::var("SMTP_FROM_USER")
+        .unwrap_or_else(|_| "noreply".to_string());
+    let from_domain = dotenvy::var("SMTP_FROM_DOMAIN")?;
+
+    let message = Message::builder()
+        .from(Mailbox::new(
+            Some(from_name),
+            Address::new(&from_user, &from_domain)?,
+        ))
+        .to(to.parse()?)
+        .subject(subject)
+        .header(ContentType::TEXT_HTML)
+        .body(html_body)?;
+
+    Ok(message)
+}
+
+pub struct SmtpEmailTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailTransport {
+    /// `SMTP_TLS` 可选 `tls`（默认，隐式 TLS）、`starttls` 或 `none`；
+    /// 未设置 `SMTP_USERNAME` 时不进行身份验证
+    pub fn from_env() -> Result<Self, MailError> {
+        let host = dotenvy::var("SMTP_HOST")?;
+        let port =
+            crate::util::env::parse_var::<u16>("SMTP_PORT").unwrap_or(465);
+        let tls =
+            dotenvy::var("SMTP_TLS").unwrap_or_else(|_| "tls".to_string());
+
+        let mut builder = match tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "starttls" => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?
+            }
+            "none" => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host)
+            }
+            other => {
+                return Err(MailError::Config(format!(
+                    "未知的 SMTP_TLS 设置: {other}"
+                )));
+            }
+        }
+        .port(port);
+
+        if let Ok(username) = dotenvy::var("SMTP_USERNAME")
+            && !username.is_empty()
+        {
+            let password = dotenvy::var("SMTP_PASSWORD")?;
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpEmailTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        self.mailer.send(message).await?;
+        Ok(())
+    }
+}
+
+/// 以 Maildir 格式保存邮件：先写入 `tmp/`，完成后移动到 `new/`
+pub struct MaildirTransport {
+    dir: PathBuf,
+}
+
+impl MaildirTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for MaildirTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        let tmp = self.dir.join("tmp");
+        let new = self.dir.join("new");
+        std::fs::create_dir_all(&tmp)?;
+        std::fs::create_dir_all(&new)?;
+
+        let name = format!(
+            "{}.{}.labrinth.eml",
+            chrono::Utc::now().timestamp_micros(),
+            uuid::Uuid::new_v4().simple()
+        );
+
+        std::fs::write(tmp.join(&name), message.formatted())?;
+        std::fs::rename(tmp.join(&name), new.join(&name))?;
+
+        Ok(())
+    }
+}
@@ -31,6 +31,7 @@ pub async fn init_client_with_database(
             "
             CREATE TABLE IF NOT EXISTS {database}.views
             (
+                event_id String DEFAULT '',
                 recorded DateTime64(4),
                 domain String,
                 site_path String,
@@ -56,6 +57,7 @@ pub async fn init_client_with_database(
             "
             CREATE TABLE IF NOT EXISTS {database}.downloads
             (
+                event_id String DEFAULT '',
                 recorded DateTime64(4),
                 domain String,
                 site_path String,
@@ -81,6 +83,7 @@ pub async fn init_client_with_database(
             "
             CREATE TABLE IF NOT EXISTS {database}.playtime
             (
+                event_id String DEFAULT '',
                 recorded DateTime64(4),
                 seconds UInt64,
 
@@ -99,5 +102,15 @@ pub async fn init_client_with_database(
         .execute()
         .await?;
 
+    // 旧表补充事件 ID 列，事件队列重放时据此去重
+    for table in ["views", "downloads", "playtime"] {
+        client
+            .query(&format!(
+                "ALTER TABLE {database}.{table} ADD COLUMN IF NOT EXISTS event_id String DEFAULT '' FIRST"
+            ))
+            .execute()
+            .await?;
+    }
+
     Ok(client.with_database(database))
 }
@@ -0,0 +1,382 @@
+use super::DatabaseError;
+use super::ids::*;
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use sqlx::PgPool;
+
+/// 写入审计日志时持有的事务级 advisory lock，保证哈希链按 id 顺序追加
+const AUDIT_LOG_LOCK_KEY: i64 = 0x6175_6469_745f_6c6f;
+
+/// 一次特权操作
+///
+/// `before` / `after` 为操作前后目标的快照，`target_id` 为目标的数据库 ID，
+/// 没有具体目标（如全量重建索引）时为空。
+#[derive(Default)]
+pub struct AuditLogBuilder {
+    pub actor_id: Option<UserId>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub actor_id: Option<UserId>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub prev_hash: Option<String>,
+    pub hash: String,
+}
+
+/// 审计日志查询条件，均为可选
+#[derive(Default)]
+pub struct AuditLogFilter {
+    pub actor_id: Option<UserId>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl AuditLogBuilder {
+    /// 在调用方事务中追加一条日志，返回日志 ID
+    ///
+    /// 日志随操作一起提交或回滚。事务提交前其他写入审计日志的事务会被阻塞。
+    pub async fn insert(
+        self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<i64, DatabaseError> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(AUDIT_LOG_LOCK_KEY)
+            .execute(&mut **transaction)
+            .await?;
+
+        let prev_hash =
+            sqlx::query!("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1")
+                .fetch_optional(&mut **transaction)
+                .await?
+                .map(|row| row.hash);
+
+        let id = sqlx::query!(r#"SELECT nextval('audit_log_id_seq') AS "id!""#)
+            .fetch_one(&mut **transaction)
+            .await?
+            .id;
+
+        let mut entry = AuditLogEntry {
+            id,
+            actor_id: self.actor_id,
+            action: self.action,
+            target_type: self.target_type,
+            target_id: self.target_id,
+            before: self.before,
+            after: self.after,
+            reason: self.reason,
+            ip: self.ip,
+            // 与数据库 timestamptz 的精度一致，保证读回后哈希不变
+            created_at: Utc::now().trunc_subsecs(6),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        sqlx::query!(
+            "
+            INSERT INTO audit_log (
+                id, actor_user_id, action, target_type, target_id,
+                before, after, reason, ip, created_at, prev_hash, hash
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ",
+            entry.id,
+            entry.actor_id.map(|x| x.0),
+            entry.action,
+            entry.target_type,
+            entry.target_id,
+            entry.before,
+            entry.after,
+            entry.reason,
+            entry.ip,
+            entry.created_at,
+            entry.prev_hash,
+            entry.hash,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// 在独立事务中追加一条日志，用于操作本身不在事务中完成的场景
+    pub async fn record(self, pool: &PgPool) -> Result<i64, DatabaseError> {
+        let mut transaction = pool.begin().await?;
+        let id = self.insert(&mut transaction).await?;
+        transaction.commit().await?;
+        Ok(id)
+    }
+}
+
+impl AuditLogEntry {
+    /// 计算本条日志的哈希：对上一条哈希与本条全部字段的 JSON 数组做 sha256
+    ///
+    /// JSON 对象按键排序序列化，快照经数据库读回后结果不变。
+    pub fn compute_hash(&self) -> String {
+        let content = serde_json::json!([
+            self.prev_hash,
+            self.id,
+            self.created_at.timestamp_micros(),
+            self.actor_id.map(|x| x.0),
+            self.action,
+            self.target_type,
+            self.target_id,
+            self.before,
+            self.after,
+            self.reason,
+            self.ip,
+        ]);
+
+        hex::encode(sha2::Sha256::digest(content.to_string().as_bytes()))
+    }
+
+    /// 校验一段连续日志的哈希链，返回第一条不一致的日志 ID
+    ///
+    /// `prev_hash` 为这段日志之前一条的哈希，从第一条开始校验时为空。
+    pub fn verify_chain(
+        prev_hash: Option<&str>,
+        entries: &[AuditLogEntry],
+    ) -> Option<i64> {
+        let mut prev_hash = prev_hash.map(str::to_string);
+
+        for entry in entries {
+            if entry.prev_hash != prev_hash
+                || entry.compute_hash() != entry.hash
+            {
+                return Some(entry.id);
+            }
+            prev_hash = Some(entry.hash.clone());
+        }
+
+        None
+    }
+
+    pub async fn get_many<'a, E>(
+        filter: &AuditLogFilter,
+        limit: i64,
+        offset: i64,
+        executor: E,
+    ) -> Result<Vec<AuditLogEntry>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT id, actor_user_id, action, target_type, target_id,
+                before, after, reason, ip, created_at, prev_hash, hash
+            FROM audit_log
+            WHERE ($1::bigint IS NULL OR actor_user_id = $1)
+              AND ($2::text IS NULL OR action = $2)
+              AND ($3::text IS NULL OR target_type = $3)
+              AND ($4::bigint IS NULL OR target_id = $4)
+              AND ($5::timestamptz IS NULL OR created_at >= $5)
+              AND ($6::timestamptz IS NULL OR created_at < $6)
+            ORDER BY id DESC
+            LIMIT $7 OFFSET $8
+            ",
+            filter.actor_id.map(|x| x.0),
+            filter.action,
+            filter.target_type,
+            filter.target_id,
+            filter.start,
+            filter.end,
+            limit,
+            offset,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| AuditLogEntry {
+                id: row.id,
+                actor_id: row.actor_user_id.map(UserId),
+                action: row.action,
+                target_type: row.target_type,
+                target_id: row.target_id,
+                before: row.before,
+                after: row.after,
+                reason: row.reason,
+                ip: row.ip,
+                created_at: row.created_at,
+                prev_hash: row.prev_hash,
+                hash: row.hash,
+            })
+            .collect())
+    }
+
+    pub async fn count<'a, E>(
+        filter: &AuditLogFilter,
+        executor: E,
+    ) -> Result<i64, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM audit_log
+            WHERE ($1::bigint IS NULL OR actor_user_id = $1)
+              AND ($2::text IS NULL OR action = $2)
+              AND ($3::text IS NULL OR target_type = $3)
+              AND ($4::bigint IS NULL OR target_id = $4)
+              AND ($5::timestamptz IS NULL OR created_at >= $5)
+              AND ($6::timestamptz IS NULL OR created_at < $6)
+            "#,
+            filter.actor_id.map(|x| x.0),
+            filter.action,
+            filter.target_type,
+            filter.target_id,
+            filter.start,
+            filter.end,
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// 按 id 升序读取 `after_id` 之后的日志，用于逐段校验哈希链
+    pub async fn get_chain<'a, E>(
+        after_id: i64,
+        limit: i64,
+        executor: E,
+    ) -> Result<Vec<AuditLogEntry>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT id, actor_user_id, action, target_type, target_id,
+                before, after, reason, ip, created_at, prev_hash, hash
+            FROM audit_log
+            WHERE id > $1
+            ORDER BY id ASC
+            LIMIT $2
+            ",
+            after_id,
+            limit,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| AuditLogEntry {
+                id: row.id,
+                actor_id: row.actor_user_id.map(UserId),
+                action: row.action,
+                target_type: row.target_type,
+                target_id: row.target_id,
+                before: row.before,
+                after: row.after,
+                reason: row.reason,
+                ip: row.ip,
+                created_at: row.created_at,
+                prev_hash: row.prev_hash,
+                hash: row.hash,
+            })
+            .collect())
+    }
+
+    /// `id` 之前一条日志的哈希
+    pub async fn get_prev_hash<'a, E>(
+        id: i64,
+        executor: E,
+    ) -> Result<Option<String>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT hash FROM audit_log
+            WHERE id < $1
+            ORDER BY id DESC
+            LIMIT 1
+            ",
+            id,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(|row| row.hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, prev_hash: Option<String>) -> AuditLogEntry {
+        let mut entry = AuditLogEntry {
+            id,
+            actor_id: Some(UserId(7)),
+            action: "ban.create".to_string(),
+            target_type: "user_ban".to_string(),
+            target_id: Some(42),
+            before: None,
+            after: Some(
+                serde_json::json!({"reason": "刷屏", "ban_type": "forum"}),
+            ),
+            reason: Some("刷屏".to_string()),
+            ip: Some("127.0.0.1".to_string()),
+            created_at: Utc::now().trunc_subsecs(6),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        entry
+    }
+
+    #[test]
+    fn chain_verifies_and_detects_tampering() {
+        let first = entry(1, None);
+        let second = entry(2, Some(first.hash.clone()));
+        let third = entry(3, Some(second.hash.clone()));
+        let mut chain = vec![first, second, third];
+
+        assert_eq!(AuditLogEntry::verify_chain(None, &chain), None);
+        assert_eq!(
+            AuditLogEntry::verify_chain(Some(&chain[0].hash), &chain[1..]),
+            None
+        );
+
+        // 修改内容
+        chain[1].reason = Some("其他原因".to_string());
+        assert_eq!(AuditLogEntry::verify_chain(None, &chain), Some(2));
+
+        // 删除中间一条
+        chain.remove(1);
+        assert_eq!(AuditLogEntry::verify_chain(None, &chain), Some(3));
+    }
+
+    #[test]
+    fn hash_ignores_object_key_order() {
+        let mut a = entry(1, None);
+        a.after = serde_json::from_str(r#"{"a": 1, "b": [true, null]}"#).ok();
+        let mut b = a.clone();
+        b.after = serde_json::from_str(r#"{"b": [true, null], "a": 1}"#).ok();
+
+        assert_eq!(a.compute_hash(), b.compute_hash());
+    }
+}
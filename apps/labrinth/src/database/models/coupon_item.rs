@@ -0,0 +1,322 @@
+use super::DatabaseError;
+use super::ids::*;
+use crate::models::promotions::DiscountType;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 优惠券
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Coupon {
+    pub id: CouponId,
+    pub code: String,
+    pub project_id: Option<ProjectId>,
+    pub organization_id: Option<OrganizationId>,
+    pub discount_type: DiscountType,
+    pub discount_value: Decimal,
+    pub max_uses: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// 限定可使用的用户，为空表示所有用户可用
+    pub allowed_users: Vec<UserId>,
+    pub enabled: bool,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 优惠券的使用次数
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CouponUsage {
+    pub total: i64,
+    pub user: i64,
+}
+
+struct CouponQueryResult {
+    id: i64,
+    code: String,
+    project_id: Option<i64>,
+    organization_id: Option<i64>,
+    discount_type: String,
+    discount_value: Decimal,
+    max_uses: Option<i32>,
+    per_user_limit: Option<i32>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    allowed_users: Vec<i64>,
+    enabled: bool,
+    created_by: i64,
+    created_at: DateTime<Utc>,
+}
+
+impl From<CouponQueryResult> for Coupon {
+    fn from(row: CouponQueryResult) -> Self {
+        Self {
+            id: CouponId(row.id),
+            code: row.code,
+            project_id: row.project_id.map(ProjectId),
+            organization_id: row.organization_id.map(OrganizationId),
+            discount_type: DiscountType::from_string(&row.discount_type),
+            discount_value: row.discount_value,
+            max_uses: row.max_uses,
+            per_user_limit: row.per_user_limit,
+            starts_at: row.starts_at,
+            ends_at: row.ends_at,
+            allowed_users: row.allowed_users.into_iter().map(UserId).collect(),
+            enabled: row.enabled,
+            created_by: UserId(row.created_by),
+            created_at: row.created_at,
+        }
+    }
+}
+
+impl Coupon {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO coupons (
+                id, code, project_id, organization_id, discount_type,
+                discount_value, max_uses, per_user_limit, starts_at, ends_at,
+                enabled, created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ",
+            self.id.0,
+            &self.code,
+            self.project_id.map(|x| x.0),
+            self.organization_id.map(|x| x.0),
+            self.discount_type.as_str(),
+            self.discount_value,
+            self.max_uses,
+            self.per_user_limit,
+            self.starts_at,
+            self.ends_at,
+            self.enabled,
+            self.created_by.0,
+            self.created_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Self::set_allowed_users(self.id, &self.allowed_users, transaction)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 更新优惠券的可编辑字段（优惠码、归属与折扣不可修改）
+    pub async fn update(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE coupons
+            SET max_uses = $2, per_user_limit = $3, starts_at = $4,
+                ends_at = $5, enabled = $6
+            WHERE id = $1
+            ",
+            self.id.0,
+            self.max_uses,
+            self.per_user_limit,
+            self.starts_at,
+            self.ends_at,
+            self.enabled,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Self::set_allowed_users(self.id, &self.allowed_users, transaction)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_allowed_users(
+        id: CouponId,
+        users: &[UserId],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM coupon_allowed_users WHERE coupon_id = $1",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO coupon_allowed_users (coupon_id, user_id)
+            SELECT $1, * FROM UNNEST($2::bigint[])
+            ON CONFLICT DO NOTHING
+            ",
+            id.0,
+            &users.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove(
+        id: CouponId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!("DELETE FROM coupons WHERE id = $1", id.0)
+            .execute(&mut **transaction)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_id<'a, E>(
+        id: CouponId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            CouponQueryResult,
+            r#"
+            SELECT c.id, c.code, c.project_id, c.organization_id, c.discount_type,
+                   c.discount_value, c.max_uses, c.per_user_limit, c.starts_at,
+                   c.ends_at, c.enabled, c.created_by, c.created_at,
+                   ARRAY(SELECT user_id FROM coupon_allowed_users WHERE coupon_id = c.id) AS "allowed_users!"
+            FROM coupons c
+            WHERE c.id = $1
+            "#,
+            id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 获取项目或组织拥有的全部优惠券
+    pub async fn get_owned<'a, E>(
+        project_id: Option<ProjectId>,
+        organization_id: Option<OrganizationId>,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            CouponQueryResult,
+            r#"
+            SELECT c.id, c.code, c.project_id, c.organization_id, c.discount_type,
+                   c.discount_value, c.max_uses, c.per_user_limit, c.starts_at,
+                   c.ends_at, c.enabled, c.created_by, c.created_at,
+                   ARRAY(SELECT user_id FROM coupon_allowed_users WHERE coupon_id = c.id) AS "allowed_users!"
+            FROM coupons c
+            WHERE c.project_id = $1 OR c.organization_id = $2
+            ORDER BY c.created_at DESC
+            "#,
+            project_id.map(|x| x.0),
+            organization_id.map(|x| x.0),
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 按优惠码查找适用于项目的优惠券并锁定，项目优惠券优先于组织优惠券
+    pub async fn get_by_code_for_update(
+        code: &str,
+        project_id: ProjectId,
+        organization_id: Option<OrganizationId>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<Self>, DatabaseError> {
+        let result = sqlx::query_as!(
+            CouponQueryResult,
+            r#"
+            SELECT c.id, c.code, c.project_id, c.organization_id, c.discount_type,
+                   c.discount_value, c.max_uses, c.per_user_limit, c.starts_at,
+                   c.ends_at, c.enabled, c.created_by, c.created_at,
+                   ARRAY(SELECT user_id FROM coupon_allowed_users WHERE coupon_id = c.id) AS "allowed_users!"
+            FROM coupons c
+            WHERE UPPER(c.code) = UPPER($1)
+                  AND (c.project_id = $2 OR c.organization_id = $3)
+            ORDER BY c.project_id NULLS LAST
+            LIMIT 1
+            FOR UPDATE
+            "#,
+            code,
+            project_id.0,
+            organization_id.map(|x| x.0),
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 统计优惠券的使用次数：已支付订单与未过期的待支付订单都占用次数
+    ///
+    /// `exclude_order` 为即将被替换的待支付订单，不计入次数
+    pub async fn get_usage<'a, E>(
+        id: CouponId,
+        user_id: UserId,
+        exclude_order: Option<PaymentOrderId>,
+        executor: E,
+    ) -> Result<CouponUsage, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "total!",
+                   COUNT(*) FILTER (WHERE user_id = $2) AS "user!"
+            FROM payment_orders
+            WHERE coupon_id = $1
+                  AND (id <> $3 OR $3 IS NULL)
+                  AND (status = 'paid' OR (status = 'pending' AND expires_at > NOW()))
+            "#,
+            id.0,
+            user_id.0,
+            exclude_order.map(|x| x.0),
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(CouponUsage {
+            total: result.total,
+            user: result.user,
+        })
+    }
+
+    /// 批量统计优惠券的总使用次数
+    pub async fn get_uses_many<'a, E>(
+        ids: &[CouponId],
+        executor: E,
+    ) -> Result<HashMap<CouponId, i64>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            r#"
+            SELECT coupon_id AS "coupon_id!", COUNT(*) AS "uses!"
+            FROM payment_orders
+            WHERE coupon_id = ANY($1)
+                  AND (status = 'paid' OR (status = 'pending' AND expires_at > NOW()))
+            GROUP BY coupon_id
+            "#,
+            &ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|x| (CouponId(x.coupon_id), x.uses))
+            .collect())
+    }
+}
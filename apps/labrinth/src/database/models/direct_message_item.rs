@@ -0,0 +1,404 @@
+use super::DatabaseError;
+use super::ids::*;
+use crate::models::messages::DmPrivacy;
+use chrono::{DateTime, Utc};
+
+/// 私信会话摘要，按当前用户视角统计未读数
+#[derive(Clone, Debug)]
+pub struct ConversationSummary {
+    pub thread_id: ThreadId,
+    pub members: Vec<UserId>,
+    pub last_message_id: Option<ThreadMessageId>,
+    pub last_message_at: Option<DateTime<Utc>>,
+    pub unread: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct UserBlock {
+    pub user_id: UserId,
+    pub blocked_user_id: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct DirectMessage;
+
+impl DirectMessage {
+    /// 用户的私信隐私设置，未设置时为所有人可发起
+    pub async fn get_privacy<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<DmPrivacy, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "SELECT privacy FROM user_dm_settings WHERE user_id = $1",
+            user_id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result
+            .map(|row| DmPrivacy::from_string(&row.privacy))
+            .unwrap_or(DmPrivacy::Everyone))
+    }
+
+    pub async fn set_privacy(
+        user_id: UserId,
+        privacy: DmPrivacy,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO user_dm_settings (user_id, privacy, updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (user_id) DO UPDATE
+            SET privacy = EXCLUDED.privacy, updated_at = NOW()
+            ",
+            user_id.0,
+            privacy.as_str(),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// `follower_id` 是否关注了 `user_id` 作为已加入成员的任一项目
+    pub async fn is_follower<'a, E>(
+        follower_id: UserId,
+        user_id: UserId,
+        executor: E,
+    ) -> Result<bool, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT EXISTS(
+                SELECT 1 FROM mod_follows f
+                INNER JOIN mods m ON m.id = f.mod_id
+                INNER JOIN team_members tm ON tm.team_id = m.team_id
+                    AND tm.accepted = TRUE
+                WHERE f.follower_id = $1 AND tm.user_id = $2
+            )
+            ",
+            follower_id.0,
+            user_id.0,
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.exists.unwrap_or(false))
+    }
+
+    /// 屏蔽用户，返回是否为新增
+    pub async fn block(
+        user_id: UserId,
+        blocked_user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            INSERT INTO user_blocks (user_id, blocked_user_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            ",
+            user_id.0,
+            blocked_user_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 取消屏蔽，返回是否存在该屏蔽记录
+    pub async fn unblock(
+        user_id: UserId,
+        blocked_user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM user_blocks
+            WHERE user_id = $1 AND blocked_user_id = $2
+            ",
+            user_id.0,
+            blocked_user_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_blocks<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<Vec<UserBlock>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT user_id, blocked_user_id, created_at
+            FROM user_blocks
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            ",
+            user_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| UserBlock {
+                user_id: UserId(row.user_id),
+                blocked_user_id: UserId(row.blocked_user_id),
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// `sender_id` 与 `user_ids` 中任一用户之间是否存在屏蔽关系（任一方向）
+    pub async fn is_blocked_between<'a, E>(
+        sender_id: UserId,
+        user_ids: &[UserId],
+        executor: E,
+    ) -> Result<bool, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let user_ids = user_ids.iter().map(|x| x.0).collect::<Vec<_>>();
+
+        let result = sqlx::query!(
+            "
+            SELECT EXISTS(
+                SELECT 1 FROM user_blocks
+                WHERE (user_id = $1 AND blocked_user_id = ANY($2))
+                   OR (blocked_user_id = $1 AND user_id = ANY($2))
+            )
+            ",
+            sender_id.0,
+            &user_ids[..],
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.exists.unwrap_or(false))
+    }
+
+    /// 两个用户之间已有的一对一私信会话
+    pub async fn find_direct<'a, E>(
+        user_a: UserId,
+        user_b: UserId,
+        executor: E,
+    ) -> Result<Option<ThreadId>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT t.id
+            FROM threads t
+            INNER JOIN threads_members a ON a.thread_id = t.id AND a.user_id = $1
+            INNER JOIN threads_members b ON b.thread_id = t.id AND b.user_id = $2
+            WHERE t.thread_type = 'direct_message'
+              AND (SELECT COUNT(*) FROM threads_members c WHERE c.thread_id = t.id) = 2
+            ORDER BY t.id
+            LIMIT 1
+            ",
+            user_a.0,
+            user_b.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(|row| ThreadId(row.id)))
+    }
+
+    /// 用户参与的私信会话，按最后一条消息时间倒序
+    pub async fn get_user_conversations<'a, E>(
+        user_id: UserId,
+        limit: i64,
+        offset: i64,
+        executor: E,
+    ) -> Result<Vec<ConversationSummary>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            r#"
+            SELECT
+                t.id AS "thread_id!",
+                ARRAY(
+                    SELECT user_id FROM threads_members
+                    WHERE thread_id = t.id
+                    ORDER BY user_id
+                ) AS "members!",
+                lm.id AS "last_message_id?",
+                lm.created AS "last_message_at?",
+                (
+                    SELECT COUNT(*) FROM threads_messages m
+                    WHERE m.thread_id = t.id
+                      AND m.created > COALESCE(tm.last_read_at, '-infinity')
+                      AND m.author_id IS DISTINCT FROM tm.user_id
+                ) AS "unread!"
+            FROM threads_members tm
+            INNER JOIN threads t ON t.id = tm.thread_id
+                AND t.thread_type = 'direct_message'
+            LEFT JOIN LATERAL (
+                SELECT id, created FROM threads_messages
+                WHERE thread_id = t.id
+                ORDER BY created DESC
+                LIMIT 1
+            ) lm ON TRUE
+            WHERE tm.user_id = $1
+            ORDER BY lm.created DESC NULLS LAST, t.id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id.0,
+            limit,
+            offset,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| ConversationSummary {
+                thread_id: ThreadId(row.thread_id),
+                members: row.members.into_iter().map(UserId).collect(),
+                last_message_id: row.last_message_id.map(ThreadMessageId),
+                last_message_at: row.last_message_at,
+                unread: row.unread,
+            })
+            .collect())
+    }
+
+    /// 用户所有私信会话的未读消息总数与含未读消息的会话数
+    pub async fn get_unread_count<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<(i64, i64), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) AS "unread!",
+                COUNT(DISTINCT m.thread_id) AS "conversations!"
+            FROM threads_members tm
+            INNER JOIN threads t ON t.id = tm.thread_id
+                AND t.thread_type = 'direct_message'
+            INNER JOIN threads_messages m ON m.thread_id = tm.thread_id
+                AND m.created > COALESCE(tm.last_read_at, '-infinity')
+                AND m.author_id IS DISTINCT FROM tm.user_id
+            WHERE tm.user_id = $1
+            "#,
+            user_id.0,
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok((result.unread, result.conversations))
+    }
+
+    /// 将会话标记为已读到当前时间，返回用户是否为会话成员
+    pub async fn mark_read(
+        thread_id: ThreadId,
+        user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE threads_members
+            SET last_read_at = NOW()
+            WHERE thread_id = $1 AND user_id = $2
+            ",
+            thread_id.0,
+            user_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 退出会话，返回用户是否为会话成员
+    pub async fn leave(
+        thread_id: ThreadId,
+        user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM threads_members
+            WHERE thread_id = $1 AND user_id = $2
+            ",
+            thread_id.0,
+            user_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 用户在某会话中未读的私信通知
+    pub async fn get_unread_notifications(
+        thread_id: ThreadId,
+        user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<NotificationId>, DatabaseError> {
+        let thread_id = crate::models::ids::ThreadId::from(thread_id);
+
+        let results = sqlx::query!(
+            "
+            SELECT id FROM notifications
+            WHERE user_id = $1
+              AND NOT read
+              AND body ->> 'type' = 'direct_message'
+              AND body ->> 'thread_id' = $2
+            ",
+            user_id.0,
+            thread_id.to_string(),
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| NotificationId(row.id))
+            .collect())
+    }
+
+    /// 在 `user_ids` 中筛出在该会话仍有未读私信通知的用户，用于通知去重
+    pub async fn get_users_with_unread_notification(
+        thread_id: ThreadId,
+        user_ids: &[UserId],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<UserId>, DatabaseError> {
+        let thread_id = crate::models::ids::ThreadId::from(thread_id);
+        let user_ids = user_ids.iter().map(|x| x.0).collect::<Vec<_>>();
+
+        let results = sqlx::query!(
+            "
+            SELECT DISTINCT user_id FROM notifications
+            WHERE user_id = ANY($1)
+              AND NOT read
+              AND body ->> 'type' = 'direct_message'
+              AND body ->> 'thread_id' = $2
+            ",
+            &user_ids[..],
+            thread_id.to_string(),
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        Ok(results.into_iter().map(|row| UserId(row.user_id)).collect())
+    }
+}
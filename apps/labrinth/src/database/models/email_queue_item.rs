@@ -0,0 +1,187 @@
+use super::DatabaseError;
+use chrono::{DateTime, Utc};
+
+/// 待发送的邮件，由 [`crate::queue::email::EmailQueue`] 在后台发送
+pub struct EmailQueueItemBuilder {
+    pub to_address: String,
+    pub template: String,
+    pub locale: String,
+    pub subject: String,
+    pub html_body: String,
+}
+
+impl EmailQueueItemBuilder {
+    pub async fn insert<'a, E>(self, exec: E) -> Result<i64, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let id = sqlx::query_scalar!(
+            "
+            INSERT INTO email_queue (to_address, template, locale, subject, html_body)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            ",
+            self.to_address,
+            self.template,
+            self.locale,
+            self.subject,
+            self.html_body,
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(id)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailQueueItem {
+    pub id: i64,
+    pub to_address: String,
+    pub template: String,
+    pub subject: String,
+    pub html_body: String,
+    pub attempts: i32,
+}
+
+impl EmailQueueItem {
+    /// 领取一批到期的邮件并标记为发送中
+    ///
+    /// 多个实例并发领取时互不重复；领取超过 `stale_after` 仍未完成的邮件
+    /// 视为发送进程已退出，会被重新领取。
+    pub async fn claim_due<'a, E>(
+        limit: i64,
+        stale_after: chrono::Duration,
+        exec: E,
+    ) -> Result<Vec<EmailQueueItem>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let stale_before = Utc::now() - stale_after;
+
+        let items = sqlx::query!(
+            "
+            UPDATE email_queue q
+            SET status = 'sending', locked_at = CURRENT_TIMESTAMP
+            FROM (
+                SELECT id FROM email_queue
+                WHERE (status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP)
+                OR (status = 'sending' AND locked_at < $2)
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            ) due
+            WHERE q.id = due.id
+            RETURNING q.id, q.to_address, q.template, q.subject, q.html_body, q.attempts
+            ",
+            limit,
+            stale_before,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| EmailQueueItem {
+            id: x.id,
+            to_address: x.to_address,
+            template: x.template,
+            subject: x.subject,
+            html_body: x.html_body,
+            attempts: x.attempts,
+        })
+        .collect();
+
+        Ok(items)
+    }
+
+    pub async fn mark_sent<'a, E>(id: i64, exec: E) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            UPDATE email_queue
+            SET status = 'sent', attempts = attempts + 1,
+                sent_at = CURRENT_TIMESTAMP, locked_at = NULL, last_error = NULL
+            WHERE id = $1
+            ",
+            id,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 发送失败，等待 `next_attempt_at` 后重试
+    pub async fn mark_retry<'a, E>(
+        id: i64,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+        exec: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            UPDATE email_queue
+            SET status = 'pending', attempts = attempts + 1,
+                next_attempt_at = $2, locked_at = NULL, last_error = $3
+            WHERE id = $1
+            ",
+            id,
+            next_attempt_at,
+            error,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 发送失败且不再重试
+    pub async fn mark_failed<'a, E>(
+        id: i64,
+        error: &str,
+        exec: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            UPDATE email_queue
+            SET status = 'failed', attempts = attempts + 1,
+                locked_at = NULL, last_error = $2
+            WHERE id = $1
+            ",
+            id,
+            error,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 删除早于 `before` 的已完成邮件；邮件正文可能包含验证链接，不长期保留
+    pub async fn remove_finished<'a, E>(
+        before: DateTime<Utc>,
+        exec: E,
+    ) -> Result<u64, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            DELETE FROM email_queue
+            WHERE status IN ('sent', 'failed') AND created_at < $1
+            ",
+            before,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -360,6 +360,54 @@ generate_ids!(
     PaymentOrderId
 );
 
+generate_ids!(
+    pub generate_coupon_id,
+    CouponId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM coupons WHERE id=$1)",
+    CouponId
+);
+
+generate_ids!(
+    pub generate_project_sale_id,
+    ProjectSaleId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM project_sales WHERE id=$1)",
+    ProjectSaleId
+);
+
+generate_ids!(
+    pub generate_payment_refund_id,
+    PaymentRefundId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM payment_refunds WHERE id=$1)",
+    PaymentRefundId
+);
+
+generate_ids!(
+    pub generate_project_license_id,
+    ProjectLicenseId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM project_licenses WHERE id=$1)",
+    ProjectLicenseId
+);
+
+generate_ids!(
+    pub generate_project_bundle_id,
+    ProjectBundleId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM project_bundles WHERE id=$1)",
+    ProjectBundleId
+);
+
+generate_ids!(
+    pub generate_purchase_code_batch_id,
+    PurchaseCodeBatchId,
+    8,
+    "SELECT EXISTS(SELECT 1 FROM purchase_code_batches WHERE id=$1)",
+    PurchaseCodeBatchId
+);
+
 #[derive(
     Copy, Clone, Debug, PartialEq, Eq, Type, Hash, Serialize, Deserialize,
 )]
@@ -967,3 +1015,105 @@ pub struct UserPurchaseId(pub i64);
 )]
 #[sqlx(transparent)]
 pub struct PaymentOrderId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[sqlx(transparent)]
+pub struct CouponId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[sqlx(transparent)]
+pub struct ProjectSaleId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[sqlx(transparent)]
+pub struct PaymentRefundId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, Serialize, Deserialize, Eq, PartialEq, Hash,
+)]
+#[sqlx(transparent)]
+pub struct ProjectLicenseId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, Serialize, Deserialize, Eq, PartialEq, Hash,
+)]
+#[sqlx(transparent)]
+pub struct ProjectBundleId(pub i64);
+
+#[derive(
+    Copy, Clone, Debug, Type, Serialize, Deserialize, Eq, PartialEq, Hash,
+)]
+#[sqlx(transparent)]
+pub struct PurchaseCodeBatchId(pub i64);
+
+impl From<ids::CouponId> for CouponId {
+    fn from(id: ids::CouponId) -> Self {
+        CouponId(id.0 as i64)
+    }
+}
+impl From<CouponId> for ids::CouponId {
+    fn from(id: CouponId) -> Self {
+        ids::CouponId(id.0 as u64)
+    }
+}
+
+impl From<ids::ProjectSaleId> for ProjectSaleId {
+    fn from(id: ids::ProjectSaleId) -> Self {
+        ProjectSaleId(id.0 as i64)
+    }
+}
+impl From<ProjectSaleId> for ids::ProjectSaleId {
+    fn from(id: ProjectSaleId) -> Self {
+        ids::ProjectSaleId(id.0 as u64)
+    }
+}
+
+impl From<ids::PaymentRefundId> for PaymentRefundId {
+    fn from(id: ids::PaymentRefundId) -> Self {
+        PaymentRefundId(id.0 as i64)
+    }
+}
+impl From<PaymentRefundId> for ids::PaymentRefundId {
+    fn from(id: PaymentRefundId) -> Self {
+        ids::PaymentRefundId(id.0 as u64)
+    }
+}
+
+impl From<ids::ProjectLicenseId> for ProjectLicenseId {
+    fn from(id: ids::ProjectLicenseId) -> Self {
+        ProjectLicenseId(id.0 as i64)
+    }
+}
+impl From<ProjectLicenseId> for ids::ProjectLicenseId {
+    fn from(id: ProjectLicenseId) -> Self {
+        ids::ProjectLicenseId(id.0 as u64)
+    }
+}
+
+impl From<ids::ProjectBundleId> for ProjectBundleId {
+    fn from(id: ids::ProjectBundleId) -> Self {
+        ProjectBundleId(id.0 as i64)
+    }
+}
+impl From<ProjectBundleId> for ids::ProjectBundleId {
+    fn from(id: ProjectBundleId) -> Self {
+        ids::ProjectBundleId(id.0 as u64)
+    }
+}
+
+impl From<ids::PurchaseCodeBatchId> for PurchaseCodeBatchId {
+    fn from(id: ids::PurchaseCodeBatchId) -> Self {
+        PurchaseCodeBatchId(id.0 as i64)
+    }
+}
+impl From<PurchaseCodeBatchId> for ids::PurchaseCodeBatchId {
+    fn from(id: PurchaseCodeBatchId) -> Self {
+        ids::PurchaseCodeBatchId(id.0 as u64)
+    }
+}
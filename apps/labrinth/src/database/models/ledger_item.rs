@@ -0,0 +1,1055 @@
+use super::DatabaseError;
+use super::ids::*;
+use super::payment_order_item::PaymentOrderItem;
+use super::project_bundle_item::split_amount;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// 账户科目
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerAccount {
+    /// 平台应付创作者的可提现收益（按用户）
+    CreatorPayable,
+    /// 直接结算到卖家商户的销售收入（按用户）
+    CreatorSales,
+    /// 支付平台代收的买家付款
+    PaymentClearing,
+    /// 平台服务费收入
+    PlatformRevenue,
+    /// 下载激励支出
+    IncentiveExpense,
+    /// 广告收入
+    AdRevenue,
+    /// 通过云账户打款给创作者的资金
+    PayoutClearing,
+    /// 提现服务费
+    WithdrawalFees,
+    /// 平台垫付的退款
+    RefundAdvance,
+    /// 账本启用前的历史余额
+    OpeningBalance,
+}
+
+impl LedgerAccount {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CreatorPayable => "creator_payable",
+            Self::CreatorSales => "creator_sales",
+            Self::PaymentClearing => "payment_clearing",
+            Self::PlatformRevenue => "platform_revenue",
+            Self::IncentiveExpense => "incentive_expense",
+            Self::AdRevenue => "ad_revenue",
+            Self::PayoutClearing => "payout_clearing",
+            Self::WithdrawalFees => "withdrawal_fees",
+            Self::RefundAdvance => "refund_advance",
+            Self::OpeningBalance => "opening_balance",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "creator_payable" => Self::CreatorPayable,
+            "creator_sales" => Self::CreatorSales,
+            "payment_clearing" => Self::PaymentClearing,
+            "platform_revenue" => Self::PlatformRevenue,
+            "incentive_expense" => Self::IncentiveExpense,
+            "ad_revenue" => Self::AdRevenue,
+            "payout_clearing" => Self::PayoutClearing,
+            "withdrawal_fees" => Self::WithdrawalFees,
+            "refund_advance" => Self::RefundAdvance,
+            _ => Self::OpeningBalance,
+        }
+    }
+}
+
+/// 分录类型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    Sale,
+    PlatformFee,
+    IncentiveAccrual,
+    AdRevenue,
+    Withdrawal,
+    ServiceFee,
+    Refund,
+    Clawback,
+    Reversal,
+    OpeningBalance,
+}
+
+impl LedgerEntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sale => "sale",
+            Self::PlatformFee => "platform_fee",
+            Self::IncentiveAccrual => "incentive_accrual",
+            Self::AdRevenue => "ad_revenue",
+            Self::Withdrawal => "withdrawal",
+            Self::ServiceFee => "service_fee",
+            Self::Refund => "refund",
+            Self::Clawback => "clawback",
+            Self::Reversal => "reversal",
+            Self::OpeningBalance => "opening_balance",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "sale" => Self::Sale,
+            "platform_fee" => Self::PlatformFee,
+            "incentive_accrual" => Self::IncentiveAccrual,
+            "ad_revenue" => Self::AdRevenue,
+            "withdrawal" => Self::Withdrawal,
+            "service_fee" => Self::ServiceFee,
+            "refund" => Self::Refund,
+            "clawback" => Self::Clawback,
+            "reversal" => Self::Reversal,
+            _ => Self::OpeningBalance,
+        }
+    }
+}
+
+/// 分录明细，金额借方为正、贷方为负
+#[derive(Clone, Debug)]
+pub struct LedgerLine {
+    pub account: LedgerAccount,
+    pub user_id: Option<UserId>,
+    pub project_id: Option<ProjectId>,
+    pub amount: Decimal,
+    pub available_at: Option<DateTime<Utc>>,
+}
+
+impl LedgerLine {
+    /// 平台科目
+    pub fn platform(account: LedgerAccount) -> Self {
+        Self {
+            account,
+            user_id: None,
+            project_id: None,
+            amount: Decimal::ZERO,
+            available_at: None,
+        }
+    }
+
+    /// 创作者科目，可选关联项目
+    pub fn creator(
+        account: LedgerAccount,
+        user_id: UserId,
+        project_id: Option<ProjectId>,
+    ) -> Self {
+        Self {
+            account,
+            user_id: Some(user_id),
+            project_id,
+            amount: Decimal::ZERO,
+            available_at: None,
+        }
+    }
+
+    /// 收益可提现的时间，不设置时立即可用
+    pub fn available_at(mut self, available_at: DateTime<Utc>) -> Self {
+        self.available_at = Some(available_at);
+        self
+    }
+}
+
+/// 分录构建器，插入时校验借贷平衡
+pub struct LedgerEntryBuilder {
+    pub kind: LedgerEntryKind,
+    pub reference: Option<String>,
+    pub description: String,
+    pub lines: Vec<LedgerLine>,
+}
+
+impl LedgerEntryBuilder {
+    pub fn new(
+        kind: LedgerEntryKind,
+        reference: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            reference: Some(reference.into()),
+            description: description.into(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn debit(mut self, line: LedgerLine, amount: Decimal) -> Self {
+        self.lines.push(LedgerLine { amount, ..line });
+        self
+    }
+
+    pub fn credit(mut self, line: LedgerLine, amount: Decimal) -> Self {
+        self.lines.push(LedgerLine {
+            amount: -amount,
+            ..line
+        });
+        self
+    }
+
+    /// 借贷合计，平衡的分录为 0
+    pub fn imbalance(&self) -> Decimal {
+        self.lines.iter().map(|x| x.amount).sum()
+    }
+
+    /// 插入分录，金额全部为 0 时不记账并返回 None
+    pub async fn insert(
+        self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<i64>, DatabaseError> {
+        let lines: Vec<LedgerLine> = self
+            .lines
+            .into_iter()
+            .filter(|x| !x.amount.is_zero())
+            .collect();
+
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let imbalance: Decimal = lines.iter().map(|x| x.amount).sum();
+        if !imbalance.is_zero() {
+            return Err(DatabaseError::SchemaError(format!(
+                "分录借贷不平衡: kind={}, reference={:?}, 差额={}",
+                self.kind.as_str(),
+                self.reference,
+                imbalance
+            )));
+        }
+
+        let entry_id = sqlx::query!(
+            "
+            INSERT INTO ledger_entries (kind, reference, description)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            ",
+            self.kind.as_str(),
+            self.reference,
+            self.description,
+        )
+        .fetch_one(&mut **transaction)
+        .await?
+        .id;
+
+        insert_lines(entry_id, &lines, transaction).await?;
+
+        Ok(Some(entry_id))
+    }
+}
+
+async fn insert_lines(
+    entry_id: i64,
+    lines: &[LedgerLine],
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), DatabaseError> {
+    let now = Utc::now();
+    let mut accounts = Vec::with_capacity(lines.len());
+    let mut user_ids = Vec::with_capacity(lines.len());
+    let mut project_ids = Vec::with_capacity(lines.len());
+    let mut amounts = Vec::with_capacity(lines.len());
+    let mut available_ats = Vec::with_capacity(lines.len());
+    for line in lines {
+        accounts.push(line.account.as_str().to_string());
+        user_ids.push(line.user_id.map(|x| x.0));
+        project_ids.push(line.project_id.map(|x| x.0));
+        amounts.push(line.amount);
+        available_ats.push(line.available_at.unwrap_or(now));
+    }
+
+    sqlx::query!(
+        "
+        INSERT INTO ledger_lines (
+            entry_id, account, user_id, project_id, amount, available_at
+        )
+        SELECT $1, * FROM UNNEST(
+            $2::varchar[], $3::bigint[], $4::bigint[], $5::numeric[],
+            $6::timestamptz[]
+        )
+        ",
+        entry_id,
+        &accounts[..],
+        &user_ids[..] as &[Option<i64>],
+        &project_ids[..] as &[Option<i64>],
+        &amounts[..],
+        &available_ats[..],
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// 创作者明细行，附带所属分录信息
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerUserLine {
+    pub entry_id: i64,
+    pub kind: LedgerEntryKind,
+    /// 冲正分录所冲正的原分录类型
+    pub reversed_kind: Option<LedgerEntryKind>,
+    pub reference: Option<String>,
+    pub description: String,
+    pub account: LedgerAccount,
+    pub project_id: Option<ProjectId>,
+    pub amount: Decimal,
+    pub available_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 创作者余额
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerBalance {
+    pub available: Decimal,
+    pub pending: Decimal,
+}
+
+/// 按订单明细的卖家实得金额（即 `record_sale` 记入各项目的销售收入）比例拆分扣回金额
+pub fn split_clawback(
+    clawback_amount: Decimal,
+    items: &[PaymentOrderItem],
+) -> Vec<Decimal> {
+    let weights: Vec<Decimal> = items.iter().map(|x| x.seller_amount).collect();
+    split_amount(clawback_amount, &weights)
+}
+
+pub struct LedgerEntry;
+
+impl LedgerEntry {
+    /// 订单支付成功：买家款项直接结算到卖家商户，并从中计提平台服务费
+    pub async fn record_sale(
+        order_no: &str,
+        seller_id: UserId,
+        items: &[PaymentOrderItem],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let mut sale = LedgerEntryBuilder::new(
+            LedgerEntryKind::Sale,
+            order_no,
+            "项目销售",
+        );
+        let mut fee = LedgerEntryBuilder::new(
+            LedgerEntryKind::PlatformFee,
+            order_no,
+            "平台服务费",
+        );
+
+        for item in items {
+            let seller_line = LedgerLine::creator(
+                LedgerAccount::CreatorSales,
+                seller_id,
+                Some(item.project_id),
+            );
+            sale = sale
+                .debit(
+                    LedgerLine::platform(LedgerAccount::PaymentClearing),
+                    item.amount,
+                )
+                .credit(seller_line.clone(), item.amount);
+            fee = fee.debit(seller_line, item.platform_fee).credit(
+                LedgerLine::platform(LedgerAccount::PlatformRevenue),
+                item.platform_fee,
+            );
+        }
+
+        sale.insert(transaction).await?;
+        fee.insert(transaction).await?;
+
+        Ok(())
+    }
+
+    /// 订单退款
+    ///
+    /// 卖家商户原路退款时冲减销售收入与平台服务费；平台垫付时记入垫付科目，
+    /// 并从卖家可提现收益中扣回 `clawback_amount`，扣回金额按各项目的销售收入比例分摊
+    pub async fn record_refund(
+        order_no: &str,
+        seller_id: UserId,
+        items: &[PaymentOrderItem],
+        clawback_amount: Decimal,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let mut refund = LedgerEntryBuilder::new(
+            LedgerEntryKind::Refund,
+            order_no,
+            "订单退款",
+        );
+
+        for item in items {
+            refund = refund.credit(
+                LedgerLine::platform(LedgerAccount::PaymentClearing),
+                item.amount,
+            );
+
+            refund = if clawback_amount > Decimal::ZERO {
+                refund.debit(
+                    LedgerLine::platform(LedgerAccount::RefundAdvance),
+                    item.amount,
+                )
+            } else {
+                refund
+                    .debit(
+                        LedgerLine::creator(
+                            LedgerAccount::CreatorSales,
+                            seller_id,
+                            Some(item.project_id),
+                        ),
+                        item.seller_amount,
+                    )
+                    .debit(
+                        LedgerLine::platform(LedgerAccount::PlatformRevenue),
+                        item.platform_fee,
+                    )
+            };
+        }
+
+        refund.insert(transaction).await?;
+
+        let mut clawback = LedgerEntryBuilder::new(
+            LedgerEntryKind::Clawback,
+            order_no,
+            "平台垫付退款扣回",
+        );
+        for (item, amount) in
+            items.iter().zip(split_clawback(clawback_amount, items))
+        {
+            clawback = clawback
+                .debit(
+                    LedgerLine::creator(
+                        LedgerAccount::CreatorPayable,
+                        seller_id,
+                        Some(item.project_id),
+                    ),
+                    amount,
+                )
+                .credit(
+                    LedgerLine::platform(LedgerAccount::RefundAdvance),
+                    amount,
+                );
+        }
+        clawback.insert(transaction).await?;
+
+        Ok(())
+    }
+
+    /// 提现占用余额：云账户通道服务费从提现金额内扣
+    pub async fn record_withdrawal(
+        payout_id: PayoutId,
+        user_id: UserId,
+        amount: Decimal,
+        fee: Decimal,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        let reference = payout_id.0.to_string();
+        let creator =
+            LedgerLine::creator(LedgerAccount::CreatorPayable, user_id, None);
+
+        LedgerEntryBuilder::new(
+            LedgerEntryKind::Withdrawal,
+            reference.clone(),
+            "提现",
+        )
+        .debit(creator.clone(), amount - fee)
+        .credit(
+            LedgerLine::platform(LedgerAccount::PayoutClearing),
+            amount - fee,
+        )
+        .insert(transaction)
+        .await?;
+
+        LedgerEntryBuilder::new(
+            LedgerEntryKind::ServiceFee,
+            reference,
+            "提现服务费",
+        )
+        .debit(creator, fee)
+        .credit(LedgerLine::platform(LedgerAccount::WithdrawalFees), fee)
+        .insert(transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 提现被退回或打款失败，冲正提现与服务费分录，余额回到创作者账户
+    pub async fn reverse_withdrawal(
+        payout_id: PayoutId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<u64, DatabaseError> {
+        Self::reverse(
+            &[LedgerEntryKind::Withdrawal, LedgerEntryKind::ServiceFee],
+            &payout_id.0.to_string(),
+            "提现未完成，退回余额",
+            transaction,
+        )
+        .await
+    }
+
+    /// 冲正指定业务单号下尚未冲正的分录，返回新建的冲正分录数
+    pub async fn reverse(
+        kinds: &[LedgerEntryKind],
+        reference: &str,
+        description: &str,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<u64, DatabaseError> {
+        let kinds: Vec<String> =
+            kinds.iter().map(|x| x.as_str().to_string()).collect();
+
+        let originals = sqlx::query!(
+            "
+            SELECT e.id
+            FROM ledger_entries e
+            WHERE e.kind = ANY($1) AND e.reference = $2
+                  AND NOT EXISTS (
+                      SELECT 1 FROM ledger_entries r WHERE r.reversal_of = e.id
+                  )
+            ORDER BY e.id
+            FOR UPDATE
+            ",
+            &kinds[..],
+            reference,
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        for original in &originals {
+            let entry_id = sqlx::query!(
+                "
+                INSERT INTO ledger_entries (kind, reference, description, reversal_of)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id
+                ",
+                LedgerEntryKind::Reversal.as_str(),
+                reference,
+                description,
+                original.id,
+            )
+            .fetch_one(&mut **transaction)
+            .await?
+            .id;
+
+            sqlx::query!(
+                "
+                INSERT INTO ledger_lines (
+                    entry_id, account, user_id, project_id, amount, available_at
+                )
+                SELECT $1, account, user_id, project_id, -amount, NOW()
+                FROM ledger_lines
+                WHERE entry_id = $2
+                ",
+                entry_id,
+                original.id,
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+
+        Ok(originals.len() as u64)
+    }
+
+    /// 业务单号下指定类型分录在某科目的借贷合计（含冲正）
+    pub async fn get_reference_total<'a, E>(
+        kinds: &[LedgerEntryKind],
+        reference: &str,
+        account: LedgerAccount,
+        executor: E,
+    ) -> Result<Decimal, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let kinds: Vec<String> =
+            kinds.iter().map(|x| x.as_str().to_string()).collect();
+
+        let result = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(l.amount), 0) AS "total!"
+            FROM ledger_lines l
+            INNER JOIN ledger_entries e ON e.id = l.entry_id
+            LEFT JOIN ledger_entries o ON o.id = e.reversal_of
+            WHERE e.reference = $2 AND l.account = $3
+                  AND (e.kind = ANY($1) OR o.kind = ANY($1))
+            "#,
+            &kinds[..],
+            reference,
+            account.as_str(),
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.total)
+    }
+
+    /// 创作者可提现余额与待结算余额
+    pub async fn get_balance<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<LedgerBalance, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(-SUM(amount) FILTER (WHERE available_at <= NOW()), 0) AS "available!",
+                COALESCE(-SUM(amount) FILTER (WHERE available_at > NOW()), 0) AS "pending!"
+            FROM ledger_lines
+            WHERE user_id = $1 AND account = $2
+            "#,
+            user_id.0,
+            LedgerAccount::CreatorPayable.as_str(),
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(LedgerBalance {
+            available: result.available,
+            pending: result.pending,
+        })
+    }
+
+    /// 创作者在指定时间点之前入账的可提现收益余额（含待结算）
+    pub async fn get_payable_balance_at<'a, E>(
+        user_id: UserId,
+        at: DateTime<Utc>,
+        executor: E,
+    ) -> Result<Decimal, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT COALESCE(-SUM(l.amount), 0) AS "balance!"
+            FROM ledger_lines l
+            INNER JOIN ledger_entries e ON e.id = l.entry_id
+            WHERE l.user_id = $1 AND l.account = $2 AND e.created_at < $3
+            "#,
+            user_id.0,
+            LedgerAccount::CreatorPayable.as_str(),
+            at,
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.balance)
+    }
+
+    /// 在指定时间范围内有创作者科目变动，或期末仍有可提现余额的用户
+    pub async fn get_active_creators<'a, E>(
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        executor: E,
+    ) -> Result<Vec<UserId>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            r#"
+            SELECT l.user_id AS "user_id!"
+            FROM ledger_lines l
+            INNER JOIN ledger_entries e ON e.id = l.entry_id
+            WHERE l.user_id IS NOT NULL AND l.account = ANY($3)
+                  AND e.created_at < $2
+            GROUP BY l.user_id
+            HAVING BOOL_OR(e.created_at >= $1)
+                OR COALESCE(SUM(l.amount) FILTER (WHERE l.account = $4), 0) <> 0
+            ORDER BY l.user_id
+            "#,
+            start,
+            end,
+            &[
+                LedgerAccount::CreatorPayable.as_str().to_string(),
+                LedgerAccount::CreatorSales.as_str().to_string(),
+            ][..],
+            LedgerAccount::CreatorPayable.as_str(),
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(|x| UserId(x.user_id)).collect())
+    }
+
+    /// 创作者在指定科目、时间范围内的明细，按时间倒序
+    pub async fn get_user_lines<'a, E>(
+        user_id: UserId,
+        accounts: &[LedgerAccount],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        executor: E,
+    ) -> Result<Vec<LedgerUserLine>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let accounts: Vec<String> =
+            accounts.iter().map(|x| x.as_str().to_string()).collect();
+
+        let results = sqlx::query!(
+            r#"
+            SELECT e.id, e.kind, e.reference, e.description, e.created_at,
+                   r.kind AS "reversed_kind?", l.account, l.project_id,
+                   l.amount, l.available_at
+            FROM ledger_lines l
+            INNER JOIN ledger_entries e ON e.id = l.entry_id
+            LEFT JOIN ledger_entries r ON r.id = e.reversal_of
+            WHERE l.user_id = $1 AND l.account = ANY($2)
+                  AND e.created_at >= $3 AND e.created_at < $4
+            ORDER BY e.created_at DESC, l.id DESC
+            "#,
+            user_id.0,
+            &accounts[..],
+            start,
+            end,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| LedgerUserLine {
+                entry_id: row.id,
+                kind: LedgerEntryKind::from_string(&row.kind),
+                reversed_kind: row
+                    .reversed_kind
+                    .as_deref()
+                    .map(LedgerEntryKind::from_string),
+                reference: row.reference,
+                description: row.description,
+                account: LedgerAccount::from_string(&row.account),
+                project_id: row.project_id.map(ProjectId),
+                amount: row.amount,
+                available_at: row.available_at,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// 所有创作者的可提现收益余额（含待结算），用于与旧收益表核对
+    pub async fn get_all_payable<'a, E>(
+        executor: E,
+    ) -> Result<Vec<(UserId, Decimal)>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            r#"
+            SELECT user_id AS "user_id!", -SUM(amount) AS "balance!"
+            FROM ledger_lines
+            WHERE account = $1 AND user_id IS NOT NULL
+            GROUP BY user_id
+            "#,
+            LedgerAccount::CreatorPayable.as_str(),
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| (UserId(row.user_id), row.balance))
+            .collect())
+    }
+
+    /// 删除用户时把账本明细转移给 deleted_user，保留资金记录
+    pub async fn transfer_user(
+        from: UserId,
+        to: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE ledger_lines
+            SET user_id = $1
+            WHERE user_id = $2
+            ",
+            to.0,
+            from.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// 对账差异类型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconciliationIssueKind {
+    /// 已支付订单缺少销售分录，或分录金额与订单金额不一致
+    OrderAmount,
+    /// 已退款订单的销售分录未冲减
+    OrderRefund,
+    /// 存在销售分录但订单未支付
+    OrderUnpaid,
+    /// 云账户订单状态与本地提现状态不一致
+    PayoutStatus,
+    /// 云账户实付金额与账本打款金额不一致
+    PayoutAmount,
+    /// 提现分录金额与提现记录不一致
+    PayoutLedger,
+    /// 账本余额与旧收益表计算的余额不一致
+    CreatorBalance,
+    /// 无法查询云账户订单
+    ProviderError,
+}
+
+impl ReconciliationIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OrderAmount => "order_amount",
+            Self::OrderRefund => "order_refund",
+            Self::OrderUnpaid => "order_unpaid",
+            Self::PayoutStatus => "payout_status",
+            Self::PayoutAmount => "payout_amount",
+            Self::PayoutLedger => "payout_ledger",
+            Self::CreatorBalance => "creator_balance",
+            Self::ProviderError => "provider_error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "order_amount" => Self::OrderAmount,
+            "order_refund" => Self::OrderRefund,
+            "order_unpaid" => Self::OrderUnpaid,
+            "payout_status" => Self::PayoutStatus,
+            "payout_amount" => Self::PayoutAmount,
+            "payout_ledger" => Self::PayoutLedger,
+            "creator_balance" => Self::CreatorBalance,
+            _ => Self::ProviderError,
+        }
+    }
+}
+
+/// 对账差异
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReconciliationIssue {
+    pub id: i64,
+    pub kind: ReconciliationIssueKind,
+    pub reference: String,
+    pub user_id: Option<UserId>,
+    pub expected: Option<Decimal>,
+    pub actual: Option<Decimal>,
+    pub detail: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<UserId>,
+    pub resolution_note: Option<String>,
+}
+
+impl ReconciliationIssue {
+    /// 记录差异；同一业务单号已有未处理的同类差异时只更新金额与最近发现时间
+    pub async fn upsert<'a, E>(
+        kind: ReconciliationIssueKind,
+        reference: &str,
+        user_id: Option<UserId>,
+        expected: Option<Decimal>,
+        actual: Option<Decimal>,
+        detail: &str,
+        executor: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            INSERT INTO ledger_reconciliation_issues (
+                kind, reference, user_id, expected, actual, detail
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (kind, reference) WHERE resolved_at IS NULL
+            DO UPDATE SET expected = EXCLUDED.expected,
+                          actual = EXCLUDED.actual,
+                          detail = EXCLUDED.detail,
+                          last_seen_at = NOW()
+            ",
+            kind.as_str(),
+            reference,
+            user_id.map(|x| x.0),
+            expected,
+            actual,
+            detail,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_id<'a, E>(
+        id: i64,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT id, kind, reference, user_id, expected, actual, detail,
+                   first_seen_at, last_seen_at, resolved_at, resolved_by,
+                   resolution_note
+            FROM ledger_reconciliation_issues
+            WHERE id = $1
+            ",
+            id,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(|row| Self {
+            id: row.id,
+            kind: ReconciliationIssueKind::from_string(&row.kind),
+            reference: row.reference,
+            user_id: row.user_id.map(UserId),
+            expected: row.expected,
+            actual: row.actual,
+            detail: row.detail,
+            first_seen_at: row.first_seen_at,
+            last_seen_at: row.last_seen_at,
+            resolved_at: row.resolved_at,
+            resolved_by: row.resolved_by.map(UserId),
+            resolution_note: row.resolution_note,
+        }))
+    }
+
+    /// 获取差异列表，按最近发现时间倒序
+    pub async fn get_many<'a, E>(
+        include_resolved: bool,
+        limit: i64,
+        offset: i64,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT id, kind, reference, user_id, expected, actual, detail,
+                   first_seen_at, last_seen_at, resolved_at, resolved_by,
+                   resolution_note
+            FROM ledger_reconciliation_issues
+            WHERE $1 OR resolved_at IS NULL
+            ORDER BY last_seen_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            ",
+            include_resolved,
+            limit,
+            offset,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| Self {
+                id: row.id,
+                kind: ReconciliationIssueKind::from_string(&row.kind),
+                reference: row.reference,
+                user_id: row.user_id.map(UserId),
+                expected: row.expected,
+                actual: row.actual,
+                detail: row.detail,
+                first_seen_at: row.first_seen_at,
+                last_seen_at: row.last_seen_at,
+                resolved_at: row.resolved_at,
+                resolved_by: row.resolved_by.map(UserId),
+                resolution_note: row.resolution_note,
+            })
+            .collect())
+    }
+
+    /// 标记差异已处理
+    pub async fn resolve<'a, E>(
+        id: i64,
+        resolved_by: UserId,
+        note: Option<&str>,
+        executor: E,
+    ) -> Result<bool, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            UPDATE ledger_reconciliation_issues
+            SET resolved_at = NOW(), resolved_by = $2, resolution_note = $3
+            WHERE id = $1 AND resolved_at IS NULL
+            ",
+            id,
+            resolved_by.0,
+            note,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debit_and_credit_balance() {
+        let entry =
+            LedgerEntryBuilder::new(LedgerEntryKind::Sale, "BB1", "项目销售")
+                .debit(
+                    LedgerLine::platform(LedgerAccount::PaymentClearing),
+                    Decimal::new(1000, 2),
+                )
+                .credit(
+                    LedgerLine::creator(
+                        LedgerAccount::CreatorSales,
+                        UserId(1),
+                        Some(ProjectId(2)),
+                    ),
+                    Decimal::new(1000, 2),
+                );
+
+        assert!(entry.imbalance().is_zero());
+        assert_eq!(entry.lines[1].amount, Decimal::new(-1000, 2));
+    }
+
+    #[test]
+    fn clawback_follows_seller_amounts() {
+        let item = |project_id, seller_amount| PaymentOrderItem {
+            order_id: PaymentOrderId(1),
+            project_id: ProjectId(project_id),
+            original_amount: Decimal::new(1000, 2),
+            amount: Decimal::new(1000, 2),
+            platform_fee: Decimal::ZERO,
+            seller_amount,
+            validity_days: None,
+            sale_id: None,
+        };
+        let items =
+            [item(1, Decimal::new(900, 2)), item(2, Decimal::new(300, 2))];
+
+        assert_eq!(
+            split_clawback(Decimal::new(1200, 2), &items),
+            vec![Decimal::new(900, 2), Decimal::new(300, 2)]
+        );
+        assert_eq!(
+            split_clawback(Decimal::new(100, 2), &items),
+            vec![Decimal::new(75, 2), Decimal::new(25, 2)]
+        );
+    }
+
+    #[test]
+    fn account_names_round_trip() {
+        for account in [
+            LedgerAccount::CreatorPayable,
+            LedgerAccount::CreatorSales,
+            LedgerAccount::PaymentClearing,
+            LedgerAccount::PlatformRevenue,
+            LedgerAccount::IncentiveExpense,
+            LedgerAccount::AdRevenue,
+            LedgerAccount::PayoutClearing,
+            LedgerAccount::WithdrawalFees,
+            LedgerAccount::RefundAdvance,
+            LedgerAccount::OpeningBalance,
+        ] {
+            assert_eq!(LedgerAccount::from_string(account.as_str()), account);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use thiserror::Error;
 pub mod categories;
 pub mod charge_item;
 pub mod collection_item;
+pub mod coupon_item;
 pub mod flow_item;
 pub mod forum;
 pub mod ids;
@@ -19,6 +20,7 @@ pub mod payout_item;
 pub mod product_item;
 pub mod project_item;
 pub mod report_item;
+pub mod search_index_queue_item;
 pub mod session_item;
 pub mod team_item;
 pub mod thread_item;
@@ -27,17 +29,30 @@ pub mod user_subscription_item;
 pub mod version_item;
 pub mod wiki_item;
 
+pub mod audit_log_item;
 pub mod creator_application_item;
+pub mod direct_message_item;
+pub mod email_queue_item;
 pub mod issues;
+pub mod ledger_item;
+pub mod notification_preference_item;
 pub mod payment_merchant_item;
 pub mod payment_order_item;
+pub mod payment_refund_item;
+pub mod project_bundle_item;
+pub mod project_license_item;
 pub mod project_pricing_item;
+pub mod purchase_code_item;
+pub mod statement_item;
 pub mod user_ban_item;
 pub mod user_purchase_item;
 pub mod wiki_cache_item;
+pub mod wiki_revision_item;
 pub mod yunzhanghu_profile_item;
 
+pub use audit_log_item::{AuditLogBuilder, AuditLogEntry, AuditLogFilter};
 pub use collection_item::Collection;
+pub use coupon_item::Coupon;
 pub use creator_application_item::{
     ApplicationStatus, CreatorApplication, CreatorApplicationBuilder,
 };
@@ -47,12 +62,25 @@ pub use forum::PostQuery;
 pub use forum::QueryDiscussion;
 pub use ids::*;
 pub use image_item::Image;
+pub use ledger_item::{
+    LedgerAccount, LedgerEntry, LedgerEntryBuilder, LedgerEntryKind,
+    LedgerLine, ReconciliationIssue, ReconciliationIssueKind,
+};
 pub use oauth_client_item::OAuthClient;
 pub use organization_item::Organization;
 pub use payment_merchant_item::{PaymentMerchant, PaymentMerchantBuilder};
-pub use payment_order_item::{OrderStatus, PaymentMethod, PaymentOrder};
+pub use payment_order_item::{
+    OrderItemPrice, OrderPrice, OrderStatus, PaymentMethod, PaymentOrder,
+    PaymentOrderItem,
+};
+pub use payment_refund_item::PaymentRefund;
+pub use project_bundle_item::ProjectBundle;
 pub use project_item::Project;
-pub use project_pricing_item::ProjectPricing;
+pub use project_license_item::{ProjectLicense, ProjectLicenseActivation};
+pub use project_pricing_item::{ProjectPricing, ProjectSale};
+pub use purchase_code_item::{PurchaseCode, PurchaseCodeBatch};
+pub use search_index_queue_item::SearchIndexQueueEntry;
+pub use statement_item::CreatorStatement;
 pub use team_item::Team;
 pub use team_item::TeamMember;
 pub use thread_item::{Thread, ThreadMessage};
@@ -65,6 +93,7 @@ pub use user_purchase_item::{PurchaseStatus, UserPurchase};
 pub use version_item::Version;
 pub use wiki_cache_item::WikiCache;
 pub use wiki_item::Wiki;
+pub use wiki_revision_item::WikiRevision;
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
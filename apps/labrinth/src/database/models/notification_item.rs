@@ -1,6 +1,8 @@
 use super::ids::*;
+use super::notification_preference_item::NotificationPreference;
 use crate::database::{models::DatabaseError, redis::RedisPool};
-use crate::models::notifications::NotificationBody;
+use crate::models::notifications::{NotificationBody, NotificationChannel};
+use crate::queue::push::{PushEventBody, PushEvents};
 use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
@@ -30,21 +32,54 @@ pub struct NotificationAction {
 }
 
 impl NotificationBuilder {
+    #[must_use = "新通知需要在事务提交后推送"]
     pub async fn insert(
         &self,
         user: UserId,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         redis: &RedisPool,
-    ) -> Result<(), DatabaseError> {
+    ) -> Result<PushEvents, DatabaseError> {
         self.insert_many(vec![user], transaction, redis).await
     }
 
+    /// 按每个用户对该通知类型的偏好投递：关闭的用户不创建通知，选择邮件
+    /// 的用户同时在事务中加入邮件队列，选择摘要的用户标记待摘要。
+    ///
+    /// 返回新通知的推送事件，调用方在事务提交后通过
+    /// [`crate::queue::push::publish`] 推送，避免事务回滚后仍推送出去
+    #[must_use = "新通知需要在事务提交后推送"]
     pub async fn insert_many(
         &self,
         users: Vec<UserId>,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         redis: &RedisPool,
-    ) -> Result<(), DatabaseError> {
+    ) -> Result<PushEvents, DatabaseError> {
+        let notification_type = self.body.type_name();
+        let channels = NotificationPreference::get_channels(
+            &users,
+            notification_type,
+            &mut **transaction,
+        )
+        .await?;
+        let channel = |user: &UserId| {
+            channels
+                .get(user)
+                .copied()
+                .unwrap_or(NotificationChannel::InApp)
+        };
+
+        let users = if NotificationBody::is_required_type(notification_type) {
+            users
+        } else {
+            users
+                .into_iter()
+                .filter(|x| channel(x) != NotificationChannel::Off)
+                .collect()
+        };
+        if users.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let notification_ids =
             generate_many_notification_ids(users.len(), &mut *transaction)
                 .await?;
@@ -55,26 +90,63 @@ impl NotificationBuilder {
             .map(|_| body.clone())
             .collect::<Vec<_>>();
 
+        let digests = users
+            .iter()
+            .map(|x| channel(x).digest().map(|x| x.to_string()))
+            .collect::<Vec<_>>();
+
         sqlx::query!(
             "
             INSERT INTO notifications (
-                id, user_id, body
+                id, user_id, body, digest
             )
-            SELECT * FROM UNNEST($1::bigint[], $2::bigint[], $3::jsonb[])
+            SELECT * FROM UNNEST($1::bigint[], $2::bigint[], $3::jsonb[], $4::varchar[])
             ",
             &notification_ids
-                .into_iter()
+                .iter()
                 .map(|x| x.0)
                 .collect::<Vec<_>>()[..],
             &users.iter().map(|x| x.0).collect::<Vec<_>>()[..],
             &bodies[..],
+            &digests[..],
         )
         .execute(&mut **transaction)
         .await?;
 
+        let email_users = users
+            .iter()
+            .filter(|x| channel(x) == NotificationChannel::Email)
+            .copied()
+            .collect::<Vec<_>>();
+        crate::queue::notifications::enqueue_emails(
+            &self.body,
+            &email_users,
+            transaction,
+        )
+        .await?;
+
         Notification::clear_user_notifications_cache(&users, redis).await?;
 
-        Ok(())
+        let created = Utc::now();
+        Ok(notification_ids
+            .into_iter()
+            .zip(users)
+            .map(|(id, user_id)| {
+                let notification = Notification {
+                    id,
+                    user_id,
+                    body: self.body.clone(),
+                    read: false,
+                    created,
+                };
+                (
+                    user_id,
+                    PushEventBody::Notification {
+                        notification: notification.into(),
+                    },
+                )
+            })
+            .collect())
     }
 }
 
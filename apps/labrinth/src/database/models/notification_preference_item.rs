@@ -0,0 +1,323 @@
+use super::DatabaseError;
+use super::ids::*;
+use crate::models::notifications::NotificationChannel;
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::HashMap;
+
+/// 用户按通知类型设置的投递方式，未设置的类型为站内通知
+pub struct NotificationPreference;
+
+impl NotificationPreference {
+    /// 用户已设置的投递方式，键为通知类型
+    pub async fn get_user<'a, E>(
+        user_id: UserId,
+        exec: E,
+    ) -> Result<HashMap<String, NotificationChannel>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let preferences = sqlx::query!(
+            "
+            SELECT notification_type, channel
+            FROM notification_preferences
+            WHERE user_id = $1
+            ",
+            user_id.0,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| {
+            (
+                x.notification_type,
+                NotificationChannel::from_string(&x.channel),
+            )
+        })
+        .collect();
+
+        Ok(preferences)
+    }
+
+    /// 多个用户对某一通知类型设置的投递方式，未设置的用户不在结果中
+    pub async fn get_channels<'a, E>(
+        user_ids: &[UserId],
+        notification_type: &str,
+        exec: E,
+    ) -> Result<HashMap<UserId, NotificationChannel>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let channels = sqlx::query!(
+            "
+            SELECT user_id, channel
+            FROM notification_preferences
+            WHERE user_id = ANY($1) AND notification_type = $2
+            ",
+            &user_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+            notification_type,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| {
+            (
+                UserId(x.user_id),
+                NotificationChannel::from_string(&x.channel),
+            )
+        })
+        .collect();
+
+        Ok(channels)
+    }
+
+    pub async fn set_many(
+        user_id: UserId,
+        preferences: &[(String, NotificationChannel)],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO notification_preferences (user_id, notification_type, channel, updated_at)
+            SELECT $1, * , NOW() FROM UNNEST($2::varchar[], $3::varchar[])
+            ON CONFLICT (user_id, notification_type) DO UPDATE
+            SET channel = EXCLUDED.channel, updated_at = NOW()
+            ",
+            user_id.0,
+            &preferences.iter().map(|x| x.0.clone()).collect::<Vec<_>>(),
+            &preferences
+                .iter()
+                .map(|x| x.1.as_str().to_string())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 通过退订令牌停止发送邮件：指定类型时只改该类型，否则改所有邮件与
+    /// 摘要方式；退订后仍保留站内通知。返回令牌对应的用户
+    pub async fn unsubscribe(
+        token: &str,
+        notification_type: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<UserId>, DatabaseError> {
+        let Some(user_id) = sqlx::query_scalar!(
+            "SELECT user_id FROM notification_unsubscribe_tokens WHERE token = $1",
+            token,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        if let Some(notification_type) = notification_type {
+            sqlx::query!(
+                "
+                INSERT INTO notification_preferences (user_id, notification_type, channel, updated_at)
+                VALUES ($1, $2, 'in_app', NOW())
+                ON CONFLICT (user_id, notification_type) DO UPDATE
+                SET channel = 'in_app', updated_at = NOW()
+                WHERE notification_preferences.channel IN ('email', 'daily', 'weekly')
+                ",
+                user_id,
+                notification_type,
+            )
+            .execute(&mut **transaction)
+            .await?;
+        } else {
+            sqlx::query!(
+                "
+                UPDATE notification_preferences
+                SET channel = 'in_app', updated_at = NOW()
+                WHERE user_id = $1 AND channel IN ('email', 'daily', 'weekly')
+                ",
+                user_id,
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+
+        sqlx::query!(
+            "
+            UPDATE notifications
+            SET digest = NULL
+            WHERE user_id = $1 AND digest IS NOT NULL
+            AND ($2::varchar IS NULL OR body ->> 'type' = $2)
+            ",
+            user_id,
+            notification_type,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(Some(UserId(user_id)))
+    }
+
+    /// 用户已验证的邮箱，未设置或未验证邮箱的用户不在结果中
+    pub async fn get_verified_emails<'a, E>(
+        user_ids: &[UserId],
+        exec: E,
+    ) -> Result<Vec<(UserId, String)>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let emails = sqlx::query!(
+            "
+            SELECT id, email
+            FROM users
+            WHERE id = ANY($1) AND email IS NOT NULL AND email_verified = TRUE
+            ",
+            &user_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .filter_map(|x| Some((UserId(x.id), x.email?)))
+        .collect();
+
+        Ok(emails)
+    }
+
+    /// 获取用户的退订令牌，没有时生成
+    pub async fn get_unsubscribe_tokens(
+        user_ids: &[UserId],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<HashMap<UserId, String>, DatabaseError> {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let tokens = user_ids
+            .iter()
+            .map(|_| {
+                (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(48)
+                    .map(char::from)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            "
+            INSERT INTO notification_unsubscribe_tokens (user_id, token)
+            SELECT * FROM UNNEST($1::bigint[], $2::varchar[])
+            ON CONFLICT (user_id) DO NOTHING
+            ",
+            &user_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+            &tokens,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        let tokens = sqlx::query!(
+            "
+            SELECT user_id, token
+            FROM notification_unsubscribe_tokens
+            WHERE user_id = ANY($1)
+            ",
+            &user_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(&mut **transaction)
+        .await?
+        .into_iter()
+        .map(|x| (UserId(x.user_id), x.token))
+        .collect();
+
+        Ok(tokens)
+    }
+}
+
+/// 到期需要发送摘要的用户与频率
+pub struct DueDigest {
+    pub user_id: UserId,
+    pub frequency: String,
+}
+
+impl DueDigest {
+    /// 距上次发送（从未发送时为最早一条待发通知）已满一个周期的摘要
+    pub async fn get_due<'a, E>(
+        limit: i64,
+        exec: E,
+    ) -> Result<Vec<DueDigest>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let due = sqlx::query!(
+            "
+            SELECT n.user_id, n.digest frequency
+            FROM notifications n
+            LEFT JOIN notification_digests d
+                ON d.user_id = n.user_id AND d.frequency = n.digest
+            WHERE n.digest IS NOT NULL
+            GROUP BY n.user_id, n.digest, d.last_sent_at
+            HAVING COALESCE(d.last_sent_at, MIN(n.created)) <= NOW() - (
+                CASE WHEN n.digest = 'weekly' THEN INTERVAL '7 days' ELSE INTERVAL '1 day' END
+            )
+            LIMIT $1
+            ",
+            limit,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .filter_map(|x| {
+            Some(DueDigest {
+                user_id: UserId(x.user_id),
+                frequency: x.frequency?,
+            })
+        })
+        .collect();
+
+        Ok(due)
+    }
+
+    /// 取出待加入摘要的通知并清除标记，返回其中未读的通知
+    pub async fn take_unread(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<NotificationId>, DatabaseError> {
+        let ids = sqlx::query!(
+            "
+            UPDATE notifications
+            SET digest = NULL
+            WHERE user_id = $1 AND digest = $2
+            RETURNING id, read
+            ",
+            self.user_id.0,
+            self.frequency,
+        )
+        .fetch_all(&mut **transaction)
+        .await?
+        .into_iter()
+        .filter(|x| !x.read)
+        .map(|x| NotificationId(x.id))
+        .collect();
+
+        Ok(ids)
+    }
+
+    pub async fn mark_sent(
+        &self,
+        sent_at: DateTime<Utc>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO notification_digests (user_id, frequency, last_sent_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, frequency) DO UPDATE
+            SET last_sent_at = EXCLUDED.last_sent_at
+            ",
+            self.user_id.0,
+            self.frequency,
+            sent_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+}
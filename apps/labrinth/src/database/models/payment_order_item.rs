@@ -82,6 +82,62 @@ pub struct PaymentOrder {
     pub created_at: DateTime<Utc>,
     pub paid_at: Option<DateTime<Utc>>,
     pub expires_at: Option<DateTime<Utc>>,
+    /// 原价（项目定价）
+    pub original_amount: Decimal,
+    /// 促销与优惠券减免的金额，amount = original_amount - discount_amount
+    pub discount_amount: Decimal,
+    pub coupon_id: Option<CouponId>,
+    pub sale_id: Option<ProjectSaleId>,
+    /// 通过捆绑包购买时的捆绑包
+    pub bundle_id: Option<ProjectBundleId>,
+    /// 订单包含的项目数量，大于 1 时为多项目订单
+    pub item_count: i32,
+    /// 赠送订单的受赠用户
+    pub gift_recipient_id: Option<UserId>,
+}
+
+/// 订单明细：订单中的单个项目及其分摊的金额
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaymentOrderItem {
+    pub order_id: PaymentOrderId,
+    pub project_id: ProjectId,
+    pub original_amount: Decimal,
+    /// 该项目分摊的实际支付金额
+    pub amount: Decimal,
+    pub platform_fee: Decimal,
+    /// 该项目的创作者收入
+    pub seller_amount: Decimal,
+    pub validity_days: Option<i32>,
+    pub sale_id: Option<ProjectSaleId>,
+}
+
+/// 下单时计算出的订单价格
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderPrice {
+    /// 原价（项目定价）
+    pub original_amount: Decimal,
+    /// 实际支付金额
+    pub amount: Decimal,
+    pub coupon_id: Option<CouponId>,
+    pub sale_id: Option<ProjectSaleId>,
+}
+
+impl OrderPrice {
+    pub fn discount_amount(&self) -> Decimal {
+        self.original_amount - self.amount
+    }
+}
+
+/// 多项目订单中单个项目的价格
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderItemPrice {
+    pub project_id: ProjectId,
+    /// 原价（项目定价）
+    pub original_amount: Decimal,
+    /// 分摊到该项目的实际支付金额
+    pub amount: Decimal,
+    pub validity_days: Option<i32>,
+    pub sale_id: Option<ProjectSaleId>,
 }
 
 /// 平台服务费率 (2.5%)
@@ -91,6 +147,11 @@ const PLATFORM_FEE_RATE: f64 = 0.025;
 const ORDER_EXPIRE_MINUTES: i64 = 30;
 
 impl PaymentOrder {
+    /// 获得购买授权的用户：赠送订单为受赠用户，否则为买家
+    pub fn grantee(&self) -> UserId {
+        self.gift_recipient_id.unwrap_or(self.user_id)
+    }
+
     /// 生成订单号
     /// 格式: BB{4位随机大写字母}{yyyyMMddHHmmss}{4位随机数}
     /// 例如: BBABCD2026013012345601234
@@ -124,36 +185,87 @@ impl PaymentOrder {
         fee.round_dp(2)
     }
 
-    /// 创建订单
+    /// 创建订单，`gift_recipient_id` 不为空时为赠送订单
     pub async fn create(
         user_id: UserId,
         project_id: ProjectId,
         seller_id: UserId,
-        amount: Decimal,
+        price: &OrderPrice,
         validity_days: Option<i32>,
+        gift_recipient_id: Option<UserId>,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Self, DatabaseError> {
+        Self::create_with_items(
+            user_id,
+            seller_id,
+            None,
+            price.coupon_id,
+            gift_recipient_id,
+            &[OrderItemPrice {
+                project_id,
+                original_amount: price.original_amount,
+                amount: price.amount,
+                validity_days,
+                sale_id: price.sale_id,
+            }],
+            transaction,
+        )
+        .await
+    }
+
+    /// 创建包含一个或多个项目的订单
+    ///
+    /// 平台服务费按每个项目分摊的金额分别计算，订单金额为各项目之和。
+    /// 多项目订单的 project_id 为第一个项目，有效期与促销记录在订单明细中。
+    pub async fn create_with_items(
+        user_id: UserId,
+        seller_id: UserId,
+        bundle_id: Option<ProjectBundleId>,
+        coupon_id: Option<CouponId>,
+        gift_recipient_id: Option<UserId>,
+        items: &[OrderItemPrice],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Self, DatabaseError> {
+        let first = items.first().ok_or_else(|| {
+            DatabaseError::SchemaError("订单至少需要包含一个项目".to_string())
+        })?;
+
         let id = generate_payment_order_id(&mut *transaction).await?;
         let order_no = Self::generate_order_no();
         let now = Utc::now();
         let expires_at = now + Duration::minutes(ORDER_EXPIRE_MINUTES);
 
-        let platform_fee = Self::calculate_platform_fee(amount);
+        // 平台服务费按实际支付金额计算
+        let fees: Vec<Decimal> = items
+            .iter()
+            .map(|x| Self::calculate_platform_fee(x.amount))
+            .collect();
+        let amount: Decimal = items.iter().map(|x| x.amount).sum();
+        let original_amount: Decimal =
+            items.iter().map(|x| x.original_amount).sum();
+        let platform_fee: Decimal = fees.iter().sum();
         let seller_amount = amount - platform_fee;
+        let discount_amount = original_amount - amount;
+
+        let single = items.len() == 1;
+        let validity_days = if single { first.validity_days } else { None };
+        let sale_id = if single { first.sale_id } else { None };
 
         sqlx::query!(
             "
             INSERT INTO payment_orders (
                 id, order_no, user_id, project_id, seller_id,
                 amount, platform_fee, seller_amount, status,
-                validity_days, created_at, expires_at
+                validity_days, created_at, expires_at,
+                original_amount, discount_amount, coupon_id, sale_id,
+                bundle_id, item_count, gift_recipient_id
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'pending', $9, $10, $11)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'pending', $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
             ",
             id.0,
             &order_no,
             user_id.0,
-            project_id.0,
+            first.project_id.0,
             seller_id.0,
             amount,
             platform_fee,
@@ -161,6 +273,43 @@ impl PaymentOrder {
             validity_days,
             now,
             expires_at,
+            original_amount,
+            discount_amount,
+            coupon_id.map(|x| x.0),
+            sale_id.map(|x| x.0),
+            bundle_id.map(|x| x.0),
+            items.len() as i32,
+            gift_recipient_id.map(|x| x.0),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO payment_order_items (
+                order_id, project_id, original_amount, amount, platform_fee,
+                seller_amount, validity_days, sale_id
+            )
+            SELECT $1, * FROM UNNEST(
+                $2::bigint[], $3::numeric[], $4::numeric[], $5::numeric[],
+                $6::numeric[], $7::int[], $8::bigint[]
+            )
+            ",
+            id.0,
+            &items.iter().map(|x| x.project_id.0).collect::<Vec<_>>(),
+            &items.iter().map(|x| x.original_amount).collect::<Vec<_>>(),
+            &items.iter().map(|x| x.amount).collect::<Vec<_>>(),
+            &fees,
+            &items
+                .iter()
+                .zip(&fees)
+                .map(|(x, fee)| x.amount - fee)
+                .collect::<Vec<_>>(),
+            &items.iter().map(|x| x.validity_days).collect::<Vec<_>>(),
+            &items
+                .iter()
+                .map(|x| x.sale_id.map(|x| x.0))
+                .collect::<Vec<_>>(),
         )
         .execute(&mut **transaction)
         .await?;
@@ -170,7 +319,7 @@ impl PaymentOrder {
             order_no,
             external_order_no: None,
             user_id,
-            project_id,
+            project_id: first.project_id,
             seller_id,
             amount,
             platform_fee,
@@ -182,9 +331,52 @@ impl PaymentOrder {
             created_at: now,
             paid_at: None,
             expires_at: Some(expires_at),
+            original_amount,
+            discount_amount,
+            coupon_id,
+            sale_id,
+            bundle_id,
+            item_count: items.len() as i32,
+            gift_recipient_id,
         })
     }
 
+    /// 获取订单明细
+    pub async fn get_items<'a, E>(
+        id: PaymentOrderId,
+        executor: E,
+    ) -> Result<Vec<PaymentOrderItem>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT order_id, project_id, original_amount, amount, platform_fee,
+                   seller_amount, validity_days, sale_id
+            FROM payment_order_items
+            WHERE order_id = $1
+            ORDER BY amount DESC, project_id
+            ",
+            id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| PaymentOrderItem {
+                order_id: PaymentOrderId(row.order_id),
+                project_id: ProjectId(row.project_id),
+                original_amount: row.original_amount,
+                amount: row.amount,
+                platform_fee: row.platform_fee,
+                seller_amount: row.seller_amount,
+                validity_days: row.validity_days,
+                sale_id: row.sale_id.map(ProjectSaleId),
+            })
+            .collect())
+    }
+
     /// 根据订单号获取订单
     pub async fn get_by_order_no<'a, E>(
         order_no: &str,
@@ -197,7 +389,9 @@ impl PaymentOrder {
             "
             SELECT id, order_no, external_order_no, user_id, project_id, seller_id,
                    amount, platform_fee, seller_amount, status, payment_method,
-                   qr_code_url, validity_days, created_at, paid_at, expires_at
+                   qr_code_url, validity_days, created_at, paid_at, expires_at,
+                   original_amount, discount_amount, coupon_id, sale_id,
+                   bundle_id, item_count, gift_recipient_id
             FROM payment_orders
             WHERE order_no = $1
             ",
@@ -225,13 +419,23 @@ impl PaymentOrder {
             created_at: row.created_at,
             paid_at: row.paid_at,
             expires_at: row.expires_at,
+            original_amount: row.original_amount,
+            discount_amount: row.discount_amount,
+            coupon_id: row.coupon_id.map(CouponId),
+            sale_id: row.sale_id.map(ProjectSaleId),
+            bundle_id: row.bundle_id.map(ProjectBundleId),
+            item_count: row.item_count,
+            gift_recipient_id: row.gift_recipient_id.map(UserId),
         }))
     }
 
-    /// 获取用户对某项目的待支付订单
+    /// 获取用户对某项目的待支付订单（仅单项目订单）
+    ///
+    /// 为自己购买与赠送给不同用户的待支付订单相互独立
     pub async fn get_pending_by_user_project<'a, E>(
         user_id: UserId,
         project_id: ProjectId,
+        gift_recipient_id: Option<UserId>,
         executor: E,
     ) -> Result<Option<Self>, DatabaseError>
     where
@@ -241,15 +445,20 @@ impl PaymentOrder {
             "
             SELECT id, order_no, external_order_no, user_id, project_id, seller_id,
                    amount, platform_fee, seller_amount, status, payment_method,
-                   qr_code_url, validity_days, created_at, paid_at, expires_at
+                   qr_code_url, validity_days, created_at, paid_at, expires_at,
+                   original_amount, discount_amount, coupon_id, sale_id,
+                   bundle_id, item_count, gift_recipient_id
             FROM payment_orders
             WHERE user_id = $1 AND project_id = $2 AND status = 'pending'
+                  AND item_count = 1
+                  AND gift_recipient_id IS NOT DISTINCT FROM $3
                   AND (expires_at IS NULL OR expires_at > NOW())
             ORDER BY created_at DESC
             LIMIT 1
             ",
             user_id.0,
             project_id.0,
+            gift_recipient_id.map(|x| x.0),
         )
         .fetch_optional(executor)
         .await?;
@@ -273,6 +482,13 @@ impl PaymentOrder {
             created_at: row.created_at,
             paid_at: row.paid_at,
             expires_at: row.expires_at,
+            original_amount: row.original_amount,
+            discount_amount: row.discount_amount,
+            coupon_id: row.coupon_id.map(CouponId),
+            sale_id: row.sale_id.map(ProjectSaleId),
+            bundle_id: row.bundle_id.map(ProjectBundleId),
+            item_count: row.item_count,
+            gift_recipient_id: row.gift_recipient_id.map(UserId),
         }))
     }
 
@@ -328,6 +544,25 @@ impl PaymentOrder {
         Ok(result.rows_affected())
     }
 
+    /// 将待支付订单标记为已过期，用于价格变化后（促销开始、使用优惠券）替换旧订单
+    pub async fn expire_pending(
+        id: PaymentOrderId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_orders
+            SET status = 'expired'
+            WHERE id = $1 AND status = 'pending'
+            ",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// 标记订单为已支付
     pub async fn mark_as_paid(
         order_no: &str,
@@ -342,7 +577,9 @@ impl PaymentOrder {
             WHERE order_no = $1 AND status = 'pending'
             RETURNING id, order_no, external_order_no, user_id, project_id, seller_id,
                       amount, platform_fee, seller_amount, status, payment_method,
-                      qr_code_url, validity_days, created_at, paid_at, expires_at
+                      qr_code_url, validity_days, created_at, paid_at, expires_at,
+                      original_amount, discount_amount, coupon_id, sale_id,
+                      bundle_id, item_count, gift_recipient_id
             ",
             order_no,
             now,
@@ -369,9 +606,54 @@ impl PaymentOrder {
             created_at: row.created_at,
             paid_at: row.paid_at,
             expires_at: row.expires_at,
+            original_amount: row.original_amount,
+            discount_amount: row.discount_amount,
+            coupon_id: row.coupon_id.map(CouponId),
+            sale_id: row.sale_id.map(ProjectSaleId),
+            bundle_id: row.bundle_id.map(ProjectBundleId),
+            item_count: row.item_count,
+            gift_recipient_id: row.gift_recipient_id.map(UserId),
         }))
     }
 
+    /// 锁定订单直到事务结束，返回订单当前状态
+    pub async fn lock_status(
+        id: PaymentOrderId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<OrderStatus>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT status FROM payment_orders
+            WHERE id = $1
+            FOR UPDATE
+            ",
+            id.0,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.map(|row| OrderStatus::from_string(&row.status)))
+    }
+
+    /// 将已支付订单标记为已退款
+    pub async fn mark_as_refunded(
+        id: PaymentOrderId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_orders
+            SET status = 'refunded'
+            WHERE id = $1 AND status = 'paid'
+            ",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// 删除超过 12 小时未付款的待支付订单
     pub async fn delete_stale_pending_orders(
         pool: &sqlx::PgPool,
@@ -401,7 +683,9 @@ impl PaymentOrder {
             "
             SELECT id, order_no, external_order_no, user_id, project_id, seller_id,
                    amount, platform_fee, seller_amount, status, payment_method,
-                   qr_code_url, validity_days, created_at, paid_at, expires_at
+                   qr_code_url, validity_days, created_at, paid_at, expires_at,
+                   original_amount, discount_amount, coupon_id, sale_id,
+                   bundle_id, item_count, gift_recipient_id
             FROM payment_orders
             WHERE user_id = $1
             ORDER BY created_at DESC
@@ -432,6 +716,13 @@ impl PaymentOrder {
                 created_at: row.created_at,
                 paid_at: row.paid_at,
                 expires_at: row.expires_at,
+                original_amount: row.original_amount,
+                discount_amount: row.discount_amount,
+                coupon_id: row.coupon_id.map(CouponId),
+                sale_id: row.sale_id.map(ProjectSaleId),
+                bundle_id: row.bundle_id.map(ProjectBundleId),
+                item_count: row.item_count,
+                gift_recipient_id: row.gift_recipient_id.map(UserId),
             })
             .collect())
     }
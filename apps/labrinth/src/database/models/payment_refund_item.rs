@@ -0,0 +1,401 @@
+use super::DatabaseError;
+use super::ids::*;
+use super::ledger_item::split_clawback;
+use super::payment_order_item::PaymentOrderItem;
+use crate::models::refunds::{RefundSource, RefundStatus};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// 订单退款记录
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaymentRefund {
+    pub id: PaymentRefundId,
+    pub order_id: PaymentOrderId,
+    /// 订单号，查询时从订单表关联
+    pub order_no: String,
+    pub user_id: UserId,
+    pub project_id: ProjectId,
+    pub seller_id: UserId,
+    pub amount: Decimal,
+    pub reason: String,
+    pub status: RefundStatus,
+    pub source: RefundSource,
+    pub thread_id: Option<ThreadId>,
+    pub reviewer_id: Option<UserId>,
+    pub review_note: Option<String>,
+    pub external_refund_no: Option<String>,
+    pub clawback_amount: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+struct PaymentRefundQueryResult {
+    id: i64,
+    order_id: i64,
+    order_no: String,
+    user_id: i64,
+    project_id: i64,
+    seller_id: i64,
+    amount: Decimal,
+    reason: String,
+    status: String,
+    source: String,
+    thread_id: Option<i64>,
+    reviewer_id: Option<i64>,
+    review_note: Option<String>,
+    external_refund_no: Option<String>,
+    clawback_amount: Decimal,
+    created_at: DateTime<Utc>,
+    reviewed_at: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<PaymentRefundQueryResult> for PaymentRefund {
+    fn from(row: PaymentRefundQueryResult) -> Self {
+        Self {
+            id: PaymentRefundId(row.id),
+            order_id: PaymentOrderId(row.order_id),
+            order_no: row.order_no,
+            user_id: UserId(row.user_id),
+            project_id: ProjectId(row.project_id),
+            seller_id: UserId(row.seller_id),
+            amount: row.amount,
+            reason: row.reason,
+            status: RefundStatus::from_string(&row.status),
+            source: RefundSource::from_string(&row.source),
+            thread_id: row.thread_id.map(ThreadId),
+            reviewer_id: row.reviewer_id.map(UserId),
+            review_note: row.review_note,
+            external_refund_no: row.external_refund_no,
+            clawback_amount: row.clawback_amount,
+            created_at: row.created_at,
+            reviewed_at: row.reviewed_at,
+            completed_at: row.completed_at,
+        }
+    }
+}
+
+impl PaymentRefund {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO payment_refunds (
+                id, order_id, user_id, project_id, seller_id, amount,
+                reason, status, source, thread_id, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ",
+            self.id.0,
+            self.order_id.0,
+            self.user_id.0,
+            self.project_id.0,
+            self.seller_id.0,
+            self.amount,
+            &self.reason,
+            self.status.as_str(),
+            self.source.as_str(),
+            self.thread_id.map(|x| x.0),
+            self.created_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_id<'a, E>(
+        id: PaymentRefundId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            PaymentRefundQueryResult,
+            "
+            SELECT r.id, r.order_id, o.order_no, r.user_id, r.project_id,
+                   r.seller_id, r.amount, r.reason, r.status, r.source,
+                   r.thread_id, r.reviewer_id, r.review_note,
+                   r.external_refund_no, r.clawback_amount, r.created_at,
+                   r.reviewed_at, r.completed_at
+            FROM payment_refunds r
+            INNER JOIN payment_orders o ON o.id = r.order_id
+            WHERE r.id = $1
+            ",
+            id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 获取订单未取消的退款记录（每个订单最多一条）
+    pub async fn get_by_order<'a, E>(
+        order_id: PaymentOrderId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            PaymentRefundQueryResult,
+            "
+            SELECT r.id, r.order_id, o.order_no, r.user_id, r.project_id,
+                   r.seller_id, r.amount, r.reason, r.status, r.source,
+                   r.thread_id, r.reviewer_id, r.review_note,
+                   r.external_refund_no, r.clawback_amount, r.created_at,
+                   r.reviewed_at, r.completed_at
+            FROM payment_refunds r
+            INNER JOIN payment_orders o ON o.id = r.order_id
+            WHERE r.order_id = $1 AND r.status <> 'cancelled'
+            ",
+            order_id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 获取用户发起的全部退款记录
+    pub async fn get_user<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            PaymentRefundQueryResult,
+            "
+            SELECT r.id, r.order_id, o.order_no, r.user_id, r.project_id,
+                   r.seller_id, r.amount, r.reason, r.status, r.source,
+                   r.thread_id, r.reviewer_id, r.review_note,
+                   r.external_refund_no, r.clawback_amount, r.created_at,
+                   r.reviewed_at, r.completed_at
+            FROM payment_refunds r
+            INNER JOIN payment_orders o ON o.id = r.order_id
+            WHERE r.user_id = $1
+            ORDER BY r.created_at DESC
+            ",
+            user_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 获取项目的全部退款记录
+    pub async fn get_project<'a, E>(
+        project_id: ProjectId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            PaymentRefundQueryResult,
+            "
+            SELECT r.id, r.order_id, o.order_no, r.user_id, r.project_id,
+                   r.seller_id, r.amount, r.reason, r.status, r.source,
+                   r.thread_id, r.reviewer_id, r.review_note,
+                   r.external_refund_no, r.clawback_amount, r.created_at,
+                   r.reviewed_at, r.completed_at
+            FROM payment_refunds r
+            INNER JOIN payment_orders o ON o.id = r.order_id
+            WHERE r.project_id = $1
+            ORDER BY r.created_at DESC
+            ",
+            project_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 获取所有待审核的退款申请，按申请时间排序
+    pub async fn get_pending<'a, E>(
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            PaymentRefundQueryResult,
+            "
+            SELECT r.id, r.order_id, o.order_no, r.user_id, r.project_id,
+                   r.seller_id, r.amount, r.reason, r.status, r.source,
+                   r.thread_id, r.reviewer_id, r.review_note,
+                   r.external_refund_no, r.clawback_amount, r.created_at,
+                   r.reviewed_at, r.completed_at
+            FROM payment_refunds r
+            INNER JOIN payment_orders o ON o.id = r.order_id
+            WHERE r.status = 'pending'
+            ORDER BY r.created_at ASC
+            ",
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 锁定待审核的申请直到事务结束，返回申请是否仍待审核
+    pub async fn lock_pending(
+        id: PaymentRefundId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id FROM payment_refunds
+            WHERE id = $1 AND status = 'pending'
+            FOR UPDATE
+            ",
+            id.0,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.is_some())
+    }
+
+    /// 将待审核的申请标记为处理中（已通过审核，等待支付平台退款）
+    pub async fn mark_processing(
+        id: PaymentRefundId,
+        reviewer_id: UserId,
+        review_note: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_refunds
+            SET status = 'processing', reviewer_id = $2, review_note = $3,
+                reviewed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            ",
+            id.0,
+            reviewer_id.0,
+            review_note,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 支付平台退款失败时，将处理中的申请退回待审核
+    pub async fn revert_processing(
+        id: PaymentRefundId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_refunds
+            SET status = 'pending', reviewer_id = NULL, review_note = NULL,
+                reviewed_at = NULL
+            WHERE id = $1 AND status = 'processing'
+            ",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 结束待审核的申请（拒绝或买家撤回）
+    pub async fn close(
+        id: PaymentRefundId,
+        status: RefundStatus,
+        reviewer_id: Option<UserId>,
+        review_note: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_refunds
+            SET status = $2, reviewer_id = $3, review_note = $4,
+                reviewed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            ",
+            id.0,
+            status.as_str(),
+            reviewer_id.map(|x| x.0),
+            review_note,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 将待审核或处理中的申请标记为已退款
+    ///
+    /// `clawback_amount` 大于 0 时（平台垫付退款），同时在创作者收益中按订单明细
+    /// 各项目的销售收入比例记负数扣回
+    pub async fn complete(
+        &self,
+        reviewer_id: Option<UserId>,
+        review_note: Option<&str>,
+        external_refund_no: Option<&str>,
+        items: &[PaymentOrderItem],
+        clawback_amount: Decimal,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payment_refunds
+            SET status = 'refunded',
+                reviewer_id = COALESCE($2, reviewer_id),
+                review_note = COALESCE($3, review_note),
+                external_refund_no = $4, clawback_amount = $5,
+                reviewed_at = COALESCE(reviewed_at, NOW()),
+                completed_at = NOW()
+            WHERE id = $1 AND status IN ('pending', 'processing')
+            ",
+            self.id.0,
+            reviewer_id.map(|x| x.0),
+            review_note,
+            external_refund_no,
+            clawback_amount,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if clawback_amount > Decimal::ZERO {
+            let mod_ids: Vec<i64> =
+                items.iter().map(|x| x.project_id.0).collect();
+            let amounts: Vec<Decimal> = split_clawback(clawback_amount, items)
+                .into_iter()
+                .map(|x| -x)
+                .collect();
+
+            sqlx::query!(
+                "
+                INSERT INTO payouts_values (user_id, mod_id, amount, created, date_available)
+                SELECT $1, mod_id, amount, NOW(), NOW()
+                FROM UNNEST($2::bigint[], $3::numeric[]) AS t(mod_id, amount)
+                ",
+                self.seller_id.0,
+                &mod_ids[..],
+                &amounts[..],
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,310 @@
+use super::DatabaseError;
+use super::ids::*;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// 项目捆绑包
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectBundle {
+    pub id: ProjectBundleId,
+    /// 用户捆绑包的所有者，与 organization_id 二选一
+    pub user_id: Option<UserId>,
+    pub organization_id: Option<OrganizationId>,
+    pub name: String,
+    pub description: String,
+    pub price: Decimal,
+    pub active: bool,
+    pub projects: Vec<ProjectId>,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+struct ProjectBundleQueryResult {
+    id: i64,
+    user_id: Option<i64>,
+    organization_id: Option<i64>,
+    name: String,
+    description: String,
+    price: Decimal,
+    active: bool,
+    projects: Vec<i64>,
+    created_by: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<ProjectBundleQueryResult> for ProjectBundle {
+    fn from(row: ProjectBundleQueryResult) -> Self {
+        Self {
+            id: ProjectBundleId(row.id),
+            user_id: row.user_id.map(UserId),
+            organization_id: row.organization_id.map(OrganizationId),
+            name: row.name,
+            description: row.description,
+            price: row.price,
+            active: row.active,
+            projects: row.projects.into_iter().map(ProjectId).collect(),
+            created_by: UserId(row.created_by),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// 按权重比例拆分金额，结果保留两位小数，舍入误差计入最后一项，保证总和等于 `total`
+///
+/// 权重总和为 0 时平均拆分。
+pub fn split_amount(total: Decimal, weights: &[Decimal]) -> Vec<Decimal> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+
+    let weight_sum: Decimal = weights.iter().sum();
+    let mut result = Vec::with_capacity(weights.len());
+    let mut allocated = Decimal::ZERO;
+
+    for (index, weight) in weights.iter().enumerate() {
+        let amount = if index == weights.len() - 1 {
+            total - allocated
+        } else if weight_sum.is_zero() {
+            (total / Decimal::from(weights.len())).round_dp(2)
+        } else {
+            (total * weight / weight_sum).round_dp(2)
+        };
+        allocated += amount;
+        result.push(amount);
+    }
+
+    result
+}
+
+impl ProjectBundle {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO project_bundles (
+                id, user_id, organization_id, name, description, price,
+                active, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ",
+            self.id.0,
+            self.user_id.map(|x| x.0),
+            self.organization_id.map(|x| x.0),
+            &self.name,
+            &self.description,
+            self.price,
+            self.active,
+            self.created_by.0,
+            self.created_at,
+            self.updated_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Self::set_projects(self.id, &self.projects, transaction).await?;
+
+        Ok(())
+    }
+
+    /// 更新捆绑包的可编辑字段（所有者不可修改）
+    pub async fn update(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE project_bundles
+            SET name = $2, description = $3, price = $4, active = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            ",
+            self.id.0,
+            &self.name,
+            &self.description,
+            self.price,
+            self.active,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Self::set_projects(self.id, &self.projects, transaction).await?;
+
+        Ok(())
+    }
+
+    async fn set_projects(
+        id: ProjectBundleId,
+        projects: &[ProjectId],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM project_bundle_items WHERE bundle_id = $1",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO project_bundle_items (bundle_id, project_id)
+            SELECT $1, * FROM UNNEST($2::bigint[])
+            ON CONFLICT DO NOTHING
+            ",
+            id.0,
+            &projects.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove(
+        id: ProjectBundleId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result =
+            sqlx::query!("DELETE FROM project_bundles WHERE id = $1", id.0)
+                .execute(&mut **transaction)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_id<'a, E>(
+        id: ProjectBundleId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            ProjectBundleQueryResult,
+            r#"
+            SELECT b.id, b.user_id, b.organization_id, b.name, b.description,
+                   b.price, b.active, b.created_by, b.created_at, b.updated_at,
+                   ARRAY(SELECT project_id FROM project_bundle_items WHERE bundle_id = b.id ORDER BY project_id) AS "projects!"
+            FROM project_bundles b
+            WHERE b.id = $1
+            "#,
+            id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 获取用户拥有的捆绑包
+    pub async fn get_user<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            ProjectBundleQueryResult,
+            r#"
+            SELECT b.id, b.user_id, b.organization_id, b.name, b.description,
+                   b.price, b.active, b.created_by, b.created_at, b.updated_at,
+                   ARRAY(SELECT project_id FROM project_bundle_items WHERE bundle_id = b.id ORDER BY project_id) AS "projects!"
+            FROM project_bundles b
+            WHERE b.user_id = $1
+            ORDER BY b.created_at DESC
+            "#,
+            user_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 获取组织拥有的捆绑包
+    pub async fn get_organization<'a, E>(
+        organization_id: OrganizationId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            ProjectBundleQueryResult,
+            r#"
+            SELECT b.id, b.user_id, b.organization_id, b.name, b.description,
+                   b.price, b.active, b.created_by, b.created_at, b.updated_at,
+                   ARRAY(SELECT project_id FROM project_bundle_items WHERE bundle_id = b.id ORDER BY project_id) AS "projects!"
+            FROM project_bundles b
+            WHERE b.organization_id = $1
+            ORDER BY b.created_at DESC
+            "#,
+            organization_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 获取包含指定项目的上架中的捆绑包
+    pub async fn get_active_for_project<'a, E>(
+        project_id: ProjectId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            ProjectBundleQueryResult,
+            r#"
+            SELECT b.id, b.user_id, b.organization_id, b.name, b.description,
+                   b.price, b.active, b.created_by, b.created_at, b.updated_at,
+                   ARRAY(SELECT project_id FROM project_bundle_items WHERE bundle_id = b.id ORDER BY project_id) AS "projects!"
+            FROM project_bundles b
+            INNER JOIN project_bundle_items i ON i.bundle_id = b.id
+            WHERE i.project_id = $1 AND b.active = TRUE
+            ORDER BY b.price ASC
+            "#,
+            project_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn split_amount_keeps_total() {
+        let parts = split_amount(dec("10.00"), &[dec("3"), dec("3"), dec("3")]);
+        assert_eq!(parts, vec![dec("3.33"), dec("3.33"), dec("3.34")]);
+        assert_eq!(parts.iter().sum::<Decimal>(), dec("10.00"));
+    }
+
+    #[test]
+    fn split_amount_by_weight() {
+        let parts = split_amount(dec("30.00"), &[dec("10.00"), dec("20.00")]);
+        assert_eq!(parts, vec![dec("10.00"), dec("20.00")]);
+
+        let parts = split_amount(dec("9.99"), &[dec("0"), dec("0")]);
+        assert_eq!(parts.iter().sum::<Decimal>(), dec("9.99"));
+
+        assert!(split_amount(dec("1"), &[]).is_empty());
+    }
+}
@@ -432,7 +432,17 @@ impl Project {
             .execute(&mut **transaction)
             .await?;
 
-            // 删除与项目相关的wiki和wiki_cache
+            // 删除与项目相关的wiki修订、wiki和wiki_cache
+            sqlx::query!(
+                "
+                DELETE FROM wiki_revisions
+                WHERE mod_id = $1
+                ",
+                id as ProjectId,
+            )
+            .execute(&mut **transaction)
+            .await?;
+
             sqlx::query!(
                 "
                 DELETE FROM wiki_cache
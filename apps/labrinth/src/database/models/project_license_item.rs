@@ -0,0 +1,320 @@
+use super::DatabaseError;
+use super::ids::*;
+use super::user_purchase_item::PurchaseStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 付费项目许可证
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectLicense {
+    pub id: ProjectLicenseId,
+    pub purchase_id: UserPurchaseId,
+    pub user_id: UserId,
+    pub project_id: ProjectId,
+    pub max_activations: i32,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoke_reason: Option<String>,
+    /// 以下字段查询时从购买记录关联
+    pub expires_at: Option<DateTime<Utc>>,
+    pub purchase_status: PurchaseStatus,
+}
+
+/// 许可证在服务器实例上的激活记录
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectLicenseActivation {
+    pub license_id: ProjectLicenseId,
+    pub instance_id: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub last_ip: Option<String>,
+}
+
+struct ProjectLicenseQueryResult {
+    id: i64,
+    purchase_id: i64,
+    user_id: i64,
+    project_id: i64,
+    max_activations: i32,
+    created_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+    revoke_reason: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    purchase_status: String,
+}
+
+impl From<ProjectLicenseQueryResult> for ProjectLicense {
+    fn from(row: ProjectLicenseQueryResult) -> Self {
+        Self {
+            id: ProjectLicenseId(row.id),
+            purchase_id: UserPurchaseId(row.purchase_id),
+            user_id: UserId(row.user_id),
+            project_id: ProjectId(row.project_id),
+            max_activations: row.max_activations,
+            created_at: row.created_at,
+            revoked_at: row.revoked_at,
+            revoke_reason: row.revoke_reason,
+            expires_at: row.expires_at,
+            purchase_status: PurchaseStatus::from_string(&row.purchase_status),
+        }
+    }
+}
+
+impl ProjectLicense {
+    /// 许可证当前是否有效：未吊销、购买记录有效且未过期
+    pub fn is_valid(&self) -> bool {
+        self.revoked_at.is_none()
+            && self.purchase_status == PurchaseStatus::Active
+            && self.expires_at.is_none_or(|x| x > Utc::now())
+    }
+
+    /// 获取购买记录对应的许可证，不存在时创建
+    pub async fn get_or_create(
+        purchase_id: UserPurchaseId,
+        max_activations: i32,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Self, DatabaseError> {
+        if let Some(license) =
+            Self::get_by_purchase(purchase_id, &mut **transaction).await?
+        {
+            return Ok(license);
+        }
+
+        let id = generate_project_license_id(transaction).await?;
+        sqlx::query!(
+            "
+            INSERT INTO project_licenses (id, purchase_id, user_id, project_id, max_activations)
+            SELECT $1, p.id, p.user_id, p.project_id, $3
+            FROM user_purchases p
+            WHERE p.id = $2
+            ON CONFLICT (purchase_id) DO NOTHING
+            ",
+            id.0,
+            purchase_id.0,
+            max_activations,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Self::get_by_purchase(purchase_id, &mut **transaction)
+            .await?
+            .ok_or_else(|| {
+                DatabaseError::SchemaError("购买记录不存在".to_string())
+            })
+    }
+
+    pub async fn get_id<'a, E>(
+        id: ProjectLicenseId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            ProjectLicenseQueryResult,
+            "
+            SELECT l.id, l.purchase_id, l.user_id, l.project_id,
+                   l.max_activations, l.created_at, l.revoked_at,
+                   l.revoke_reason, p.expires_at, p.status purchase_status
+            FROM project_licenses l
+            INNER JOIN user_purchases p ON p.id = l.purchase_id
+            WHERE l.id = $1
+            ",
+            id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    pub async fn get_by_purchase<'a, E>(
+        purchase_id: UserPurchaseId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query_as!(
+            ProjectLicenseQueryResult,
+            "
+            SELECT l.id, l.purchase_id, l.user_id, l.project_id,
+                   l.max_activations, l.created_at, l.revoked_at,
+                   l.revoke_reason, p.expires_at, p.status purchase_status
+            FROM project_licenses l
+            INNER JOIN user_purchases p ON p.id = l.purchase_id
+            WHERE l.purchase_id = $1
+            ",
+            purchase_id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(Into::into))
+    }
+
+    /// 获取用户的全部许可证
+    pub async fn get_user<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query_as!(
+            ProjectLicenseQueryResult,
+            "
+            SELECT l.id, l.purchase_id, l.user_id, l.project_id,
+                   l.max_activations, l.created_at, l.revoked_at,
+                   l.revoke_reason, p.expires_at, p.status purchase_status
+            FROM project_licenses l
+            INNER JOIN user_purchases p ON p.id = l.purchase_id
+            WHERE l.user_id = $1
+            ORDER BY l.created_at DESC
+            ",
+            user_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// 锁定许可证直到事务结束，用于串行化同一许可证的激活
+    pub async fn lock(
+        id: ProjectLicenseId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id FROM project_licenses
+            WHERE id = $1
+            FOR UPDATE
+            ",
+            id.0,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.is_some())
+    }
+
+    /// 吊销许可证，吊销后在线验证将失败
+    pub async fn revoke(
+        id: ProjectLicenseId,
+        reason: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE project_licenses
+            SET revoked_at = NOW(), revoke_reason = $2
+            WHERE id = $1 AND revoked_at IS NULL
+            ",
+            id.0,
+            reason,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_activations<'a, E>(
+        id: ProjectLicenseId,
+        executor: E,
+    ) -> Result<Vec<ProjectLicenseActivation>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT license_id, instance_id, first_seen_at, last_seen_at, last_ip
+            FROM project_license_activations
+            WHERE license_id = $1
+            ORDER BY first_seen_at ASC
+            ",
+            id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| ProjectLicenseActivation {
+                license_id: ProjectLicenseId(row.license_id),
+                instance_id: row.instance_id,
+                first_seen_at: row.first_seen_at,
+                last_seen_at: row.last_seen_at,
+                last_ip: row.last_ip,
+            })
+            .collect())
+    }
+
+    /// 登记服务器实例激活
+    ///
+    /// 实例已激活时只更新最近验证时间；新实例在达到激活上限时返回 false。
+    /// 调用前需先通过 [`Self::lock`] 锁定许可证。
+    pub async fn activate(
+        &self,
+        instance_id: &str,
+        ip: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let updated = sqlx::query!(
+            "
+            UPDATE project_license_activations
+            SET last_seen_at = NOW(), last_ip = $3
+            WHERE license_id = $1 AND instance_id = $2
+            ",
+            self.id.0,
+            instance_id,
+            ip,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        if updated.rows_affected() > 0 {
+            return Ok(true);
+        }
+
+        let inserted = sqlx::query!(
+            "
+            INSERT INTO project_license_activations (license_id, instance_id, last_ip)
+            SELECT $1, $2, $3
+            WHERE (
+                SELECT COUNT(*) FROM project_license_activations
+                WHERE license_id = $1
+            ) < $4
+            ",
+            self.id.0,
+            instance_id,
+            ip,
+            self.max_activations as i64,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    /// 解除服务器实例的激活，释放名额
+    pub async fn deactivate(
+        id: ProjectLicenseId,
+        instance_id: &str,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM project_license_activations
+            WHERE license_id = $1 AND instance_id = $2
+            ",
+            id.0,
+            instance_id,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -10,6 +10,10 @@ pub struct ProjectPricing {
     pub project_id: ProjectId,
     pub price: Decimal,             // 价格（单位：元）
     pub validity_days: Option<i32>, // 有效期天数，None 表示永久
+    /// 支付后可申请退款的天数，0 表示不接受退款申请
+    pub refund_window_days: i32,
+    /// 展示给买家的退款说明
+    pub refund_policy: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -34,7 +38,7 @@ impl ProjectPricing {
                 price = $2,
                 validity_days = $3,
                 updated_at = $4
-            RETURNING created_at, updated_at
+            RETURNING refund_window_days, refund_policy, created_at, updated_at
             ",
             project_id.0,
             price,
@@ -48,6 +52,8 @@ impl ProjectPricing {
             project_id,
             price,
             validity_days,
+            refund_window_days: result.refund_window_days,
+            refund_policy: result.refund_policy,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
@@ -63,7 +69,8 @@ impl ProjectPricing {
     {
         let result = sqlx::query!(
             "
-            SELECT project_id, price, validity_days, created_at, updated_at
+            SELECT project_id, price, validity_days, refund_window_days,
+                   refund_policy, created_at, updated_at
             FROM project_pricing
             WHERE project_id = $1
             ",
@@ -76,11 +83,70 @@ impl ProjectPricing {
             project_id: ProjectId(row.project_id),
             price: row.price,
             validity_days: row.validity_days,
+            refund_window_days: row.refund_window_days,
+            refund_policy: row.refund_policy,
             created_at: row.created_at,
             updated_at: row.updated_at,
         }))
     }
 
+    /// 批量获取项目定价，未设置定价的项目不在结果中
+    pub async fn get_many<'a, E>(
+        project_ids: &[ProjectId],
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT project_id, price, validity_days, refund_window_days,
+                   refund_policy, created_at, updated_at
+            FROM project_pricing
+            WHERE project_id = ANY($1)
+            ",
+            &project_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| Self {
+                project_id: ProjectId(row.project_id),
+                price: row.price,
+                validity_days: row.validity_days,
+                refund_window_days: row.refund_window_days,
+                refund_policy: row.refund_policy,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+            .collect())
+    }
+
+    /// 更新项目的退款政策
+    pub async fn set_refund_policy(
+        project_id: ProjectId,
+        refund_window_days: i32,
+        refund_policy: Option<&str>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE project_pricing
+            SET refund_window_days = $2, refund_policy = $3, updated_at = NOW()
+            WHERE project_id = $1
+            ",
+            project_id.0,
+            refund_window_days,
+            refund_policy,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// 删除项目定价
     pub async fn delete(
         project_id: ProjectId,
@@ -96,3 +162,96 @@ impl ProjectPricing {
         Ok(result.rows_affected() > 0)
     }
 }
+
+/// 项目限时促销
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectSale {
+    pub id: ProjectSaleId,
+    pub project_id: ProjectId,
+    pub sale_price: Decimal,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProjectSale {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO project_sales (id, project_id, sale_price, starts_at, ends_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+            self.id.0,
+            self.project_id.0,
+            self.sale_price,
+            self.starts_at,
+            self.ends_at,
+            self.created_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 获取项目尚未结束的促销，按开始时间排序
+    pub async fn get_project<'a, E>(
+        project_id: ProjectId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT id, project_id, sale_price, starts_at, ends_at, created_at
+            FROM project_sales
+            WHERE project_id = $1 AND ends_at > NOW()
+            ORDER BY starts_at ASC
+            ",
+            project_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| Self {
+                id: ProjectSaleId(row.id),
+                project_id: ProjectId(row.project_id),
+                sale_price: row.sale_price,
+                starts_at: row.starts_at,
+                ends_at: row.ends_at,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// 删除项目的促销
+    pub async fn remove(
+        id: ProjectSaleId,
+        project_id: ProjectId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "DELETE FROM project_sales WHERE id = $1 AND project_id = $2",
+            id.0,
+            project_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 从促销列表中找出当前生效的促销，时间重叠时取最低价
+    pub fn active(sales: &[Self], now: DateTime<Utc>) -> Option<&Self> {
+        sales
+            .iter()
+            .filter(|x| x.starts_at <= now && now < x.ends_at)
+            .min_by_key(|x| x.sale_price)
+    }
+}
@@ -0,0 +1,366 @@
+use super::DatabaseError;
+use super::ids::*;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// 兑换码字符集，去掉了容易混淆的 0/O、1/I/L
+const CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+/// 兑换码分组数与每组长度，格式如 ABCD-EFGH-JKMN-PQRS
+const CODE_GROUPS: usize = 4;
+const CODE_GROUP_LEN: usize = 4;
+
+/// 兑换码批次
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PurchaseCodeBatch {
+    pub id: PurchaseCodeBatchId,
+    pub project_id: ProjectId,
+    pub name: String,
+    pub code_count: i32,
+    /// 已兑换的数量，插入时忽略
+    pub redeemed_count: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 兑换码
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PurchaseCode {
+    pub code: String,
+    pub batch_id: PurchaseCodeBatchId,
+    pub redeemed_by: Option<UserId>,
+    pub redeemed_at: Option<DateTime<Utc>>,
+}
+
+/// 兑换时锁定的兑换码及其批次信息
+#[derive(Clone, Debug)]
+pub struct RedeemableCode {
+    pub code: String,
+    pub batch_id: PurchaseCodeBatchId,
+    pub project_id: ProjectId,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+    pub redeemed: bool,
+}
+
+impl PurchaseCode {
+    /// 生成随机兑换码
+    pub fn generate() -> String {
+        let mut rng = rand::thread_rng();
+        (0..CODE_GROUPS)
+            .map(|_| {
+                (0..CODE_GROUP_LEN)
+                    .map(|_| {
+                        CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())]
+                            as char
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// 规范化用户输入的兑换码：忽略大小写、空白与分隔符
+    ///
+    /// 无法组成合法兑换码时返回 None
+    pub fn normalize(input: &str) -> Option<String> {
+        let chars: Vec<char> = input
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        if chars.len() != CODE_GROUPS * CODE_GROUP_LEN
+            || !chars
+                .iter()
+                .all(|c| c.is_ascii() && CODE_ALPHABET.contains(&(*c as u8)))
+        {
+            return None;
+        }
+
+        Some(
+            chars
+                .chunks(CODE_GROUP_LEN)
+                .map(|x| x.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("-"),
+        )
+    }
+
+    /// 按兑换码查找并锁定，用于兑换
+    pub async fn get_for_redeem(
+        code: &str,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<RedeemableCode>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT c.code, c.batch_id, c.redeemed_at, b.project_id,
+                   b.expires_at, b.revoked
+            FROM purchase_codes c
+            INNER JOIN purchase_code_batches b ON b.id = c.batch_id
+            WHERE c.code = $1
+            FOR UPDATE OF c
+            ",
+            code,
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(result.map(|row| RedeemableCode {
+            code: row.code,
+            batch_id: PurchaseCodeBatchId(row.batch_id),
+            project_id: ProjectId(row.project_id),
+            expires_at: row.expires_at,
+            revoked: row.revoked,
+            redeemed: row.redeemed_at.is_some(),
+        }))
+    }
+
+    /// 标记兑换码已被用户兑换，兑换码已被使用时返回 false
+    pub async fn redeem(
+        code: &str,
+        user_id: UserId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE purchase_codes
+            SET redeemed_by = $2, redeemed_at = NOW()
+            WHERE code = $1 AND redeemed_at IS NULL
+            ",
+            code,
+            user_id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+impl PurchaseCodeBatch {
+    /// 插入批次及其兑换码
+    pub async fn insert(
+        &self,
+        codes: &[String],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO purchase_code_batches (
+                id, project_id, name, code_count, expires_at, revoked,
+                created_by, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ",
+            self.id.0,
+            self.project_id.0,
+            &self.name,
+            self.code_count,
+            self.expires_at,
+            self.revoked,
+            self.created_by.0,
+            self.created_at,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO purchase_codes (code, batch_id)
+            SELECT *, $2 FROM UNNEST($1::varchar[])
+            ",
+            codes,
+            self.id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 作废批次，未兑换的兑换码不能再使用，已兑换的购买记录不受影响
+    pub async fn revoke(
+        id: PurchaseCodeBatchId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE purchase_code_batches
+            SET revoked = TRUE
+            WHERE id = $1 AND NOT revoked
+            ",
+            id.0,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_id<'a, E>(
+        id: PurchaseCodeBatchId,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT b.id, b.project_id, b.name, b.code_count, b.expires_at,
+                   b.revoked, b.created_by, b.created_at,
+                   (SELECT COUNT(*) FROM purchase_codes c
+                    WHERE c.batch_id = b.id AND c.redeemed_at IS NOT NULL) AS "redeemed_count!"
+            FROM purchase_code_batches b
+            WHERE b.id = $1
+            "#,
+            id.0,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(|row| Self {
+            id: PurchaseCodeBatchId(row.id),
+            project_id: ProjectId(row.project_id),
+            name: row.name,
+            code_count: row.code_count,
+            redeemed_count: row.redeemed_count,
+            expires_at: row.expires_at,
+            revoked: row.revoked,
+            created_by: UserId(row.created_by),
+            created_at: row.created_at,
+        }))
+    }
+
+    /// 获取项目的全部批次
+    pub async fn get_project<'a, E>(
+        project_id: ProjectId,
+        executor: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            r#"
+            SELECT b.id, b.project_id, b.name, b.code_count, b.expires_at,
+                   b.revoked, b.created_by, b.created_at,
+                   (SELECT COUNT(*) FROM purchase_codes c
+                    WHERE c.batch_id = b.id AND c.redeemed_at IS NOT NULL) AS "redeemed_count!"
+            FROM purchase_code_batches b
+            WHERE b.project_id = $1
+            ORDER BY b.created_at DESC
+            "#,
+            project_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| Self {
+                id: PurchaseCodeBatchId(row.id),
+                project_id: ProjectId(row.project_id),
+                name: row.name,
+                code_count: row.code_count,
+                redeemed_count: row.redeemed_count,
+                expires_at: row.expires_at,
+                revoked: row.revoked,
+                created_by: UserId(row.created_by),
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// 统计项目仍可兑换的兑换码数量（未兑换、未作废且未过期）
+    pub async fn get_outstanding_count<'a, E>(
+        project_id: ProjectId,
+        executor: E,
+    ) -> Result<i64, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM purchase_codes c
+            INNER JOIN purchase_code_batches b ON b.id = c.batch_id
+            WHERE b.project_id = $1 AND NOT b.revoked
+                  AND c.redeemed_at IS NULL
+                  AND (b.expires_at IS NULL OR b.expires_at > NOW())
+            "#,
+            project_id.0,
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// 获取批次的全部兑换码，已兑换的排在前面
+    pub async fn get_codes<'a, E>(
+        id: PurchaseCodeBatchId,
+        executor: E,
+    ) -> Result<Vec<PurchaseCode>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT code, batch_id, redeemed_by, redeemed_at
+            FROM purchase_codes
+            WHERE batch_id = $1
+            ORDER BY redeemed_at DESC NULLS LAST, code
+            ",
+            id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| PurchaseCode {
+                code: row.code,
+                batch_id: PurchaseCodeBatchId(row.batch_id),
+                redeemed_by: row.redeemed_by.map(UserId),
+                redeemed_at: row.redeemed_at,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_codes_are_normalized() {
+        for _ in 0..100 {
+            let code = PurchaseCode::generate();
+            assert_eq!(code.len(), 19);
+            assert_eq!(PurchaseCode::normalize(&code).as_deref(), Some(&*code));
+        }
+    }
+
+    #[test]
+    fn normalize_ignores_case_and_separators() {
+        assert_eq!(
+            PurchaseCode::normalize(" abcd efgh-jkmn pqrs ").as_deref(),
+            Some("ABCD-EFGH-JKMN-PQRS")
+        );
+        assert_eq!(
+            PurchaseCode::normalize("ABCDEFGHJKMNPQRS").as_deref(),
+            Some("ABCD-EFGH-JKMN-PQRS")
+        );
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_codes() {
+        assert_eq!(PurchaseCode::normalize(""), None);
+        assert_eq!(PurchaseCode::normalize("ABCD-EFGH-JKMN"), None);
+        // 字符集不包含 0、O、1、I、L
+        assert_eq!(PurchaseCode::normalize("ABCD-EFGH-JKMN-PQR0"), None);
+        assert_eq!(PurchaseCode::normalize("ABCD-EFGH-JKMN-PQRÖ"), None);
+    }
+}
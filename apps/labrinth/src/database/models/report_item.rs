@@ -7,6 +7,12 @@ pub struct Report {
     pub project_id: Option<ProjectId>,
     pub version_id: Option<VersionId>,
     pub user_id: Option<UserId>,
+    pub post_id: Option<PostId>,
+    pub issue_comment_id: Option<IssuesCommentsId>,
+    pub wiki_id: Option<WikiId>,
+    pub thread_message_id: Option<ThreadMessageId>,
+    pub content_author_id: Option<UserId>,
+    pub content_snapshot: Option<serde_json::Value>,
     pub body: String,
     pub reporter: UserId,
     pub created: DateTime<Utc>,
@@ -19,6 +25,12 @@ pub struct QueryReport {
     pub project_id: Option<ProjectId>,
     pub version_id: Option<VersionId>,
     pub user_id: Option<UserId>,
+    pub post_id: Option<PostId>,
+    pub issue_comment_id: Option<IssuesCommentsId>,
+    pub wiki_id: Option<WikiId>,
+    pub thread_message_id: Option<ThreadMessageId>,
+    pub content_author_id: Option<UserId>,
+    pub content_snapshot: Option<serde_json::Value>,
     pub body: String,
     pub reporter: UserId,
     pub created: DateTime<Utc>,
@@ -26,6 +38,18 @@ pub struct QueryReport {
     pub thread_id: ThreadId,
 }
 
+/// 同一举报对象的未关闭举报聚合
+pub struct ReportGroup {
+    pub item_type: String,
+    pub item_id: i64,
+    pub report_count: i64,
+    pub reporter_count: i64,
+    pub report_types: Vec<String>,
+    pub report_ids: Vec<ReportId>,
+    pub first_reported: DateTime<Utc>,
+    pub last_reported: DateTime<Utc>,
+}
+
 impl Report {
     pub async fn insert(
         &self,
@@ -35,11 +59,13 @@ impl Report {
             "
             INSERT INTO reports (
                 id, report_type_id, mod_id, version_id, user_id,
-                body, reporter
+                post_id, issue_comment_id, wiki_id, thread_message_id,
+                content_author_id, content_snapshot, body, reporter
             )
             VALUES (
                 $1, $2, $3, $4, $5,
-                $6, $7
+                $6, $7, $8, $9,
+                $10, $11, $12, $13
             )
             ",
             self.id as ReportId,
@@ -47,6 +73,12 @@ impl Report {
             self.project_id.map(|x| x.0 as i64),
             self.version_id.map(|x| x.0 as i64),
             self.user_id.map(|x| x.0 as i64),
+            self.post_id.map(|x| x.0),
+            self.issue_comment_id.map(|x| x.0),
+            self.wiki_id.map(|x| x.0),
+            self.thread_message_id.map(|x| x.0),
+            self.content_author_id.map(|x| x.0),
+            self.content_snapshot,
             self.body,
             self.reporter as UserId
         )
@@ -81,7 +113,8 @@ impl Report {
             report_ids.iter().map(|x| x.0).collect();
         let reports = sqlx::query!(
             "
-            SELECT r.id, rt.name, r.mod_id, r.version_id, r.user_id, r.body, r.reporter, r.created, t.id thread_id, r.closed
+            SELECT r.id, rt.name, r.mod_id, r.version_id, r.user_id, r.post_id, r.issue_comment_id, r.wiki_id, r.thread_message_id,
+                r.content_author_id, r.content_snapshot, r.body, r.reporter, r.created, t.id thread_id, r.closed
             FROM reports r
             INNER JOIN report_types rt ON rt.id = r.report_type_id
             INNER JOIN threads t ON t.report_id = r.id
@@ -97,6 +130,12 @@ impl Report {
             project_id: x.mod_id.map(ProjectId),
             version_id: x.version_id.map(VersionId),
             user_id: x.user_id.map(UserId),
+            post_id: x.post_id.map(PostId),
+            issue_comment_id: x.issue_comment_id.map(IssuesCommentsId),
+            wiki_id: x.wiki_id.map(WikiId),
+            thread_message_id: x.thread_message_id.map(ThreadMessageId),
+            content_author_id: x.content_author_id.map(UserId),
+            content_snapshot: x.content_snapshot,
             body: x.body,
             reporter: UserId(x.reporter),
             created: x.created,
@@ -109,6 +148,125 @@ impl Report {
         Ok(reports)
     }
 
+    /// 举报者是否已对同一对象提交过未关闭的举报
+    pub async fn has_open_report<'a, E>(
+        reporter: UserId,
+        item_type: &str,
+        item_id: i64,
+        exec: E,
+    ) -> Result<bool, sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT EXISTS(
+                SELECT 1 FROM reports
+                WHERE reporter = $1 AND item_type = $2 AND item_id = $3 AND closed = FALSE
+            )
+            ",
+            reporter as UserId,
+            item_type,
+            item_id,
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(result.exists.unwrap_or(false))
+    }
+
+    /// 按举报对象聚合未关闭的举报，举报数多、最近被举报的排在前面
+    pub async fn get_open_groups<'a, E>(
+        limit: i64,
+        offset: i64,
+        exec: E,
+    ) -> Result<Vec<ReportGroup>, sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let groups = sqlx::query!(
+            r#"
+            SELECT
+                r.item_type AS "item_type!",
+                r.item_id AS "item_id!",
+                COUNT(*) AS "report_count!",
+                COUNT(DISTINCT r.reporter) AS "reporter_count!",
+                ARRAY_AGG(DISTINCT rt.name) AS "report_types!",
+                ARRAY_AGG(r.id ORDER BY r.created) AS "report_ids!",
+                MIN(r.created) AS "first_reported!",
+                MAX(r.created) AS "last_reported!"
+            FROM reports r
+            INNER JOIN report_types rt ON rt.id = r.report_type_id
+            WHERE r.closed = FALSE AND r.item_id IS NOT NULL
+            GROUP BY r.item_type, r.item_id
+            ORDER BY COUNT(*) DESC, MAX(r.created) DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            limit,
+            offset,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(groups
+            .into_iter()
+            .map(|x| ReportGroup {
+                item_type: x.item_type,
+                item_id: x.item_id,
+                report_count: x.report_count,
+                reporter_count: x.reporter_count,
+                report_types: x.report_types,
+                report_ids: x.report_ids.into_iter().map(ReportId).collect(),
+                first_reported: x.first_reported,
+                last_reported: x.last_reported,
+            })
+            .collect())
+    }
+
+    pub async fn count_open_groups<'a, E>(exec: E) -> Result<i64, sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(DISTINCT (item_type, item_id)) AS "count!"
+            FROM reports
+            WHERE closed = FALSE AND item_id IS NOT NULL
+            "#
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// 关闭同一对象的全部未关闭举报，返回被关闭举报的讨论串
+    pub async fn close_for_item(
+        item_type: &str,
+        item_id: i64,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<(ReportId, ThreadId)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "
+            UPDATE reports r
+            SET closed = TRUE
+            FROM threads t
+            WHERE t.report_id = r.id
+              AND r.item_type = $1 AND r.item_id = $2 AND r.closed = FALSE
+            RETURNING r.id, t.id AS thread_id
+            ",
+            item_type,
+            item_id,
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|x| (ReportId(x.id), ThreadId(x.thread_id)))
+            .collect())
+    }
+
     pub async fn remove_full(
         id: ReportId,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -0,0 +1,123 @@
+use super::DatabaseError;
+use super::ids::*;
+use chrono::{DateTime, Utc};
+
+/// 等待更新搜索索引的项目
+///
+/// 项目或版本的创建、编辑、删除与状态变更都会在同一个事务中写入队列，
+/// 后台任务取出后只重建这些项目的文档；同一项目重复入队只保留一条。
+#[derive(Clone, Debug)]
+pub struct SearchIndexQueueEntry {
+    pub project_id: ProjectId,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl SearchIndexQueueEntry {
+    pub async fn enqueue<'a, E>(
+        project_ids: &[ProjectId],
+        exec: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        if project_ids.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            "
+            INSERT INTO search_index_queue (project_id)
+            SELECT * FROM UNNEST($1::bigint[])
+            ON CONFLICT (project_id)
+            DO UPDATE SET queued_at = CURRENT_TIMESTAMP
+            ",
+            &project_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 按版本所属的项目入队，需在删除版本之前调用
+    pub async fn enqueue_versions<'a, E>(
+        version_ids: &[VersionId],
+        exec: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        if version_ids.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            "
+            INSERT INTO search_index_queue (project_id)
+            SELECT DISTINCT mod_id FROM versions
+            WHERE id = ANY($1)
+            ON CONFLICT (project_id)
+            DO UPDATE SET queued_at = CURRENT_TIMESTAMP
+            ",
+            &version_ids.iter().map(|x| x.0).collect::<Vec<_>>(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 取出最早入队的一批项目，处理完成后调用 [`Self::remove`]
+    pub async fn get_batch<'a, E>(
+        limit: i64,
+        exec: E,
+    ) -> Result<Vec<SearchIndexQueueEntry>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let entries = sqlx::query!(
+            "
+            SELECT project_id, queued_at
+            FROM search_index_queue
+            ORDER BY queued_at
+            LIMIT $1
+            ",
+            limit,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|x| SearchIndexQueueEntry {
+            project_id: ProjectId(x.project_id),
+            queued_at: x.queued_at,
+        })
+        .collect();
+
+        Ok(entries)
+    }
+
+    /// 移除已处理的条目；处理期间再次入队的项目会保留到下一批
+    pub async fn remove<'a, E>(
+        entries: &[SearchIndexQueueEntry],
+        exec: E,
+    ) -> Result<(), DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            DELETE FROM search_index_queue q
+            USING UNNEST($1::bigint[], $2::timestamptz[])
+                AS done(project_id, queued_at)
+            WHERE q.project_id = done.project_id
+            AND q.queued_at <= done.queued_at
+            ",
+            &entries.iter().map(|x| x.project_id.0).collect::<Vec<_>>(),
+            &entries.iter().map(|x| x.queued_at).collect::<Vec<_>>(),
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use super::DatabaseError;
+use super::ids::*;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// 归档的创作者月度对账单，`data` 为生成时的对账单快照
+#[derive(Clone, Debug)]
+pub struct CreatorStatement {
+    pub user_id: UserId,
+    pub period: NaiveDate,
+    pub data: serde_json::Value,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl CreatorStatement {
+    /// 写入对账单；`overwrite` 为 false 时已归档的对账单保持不变
+    ///
+    /// 返回是否写入
+    pub async fn insert<'a, E>(
+        &self,
+        overwrite: bool,
+        executor: E,
+    ) -> Result<bool, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            INSERT INTO creator_statements (user_id, period, data, generated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, period) DO UPDATE
+            SET data = EXCLUDED.data, generated_at = EXCLUDED.generated_at
+            WHERE $5
+            ",
+            self.user_id.0,
+            self.period,
+            self.data,
+            self.generated_at,
+            overwrite,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get<'a, E>(
+        user_id: UserId,
+        period: NaiveDate,
+        executor: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let result = sqlx::query!(
+            "
+            SELECT user_id, period, data, generated_at
+            FROM creator_statements
+            WHERE user_id = $1 AND period = $2
+            ",
+            user_id.0,
+            period,
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(result.map(|row| Self {
+            user_id: UserId(row.user_id),
+            period: row.period,
+            data: row.data,
+            generated_at: row.generated_at,
+        }))
+    }
+
+    /// 用户已归档的对账单月份与生成时间，按月份倒序
+    pub async fn get_user_periods<'a, E>(
+        user_id: UserId,
+        executor: E,
+    ) -> Result<Vec<(NaiveDate, DateTime<Utc>)>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let results = sqlx::query!(
+            "
+            SELECT period, generated_at
+            FROM creator_statements
+            WHERE user_id = $1
+            ORDER BY period DESC
+            ",
+            user_id.0,
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|row| (row.period, row.generated_at))
+            .collect())
+    }
+}
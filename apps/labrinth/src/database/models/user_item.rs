@@ -831,6 +831,8 @@ impl User {
             )
             .execute(&mut **transaction)
             .await?;
+            super::LedgerEntry::transfer_user(id, deleted_user, transaction)
+                .await?;
 
             sqlx::query!(
                 "
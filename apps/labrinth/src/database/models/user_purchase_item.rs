@@ -272,6 +272,32 @@ impl UserPurchase {
         Ok(result.rows_affected() > 0)
     }
 
+    /// 订单退款后撤销对应的购买授权，返回被撤销授权的用户与项目
+    ///
+    /// 多项目订单会撤销订单中全部项目的授权，赠送订单撤销的是受赠用户的授权。
+    /// 只有当前授权来自该订单时才撤销；之后续费的订单不受旧订单退款影响
+    pub async fn refund(
+        order_no: &str,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<(UserId, ProjectId)>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            UPDATE user_purchases
+            SET status = 'refunded'
+            WHERE order_no = $1
+            RETURNING user_id, project_id
+            ",
+            order_no,
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|x| (UserId(x.user_id), ProjectId(x.project_id)))
+            .collect())
+    }
+
     // ==================== 带 Redis 缓存的方法 ====================
 
     /// 获取用户已购买的项目 ID 集合（带缓存）
@@ -5,7 +5,9 @@ use crate::database::models::loader_fields::{
     QueryLoaderField, QueryLoaderFieldEnumValue, QueryVersionField,
 };
 use crate::database::redis::RedisPool;
+use crate::models::malware::ScanFinding;
 use crate::models::projects::{FileType, VersionStatus};
+use crate::models::structure::StructureMetadata;
 use chrono::{DateTime, Utc};
 use dashmap::{DashMap, DashSet};
 use futures::TryStreamExt;
@@ -186,6 +188,9 @@ pub struct VersionFileBuilder {
     pub size: u32,
     pub file_type: Option<FileType>,
     pub is_private: bool, // 是否存储在私有桶（付费资源）
+    pub structure_metadata: Option<StructureMetadata>,
+    /// 可疑代码的扫描结果，未扫描的文件为 None
+    pub malware_findings: Option<Vec<ScanFinding>>,
 }
 
 impl VersionFileBuilder {
@@ -198,8 +203,8 @@ impl VersionFileBuilder {
 
         sqlx::query!(
             "
-            INSERT INTO files (id, version_id, url, filename, is_primary, size, file_type, is_private)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO files (id, version_id, url, filename, is_primary, size, file_type, is_private, structure_metadata, malware_findings)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             ",
             file_id as FileId,
             version_id as VersionId,
@@ -209,6 +214,12 @@ impl VersionFileBuilder {
             self.size as i32,
             self.file_type.map(|x| x.as_str()),
             self.is_private,
+            self.structure_metadata
+                .map(serde_json::to_value)
+                .transpose()?,
+            self.malware_findings
+                .map(serde_json::to_value)
+                .transpose()?,
         )
         .execute(&mut **transaction)
         .await?;
@@ -742,13 +753,14 @@ impl Version {
                     pub size: u32,
                     pub file_type: Option<FileType>,
                     pub is_private: bool,
+                    pub structure_metadata: Option<StructureMetadata>,
                 }
 
                 let file_ids = DashSet::new();
                 let reverse_file_map = DashMap::new();
                 let files : DashMap<VersionId, Vec<File>> = sqlx::query!(
                     "
-                    SELECT DISTINCT version_id, f.id, f.url, f.filename, f.is_primary, f.size, f.file_type, f.is_private
+                    SELECT DISTINCT version_id, f.id, f.url, f.filename, f.is_primary, f.size, f.file_type, f.is_private, f.structure_metadata
                     FROM files f
                     WHERE f.version_id = ANY($1)
                     ",
@@ -763,6 +775,8 @@ impl Version {
                             size: m.size as u32,
                             file_type: m.file_type.map(|x| FileType::from_string(&x)),
                             is_private: m.is_private,
+                            structure_metadata: m.structure_metadata
+                                .and_then(|x| serde_json::from_value(x).ok()),
                         };
 
                         file_ids.insert(FileId(m.id));
@@ -969,6 +983,7 @@ impl Version {
                                         size: x.size,
                                         file_type: x.file_type,
                                         is_private: x.is_private,
+                                        structure_metadata: x.structure_metadata.clone(),
                                     }
                                 }).collect::<Vec<_>>();
 
@@ -992,6 +1007,7 @@ impl Version {
                                         size: 0,
                                         file_type: None,
                                         is_private: false,
+                                        structure_metadata: None,
                                     });
                                 }
 
@@ -1191,6 +1207,9 @@ pub struct QueryFile {
     pub size: u32,
     pub file_type: Option<FileType>,
     pub is_private: bool, // 是否存储在私有桶（付费资源）
+    /// 缓存中早于此字段的版本没有这个值
+    #[serde(default)]
+    pub structure_metadata: Option<StructureMetadata>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
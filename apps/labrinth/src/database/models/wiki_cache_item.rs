@@ -1,12 +1,24 @@
 use super::ids::*;
-use crate::database::models::DatabaseError;
-use crate::models::users::User;
+use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::{
+    DatabaseError, Project, TeamMember, User as DBUser,
+};
+use crate::database::redis::RedisPool;
+use crate::models::notifications::NotificationBody;
+use crate::models::teams::ProjectPermissions;
+use crate::models::users::{Role, User};
 use chrono::{DateTime, Utc};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 pub const WIKI_CACHE_NAMESPACE: &str = "wikis_cache";
 
+/// 草稿在最后一次开始编辑后超过该时长未提交即被关闭
+const DRAFT_TIMEOUT_HOURS: i32 = 5;
+/// 超时或放弃编辑累计达到该次数后禁止编辑 72 小时
+pub const WIKI_OVERTAKE_LIMIT: i64 = 3;
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Debug)]
 pub struct WikiCache {
     pub id: WikiCacheId,
@@ -19,6 +31,20 @@ pub struct WikiCache {
     pub message: serde_json::Value,
     pub again_count: u64,
     pub again_time: DateTime<Utc>,
+    pub base_revisions: serde_json::Value,
+    pub conflicts: serde_json::Value,
+    /// 草稿编辑的页面；为空的是旧版整站草稿
+    pub wiki_id: Option<WikiId>,
+}
+
+/// 草稿合并失败时记录的单个页面冲突
+#[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Debug)]
+pub struct WikiConflict {
+    pub wiki_id: WikiId,
+    pub title: String,
+    pub reason: String,
+    /// 草稿开始编辑时该页面的修订 id
+    pub base_revision_id: Option<i64>,
 }
 
 impl WikiCache {
@@ -33,9 +59,21 @@ impl WikiCache {
                 "message": msg.to_owned()
             }));
     }
+
+    /// 最近一条留言内容，草稿通过时作为本次修订的编辑说明
+    pub fn latest_message(&self) -> String {
+        self.message
+            .as_array()
+            .and_then(|messages| messages.last())
+            .and_then(|m| m["message"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+    /// 用户编辑该页面的草稿；旧版整站草稿包含所有页面，同样返回
     pub async fn get_draft<'a, E>(
         project_id: ProjectId,
         user_id: UserId,
+        wiki_id: WikiId,
         exec: E,
     ) -> Result<Option<WikiCache>, DatabaseError>
     where
@@ -43,9 +81,10 @@ impl WikiCache {
     {
         let mut exec = exec.acquire().await?;
         let wiki_cache= sqlx::query!(
-            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time FROM wiki_cache WHERE mod_id = $1 AND user_id = $2 AND status = 'draft'",
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE mod_id = $1 AND user_id = $2 AND status = 'draft' AND (wiki_id = $3 OR wiki_id IS NULL) ORDER BY wiki_id NULLS LAST LIMIT 1",
             &project_id.0,
-            &user_id.0
+            &user_id.0,
+            &wiki_id.0
         ).fetch_optional(&mut *exec)
             .await?
             .map(|row| WikiCache {
@@ -58,7 +97,10 @@ impl WikiCache {
                 old: row.old,
                 message: row.message,
                 again_count: row.again_count as u64,
-                again_time: row.again_time
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
             });
 
         Ok(wiki_cache)
@@ -69,7 +111,7 @@ impl WikiCache {
     ) -> Result<Vec<WikiCache>, DatabaseError> {
         let mut exec = exec.acquire().await?;
         let wiki_cache= sqlx::query!(
-            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time FROM wiki_cache WHERE status = 'draft'"
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE status = 'draft'"
         ).fetch_all(&mut *exec)
             .await?
             .into_iter()
@@ -83,7 +125,10 @@ impl WikiCache {
                 old: row.old,
                 message: row.message,
                 again_count: row.again_count as u64,
-                again_time: row.again_time
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
             }).collect::<Vec<_>>();
         Ok(wiki_cache)
     }
@@ -98,7 +143,7 @@ impl WikiCache {
     {
         let mut exec = exec.acquire().await?;
         let wiki_cache= sqlx::query!(
-            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time FROM wiki_cache WHERE id = $1 AND user_id = $2 AND (status = 'reject' OR status = 'review')",
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE id = $1 AND user_id = $2 AND (status = 'reject' OR status = 'review')",
             &cache_id,
             &user_id.0
         ).fetch_optional(&mut *exec)
@@ -113,14 +158,20 @@ impl WikiCache {
                 old: row.old,
                 message: row.message,
                 again_count: row.again_count as u64,
-                again_time: row.again_time
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
             });
 
         Ok(wiki_cache)
     }
 
-    pub async fn get_draft_or_review<'a, E>(
+    /// 获取用户在该页面上进行中（草稿或待审核）的编辑
+    pub async fn get_user_active<'a, E>(
         project_id: ProjectId,
+        user_id: UserId,
+        wiki_id: WikiId,
         exec: E,
     ) -> Result<Option<WikiCache>, DatabaseError>
     where
@@ -128,8 +179,10 @@ impl WikiCache {
     {
         let mut exec = exec.acquire().await?;
         let wiki_cache= sqlx::query!(
-            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time FROM wiki_cache WHERE mod_id = $1 AND (status = 'draft' OR status = 'review')",
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE mod_id = $1 AND user_id = $2 AND (status = 'draft' OR status = 'review') AND (wiki_id = $3 OR wiki_id IS NULL) ORDER BY wiki_id NULLS LAST, created LIMIT 1",
             &project_id.0,
+            &user_id.0,
+            &wiki_id.0
         ).fetch_optional(&mut *exec)
             .await?
             .map(|row| WikiCache {
@@ -142,23 +195,65 @@ impl WikiCache {
                 old: row.old,
                 message: row.message,
                 again_count: row.again_count as u64,
-                again_time: row.again_time
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
             });
 
         Ok(wiki_cache)
     }
 
-    pub async fn get_has_draft_or_review<'a, E>(
+    /// 获取用户在该项目下所有进行中（草稿或待审核）的编辑，按创建时间排序
+    pub async fn get_user_actives<'a, E>(
         project_id: ProjectId,
+        user_id: UserId,
         exec: E,
-    ) -> Result<Option<WikiCache>, DatabaseError>
+    ) -> Result<Vec<WikiCache>, DatabaseError>
     where
         E: sqlx::Acquire<'a, Database = sqlx::Postgres>,
     {
         let mut exec = exec.acquire().await?;
         let wiki_cache= sqlx::query!(
-            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time FROM wiki_cache WHERE mod_id = $1 AND (status = 'draft' OR status = 'review')",
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE mod_id = $1 AND user_id = $2 AND (status = 'draft' OR status = 'review') ORDER BY created",
             &project_id.0,
+            &user_id.0
+        ).fetch_all(&mut *exec)
+            .await?
+            .into_iter()
+            .map(|row| WikiCache {
+                id: WikiCacheId(row.id),
+                project_id: ProjectId(row.mod_id),
+                user_id: UserId(row.user_id),
+                created: row.created,
+                status: row.status,
+                cache: row.caches,
+                old: row.old,
+                message: row.message,
+                again_count: row.again_count as u64,
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
+            }).collect::<Vec<_>>();
+
+        Ok(wiki_cache)
+    }
+
+    /// 按 id 获取项目下进行中（草稿或待审核）的编辑
+    pub async fn get_active<'a, E>(
+        cache_id: WikiCacheId,
+        project_id: ProjectId,
+        exec: E,
+    ) -> Result<Option<WikiCache>, DatabaseError>
+    where
+        E: sqlx::Acquire<'a, Database = sqlx::Postgres>,
+    {
+        let mut exec = exec.acquire().await?;
+        let wiki_cache= sqlx::query!(
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE id = $1 AND mod_id = $2 AND (status = 'draft' OR status = 'review')",
+            &cache_id.0,
+            &project_id.0
         ).fetch_optional(&mut *exec)
             .await?
             .map(|row| WikiCache {
@@ -171,24 +266,95 @@ impl WikiCache {
                 old: row.old,
                 message: row.message,
                 again_count: row.again_count as u64,
-                again_time: row.again_time
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
+            });
+
+        Ok(wiki_cache)
+    }
+
+    /// 在事务中锁定进行中（草稿或待审核）的编辑并读取最新内容；
+    /// 已被处理的编辑返回 None
+    pub async fn lock_active(
+        cache_id: WikiCacheId,
+        project_id: ProjectId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<WikiCache>, DatabaseError> {
+        let wiki_cache = sqlx::query!(
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE id = $1 AND mod_id = $2 AND status IN ('draft', 'review') FOR UPDATE",
+            &cache_id.0,
+            &project_id.0
+        ).fetch_optional(&mut **transaction)
+            .await?
+            .map(|row| WikiCache {
+                id: WikiCacheId(row.id),
+                project_id: ProjectId(row.mod_id),
+                user_id: UserId(row.user_id),
+                created: row.created,
+                status: row.status,
+                cache: row.caches,
+                old: row.old,
+                message: row.message,
+                again_count: row.again_count as u64,
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
             });
 
         Ok(wiki_cache)
     }
 
+    /// 获取项目下所有待审核的编辑，按创建时间排序
+    pub async fn get_project_reviews<'a, E>(
+        project_id: ProjectId,
+        exec: E,
+    ) -> Result<Vec<WikiCache>, DatabaseError>
+    where
+        E: sqlx::Acquire<'a, Database = sqlx::Postgres>,
+    {
+        let mut exec = exec.acquire().await?;
+        let wiki_cache= sqlx::query!(
+            "SELECT id, mod_id, user_id, created, status ,caches, old, message,again_count,again_time, base_revisions, conflicts, wiki_id FROM wiki_cache WHERE mod_id = $1 AND status = 'review' ORDER BY created",
+            &project_id.0,
+        ).fetch_all(&mut *exec)
+            .await?
+            .into_iter()
+            .map(|row| WikiCache {
+                id: WikiCacheId(row.id),
+                project_id: ProjectId(row.mod_id),
+                user_id: UserId(row.user_id),
+                created: row.created,
+                status: row.status,
+                cache: row.caches,
+                old: row.old,
+                message: row.message,
+                again_count: row.again_count as u64,
+                again_time: row.again_time,
+                base_revisions: row.base_revisions,
+                conflicts: row.conflicts,
+                wiki_id: row.wiki_id.map(WikiId),
+            }).collect::<Vec<_>>();
+
+        Ok(wiki_cache)
+    }
+
     pub async fn insert(
         &self,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<WikiCache, sqlx::Error> {
         let row = sqlx::query!(
-            "INSERT INTO wiki_cache (id, mod_id, user_id, caches,old, message) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            "INSERT INTO wiki_cache (id, mod_id, user_id, caches,old, message, base_revisions, wiki_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *",
             self.id.0,
             self.project_id.0,
             self.user_id.0,
             self.cache,
             self.old,
-            self.message
+            self.message,
+            self.base_revisions,
+            self.wiki_id.map(|x| x.0)
         ).fetch_one(&mut **transaction).await?;
 
         Ok(WikiCache {
@@ -202,6 +368,9 @@ impl WikiCache {
             message: row.message,
             again_count: row.again_count as u64,
             again_time: row.again_time,
+            base_revisions: row.base_revisions,
+            conflicts: row.conflicts,
+            wiki_id: row.wiki_id.map(WikiId),
         })
     }
     pub async fn update_cache(
@@ -209,10 +378,12 @@ impl WikiCache {
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<WikiCache, sqlx::Error> {
         let row = sqlx::query!(
-            "UPDATE wiki_cache SET caches = $1,status = $2,message=$3 WHERE id=$4 RETURNING *",
+            "UPDATE wiki_cache SET caches = $1,status = $2,message=$3,old = $4,base_revisions = $5 WHERE id=$6 RETURNING *",
             self.cache,
             self.status,
             self.message,
+            self.old,
+            self.base_revisions,
             self.id.0
         ).fetch_one(&mut **transaction).await?;
         Ok(WikiCache {
@@ -226,6 +397,9 @@ impl WikiCache {
             message: row.message,
             again_count: row.again_count as u64,
             again_time: row.again_time,
+            base_revisions: row.base_revisions,
+            conflicts: row.conflicts,
+            wiki_id: row.wiki_id.map(WikiId),
         })
     }
     pub async fn finish_cache(
@@ -243,6 +417,26 @@ impl WikiCache {
         Ok(())
     }
 
+    /// 合并冲突后把草稿变基到当前内容：写入合并结果与冲突列表，并退回给提交者处理
+    pub async fn rebase(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE wiki_cache SET status = $1, caches = $2, old = $3, base_revisions = $4, conflicts = $5, message = $6 WHERE id = $7",
+            "reject",
+            self.cache,
+            self.old,
+            self.base_revisions,
+            self.conflicts,
+            self.message,
+            self.id.0
+        )
+        .execute(&mut **transaction)
+        .await?;
+        Ok(())
+    }
+
     pub async fn reject_cache(
         &self,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -361,4 +555,146 @@ impl WikiCache {
         .await?;
         Ok(())
     }
+
+    /// 关闭长时间未提交的草稿并通知编辑者，返回关闭的数量
+    ///
+    /// 有 WIKI_EDIT 权限的成员的草稿被退回，可重新发起编辑；其他用户的草稿
+    /// 记为超时，并按累计超时次数禁止编辑。每份草稿使用单独的事务，
+    /// 个别草稿处理失败不影响其他草稿。
+    pub async fn expire_stale_drafts(
+        pool: &PgPool,
+        redis: &RedisPool,
+    ) -> Result<usize, DatabaseError> {
+        let stale = sqlx::query!(
+            "
+            SELECT id, user_id, mod_id
+            FROM wiki_cache
+            WHERE status = 'draft'
+              AND again_time < now() - make_interval(hours => $1)
+            ",
+            DRAFT_TIMEOUT_HOURS,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut expired = 0;
+        for item in stale {
+            match Self::expire_draft(
+                WikiCacheId(item.id),
+                ProjectId(item.mod_id),
+                UserId(item.user_id),
+                pool,
+                redis,
+            )
+            .await
+            {
+                Ok(true) => expired += 1,
+                Ok(false) => {}
+                Err(e) => warn!("关闭超时百科草稿 {} 失败: {:?}", item.id, e),
+            }
+        }
+
+        Ok(expired)
+    }
+
+    async fn expire_draft(
+        id: WikiCacheId,
+        project_id: ProjectId,
+        user_id: UserId,
+        pool: &PgPool,
+        redis: &RedisPool,
+    ) -> Result<bool, DatabaseError> {
+        let Some(project) = Project::get_id(project_id, pool, redis).await?
+        else {
+            return Ok(false);
+        };
+        let Some(user) = DBUser::get_id(user_id, pool, redis).await? else {
+            return Ok(false);
+        };
+
+        let (team_member, organization_team_member) =
+            TeamMember::get_for_project_permissions(
+                &project.inner,
+                user_id,
+                pool,
+            )
+            .await?;
+        let can_edit = ProjectPermissions::get_permissions_by_role(
+            &Role::from_string(&user.role),
+            &team_member,
+            &organization_team_member,
+        )
+        .is_some_and(|x| x.contains(ProjectPermissions::WIKI_EDIT));
+
+        let mut transaction = pool.begin().await?;
+
+        let updated = sqlx::query!(
+            "UPDATE wiki_cache SET status = $1 WHERE id = $2 AND status = 'draft'",
+            if can_edit { "reject" } else { "timeout" },
+            id.0
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        let (type_, msg) = if can_edit {
+            ("reject", "资源编辑超时，您是该资源的管理员可重新发起编辑")
+        } else if user.wiki_overtake_count + 1 >= WIKI_OVERTAKE_LIMIT {
+            Self::ban_editor(user_id, 72, true, &mut transaction).await?;
+            (
+                "time_out",
+                "资源编辑超时，并且累计3次超时/取消编辑 您已经被禁止编辑72小时",
+            )
+        } else {
+            Self::ban_editor(user_id, 24, false, &mut transaction).await?;
+            ("time_out", "资源编辑超时，您已被禁止编辑24小时")
+        };
+
+        let push_events = NotificationBuilder {
+            body: NotificationBody::WikiCache {
+                project_id: project_id.into(),
+                project_title: project.inner.name.clone(),
+                wiki_cache_id: id,
+                type_: type_.to_string(),
+                msg: msg.to_string(),
+            },
+        }
+        .insert(user_id, &mut transaction, redis)
+        .await?;
+        transaction.commit().await?;
+        crate::queue::push::publish(push_events, redis).await;
+
+        if !can_edit {
+            DBUser::clear_caches(&[(user_id, Some(user.username))], redis)
+                .await?;
+        }
+
+        Ok(true)
+    }
+
+    /// 禁止用户编辑百科；`reset` 为真时同时清零超时次数，否则次数加一
+    async fn ban_editor(
+        user_id: UserId,
+        hours: i64,
+        reset: bool,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            UPDATE users
+            SET wiki_ban_time = now() + interval '1 hour' * $1,
+                wiki_overtake_count = CASE WHEN $2 THEN 0 ELSE wiki_overtake_count + 1 END
+            WHERE id = $3
+            ",
+            hours,
+            reset,
+            user_id.0
+        )
+        .execute(&mut **transaction)
+        .await?;
+        Ok(())
+    }
 }
@@ -42,10 +42,14 @@ pub struct Wikis {
     pub wikis: Vec<WikiDisplays>,
     pub is_editor: bool,
     pub cache: Option<WikiCache>,
+    /// 当前用户所有进行中的页面草稿，`cache` 为其中最早的一份
+    pub drafts: Vec<WikiCache>,
     pub is_editor_user: bool,
     pub editor_user: Option<User>,
     pub is_visitors: bool,
     pub requires_purchase: bool,
+    /// 待审核的编辑（仅对有 WIKI_EDIT 权限的成员返回）
+    pub reviews: Vec<WikiCache>,
 }
 
 impl Wiki {
@@ -165,6 +169,40 @@ impl Wiki {
         Ok(val)
     }
 
+    /// 在事务中锁定项目已发布的全部页面并读取最新内容，不经过缓存
+    pub async fn get_project_for_update(
+        project_id: ProjectId,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<Wiki>, DatabaseError> {
+        let mut wikis = sqlx::query!("
+                    SELECT id, mod_id, sort_order, title, body, parent_wiki_id, featured, created, updated, slug
+                    FROM wikis
+                    WHERE mod_id = $1 AND draft = FALSE
+                    ORDER BY id
+                    FOR UPDATE;
+                    ",
+                    project_id.0
+                )
+            .fetch(&mut **transaction)
+            .map_ok(|w| Wiki {
+                id: WikiId(w.id),
+                project_id: ProjectId(w.mod_id),
+                sort_order: w.sort_order,
+                title: w.title,
+                body: w.body,
+                parent_wiki_id: WikiId(w.parent_wiki_id),
+                featured: w.featured,
+                created: w.created,
+                updated: w.updated,
+                slug: w.slug,
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        wikis.sort_by(|a, b| a.sort_order.cmp(&b.sort_order));
+        Ok(wikis)
+    }
+
     pub async fn get<'a, E>(
         wiki_id: i64,
         exec: E,
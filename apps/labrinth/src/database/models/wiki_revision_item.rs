@@ -0,0 +1,309 @@
+use super::DatabaseError;
+use super::ids::*;
+use crate::database::models::Wiki;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 百科页面的一次不可变修订记录
+///
+/// 每次草稿被通过（或有权限的成员直接提交、回滚）时，为每个发生变化的
+/// 页面写入一条修订，保存变更后的完整内容；页面被删除时也会记录一条
+/// `delete` 修订，保存删除前的内容，用于整体回滚时恢复。
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WikiRevision {
+    pub id: i64,
+    pub wiki_id: WikiId,
+    pub project_id: ProjectId,
+    pub wiki_cache_id: Option<WikiCacheId>,
+    pub revision_number: i32,
+    pub action: WikiRevisionAction,
+    pub author_id: Option<UserId>,
+    pub reviewer_id: Option<UserId>,
+    pub message: String,
+    pub parent_wiki_id: WikiId,
+    pub sort_order: i32,
+    pub title: String,
+    pub slug: String,
+    pub body: String,
+    pub featured: bool,
+    pub created: DateTime<Utc>,
+}
+
+/// 修订列表使用的摘要，不包含正文
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WikiRevisionSummary {
+    pub id: i64,
+    pub wiki_id: WikiId,
+    pub wiki_cache_id: Option<WikiCacheId>,
+    pub revision_number: i32,
+    pub action: WikiRevisionAction,
+    pub author_id: Option<UserId>,
+    pub reviewer_id: Option<UserId>,
+    pub message: String,
+    pub title: String,
+    pub body_length: i32,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WikiRevisionAction {
+    /// 迁移时为已有页面补录的初始版本
+    Initial,
+    Create,
+    Edit,
+    Delete,
+    Rollback,
+}
+
+impl WikiRevisionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WikiRevisionAction::Initial => "initial",
+            WikiRevisionAction::Create => "create",
+            WikiRevisionAction::Edit => "edit",
+            WikiRevisionAction::Delete => "delete",
+            WikiRevisionAction::Rollback => "rollback",
+        }
+    }
+
+    pub fn from_string(string: &str) -> Self {
+        match string {
+            "initial" => WikiRevisionAction::Initial,
+            "create" => WikiRevisionAction::Create,
+            "delete" => WikiRevisionAction::Delete,
+            "rollback" => WikiRevisionAction::Rollback,
+            _ => WikiRevisionAction::Edit,
+        }
+    }
+}
+
+/// 写入修订时的上下文：谁提交、谁审核、来自哪个草稿
+pub struct WikiRevisionContext<'a> {
+    pub wiki_cache_id: Option<WikiCacheId>,
+    pub author_id: Option<UserId>,
+    pub reviewer_id: Option<UserId>,
+    pub message: &'a str,
+}
+
+impl WikiRevision {
+    /// 以页面当前内容写入一条新修订，版本号在该页面内递增
+    ///
+    /// 先锁定页面行再计算版本号，同一页面的并发写入依次分配版本号；
+    /// 页面行必须存在（删除页面时需在删除前记录）。
+    pub async fn record(
+        wiki: &Wiki,
+        action: WikiRevisionAction,
+        context: &WikiRevisionContext<'_>,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<i64, DatabaseError> {
+        sqlx::query!(
+            "SELECT id FROM wikis WHERE id = $1 FOR UPDATE",
+            wiki.id.0
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        let row = sqlx::query!(
+            "
+            INSERT INTO wiki_revisions (
+                wiki_id, mod_id, wiki_cache_id, revision_number, action, author_id,
+                reviewer_id, message, parent_wiki_id, sort_order, title, slug, body, featured
+            )
+            SELECT $1, $2, $3, COALESCE(MAX(revision_number), 0) + 1, $4, $5,
+                   $6, $7, $8, $9, $10, $11, $12, $13
+            FROM wiki_revisions
+            WHERE wiki_id = $1
+            RETURNING id
+            ",
+            wiki.id.0,
+            wiki.project_id.0,
+            context.wiki_cache_id.map(|x| x.0),
+            action.as_str(),
+            context.author_id.map(|x| x.0),
+            context.reviewer_id.map(|x| x.0),
+            context.message,
+            wiki.parent_wiki_id.0,
+            wiki.sort_order,
+            wiki.title,
+            wiki.slug,
+            wiki.body,
+            wiki.featured,
+        )
+        .fetch_one(&mut **transaction)
+        .await?;
+
+        Ok(row.id)
+    }
+
+    pub async fn get<'a, E>(
+        id: i64,
+        exec: E,
+    ) -> Result<Option<WikiRevision>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let row = sqlx::query!(
+            "
+            SELECT id, wiki_id, mod_id, wiki_cache_id, revision_number, action, author_id,
+                   reviewer_id, message, parent_wiki_id, sort_order, title, slug, body, featured, created
+            FROM wiki_revisions
+            WHERE id = $1
+            ",
+            id,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(row.map(|row| WikiRevision {
+            id: row.id,
+            wiki_id: WikiId(row.wiki_id),
+            project_id: ProjectId(row.mod_id),
+            wiki_cache_id: row.wiki_cache_id.map(WikiCacheId),
+            revision_number: row.revision_number,
+            action: WikiRevisionAction::from_string(&row.action),
+            author_id: row.author_id.map(UserId),
+            reviewer_id: row.reviewer_id.map(UserId),
+            message: row.message,
+            parent_wiki_id: WikiId(row.parent_wiki_id),
+            sort_order: row.sort_order,
+            title: row.title,
+            slug: row.slug,
+            body: row.body,
+            featured: row.featured,
+            created: row.created,
+        }))
+    }
+
+    /// 获取某个页面的修订列表，按版本号倒序
+    pub async fn get_summaries_for_wiki<'a, E>(
+        wiki_id: WikiId,
+        project_id: ProjectId,
+        exec: E,
+    ) -> Result<Vec<WikiRevisionSummary>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query!(
+            "
+            SELECT id, wiki_id, wiki_cache_id, revision_number, action, author_id,
+                   reviewer_id, message, title, char_length(body) AS \"body_length!\", created
+            FROM wiki_revisions
+            WHERE wiki_id = $1 AND mod_id = $2
+            ORDER BY revision_number DESC
+            ",
+            wiki_id.0,
+            project_id.0,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WikiRevisionSummary {
+                id: row.id,
+                wiki_id: WikiId(row.wiki_id),
+                wiki_cache_id: row.wiki_cache_id.map(WikiCacheId),
+                revision_number: row.revision_number,
+                action: WikiRevisionAction::from_string(&row.action),
+                author_id: row.author_id.map(UserId),
+                reviewer_id: row.reviewer_id.map(UserId),
+                message: row.message,
+                title: row.title,
+                body_length: row.body_length,
+                created: row.created,
+            })
+            .collect())
+    }
+
+    /// 获取项目中每个页面最新修订的 id，用作草稿的基线版本
+    pub async fn get_latest_ids<'a, E>(
+        project_id: ProjectId,
+        exec: E,
+    ) -> Result<Vec<(WikiId, i64)>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query!(
+            "
+            SELECT DISTINCT ON (wiki_id) wiki_id, id
+            FROM wiki_revisions
+            WHERE mod_id = $1
+            ORDER BY wiki_id, revision_number DESC
+            ",
+            project_id.0,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (WikiId(row.wiki_id), row.id))
+            .collect())
+    }
+
+    /// 获取项目百科在某一时刻的状态：每个页面在该时刻之前的最后一条修订
+    ///
+    /// 结果中 `action` 为 `delete` 的页面在该时刻已被删除。
+    pub async fn get_project_state_at<'a, E>(
+        project_id: ProjectId,
+        at: DateTime<Utc>,
+        exec: E,
+    ) -> Result<Vec<WikiRevision>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query!(
+            "
+            SELECT DISTINCT ON (wiki_id)
+                   id, wiki_id, mod_id, wiki_cache_id, revision_number, action, author_id,
+                   reviewer_id, message, parent_wiki_id, sort_order, title, slug, body, featured, created
+            FROM wiki_revisions
+            WHERE mod_id = $1 AND created <= $2
+            ORDER BY wiki_id, created DESC, revision_number DESC
+            ",
+            project_id.0,
+            at,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WikiRevision {
+                id: row.id,
+                wiki_id: WikiId(row.wiki_id),
+                project_id: ProjectId(row.mod_id),
+                wiki_cache_id: row.wiki_cache_id.map(WikiCacheId),
+                revision_number: row.revision_number,
+                action: WikiRevisionAction::from_string(&row.action),
+                author_id: row.author_id.map(UserId),
+                reviewer_id: row.reviewer_id.map(UserId),
+                message: row.message,
+                parent_wiki_id: WikiId(row.parent_wiki_id),
+                sort_order: row.sort_order,
+                title: row.title,
+                slug: row.slug,
+                body: row.body,
+                featured: row.featured,
+                created: row.created,
+            })
+            .collect())
+    }
+
+    /// 将修订内容还原为页面结构
+    pub fn to_wiki(&self, created: DateTime<Utc>) -> Wiki {
+        Wiki {
+            id: self.wiki_id,
+            project_id: self.project_id,
+            sort_order: self.sort_order,
+            title: self.title.clone(),
+            body: self.body.clone(),
+            parent_wiki_id: self.parent_wiki_id,
+            featured: self.featured,
+            created,
+            updated: Utc::now(),
+            slug: self.slug.clone(),
+        }
+    }
+}
@@ -1,38 +1,26 @@
-use std::num::NonZeroU32;
-use std::ops::Add;
 use std::sync::Arc;
 use std::time::Duration;
 
 use actix_web::web;
-use chrono::Utc;
 use database::redis::RedisPool;
 use queue::{
     analytics::AnalyticsQueue, incentive::IncentiveQueue,
-    payouts::PayoutsQueue, session::AuthQueue, socket::ActiveSockets,
+    payouts::PayoutsQueue, push::PushHub, session::AuthQueue,
+    socket::ActiveSockets,
 };
 use sqlx::Postgres;
 use tokio::sync::RwLock;
 
 extern crate clickhouse as clickhouse_crate;
 use clickhouse_crate::Client;
-use governor::middleware::StateInformationMiddleware;
-use governor::{Quota, RateLimiter};
 use log::{info, warn};
 use util::cors::default_cors;
 
-use crate::database::Project;
-use crate::database::models::notification_item::NotificationBuilder;
-use crate::database::models::{
-    OrganizationId, ProjectId, TeamId, User, UserId,
-};
-use crate::models::notifications::NotificationBody;
-use crate::models::projects::{MonetizationStatus, ProjectStatus};
-use crate::models::teams::ProjectPermissions;
-use crate::models::users::{Badges, Role};
 use crate::queue::moderation::AutomatedModerationQueue;
-use crate::util::ratelimit::KeyedRateLimiter;
+use crate::util::ratelimit::RateLimits;
 use crate::{
-    search::indexing::index_projects,
+    search::indexing::content::index_content,
+    search::indexing::{index_projects, index_queued_projects},
     util::env::{parse_strings_from_var, parse_var},
 };
 
@@ -69,8 +57,9 @@ pub struct LabrinthConfig {
     pub analytics_queue: Arc<AnalyticsQueue>,
     pub incentive_queue: Arc<IncentiveQueue>,
     pub active_sockets: web::Data<RwLock<ActiveSockets>>,
+    pub push_hub: web::Data<PushHub>,
     pub automated_moderation_queue: web::Data<AutomatedModerationQueue>,
-    pub rate_limiter: KeyedRateLimiter,
+    pub rate_limits: web::Data<RateLimits>,
     // pub stripe_client: stripe::Client,
 }
 
@@ -83,6 +72,10 @@ pub fn app_setup(
     private_file_host: Option<Arc<file_hosting::S3PrivateHost>>,
 ) -> LabrinthConfig {
     info!("启动 Labrinth 于 {}", dotenvy::var("BIND_ADDR").unwrap());
+    #[cfg(any(test, feature = "mock-providers"))]
+    if routes::mock_providers::enabled() {
+        warn!("已启用支付服务商模拟接口 (/_mock)，切勿在生产环境使用");
+    }
 
     let automated_moderation_queue =
         web::Data::new(AutomatedModerationQueue::default());
@@ -100,22 +93,21 @@ pub fn app_setup(
 
     let mut scheduler = scheduler::Scheduler::new();
 
-    let limiter: KeyedRateLimiter = Arc::new(
-        RateLimiter::keyed(Quota::per_minute(NonZeroU32::new(500).unwrap()))
-            .with_middleware::<StateInformationMiddleware>(),
-    );
-    let limiter_clone = Arc::clone(&limiter);
+    let rate_limits = web::Data::new(RateLimits::new(
+        pool.clone(),
+        redis_pool.clone(),
+    ));
+    let rate_limits_ref = rate_limits.clone();
     scheduler.run(Duration::from_secs(60), move || {
-        info!("清理速率限制器，存储大小：{}", limiter_clone.len());
-        limiter_clone.retain_recent();
-        info!("完成清理速率限制器，存储大小：{}", limiter_clone.len());
+        rate_limits_ref.retain_recent();
 
         async move {}
     });
 
-    // 本地数据库索引的间隔时间，单位为秒。默认值为 1 小时。
+    // 全量重建搜索索引的间隔时间，单位为秒。默认值为 6 小时。
+    // 日常的变更由索引队列增量处理，全量重建只用于修正遗漏并刷新下载量等统计。
     let local_index_interval = std::time::Duration::from_secs(
-        parse_var("LOCAL_INDEX_INTERVAL").unwrap_or(3600),
+        parse_var("LOCAL_INDEX_INTERVAL").unwrap_or(21600),
     );
 
     let pool_ref = pool.clone();
@@ -128,7 +120,7 @@ pub fn app_setup(
         async move {
             info!("索引本地数据库");
             let result = index_projects(
-                pool_ref,
+                pool_ref.clone(),
                 redis_pool_ref.clone(),
                 &search_config_ref,
             )
@@ -136,10 +128,37 @@ pub fn app_setup(
             if let Err(e) = result {
                 warn!("本地项目索引失败：{:?}", e);
             }
+            let result = index_content(&pool_ref, &search_config_ref).await;
+            if let Err(e) = result {
+                warn!("站内内容索引失败：{:?}", e);
+            }
             info!("完成索引本地数据库");
         }
     });
 
+    // 处理搜索索引队列的间隔时间，单位为秒。默认值为 10 秒。
+    let search_queue_interval = std::time::Duration::from_secs(
+        parse_var("SEARCH_INDEX_QUEUE_INTERVAL").unwrap_or(10),
+    );
+
+    let pool_ref = pool.clone();
+    let search_config_ref = search_config.clone();
+    scheduler.run(search_queue_interval, move || {
+        let pool_ref = pool_ref.clone();
+        let search_config_ref = search_config_ref.clone();
+        async move {
+            match index_queued_projects(&pool_ref, &search_config_ref).await {
+                Ok(count) if count > 0 => {
+                    info!("已增量更新 {} 个项目的搜索索引", count);
+                }
+                Err(e) => {
+                    warn!("处理搜索索引队列失败：{:?}", e);
+                }
+                _ => {}
+            }
+        }
+    });
+
     // Changes statuses of scheduled projects/versions
     let pool_ref = pool.clone();
     // TODO: Clear cache when these are run
@@ -150,9 +169,16 @@ pub fn app_setup(
         async move {
             let projects_results = sqlx::query!(
                 "
-                UPDATE mods
-                SET status = requested_status
-                WHERE status = $1 AND approved < CURRENT_DATE AND requested_status IS NOT NULL
+                WITH published AS (
+                    UPDATE mods
+                    SET status = requested_status
+                    WHERE status = $1 AND approved < CURRENT_DATE AND requested_status IS NOT NULL
+                    RETURNING id
+                )
+                INSERT INTO search_index_queue (project_id)
+                SELECT id FROM published
+                ON CONFLICT (project_id)
+                DO UPDATE SET queued_at = CURRENT_TIMESTAMP
                 ",
                 crate::models::projects::ProjectStatus::Scheduled.as_str(),
             )
@@ -165,9 +191,16 @@ pub fn app_setup(
 
             let versions_results = sqlx::query!(
                 "
-                UPDATE versions
-                SET status = requested_status
-                WHERE status = $1 AND date_published < CURRENT_DATE AND requested_status IS NOT NULL
+                WITH published AS (
+                    UPDATE versions
+                    SET status = requested_status
+                    WHERE status = $1 AND date_published < CURRENT_DATE AND requested_status IS NOT NULL
+                    RETURNING mod_id
+                )
+                INSERT INTO search_index_queue (project_id)
+                SELECT DISTINCT mod_id FROM published
+                ON CONFLICT (project_id)
+                DO UPDATE SET queued_at = CURRENT_TIMESTAMP
                 ",
                 crate::models::projects::VersionStatus::Scheduled.as_str(),
             )
@@ -266,23 +299,20 @@ pub fn app_setup(
         }
     });
 
-    let analytics_queue = Arc::new(AnalyticsQueue::new());
+    let analytics_queue = Arc::new(AnalyticsQueue::new(redis_pool.clone()));
     {
         let client_ref = clickhouse.clone();
         let analytics_queue_ref = analytics_queue.clone();
         let pool_ref = pool.clone();
-        let redis_ref = redis_pool.clone();
         scheduler.run(std::time::Duration::from_secs(15), move || {
             let client_ref = client_ref.clone();
             let analytics_queue_ref = analytics_queue_ref.clone();
             let pool_ref = pool_ref.clone();
-            let redis_ref = redis_ref.clone();
 
             async move {
                 info!("开始索引分析服务");
-                let result = analytics_queue_ref
-                    .index(client_ref, &redis_ref, &pool_ref)
-                    .await;
+                let result =
+                    analytics_queue_ref.index(client_ref, &pool_ref).await;
                 if let Err(e) = result {
                     warn!("分析服务索引失败: {:?}", e);
                 }
@@ -290,8 +320,25 @@ pub fn app_setup(
             }
         });
     }
+    {
+        let pool_ref = pool.clone();
+        scheduler.run(std::time::Duration::from_secs(86_400), move || {
+            let pool_ref = pool_ref.clone();
+            async move {
+                match queue::analytics::cleanup_processed_events(&pool_ref)
+                    .await
+                {
+                    Ok(n) if n > 0 => {
+                        info!("分析事件去重记录清理完成 {} 条", n)
+                    }
+                    Err(e) => warn!("分析事件去重记录清理失败: {:?}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
 
-    let incentive_queue = Arc::new(IncentiveQueue::new());
+    let incentive_queue = Arc::new(IncentiveQueue::new(redis_pool.clone()));
     {
         let incentive_queue_ref = incentive_queue.clone();
         let pool_ref = pool.clone();
@@ -344,381 +391,113 @@ pub fn app_setup(
             }
         });
     }
+    // 每日账本对账：与支付回调记录、云账户订单和旧收益表核对，差异交由管理员处理
+    {
+        let pool_ref = pool.clone();
+        let redis_ref = redis_pool.clone();
+        scheduler.run(std::time::Duration::from_secs(86_400), move || {
+            let pool_ref = pool_ref.clone();
+            let redis_ref = redis_ref.clone();
+            async move {
+                match queue::ledger::reconcile(&pool_ref).await {
+                    Ok(n) if n > 0 => {
+                        warn!("账本对账发现 {} 处差异", n);
+                        routes::internal::moderation::clear_pending_counts_cache(
+                            &redis_ref,
+                        )
+                        .await;
+                    }
+                    Ok(_) => info!("账本对账完成，未发现差异"),
+                    Err(e) => warn!("账本对账失败: {:?}", e),
+                }
+            }
+        });
+    }
+
+    // 每日检查上月对账单，为尚未归档的创作者生成
+    {
+        let pool_ref = pool.clone();
+        scheduler.run(std::time::Duration::from_secs(86_400), move || {
+            let pool_ref = pool_ref.clone();
+            async move {
+                match queue::statements::generate_last_period(&pool_ref).await
+                {
+                    Ok(n) if n > 0 => info!("月度对账单生成 {} 份", n),
+                    Err(e) => warn!("月度对账单生成失败: {:?}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // 发送邮件队列中到期的邮件
+    match auth::email::transport::transport_from_env() {
+        Ok(transport) => {
+            let email_queue =
+                Arc::new(queue::email::EmailQueue::new(transport));
+            let pool_ref = pool.clone();
+            scheduler.run(std::time::Duration::from_secs(10), move || {
+                let email_queue_ref = email_queue.clone();
+                let pool_ref = pool_ref.clone();
+                async move {
+                    match email_queue_ref.process(&pool_ref).await {
+                        Ok(n) if n > 0 => info!("邮件发送 {} 封", n),
+                        Err(e) => warn!("邮件队列处理失败: {:?}", e),
+                        _ => {}
+                    }
+                }
+            });
+        }
+        Err(e) => warn!("邮件发送配置无效，邮件将保留在队列中: {}", e),
+    }
+    {
+        let pool_ref = pool.clone();
+        scheduler.run(std::time::Duration::from_secs(86_400), move || {
+            let pool_ref = pool_ref.clone();
+            async move {
+                match queue::email::cleanup_finished(&pool_ref).await {
+                    Ok(n) if n > 0 => info!("已完成邮件清理 {} 封", n),
+                    Err(e) => warn!("已完成邮件清理失败: {:?}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // 每小时检查到期的通知摘要邮件
+    {
+        let pool_ref = pool.clone();
+        scheduler.run(std::time::Duration::from_secs(3600), move || {
+            let pool_ref = pool_ref.clone();
+            async move {
+                match queue::notifications::send_digests(&pool_ref).await {
+                    Ok(n) if n > 0 => info!("通知摘要邮件入队 {} 封", n),
+                    Err(e) => warn!("通知摘要邮件处理失败: {:?}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
 
     info!("启动检测超时百科编辑");
     {
-        let pool_ref_clone2 = pool.clone();
-        let redis_ref2 = redis_pool.clone();
+        let pool_ref = pool.clone();
+        let redis_ref = redis_pool.clone();
 
         scheduler.run(std::time::Duration::from_secs(60), move || {
-            let pool_ref_clone2 = pool_ref_clone2.clone();
-            let redis_ref2 = redis_ref2.clone();
+            let pool_ref = pool_ref.clone();
+            let redis_ref = redis_ref.clone();
 
             async move {
-                // info!("开始检测超时百科编辑");
-
-                let result = database::models::WikiCache::get_all_draft(&pool_ref_clone2).await;
-                match result {
-                    Ok(items) => {
-                        for item in items {
-                            if item.again_time.add(Duration::from_secs(3600 * 5)) < Utc::now() {
-
-
-                                let projects = sqlx::query!(
-                                    "
-                                    SELECT m.id id, m.name name, m.summary summary, m.downloads downloads, m.follows follows,
-                                    m.icon_url icon_url, m.raw_icon_url raw_icon_url, m.description description, m.published published,
-                                    m.updated updated, m.approved approved, m.queued, m.status status, m.requested_status requested_status,
-                                    m.license_url license_url,
-                                    m.team_id team_id, m.organization_id organization_id, m.license license, m.slug slug, m.moderation_message moderation_message, m.moderation_message_body moderation_message_body,
-                                    m.webhook_sent, m.color, m.wiki_open,m.issues_type issues_type, m.translation_tracking, m.translation_tracker, m.is_paid,
-                                    (SELECT slug FROM mods WHERE translation_tracker = m.slug AND m.slug IS NOT NULL LIMIT 1) as translation_source,
-                                    t.id thread_id, m.monetization_status monetization_status,
-                                    ARRAY_AGG(DISTINCT c.category) filter (where c.category is not null and mc.is_additional is false) categories,
-                                    ARRAY_AGG(DISTINCT c.category) filter (where c.category is not null and mc.is_additional is true) additional_categories
-                                    FROM mods m
-                                    INNER JOIN threads t ON t.mod_id = m.id
-                                    LEFT JOIN mods_categories mc ON mc.joining_mod_id = m.id
-                                    LEFT JOIN categories c ON mc.joining_category_id = c.id
-                                    WHERE m.id = ANY($1)
-                                    GROUP BY t.id, m.id;
-                                    ",
-                                    &[item.project_id.clone().0]
-                                ).fetch_all(&pool_ref_clone2).await;
-                                match projects {
-                                    Ok(projects) => {
-                                        let m = projects.first().unwrap();
-                                        let id = m.id;
-                                        let inner = Project {
-                                            id: ProjectId(id),
-                                            team_id: TeamId(m.team_id),
-                                            organization_id: m.organization_id.map(OrganizationId),
-                                            name: m.name.clone(),
-                                            summary: m.summary.clone(),
-                                            downloads: m.downloads,
-                                            icon_url: m.icon_url.clone(),
-                                            raw_icon_url: m.raw_icon_url.clone(),
-                                            published: m.published,
-                                            updated: m.updated,
-                                            license_url: m.license_url.clone(),
-                                            status: ProjectStatus::from_string(
-                                                &m.status,
-                                            ),
-                                            requested_status: None,
-                                            license: m.license.clone(),
-                                            slug: m.slug.clone(),
-                                            description: m.description.clone(),
-                                            follows: m.follows,
-                                            moderation_message: None,
-                                            moderation_message_body: None,
-                                            approved: m.approved,
-                                            webhook_sent: m.webhook_sent,
-                                            wiki_open: m.wiki_open,
-                                            color: m.color.map(|x| x as u32),
-                                            queued: m.queued,
-                                            monetization_status: MonetizationStatus::from_string(
-                                                &m.monetization_status,
-                                            ),
-                                            loaders: vec![],
-                                            issues_type: m.issues_type,
-                                            forum: None,
-                                            translation_tracking: m.translation_tracking,
-                                            translation_tracker: m.translation_tracker.clone(),
-                                            translation_source: m.translation_source.clone(),
-                                            is_paid: m.is_paid,
-                                        };
-                                        // println!("{:?}", inner);
-
-                                        let users = sqlx::query!(
-                                            "
-                                            SELECT id, email,
-                                                avatar_url, raw_avatar_url, username, bio,
-                                                created, role, badges,
-                                                github_id, discord_id, gitlab_id, google_id, steam_id, microsoft_id,
-                                                email_verified, password, totp_secret,
-                                                stripe_customer_id,wiki_overtake_count,wiki_ban_time
-                                            FROM users
-                                            WHERE id = $1
-                                            ",
-                                            &item.user_id.0
-                                        ).fetch_all(&pool_ref_clone2).await;
-                                        match users {
-                                            Ok(users) => {
-                                                let u = users.first().unwrap();
-                                                let user = User {
-                                                    id: UserId(u.id),
-                                                    github_id: None,
-                                                    discord_id: None,
-                                                    gitlab_id: None,
-                                                    google_id: None,
-                                                    steam_id: None,
-                                                    microsoft_id: None,
-                                                    bilibili_id: None,
-                                                    qq_id: None,
-                                                    email: None,
-                                                    email_verified: true,
-                                                    avatar_url: None,
-                                                    raw_avatar_url: None,
-                                                    username: u.username.clone(),
-                                                    bio: u.bio.clone(),
-                                                    created: u.created,
-                                                    role: u.role.clone(),
-                                                    badges: Badges::from_bits(u.badges as u64).unwrap_or_default(),
-                                                    password: None,
-                                                    stripe_customer_id: None,
-                                                    totp_secret: None,
-                                                    wiki_overtake_count: u.wiki_overtake_count,
-                                                    wiki_ban_time: u.wiki_ban_time,
-                                                    phone_number: None,
-                                                    is_premium_creator: false,
-                                                    creator_verified_at: None,
-                                                    active_bans: vec![],
-                                                    pending_profile_reviews: vec![],
-                                                };
-
-                                                let (team_member, organization_team_member) = match crate::database::models::TeamMember::get_for_project_permissions(&inner, item.user_id, &pool_ref_clone2).await {
-                                                    Ok(result) => result,
-                                                    Err(e) => {
-                                                        warn!("Failed to get team member: {:?}", e);
-                                                        continue;
-                                                    }
-                                                };
-                                                // println!("{:?}", team_member);
-
-                                                let permissions = ProjectPermissions::get_permissions_by_role(
-                                                    &Role::from_string(&user.role),
-                                                    &team_member,
-                                                    &organization_team_member,
-                                                );
-                                                if permissions.is_some() && permissions.unwrap().contains(ProjectPermissions::WIKI_EDIT) {
-                                                    let mut transaction = match pool_ref_clone2.begin().await{
-                                                        Ok(transaction) => transaction,
-                                                        Err(e) => {
-                                                            println!("Failed to get transaction: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-
-                                                    match sqlx::query!(
-                                                       "UPDATE wiki_cache SET status = $1, message = $2 WHERE id = $3",
-                                                        "reject",
-                                                        item.message,
-                                                        item.id.0
-                                                    ).execute( &mut *transaction).await{
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to update wiki_cache: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-                                                    let n = NotificationBuilder {
-                                                        body: NotificationBody::WikiCache {
-                                                            project_id: crate::models::ids::ProjectId::from(inner.id),
-                                                            project_title: inner.name.clone(),
-                                                            wiki_cache_id: item.id,
-                                                            type_: "reject".to_string(),
-                                                            msg: "资源编辑超时，您是该资源的管理员可重新发起编辑".to_string(),
-                                                        },
-                                                    };
-                                                    match n.insert(user.id, &mut transaction, &redis_ref2).await{
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to insert notification: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-                                                    match transaction.commit().await{
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to commit transaction: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-
-                                                }else {
-                                                    println!("无权限超时");
-
-                                                    let mut transaction = match pool_ref_clone2.begin().await{
-                                                        Ok(transaction) => transaction,
-                                                        Err(e) => {
-                                                            println!("Failed to get transaction: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-                                                    match sqlx::query!(
-                                                       "UPDATE wiki_cache SET status = $1, message = $2 WHERE id = $3",
-                                                        "timeout",
-                                                        item.message,
-                                                        item.id.0
-                                                    ).execute(&mut *transaction).await {
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to update wiki_cache: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-
-                                                    if user.wiki_overtake_count+1 >= 3 {
-                                                        match sqlx::query!(
-                                                           "UPDATE users SET wiki_ban_time = now() + interval '1 hour' * $1,wiki_overtake_count=0 WHERE id = $2",
-                                                            72 as i64,
-                                                            user.id.0
-                                                        ).execute(&mut *transaction).await{
-                                                            Ok(_) => {},
-                                                            Err(e) => {
-                                                                println!("Failed to update users: {:?}", e);
-                                                                continue
-                                                            }
-                                                        };
-                                                        println!("{:?} 超时未提交,并累计超过3次,已被禁止编辑72小时", user.username);
-                                                        let n = NotificationBuilder {
-                                                            body: NotificationBody::WikiCache {
-                                                                project_id: crate::models::ids::ProjectId::from(inner.id),
-                                                                project_title: inner.name.clone(),
-                                                                wiki_cache_id: item.id,
-                                                                type_: "time_out".to_string(),
-                                                                msg: "资源编辑超时，并且累计3次超时/取消编辑 您已经被禁止编辑72小时".to_string(),
-                                                            },
-                                                        };
-                                                        match n.insert(user.id, &mut transaction, &redis_ref2).await{
-                                                            Ok(_) => {},
-                                                            Err(e) => {
-                                                                println!("Failed to insert notification: {:?}", e);
-                                                                continue
-                                                            }
-                                                        };
-
-                                                    }else {
-                                                        match sqlx::query!(
-                                                           "UPDATE users SET wiki_ban_time = now() + interval '1 hour' * $1,wiki_overtake_count=wiki_overtake_count+1 WHERE id = $2",
-                                                            24 as i64,
-                                                            user.id.0
-                                                        ).execute(&mut *transaction).await{
-                                                            Ok(_) => {},
-                                                            Err(e) => {
-                                                                println!("Failed to update users: {:?}", e);
-                                                                continue
-                                                            }
-                                                        };
-                                                        println!("{:?} 超时未提交 已被禁止编辑24小时",user.username);
-                                                        let n = NotificationBuilder {
-                                                            body: NotificationBody::WikiCache {
-                                                                project_id: crate::models::ids::ProjectId::from(inner.id),
-                                                                project_title: inner.name.clone(),
-                                                                wiki_cache_id: item.id,
-                                                                type_: "time_out".to_string(),
-                                                                msg: "资源编辑超时，您已被禁止编辑24小时".to_string(),
-                                                            },
-                                                        };
-                                                        match n.insert(user.id, &mut transaction, &redis_ref2)
-                                                            .await{
-
-                                                            Ok(_) => {},
-                                                            Err(e) => {
-                                                                println!("Failed to insert notification: {:?}", e);
-                                                                continue
-                                                            }
-                                                        };
-                                                    }
-
-
-                                                    match transaction.commit().await{
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to commit transaction: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-                                                    match User::clear_caches(&[(user.id, Some(user.username.clone()))], &redis_ref2)
-                                                        .await{
-                                                        Ok(_) => {},
-                                                        Err(e) => {
-                                                            println!("Failed to clear caches: {:?}", e);
-                                                            continue
-                                                        }
-                                                    };
-                                                    continue
-
-                                                }
-
-                                            }
-                                            Err(e) => {
-                                                println!("Failed to get user with ID {:?}: {:?}", item.user_id.clone(), e);
-                                                continue
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        println!("Failed to get user with ID {:?}: {:?}", item.user_id.clone(), e);
-                                        continue
-                                    }
-                                }
-                                // let project_result_ = database::models::Project::get_id(item.project_id.clone(), &pool_ref_clone2).await;
-                                // if project_result_.is_err() {
-                                // if project_result_.await.is_err() {
-                                //     continue;
-                                // }
-                                //     .await;
-                                // 处理 project_result_ 的逻辑
-
-                                // if let DatabaseError(e) = project_result_ {
-                                //     println!("Failed to get project with ID {:?}: {:?}", item.project_id.clone(), e);
-                                //     continue;
-                                // }
-                                // if project_result_.is_err() {
-                                //     continue
-                                // }
-
-                                // let project = project_result_.await.unwrap(); // 此时 unwrap 是安全的，因为前面已经检查过错误情况
-                                // if project.is_none() {
-                                //     continue
-                                // }
-                                // let project = project.unwrap();
-                                // println!("{:?}", project);
-
-                                // let user_option =
-                                //     match database::models::User::get_id(
-                                //         item.user_id.clone(),
-                                //         &pool_ref_clone2, &redis_ref2,
-                                //     ).await{
-                                //         Ok(user) => user,
-                                //         Err(e) => {
-                                //             warn!("Failed to get user: {:?}", e);
-                                //             return;
-                                //         }
-                                //     };
-                                // if user_option.is_none() {
-                                //     continue;
-                                // }
-                                // let user_option = user_option.unwrap();
-                                // let (team_member, organization_team_member) = match crate::database::models::TeamMember::get_for_project_permissions(&project.inner, item.user_id.clone(), &pool_ref_clone2).await {
-                                //     Ok(result) => result,
-                                //     Err(e) => {
-                                //         warn!("Failed to get team member: {:?}", e);
-                                //         return;
-                                //     }
-                                // };
-                                //
-                                //
-                                // let permissions = ProjectPermissions::get_permissions_by_role(
-                                //     &Role::from_string(&user_option.role),
-                                //     &team_member,
-                                //     &organization_team_member,
-                                // );
-                                // if !permissions.is_none() && permissions.unwrap().contains(ProjectPermissions::WIKI_EDIT) {
-                                //     println!("有权限超时");
-                                //     continue
-                                // }else {
-                                //     println!("无权限超时")
-                                // }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        warn!("检测超时百科编辑失败: {:?}", e);
-                    }
+                match database::models::WikiCache::expire_stale_drafts(
+                    &pool_ref, &redis_ref,
+                )
+                .await
+                {
+                    Ok(n) if n > 0 => info!("已关闭超时百科草稿 {} 条", n),
+                    Err(e) => warn!("检测超时百科编辑失败: {:?}", e),
+                    _ => {}
                 }
-                // info!("检测超时百科编辑完成");
             }
         });
     }
@@ -776,6 +555,15 @@ pub fn app_setup(
     let payouts_queue = web::Data::new(PayoutsQueue::new());
     let active_sockets = web::Data::new(RwLock::new(ActiveSockets::default()));
 
+    // 实时推送：订阅各实例广播的事件，分发给本实例的 WebSocket 连接
+    let push_hub = web::Data::new(PushHub::default());
+    {
+        let push_hub_ref = push_hub.clone();
+        actix_rt::spawn(async move {
+            push_hub_ref.run().await;
+        });
+    }
+
     LabrinthConfig {
         pool,
         redis_pool,
@@ -790,8 +578,9 @@ pub fn app_setup(
         analytics_queue,
         incentive_queue,
         active_sockets,
+        push_hub,
         automated_moderation_queue,
-        rate_limiter: limiter,
+        rate_limits,
     }
 }
 
@@ -823,13 +612,19 @@ pub fn app_config(
     .app_data(web::Data::new(labrinth_config.incentive_queue.clone()))
     .app_data(web::Data::new(labrinth_config.clickhouse.clone()))
     .app_data(labrinth_config.active_sockets.clone())
+    .app_data(labrinth_config.push_hub.clone())
+    .app_data(labrinth_config.rate_limits.clone())
     .app_data(labrinth_config.automated_moderation_queue.clone())
     // .app_data(web::Data::new(labrinth_config.stripe_client.clone()))
     .configure(routes::v2::config)
     .configure(routes::v3::config)
-    .configure(routes::internal::config)
-    .configure(routes::root_config)
-    .default_service(web::get().wrap(default_cors()).to(routes::not_found));
+    .configure(routes::internal::config);
+
+    #[cfg(any(test, feature = "mock-providers"))]
+    cfg.configure(routes::mock_providers::config);
+
+    cfg.configure(routes::root_config)
+        .default_service(web::get().wrap(default_cors()).to(routes::not_found));
 }
 
 // This is so that env vars not used immediately don't panic at runtime
@@ -853,12 +648,27 @@ pub fn check_env_vars() -> bool {
     failed |= check_var::<String>("LABRINTH_ADMIN_KEY");
     failed |= check_var::<String>("RATE_LIMIT_IGNORE_KEY");
     failed |= check_var::<String>("DATABASE_URL");
-    failed |= check_var::<String>("MEILISEARCH_ADDR");
-    failed |= check_var::<String>("MEILISEARCH_KEY");
     failed |= check_var::<String>("REDIS_URL");
     failed |= check_var::<String>("BIND_ADDR");
     failed |= check_var::<String>("SELF_ADDR");
 
+    // 未设置时默认使用 MeiliSearch
+    let search_backend = dotenvy::var("SEARCH_BACKEND").ok();
+    match search_backend.as_deref() {
+        None | Some("meilisearch") => {
+            failed |= check_var::<String>("MEILISEARCH_ADDR");
+            failed |= check_var::<String>("MEILISEARCH_KEY");
+        }
+        Some("postgres") => {}
+        Some(backend) => {
+            warn!(
+                "变量 `SEARCH_BACKEND` 包含无效值：{}。预期值为 \"meilisearch\" 或 \"postgres\"。",
+                backend
+            );
+            failed |= true;
+        }
+    }
+
     failed |= check_var::<String>("STORAGE_BACKEND");
 
     let storage_backend = dotenvy::var("STORAGE_BACKEND").ok();
@@ -926,9 +736,11 @@ pub fn check_env_vars() -> bool {
 
     failed |= check_var::<String>("HCAPTCHA_SECRET");
 
-    failed |= check_var::<String>("SMTP_USERNAME");
-    failed |= check_var::<String>("SMTP_PASSWORD");
-    failed |= check_var::<String>("SMTP_HOST");
+    if dotenvy::var("EMAIL_TRANSPORT").as_deref() != Ok("file") {
+        failed |= check_var::<String>("SMTP_USERNAME");
+        failed |= check_var::<String>("SMTP_PASSWORD");
+        failed |= check_var::<String>("SMTP_HOST");
+    }
 
     failed |= check_var::<String>("SITE_VERIFY_EMAIL_PATH");
     failed |= check_var::<String>("SITE_RESET_PASSWORD_PATH");
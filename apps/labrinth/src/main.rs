@@ -3,7 +3,7 @@ use actix_web_prom::PrometheusMetricsBuilder;
 use labrinth::database::redis::RedisPool;
 use labrinth::file_hosting::{S3Host, S3PrivateHost};
 use labrinth::search;
-use labrinth::util::ratelimit::RateLimit;
+use labrinth::util::ratelimit::{GLOBAL, RateLimit};
 use labrinth::{check_env_vars, clickhouse, database, file_hosting};
 use std::sync::Arc;
 use tracing::{error, info};
@@ -128,6 +128,8 @@ async fn main() -> std::io::Result<()> {
         .exclude_regex("^/v[23]/version/[^/]+/download$")
         .build()
         .expect("创建 prometheus 指标中间件失败");
+    labrinth::queue::stream::register_metrics(&prometheus.registry)
+        .expect("注册事件队列指标失败");
     println!("prometheus: 正常");
     let search_config = search::SearchConfig::new(None);
     println!("search_config: 正常");
@@ -146,7 +148,7 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .wrap(prometheus.clone())
-            .wrap(RateLimit(Arc::clone(&labrinth_config.rate_limiter)))
+            .wrap(RateLimit(&GLOBAL))
             .wrap(actix_web::middleware::Compress::default())
             .wrap(sentry_actix::Sentry::new())
             .configure(|cfg| labrinth::app_config(cfg, labrinth_config.clone()))
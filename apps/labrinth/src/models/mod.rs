@@ -4,10 +4,14 @@ pub mod v3;
 
 pub use v3::analytics;
 pub use v3::billing;
+pub use v3::bundles;
 pub use v3::collections;
 pub use v3::forum;
 pub use v3::ids;
 pub use v3::images;
+pub use v3::licenses;
+pub use v3::malware;
+pub use v3::messages;
 pub use v3::notifications;
 pub use v3::oauth_clients;
 pub use v3::organizations;
@@ -15,8 +19,13 @@ pub use v3::pack;
 pub use v3::pats;
 pub use v3::payouts;
 pub use v3::projects;
+pub use v3::promotions;
+pub use v3::purchase_codes;
+pub use v3::refunds;
 pub use v3::reports;
 pub use v3::sessions;
+pub use v3::statements;
+pub use v3::structure;
 pub use v3::teams;
 pub use v3::threads;
 pub use v3::users;
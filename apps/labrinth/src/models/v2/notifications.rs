@@ -149,6 +149,13 @@ pub enum LegacyNotificationBody {
         status: String,
         review_notes: Option<String>,
     },
+    /// 私信会话有新消息
+    DirectMessage {
+        thread_id: ThreadId,
+        message_id: ThreadMessageId,
+        sender_id: UserId,
+        sender: String,
+    },
     Unknown,
 }
 
@@ -204,6 +211,9 @@ impl LegacyNotification {
             NotificationBody::ImageReviewResult { .. } => {
                 Some("image_review_result".to_string())
             }
+            NotificationBody::DirectMessage { .. } => {
+                Some("direct_message".to_string())
+            }
             NotificationBody::LegacyMarkdown {
                 notification_type, ..
             } => notification_type.clone(),
@@ -391,6 +401,17 @@ impl LegacyNotification {
                 status,
                 review_notes,
             },
+            NotificationBody::DirectMessage {
+                thread_id,
+                message_id,
+                sender_id,
+                sender,
+            } => LegacyNotificationBody::DirectMessage {
+                thread_id,
+                message_id,
+                sender_id,
+                sender,
+            },
             NotificationBody::Unknown => LegacyNotificationBody::Unknown,
         };
 
@@ -30,7 +30,11 @@ impl From<ItemType> for LegacyItemType {
             ItemType::Project => LegacyItemType::Project,
             ItemType::Version => LegacyItemType::Version,
             ItemType::User => LegacyItemType::User,
-            ItemType::Unknown => LegacyItemType::Unknown,
+            ItemType::ForumPost
+            | ItemType::IssueComment
+            | ItemType::WikiPage
+            | ItemType::ThreadMessage
+            | ItemType::Unknown => LegacyItemType::Unknown,
         }
     }
 }
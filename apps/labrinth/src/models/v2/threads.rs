@@ -84,6 +84,10 @@ impl From<crate::models::v3::threads::ThreadType> for LegacyThreadType {
                 // Incentive application threads 也映射为 Report
                 LegacyThreadType::Report
             }
+            crate::models::v3::threads::ThreadType::Refund => {
+                // 退款线程同样映射为 Report
+                LegacyThreadType::Report
+            }
         }
     }
 }
@@ -3,8 +3,15 @@ use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 use std::net::Ipv6Addr;
 
+/// 生成分析事件的 ID
+pub fn new_event_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
 #[derive(Row, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub struct Download {
+    /// 事件 ID，事件队列重放时用于去重
+    pub event_id: String,
     pub recorded: i64,
     pub domain: String,
     pub site_path: String,
@@ -26,6 +33,8 @@ pub struct Download {
 
 #[derive(Row, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub struct PageView {
+    /// 事件 ID，事件队列重放时用于去重
+    pub event_id: String,
     pub recorded: i64,
     pub domain: String,
     pub site_path: String,
@@ -47,6 +56,8 @@ pub struct PageView {
 
 #[derive(Row, Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Playtime {
+    /// 事件 ID，事件队列重放时用于去重
+    pub event_id: String,
     pub recorded: i64,
     pub seconds: u64,
 
@@ -0,0 +1,50 @@
+use super::ids::Base62Id;
+use crate::database;
+use crate::models::ids::{OrganizationId, ProjectId, UserId};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct ProjectBundleId(pub u64);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectBundle {
+    pub id: ProjectBundleId,
+    /// 用户捆绑包的所有者，与 organization_id 二选一
+    pub user_id: Option<UserId>,
+    pub organization_id: Option<OrganizationId>,
+    pub name: String,
+    pub description: String,
+    /// 捆绑包价格（元）
+    pub price: Decimal,
+    /// 捆绑包内项目单独购买的原价总和（元）
+    pub list_price: Decimal,
+    pub active: bool,
+    pub projects: Vec<ProjectId>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProjectBundle {
+    pub fn from(
+        data: database::models::ProjectBundle,
+        list_price: Decimal,
+    ) -> Self {
+        Self {
+            id: data.id.into(),
+            user_id: data.user_id.map(Into::into),
+            organization_id: data.organization_id.map(Into::into),
+            name: data.name,
+            description: data.description,
+            price: data.price,
+            list_price,
+            active: data.active,
+            projects: data.projects.into_iter().map(Into::into).collect(),
+            created_at: data.created_at,
+            updated_at: data.updated_at,
+        }
+    }
+}
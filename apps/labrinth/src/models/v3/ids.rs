@@ -1,8 +1,10 @@
 pub use super::bans::{BanAppealId, BanHistoryId, UserBanId};
+pub use super::bundles::ProjectBundleId;
 pub use super::collections::CollectionId;
 pub use super::forum::{DiscussionId, PostId};
 pub use super::images::ImageId;
 pub use super::issues::{IssuesCommentsId, IssuesId};
+pub use super::licenses::ProjectLicenseId;
 pub use super::notifications::NotificationId;
 pub use super::oauth_clients::OAuthClientAuthorizationId;
 pub use super::oauth_clients::{OAuthClientId, OAuthRedirectUriId};
@@ -10,6 +12,9 @@ pub use super::organizations::OrganizationId;
 pub use super::pats::PatId;
 pub use super::payouts::PayoutId;
 pub use super::projects::{ProjectId, VersionId, WikiId};
+pub use super::promotions::{CouponId, ProjectSaleId};
+pub use super::purchase_codes::PurchaseCodeBatchId;
+pub use super::refunds::PaymentRefundId;
 pub use super::reports::ReportId;
 pub use super::sessions::SessionId;
 pub use super::teams::TeamId;
@@ -154,6 +159,12 @@ base62_id_impl!(IssuesCommentsId, IssuesCommentsId);
 base62_id_impl!(UserBanId, UserBanId);
 base62_id_impl!(BanHistoryId, BanHistoryId);
 base62_id_impl!(BanAppealId, BanAppealId);
+base62_id_impl!(CouponId, CouponId);
+base62_id_impl!(ProjectSaleId, ProjectSaleId);
+base62_id_impl!(PaymentRefundId, PaymentRefundId);
+base62_id_impl!(ProjectLicenseId, ProjectLicenseId);
+base62_id_impl!(ProjectBundleId, ProjectBundleId);
+base62_id_impl!(PurchaseCodeBatchId, PurchaseCodeBatchId);
 
 pub mod base62_impl {
     use serde::de::{self, Deserializer, Visitor};
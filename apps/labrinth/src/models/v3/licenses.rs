@@ -0,0 +1,65 @@
+use super::ids::Base62Id;
+use crate::database;
+use crate::models::ids::{ProjectId, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct ProjectLicenseId(pub u64);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectLicense {
+    pub id: ProjectLicenseId,
+    pub user_id: UserId,
+    pub project_id: ProjectId,
+    /// 允许同时激活的服务器实例数量
+    pub max_activations: i32,
+    pub created_at: DateTime<Utc>,
+    /// 过期时间，与购买记录一致，永久授权为空
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 未吊销且购买记录有效
+    pub valid: bool,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoke_reason: Option<String>,
+    pub activations: Vec<LicenseActivation>,
+}
+
+impl ProjectLicense {
+    pub fn from(
+        data: database::models::ProjectLicense,
+        activations: Vec<database::models::ProjectLicenseActivation>,
+    ) -> Self {
+        Self {
+            id: data.id.into(),
+            user_id: data.user_id.into(),
+            project_id: data.project_id.into(),
+            max_activations: data.max_activations,
+            created_at: data.created_at,
+            expires_at: data.expires_at,
+            valid: data.is_valid(),
+            revoked_at: data.revoked_at,
+            revoke_reason: data.revoke_reason,
+            activations: activations.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// 服务器实例激活记录（不返回 IP）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LicenseActivation {
+    pub instance_id: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl From<database::models::ProjectLicenseActivation> for LicenseActivation {
+    fn from(data: database::models::ProjectLicenseActivation) -> Self {
+        Self {
+            instance_id: data.instance_id,
+            first_seen_at: data.first_seen_at,
+            last_seen_at: data.last_seen_at,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// 可疑代码的严重程度
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl ScanSeverity {
+    pub fn as_friendly_str(&self) -> &'static str {
+        match self {
+            ScanSeverity::Low => "低",
+            ScanSeverity::Medium => "中",
+            ScanSeverity::High => "高",
+            ScanSeverity::Critical => "严重",
+        }
+    }
+}
+
+impl FromStr for ScanSeverity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(ScanSeverity::Low),
+            "medium" => Ok(ScanSeverity::Medium),
+            "high" => Ok(ScanSeverity::High),
+            "critical" => Ok(ScanSeverity::Critical),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanRule {
+    KnownMalware,
+    ObfuscatedLoader,
+    ProcessExecution,
+    NativeLibraryDrop,
+    RemoteClassLoading,
+    TokenStealing,
+}
+
+impl ScanRule {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ScanRule::KnownMalware => "已知恶意软件特征",
+            ScanRule::ObfuscatedLoader => "解密后动态定义类",
+            ScanRule::ProcessExecution => "执行外部程序",
+            ScanRule::NativeLibraryDrop => "释放并加载本地库",
+            ScanRule::RemoteClassLoading => "从远程地址加载代码",
+            ScanRule::TokenStealing => "读取账户凭据或会话令牌",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ScanFinding {
+    pub severity: ScanSeverity,
+    pub rule: ScanRule,
+    /// 命中的条目，内嵌 jar 中的条目以 `!/` 分隔
+    pub path: String,
+    /// 命中的类、方法或字符串
+    pub detail: String,
+}
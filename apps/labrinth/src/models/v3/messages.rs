@@ -0,0 +1,69 @@
+use crate::models::ids::ThreadId;
+use crate::models::threads::ThreadMessage;
+use crate::models::users::User;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 单个私信会话最多的成员数（含发起者）
+pub const MAX_CONVERSATION_MEMBERS: usize = 10;
+
+/// 谁可以向用户发起新的私信会话
+///
+/// 已存在的会话不受隐私设置影响，只受屏蔽关系约束。
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DmPrivacy {
+    /// 所有人
+    Everyone,
+    /// 仅关注了该用户所在团队项目的用户
+    Followers,
+    /// 不接收新会话
+    Nobody,
+}
+
+impl DmPrivacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DmPrivacy::Everyone => "everyone",
+            DmPrivacy::Followers => "followers",
+            DmPrivacy::Nobody => "nobody",
+        }
+    }
+
+    pub fn from_string(string: &str) -> DmPrivacy {
+        match string {
+            "followers" => DmPrivacy::Followers,
+            "nobody" => DmPrivacy::Nobody,
+            _ => DmPrivacy::Everyone,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DmSettings {
+    pub privacy: DmPrivacy,
+}
+
+/// 会话列表中的一项
+#[derive(Serialize, Deserialize)]
+pub struct Conversation {
+    pub thread_id: ThreadId,
+    pub members: Vec<User>,
+    pub last_message: Option<ThreadMessage>,
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// 其他成员发送的、当前用户尚未阅读的消息数
+    pub unread: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserBlock {
+    pub user: User,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnreadCount {
+    pub unread: i64,
+    /// 含未读消息的会话数
+    pub conversations: i64,
+}
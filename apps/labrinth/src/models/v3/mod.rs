@@ -1,11 +1,15 @@
 pub mod analytics;
 pub mod bans;
 pub mod billing;
+pub mod bundles;
 pub mod collections;
 pub mod forum;
 pub mod ids;
 pub mod images;
 pub mod issues;
+pub mod licenses;
+pub mod malware;
+pub mod messages;
 pub mod notifications;
 pub mod oauth_clients;
 pub mod organizations;
@@ -13,8 +17,13 @@ pub mod pack;
 pub mod pats;
 pub mod payouts;
 pub mod projects;
+pub mod promotions;
+pub mod purchase_codes;
+pub mod refunds;
 pub mod reports;
 pub mod sessions;
+pub mod statements;
+pub mod structure;
 pub mod teams;
 pub mod threads;
 pub mod users;
@@ -146,9 +146,146 @@ pub enum NotificationBody {
         status: String,
         review_notes: Option<String>,
     },
+    /// 私信会话有新消息
+    DirectMessage {
+        thread_id: ThreadId,
+        message_id: ThreadMessageId,
+        sender_id: UserId,
+        sender: String,
+    },
     Unknown,
 }
 
+impl NotificationBody {
+    /// 可设置投递方式的通知类型，与序列化后的 `type` 字段一致
+    pub const CONFIGURABLE_TYPES: &'static [&'static str] = &[
+        "project_update",
+        "team_invite",
+        "organization_invite",
+        "status_change",
+        "moderator_message",
+        "wiki_cache",
+        "forum",
+        "user_banned",
+        "user_unbanned",
+        "appeal_reviewed",
+        "ban_appeal_message",
+        "creator_application_message",
+        "creator_application_approved",
+        "creator_application_rejected",
+        "profile_review_pending",
+        "profile_review_result",
+        "image_review_result",
+        "direct_message",
+    ];
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NotificationBody::ProjectUpdate { .. } => "project_update",
+            NotificationBody::TeamInvite { .. } => "team_invite",
+            NotificationBody::OrganizationInvite { .. } => {
+                "organization_invite"
+            }
+            NotificationBody::StatusChange { .. } => "status_change",
+            NotificationBody::ModeratorMessage { .. } => "moderator_message",
+            NotificationBody::LegacyMarkdown { .. } => "legacy_markdown",
+            NotificationBody::WikiCache { .. } => "wiki_cache",
+            NotificationBody::Forum { .. } => "forum",
+            NotificationBody::UserBanned { .. } => "user_banned",
+            NotificationBody::UserUnbanned { .. } => "user_unbanned",
+            NotificationBody::AppealReviewed { .. } => "appeal_reviewed",
+            NotificationBody::BanAppealMessage { .. } => "ban_appeal_message",
+            NotificationBody::CreatorApplicationMessage { .. } => {
+                "creator_application_message"
+            }
+            NotificationBody::CreatorApplicationApproved { .. } => {
+                "creator_application_approved"
+            }
+            NotificationBody::CreatorApplicationRejected { .. } => {
+                "creator_application_rejected"
+            }
+            NotificationBody::ProfileReviewPending { .. } => {
+                "profile_review_pending"
+            }
+            NotificationBody::ProfileReviewResult { .. } => {
+                "profile_review_result"
+            }
+            NotificationBody::ImageReviewResult { .. } => "image_review_result",
+            NotificationBody::DirectMessage { .. } => "direct_message",
+            NotificationBody::Unknown => "unknown",
+        }
+    }
+
+    /// 涉及账户状态的通知不能关闭，只能选择投递方式
+    pub fn is_required_type(type_name: &str) -> bool {
+        matches!(
+            type_name,
+            "moderator_message"
+                | "user_banned"
+                | "user_unbanned"
+                | "appeal_reviewed"
+                | "ban_appeal_message"
+        )
+    }
+}
+
+/// 通知投递方式
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// 仅站内通知
+    InApp,
+    /// 站内通知并立即发送邮件
+    Email,
+    /// 站内通知并加入每日摘要邮件
+    Daily,
+    /// 站内通知并加入每周摘要邮件
+    Weekly,
+    /// 不接收
+    Off,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::InApp => "in_app",
+            NotificationChannel::Email => "email",
+            NotificationChannel::Daily => "daily",
+            NotificationChannel::Weekly => "weekly",
+            NotificationChannel::Off => "off",
+        }
+    }
+
+    pub fn from_string(string: &str) -> NotificationChannel {
+        match string {
+            "email" => NotificationChannel::Email,
+            "daily" => NotificationChannel::Daily,
+            "weekly" => NotificationChannel::Weekly,
+            "off" => NotificationChannel::Off,
+            _ => NotificationChannel::InApp,
+        }
+    }
+
+    /// 摘要频率，非摘要方式返回 `None`
+    pub fn digest(&self) -> Option<&'static str> {
+        match self {
+            NotificationChannel::Daily | NotificationChannel::Weekly => {
+                Some(self.as_str())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationPreference {
+    #[serde(rename = "type")]
+    pub notification_type: String,
+    pub channel: NotificationChannel,
+    /// 是否允许设置为 `off`
+    pub can_disable: bool,
+}
+
 impl From<DBNotification> for Notification {
     fn from(notif: DBNotification) -> Self {
         let (name, text, link, actions) = {
@@ -477,6 +614,14 @@ impl From<DBNotification> for Notification {
                         vec![],
                     )
                 }
+                NotificationBody::DirectMessage {
+                    thread_id, sender, ..
+                } => (
+                    format!("{} 给您发来了私信", sender),
+                    "您有一条新的私信，请前往消息中心查看。".to_string(),
+                    format!("/messages/{}", thread_id),
+                    vec![],
+                ),
                 NotificationBody::Unknown => {
                     ("".to_string(), "".to_string(), "#".to_string(), vec![])
                 }
@@ -513,3 +658,49 @@ impl From<DBNotificationAction> for NotificationAction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_name_matches_serde_tag() {
+        let bodies = [
+            NotificationBody::ProjectUpdate {
+                project_id: ProjectId(1),
+                version_id: VersionId(2),
+            },
+            NotificationBody::CreatorApplicationApproved { application_id: 1 },
+            NotificationBody::ImageReviewResult {
+                review_id: 1,
+                source_type: "gallery".to_string(),
+                status: "rejected".to_string(),
+                review_notes: None,
+            },
+            NotificationBody::Unknown,
+        ];
+
+        for body in bodies {
+            let value = serde_json::to_value(&body).unwrap();
+            assert_eq!(value["type"], body.type_name());
+        }
+    }
+
+    #[test]
+    fn channel_round_trips_with_serde_name() {
+        for channel in [
+            NotificationChannel::InApp,
+            NotificationChannel::Email,
+            NotificationChannel::Daily,
+            NotificationChannel::Weekly,
+            NotificationChannel::Off,
+        ] {
+            let value = serde_json::to_value(channel).unwrap();
+            assert_eq!(value, channel.as_str());
+            assert_eq!(
+                NotificationChannel::from_string(channel.as_str()),
+                channel
+            );
+        }
+    }
+}
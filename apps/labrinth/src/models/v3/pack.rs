@@ -115,3 +115,87 @@ impl PackDependency {
         }
     }
 }
+
+/// CurseForge 整合包的 `manifest.json`
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeManifest {
+    pub minecraft: CurseForgeMinecraft,
+    pub manifest_type: String,
+    pub manifest_version: i32,
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    /// 由 CurseForge 分发的文件，只记录项目与文件 ID
+    #[serde(default)]
+    pub files: Vec<CurseForgeFile>,
+    /// 直接打包在整合包中的文件所在目录
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<CurseForgeModLoader>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct CurseForgeModLoader {
+    /// 形如 `forge-47.2.0`、`fabric-0.15.7`
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct CurseForgeFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u32,
+    #[serde(rename = "fileID")]
+    pub file_id: u32,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl CurseForgeManifest {
+    /// 覆盖目录在压缩包中的路径前缀，带结尾的 `/`
+    pub fn overrides_prefix(&self) -> String {
+        format!("{}/", self.overrides.trim_matches('/'))
+    }
+
+    /// 转换为 mrpack 格式的游戏与加载器依赖
+    pub fn pack_dependencies(
+        &self,
+    ) -> std::collections::HashMap<PackDependency, String> {
+        let mut dependencies = std::collections::HashMap::new();
+        dependencies
+            .insert(PackDependency::Minecraft, self.minecraft.version.clone());
+
+        for loader in &self.minecraft.mod_loaders {
+            let Some((name, version)) = loader.id.split_once('-') else {
+                continue;
+            };
+            let dependency = match name {
+                "forge" => PackDependency::Forge,
+                "neoforge" => PackDependency::Neoforge,
+                "fabric" => PackDependency::FabricLoader,
+                "quilt" => PackDependency::QuiltLoader,
+                _ => continue,
+            };
+            dependencies.insert(dependency, version.to_string());
+        }
+
+        dependencies
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
 use super::ids::{Base62Id, DiscussionId, OrganizationId};
+use super::structure::StructureMetadata;
 use super::teams::TeamId;
 use super::users::UserId;
 use crate::database::models::loader_fields::VersionField;
@@ -780,6 +781,7 @@ impl From<QueryVersion> for Version {
                     primary: f.primary,
                     size: f.size,
                     file_type: f.file_type,
+                    structure_metadata: f.structure_metadata,
                 })
                 .collect(),
             dependencies: data
@@ -947,6 +949,9 @@ pub struct VersionFile {
     pub size: u32,
     /// The type of the file
     pub file_type: Option<FileType>,
+    /// 结构文件与存档中解析出的信息，其他文件没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure_metadata: Option<StructureMetadata>,
 }
 
 /// A dendency which describes what versions are required, break support, or are optional to the
@@ -0,0 +1,163 @@
+use super::ids::Base62Id;
+use crate::database;
+use crate::models::ids::{OrganizationId, ProjectId, UserId};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct CouponId(pub u64);
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct ProjectSaleId(pub u64);
+
+/// 订单的最低支付金额（元），优惠后不会低于此金额
+pub fn min_order_amount() -> Decimal {
+    Decimal::new(1, 2)
+}
+
+/// 优惠券折扣方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountType {
+    /// 按百分比减免，折扣值为减免的百分比（1-99）
+    Percent,
+    /// 固定金额减免，折扣值为减免的金额（元）
+    Fixed,
+}
+
+impl DiscountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Percent => "percent",
+            Self::Fixed => "fixed",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "fixed" => Self::Fixed,
+            _ => Self::Percent,
+        }
+    }
+
+    /// 计算优惠后的价格，保留两位小数且不低于最低支付金额
+    pub fn apply(&self, price: Decimal, value: Decimal) -> Decimal {
+        let discounted = match self {
+            Self::Percent => {
+                price * (Decimal::ONE_HUNDRED - value) / Decimal::ONE_HUNDRED
+            }
+            Self::Fixed => price - value,
+        };
+
+        discounted.round_dp(2).max(min_order_amount()).min(price)
+    }
+}
+
+/// 优惠券；组织优惠券适用于组织下的所有付费项目
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Coupon {
+    pub id: CouponId,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<ProjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<OrganizationId>,
+    pub discount_type: DiscountType,
+    pub discount_value: Decimal,
+    /// 总使用次数上限，None 表示不限
+    pub max_uses: Option<i32>,
+    /// 每个用户的使用次数上限，None 表示不限
+    pub per_user_limit: Option<i32>,
+    /// 已使用次数（已支付及未过期的待支付订单）
+    pub uses: i64,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// 限定可使用的用户，为空表示所有用户可用
+    pub allowed_users: Vec<UserId>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Coupon {
+    pub fn from(
+        data: database::models::coupon_item::Coupon,
+        uses: i64,
+    ) -> Self {
+        Self {
+            id: data.id.into(),
+            code: data.code,
+            project_id: data.project_id.map(Into::into),
+            organization_id: data.organization_id.map(Into::into),
+            discount_type: data.discount_type,
+            discount_value: data.discount_value,
+            max_uses: data.max_uses,
+            per_user_limit: data.per_user_limit,
+            uses,
+            starts_at: data.starts_at,
+            ends_at: data.ends_at,
+            allowed_users: data
+                .allowed_users
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            enabled: data.enabled,
+            created_at: data.created_at,
+        }
+    }
+}
+
+/// 项目的限时促销
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectSale {
+    pub id: ProjectSaleId,
+    pub project_id: ProjectId,
+    /// 促销期间的价格（元）
+    pub sale_price: Decimal,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl From<database::models::project_pricing_item::ProjectSale> for ProjectSale {
+    fn from(data: database::models::project_pricing_item::ProjectSale) -> Self {
+        Self {
+            id: data.id.into(),
+            project_id: data.project_id.into(),
+            sale_price: data.sale_price,
+            starts_at: data.starts_at,
+            ends_at: data.ends_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn percent_discount_rounds_to_cents() {
+        assert_eq!(
+            DiscountType::Percent.apply(dec("9.99"), dec("15")),
+            dec("8.49")
+        );
+        assert_eq!(
+            DiscountType::Percent.apply(dec("30"), dec("50")),
+            dec("15")
+        );
+    }
+
+    #[test]
+    fn discount_never_goes_below_minimum() {
+        assert_eq!(DiscountType::Fixed.apply(dec("5"), dec("10")), dec("0.01"));
+        assert_eq!(DiscountType::Fixed.apply(dec("5"), dec("2.5")), dec("2.5"));
+    }
+}
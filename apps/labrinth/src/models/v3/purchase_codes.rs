@@ -0,0 +1,66 @@
+use super::ids::Base62Id;
+use crate::database;
+use crate::models::ids::{ProjectId, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct PurchaseCodeBatchId(pub u64);
+
+/// 兑换码批次及其使用情况
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PurchaseCodeBatch {
+    pub id: PurchaseCodeBatchId,
+    pub project_id: ProjectId,
+    pub name: String,
+    pub code_count: i32,
+    /// 已兑换的数量
+    pub redeemed_count: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 作废后未兑换的兑换码不能再使用
+    pub revoked: bool,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+    /// 仅在创建批次与查看批次详情时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codes: Option<Vec<PurchaseCode>>,
+}
+
+/// 单个兑换码
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PurchaseCode {
+    pub code: String,
+    pub redeemed_by: Option<UserId>,
+    pub redeemed_at: Option<DateTime<Utc>>,
+}
+
+impl PurchaseCodeBatch {
+    pub fn from(
+        data: database::models::PurchaseCodeBatch,
+        codes: Option<Vec<database::models::PurchaseCode>>,
+    ) -> Self {
+        Self {
+            id: data.id.into(),
+            project_id: data.project_id.into(),
+            name: data.name,
+            code_count: data.code_count,
+            redeemed_count: data.redeemed_count,
+            expires_at: data.expires_at,
+            revoked: data.revoked,
+            created_by: data.created_by.into(),
+            created_at: data.created_at,
+            codes: codes.map(|codes| {
+                codes
+                    .into_iter()
+                    .map(|x| PurchaseCode {
+                        code: x.code,
+                        redeemed_by: x.redeemed_by.map(Into::into),
+                        redeemed_at: x.redeemed_at,
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
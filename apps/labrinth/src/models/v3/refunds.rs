@@ -0,0 +1,121 @@
+use super::ids::Base62Id;
+use crate::database;
+use crate::models::ids::{ProjectId, ThreadId, UserId};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Hash)]
+#[serde(from = "Base62Id")]
+#[serde(into = "Base62Id")]
+pub struct PaymentRefundId(pub u64);
+
+/// 退款状态
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundStatus {
+    /// 等待作者或管理员审核
+    Pending,
+    /// 已通过审核，等待支付平台完成原路退款
+    Processing,
+    Rejected,
+    /// 买家撤回了申请
+    Cancelled,
+    Refunded,
+}
+
+impl RefundStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Processing => "processing",
+            Self::Rejected => "rejected",
+            Self::Cancelled => "cancelled",
+            Self::Refunded => "refunded",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "processing" => Self::Processing,
+            "rejected" => Self::Rejected,
+            "cancelled" => Self::Cancelled,
+            "refunded" => Self::Refunded,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// 退款来源
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefundSource {
+    /// 买家申请
+    Buyer,
+    /// 支付平台直接退款（拒付、平台客服退款）
+    Chargeback,
+}
+
+impl RefundSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buyer => "buyer",
+            Self::Chargeback => "chargeback",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "chargeback" => Self::Chargeback,
+            _ => Self::Buyer,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PaymentRefund {
+    pub id: PaymentRefundId,
+    pub order_no: String,
+    pub user_id: UserId,
+    pub project_id: ProjectId,
+    /// 退款金额（元），等于订单实际支付金额
+    pub amount: Decimal,
+    pub reason: String,
+    pub status: RefundStatus,
+    pub source: RefundSource,
+    /// 买家与作者、管理员沟通退款事宜的消息线程
+    pub thread_id: Option<ThreadId>,
+    pub reviewer_id: Option<UserId>,
+    pub review_note: Option<String>,
+    /// 由平台垫付退款时从创作者收益中扣回的金额
+    pub clawback_amount: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<database::models::payment_refund_item::PaymentRefund>
+    for PaymentRefund
+{
+    fn from(
+        data: database::models::payment_refund_item::PaymentRefund,
+    ) -> Self {
+        Self {
+            id: data.id.into(),
+            order_no: data.order_no,
+            user_id: data.user_id.into(),
+            project_id: data.project_id.into(),
+            amount: data.amount,
+            reason: data.reason,
+            status: data.status,
+            source: data.source,
+            thread_id: data.thread_id.map(Into::into),
+            reviewer_id: data.reviewer_id.map(Into::into),
+            review_note: data.review_note,
+            clawback_amount: data.clawback_amount,
+            created_at: data.created_at,
+            reviewed_at: data.reviewed_at,
+            completed_at: data.completed_at,
+        }
+    }
+}
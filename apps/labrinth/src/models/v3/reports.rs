@@ -1,6 +1,9 @@
 use super::ids::Base62Id;
 use crate::database::models::report_item::QueryReport as DBReport;
-use crate::models::ids::{ProjectId, ThreadId, UserId, VersionId};
+use crate::models::ids::{
+    IssuesCommentsId, PostId, ProjectId, ThreadId, ThreadMessageId, UserId,
+    VersionId, WikiId,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -20,14 +23,22 @@ pub struct Report {
     pub created: DateTime<Utc>,
     pub closed: bool,
     pub thread_id: ThreadId,
+    /// 被举报内容的作者
+    pub content_author: Option<UserId>,
+    /// 举报时的内容快照，内容被删除后仍然保留
+    pub content: Option<ReportedContent>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub enum ItemType {
     Project,
     Version,
     User,
+    ForumPost,
+    IssueComment,
+    WikiPage,
+    ThreadMessage,
     Unknown,
 }
 
@@ -37,9 +48,74 @@ impl ItemType {
             ItemType::Project => "project",
             ItemType::Version => "version",
             ItemType::User => "user",
+            ItemType::ForumPost => "forum-post",
+            ItemType::IssueComment => "issue-comment",
+            ItemType::WikiPage => "wiki-page",
+            ItemType::ThreadMessage => "thread-message",
             ItemType::Unknown => "unknown",
         }
     }
+
+    pub fn from_string(string: &str) -> ItemType {
+        match string {
+            "project" => ItemType::Project,
+            "version" => ItemType::Version,
+            "user" => ItemType::User,
+            "forum-post" => ItemType::ForumPost,
+            "issue-comment" => ItemType::IssueComment,
+            "wiki-page" => ItemType::WikiPage,
+            "thread-message" => ItemType::ThreadMessage,
+            _ => ItemType::Unknown,
+        }
+    }
+
+    /// 是否为可由版主直接隐藏或删除的用户内容
+    pub fn is_content(&self) -> bool {
+        matches!(
+            self,
+            ItemType::ForumPost
+                | ItemType::IssueComment
+                | ItemType::WikiPage
+                | ItemType::ThreadMessage
+        )
+    }
+}
+
+/// 被举报内容的快照
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReportedContent {
+    /// 所在帖子、Issue 或百科页面的标题
+    pub title: Option<String>,
+    pub body: String,
+    /// 内容所在的帖子、Issue、项目或讨论串 ID
+    pub parent_id: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// 版主对被举报内容的处理方式
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentAction {
+    /// 对外隐藏，保留原始内容
+    Hide,
+    /// 删除内容，原始内容只保留在举报快照中
+    Delete,
+}
+
+/// 同一内容的未关闭举报聚合
+#[derive(Serialize, Deserialize)]
+pub struct AggregatedReport {
+    pub item_type: ItemType,
+    pub item_id: String,
+    pub report_count: i64,
+    pub reporter_count: i64,
+    pub report_types: Vec<String>,
+    pub report_ids: Vec<ReportId>,
+    pub first_reported: DateTime<Utc>,
+    pub last_reported: DateTime<Utc>,
+    pub content_author: Option<UserId>,
+    /// 最近一条举报的内容快照
+    pub content: Option<ReportedContent>,
 }
 
 impl From<DBReport> for Report {
@@ -56,6 +132,18 @@ impl From<DBReport> for Report {
         } else if let Some(user_id) = x.user_id {
             item_id = UserId::from(user_id).to_string();
             item_type = ItemType::User;
+        } else if let Some(post_id) = x.post_id {
+            item_id = PostId::from(post_id).to_string();
+            item_type = ItemType::ForumPost;
+        } else if let Some(comment_id) = x.issue_comment_id {
+            item_id = IssuesCommentsId::from(comment_id).to_string();
+            item_type = ItemType::IssueComment;
+        } else if let Some(wiki_id) = x.wiki_id {
+            item_id = WikiId::from(wiki_id).to_string();
+            item_type = ItemType::WikiPage;
+        } else if let Some(message_id) = x.thread_message_id {
+            item_id = ThreadMessageId::from(message_id).to_string();
+            item_type = ItemType::ThreadMessage;
         }
 
         Report {
@@ -68,6 +156,36 @@ impl From<DBReport> for Report {
             created: x.created,
             closed: x.closed,
             thread_id: x.thread_id.into(),
+            content_author: x.content_author_id.map(UserId::from),
+            content: x
+                .content_snapshot
+                .and_then(|x| serde_json::from_value(x).ok()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_type_round_trips_with_serde_name() {
+        for item_type in [
+            ItemType::Project,
+            ItemType::Version,
+            ItemType::User,
+            ItemType::ForumPost,
+            ItemType::IssueComment,
+            ItemType::WikiPage,
+            ItemType::ThreadMessage,
+            ItemType::Unknown,
+        ] {
+            // 数据库 item_type 列与 API 使用同一组名称
+            assert_eq!(ItemType::from_string(item_type.as_str()), item_type);
+            assert_eq!(
+                serde_json::to_value(item_type).unwrap(),
+                serde_json::json!(item_type.as_str())
+            );
         }
     }
 }
@@ -0,0 +1,497 @@
+use crate::models::ids::{PayoutId, ProjectId, UserId};
+use crate::models::payouts::PayoutStatus;
+use crate::util::date::format_app_tz;
+use crate::util::pdf::{TextCell, TextPdf};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// 创作者月度收益对账单
+///
+/// 销售款由支付商户直接结算给创作者，只计入对账单的销售部分，不影响
+/// 平台代管的可提现余额；激励、广告收益、退款扣回与提现计入余额变动。
+/// 金额均为正数，方向由所在栏目表示。
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EarningsStatement {
+    pub user_id: UserId,
+    pub username: String,
+    /// 对账月份，格式 `YYYY-MM`
+    pub period: String,
+    pub start: DateTime<Utc>,
+    /// 下个月的开始时间，不含
+    pub end: DateTime<Utc>,
+    pub opening_balance: Decimal,
+    pub closing_balance: Decimal,
+    pub projects: Vec<StatementProject>,
+    pub refunds: Vec<StatementRefund>,
+    pub withdrawals: Vec<StatementWithdrawal>,
+    /// 期初迁移、账户合并等其他余额调整，可为负数
+    pub adjustments: Decimal,
+    pub totals: StatementTotals,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// 按项目汇总的收入
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct StatementProject {
+    pub project_id: Option<ProjectId>,
+    pub title: Option<String>,
+    pub sales_count: i64,
+    pub sales: Decimal,
+    pub platform_fee: Decimal,
+    /// 原路退款冲减的销售额
+    pub refunds: Decimal,
+    /// 平台垫付退款后从可提现余额中扣回的金额
+    pub clawback: Decimal,
+    pub incentive: Decimal,
+    pub ad_revenue: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatementRefund {
+    pub order_no: String,
+    pub project_id: Option<ProjectId>,
+    pub refunded_at: DateTime<Utc>,
+    pub refunds: Decimal,
+    pub clawback: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatementWithdrawal {
+    pub payout_id: PayoutId,
+    pub created: DateTime<Utc>,
+    pub status: PayoutStatus,
+    /// 占用的余额，含服务费
+    pub amount: Decimal,
+    pub service_fee: Decimal,
+    pub arrival_amount: Decimal,
+    /// 云账户 `calc_tax` 试算的个人承担税费，试算失败时为空
+    pub tax: Option<Decimal>,
+    pub after_tax_amount: Option<Decimal>,
+    /// 提现失败或被退回后返还的余额
+    pub returned: Decimal,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct StatementTotals {
+    pub sales_count: i64,
+    pub sales: Decimal,
+    pub platform_fee: Decimal,
+    pub refunds: Decimal,
+    pub clawback: Decimal,
+    pub incentive: Decimal,
+    pub ad_revenue: Decimal,
+    pub withdrawals: Decimal,
+    pub service_fees: Decimal,
+    pub tax: Decimal,
+    pub returned: Decimal,
+}
+
+impl StatementTotals {
+    pub fn from_parts(
+        projects: &[StatementProject],
+        withdrawals: &[StatementWithdrawal],
+    ) -> Self {
+        let mut totals = Self::default();
+        for p in projects {
+            totals.sales_count += p.sales_count;
+            totals.sales += p.sales;
+            totals.platform_fee += p.platform_fee;
+            totals.refunds += p.refunds;
+            totals.clawback += p.clawback;
+            totals.incentive += p.incentive;
+            totals.ad_revenue += p.ad_revenue;
+        }
+        for w in withdrawals {
+            totals.withdrawals += w.arrival_amount;
+            totals.service_fees += w.service_fee;
+            totals.tax += w.tax.unwrap_or_default();
+            totals.returned += w.returned;
+        }
+        totals
+    }
+}
+
+fn money(value: Decimal) -> String {
+    format!(
+        "{:.2}",
+        value.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+    )
+}
+
+/// CSV 字段转义：包含分隔符、引号或换行时加引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(out: &mut String, fields: &[&str]) {
+    let row = fields
+        .iter()
+        .map(|x| csv_field(x))
+        .collect::<Vec<_>>()
+        .join(",");
+    let _ = write!(out, "{row}\r\n");
+}
+
+/// 超出宽度的项目名称截断显示
+fn truncate(value: &str, max_chars: usize) -> String {
+    if value.chars().count() <= max_chars {
+        value.to_string()
+    } else {
+        let mut out: String = value.chars().take(max_chars - 1).collect();
+        out.push('…');
+        out
+    }
+}
+
+impl EarningsStatement {
+    fn project_name(&self, project: &StatementProject) -> String {
+        match (&project.title, project.project_id) {
+            (Some(title), _) => title.clone(),
+            (None, Some(id)) => id.to_string(),
+            (None, None) => "其他".to_string(),
+        }
+    }
+
+    fn balance_rows(&self) -> Vec<(&'static str, String)> {
+        let t = &self.totals;
+        vec![
+            ("期初余额", money(self.opening_balance)),
+            ("激励收益", format!("+{}", money(t.incentive))),
+            ("广告收益", format!("+{}", money(t.ad_revenue))),
+            ("退款扣回", format!("-{}", money(t.clawback))),
+            ("提现", format!("-{}", money(t.withdrawals))),
+            ("提现服务费", format!("-{}", money(t.service_fees))),
+            ("提现退回", format!("+{}", money(t.returned))),
+            ("其他调整", money(self.adjustments)),
+            ("期末余额", money(self.closing_balance)),
+        ]
+    }
+
+    /// 导出为 CSV，带 UTF-8 BOM 以便表格软件正确识别中文
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("\u{feff}");
+        let t = &self.totals;
+
+        csv_row(&mut out, &["收益对账单", &self.period]);
+        csv_row(&mut out, &["用户", &self.username]);
+        csv_row(
+            &mut out,
+            &[
+                "期间",
+                &format_app_tz(self.start),
+                &format_app_tz(self.end - Duration::seconds(1)),
+            ],
+        );
+        csv_row(&mut out, &["生成时间", &format_app_tz(self.generated_at)]);
+        out.push_str("\r\n");
+
+        csv_row(&mut out, &["项目收益"]);
+        csv_row(
+            &mut out,
+            &[
+                "项目ID",
+                "项目名称",
+                "销售笔数",
+                "销售额",
+                "平台服务费",
+                "退款",
+                "退款扣回",
+                "激励收益",
+                "广告收益",
+            ],
+        );
+        for p in &self.projects {
+            csv_row(
+                &mut out,
+                &[
+                    &p.project_id.map(|x| x.to_string()).unwrap_or_default(),
+                    &self.project_name(p),
+                    &p.sales_count.to_string(),
+                    &money(p.sales),
+                    &money(p.platform_fee),
+                    &money(p.refunds),
+                    &money(p.clawback),
+                    &money(p.incentive),
+                    &money(p.ad_revenue),
+                ],
+            );
+        }
+        csv_row(
+            &mut out,
+            &[
+                "合计",
+                "",
+                &t.sales_count.to_string(),
+                &money(t.sales),
+                &money(t.platform_fee),
+                &money(t.refunds),
+                &money(t.clawback),
+                &money(t.incentive),
+                &money(t.ad_revenue),
+            ],
+        );
+        out.push_str("\r\n");
+
+        csv_row(&mut out, &["退款明细"]);
+        csv_row(
+            &mut out,
+            &["订单号", "项目ID", "退款时间", "退款", "退款扣回"],
+        );
+        for r in &self.refunds {
+            csv_row(
+                &mut out,
+                &[
+                    &r.order_no,
+                    &r.project_id.map(|x| x.to_string()).unwrap_or_default(),
+                    &format_app_tz(r.refunded_at),
+                    &money(r.refunds),
+                    &money(r.clawback),
+                ],
+            );
+        }
+        out.push_str("\r\n");
+
+        csv_row(&mut out, &["提现明细"]);
+        csv_row(
+            &mut out,
+            &[
+                "提现ID",
+                "申请时间",
+                "状态",
+                "提现金额",
+                "服务费",
+                "到账金额",
+                "个人税费",
+                "税后金额",
+                "退回余额",
+                "备注",
+            ],
+        );
+        for w in &self.withdrawals {
+            csv_row(
+                &mut out,
+                &[
+                    &w.payout_id.to_string(),
+                    &format_app_tz(w.created),
+                    w.status.as_str(),
+                    &money(w.amount),
+                    &money(w.service_fee),
+                    &money(w.arrival_amount),
+                    &w.tax.map(money).unwrap_or_default(),
+                    &w.after_tax_amount.map(money).unwrap_or_default(),
+                    &money(w.returned),
+                    w.note.as_deref().unwrap_or_default(),
+                ],
+            );
+        }
+        out.push_str("\r\n");
+
+        csv_row(&mut out, &["余额变动"]);
+        for (label, value) in self.balance_rows() {
+            csv_row(&mut out, &[label, &value]);
+        }
+
+        out
+    }
+
+    /// 导出为 PDF
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut pdf = TextPdf::new();
+        let t = &self.totals;
+
+        pdf.line(&format!("BBSMC 创作者收益对账单 {}", self.period), 16.0);
+        pdf.line(&format!("用户：{}", self.username), 10.0);
+        pdf.line(
+            &format!(
+                "期间：{} 至 {}（北京时间）",
+                format_app_tz(self.start),
+                format_app_tz(self.end - Duration::seconds(1))
+            ),
+            10.0,
+        );
+        pdf.line(
+            &format!("生成时间：{}", format_app_tz(self.generated_at)),
+            10.0,
+        );
+        pdf.gap(8.0);
+
+        pdf.line("余额变动", 12.0);
+        pdf.rule();
+        for (label, value) in self.balance_rows() {
+            pdf.row(
+                &[
+                    TextCell {
+                        x: 0.0,
+                        text: label,
+                    },
+                    TextCell {
+                        x: 120.0,
+                        text: &value,
+                    },
+                ],
+                9.0,
+            );
+        }
+        pdf.gap(8.0);
+
+        const PROJECT_COLUMNS: [f32; 7] =
+            [0.0, 150.0, 210.0, 260.0, 310.0, 360.0, 420.0];
+        pdf.line("项目收益", 12.0);
+        pdf.line("销售款由支付商户直接结算，不计入可提现余额", 8.0);
+        pdf.rule();
+        let header =
+            ["项目", "销售额", "服务费", "退款", "扣回", "激励", "广告"];
+        pdf.row(
+            &PROJECT_COLUMNS
+                .iter()
+                .zip(header)
+                .map(|(x, text)| TextCell { x: *x, text })
+                .collect::<Vec<_>>(),
+            9.0,
+        );
+        let mut rows = self
+            .projects
+            .iter()
+            .map(|p| {
+                [
+                    truncate(&self.project_name(p), 14),
+                    money(p.sales),
+                    money(p.platform_fee),
+                    money(p.refunds),
+                    money(p.clawback),
+                    money(p.incentive),
+                    money(p.ad_revenue),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push([
+            "合计".to_string(),
+            money(t.sales),
+            money(t.platform_fee),
+            money(t.refunds),
+            money(t.clawback),
+            money(t.incentive),
+            money(t.ad_revenue),
+        ]);
+        for row in &rows {
+            pdf.row(
+                &PROJECT_COLUMNS
+                    .iter()
+                    .zip(row)
+                    .map(|(x, text)| TextCell { x: *x, text })
+                    .collect::<Vec<_>>(),
+                9.0,
+            );
+        }
+        pdf.gap(8.0);
+
+        if !self.refunds.is_empty() {
+            const REFUND_COLUMNS: [f32; 4] = [0.0, 170.0, 290.0, 370.0];
+            pdf.line("退款明细", 12.0);
+            pdf.rule();
+            pdf.row(
+                &REFUND_COLUMNS
+                    .iter()
+                    .zip(["订单号", "退款时间", "退款", "扣回"])
+                    .map(|(x, text)| TextCell { x: *x, text })
+                    .collect::<Vec<_>>(),
+                9.0,
+            );
+            for r in &self.refunds {
+                let row = [
+                    r.order_no.clone(),
+                    format_app_tz(r.refunded_at),
+                    money(r.refunds),
+                    money(r.clawback),
+                ];
+                pdf.row(
+                    &REFUND_COLUMNS
+                        .iter()
+                        .zip(&row)
+                        .map(|(x, text)| TextCell { x: *x, text })
+                        .collect::<Vec<_>>(),
+                    9.0,
+                );
+            }
+            pdf.gap(8.0);
+        }
+
+        if !self.withdrawals.is_empty() {
+            const WITHDRAWAL_COLUMNS: [f32; 7] =
+                [0.0, 100.0, 160.0, 220.0, 270.0, 330.0, 390.0];
+            pdf.line("提现明细", 12.0);
+            pdf.line("个人税费为云账户税费试算结果，以实际完税凭证为准", 8.0);
+            pdf.rule();
+            pdf.row(
+                &WITHDRAWAL_COLUMNS
+                    .iter()
+                    .zip([
+                        "申请日期",
+                        "状态",
+                        "提现金额",
+                        "服务费",
+                        "个人税费",
+                        "税后金额",
+                        "退回余额",
+                    ])
+                    .map(|(x, text)| TextCell { x: *x, text })
+                    .collect::<Vec<_>>(),
+                9.0,
+            );
+            for w in &self.withdrawals {
+                let row = [
+                    format_app_tz(w.created)[..10].to_string(),
+                    w.status.as_str().to_string(),
+                    money(w.amount),
+                    money(w.service_fee),
+                    w.tax.map(money).unwrap_or_else(|| "-".to_string()),
+                    w.after_tax_amount
+                        .map(money)
+                        .unwrap_or_else(|| "-".to_string()),
+                    money(w.returned),
+                ];
+                pdf.row(
+                    &WITHDRAWAL_COLUMNS
+                        .iter()
+                        .zip(&row)
+                        .map(|(x, text)| TextCell { x: *x, text })
+                        .collect::<Vec<_>>(),
+                    9.0,
+                );
+            }
+        }
+
+        pdf.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_escapes_special_characters() {
+        assert_eq!(csv_field("普通项目"), "普通项目");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn truncate_keeps_short_names() {
+        assert_eq!(truncate("短名称", 14), "短名称");
+        assert_eq!(truncate("一二三四五", 4), "一二三…");
+    }
+
+    #[test]
+    fn money_rounds_to_cents() {
+        assert_eq!(money(Decimal::new(12345, 3)), "12.35");
+        assert_eq!(money(Decimal::ZERO), "0.00");
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// 从地图、建筑模板与结构文件中读取到的信息，随版本文件保存
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StructureMetadata {
+    /// 文件的具体格式
+    pub format: StructureFormat,
+    /// 结构的尺寸；存档没有固定尺寸
+    pub size: Option<StructureSize>,
+    /// 非空气方块的数量
+    pub block_count: u64,
+    /// 方块实体（箱子、告示牌等）的数量
+    pub block_entity_count: u32,
+    /// 实体的数量
+    pub entity_count: u32,
+    /// 使用到的方块种类数（不区分方块状态）
+    pub block_types: u32,
+    /// 数量最多的方块，按数量降序排列
+    pub top_materials: Vec<MaterialCount>,
+    /// 文件保存时的数据版本（DataVersion），基岩版文件没有
+    pub data_version: Option<i32>,
+    /// 数据版本或存档版本对应的游戏版本
+    pub game_version: Option<String>,
+    /// 投影或存档的名称
+    pub name: Option<String>,
+    /// Litematica 投影中的选区名称
+    pub regions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StructureFormat {
+    /// WorldEdit 等使用的 Sponge `.schem`
+    SpongeSchematic,
+    /// MCEdit / Schematica 的旧版 `.schematic`
+    McEditSchematic,
+    /// Litematica 投影 `.litematic`
+    Litematic,
+    /// 原版结构方块导出的 `.nbt`
+    Structure,
+    /// 基岩版结构 `.mcstructure`
+    BedrockStructure,
+    /// 基岩版存档 `.mcworld` 与世界模板 `.mctemplate`
+    BedrockWorld,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StructureSize {
+    pub width: u32,
+    pub height: u32,
+    pub length: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MaterialCount {
+    pub name: String,
+    pub count: u64,
+}
@@ -77,6 +77,7 @@ pub enum ThreadType {
     BanAppeal,
     CreatorApplication,
     IncentiveApplication,
+    Refund,
 }
 
 impl std::fmt::Display for ThreadType {
@@ -96,6 +97,7 @@ impl ThreadType {
             ThreadType::BanAppeal => "ban_appeal",
             ThreadType::CreatorApplication => "creator_application",
             ThreadType::IncentiveApplication => "incentive_application",
+            ThreadType::Refund => "refund",
         }
     }
 
@@ -108,6 +110,7 @@ impl ThreadType {
             "ban_appeal" => ThreadType::BanAppeal,
             "creator_application" => ThreadType::CreatorApplication,
             "incentive_application" => ThreadType::IncentiveApplication,
+            "refund" => ThreadType::Refund,
             _ => ThreadType::DirectMessage,
         }
     }
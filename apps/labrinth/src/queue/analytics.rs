@@ -1,69 +1,169 @@
 use crate::database::models::DatabaseError;
 use crate::database::redis::RedisPool;
 use crate::models::analytics::{Download, PageView, Playtime};
+use crate::queue::stream::{EventBatch, EventStream};
 use crate::routes::ApiError;
-use dashmap::{DashMap, DashSet};
+use dashmap::DashMap;
 use redis::cmd;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 const DOWNLOADS_NAMESPACE: &str = "downloads";
 const VIEWS_NAMESPACE: &str = "views";
 
-pub struct AnalyticsQueue {
-    views_queue: DashMap<(u64, u64), Vec<PageView>>,
-    downloads_queue: DashMap<(u64, u64), Download>,
-    playtime_queue: DashSet<Playtime>,
+const EVENTS_STREAM: &str = "analytics_events";
+/// 每批最多处理的事件数
+const BATCH_SIZE: usize = 10_000;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnalyticsEvent {
+    View(PageView),
+    Download(Download),
+    Playtime(Playtime),
 }
 
-impl Default for AnalyticsQueue {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(clickhouse::Row, Deserialize)]
+struct EventId {
+    event_id: String,
 }
 
-// Batches analytics data points + transactions every few minutes
+pub struct AnalyticsQueue {
+    redis: RedisPool,
+    stream: EventStream,
+}
+
+// 事件先写入 Redis Stream，每隔几秒批量写入 ClickHouse，写入成功后才确认
 impl AnalyticsQueue {
-    pub fn new() -> Self {
+    pub fn new(redis: RedisPool) -> Self {
         AnalyticsQueue {
-            views_queue: DashMap::with_capacity(1000),
-            downloads_queue: DashMap::with_capacity(1000),
-            playtime_queue: DashSet::with_capacity(1000),
+            redis,
+            stream: EventStream::new(EVENTS_STREAM),
         }
     }
 
-    pub fn add_view(&self, page_view: PageView) {
-        let ip_stripped = crate::util::ip::strip_ip(page_view.ip);
-
-        self.views_queue
-            .entry((ip_stripped, page_view.project_id))
-            .or_default()
-            .push(page_view);
+    pub async fn add_view(&self, page_view: PageView) {
+        self.stream
+            .push_or_log(&self.redis, &[AnalyticsEvent::View(page_view)])
+            .await;
     }
-    pub fn add_download(&self, download: Download) {
-        let ip_stripped = crate::util::ip::strip_ip(download.ip);
-        self.downloads_queue
-            .insert((ip_stripped, download.project_id), download);
+
+    pub async fn add_download(&self, download: Download) {
+        self.stream
+            .push_or_log(&self.redis, &[AnalyticsEvent::Download(download)])
+            .await;
     }
 
-    pub fn add_playtime(&self, playtime: Playtime) {
-        self.playtime_queue.insert(playtime);
+    pub async fn add_playtimes(&self, playtimes: Vec<Playtime>) {
+        let events = playtimes
+            .into_iter()
+            .map(AnalyticsEvent::Playtime)
+            .collect::<Vec<_>>();
+        self.stream.push_or_log(&self.redis, &events).await;
     }
 
     pub async fn index(
         &self,
         client: clickhouse::Client,
-        redis: &RedisPool,
         pool: &PgPool,
     ) -> Result<(), ApiError> {
-        let views_queue = self.views_queue.clone();
-        self.views_queue.clear();
+        loop {
+            let batch = self
+                .stream
+                .read::<AnalyticsEvent>(&self.redis, BATCH_SIZE)
+                .await?;
+            if batch.events.is_empty() {
+                break;
+            }
+
+            let ids = batch
+                .events
+                .iter()
+                .map(|x| x.id.clone())
+                .collect::<Vec<_>>();
+
+            if let Err(err) = self.write_batch(batch, &client, pool).await {
+                self.stream.record_flush_failure();
+                self.stream.record_depth(&self.redis).await?;
+                return Err(err);
+            }
+
+            self.stream.ack(&self.redis, &ids).await?;
+        }
 
-        let downloads_queue = self.downloads_queue.clone();
-        self.downloads_queue.clear();
+        self.stream.record_depth(&self.redis).await?;
 
-        let playtime_queue = self.playtime_queue.clone();
-        self.playtime_queue.clear();
+        Ok(())
+    }
+
+    async fn write_batch(
+        &self,
+        batch: EventBatch<AnalyticsEvent>,
+        client: &clickhouse::Client,
+        pool: &PgPool,
+    ) -> Result<(), ApiError> {
+        let mut views_queue: HashMap<(u64, u64), Vec<PageView>> =
+            HashMap::new();
+        let mut downloads_queue: HashMap<(u64, u64), Download> = HashMap::new();
+        let mut playtime_queue = Vec::new();
+
+        for event in batch.events {
+            match event.event {
+                AnalyticsEvent::View(page_view) => {
+                    let ip_stripped = crate::util::ip::strip_ip(page_view.ip);
+                    views_queue
+                        .entry((ip_stripped, page_view.project_id))
+                        .or_default()
+                        .push(page_view);
+                }
+                AnalyticsEvent::Download(download) => {
+                    let ip_stripped = crate::util::ip::strip_ip(download.ip);
+                    downloads_queue
+                        .insert((ip_stripped, download.project_id), download);
+                }
+                AnalyticsEvent::Playtime(playtime) => {
+                    playtime_queue.push(playtime);
+                }
+            }
+        }
+
+        // 重放的批次可能已经写入过 ClickHouse，跳过已经存在的事件
+        if batch.redelivered {
+            let written = existing_event_ids(
+                client,
+                "playtime",
+                playtime_queue.iter().map(|x| x.event_id.clone()).collect(),
+            )
+            .await?;
+            playtime_queue.retain(|x| !written.contains(&x.event_id));
+
+            let written = existing_event_ids(
+                client,
+                "views",
+                views_queue
+                    .values()
+                    .flatten()
+                    .map(|x| x.event_id.clone())
+                    .collect(),
+            )
+            .await?;
+            for views in views_queue.values_mut() {
+                views.retain(|x| !written.contains(&x.event_id));
+            }
+            views_queue.retain(|_, views| !views.is_empty());
+
+            let written = existing_event_ids(
+                client,
+                "downloads",
+                downloads_queue
+                    .values()
+                    .map(|x| x.event_id.clone())
+                    .collect(),
+            )
+            .await?;
+            downloads_queue.retain(|_, x| !written.contains(&x.event_id));
+        }
 
         if !playtime_queue.is_empty() {
             let mut playtimes = client.insert::<Playtime>("playtime").await?;
@@ -84,8 +184,12 @@ impl AnalyticsQueue {
                 raw_views.push((views, true));
             }
 
-            let mut redis =
-                redis.pool.get().await.map_err(DatabaseError::RedisPool)?;
+            let mut redis = self
+                .redis
+                .pool
+                .get()
+                .await
+                .map_err(DatabaseError::RedisPool)?;
 
             let results = cmd("MGET")
                 .arg(
@@ -158,8 +262,12 @@ impl AnalyticsQueue {
                 raw_downloads.insert(index, download);
             }
 
-            let mut redis =
-                redis.pool.get().await.map_err(DatabaseError::RedisPool)?;
+            let mut redis = self
+                .redis
+                .pool
+                .get()
+                .await
+                .map_err(DatabaseError::RedisPool)?;
 
             let results = cmd("MGET")
                 .arg(
@@ -199,21 +307,47 @@ impl AnalyticsQueue {
                 .await
                 .map_err(DatabaseError::CacheError)?;
 
-            let mut transaction = pool.begin().await?;
             let mut downloads = client.insert::<Download>("downloads").await?;
+            for (_, download) in raw_downloads.iter().map(|x| x.pair()) {
+                downloads.write(download).await?;
+            }
+            downloads.end().await?;
+
+            let mut transaction = pool.begin().await?;
+
+            // 下载计数按事件 ID 去重，重放的批次不会重复计数
+            let counted = sqlx::query!(
+                "
+                INSERT INTO analytics_processed_events (event_id)
+                SELECT * FROM UNNEST($1::text[])
+                ON CONFLICT (event_id) DO NOTHING
+                RETURNING event_id
+                ",
+                &raw_downloads
+                    .iter()
+                    .map(|x| x.event_id.clone())
+                    .collect::<Vec<_>>()[..],
+            )
+            .fetch_all(&mut *transaction)
+            .await?
+            .into_iter()
+            .map(|x| x.event_id)
+            .collect::<HashSet<_>>();
 
             let mut version_downloads: HashMap<i64, i32> = HashMap::new();
             let mut project_downloads: HashMap<i64, i32> = HashMap::new();
 
             for (_, download) in raw_downloads {
+                if !counted.contains(&download.event_id) {
+                    continue;
+                }
+
                 *version_downloads
                     .entry(download.version_id as i64)
                     .or_default() += 1;
                 *project_downloads
                     .entry(download.project_id as i64)
                     .or_default() += 1;
-
-                downloads.write(&download).await?;
             }
 
             sqlx::query(
@@ -243,9 +377,48 @@ impl AnalyticsQueue {
             .await?;
 
             transaction.commit().await?;
-            downloads.end().await?;
         }
 
         Ok(())
     }
 }
+
+/// 已写入 ClickHouse 的事件 ID
+///
+/// 重放只会涉及一周内的事件（与 [`cleanup_processed_events`] 一致），按
+/// `recorded` 限定范围，避免全表扫描。
+async fn existing_event_ids(
+    client: &clickhouse::Client,
+    table: &str,
+    event_ids: Vec<String>,
+) -> Result<HashSet<String>, ApiError> {
+    if event_ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    Ok(client
+        .query(&format!("SELECT event_id FROM {table} WHERE recorded >= now() - INTERVAL 7 DAY AND event_id IN ?"))
+        .bind(event_ids)
+        .fetch_all::<EventId>()
+        .await?
+        .into_iter()
+        .map(|x| x.event_id)
+        .collect())
+}
+
+/// 清理一周前的下载计数去重记录；更早的事件不会再被重放
+pub async fn cleanup_processed_events(
+    pool: &PgPool,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query!(
+        "
+        DELETE FROM analytics_processed_events
+        WHERE processed_at < NOW() - INTERVAL '7 days'
+        ",
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(deleted)
+}
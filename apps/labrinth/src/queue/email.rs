@@ -0,0 +1,115 @@
+//! 异步邮件发送
+//!
+//! 请求处理中只把渲染好的邮件写入 `email_queue`，由定时任务领取后通过
+//! [`EmailTransport`] 发送。发送失败按指数退避重试，超过次数上限后标记为
+//! 失败；已完成的邮件保留一段时间供排查后删除。
+
+use crate::auth::email::MailError;
+use crate::auth::email::transport::{EmailTransport, build_message};
+use crate::database::models::DatabaseError;
+use crate::database::models::email_queue_item::EmailQueueItem;
+use chrono::{Duration, Utc};
+use log::warn;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// 每次领取的邮件数量
+const BATCH_SIZE: i64 = 50;
+/// 最多尝试发送的次数
+pub const MAX_ATTEMPTS: i32 = 8;
+/// 领取后超过该时长仍未完成的邮件会被重新领取
+const STALE_AFTER_MINUTES: i64 = 10;
+/// 已完成邮件的保留天数
+const RETENTION_DAYS: i64 = 7;
+
+/// 第 `attempts` 次发送失败后的重试间隔：1 分钟起逐次翻倍，最长 6 小时
+pub fn retry_delay(attempts: i32) -> Duration {
+    let minutes = 1i64 << attempts.clamp(1, 10).saturating_sub(1);
+    Duration::minutes(minutes.min(6 * 60))
+}
+
+pub struct EmailQueue {
+    transport: Arc<dyn EmailTransport>,
+}
+
+impl EmailQueue {
+    pub fn new(transport: Arc<dyn EmailTransport>) -> Self {
+        Self { transport }
+    }
+
+    /// 发送一批到期的邮件，返回发送成功的数量
+    pub async fn process(&self, pool: &PgPool) -> Result<usize, DatabaseError> {
+        let items = EmailQueueItem::claim_due(
+            BATCH_SIZE,
+            Duration::minutes(STALE_AFTER_MINUTES),
+            pool,
+        )
+        .await?;
+
+        let mut sent = 0;
+        for item in items {
+            match self.send(&item).await {
+                Ok(()) => {
+                    EmailQueueItem::mark_sent(item.id, pool).await?;
+                    sent += 1;
+                }
+                Err(e) => {
+                    let attempts = item.attempts + 1;
+                    let error = e.to_string();
+                    warn!(
+                        "邮件 {} ({}) 第 {} 次发送失败: {}",
+                        item.id, item.template, attempts, error
+                    );
+
+                    if attempts >= MAX_ATTEMPTS {
+                        EmailQueueItem::mark_failed(item.id, &error, pool)
+                            .await?;
+                    } else {
+                        EmailQueueItem::mark_retry(
+                            item.id,
+                            &error,
+                            Utc::now() + retry_delay(attempts),
+                            pool,
+                        )
+                        .await?;
+                    }
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    async fn send(&self, item: &EmailQueueItem) -> Result<(), MailError> {
+        let message = build_message(
+            &item.to_address,
+            &item.subject,
+            item.html_body.clone(),
+        )?;
+        self.transport.send(message).await
+    }
+}
+
+/// 删除超过保留期的已完成邮件
+pub async fn cleanup_finished(pool: &PgPool) -> Result<u64, DatabaseError> {
+    EmailQueueItem::remove_finished(
+        Utc::now() - Duration::days(RETENTION_DAYS),
+        pool,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off_exponentially_with_cap() {
+        assert_eq!(retry_delay(1), Duration::minutes(1));
+        assert_eq!(retry_delay(2), Duration::minutes(2));
+        assert_eq!(retry_delay(5), Duration::minutes(16));
+        assert_eq!(retry_delay(9), Duration::minutes(256));
+        assert_eq!(retry_delay(10), Duration::hours(6));
+        assert_eq!(retry_delay(100), Duration::hours(6));
+    }
+}
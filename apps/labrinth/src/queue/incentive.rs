@@ -1,21 +1,34 @@
+use crate::database::models::ids::{ProjectId, UserId};
+use crate::database::models::{
+    AuditLogBuilder, DatabaseError, LedgerAccount, LedgerEntryBuilder,
+    LedgerEntryKind, LedgerLine,
+};
+use crate::database::redis::RedisPool;
+use crate::models::analytics::new_event_id;
+use crate::queue::stream::EventStream;
 use crate::routes::ApiError;
-use dashmap::DashMap;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::net::Ipv6Addr;
 use std::str::FromStr;
 
 const WEEK_SECS: i64 = 7 * 86_400;
 
+const EVENTS_STREAM: &str = "incentive_events";
+const BATCH_SIZE: usize = 1_000;
+
 const TIER1_END: i64 = 1_000;
 const TIER2_END: i64 = 10_000;
 const RATE_TIER1: &str = "0.02";
 const RATE_TIER2: &str = "0.01";
 const RATE_TIER3: &str = "0.008";
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IncentiveEvent {
+    /// 事件 ID，重放时写入的事件明细按此去重
+    pub event_id: String,
     pub project_id: u64,
     pub user_id: u64,
     pub user_identity: Option<String>,
@@ -24,23 +37,19 @@ pub struct IncentiveEvent {
 }
 
 pub struct IncentiveQueue {
-    pending: DashMap<(u64, String, i64), IncentiveEvent>,
-}
-
-impl Default for IncentiveQueue {
-    fn default() -> Self {
-        Self::new()
-    }
+    redis: RedisPool,
+    stream: EventStream,
 }
 
 impl IncentiveQueue {
-    pub fn new() -> Self {
+    pub fn new(redis: RedisPool) -> Self {
         Self {
-            pending: DashMap::with_capacity(1024),
+            redis,
+            stream: EventStream::new(EVENTS_STREAM),
         }
     }
 
-    pub fn add(
+    pub async fn add(
         &self,
         project_id: u64,
         user_id: u64,
@@ -55,31 +64,55 @@ impl IncentiveQueue {
         let ip_identity = crate::util::ip::ip_to_identity_64(ip);
         let week_bucket = recorded_at_secs / WEEK_SECS;
 
-        let key = (project_id, ip_identity.clone(), week_bucket);
-        self.pending.entry(key).or_insert(IncentiveEvent {
+        let event = IncentiveEvent {
+            event_id: new_event_id(),
             project_id,
             user_id,
             user_identity,
             ip_identity,
             week_bucket,
-        });
+        };
+        self.stream.push_or_log(&self.redis, &[event]).await;
     }
 
+    /// 逐条处理事件，只确认处理成功的事件；失败的事件留在队列中稍后重试
     pub async fn index(&self, pool: &PgPool) -> Result<(), ApiError> {
-        let snapshot: Vec<IncentiveEvent> =
-            self.pending.iter().map(|e| e.value().clone()).collect();
-        self.pending.clear();
-
-        for evt in snapshot {
-            if let Err(e) = process_event(&evt, pool).await {
-                tracing::warn!(
-                    "incentive event failed (project {}): {:?}",
-                    evt.project_id,
-                    e
-                );
+        loop {
+            let batch = self
+                .stream
+                .read::<IncentiveEvent>(&self.redis, BATCH_SIZE)
+                .await?;
+            if batch.events.is_empty() {
+                break;
+            }
+
+            let mut processed = Vec::with_capacity(batch.events.len());
+            let mut failed = false;
+
+            for evt in batch.events {
+                match process_event(&evt.event, pool).await {
+                    Ok(()) => processed.push(evt.id),
+                    Err(e) => {
+                        failed = true;
+                        tracing::warn!(
+                            "incentive event failed (project {}): {:?}",
+                            evt.event.project_id,
+                            e
+                        );
+                    }
+                }
+            }
+
+            self.stream.ack(&self.redis, &processed).await?;
+
+            if failed {
+                self.stream.record_flush_failure();
+                break;
             }
         }
 
+        self.stream.record_depth(&self.redis).await?;
+
         Ok(())
     }
 }
@@ -166,8 +199,8 @@ async fn process_event(
     let inserted = sqlx::query!(
         "
         INSERT INTO incentive_download_events
-        (project_id, team_id, user_identity, ip_identity, week_bucket, payout_amount, status, split_snapshot)
-        VALUES ($1, $2, $3, $4, $5, $6, 'pending', $7)
+        (project_id, team_id, user_identity, ip_identity, week_bucket, payout_amount, status, split_snapshot, event_id)
+        VALUES ($1, $2, $3, $4, $5, $6, 'pending', $7, $8)
         ON CONFLICT DO NOTHING
         ",
         evt.project_id as i64,
@@ -177,6 +210,7 @@ async fn process_event(
         evt.week_bucket,
         payout,
         split_snapshot,
+        evt.event_id,
     )
     .execute(&mut *tx)
     .await?;
@@ -219,7 +253,7 @@ fn next_unit_payout(current_lifetime: i64) -> Decimal {
 }
 
 /// 7 天前 pending 的事件结算到 payouts_values，按事件发生时的 split 快照拆分
-pub async fn settle_pending(pool: &PgPool) -> Result<u64, sqlx::Error> {
+pub async fn settle_pending(pool: &PgPool) -> Result<u64, DatabaseError> {
     let events = sqlx::query!(
         "
         SELECT id, project_id, team_id, payout_amount, split_snapshot
@@ -299,12 +333,31 @@ pub async fn settle_pending(pool: &PgPool) -> Result<u64, sqlx::Error> {
             continue;
         }
 
-        // 按 split 比例拆分写入 payouts_values（使用事件快照）
+        // 按 split 比例拆分写入 payouts_values（使用事件快照），并在账本中记为激励计提
+        let mut entry = LedgerEntryBuilder::new(
+            LedgerEntryKind::IncentiveAccrual,
+            e.id.to_string(),
+            "下载激励结算",
+        );
         for m in &members {
             let frac = m.split / total_split;
             let frac_dec = Decimal::from_f64(frac).unwrap_or_default();
             let amount = e.payout_amount * frac_dec;
 
+            entry = entry
+                .debit(
+                    LedgerLine::platform(LedgerAccount::IncentiveExpense),
+                    amount,
+                )
+                .credit(
+                    LedgerLine::creator(
+                        LedgerAccount::CreatorPayable,
+                        UserId(m.user_id),
+                        Some(ProjectId(e.project_id)),
+                    ),
+                    amount,
+                );
+
             sqlx::query!(
                 "
                 INSERT INTO payouts_values (user_id, mod_id, amount, created, date_available)
@@ -317,6 +370,7 @@ pub async fn settle_pending(pool: &PgPool) -> Result<u64, sqlx::Error> {
             .execute(&mut *tx)
             .await?;
         }
+        entry.insert(&mut tx).await?;
 
         sqlx::query!(
             "
@@ -420,6 +474,9 @@ pub async fn detect_anomalies(pool: &PgPool) -> Result<u64, sqlx::Error> {
 }
 
 /// 写一条审计日志（操作可追溯）
+///
+/// 写入统一审计日志，动作名加 `incentive.` 前缀；`incentive_audit_log`
+/// 表只保留历史记录。
 pub async fn audit_log(
     pool: &PgPool,
     actor_user_id: Option<i64>,
@@ -427,20 +484,16 @@ pub async fn audit_log(
     target_type: &str,
     target_id: i64,
     metadata: Option<serde_json::Value>,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        "
-        INSERT INTO incentive_audit_log
-            (actor_user_id, action, target_type, target_id, metadata)
-        VALUES ($1, $2, $3, $4, $5)
-        ",
-        actor_user_id,
-        action,
-        target_type,
-        target_id,
-        metadata,
-    )
-    .execute(pool)
+) -> Result<(), DatabaseError> {
+    AuditLogBuilder {
+        actor_id: actor_user_id.map(UserId),
+        action: format!("incentive.{action}"),
+        target_type: target_type.to_string(),
+        target_id: Some(target_id),
+        after: metadata,
+        ..Default::default()
+    }
+    .record(pool)
     .await?;
     Ok(())
 }
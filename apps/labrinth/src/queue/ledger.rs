@@ -0,0 +1,365 @@
+//! 账本每日对账
+//!
+//! 对账范围：
+//! - 支付回调记录：近期已支付/已退款订单的金额与账本销售、退款分录一致，
+//!   账本中的销售分录都对应已支付的订单
+//! - 云账户：近期提交的提现订单通过 `query_order` 查询，状态与实付金额和本地
+//!   提现记录、账本提现分录一致
+//! - 旧收益表：按 `payouts_values` 与 `payouts` 计算的余额与账本余额一致
+//!
+//! 发现的差异写入 `ledger_reconciliation_issues`，由管理员在后台处理。
+
+use crate::database::models::ids::UserId;
+use crate::database::models::{
+    DatabaseError, LedgerAccount, LedgerEntry, LedgerEntryKind,
+    ReconciliationIssue, ReconciliationIssueKind,
+};
+use crate::models::payouts::PayoutStatus;
+use crate::util::yunzhanghu::{YzhClient, api as yzh_api, secrets};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// 订单对账回溯天数，覆盖退款申请期限
+const ORDER_WINDOW_DAYS: i64 = 30;
+/// 提现对账回溯天数
+const PAYOUT_WINDOW_DAYS: i64 = 7;
+/// 余额比对允许的舍入误差
+const BALANCE_TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// 执行一次对账，返回本次发现的差异数
+pub async fn reconcile(pool: &PgPool) -> Result<u64, DatabaseError> {
+    let mut issues = 0;
+
+    issues += reconcile_orders(pool).await?;
+
+    if secrets::load().is_ok() {
+        issues += reconcile_payouts(pool).await?;
+    } else {
+        log::warn!("云账户凭据未配置，跳过提现对账");
+    }
+
+    issues += reconcile_balances(pool).await?;
+
+    Ok(issues)
+}
+
+/// 已支付订单与账本销售、退款分录对账
+async fn reconcile_orders(pool: &PgPool) -> Result<u64, DatabaseError> {
+    let since = Utc::now() - Duration::days(ORDER_WINDOW_DAYS);
+    let mut issues = 0;
+
+    let orders = sqlx::query!(
+        r#"
+        SELECT o.order_no, o.seller_id, o.status, o.amount,
+               COALESCE(SUM(l.amount) FILTER (WHERE e.kind = 'sale'), 0) AS "sale_total!",
+               COALESCE(SUM(l.amount) FILTER (WHERE e.kind = 'refund'), 0) AS "refund_total!"
+        FROM payment_orders o
+        LEFT JOIN ledger_entries e
+            ON e.reference = o.order_no AND e.kind IN ('sale', 'refund')
+        LEFT JOIN ledger_lines l
+            ON l.entry_id = e.id AND l.account = 'payment_clearing'
+        WHERE o.status IN ('paid', 'refunded') AND o.paid_at >= $1
+        GROUP BY o.id
+        "#,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for order in orders {
+        let seller_id = Some(UserId(order.seller_id));
+
+        if order.sale_total != order.amount {
+            ReconciliationIssue::upsert(
+                ReconciliationIssueKind::OrderAmount,
+                &order.order_no,
+                seller_id,
+                Some(order.amount),
+                Some(order.sale_total),
+                "订单金额与账本销售分录不一致",
+                pool,
+            )
+            .await?;
+            issues += 1;
+        }
+
+        let expected_refund = if order.status == "refunded" {
+            -order.amount
+        } else {
+            Decimal::ZERO
+        };
+        if order.refund_total != expected_refund {
+            ReconciliationIssue::upsert(
+                ReconciliationIssueKind::OrderRefund,
+                &order.order_no,
+                seller_id,
+                Some(expected_refund),
+                Some(order.refund_total),
+                &format!("订单状态为 {}，账本退款分录金额不一致", order.status),
+                pool,
+            )
+            .await?;
+            issues += 1;
+        }
+    }
+
+    let orphans = sqlx::query!(
+        r#"
+        SELECT e.reference AS "reference!", SUM(l.amount) AS "total!",
+               MAX(o.status) AS status
+        FROM ledger_entries e
+        INNER JOIN ledger_lines l
+            ON l.entry_id = e.id AND l.account = 'payment_clearing'
+        LEFT JOIN payment_orders o ON o.order_no = e.reference
+        WHERE e.kind = 'sale' AND e.created_at >= $1
+              AND e.reference IS NOT NULL
+              AND (o.id IS NULL OR o.status NOT IN ('paid', 'refunded'))
+        GROUP BY e.reference
+        "#,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for orphan in orphans {
+        ReconciliationIssue::upsert(
+            ReconciliationIssueKind::OrderUnpaid,
+            &orphan.reference,
+            None,
+            Some(Decimal::ZERO),
+            Some(orphan.total),
+            &format!(
+                "账本存在销售分录，但订单状态为 {}",
+                orphan.status.as_deref().unwrap_or("不存在")
+            ),
+            pool,
+        )
+        .await?;
+        issues += 1;
+    }
+
+    Ok(issues)
+}
+
+/// 云账户提现订单与本地提现记录、账本提现分录对账
+async fn reconcile_payouts(pool: &PgPool) -> Result<u64, DatabaseError> {
+    let since = Utc::now() - Duration::days(PAYOUT_WINDOW_DAYS);
+    let mut issues = 0;
+
+    let payouts = sqlx::query!(
+        "
+        SELECT id, user_id, status, amount, yunzhanghu_order_id
+        FROM payouts
+        WHERE method = 'yunzhanghu_alipay'
+          AND (platform_id IS NOT NULL OR yunzhanghu_submit_started_at IS NOT NULL)
+          AND (created >= $1 OR status = 'in-transit')
+        ORDER BY created ASC
+        ",
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let client = YzhClient::new();
+
+    for payout in payouts {
+        let reference = payout.id.to_string();
+        let user_id = Some(UserId(payout.user_id));
+        let local_status = PayoutStatus::from_string(&payout.status);
+
+        // 账本中提现与服务费占用的余额（含冲正）
+        let withdrawn = LedgerEntry::get_reference_total(
+            &[LedgerEntryKind::Withdrawal, LedgerEntryKind::ServiceFee],
+            &reference,
+            LedgerAccount::CreatorPayable,
+            pool,
+        )
+        .await?;
+        let expected_withdrawn = match local_status {
+            PayoutStatus::Success | PayoutStatus::InTransit => payout.amount,
+            _ => Decimal::ZERO,
+        };
+        if withdrawn != expected_withdrawn {
+            ReconciliationIssue::upsert(
+                ReconciliationIssueKind::PayoutLedger,
+                &reference,
+                user_id,
+                Some(expected_withdrawn),
+                Some(withdrawn),
+                &format!(
+                    "提现状态为 {}，账本占用余额不一致",
+                    local_status.as_str()
+                ),
+                pool,
+            )
+            .await?;
+            issues += 1;
+        }
+
+        let order_id = payout.yunzhanghu_order_id.unwrap_or_else(|| {
+            format!(
+                "bbsmc-{}",
+                crate::models::ids::PayoutId::from(
+                    crate::database::models::PayoutId(payout.id)
+                )
+            )
+        });
+
+        let resp = match yzh_api::query_order(
+            &client,
+            &yzh_api::QueryOrderRequest {
+                order_id: &order_id,
+                channel: "支付宝",
+            },
+        )
+        .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                ReconciliationIssue::upsert(
+                    ReconciliationIssueKind::ProviderError,
+                    &reference,
+                    user_id,
+                    None,
+                    None,
+                    &format!("查询云账户订单 {} 失败: {}", order_id, e),
+                    pool,
+                )
+                .await?;
+                issues += 1;
+                continue;
+            }
+        };
+
+        let Some(remote_status) = yzh_api::map_order_status(&resp.status)
+        else {
+            ReconciliationIssue::upsert(
+                ReconciliationIssueKind::PayoutStatus,
+                &reference,
+                user_id,
+                None,
+                None,
+                &format!("未识别的云账户订单状态 {}", resp.status),
+                pool,
+            )
+            .await?;
+            issues += 1;
+            continue;
+        };
+
+        // 云账户仍在处理中时，本地状态由轮询任务同步，不视为差异
+        if remote_status != PayoutStatus::InTransit
+            && remote_status != local_status
+        {
+            ReconciliationIssue::upsert(
+                ReconciliationIssueKind::PayoutStatus,
+                &reference,
+                user_id,
+                None,
+                None,
+                &format!(
+                    "云账户订单状态为 {}，本地提现状态为 {}",
+                    remote_status.as_str(),
+                    local_status.as_str()
+                ),
+                pool,
+            )
+            .await?;
+            issues += 1;
+        }
+
+        if remote_status == PayoutStatus::Success {
+            let paid = -LedgerEntry::get_reference_total(
+                &[LedgerEntryKind::Withdrawal],
+                &reference,
+                LedgerAccount::PayoutClearing,
+                pool,
+            )
+            .await?;
+            let remote_pay = resp.pay.trim().parse::<Decimal>().ok();
+
+            if remote_pay != Some(paid) {
+                ReconciliationIssue::upsert(
+                    ReconciliationIssueKind::PayoutAmount,
+                    &reference,
+                    user_id,
+                    Some(paid),
+                    remote_pay,
+                    &format!(
+                        "云账户订单 {} 实付金额 {} 与账本打款金额不一致",
+                        order_id, resp.pay
+                    ),
+                    pool,
+                )
+                .await?;
+                issues += 1;
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+/// 账本余额与旧收益表计算的余额对账
+async fn reconcile_balances(pool: &PgPool) -> Result<u64, DatabaseError> {
+    let mut issues = 0;
+
+    let legacy = sqlx::query!(
+        r#"
+        SELECT user_id AS "user_id!", SUM(amount) AS "balance!"
+        FROM (
+            SELECT user_id, amount
+            FROM payouts_values
+            UNION ALL
+            SELECT user_id,
+                   -(amount + CASE
+                       WHEN method = 'yunzhanghu_alipay' THEN 0
+                       ELSE COALESCE(fee, 0)
+                   END)
+            FROM payouts
+            WHERE status IN ('success', 'in-transit')
+        ) balances
+        GROUP BY user_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut ledger: HashMap<UserId, Decimal> =
+        LedgerEntry::get_all_payable(pool)
+            .await?
+            .into_iter()
+            .collect();
+
+    let mut compare = Vec::new();
+    for row in legacy {
+        let user_id = UserId(row.user_id);
+        let actual = ledger.remove(&user_id).unwrap_or(Decimal::ZERO);
+        compare.push((user_id, row.balance, actual));
+    }
+    for (user_id, actual) in ledger {
+        compare.push((user_id, Decimal::ZERO, actual));
+    }
+
+    for (user_id, expected, actual) in compare {
+        if (expected - actual).abs() < BALANCE_TOLERANCE {
+            continue;
+        }
+
+        ReconciliationIssue::upsert(
+            ReconciliationIssueKind::CreatorBalance,
+            &user_id.0.to_string(),
+            Some(user_id),
+            Some(expected),
+            Some(actual),
+            "账本余额与收益记录、提现记录计算的余额不一致",
+            pool,
+        )
+        .await?;
+        issues += 1;
+    }
+
+    Ok(issues)
+}
@@ -1,6 +1,12 @@
 pub mod analytics;
+pub mod email;
 pub mod incentive;
+pub mod ledger;
 pub mod moderation;
+pub mod notifications;
 pub mod payouts;
+pub mod push;
 pub mod session;
 pub mod socket;
+pub mod statements;
+pub mod stream;
@@ -1,14 +1,18 @@
 use crate::auth::checks::filter_visible_versions;
 use crate::database;
+use crate::database::models::DatabaseError;
 use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::thread_item::ThreadMessageBuilder;
 use crate::database::redis::RedisPool;
 use crate::models::ids::ProjectId;
+use crate::models::malware::ScanFinding;
 use crate::models::notifications::NotificationBody;
 use crate::models::pack::{PackFile, PackFileHash, PackFormat};
-use crate::models::projects::ProjectStatus;
+use crate::models::projects::{ProjectStatus, VersionStatus};
 use crate::models::threads::MessageBody;
 use crate::routes::ApiError;
+use crate::validate::curseforge;
+use crate::validate::malware;
 use dashmap::DashSet;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -89,6 +93,10 @@ pub enum ModerationMessage {
     MissingCustomLicenseUrl {
         license: String,
     },
+    SuspiciousCode {
+        file_name: String,
+        findings: Vec<ScanFinding>,
+    },
 }
 
 impl ModerationMessage {
@@ -110,6 +118,8 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => true,
             ModerationMessage::MissingCustomLicenseUrl { .. } => true,
             ModerationMessage::NoSideTypes => true,
+            // 扫描结果可能误报，只暂缓发布对应的版本，由审核员决定
+            ModerationMessage::SuspiciousCode { .. } => false,
         }
     }
 
@@ -123,6 +133,19 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => false,
             ModerationMessage::MissingCustomLicenseUrl { .. } => false,
             ModerationMessage::NoSideTypes => false,
+            ModerationMessage::SuspiciousCode { findings, .. } => {
+                !malware::should_withhold(findings)
+            }
+        }
+    }
+
+    /// 版本是否需要暂缓发布，等待审核员复核
+    pub fn withholds_version(&self) -> bool {
+        match self {
+            ModerationMessage::SuspiciousCode { findings, .. } => {
+                malware::should_withhold(findings)
+            }
+            _ => false,
         }
     }
 
@@ -138,6 +161,7 @@ impl ModerationMessage {
                 "缺少许可证链接"
             }
             ModerationMessage::NoSideTypes => "缺少运行环境信息",
+            ModerationMessage::SuspiciousCode { .. } => "检测到可疑代码",
         }
     }
 
@@ -203,10 +227,75 @@ impl ModerationMessage {
             ModerationMessage::MissingLicense => "您的项目必须先选择一个许可证才能公开发布。设置许可证对于保护您的权益以及让他人按照您的意愿使用您的内容非常重要。更多信息请参阅[内容规则](https://bbsmc.net/legal/rules)。".to_string(),
             ModerationMessage::MissingCustomLicenseUrl { license } => format!("您选择了许可证 \"{license}\"，但未提供有效的许可证链接。使用自定义许可证时，您必须在许可证链接字段中提供指向该许可证的直接链接。"),
             ModerationMessage::NoSideTypes => "您的项目的运行环境目前两端均设置为「未知」。请设置准确的运行环境类型！".to_string(),
+            ModerationMessage::SuspiciousCode { file_name, findings } => {
+                let mut str = format!("自动扫描在文件 `{file_name}` 中发现了以下可疑代码：\n\n");
+
+                for finding in findings {
+                    str.push_str(&format!(
+                        "- [{}] {}：`{}`（{}）\n",
+                        finding.severity.as_friendly_str(),
+                        finding.rule.description(),
+                        finding.path,
+                        finding.detail,
+                    ));
+                }
+
+                if malware::should_withhold(findings) {
+                    str.push_str("\n此版本已暂缓发布，审核员复核后会恢复为您选择的状态。如果这些代码是正常功能的一部分，请在此说明其用途。\n");
+                } else {
+                    str.push_str("\n如果这些代码是正常功能的一部分，可以忽略此消息。\n");
+                }
+
+                str
+            }
         }
     }
 }
 
+/// 以 AutoMod 的身份在项目的审核讨论串中发送审核消息
+pub async fn send_automod_message(
+    thread_id: database::models::ThreadId,
+    messages: &ModerationMessages,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<database::models::ThreadMessageId, DatabaseError> {
+    ThreadMessageBuilder {
+        author_id: Some(database::models::UserId(AUTOMOD_ID)),
+        body: MessageBody::Text {
+            body: messages.markdown(true),
+            private: false,
+            replying_to: None,
+            associated_images: vec![],
+        },
+        thread_id,
+        hide_identity: false,
+    }
+    .insert(transaction)
+    .await
+}
+
+/// 暂缓发布版本：改为草稿，原来的状态记入 requested_status，由审核员复核后恢复
+pub async fn withhold_version<'a, E>(
+    version_id: database::models::VersionId,
+    exec: E,
+) -> Result<(), DatabaseError>
+where
+    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+{
+    sqlx::query!(
+        "
+        UPDATE versions
+        SET requested_status = status, status = $1
+        WHERE id = $2
+        ",
+        VersionStatus::Draft.as_str(),
+        version_id as database::models::VersionId,
+    )
+    .execute(exec)
+    .await?;
+
+    Ok(())
+}
+
 pub struct AutomatedModerationQueue {
     pub projects: DashSet<ProjectId>,
 }
@@ -271,6 +360,68 @@ impl AutomatedModerationQueue {
                                 mod_messages.messages.push(ModerationMessage::MissingGalleryImage);
                             }
 
+                            // 补扫上传时还没有扫描过的 jar 文件
+                            let unscanned_files = sqlx::query!(
+                                "
+                                SELECT f.id, f.url, f.filename, v.id version_id, v.version_number
+                                FROM files f
+                                INNER JOIN versions v ON v.id = f.version_id
+                                WHERE v.mod_id = $1 AND f.malware_findings IS NULL AND NOT f.is_private AND f.filename LIKE ANY($2)
+                                ",
+                                project.inner.id.0,
+                                &malware::SCANNED_EXTENSIONS.iter().map(|x| format!("%.{x}")).collect::<Vec<_>>()
+                            )
+                                .fetch_all(&pool)
+                                .await?;
+
+                            let mut withheld_versions = Vec::new();
+                            for file in unscanned_files {
+                                if !file.url.starts_with("http") {
+                                    continue;
+                                }
+
+                                let data = reqwest::get(&file.url).await?.bytes().await?;
+                                let findings = match malware::scan_jar_bytes(data).await {
+                                    Ok(findings) => findings,
+                                    Err(err) => {
+                                        log::warn!("扫描文件 {} 失败: {err}", file.id);
+                                        continue;
+                                    }
+                                };
+
+                                sqlx::query!(
+                                    "
+                                    UPDATE files
+                                    SET malware_findings = $1
+                                    WHERE id = $2
+                                    ",
+                                    serde_json::to_value(&findings)?,
+                                    file.id
+                                )
+                                    .execute(&pool)
+                                    .await?;
+
+                                if findings.is_empty() {
+                                    continue;
+                                }
+
+                                let message = ModerationMessage::SuspiciousCode { file_name: file.filename, findings };
+                                if message.withholds_version() {
+                                    withheld_versions.push(database::models::VersionId(file.version_id));
+                                }
+                                let val = mod_messages.version_specific.entry(file.version_number).or_default();
+                                val.push(message);
+                            }
+
+                            for version in database::Version::get_many(&withheld_versions.into_iter().unique().collect::<Vec<_>>(), &pool, &redis).await? {
+                                if version.inner.status == VersionStatus::Draft && version.inner.requested_status.is_some() {
+                                    continue;
+                                }
+
+                                withhold_version(version.inner.id, &pool).await?;
+                                database::models::Version::clear_cache(&version, &redis).await?;
+                            }
+
                             let versions =
                                 database::Version::get_many(&project.versions, &pool, &redis)
                                     .await?
@@ -307,18 +458,16 @@ impl AutomatedModerationQueue {
                                     let reader = Cursor::new(data);
                                     let mut zip = ZipArchive::new(reader)?;
 
-                                    let pack: PackFormat = {
-                                        let mut file =
-                                            if let Ok(file) = zip.by_name("modrinth.index.json") {
-                                                file
-                                            } else {
-                                                continue;
-                                            };
-
+                                    let pack_files: Vec<PackFile> = if let Ok(mut file) = zip.by_name("modrinth.index.json") {
                                         let mut contents = String::new();
                                         file.read_to_string(&mut contents)?;
 
-                                        serde_json::from_str(&contents)?
+                                        serde_json::from_str::<PackFormat>(&contents)?.files
+                                    } else if zip.by_name(curseforge::MANIFEST_FILE).is_ok() {
+                                        // CurseForge 整合包引用的文件由 CurseForge 分发，只需检查覆盖目录中打包的文件
+                                        Vec::new()
+                                    } else {
+                                        continue;
                                     };
 
                                     // sha1, pack file, file path, murmur
@@ -327,9 +476,7 @@ impl AutomatedModerationQueue {
                                         Option<PackFile>,
                                         String,
                                         Option<u32>,
-                                    )> = pack
-                                        .files
-                                        .clone()
+                                    )> = pack_files
                                         .into_iter()
                                         .flat_map(|x| {
                                             let hash = x.hashes.get(&PackFileHash::Sha1);
@@ -666,19 +813,7 @@ impl AutomatedModerationQueue {
                                     .unwrap_or(true);
 
                                 let mut transaction = pool.begin().await?;
-                                let id = ThreadMessageBuilder {
-                                    author_id: Some(database::models::UserId(AUTOMOD_ID)),
-                                    body: MessageBody::Text {
-                                        body: mod_messages.markdown(true),
-                                        private: false,
-                                        replying_to: None,
-                                        associated_images: vec![],
-                                    },
-                                    thread_id: project.thread_id,
-                                    hide_identity: false,
-                                }
-                                    .insert(&mut transaction)
-                                    .await?;
+                                let id = send_automod_message(project.thread_id, &mod_messages, &mut transaction).await?;
 
                                 let members = database::models::TeamMember::get_from_team_full(
                                     project.inner.team_id,
@@ -687,7 +822,7 @@ impl AutomatedModerationQueue {
                                 )
                                     .await?;
 
-                                if mod_messages.should_reject(first_time) {
+                                let push_events = if mod_messages.should_reject(first_time) {
                                     ThreadMessageBuilder {
                                         author_id: Some(database::models::UserId(AUTOMOD_ID)),
                                         body: MessageBody::StatusChange {
@@ -700,7 +835,7 @@ impl AutomatedModerationQueue {
                                         .insert(&mut transaction)
                                         .await?;
 
-                                    NotificationBuilder {
+                                    let push_events = NotificationBuilder {
                                         body: NotificationBody::StatusChange {
                                             project_id: project.inner.id.into(),
                                             old_status: project.inner.status,
@@ -739,6 +874,11 @@ impl AutomatedModerationQueue {
                                     )
                                         .execute(&pool)
                                         .await?;
+                                    database::models::SearchIndexQueueEntry::enqueue(
+                                        &[project.inner.id],
+                                        &pool,
+                                    )
+                                        .await?;
 
                                     database::models::Project::clear_cache(
                                         project.inner.id,
@@ -747,6 +887,8 @@ impl AutomatedModerationQueue {
                                         &redis,
                                     )
                                         .await?;
+
+                                    push_events
                                 } else {
                                     NotificationBuilder {
                                         body: NotificationBody::ModeratorMessage {
@@ -761,10 +903,11 @@ impl AutomatedModerationQueue {
                                             &mut transaction,
                                             &redis,
                                         )
-                                        .await?;
-                                }
+                                        .await?
+                                };
 
                                 transaction.commit().await?;
+                                crate::queue::push::publish(push_events, &redis).await;
                             }
 
                             Ok::<(), ApiError>(())
@@ -901,6 +1044,11 @@ pub struct FlameFile {
     pub mod_id: u32,
     pub hashes: Vec<FlameFileHash>,
     pub file_fingerprint: u32,
+    #[serde(default)]
+    pub file_name: String,
+    /// 作者关闭第三方分发时为空
+    #[serde(default)]
+    pub download_url: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -916,6 +1064,8 @@ pub struct FlameProject {
     pub name: String,
     pub slug: String,
     pub links: FlameLinks,
+    #[serde(default)]
+    pub class_id: Option<u32>,
 }
 
 #[derive(Deserialize, Serialize)]
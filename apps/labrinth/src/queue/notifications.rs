@@ -0,0 +1,270 @@
+//! 通知邮件投递
+//!
+//! 用户可按通知类型选择立即发送邮件或加入每日/每周摘要。立即发送的邮件在
+//! 创建通知的同一事务中入队；摘要由定时任务按用户汇总仍未读的通知后发送。
+//! 只向已验证的邮箱发送，每封邮件附带一键退订链接。
+
+use crate::auth::email::template::escape_html;
+use crate::auth::email::{DEFAULT_LOCALE, EmailTemplate, prepare_email};
+use crate::database::models::notification_item::Notification as DBNotification;
+use crate::database::models::notification_preference_item::{
+    DueDigest, NotificationPreference,
+};
+use crate::database::models::{DatabaseError, NotificationId, UserId};
+use crate::models::notifications::{Notification, NotificationBody};
+use chrono::Utc;
+use log::warn;
+use sqlx::PgPool;
+
+/// 每次处理的摘要数量
+const DIGEST_BATCH_SIZE: i64 = 200;
+/// 摘要邮件中最多列出的通知数量
+const DIGEST_MAX_ITEMS: usize = 20;
+
+fn site_url() -> Option<String> {
+    match dotenvy::var("SITE_URL") {
+        Ok(url) => Some(url.trim_end_matches('/').to_string()),
+        Err(e) => {
+            warn!("未配置 SITE_URL，跳过通知邮件: {}", e);
+            None
+        }
+    }
+}
+
+/// 站内通知链接转为站点完整链接
+fn absolute_link(site_url: &str, link: &str) -> String {
+    if link.starts_with("http://") || link.starts_with("https://") {
+        link.to_string()
+    } else if link.starts_with('/') {
+        format!("{site_url}{link}")
+    } else {
+        format!("{site_url}/notifications")
+    }
+}
+
+fn unsubscribe_link(
+    site_url: &str,
+    token: &str,
+    notification_type: Option<&str>,
+) -> String {
+    let path = dotenvy::var("SITE_NOTIFICATION_UNSUBSCRIBE_PATH")
+        .unwrap_or_else(|_| "settings/notifications/unsubscribe".to_string());
+    let mut link = format!("{site_url}/{path}?token={token}");
+    if let Some(notification_type) = notification_type {
+        link.push_str("&type=");
+        link.push_str(notification_type);
+    }
+    link
+}
+
+/// 通知的标题、正文与链接，与站内通知展示一致
+fn describe(user_id: UserId, body: &NotificationBody) -> Notification {
+    Notification::from(DBNotification {
+        id: NotificationId(0),
+        user_id,
+        body: body.clone(),
+        read: false,
+        created: Utc::now(),
+    })
+}
+
+/// 为选择立即发送邮件的用户加入邮件队列
+pub async fn enqueue_emails(
+    body: &NotificationBody,
+    users: &[UserId],
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), DatabaseError> {
+    if users.is_empty() {
+        return Ok(());
+    }
+    let Some(site_url) = site_url() else {
+        return Ok(());
+    };
+
+    let recipients =
+        NotificationPreference::get_verified_emails(users, &mut **transaction)
+            .await?;
+    if recipients.is_empty() {
+        return Ok(());
+    }
+    let tokens = NotificationPreference::get_unsubscribe_tokens(
+        &recipients.iter().map(|x| x.0).collect::<Vec<_>>(),
+        transaction,
+    )
+    .await?;
+
+    let notification_type = body.type_name();
+    for (user_id, email) in recipients {
+        let Some(token) = tokens.get(&user_id) else {
+            continue;
+        };
+        let notification = describe(user_id, body);
+
+        let email = prepare_email(
+            email,
+            EmailTemplate::Notification,
+            DEFAULT_LOCALE,
+            &[
+                ("title", &notification.name),
+                ("text", &notification.text),
+                ("link", &absolute_link(&site_url, &notification.link)),
+                (
+                    "unsubscribe_link",
+                    &unsubscribe_link(
+                        &site_url,
+                        token,
+                        Some(notification_type),
+                    ),
+                ),
+            ],
+        );
+        match email {
+            Ok(email) => {
+                email.insert(&mut **transaction).await?;
+            }
+            Err(e) => {
+                warn!("通知邮件生成失败 (用户 {}): {}", user_id.0, e)
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 发送到期的摘要邮件，返回发送的数量
+pub async fn send_digests(pool: &PgPool) -> Result<usize, DatabaseError> {
+    let Some(site_url) = site_url() else {
+        return Ok(0);
+    };
+
+    let due = DueDigest::get_due(DIGEST_BATCH_SIZE, pool).await?;
+
+    let mut sent = 0;
+    for digest in due {
+        let mut transaction = pool.begin().await?;
+
+        let unread = digest.take_unread(&mut transaction).await?;
+        if !unread.is_empty()
+            && enqueue_digest(
+                &site_url,
+                &digest,
+                &unread,
+                pool,
+                &mut transaction,
+            )
+            .await?
+        {
+            sent += 1;
+        }
+
+        digest.mark_sent(Utc::now(), &mut transaction).await?;
+        transaction.commit().await?;
+    }
+
+    Ok(sent)
+}
+
+async fn enqueue_digest(
+    site_url: &str,
+    digest: &DueDigest,
+    unread: &[NotificationId],
+    pool: &PgPool,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<bool, DatabaseError> {
+    let Some((_, email)) = NotificationPreference::get_verified_emails(
+        &[digest.user_id],
+        &mut **transaction,
+    )
+    .await?
+    .into_iter()
+    .next() else {
+        return Ok(false);
+    };
+
+    let notifications = DBNotification::get_many(unread, pool).await?;
+    if notifications.is_empty() {
+        return Ok(false);
+    }
+    let count = notifications.len();
+
+    let mut items_html = notifications
+        .into_iter()
+        .take(DIGEST_MAX_ITEMS)
+        .map(Notification::from)
+        .map(|x| {
+            format!(
+                "<a href=\"{}\">{}</a>：{}<br>",
+                escape_html(&absolute_link(site_url, &x.link)),
+                escape_html(&x.name),
+                escape_html(&x.text)
+            )
+        })
+        .collect::<String>();
+    if count > DIGEST_MAX_ITEMS {
+        items_html.push_str(&format!(
+            "以及其他 {} 条通知<br>",
+            count - DIGEST_MAX_ITEMS
+        ));
+    }
+
+    let token = NotificationPreference::get_unsubscribe_tokens(
+        &[digest.user_id],
+        transaction,
+    )
+    .await?
+    .remove(&digest.user_id)
+    .unwrap_or_default();
+
+    let email = prepare_email(
+        email,
+        EmailTemplate::NotificationDigest,
+        DEFAULT_LOCALE,
+        &[
+            ("count", &count.to_string()),
+            (
+                "period",
+                if digest.frequency == "weekly" {
+                    "过去一周"
+                } else {
+                    "过去一天"
+                },
+            ),
+            ("items_html", &items_html),
+            ("link", &format!("{site_url}/notifications")),
+            (
+                "unsubscribe_link",
+                &unsubscribe_link(site_url, &token, None),
+            ),
+        ],
+    );
+
+    match email {
+        Ok(email) => {
+            email.insert(&mut **transaction).await?;
+            Ok(true)
+        }
+        Err(e) => {
+            warn!("摘要邮件生成失败 (用户 {}): {}", digest.user_id.0, e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_links_are_made_absolute() {
+        let site = "https://bbsmc.net";
+        assert_eq!(
+            absolute_link(site, "/project/abc"),
+            "https://bbsmc.net/project/abc"
+        );
+        assert_eq!(absolute_link(site, "#"), "https://bbsmc.net/notifications");
+        assert_eq!(
+            absolute_link(site, "https://example.com/x"),
+            "https://example.com/x"
+        );
+    }
+}
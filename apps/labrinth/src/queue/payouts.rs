@@ -1,3 +1,8 @@
+use crate::database::models::ids::{ProjectId, UserId};
+use crate::database::models::{
+    DatabaseError, LedgerAccount, LedgerEntryBuilder, LedgerEntryKind,
+    LedgerLine,
+};
 use crate::models::projects::MonetizationStatus;
 use crate::routes::ApiError;
 use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
@@ -371,11 +376,60 @@ pub async fn process_payout(
     .execute(&mut *transaction)
     .await?;
 
+    record_ad_revenue(
+        start,
+        &insert_user_ids,
+        &insert_project_ids,
+        &insert_payouts,
+        &insert_availables,
+        &mut transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
 
     Ok(())
 }
 
+/// 在账本中记录一天的广告收益分成，收益在 `availables` 之后才可提现
+async fn record_ad_revenue(
+    start: DateTime<Utc>,
+    user_ids: &[i64],
+    project_ids: &[i64],
+    payouts: &[Decimal],
+    availables: &[DateTime<Utc>],
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), DatabaseError> {
+    let mut entry = LedgerEntryBuilder::new(
+        LedgerEntryKind::AdRevenue,
+        start.format("%Y-%m-%d").to_string(),
+        "广告收益分成",
+    );
+
+    for (((user_id, project_id), payout), available) in user_ids
+        .iter()
+        .zip(project_ids)
+        .zip(payouts)
+        .zip(availables)
+    {
+        entry = entry
+            .debit(LedgerLine::platform(LedgerAccount::AdRevenue), *payout)
+            .credit(
+                LedgerLine::creator(
+                    LedgerAccount::CreatorPayable,
+                    UserId(*user_id),
+                    Some(ProjectId(*project_id)),
+                )
+                .available_at(*available),
+                *payout,
+            );
+    }
+
+    entry.insert(transaction).await?;
+
+    Ok(())
+}
+
 // Used for testing, should be the same as the above function
 pub async fn insert_payouts(
     insert_user_ids: Vec<i64>,
@@ -384,8 +438,8 @@ pub async fn insert_payouts(
     insert_starts: Vec<DateTime<Utc>>,
     insert_availables: Vec<DateTime<Utc>>,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-) -> sqlx::Result<PgQueryResult> {
-    sqlx::query!(
+) -> Result<PgQueryResult, DatabaseError> {
+    let result = sqlx::query!(
         "
         INSERT INTO payouts_values (user_id, mod_id, amount, created, date_available)
         SELECT * FROM UNNEST ($1::bigint[], $2::bigint[], $3::numeric[], $4::timestamptz[], $5::timestamptz[])
@@ -397,5 +451,17 @@ pub async fn insert_payouts(
         &insert_availables[..],
     )
     .execute(&mut **transaction)
-    .await
+    .await?;
+
+    record_ad_revenue(
+        insert_starts.first().copied().unwrap_or_else(Utc::now),
+        &insert_user_ids,
+        &insert_project_ids,
+        &insert_payouts,
+        &insert_availables,
+        transaction,
+    )
+    .await?;
+
+    Ok(result)
 }
@@ -0,0 +1,294 @@
+//! 实时推送
+//!
+//! 每个用户的推送事件写入各自的 Redis Stream `push_events:{user_id}`，
+//! 同时通过 Redis 发布/订阅广播给所有实例；各实例的 [`PushHub`] 再分发给
+//! 本实例上该用户的 WebSocket 连接。Stream 只保留最近的事件，客户端重连
+//! 时带上最后收到的事件 ID，从 Stream 中补发断线期间的事件。
+//!
+//! 推送只是提醒，客户端收到后仍以 REST 接口的数据为准。
+
+use crate::database::models::{DatabaseError, UserId};
+use crate::database::redis::RedisPool;
+use crate::models::ids::{PayoutId, ThreadId, ThreadMessageId};
+use crate::models::notifications::Notification;
+use crate::models::payouts::PayoutStatus;
+use dashmap::DashMap;
+use futures::StreamExt;
+use log::{info, warn};
+use redis::cmd;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const CHANNEL: &str = "push_events";
+const EVENT_FIELD: &str = "event";
+/// 每个用户保留的事件数量
+const STREAM_MAX_LEN: usize = 200;
+/// 事件保留时长，超过后断线重连无法补发
+const STREAM_EXPIRY: i64 = 60 * 60 * 24;
+/// 订阅连接断开后的重连间隔
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// 每个连接最多积压的事件数量，超过后断开该连接，客户端重连时再补发
+const CONNECTION_BUFFER: usize = 64;
+
+fn stream_key(user_id: UserId) -> String {
+    format!("{CHANNEL}:{}", user_id.0)
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PushEventBody {
+    /// 新通知，包括论坛回复、审核结果、封禁等
+    Notification { notification: Notification },
+    /// 线程或私信会话中的新消息
+    ThreadMessage {
+        thread_id: ThreadId,
+        message_id: ThreadMessageId,
+    },
+    /// 提现状态变化
+    PayoutStatus {
+        payout_id: PayoutId,
+        status: PayoutStatus,
+    },
+}
+
+/// 待推送的事件及其接收用户
+pub type PushEvents = Vec<(UserId, PushEventBody)>;
+
+/// 发送给客户端的一条事件，`text` 为带有事件 ID 的 JSON
+#[derive(Clone)]
+pub struct PushFrame {
+    pub id: String,
+    pub text: Arc<str>,
+}
+
+/// 实例之间广播的消息
+#[derive(Serialize, Deserialize)]
+struct PushMessage {
+    user_id: UserId,
+    id: String,
+    text: String,
+}
+
+/// 事件 ID 即 Stream 条目 ID `{毫秒时间戳}-{序号}`
+pub fn parse_event_id(id: &str) -> Option<(u64, u64)> {
+    let (time, seq) = id.split_once('-')?;
+    Some((time.parse().ok()?, seq.parse().ok()?))
+}
+
+/// 在事件正文中加入事件 ID
+fn event_text(id: &str, mut body: serde_json::Value) -> String {
+    if let Some(object) = body.as_object_mut() {
+        object.insert("id".to_string(), id.into());
+    }
+    body.to_string()
+}
+
+/// 推送事件；推送失败不影响调用方，只记录日志
+///
+/// 必须在相关事务提交后调用，否则事务回滚时客户端会收到不存在的数据。
+pub async fn publish(events: PushEvents, redis: &RedisPool) {
+    if events.is_empty() {
+        return;
+    }
+    if let Err(e) = try_publish(events, redis).await {
+        warn!("实时推送失败: {:?}", e);
+    }
+}
+
+async fn try_publish(
+    events: PushEvents,
+    redis: &RedisPool,
+) -> Result<(), DatabaseError> {
+    let events = events
+        .into_iter()
+        .map(|(user_id, body)| Ok((user_id, serde_json::to_value(body)?)))
+        .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+    let mut redis = redis.pool.get().await?;
+
+    let mut pipe = redis::pipe();
+    for (user_id, body) in &events {
+        let key = stream_key(*user_id);
+        pipe.cmd("XADD")
+            .arg(&key)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(STREAM_MAX_LEN)
+            .arg("*")
+            .arg(EVENT_FIELD)
+            .arg(body.to_string());
+        pipe.cmd("EXPIRE").arg(&key).arg(STREAM_EXPIRY).ignore();
+    }
+    let ids: Vec<String> = pipe.query_async(&mut redis).await?;
+
+    let mut pipe = redis::pipe();
+    for ((user_id, body), id) in events.into_iter().zip(ids) {
+        let message = PushMessage {
+            user_id,
+            text: event_text(&id, body),
+            id,
+        };
+        pipe.cmd("PUBLISH")
+            .arg(CHANNEL)
+            .arg(serde_json::to_string(&message)?)
+            .ignore();
+    }
+    pipe.query_async::<()>(&mut redis).await?;
+
+    Ok(())
+}
+
+/// 读取 `after` 之后仍保留的事件
+pub async fn replay(
+    user_id: UserId,
+    after: &str,
+    redis: &RedisPool,
+) -> Result<Vec<PushFrame>, DatabaseError> {
+    if parse_event_id(after).is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut redis = redis.pool.get().await?;
+    let reply: redis::streams::StreamRangeReply = cmd("XRANGE")
+        .arg(stream_key(user_id))
+        .arg(format!("({after}"))
+        .arg("+")
+        .query_async(&mut redis)
+        .await?;
+
+    let frames = reply
+        .ids
+        .into_iter()
+        .filter_map(|entry| {
+            let body = entry.get::<String>(EVENT_FIELD)?;
+            let body = serde_json::from_str(&body).ok()?;
+            Some(PushFrame {
+                text: event_text(&entry.id, body).into(),
+                id: entry.id,
+            })
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+/// 本实例上的推送连接
+#[derive(Default)]
+pub struct PushHub {
+    connections: DashMap<UserId, Vec<(u64, mpsc::Sender<PushFrame>)>>,
+    next_id: AtomicU64,
+}
+
+impl PushHub {
+    /// 登记一个连接，返回连接 ID 与接收事件的通道
+    pub fn subscribe(
+        &self,
+        user_id: UserId,
+    ) -> (u64, mpsc::Receiver<PushFrame>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(CONNECTION_BUFFER);
+        self.connections
+            .entry(user_id)
+            .or_default()
+            .push((id, sender));
+        (id, receiver)
+    }
+
+    pub fn unsubscribe(&self, user_id: UserId, id: u64) {
+        self.connections.remove_if_mut(&user_id, |_, senders| {
+            senders.retain(|(x, _)| *x != id);
+            senders.is_empty()
+        });
+    }
+
+    /// 分发事件；连接已关闭或积压已满时移除该连接，丢弃发送端后
+    /// 连接处理完已积压的事件即断开，不会拖慢其他连接
+    fn dispatch(&self, user_id: UserId, frame: PushFrame) {
+        self.connections.remove_if_mut(&user_id, |_, senders| {
+            senders.retain(|(id, sender)| {
+                match sender.try_send(frame.clone()) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        warn!(
+                            "推送连接积压过多，断开连接 (用户 {}, 连接 {})",
+                            user_id.0, id
+                        );
+                        false
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                }
+            });
+            senders.is_empty()
+        });
+    }
+
+    /// 订阅其他实例广播的事件并分发给本实例的连接，断开后自动重连
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.listen().await {
+                warn!("实时推送订阅断开: {:?}", e);
+            }
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    }
+
+    async fn listen(&self) -> Result<(), redis::RedisError> {
+        let redis_url = dotenvy::var("REDIS_URL").unwrap_or_default();
+        let client = redis::Client::open(redis_url)?;
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.subscribe(CHANNEL).await?;
+        info!("已订阅实时推送频道");
+
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let payload = message.get_payload::<String>()?;
+            match serde_json::from_str::<PushMessage>(&payload) {
+                Ok(message) => self.dispatch(
+                    message.user_id,
+                    PushFrame {
+                        id: message.id,
+                        text: message.text.into(),
+                    },
+                ),
+                Err(e) => warn!("无法解析实时推送消息: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_ids_are_parsed_and_ordered() {
+        assert_eq!(parse_event_id("1712345678901-0"), Some((1712345678901, 0)));
+        assert!(
+            parse_event_id("1712345678901-2")
+                > parse_event_id("1712345678901-1")
+        );
+        assert!(
+            parse_event_id("1712345678902-0")
+                > parse_event_id("1712345678901-9")
+        );
+        assert_eq!(parse_event_id("1712345678901"), None);
+        assert_eq!(parse_event_id("abc-1"), None);
+        assert_eq!(parse_event_id(""), None);
+    }
+
+    #[test]
+    fn event_text_includes_id() {
+        let text = event_text(
+            "1-0",
+            serde_json::json!({ "type": "thread_message", "thread_id": "abc" }),
+        );
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["id"], "1-0");
+        assert_eq!(value["type"], "thread_message");
+    }
+}
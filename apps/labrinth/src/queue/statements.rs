@@ -0,0 +1,462 @@
+//! 创作者月度收益对账单
+//!
+//! 对账单按北京时间自然月从账本明细汇总生成，生成后归档到
+//! `creator_statements`。归档的对账单不随账本后续变动而改变，只有管理员
+//! 重新生成时才会覆盖。每月初由定时任务为上月有收益变动的创作者生成。
+
+use crate::database::models::ids::{PayoutId, ProjectId, UserId};
+use crate::database::models::yunzhanghu_profile_item::YunzhanghuProfile;
+use crate::database::models::{
+    CreatorStatement, DatabaseError, LedgerAccount, LedgerEntry,
+    LedgerEntryKind,
+};
+use crate::models::payouts::{PayoutMethodType, PayoutStatus};
+use crate::models::statements::{
+    EarningsStatement, StatementProject, StatementRefund, StatementTotals,
+    StatementWithdrawal,
+};
+use crate::util::date::app_tz;
+use crate::util::yunzhanghu::secrets;
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeZone, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+
+/// 解析 `YYYY-MM` 格式的对账月份，返回当月第一天
+pub fn parse_period(value: &str) -> Option<NaiveDate> {
+    let (year, month) = value.split_once('-')?;
+    if year.len() != 4 || month.len() != 2 {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)
+}
+
+/// 对账月份对应的时间范围（北京时间自然月），左闭右开
+pub fn period_range(period: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let to_utc = |date: NaiveDate| {
+        app_tz()
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+    let next = period + Months::new(1);
+    (to_utc(period), to_utc(next))
+}
+
+/// 已结束的最近一个对账月份
+pub fn last_closed_period(now: DateTime<Utc>) -> NaiveDate {
+    let today = now.with_timezone(&app_tz()).date_naive();
+    NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap()
+        - Months::new(1)
+}
+
+/// 对账月份是否已结束，未结束的月份不能生成对账单
+pub fn is_closed_period(period: NaiveDate, now: DateTime<Utc>) -> bool {
+    period <= last_closed_period(now)
+}
+
+/// 提现明细的累计值
+#[derive(Default)]
+struct WithdrawalTotals {
+    arrival_amount: Decimal,
+    service_fee: Decimal,
+    returned: Decimal,
+}
+
+/// 从账本汇总生成对账单，不写入归档
+pub async fn build_statement(
+    user_id: UserId,
+    period: NaiveDate,
+    pool: &PgPool,
+) -> Result<EarningsStatement, DatabaseError> {
+    let (start, end) = period_range(period);
+
+    let username = sqlx::query_scalar!(
+        "SELECT username FROM users WHERE id = $1",
+        user_id.0
+    )
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or_default();
+
+    let opening_balance =
+        LedgerEntry::get_payable_balance_at(user_id, start, pool).await?;
+    let closing_balance =
+        LedgerEntry::get_payable_balance_at(user_id, end, pool).await?;
+
+    let mut lines = LedgerEntry::get_user_lines(
+        user_id,
+        &[LedgerAccount::CreatorPayable, LedgerAccount::CreatorSales],
+        start,
+        end,
+        pool,
+    )
+    .await?;
+    lines.reverse();
+
+    let mut projects: HashMap<Option<ProjectId>, StatementProject> =
+        HashMap::new();
+    let mut sale_orders = HashSet::new();
+    let mut refunds: Vec<StatementRefund> = Vec::new();
+    let mut withdrawals: Vec<(PayoutId, WithdrawalTotals)> = Vec::new();
+    let mut adjustments = Decimal::ZERO;
+
+    for line in lines {
+        // 以创作者视角计：收入为正
+        let amount = -line.amount;
+        let is_reversal = line.kind == LedgerEntryKind::Reversal;
+        let kind = if is_reversal {
+            line.reversed_kind.unwrap_or(line.kind)
+        } else {
+            line.kind
+        };
+        let project = projects.entry(line.project_id).or_default();
+        let reference = line.reference.clone().unwrap_or_default();
+
+        // 提现与手续费分录的 reference 为提现 ID
+        let payout_id = reference.parse::<i64>().ok().map(PayoutId);
+
+        match (line.account, kind, payout_id) {
+            (LedgerAccount::CreatorSales, LedgerEntryKind::Sale, _) => {
+                project.sales += amount;
+                if !is_reversal
+                    && sale_orders.insert((line.project_id, reference))
+                {
+                    project.sales_count += 1;
+                }
+            }
+            (LedgerAccount::CreatorSales, LedgerEntryKind::PlatformFee, _) => {
+                project.platform_fee -= amount;
+            }
+            (LedgerAccount::CreatorSales, LedgerEntryKind::Refund, _)
+            | (LedgerAccount::CreatorPayable, LedgerEntryKind::Clawback, _) => {
+                let index = match refunds
+                    .iter()
+                    .position(|x| x.order_no == reference)
+                {
+                    Some(index) => index,
+                    None => {
+                        refunds.push(StatementRefund {
+                            order_no: reference,
+                            project_id: line.project_id.map(Into::into),
+                            refunded_at: line.created_at,
+                            refunds: Decimal::ZERO,
+                            clawback: Decimal::ZERO,
+                        });
+                        refunds.len() - 1
+                    }
+                };
+                let refund = &mut refunds[index];
+                if kind == LedgerEntryKind::Refund {
+                    project.refunds -= amount;
+                    refund.refunds -= amount;
+                } else {
+                    project.clawback -= amount;
+                    refund.clawback -= amount;
+                }
+            }
+            (
+                LedgerAccount::CreatorPayable,
+                LedgerEntryKind::IncentiveAccrual,
+                _,
+            ) => {
+                project.incentive += amount;
+            }
+            (LedgerAccount::CreatorPayable, LedgerEntryKind::AdRevenue, _) => {
+                project.ad_revenue += amount;
+            }
+            (
+                LedgerAccount::CreatorPayable,
+                LedgerEntryKind::Withdrawal | LedgerEntryKind::ServiceFee,
+                Some(payout_id),
+            ) => {
+                let index = match withdrawals
+                    .iter()
+                    .position(|(id, _)| *id == payout_id)
+                {
+                    Some(index) => index,
+                    None => {
+                        withdrawals
+                            .push((payout_id, WithdrawalTotals::default()));
+                        withdrawals.len() - 1
+                    }
+                };
+                let totals = &mut withdrawals[index].1;
+                if is_reversal {
+                    totals.returned += amount;
+                } else if kind == LedgerEntryKind::Withdrawal {
+                    totals.arrival_amount -= amount;
+                } else {
+                    totals.service_fee -= amount;
+                }
+            }
+            (LedgerAccount::CreatorPayable, _, _) => {
+                adjustments += amount;
+            }
+            _ => {}
+        }
+    }
+
+    let titles = project_titles(
+        projects.keys().filter_map(|x| x.map(|x| x.0)).collect(),
+        pool,
+    )
+    .await?;
+    let mut projects = projects
+        .into_iter()
+        .filter(|(_, p)| {
+            p.sales_count > 0
+                || !p.sales.is_zero()
+                || !p.platform_fee.is_zero()
+                || !p.refunds.is_zero()
+                || !p.clawback.is_zero()
+                || !p.incentive.is_zero()
+                || !p.ad_revenue.is_zero()
+        })
+        .map(|(id, mut p)| {
+            p.project_id = id.map(Into::into);
+            p.title = id.and_then(|id| titles.get(&id.0).cloned());
+            p
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| {
+        (b.sales + b.incentive + b.ad_revenue)
+            .cmp(&(a.sales + a.incentive + a.ad_revenue))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    let withdrawals = withdrawal_details(user_id, withdrawals, pool).await?;
+    let totals = StatementTotals::from_parts(&projects, &withdrawals);
+
+    Ok(EarningsStatement {
+        user_id: user_id.into(),
+        username,
+        period: period.format("%Y-%m").to_string(),
+        start,
+        end,
+        opening_balance,
+        closing_balance,
+        projects,
+        refunds,
+        withdrawals,
+        adjustments,
+        totals,
+        generated_at: Utc::now(),
+    })
+}
+
+async fn project_titles(
+    ids: Vec<i64>,
+    pool: &PgPool,
+) -> Result<HashMap<i64, String>, DatabaseError> {
+    let results =
+        sqlx::query!("SELECT id, name FROM mods WHERE id = ANY($1)", &ids[..],)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(results.into_iter().map(|x| (x.id, x.name)).collect())
+}
+
+/// 补充提现记录信息，并通过云账户税费试算计算个人承担的税费
+async fn withdrawal_details(
+    user_id: UserId,
+    withdrawals: Vec<(PayoutId, WithdrawalTotals)>,
+    pool: &PgPool,
+) -> Result<Vec<StatementWithdrawal>, DatabaseError> {
+    if withdrawals.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids = withdrawals.iter().map(|(id, _)| id.0).collect::<Vec<_>>();
+    let payouts = sqlx::query!(
+        "
+        SELECT id, created, status, method
+        FROM payouts
+        WHERE user_id = $1 AND id = ANY($2)
+        ",
+        user_id.0,
+        &ids[..],
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|x| (x.id, x))
+    .collect::<HashMap<_, _>>();
+
+    let can_calc_tax = secrets::load().is_ok();
+    let profile = YunzhanghuProfile::get(user_id, pool).await?;
+
+    let mut results = Vec::with_capacity(withdrawals.len());
+    for (payout_id, totals) in withdrawals {
+        let Some(payout) = payouts.get(&payout_id.0) else {
+            continue;
+        };
+        let status = PayoutStatus::from_string(&payout.status);
+        let amount = totals.arrival_amount + totals.service_fee;
+
+        let mut tax = None;
+        let mut after_tax_amount = None;
+        let mut note = None;
+        if payout.method.as_deref()
+            == Some(PayoutMethodType::YunzhanghuAlipay.as_str())
+            && matches!(status, PayoutStatus::Success | PayoutStatus::InTransit)
+            && amount > Decimal::ZERO
+        {
+            match (&profile, can_calc_tax) {
+                (_, false) => {
+                    note = Some("云账户未配置，未进行税费试算".to_string());
+                }
+                (None, true) => {
+                    note =
+                        Some("缺少云账户实名信息，未进行税费试算".to_string());
+                }
+                (Some(profile), true) => {
+                    match crate::routes::v3::payouts::quote_yunzhanghu_payout(
+                        amount, profile,
+                    )
+                    .await
+                    {
+                        Ok(quote) => {
+                            tax = quote.user_tax;
+                            after_tax_amount = quote.after_tax_amount;
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "对账单税费试算失败 user_id={} payout_id={}: {}",
+                                user_id.0,
+                                payout_id.0,
+                                e
+                            );
+                            note = Some(format!("税费试算失败：{}", e));
+                        }
+                    }
+                }
+            }
+        }
+
+        results.push(StatementWithdrawal {
+            payout_id: payout_id.into(),
+            created: payout.created,
+            status,
+            amount,
+            service_fee: totals.service_fee,
+            arrival_amount: totals.arrival_amount,
+            tax,
+            after_tax_amount,
+            returned: totals.returned,
+            note,
+        });
+    }
+    results.sort_by_key(|x| x.created);
+
+    Ok(results)
+}
+
+/// 生成并归档对账单；`overwrite` 为 false 时已归档的对账单保持不变
+///
+/// 返回是否写入了新的对账单
+pub async fn generate(
+    user_id: UserId,
+    period: NaiveDate,
+    overwrite: bool,
+    pool: &PgPool,
+) -> Result<bool, DatabaseError> {
+    if !overwrite
+        && CreatorStatement::get(user_id, period, pool)
+            .await?
+            .is_some()
+    {
+        return Ok(false);
+    }
+
+    let statement = build_statement(user_id, period, pool).await?;
+    let data = serde_json::to_value(&statement)
+        .map_err(|e| DatabaseError::SchemaError(e.to_string()))?;
+
+    CreatorStatement {
+        user_id,
+        period,
+        data,
+        generated_at: statement.generated_at,
+    }
+    .insert(overwrite, pool)
+    .await
+}
+
+/// 为指定月份有收益变动或仍有余额的创作者生成对账单，返回写入数量
+///
+/// `user_ids` 非空时只处理这些用户
+pub async fn generate_period(
+    period: NaiveDate,
+    user_ids: Option<&[UserId]>,
+    overwrite: bool,
+    pool: &PgPool,
+) -> Result<u64, DatabaseError> {
+    let (start, end) = period_range(period);
+    let users = match user_ids {
+        Some(user_ids) => user_ids.to_vec(),
+        None => LedgerEntry::get_active_creators(start, end, pool).await?,
+    };
+
+    let mut count = 0;
+    for user_id in users {
+        match generate(user_id, period, overwrite, pool).await {
+            Ok(true) => count += 1,
+            Ok(false) => {}
+            Err(e) => log::warn!(
+                "生成对账单失败 user_id={} period={}: {:?}",
+                user_id.0,
+                period,
+                e
+            ),
+        }
+    }
+
+    Ok(count)
+}
+
+/// 定时任务：为上个月生成尚未归档的对账单
+pub async fn generate_last_period(pool: &PgPool) -> Result<u64, DatabaseError> {
+    generate_period(last_closed_period(Utc::now()), None, false, pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_period_accepts_year_month() {
+        assert_eq!(
+            parse_period("2026-09"),
+            NaiveDate::from_ymd_opt(2026, 9, 1)
+        );
+        assert_eq!(parse_period("2026-13"), None);
+        assert_eq!(parse_period("2026-9"), None);
+        assert_eq!(parse_period("2026-09-01"), None);
+    }
+
+    #[test]
+    fn period_range_uses_beijing_month() {
+        let (start, end) =
+            period_range(NaiveDate::from_ymd_opt(2026, 12, 1).unwrap());
+        assert_eq!(
+            start,
+            Utc.with_ymd_and_hms(2026, 11, 30, 16, 0, 0).unwrap()
+        );
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 12, 31, 16, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn last_closed_period_follows_beijing_date() {
+        // 北京时间 10 月 1 日 02:00，上一个已结束的月份是 9 月
+        let now = Utc.with_ymd_and_hms(2026, 9, 30, 18, 0, 0).unwrap();
+        assert_eq!(
+            last_closed_period(now),
+            NaiveDate::from_ymd_opt(2026, 9, 1).unwrap()
+        );
+        assert!(!is_closed_period(
+            NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            now
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+//! 基于 Redis Stream 的持久化事件队列
+//!
+//! 事件写入 Stream 后才算入队，进程重启或崩溃不会丢失。队列通过消费者组保证至少一次投递：
+//! 一批事件写入成功后才确认并删除，未确认的事件会在启动后重放，其他实例遗留的事件在闲置
+//! [`CLAIM_IDLE_MS`] 后被认领。重放的事件可能已经写入过，写入端需要按事件 ID 去重。
+
+use crate::database::models::DatabaseError;
+use crate::database::redis::RedisPool;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts, Registry};
+use redis::cmd;
+use redis::streams::{StreamAutoClaimReply, StreamId, StreamReadReply};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const GROUP: &str = "labrinth";
+const EVENT_FIELD: &str = "event";
+
+/// 其他消费者的事件闲置超过此时间后视为该消费者已经退出
+const CLAIM_IDLE_MS: u64 = 5 * 60 * 1000;
+
+pub static QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    IntGaugeVec::new(
+        Opts::new("labrinth_event_queue_depth", "事件队列中尚未确认的事件数"),
+        &["queue"],
+    )
+    .unwrap()
+});
+
+pub static FLUSH_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "labrinth_event_queue_flush_failures_total",
+            "事件队列写入失败的批次数",
+        ),
+        &["queue"],
+    )
+    .unwrap()
+});
+
+pub static ENQUEUE_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "labrinth_event_queue_enqueue_failures_total",
+            "无法写入事件队列而丢失的事件数",
+        ),
+        &["queue"],
+    )
+    .unwrap()
+});
+
+/// 把事件队列的指标注册到 `/metrics` 使用的注册表
+pub fn register_metrics(registry: &Registry) -> prometheus::Result<()> {
+    registry.register(Box::new(QUEUE_DEPTH.clone()))?;
+    registry.register(Box::new(FLUSH_FAILURES.clone()))?;
+    registry.register(Box::new(ENQUEUE_FAILURES.clone()))?;
+    Ok(())
+}
+
+pub struct StreamEvent<T> {
+    /// Stream 条目 ID，用于确认
+    pub id: String,
+    pub event: T,
+}
+
+pub struct EventBatch<T> {
+    pub events: Vec<StreamEvent<T>>,
+    /// 这批事件之前投递过但没有确认，可能已经部分写入
+    pub redelivered: bool,
+}
+
+pub struct EventStream {
+    key: &'static str,
+    consumer: String,
+    group_created: AtomicBool,
+    replayed: AtomicBool,
+}
+
+impl EventStream {
+    pub fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            // 重启后沿用同一个消费者名称，才能直接取回上次未确认的事件
+            consumer: dotenvy::var("QUEUE_CONSUMER_NAME")
+                .unwrap_or_else(|_| "labrinth".to_string()),
+            group_created: AtomicBool::new(false),
+            replayed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.key
+    }
+
+    pub async fn push<T: Serialize>(
+        &self,
+        redis: &RedisPool,
+        events: &[T],
+    ) -> Result<(), DatabaseError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut redis = redis.pool.get().await?;
+
+        let mut pipe = redis::pipe();
+        for event in events {
+            pipe.cmd("XADD")
+                .arg(self.key)
+                .arg("*")
+                .arg(EVENT_FIELD)
+                .arg(serde_json::to_string(event)?)
+                .ignore();
+        }
+        pipe.query_async::<()>(&mut redis).await?;
+
+        Ok(())
+    }
+
+    /// 写入事件，失败时只记录日志与指标，不影响调用方的请求
+    pub async fn push_or_log<T: Serialize>(
+        &self,
+        redis: &RedisPool,
+        events: &[T],
+    ) {
+        if let Err(err) = self.push(redis, events).await {
+            ENQUEUE_FAILURES
+                .with_label_values(&[self.key])
+                .inc_by(events.len() as u64);
+            log::warn!("写入事件队列 {} 失败: {err}", self.key);
+        }
+    }
+
+    /// 读取一批事件：启动后先重放本消费者未确认的事件，再认领其他消费者遗留的事件，最后读取新事件
+    pub async fn read<T: DeserializeOwned>(
+        &self,
+        redis: &RedisPool,
+        count: usize,
+    ) -> Result<EventBatch<T>, DatabaseError> {
+        let mut redis = redis.pool.get().await?;
+
+        if !self.group_created.load(Ordering::Relaxed) {
+            let created = cmd("XGROUP")
+                .arg("CREATE")
+                .arg(self.key)
+                .arg(GROUP)
+                .arg("0")
+                .arg("MKSTREAM")
+                .query_async::<()>(&mut redis)
+                .await;
+            match created {
+                Ok(()) => {}
+                Err(err) if err.code() == Some("BUSYGROUP") => {}
+                Err(err) => return Err(err.into()),
+            }
+            self.group_created.store(true, Ordering::Relaxed);
+        }
+
+        if !self.replayed.load(Ordering::Relaxed) {
+            let entries = self.read_group(&mut redis, "0", count).await?;
+            if !entries.is_empty() {
+                return self.batch(&mut redis, entries, true).await;
+            }
+            self.replayed.store(true, Ordering::Relaxed);
+        }
+
+        let claimed = cmd("XAUTOCLAIM")
+            .arg(self.key)
+            .arg(GROUP)
+            .arg(&self.consumer)
+            .arg(CLAIM_IDLE_MS)
+            .arg("0-0")
+            .arg("COUNT")
+            .arg(count)
+            .query_async::<StreamAutoClaimReply>(&mut redis)
+            .await?;
+        if !claimed.claimed.is_empty() {
+            return self.batch(&mut redis, claimed.claimed, true).await;
+        }
+
+        let entries = self.read_group(&mut redis, ">", count).await?;
+        self.batch(&mut redis, entries, false).await
+    }
+
+    async fn read_group(
+        &self,
+        redis: &mut deadpool_redis::Connection,
+        id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, DatabaseError> {
+        let reply = cmd("XREADGROUP")
+            .arg("GROUP")
+            .arg(GROUP)
+            .arg(&self.consumer)
+            .arg("COUNT")
+            .arg(count)
+            .arg("STREAMS")
+            .arg(self.key)
+            .arg(id)
+            .query_async::<Option<StreamReadReply>>(redis)
+            .await?;
+
+        Ok(reply
+            .into_iter()
+            .flat_map(|x| x.keys)
+            .flat_map(|x| x.ids)
+            .collect())
+    }
+
+    /// 解析事件；无法解析的事件永远不会成功，直接确认丢弃
+    async fn batch<T: DeserializeOwned>(
+        &self,
+        redis: &mut deadpool_redis::Connection,
+        entries: Vec<StreamId>,
+        redelivered: bool,
+    ) -> Result<EventBatch<T>, DatabaseError> {
+        let mut events = Vec::with_capacity(entries.len());
+        let mut invalid = Vec::new();
+
+        for entry in entries {
+            let event = entry
+                .get::<String>(EVENT_FIELD)
+                .and_then(|x| serde_json::from_str(&x).ok());
+            match event {
+                Some(event) => events.push(StreamEvent {
+                    id: entry.id,
+                    event,
+                }),
+                None => {
+                    log::warn!(
+                        "丢弃事件队列 {} 中无法解析的事件 {}",
+                        self.key,
+                        entry.id
+                    );
+                    invalid.push(entry.id);
+                }
+            }
+        }
+
+        if !invalid.is_empty() {
+            self.ack_ids(redis, &invalid).await?;
+        }
+
+        Ok(EventBatch {
+            events,
+            redelivered,
+        })
+    }
+
+    /// 确认并删除已经写入的事件
+    pub async fn ack(
+        &self,
+        redis: &RedisPool,
+        ids: &[String],
+    ) -> Result<(), DatabaseError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut redis = redis.pool.get().await?;
+        self.ack_ids(&mut redis, ids).await
+    }
+
+    async fn ack_ids(
+        &self,
+        redis: &mut deadpool_redis::Connection,
+        ids: &[String],
+    ) -> Result<(), DatabaseError> {
+        redis::pipe()
+            .atomic()
+            .cmd("XACK")
+            .arg(self.key)
+            .arg(GROUP)
+            .arg(ids)
+            .ignore()
+            .cmd("XDEL")
+            .arg(self.key)
+            .arg(ids)
+            .ignore()
+            .query_async::<()>(redis)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 更新队列深度指标；确认的事件会被删除，所以 Stream 长度就是未处理的事件数
+    pub async fn record_depth(
+        &self,
+        redis: &RedisPool,
+    ) -> Result<(), DatabaseError> {
+        let mut redis = redis.pool.get().await?;
+        let depth = cmd("XLEN")
+            .arg(self.key)
+            .query_async::<i64>(&mut redis)
+            .await?;
+        QUEUE_DEPTH.with_label_values(&[self.key]).set(depth);
+
+        Ok(())
+    }
+
+    pub fn record_flush_failure(&self) {
+        FLUSH_FAILURES.with_label_values(&[self.key]).inc();
+    }
+}
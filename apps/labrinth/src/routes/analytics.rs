@@ -1,6 +1,6 @@
 use crate::auth::get_user_from_headers;
 use crate::database::redis::RedisPool;
-use crate::models::analytics::{PageView, Playtime};
+use crate::models::analytics::{PageView, Playtime, new_event_id};
 use crate::models::pats::Scopes;
 use crate::queue::analytics::AnalyticsQueue;
 use crate::queue::session::AuthQueue;
@@ -103,6 +103,7 @@ pub async fn page_view_ingest(
     .unwrap_or_else(|_| Ipv4Addr::new(127, 0, 0, 1).to_ipv6_mapped());
 
     let mut view = PageView {
+        event_id: new_event_id(),
         recorded: get_current_tenths_of_ms(),
         domain: domain.to_string(),
         site_path: url.path().to_string(),
@@ -152,7 +153,7 @@ pub async fn page_view_ingest(
         view.user_id = user.id.0;
     }
 
-    analytics_queue.add_view(view);
+    analytics_queue.add_view(view).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -200,6 +201,7 @@ pub async fn playtime_ingest(
     )
     .await?;
 
+    let mut events = Vec::with_capacity(playtimes.len());
     for (id, playtime) in playtimes {
         if playtime.seconds > 300 {
             continue;
@@ -207,7 +209,8 @@ pub async fn playtime_ingest(
 
         if let Some(version) = versions.iter().find(|x| id == x.inner.id.into())
         {
-            analytics_queue.add_playtime(Playtime {
+            events.push(Playtime {
+                event_id: new_event_id(),
                 recorded: get_current_tenths_of_ms(),
                 seconds: playtime.seconds as u64,
                 user_id: user.id.0,
@@ -220,5 +223,7 @@ pub async fn playtime_ingest(
         }
     }
 
+    analytics_queue.add_playtimes(events).await;
+
     Ok(HttpResponse::NoContent().finish())
 }
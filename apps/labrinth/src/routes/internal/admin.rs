@@ -1,7 +1,7 @@
 use crate::auth::check_is_admin_from_headers;
 use crate::auth::validate::get_user_record_from_bearer_token;
 use crate::database::redis::RedisPool;
-use crate::models::analytics::Download;
+use crate::models::analytics::{Download, new_event_id};
 use crate::models::ids::ProjectId;
 use crate::models::ids::base62_impl::{parse_base62, to_base62};
 use crate::models::pats::Scopes;
@@ -12,7 +12,9 @@ use crate::routes::ApiError;
 use crate::search::SearchConfig;
 use crate::util::date::get_current_tenths_of_ms;
 use crate::util::guards::admin_key_guard;
-use actix_web::{HttpRequest, HttpResponse, get, patch, post, web};
+use crate::util::ip::request_ip;
+use crate::util::ratelimit::{RateLimitSubject, RateLimits, find_policy};
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -29,7 +31,9 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(list_incentive_projects)
             .service(incentive_stats)
             .service(list_incentive_applications)
-            .service(review_incentive_application),
+            .service(review_incentive_application)
+            .service(get_rate_limit)
+            .service(reset_rate_limit),
     );
 }
 
@@ -127,36 +131,41 @@ pub async fn count_download(
         })
         .unwrap_or(0);
 
-    analytics_queue.add_download(Download {
-        recorded: get_current_tenths_of_ms(),
-        domain: url.host_str().unwrap_or_default().to_string(),
-        site_path: url.path().to_string(),
-        user_id,
-        project_id: project_id as u64,
-        version_id: version_id as u64,
-        ip,
-        country: String::new(), // MaxMind 功能已移除
-        user_agent: download_body
-            .headers
-            .get("user-agent")
-            .cloned()
-            .unwrap_or_default(),
-        headers: download_body
-            .headers
-            .clone()
-            .into_iter()
-            .filter(|x| {
-                !crate::routes::analytics::FILTERED_HEADERS
-                    .contains(&&*x.0.to_lowercase())
-            })
-            .collect(),
-    });
-    incentive_queue.add(
-        project_id as u64,
-        user_id,
-        ip,
-        chrono::Utc::now().timestamp(),
-    );
+    analytics_queue
+        .add_download(Download {
+            event_id: new_event_id(),
+            recorded: get_current_tenths_of_ms(),
+            domain: url.host_str().unwrap_or_default().to_string(),
+            site_path: url.path().to_string(),
+            user_id,
+            project_id: project_id as u64,
+            version_id: version_id as u64,
+            ip,
+            country: String::new(), // MaxMind 功能已移除
+            user_agent: download_body
+                .headers
+                .get("user-agent")
+                .cloned()
+                .unwrap_or_default(),
+            headers: download_body
+                .headers
+                .clone()
+                .into_iter()
+                .filter(|x| {
+                    !crate::routes::analytics::FILTERED_HEADERS
+                        .contains(&&*x.0.to_lowercase())
+                })
+                .collect(),
+        })
+        .await;
+    incentive_queue
+        .add(
+            project_id as u64,
+            user_id,
+            ip,
+            chrono::Utc::now().timestamp(),
+        )
+        .await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
@@ -167,9 +176,18 @@ pub async fn force_reindex(
     redis: web::Data<RedisPool>,
     config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
+    use crate::search::indexing::content::index_content;
     use crate::search::indexing::index_projects;
     let redis = redis.get_ref();
     index_projects(pool.as_ref().clone(), redis.clone(), &config).await?;
+    index_content(&pool, &config).await?;
+    crate::database::models::AuditLogBuilder {
+        action: "admin.force_reindex".to_string(),
+        target_type: "search_index".to_string(),
+        ..Default::default()
+    }
+    .record(&pool)
+    .await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -452,7 +470,28 @@ pub async fn fix_modpack_loaders(
     }
 
     if !body.dry_run {
-        // 12. 提交事务
+        // 12. 提交事务，同时把修改过的项目加入搜索索引队列
+        crate::database::models::SearchIndexQueueEntry::enqueue(
+            &project_ids_to_clear
+                .iter()
+                .map(|x| db_ids::ProjectId(*x))
+                .collect::<Vec<_>>(),
+            &mut *transaction,
+        )
+        .await?;
+        crate::database::models::AuditLogBuilder {
+            action: "admin.fix_modpack_loaders".to_string(),
+            target_type: "version".to_string(),
+            after: Some(serde_json::json!({
+                "project_id": body.project_id,
+                "version_ids": body.version_ids,
+                "fixed_count": result.fixed_count,
+                "skipped_count": result.skipped_count,
+            })),
+            ..Default::default()
+        }
+        .insert(&mut transaction)
+        .await?;
         transaction.commit().await?;
 
         // 13. 清除项目缓存
@@ -466,16 +505,10 @@ pub async fn fix_modpack_loaders(
             .await?;
         }
 
-        // 14. 触发重新索引（如果请求）
+        // 14. 立即处理索引队列（如果请求），否则由后台任务稍后处理
         if body.reindex && result.fixed_count > 0 {
-            use crate::search::indexing::index_projects;
-            let redis_ref = redis.get_ref();
-            index_projects(
-                pool.as_ref().clone(),
-                redis_ref.clone(),
-                &search_config,
-            )
-            .await?;
+            use crate::search::indexing::index_queued_projects;
+            index_queued_projects(&pool, &search_config).await?;
             result.reindexed = true;
             log::info!("已重新索引搜索");
         }
@@ -933,7 +966,7 @@ pub async fn review_incentive_application(
     }
 
     // 通知申请人
-    {
+    let push_events = {
         let project_title = sqlx::query!(
             "SELECT name FROM mods WHERE id = $1",
             pending.project_id,
@@ -978,10 +1011,11 @@ pub async fn review_incentive_application(
             &mut tx,
             &redis,
         )
-        .await?;
-    }
+        .await?
+    };
 
     tx.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     let _ = crate::queue::incentive::audit_log(
         pool.as_ref(),
@@ -1191,3 +1225,85 @@ pub async fn incentive_stats(
         top_projects,
     }))
 }
+
+// ==================== 请求频率限制 ====================
+
+#[derive(Deserialize)]
+pub struct RateLimitQuery {
+    /// 计数对象，如 `ip:1.2.3.4`、`user:{id}`、`oauth:{id}`
+    pub subject: String,
+    /// 只重置该策略的计数，未指定时重置所有策略
+    pub policy: Option<String>,
+}
+
+fn parse_rate_limit_subject(
+    subject: &str,
+) -> Result<RateLimitSubject, ApiError> {
+    RateLimitSubject::parse(subject)
+        .ok_or_else(|| ApiError::InvalidInput("无效的计数对象".to_string()))
+}
+
+/// 查看计数对象在各策略下的剩余次数
+#[get("ratelimit")]
+pub async fn get_rate_limit(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    rate_limits: web::Data<RateLimits>,
+    web::Query(query): web::Query<RateLimitQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_admin_from_headers(&req, &**pool, &redis, &session_queue, None)
+        .await?;
+
+    let subject = parse_rate_limit_subject(&query.subject)?;
+    let buckets = rate_limits.inspect(&subject).await?;
+
+    Ok(HttpResponse::Ok().json(buckets))
+}
+
+/// 重置计数对象的请求计数
+#[delete("ratelimit")]
+pub async fn reset_rate_limit(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    rate_limits: web::Data<RateLimits>,
+    web::Query(query): web::Query<RateLimitQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let user = check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        None,
+    )
+    .await?;
+
+    let subject = parse_rate_limit_subject(&query.subject)?;
+    let policy = match &query.policy {
+        Some(name) => Some(find_policy(name).ok_or_else(|| {
+            ApiError::InvalidInput("不存在该频率限制策略".to_string())
+        })?),
+        None => None,
+    };
+
+    rate_limits.reset(&subject, policy).await?;
+
+    crate::database::models::AuditLogBuilder {
+        actor_id: Some(user.id.into()),
+        action: "ratelimit.reset".to_string(),
+        target_type: "ratelimit".to_string(),
+        after: Some(serde_json::json!({
+            "subject": subject.to_string(),
+            "policy": policy.map(|x| x.name),
+        })),
+        ip: request_ip(&req),
+        ..Default::default()
+    }
+    .record(&pool)
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
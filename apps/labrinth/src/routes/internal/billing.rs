@@ -1,3 +1,4 @@
+use crate::auth::email::{DEFAULT_LOCALE, EmailTemplate};
 use crate::auth::{get_user_from_headers, send_email};
 use crate::database::models::charge_item::ChargeItem;
 use crate::database::models::{
@@ -1592,21 +1593,23 @@ pub async fn stripe_webhook(
                         );
 
                         let _ = send_email(
+                            &mut *transaction,
                             email,
-                            "BBSMC 支付失败通知",
-                            &format!(
-                                "我们尝试从您的绑定银行卡扣款 {money} 未成功。"
-                            ),
-                            "请点击下方链接更新您的支付方式，或联系银行卡发卡机构。如果按钮无法使用，您可以复制链接并粘贴到浏览器中。",
-                            Some((
-                                "更新账单设置",
-                                &format!(
-                                    "{}/{}",
-                                    dotenvy::var("SITE_URL")?,
-                                    dotenvy::var("SITE_BILLING_PATH")?
+                            EmailTemplate::PaymentFailed,
+                            DEFAULT_LOCALE,
+                            &[
+                                ("amount", &money.to_string()),
+                                (
+                                    "link",
+                                    &format!(
+                                        "{}/{}",
+                                        dotenvy::var("SITE_URL")?,
+                                        dotenvy::var("SITE_BILLING_PATH")?
+                                    ),
                                 ),
-                            )),
-                        );
+                            ],
+                        )
+                        .await;
                     }
 
                     transaction.commit().await?;
@@ -4,6 +4,7 @@
 
 use super::ApiError;
 use crate::auth::check_is_admin_from_headers;
+use crate::database::models::AuditLogBuilder;
 use crate::database::models::UserId as DBUserId;
 use crate::database::models::creator_application_item::{
     ApplicationStatus, CreatorApplication,
@@ -13,6 +14,7 @@ use crate::database::redis::RedisPool;
 use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
+use crate::util::ip::request_ip;
 use actix_web::{HttpRequest, HttpResponse, web};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -222,13 +224,32 @@ pub async fn approve_application(
     .await?;
 
     // 发送通知给申请用户
-    NotificationBuilder {
+    let push_events = NotificationBuilder {
         body: NotificationBody::CreatorApplicationApproved { application_id },
     }
     .insert(application.user_id, &mut transaction, &redis)
     .await?;
 
+    AuditLogBuilder {
+        actor_id: Some(reviewer_id),
+        action: "creator_application.approve".to_string(),
+        target_type: "creator_application".to_string(),
+        target_id: Some(application_id),
+        before: Some(serde_json::json!({
+            "user_id": application.user_id.0,
+            "status": application.status.as_str(),
+        })),
+        after: Some(serde_json::json!({
+            "status": ApplicationStatus::Approved.as_str(),
+        })),
+        reason: body.review_note.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
@@ -294,7 +315,7 @@ pub async fn reject_application(
     .await?;
 
     // 发送通知给申请用户
-    NotificationBuilder {
+    let push_events = NotificationBuilder {
         body: NotificationBody::CreatorApplicationRejected {
             application_id,
             reason: review_note.map(|s| s.to_string()),
@@ -303,7 +324,26 @@ pub async fn reject_application(
     .insert(application.user_id, &mut transaction, &redis)
     .await?;
 
+    AuditLogBuilder {
+        actor_id: Some(reviewer_id),
+        action: "creator_application.reject".to_string(),
+        target_type: "creator_application".to_string(),
+        target_id: Some(application_id),
+        before: Some(serde_json::json!({
+            "user_id": application.user_id.0,
+            "status": application.status.as_str(),
+        })),
+        after: Some(serde_json::json!({
+            "status": ApplicationStatus::Rejected.as_str(),
+        })),
+        reason: body.review_note.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
@@ -1,4 +1,4 @@
-use crate::auth::email::send_email;
+use crate::auth::email::{EmailTemplate, locale_from_request, send_email};
 use crate::auth::validate::get_user_record_from_bearer_token;
 use crate::auth::{AuthProvider, AuthenticationError, get_user_from_headers};
 use crate::database::models::flow_item::Flow;
@@ -17,6 +17,7 @@ use crate::util::env::parse_strings_from_var;
 use crate::util::ext::get_image_ext;
 use crate::util::img::upload_image_optimized;
 use crate::util::phone::send_phone_number_code;
+use crate::util::ratelimit::{LOGIN, RateLimit, SMS};
 use crate::util::validate::{RE_URL_SAFE, validation_errors_to_string};
 use actix_web::web::{Data, Payload, Query, ServiceConfig, scope};
 use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
@@ -1534,12 +1535,13 @@ pub async fn auth_callback(
 
                 if let Some(email) = user.and_then(|x| x.email) {
                     send_email(
+                        &mut *transaction,
                         email,
-                        "已添加身份验证方法",
-                        &format!("您现在可以使用 {} 身份验证提供程序登录 BBSMC。", provider.as_str()),
-                        "如果不是您进行的更改，请立即发送电子邮件 (support@bbsmc.net) 联系我们。",
-                        None,
-                    )?;
+                        EmailTemplate::AuthMethodAdded,
+                        locale_from_request(&req),
+                        &[("provider", provider.as_str())],
+                    )
+                    .await?;
                 }
 
                 transaction.commit().await?;
@@ -1732,15 +1734,13 @@ pub async fn delete_auth_provider(
 
     if let Some(email) = user.email {
         send_email(
+            &mut *transaction,
             email,
-            "身份验证方法已移除",
-            &format!(
-                "您现在无法使用 {} 身份验证提供程序登录 BBSMC",
-                delete_provider.provider.as_str()
-            ),
-            "如果不是您进行的更改，请立即通过电子邮件 (support@bbsmc.net) 联系我们。",
-            None,
-        )?;
+            EmailTemplate::AuthMethodRemoved,
+            locale_from_request(&req),
+            &[("provider", delete_provider.provider.as_str())],
+        )
+        .await?;
     }
 
     transaction.commit().await?;
@@ -1791,7 +1791,7 @@ pub struct NewAccount {
     pub sign_up_newsletter: Option<bool>,
 }
 
-#[post("create")]
+#[post("create", wrap = "RateLimit(&LOGIN)")]
 pub async fn create_account_with_password(
     req: HttpRequest,
     pool: Data<PgPool>,
@@ -1899,6 +1899,7 @@ pub async fn create_account_with_password(
     .insert(&mut transaction)
     .await?;
 
+    let locale = locale_from_request(&req);
     let session = issue_session(req, user_id, &mut transaction, &redis).await?;
     let res = crate::models::sessions::Session::from(session, true, None);
 
@@ -1910,10 +1911,13 @@ pub async fn create_account_with_password(
     .await?;
 
     send_email_verify(
+        &mut *transaction,
+        locale,
         new_account.email.clone(),
         flow,
-        &format!("欢迎加入 BBSMC 资源社区, {}!", new_account.username),
-    )?;
+        Some(&new_account.username),
+    )
+    .await?;
 
     if new_account.sign_up_newsletter.unwrap_or(false) {
         // sign_up_beehiiv(&new_account.email).await?;
@@ -1940,7 +1944,7 @@ pub struct Login {
 //     pub pass_token: String,
 // }
 
-#[post("login")]
+#[post("login", wrap = "RateLimit(&LOGIN)")]
 pub async fn login_password(
     req: HttpRequest,
     pool: Data<PgPool>,
@@ -2006,7 +2010,7 @@ pub struct PhoneNumberCode {
     pub challenge: String,
 }
 
-#[post("phone_number_code")]
+#[post("phone_number_code", wrap = "RateLimit(&SMS)")]
 pub async fn phone_number_code(
     req: HttpRequest,
     pool: Data<PgPool>,
@@ -2147,15 +2151,13 @@ pub async fn phone_number_bind(
 
     if let Some(user_email) = user.email {
         send_email(
+            &mut *transaction,
             user_email,
-            "手机号已绑定",
-            &format!(
-                "您的账户手机号已更新为 {}。",
-                phone_number_bind.phone_number
-            ),
-            "如果不是您进行的更改，请立即通过我们的电子邮件 (support@bbsmc.net) 联系我们。",
-            None,
-        )?;
+            EmailTemplate::PhoneChanged,
+            locale_from_request(&req),
+            &[("phone_number", &phone_number_bind.phone_number)],
+        )
+        .await?;
     }
 
     transaction.commit().await?;
@@ -2255,7 +2257,7 @@ async fn validate_2fa_code(
     }
 }
 
-#[post("login/2fa")]
+#[post("login/2fa", wrap = "RateLimit(&LOGIN)")]
 pub async fn login_2fa(
     req: HttpRequest,
     pool: Data<PgPool>,
@@ -2440,12 +2442,13 @@ pub async fn finish_2fa_flow(
 
         if let Some(email) = user.email {
             send_email(
+                &mut *transaction,
                 email,
-                "已启用双因素身份验证",
-                "登录 BBSMC 时，您现在可以在输入常用的电子邮件地址和密码后，输入由身份验证应用生成的代码。",
-                "如果不是您进行的更改，请立即通过我们的电子邮件(support@bbsmc.net)联系我们。",
-                None,
-            )?;
+                EmailTemplate::TwoFactorEnabled,
+                locale_from_request(&req),
+                &[],
+            )
+            .await?;
         }
 
         transaction.commit().await?;
@@ -2537,12 +2540,13 @@ pub async fn remove_2fa(
 
     if let Some(email) = user.email {
         send_email(
+            &mut *transaction,
             email,
-            "双因素身份验证已移除",
-            "登录 BBSMC 时，您不再需要双因素身份验证即可访问。",
-            "如果不是您进行的更改，请立即通过电子邮件 (support@bbsmc.net) 联系我们。",
-            None,
-        )?;
+            EmailTemplate::TwoFactorRemoved,
+            locale_from_request(&req),
+            &[],
+        )
+        .await?;
     }
 
     transaction.commit().await?;
@@ -2558,8 +2562,9 @@ pub struct ResetPassword {
     pub challenge: String,
 }
 
-#[post("password/reset")]
+#[post("password/reset", wrap = "RateLimit(&LOGIN)")]
 pub async fn reset_password_begin(
+    req: HttpRequest,
     pool: Data<PgPool>,
     redis: Data<RedisPool>,
     reset_password: web::Json<ResetPassword>,
@@ -2591,20 +2596,21 @@ pub async fn reset_password_begin(
 
         if let Some(email) = user.email {
             send_email(
+                &**pool,
                 email,
-                "重置您的密码",
-                "请访问以下链接以重置您的密码。如果按钮无法使用，您可以复制链接并将其粘贴到浏览器中。",
-                "如果您没有请求重置密码，您可以放心忽略此邮件。",
-                Some((
-                    "重置密码",
+                EmailTemplate::ResetPassword,
+                locale_from_request(&req),
+                &[(
+                    "link",
                     &format!(
                         "{}/{}?flow={}",
                         dotenvy::var("SITE_URL")?,
                         dotenvy::var("SITE_RESET_PASSWORD_PATH")?,
                         flow
                     ),
-                )),
-            )?;
+                )],
+            )
+            .await?;
         }
     }
 
@@ -2741,19 +2747,20 @@ pub async fn change_password(
     }
 
     if let Some(email) = user.email {
-        let changed = if update_password.is_some() {
-            "修改完成"
+        let template = if update_password.is_some() {
+            EmailTemplate::PasswordChanged
         } else {
-            "已删除"
+            EmailTemplate::PasswordRemoved
         };
 
         send_email(
+            &mut *transaction,
             email,
-            &format!("密码{}", changed),
-            &format!("您的账户密码已{}。", changed),
-            "如果不是您进行的更改，请立即通过我们的电子邮件 (support@bbsmc.net) 联系我们。",
-            None,
-        )?;
+            template,
+            locale_from_request(&req),
+            &[],
+        )
+        .await?;
     }
 
     transaction.commit().await?;
@@ -2807,12 +2814,13 @@ pub async fn set_email(
 
     if let Some(user_email) = user.email {
         send_email(
+            &mut *transaction,
             user_email,
-            "邮箱已更改",
-            &format!("您的账户邮箱已更新为 {}。", email.email),
-            "如果不是您进行的更改，请立即通过我们的电子邮件 (support@bbsmc.net) 联系我们。",
-            None,
-        )?;
+            EmailTemplate::EmailChanged,
+            locale_from_request(&req),
+            &[("email", &email.email)],
+        )
+        .await?;
     }
 
     let flow = Flow::ConfirmEmail {
@@ -2822,7 +2830,14 @@ pub async fn set_email(
     .insert(Duration::hours(24), &redis)
     .await?;
 
-    send_email_verify(email.email.clone(), flow, "我们需要验证您的邮箱地址。")?;
+    send_email_verify(
+        &mut *transaction,
+        locale_from_request(&req),
+        email.email.clone(),
+        flow,
+        None,
+    )
+    .await?;
 
     transaction.commit().await?;
     crate::database::models::User::clear_caches(
@@ -2834,7 +2849,7 @@ pub async fn set_email(
     Ok(HttpResponse::Ok().finish())
 }
 
-#[post("email/resend_verify")]
+#[post("email/resend_verify", wrap = "RateLimit(&SMS)")]
 pub async fn resend_verify_email(
     req: HttpRequest,
     pool: Data<PgPool>,
@@ -2863,7 +2878,14 @@ pub async fn resend_verify_email(
         .insert(Duration::hours(24), &redis)
         .await?;
 
-        send_email_verify(email, flow, "我们需要验证您的邮箱地址。")?;
+        send_email_verify(
+            &**pool,
+            locale_from_request(&req),
+            email,
+            flow,
+            None,
+        )
+        .await?;
 
         Ok(HttpResponse::NoContent().finish())
     } else {
@@ -2954,24 +2976,44 @@ pub async fn subscribe_newsletter(
     }
 }
 
-fn send_email_verify(
+/// 发送邮箱验证邮件；新注册的账户传入用户名，使用欢迎语
+async fn send_email_verify<'a, E>(
+    exec: E,
+    locale: &str,
     email: String,
     flow: String,
-    opener: &str,
-) -> Result<(), crate::auth::email::MailError> {
-    send_email(
-        email,
-        "验证您的邮箱",
-        opener,
-        "请点击下面的链接以验证您的邮箱。如果按钮无法使用，您可以复制链接并粘贴到浏览器中。该链接将在 24 小时后失效。",
-        Some((
-            "验证邮箱",
-            &format!(
-                "{}/{}?flow={}",
-                dotenvy::var("SITE_URL")?,
-                dotenvy::var("SITE_VERIFY_EMAIL_PATH")?,
-                flow
-            ),
-        )),
-    )
+    username: Option<&str>,
+) -> Result<(), crate::auth::email::MailError>
+where
+    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+{
+    let link = format!(
+        "{}/{}?flow={}",
+        dotenvy::var("SITE_URL")?,
+        dotenvy::var("SITE_VERIFY_EMAIL_PATH")?,
+        flow
+    );
+
+    match username {
+        Some(username) => {
+            send_email(
+                exec,
+                email,
+                EmailTemplate::WelcomeVerifyEmail,
+                locale,
+                &[("username", username), ("link", &link)],
+            )
+            .await
+        }
+        None => {
+            send_email(
+                exec,
+                email,
+                EmailTemplate::VerifyEmail,
+                locale,
+                &[("link", &link)],
+            )
+            .await
+        }
+    }
 }
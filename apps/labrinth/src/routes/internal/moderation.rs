@@ -6,6 +6,7 @@ use crate::models::ids::random_base62;
 use crate::models::projects::ProjectStatus;
 use crate::queue::moderation::{ApprovalType, IdentifiedFile, MissingMetadata};
 use crate::queue::session::AuthQueue;
+use crate::util::ip::request_ip;
 use crate::{auth::check_is_moderator_from_headers, models::pats::Scopes};
 use actix_web::{HttpRequest, HttpResponse, web};
 use chrono::{DateTime, Duration, Utc};
@@ -281,7 +282,7 @@ pub async fn set_project_meta(
     session_queue: web::Data<AuthQueue>,
     judgements: web::Json<HashMap<String, Judgement>>,
 ) -> Result<HttpResponse, ApiError> {
-    check_is_moderator_from_headers(
+    let user = check_is_moderator_from_headers(
         &req,
         &**pool,
         &redis,
@@ -362,6 +363,28 @@ pub async fn set_project_meta(
     .execute(&mut *transaction)
     .await?;
 
+    for (i, id) in ids.iter().enumerate() {
+        database::models::AuditLogBuilder {
+            actor_id: Some(user.id.into()),
+            action: "moderation.external_license".to_string(),
+            target_type: "external_license".to_string(),
+            target_id: Some(*id),
+            before: None,
+            after: Some(serde_json::json!({
+                "sha1": file_hashes[i],
+                "title": titles[i],
+                "status": statuses[i],
+                "link": links[i],
+                "proof": proofs[i],
+                "flame_project_id": flame_ids[i],
+            })),
+            reason: None,
+            ip: request_ip(&req),
+        }
+        .insert(&mut transaction)
+        .await?;
+    }
+
     transaction.commit().await?;
 
     Ok(HttpResponse::NoContent().finish())
@@ -550,6 +573,9 @@ pub struct ModerationPendingCounts {
     pub incentive_applications: i64,
     #[serde(default)]
     pub payout_transfers: i64,
+    /// 未处理的账本对账差异，仅管理员可见
+    #[serde(default)]
+    pub ledger_issues: i64,
 }
 
 pub(crate) const PENDING_COUNTS_NAMESPACE: &str = "moderation_pending_counts";
@@ -632,6 +658,21 @@ pub async fn get_pending_counts(
         0
     };
 
+    let ledger_issues = if user.role.is_admin() {
+        sqlx::query_scalar!(
+            "
+            SELECT COUNT(*)
+            FROM ledger_reconciliation_issues
+            WHERE resolved_at IS NULL
+            "
+        )
+        .fetch_one(&**pool)
+        .await?
+        .unwrap_or(0)
+    } else {
+        0
+    };
+
     let result = ModerationPendingCounts {
         projects: counts.projects,
         reports: counts.reports,
@@ -641,6 +682,7 @@ pub async fn get_pending_counts(
         creator_applications: counts.creator_applications,
         incentive_applications,
         payout_transfers,
+        ledger_issues,
     };
 
     redis_conn
@@ -1,20 +1,25 @@
 //! 支付回调路由（内部 API）
 //!
 //! 接收来自支付平台的回调通知。
-//! 验证签名后更新订单状态并创建购买记录。
+//! 验证签名后更新订单状态并为订单中的每个项目创建购买记录；退款通知会撤销对应的购买授权。
 
 use actix_web::{HttpRequest, HttpResponse, post, web};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::BTreeMap;
 use subtle::ConstantTimeEq;
 
-use crate::database::models::ids::{ProjectId, UserId};
+use crate::database::models::generate_payment_refund_id;
 use crate::database::models::payment_order_item::{OrderStatus, PaymentOrder};
+use crate::database::models::payment_refund_item::PaymentRefund;
 use crate::database::models::user_purchase_item::UserPurchase;
 use crate::database::redis::RedisPool;
+use crate::models::refunds::{RefundSource, RefundStatus};
 use crate::routes::ApiError;
+use crate::routes::v3::project_order::grant_order_purchases;
+use crate::routes::v3::refunds::complete_refund;
+use rust_decimal::Decimal;
 
 /// 验证请求 IP 是否在白名单中
 fn verify_ip_whitelist(req: &HttpRequest) -> Result<(), String> {
@@ -214,6 +219,35 @@ pub async fn payment_callback(
 
     log::info!("支付回调签名验证成功: order_id={}", data.order_id);
 
+    // 支付平台发起的退款（买家拒付或平台客服退款）
+    if data.trade_state == "REFUND" {
+        return match process_refund_callback(
+            &data.other_order_no,
+            &data.order_id,
+            &pool,
+            &redis,
+        )
+        .await
+        {
+            Ok(_) => Ok(HttpResponse::Ok().json(CallbackResponse {
+                code: 200,
+                message: "success".to_string(),
+            })),
+            Err(e) => {
+                log::error!(
+                    "退款回调处理失败: order_id={}, other_order_no={}, error={}",
+                    data.order_id,
+                    data.other_order_no,
+                    e
+                );
+                Ok(HttpResponse::Ok().json(CallbackResponse {
+                    code: 500,
+                    message: e,
+                }))
+            }
+        };
+    }
+
     // 检查交易状态
     if data.trade_state != "SUCCESS" {
         log::warn!(
@@ -266,7 +300,7 @@ pub async fn payment_callback(
 }
 
 /// 验证支付签名
-fn verify_payment_signature(
+pub(crate) fn verify_payment_signature(
     data: &PaymentCallbackData,
     sign: &str,
     keycode: &str,
@@ -359,45 +393,36 @@ async fn process_payment_callback(
 
     log::info!("订单状态已更新为已支付: order_no={}", order_no);
 
-    // 6. 计算购买有效期
-    let expires_at = paid_order
-        .validity_days
-        .map(|days| Utc::now() + Duration::days(days as i64));
-
-    // 7. 创建用户购买记录
-    let purchase = UserPurchase::create(
-        paid_order.user_id,
-        paid_order.project_id,
-        Some(order_no.to_string()),
-        paid_order.amount,
-        expires_at,
-        &mut transaction,
-    )
-    .await
-    .map_err(|e| format!("创建购买记录失败: {}", e))?;
+    // 6. 为订单中的每个项目创建用户购买记录（赠送订单授予受赠用户）
+    let (project_ids, push_events) =
+        grant_order_purchases(&paid_order, &mut transaction, redis)
+            .await
+            .map_err(|e| format!("创建购买记录失败: {}", e))?;
 
     log::info!(
-        "购买记录已创建: user_id={}, project_id={}, purchase_id={}",
-        paid_order.user_id.0,
-        paid_order.project_id.0,
-        purchase.id.0
+        "购买记录已创建: user_id={}, project_ids={:?}",
+        paid_order.grantee().0,
+        project_ids.iter().map(|x| x.0).collect::<Vec<_>>()
     );
 
-    // 8. 提交事务
+    // 7. 提交事务
     transaction
         .commit()
         .await
         .map_err(|e| format!("提交事务失败: {}", e))?;
-
-    // 9. 更新 Redis 缓存
-    if let Err(e) = UserPurchase::add_to_user_purchase_cache(
-        UserId(paid_order.user_id.0),
-        ProjectId(paid_order.project_id.0),
-        redis,
-    )
-    .await
-    {
-        log::warn!("更新购买缓存失败: {:?}", e);
+    crate::queue::push::publish(push_events, redis).await;
+
+    // 8. 更新 Redis 缓存
+    for project_id in project_ids {
+        if let Err(e) = UserPurchase::add_to_user_purchase_cache(
+            paid_order.grantee(),
+            project_id,
+            redis,
+        )
+        .await
+        {
+            log::warn!("更新购买缓存失败: {:?}", e);
+        }
     }
 
     log::info!(
@@ -409,3 +434,119 @@ async fn process_payment_callback(
 
     Ok(())
 }
+
+/// 处理退款回调
+///
+/// 已有待审核或处理中的退款申请时直接完成该申请，否则记录一条拒付退款。
+/// 退款已从卖家商户原路退回，不需要从创作者收益中扣回。
+async fn process_refund_callback(
+    order_no: &str,
+    external_order_id: &str,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), String> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| format!("开始事务失败: {}", e))?;
+
+    let order = PaymentOrder::get_by_order_no(order_no, &mut *transaction)
+        .await
+        .map_err(|e| format!("查询订单失败: {}", e))?
+        .ok_or_else(|| format!("订单不存在: {}", order_no))?;
+
+    // 锁定订单，与审核通过的退款请求串行执行
+    let status = PaymentOrder::lock_status(order.id, &mut transaction)
+        .await
+        .map_err(|e| format!("锁定订单失败: {}", e))?
+        .ok_or_else(|| format!("订单不存在: {}", order_no))?;
+
+    // 幂等性检查
+    if status == OrderStatus::Refunded {
+        log::info!("订单已退款，跳过: order_no={}", order_no);
+        return Ok(());
+    }
+
+    if status != OrderStatus::Paid {
+        return Err(format!(
+            "订单状态异常: order_no={}, status={:?}",
+            order_no, status
+        ));
+    }
+
+    // 审核通过后正在等待支付平台确认的申请也由回调完成
+    let existing = PaymentRefund::get_by_order(order.id, &mut *transaction)
+        .await
+        .map_err(|e| format!("查询退款记录失败: {}", e))?
+        .filter(|x| {
+            matches!(x.status, RefundStatus::Pending | RefundStatus::Processing)
+        });
+
+    let refund = match existing {
+        Some(refund) => refund,
+        None => {
+            let refund = PaymentRefund {
+                id: generate_payment_refund_id(&mut transaction)
+                    .await
+                    .map_err(|e| format!("生成退款 ID 失败: {}", e))?,
+                order_id: order.id,
+                order_no: order.order_no.clone(),
+                user_id: order.user_id,
+                project_id: order.project_id,
+                seller_id: order.seller_id,
+                amount: order.amount,
+                reason: "支付平台退款".to_string(),
+                status: RefundStatus::Pending,
+                source: RefundSource::Chargeback,
+                thread_id: None,
+                reviewer_id: None,
+                review_note: None,
+                external_refund_no: None,
+                clawback_amount: Decimal::ZERO,
+                created_at: Utc::now(),
+                reviewed_at: None,
+                completed_at: None,
+            };
+            refund
+                .insert(&mut transaction)
+                .await
+                .map_err(|e| format!("创建退款记录失败: {}", e))?;
+            refund
+        }
+    };
+
+    let refunded_projects = complete_refund(
+        &refund,
+        None,
+        None,
+        Some(external_order_id),
+        Decimal::ZERO,
+        &mut transaction,
+    )
+    .await
+    .map_err(|e| format!("完成退款失败: {}", e))?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("提交事务失败: {}", e))?;
+
+    for (user_id, project_id) in refunded_projects {
+        if let Err(e) = UserPurchase::remove_from_user_purchase_cache(
+            user_id, project_id, redis,
+        )
+        .await
+        {
+            log::warn!("更新购买缓存失败: {:?}", e);
+        }
+    }
+
+    log::info!(
+        "退款回调处理完成: order_no={}, user_id={}, project_id={}",
+        order_no,
+        order.user_id.0,
+        order.project_id.0
+    );
+
+    Ok(())
+}
@@ -0,0 +1,478 @@
+//! 支付服务商本地模拟（仅用于端到端测试）
+//!
+//! 在进程内模拟云账户与支付平台（7Pay）的接口：请求与回调使用与真实服务商
+//! 相同的签名、加密格式，测试可以为每笔订单预设结果，模拟服务会把回调投递
+//! 回 labrinth，从而在无网络的环境中覆盖提现状态机与订单回调。
+//!
+//! 设置 `MOCK_PAYMENT_PROVIDERS=true` 后挂载在 `/_mock` 下：
+//! - 云账户：`YUNZHANGHU_API_URL` 指向 `<SELF_ADDR>/_mock/yunzhanghu`，
+//!   `MOCK_YUNZHANGHU_PLATFORM_PRIVATE_KEY_PATH` 为与
+//!   `YUNZHANGHU_PLATFORM_PUBLIC_KEY_PATH` 配对的私钥，用于签名回调
+//! - 支付平台：`SEVENPAY_API_URL` 指向 `<SELF_ADDR>/_mock/sevenpay`，
+//!   各接口路径见 [`sevenpay`]；`MOCK_SEVENPAY_MERCHANTS` 配置商户密钥
+//!
+//! 测试控制接口（需要 `Modrinth-Admin` 管理员密钥）：
+//! - `POST /_mock/scenarios`：为某类操作预设结果，`key` 为空时作为该类默认结果
+//! - `DELETE /_mock/scenarios`：清空预设结果、模拟订单与全部记录
+//! - `GET /_mock/requests`：模拟服务收到的接口调用
+//! - `GET /_mock/deliveries`：回调投递记录与 labrinth 的响应
+//!
+//! 模块只在启用 `mock-providers` feature 时编译，切勿在生产构建中开启。
+
+use actix_web::{HttpResponse, delete, get, post, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::util::env::parse_var;
+use crate::util::guards::admin_key_guard;
+
+pub mod sevenpay;
+pub mod yunzhanghu;
+
+/// 保留的调用与投递记录条数上限
+const MAX_RECORDS: usize = 1000;
+/// 回调请求超时
+const DELIVERY_TIMEOUT_SECONDS: u64 = 10;
+
+/// 是否启用支付服务商模拟
+pub fn enabled() -> bool {
+    parse_var::<bool>("MOCK_PAYMENT_PROVIDERS").unwrap_or(false)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    if !enabled() {
+        return;
+    }
+
+    cfg.service(
+        web::scope("_mock")
+            .service(scenario_set)
+            .service(scenario_reset)
+            .service(requests_list)
+            .service(deliveries_list)
+            .configure(yunzhanghu::config)
+            .configure(sevenpay::config),
+    );
+}
+
+// ==================== 预设结果 ====================
+
+/// 可预设结果的操作
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioKind {
+    /// 云账户 H5 签约，按身份证号匹配
+    YunzhanghuSign,
+    /// 云账户 H5 解约，按身份证号匹配
+    YunzhanghuRelease,
+    /// 云账户支付宝实时支付，按平台订单号匹配
+    YunzhanghuOrder,
+    /// 支付平台下单支付，按订单号匹配
+    SevenpayOrder,
+    /// 支付平台原路退款，按订单号匹配
+    SevenpayRefund,
+}
+
+/// 操作结果
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Outcome {
+    /// 立即成功
+    #[default]
+    Success,
+    /// 受理后处理失败
+    Failure { message: Option<String> },
+    /// 一直处理中，只能通过手动通知改变状态
+    Pending,
+    /// 前 `polls` 次查询返回处理中，之后成功
+    PendingThenSuccess { polls: u32 },
+    /// 接口直接返回业务错误，不受理
+    Reject {
+        code: Option<String>,
+        message: Option<String>,
+    },
+}
+
+/// 预设结果与回调投递方式
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub outcome: Outcome,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            outcome: Outcome::Success,
+            delivery: DeliveryOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryOptions {
+    /// 是否投递回调，关闭时结果只能通过查询接口获得
+    #[serde(default = "default_callback")]
+    pub callback: bool,
+    /// 同一回调额外重复投递的次数
+    #[serde(default)]
+    pub duplicates: u32,
+    /// 使用错误的签名投递回调
+    #[serde(default)]
+    pub bad_signature: bool,
+    /// 回调延迟，默认稍晚于接口响应，与真实服务商一致
+    #[serde(default = "default_callback_delay_ms")]
+    pub callback_delay_ms: u64,
+}
+
+impl Default for DeliveryOptions {
+    fn default() -> Self {
+        Self {
+            callback: default_callback(),
+            duplicates: 0,
+            bad_signature: false,
+            callback_delay_ms: default_callback_delay_ms(),
+        }
+    }
+}
+
+fn default_callback() -> bool {
+    true
+}
+
+fn default_callback_delay_ms() -> u64 {
+    500
+}
+
+/// 模拟订单或签约的处理进度
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Progress {
+    /// 处理中，`polls_left` 为空时不会自动结束
+    Pending {
+        polls_left: Option<u32>,
+    },
+    Succeeded,
+    Failed(String),
+}
+
+impl Progress {
+    /// 受理时的初始进度，`Reject` 不会受理
+    pub fn start(outcome: &Outcome) -> Self {
+        match outcome {
+            Outcome::Success | Outcome::Reject { .. } => Progress::Succeeded,
+            Outcome::Failure { message } => Progress::Failed(
+                message
+                    .clone()
+                    .unwrap_or_else(|| "模拟处理失败".to_string()),
+            ),
+            Outcome::Pending => Progress::Pending { polls_left: None },
+            Outcome::PendingThenSuccess { polls: 0 } => Progress::Succeeded,
+            Outcome::PendingThenSuccess { polls } => Progress::Pending {
+                polls_left: Some(*polls),
+            },
+        }
+    }
+
+    /// 记录一次查询，返回本次查询后是否刚刚结束
+    pub fn poll(&mut self) -> bool {
+        match self {
+            Progress::Pending {
+                polls_left: Some(left),
+            } => {
+                *left = left.saturating_sub(1);
+                if *left == 0 {
+                    *self = Progress::Succeeded;
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Progress::Pending { .. })
+    }
+}
+
+// ==================== 进程内状态 ====================
+
+/// 模拟服务收到的一次接口调用
+#[derive(Clone, Serialize)]
+pub struct RecordedRequest {
+    pub provider: &'static str,
+    pub operation: &'static str,
+    /// 签名是否有效
+    pub signature_valid: bool,
+    /// 解密后的业务参数
+    pub payload: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+}
+
+/// 一次回调投递
+#[derive(Clone, Serialize)]
+pub struct Delivery {
+    pub provider: &'static str,
+    pub event: &'static str,
+    pub url: String,
+    /// 从 0 开始，大于 0 为重复投递
+    pub attempt: u32,
+    pub bad_signature: bool,
+    pub status: Option<u16>,
+    pub response: String,
+    pub delivered_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct MockState {
+    scenarios: HashMap<(ScenarioKind, String), Scenario>,
+    defaults: HashMap<ScenarioKind, Scenario>,
+    requests: Vec<RecordedRequest>,
+    deliveries: Vec<Delivery>,
+    pub yunzhanghu: yunzhanghu::YzhState,
+    pub sevenpay: sevenpay::SevenPayState,
+}
+
+impl MockState {
+    /// 取出某个操作的预设结果，按键匹配优先，其次为该类默认结果
+    pub fn scenario(&self, kind: ScenarioKind, key: &str) -> Scenario {
+        self.scenarios
+            .get(&(kind, key.to_string()))
+            .or_else(|| self.defaults.get(&kind))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn record_request(
+        &mut self,
+        provider: &'static str,
+        operation: &'static str,
+        signature_valid: bool,
+        payload: serde_json::Value,
+    ) {
+        push_bounded(
+            &mut self.requests,
+            RecordedRequest {
+                provider,
+                operation,
+                signature_valid,
+                payload,
+                received_at: Utc::now(),
+            },
+        );
+    }
+}
+
+fn push_bounded<T>(records: &mut Vec<T>, record: T) {
+    if records.len() >= MAX_RECORDS {
+        records.remove(0);
+    }
+    records.push(record);
+}
+
+static STATE: LazyLock<Mutex<MockState>> =
+    LazyLock::new(|| Mutex::new(MockState::default()));
+
+/// 锁定模拟状态。锁不能跨 `await` 持有
+pub fn state() -> MutexGuard<'static, MockState> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 回调基础地址（labrinth 自身）
+pub fn self_addr() -> String {
+    dotenvy::var("SELF_ADDR")
+        .unwrap_or_default()
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// ==================== 回调投递 ====================
+
+pub enum DeliveryBody {
+    Form(Vec<(&'static str, String)>),
+    Json(serde_json::Value),
+}
+
+/// 异步投递回调，按预设重复投递，结果写入投递记录
+pub fn deliver(
+    provider: &'static str,
+    event: &'static str,
+    url: String,
+    body: DeliveryBody,
+    options: &DeliveryOptions,
+) {
+    if !options.callback || url.is_empty() {
+        return;
+    }
+
+    let options = options.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(options.callback_delay_ms))
+            .await;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECONDS))
+            .build()
+            .unwrap_or_default();
+
+        for attempt in 0..=options.duplicates {
+            let request = match &body {
+                DeliveryBody::Form(form) => client.post(&url).form(form),
+                DeliveryBody::Json(json) => client.post(&url).json(json),
+            };
+            let (status, response) = match request.send().await {
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    (Some(status), resp.text().await.unwrap_or_default())
+                }
+                Err(e) => (None, e.to_string()),
+            };
+
+            log::info!(
+                "模拟{}回调 {} 投递至 {}: attempt={} status={:?}",
+                provider,
+                event,
+                url,
+                attempt,
+                status
+            );
+
+            push_bounded(
+                &mut state().deliveries,
+                Delivery {
+                    provider,
+                    event,
+                    url: url.clone(),
+                    attempt,
+                    bad_signature: options.bad_signature,
+                    status,
+                    response,
+                    delivered_at: Utc::now(),
+                },
+            );
+        }
+    });
+}
+
+// ==================== 控制接口 ====================
+
+#[derive(Deserialize)]
+pub struct ScenarioRequest {
+    pub kind: ScenarioKind,
+    /// 订单号或身份证号，为空时作为该类操作的默认结果
+    pub key: Option<String>,
+    #[serde(flatten)]
+    pub scenario: Scenario,
+}
+
+#[post("scenarios", guard = "admin_key_guard")]
+pub async fn scenario_set(body: web::Json<ScenarioRequest>) -> HttpResponse {
+    let body = body.into_inner();
+    let mut state = state();
+    match body.key {
+        Some(key) => {
+            state.scenarios.insert((body.kind, key), body.scenario);
+        }
+        None => {
+            state.defaults.insert(body.kind, body.scenario);
+        }
+    }
+
+    HttpResponse::NoContent().finish()
+}
+
+#[delete("scenarios", guard = "admin_key_guard")]
+pub async fn scenario_reset() -> HttpResponse {
+    *state() = MockState::default();
+
+    HttpResponse::NoContent().finish()
+}
+
+#[get("requests", guard = "admin_key_guard")]
+pub async fn requests_list() -> HttpResponse {
+    let requests = state().requests.clone();
+
+    HttpResponse::Ok().json(requests)
+}
+
+#[get("deliveries", guard = "admin_key_guard")]
+pub async fn deliveries_list() -> HttpResponse {
+    let deliveries = state().deliveries.clone();
+
+    HttpResponse::Ok().json(deliveries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_then_success_settles_after_polls() {
+        let mut progress =
+            Progress::start(&Outcome::PendingThenSuccess { polls: 2 });
+        assert!(progress.is_pending());
+        assert!(!progress.poll());
+        assert!(progress.poll());
+        assert_eq!(progress, Progress::Succeeded);
+        assert!(!progress.poll());
+    }
+
+    #[test]
+    fn pending_never_settles() {
+        let mut progress = Progress::start(&Outcome::Pending);
+        for _ in 0..10 {
+            assert!(!progress.poll());
+        }
+        assert!(progress.is_pending());
+    }
+
+    #[test]
+    fn scenario_defaults() {
+        let scenario: Scenario = serde_json::from_value(serde_json::json!({
+            "outcome": { "type": "failure", "message": "余额不足" },
+            "duplicates": 2
+        }))
+        .unwrap();
+        assert_eq!(
+            scenario.outcome,
+            Outcome::Failure {
+                message: Some("余额不足".to_string())
+            }
+        );
+        assert!(scenario.delivery.callback);
+        assert_eq!(scenario.delivery.duplicates, 2);
+        assert!(!scenario.delivery.bad_signature);
+
+        let mut state = MockState::default();
+        state
+            .defaults
+            .insert(ScenarioKind::YunzhanghuOrder, scenario);
+        state.scenarios.insert(
+            (ScenarioKind::YunzhanghuOrder, "bbsmc-1".to_string()),
+            Scenario::default(),
+        );
+        assert_eq!(
+            state
+                .scenario(ScenarioKind::YunzhanghuOrder, "bbsmc-1")
+                .outcome,
+            Outcome::Success
+        );
+        assert_eq!(
+            state
+                .scenario(ScenarioKind::YunzhanghuOrder, "bbsmc-2")
+                .delivery
+                .duplicates,
+            2
+        );
+        assert_eq!(
+            state.scenario(ScenarioKind::SevenpayOrder, "x").outcome,
+            Outcome::Success
+        );
+    }
+}
@@ -0,0 +1,560 @@
+//! 支付平台（7Pay）接口模拟
+//!
+//! 接口均为 GET，`Authorization` 头为参数值按顺序拼接商户密钥后的 MD5，
+//! 商户密钥通过 `MOCK_SEVENPAY_MERCHANTS`（`sid:密钥`，多个用逗号分隔）配置。
+//! 使用时将各接口路径配置为：
+//! - `SEVENPAY_CREATE_ORDER_PATH=/createOrder`
+//! - `SEVENPAY_QUERY_ORDER_PATH=/queryOrder`
+//! - `SEVENPAY_REFUND_ORDER_PATH=/refundOrder`
+//! - `SEVENPAY_SHIP_ORDER_PATH=/shipOrder`
+//! - `SEVENPAY_VERIFY_MERCHANT_PATH=/verifyMerchant`
+//!
+//! 支付结果回调使用 `SEVENPAY_KEYCODE` 签名，以 JSON 投递到
+//! `MOCK_SEVENPAY_NOTIFY_URL`（默认 `<SELF_ADDR>/_internal/payment/callback`）。
+//! 主动退款不会回调；买家拒付等平台侧退款通过测试接口（需要管理员密钥）触发：
+//! - `POST /_mock/sevenpay/orders/{order_no}/notify`：把订单改为指定交易状态并投递回调
+
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::{
+    DeliveryBody, DeliveryOptions, Outcome, Progress, ScenarioKind, deliver,
+    self_addr, state,
+};
+use crate::util::guards::admin_key_guard;
+
+const PROVIDER: &str = "支付平台";
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("sevenpay")
+            .service(create_order)
+            .service(query_order)
+            .service(refund_order)
+            .service(ship_order)
+            .service(verify_merchant)
+            .service(order_notify),
+    );
+}
+
+// ==================== 状态 ====================
+
+#[derive(Default)]
+pub struct SevenPayState {
+    orders: HashMap<String, SevenPayOrder>,
+}
+
+struct SevenPayOrder {
+    order_no: String,
+    order_id: String,
+    sid: String,
+    title: String,
+    pay_type: String,
+    user_display_name: String,
+    /// 金额（分）
+    money: String,
+    trade_state: String,
+    progress: Progress,
+    delivery: DeliveryOptions,
+}
+
+impl SevenPayOrder {
+    /// 按处理进度更新交易状态
+    fn settle(&mut self) {
+        self.trade_state = match &self.progress {
+            Progress::Pending { .. } => "NOTPAY",
+            Progress::Succeeded => "SUCCESS",
+            Progress::Failed(_) => "CLOSED",
+        }
+        .to_string();
+    }
+
+    fn callback_data(&self) -> serde_json::Value {
+        json!({
+            "tradeState": self.trade_state,
+            "otherOrderNo": self.order_no,
+            "orderId": self.order_id,
+            "orderTransactionId": format!("mock-txn-{}", self.order_id),
+            "sid": self.sid,
+            "title": self.title,
+            "payType": if self.pay_type == "1" { "WECHAT" } else { "ALIPAY" },
+            "userDisplayName": self.user_display_name,
+            "money": self.money,
+            "settlement": "0",
+        })
+    }
+}
+
+// ==================== 签名 ====================
+
+/// 解析 `sid:密钥,sid:密钥` 格式的商户配置
+pub fn parse_merchants(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|x| x.trim().split_once(':'))
+        .map(|(sid, key)| (sid.trim().to_string(), key.trim().to_string()))
+        .filter(|(sid, key)| !sid.is_empty() && !key.is_empty())
+        .collect()
+}
+
+fn merchant_key(sid: &str) -> Option<String> {
+    parse_merchants(
+        &dotenvy::var("MOCK_SEVENPAY_MERCHANTS").unwrap_or_default(),
+    )
+    .remove(sid)
+}
+
+/// 请求签名：参数值按顺序拼接后加商户密钥，取 MD5
+pub fn request_sign(values: &[&str], secret_key: &str) -> String {
+    format!("{:x}", md5::compute(values.concat() + secret_key))
+}
+
+/// 回调签名：`data` 各字段值按字段名排序拼接，首尾加 keycode，取大写 MD5
+pub fn callback_sign(data: &serde_json::Value, keycode: &str) -> String {
+    let values: BTreeMap<&str, String> = data
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(x) => x.clone(),
+                x => x.to_string(),
+            };
+            (key.as_str(), value)
+        })
+        .collect();
+    let raw = format!(
+        "{}{}{}",
+        keycode,
+        values.values().map(String::as_str).collect::<String>(),
+        keycode
+    );
+    format!("{:x}", md5::compute(raw)).to_uppercase()
+}
+
+fn respond(code: i32, msg: &str, data: serde_json::Value) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "code": code, "msg": msg, "data": data }))
+}
+
+/// 校验 `Authorization` 签名并记录调用，返回商户是否存在
+fn accept(
+    req: &HttpRequest,
+    operation: &'static str,
+    sid: &str,
+    values: &[&str],
+    payload: serde_json::Value,
+) -> Result<(), HttpResponse> {
+    let Some(secret_key) = merchant_key(sid) else {
+        state().record_request(PROVIDER, operation, false, payload);
+        return Err(respond(404, "商户不存在", serde_json::Value::Null));
+    };
+
+    let authorization = req
+        .headers()
+        .get("Authorization")
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default();
+    let valid = authorization == request_sign(values, &secret_key);
+    state().record_request(PROVIDER, operation, valid, payload);
+
+    if valid {
+        Ok(())
+    } else {
+        Err(respond(401, "签名错误", serde_json::Value::Null))
+    }
+}
+
+/// 签名并异步投递支付回调
+fn send_notify(
+    event: &'static str,
+    data: serde_json::Value,
+    delivery: &DeliveryOptions,
+) {
+    let keycode = dotenvy::var("SEVENPAY_KEYCODE").unwrap_or_default();
+    let sign = if delivery.bad_signature {
+        callback_sign(&data, "mock-bad-keycode")
+    } else {
+        callback_sign(&data, &keycode)
+    };
+    let url = dotenvy::var("MOCK_SEVENPAY_NOTIFY_URL").unwrap_or_else(|_| {
+        format!("{}/_internal/payment/callback", self_addr())
+    });
+
+    deliver(
+        PROVIDER,
+        event,
+        url,
+        DeliveryBody::Json(json!({ "data": data, "sign": sign })),
+        delivery,
+    );
+}
+
+// ==================== 接口 ====================
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOrderQuery {
+    pub order_no: String,
+    pub sid: String,
+    pub title: String,
+    pub pay_type: String,
+    pub user_display_name: String,
+    pub money: String,
+}
+
+#[get("createOrder")]
+pub async fn create_order(
+    req: HttpRequest,
+    query: web::Query<CreateOrderQuery>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    if let Err(resp) = accept(
+        &req,
+        "create_order",
+        &query.sid,
+        &[
+            &query.order_no,
+            &query.sid,
+            &query.title,
+            &query.pay_type,
+            &query.user_display_name,
+            &query.money,
+        ],
+        json!({
+            "orderNo": query.order_no,
+            "sid": query.sid,
+            "title": query.title,
+            "payType": query.pay_type,
+            "userDisplayName": query.user_display_name,
+            "money": query.money,
+        }),
+    ) {
+        return resp;
+    }
+
+    let notify = {
+        let mut state = state();
+        if state.sevenpay.orders.contains_key(&query.order_no) {
+            return respond(500, "订单号重复", serde_json::Value::Null);
+        }
+
+        let scenario =
+            state.scenario(ScenarioKind::SevenpayOrder, &query.order_no);
+        if let Outcome::Reject { message, .. } = &scenario.outcome {
+            return respond(
+                500,
+                message.as_deref().unwrap_or("模拟拒绝"),
+                serde_json::Value::Null,
+            );
+        }
+
+        let mut order = SevenPayOrder {
+            order_no: query.order_no.clone(),
+            order_id: Uuid::new_v4().simple().to_string(),
+            sid: query.sid,
+            title: query.title,
+            pay_type: query.pay_type,
+            user_display_name: query.user_display_name,
+            money: query.money,
+            trade_state: String::new(),
+            progress: Progress::start(&scenario.outcome),
+            delivery: scenario.delivery,
+        };
+        order.settle();
+
+        let notify = (!order.progress.is_pending())
+            .then(|| (order.callback_data(), order.delivery.clone()));
+        state.sevenpay.orders.insert(query.order_no.clone(), order);
+        notify
+    };
+
+    if let Some((data, delivery)) = notify {
+        send_notify("payment", data, &delivery);
+    }
+
+    respond(
+        200,
+        "success",
+        json!({
+            "img": format!("{}/_mock/sevenpay/qr/{}", self_addr(), query.order_no),
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderQuery {
+    pub order_no: String,
+    pub sid: String,
+}
+
+#[get("queryOrder")]
+pub async fn query_order(
+    req: HttpRequest,
+    query: web::Query<OrderQuery>,
+) -> HttpResponse {
+    if let Err(resp) = accept(
+        &req,
+        "query_order",
+        &query.sid,
+        &[&query.order_no, &query.sid],
+        json!({ "orderNo": query.order_no, "sid": query.sid }),
+    ) {
+        return resp;
+    }
+
+    let (response, notify) = {
+        let mut state = state();
+        let Some(order) = state.sevenpay.orders.get_mut(&query.order_no) else {
+            return respond(404, "订单不存在", serde_json::Value::Null);
+        };
+
+        let settled = order.progress.poll();
+        if settled {
+            order.settle();
+        }
+
+        (
+            json!({ "tradeState": order.trade_state, "orderId": order.order_id }),
+            settled.then(|| (order.callback_data(), order.delivery.clone())),
+        )
+    };
+
+    if let Some((data, delivery)) = notify {
+        send_notify("payment", data, &delivery);
+    }
+
+    respond(200, "success", response)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundQuery {
+    pub order_no: String,
+    pub sid: String,
+    pub money: String,
+}
+
+#[get("refundOrder")]
+pub async fn refund_order(
+    req: HttpRequest,
+    query: web::Query<RefundQuery>,
+) -> HttpResponse {
+    if let Err(resp) = accept(
+        &req,
+        "refund_order",
+        &query.sid,
+        &[&query.order_no, &query.sid, &query.money],
+        json!({
+            "orderNo": query.order_no,
+            "sid": query.sid,
+            "money": query.money,
+        }),
+    ) {
+        return resp;
+    }
+
+    let mut state = state();
+    let scenario =
+        state.scenario(ScenarioKind::SevenpayRefund, &query.order_no);
+    let Some(order) = state.sevenpay.orders.get_mut(&query.order_no) else {
+        return respond(404, "订单不存在", serde_json::Value::Null);
+    };
+
+    match scenario.outcome {
+        Outcome::Reject { message, .. } | Outcome::Failure { message } => {
+            return respond(
+                500,
+                message.as_deref().unwrap_or("模拟退款失败"),
+                serde_json::Value::Null,
+            );
+        }
+        Outcome::Success
+        | Outcome::Pending
+        | Outcome::PendingThenSuccess { .. } => {}
+    }
+
+    if order.trade_state != "SUCCESS" {
+        return respond(500, "订单未支付或已退款", serde_json::Value::Null);
+    }
+    let requested = query.money.parse::<i64>().unwrap_or(0);
+    let paid = order.money.parse::<i64>().unwrap_or(0);
+    if requested <= 0 || requested > paid {
+        return respond(500, "退款金额错误", serde_json::Value::Null);
+    }
+
+    order.trade_state = "REFUND".to_string();
+
+    respond(
+        200,
+        "success",
+        json!({ "refundNo": format!("mock-refund-{}", Uuid::new_v4().simple()) }),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShipQuery {
+    pub order_no: String,
+    pub sid: String,
+    pub other: String,
+}
+
+#[get("shipOrder")]
+pub async fn ship_order(
+    req: HttpRequest,
+    query: web::Query<ShipQuery>,
+) -> HttpResponse {
+    if let Err(resp) = accept(
+        &req,
+        "ship_order",
+        &query.sid,
+        &[&query.order_no, &query.sid, &query.other],
+        json!({
+            "orderNo": query.order_no,
+            "sid": query.sid,
+            "other": query.other,
+        }),
+    ) {
+        return resp;
+    }
+
+    if !state().sevenpay.orders.contains_key(&query.order_no) {
+        return respond(404, "订单不存在", serde_json::Value::Null);
+    }
+
+    respond(200, "success", serde_json::Value::Null)
+}
+
+#[derive(Deserialize)]
+pub struct VerifyQuery {
+    pub sid: String,
+}
+
+#[get("verifyMerchant")]
+pub async fn verify_merchant(
+    req: HttpRequest,
+    query: web::Query<VerifyQuery>,
+) -> HttpResponse {
+    if merchant_key(&query.sid).is_none() {
+        state().record_request(
+            PROVIDER,
+            "verify_merchant",
+            false,
+            json!({ "sid": query.sid }),
+        );
+        return respond(
+            200,
+            "success",
+            json!({ "exists": false, "alipayBound": false }),
+        );
+    }
+
+    if let Err(resp) = accept(
+        &req,
+        "verify_merchant",
+        &query.sid,
+        &[&query.sid],
+        json!({ "sid": query.sid }),
+    ) {
+        return resp;
+    }
+
+    respond(
+        200,
+        "success",
+        json!({
+            "exists": true,
+            "alipayBound": true,
+            "wechatBound": true,
+            "serverName": format!("模拟商户 {}", query.sid),
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct OrderNotifyRequest {
+    /// 交易状态：SUCCESS / NOTPAY / CLOSED / REFUND
+    pub trade_state: String,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+/// 修改模拟订单交易状态并投递支付回调
+#[post("orders/{order_no}/notify", guard = "admin_key_guard")]
+pub async fn order_notify(
+    path: web::Path<String>,
+    body: web::Json<OrderNotifyRequest>,
+) -> HttpResponse {
+    let body = body.into_inner();
+    let data = {
+        let mut state = state();
+        let Some(order) = state.sevenpay.orders.get_mut(&*path) else {
+            return HttpResponse::NotFound().finish();
+        };
+
+        order.progress = Progress::Succeeded;
+        order.trade_state = body.trade_state;
+        order.callback_data()
+    };
+
+    send_notify("payment", data, &body.delivery);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::internal::payment::{
+        PaymentCallbackRequest, verify_payment_signature,
+    };
+
+    #[test]
+    fn merchants_parse() {
+        let merchants = parse_merchants("1001:abc, 1002 : def,,bad,1003:");
+        assert_eq!(merchants.len(), 2);
+        assert_eq!(merchants["1001"], "abc");
+        assert_eq!(merchants["1002"], "def");
+    }
+
+    #[test]
+    fn request_sign_concatenates_values() {
+        assert_eq!(
+            request_sign(&["a", "1"], "key"),
+            format!("{:x}", md5::compute("a1key"))
+        );
+    }
+
+    #[test]
+    fn callback_accepted_by_payment_route() {
+        let mut order = SevenPayOrder {
+            order_no: "BBSMC202610180001".to_string(),
+            order_id: "abc123".to_string(),
+            sid: "1001".to_string(),
+            title: "测试项目".to_string(),
+            pay_type: "2".to_string(),
+            user_display_name: "tester".to_string(),
+            money: "990".to_string(),
+            trade_state: String::new(),
+            progress: Progress::Succeeded,
+            delivery: DeliveryOptions::default(),
+        };
+        order.settle();
+
+        let data = order.callback_data();
+        let body: PaymentCallbackRequest = serde_json::from_value(json!({
+            "data": data,
+            "sign": callback_sign(&data, "keycode"),
+        }))
+        .unwrap();
+        assert_eq!(body.data.trade_state, "SUCCESS");
+        assert!(verify_payment_signature(&body.data, &body.sign, "keycode"));
+
+        let bad = callback_sign(&data, "mock-bad-keycode");
+        assert!(!verify_payment_signature(&body.data, &bad, "keycode"));
+    }
+}
@@ -0,0 +1,869 @@
+//! 云账户接口模拟
+//!
+//! 与真实接口相同：请求 `data` 为 3DES 密文，使用平台企业私钥对应的公钥验签；
+//! 响应 `data` 同样 3DES 加密；回调使用 `MOCK_YUNZHANGHU_PLATFORM_PRIVATE_KEY_PATH`
+//! 签名，以 form-urlencoded 投递到请求中的回调地址。
+//!
+//! 除云账户接口外提供一个测试用接口（需要管理员密钥）：
+//! - `POST /_mock/yunzhanghu/orders/{order_id}/notify`：把订单改为指定状态并
+//!   投递回调，用于模拟退汇、撤销等后续状态变化
+
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::{
+    DeliveryBody, DeliveryOptions, MockState, Progress, ScenarioKind, deliver,
+    self_addr, state,
+};
+use crate::util::date::format_app_tz;
+use crate::util::guards::admin_key_guard;
+use crate::util::yunzhanghu::YzhError;
+use crate::util::yunzhanghu::crypto::{
+    decrypt_3des, encrypt_3des, public_key_pem_from_private, rsa_sign_sha256,
+    rsa_verify_sha256,
+};
+use crate::util::yunzhanghu::secrets::{self, YzhCredentials};
+
+const PROVIDER: &str = "云账户";
+
+// 模拟服务使用的业务错误码
+const CODE_SUCCESS: &str = "0000";
+const CODE_DEALER_MISMATCH: &str = "1001";
+const CODE_BAD_SIGNATURE: &str = "1003";
+const CODE_BAD_DATA: &str = "1004";
+const CODE_NOT_FOUND: &str = "2018";
+const CODE_DUPLICATE_ORDER: &str = "2002";
+const CODE_REJECTED: &str = "2001";
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("yunzhanghu")
+            .service(presign)
+            .service(sign_h5)
+            .service(sign_status)
+            .service(release_h5)
+            .service(order_alipay)
+            .service(calc_tax)
+            .service(query_order)
+            .service(order_notify),
+    );
+}
+
+// ==================== 状态 ====================
+
+#[derive(Default)]
+pub struct YzhState {
+    /// 签约 token → 身份证号
+    tokens: HashMap<String, String>,
+    /// 身份证号 → 签约信息
+    signers: HashMap<String, YzhSigner>,
+    orders: HashMap<String, YzhOrder>,
+}
+
+struct YzhSigner {
+    real_name: String,
+    id_card: String,
+    /// 0=未签约 / 1=已签约 / 2=已解约
+    status: i32,
+    signed_at: Option<DateTime<Utc>>,
+    pending: Option<PendingSign>,
+}
+
+/// 处理中的签约或解约
+struct PendingSign {
+    target: i32,
+    progress: Progress,
+    callback_url: String,
+    delivery: DeliveryOptions,
+}
+
+struct YzhOrder {
+    order_id: String,
+    ref_id: String,
+    pay: String,
+    real_name: String,
+    card_no: String,
+    id_card: String,
+    phone_no: String,
+    notify_url: String,
+    status: String,
+    status_detail_message: String,
+    progress: Progress,
+    delivery: DeliveryOptions,
+}
+
+impl YzhOrder {
+    /// 按处理进度更新订单状态
+    fn settle(&mut self) {
+        match &self.progress {
+            Progress::Pending { .. } => self.status = "0".to_string(),
+            Progress::Succeeded => {
+                self.status = "1".to_string();
+                self.status_detail_message = String::new();
+            }
+            Progress::Failed(message) => {
+                self.status = "2".to_string();
+                self.status_detail_message = message.clone();
+            }
+        }
+    }
+
+    fn notify_payload(&self, creds: &YzhCredentials) -> serde_json::Value {
+        json!({
+            "notify_id": Uuid::new_v4().simple().to_string(),
+            "notify_time": format_app_tz(Utc::now()),
+            "data": {
+                "order_id": self.order_id,
+                "ref": self.ref_id,
+                "pay": self.pay,
+                "dealer_id": creds.dealer_id,
+                "broker_id": creds.broker_id,
+                "real_name": self.real_name,
+                "card_no": self.card_no,
+                "id_card": self.id_card,
+                "phone_no": self.phone_no,
+                "status": self.status,
+                "status_detail": if self.status == "2" { "1" } else { "0" },
+                "status_detail_message": self.status_detail_message,
+            },
+        })
+    }
+}
+
+// ==================== 报文 ====================
+
+/// 平台企业请求的五个字段
+#[derive(Deserialize)]
+pub struct SignedRequest {
+    pub data: String,
+    pub mess: String,
+    pub timestamp: String,
+    pub sign: String,
+    #[serde(default)]
+    pub sign_type: String,
+}
+
+/// 验签并解密平台企业请求
+pub fn open_request(
+    body: &SignedRequest,
+    des_key: &str,
+    app_key: &str,
+    dealer_public_key_pem: &str,
+) -> Result<serde_json::Value, YzhError> {
+    let signing_str = format!(
+        "data={}&mess={}&timestamp={}&key={}",
+        body.data, body.mess, body.timestamp, app_key
+    );
+    rsa_verify_sha256(&signing_str, &body.sign, dealer_public_key_pem)?;
+    let plaintext = decrypt_3des(des_key.as_bytes(), &body.data)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// 生成回调 Body；`bad_signature` 时签名内容与报文不一致
+pub fn seal_notify(
+    payload: &serde_json::Value,
+    des_key: &str,
+    app_key: &str,
+    platform_private_key_pem: &str,
+    bad_signature: bool,
+) -> Result<Vec<(&'static str, String)>, YzhError> {
+    let data = encrypt_3des(des_key.as_bytes(), &serde_json::to_vec(payload)?)?;
+    let mess = Uuid::new_v4().simple().to_string();
+    let timestamp = Utc::now().timestamp().to_string();
+
+    let signing_str = format!(
+        "data={}&mess={}&timestamp={}&key={}",
+        if bad_signature { "tampered" } else { &data },
+        mess,
+        timestamp,
+        app_key
+    );
+    let sign = rsa_sign_sha256(&signing_str, platform_private_key_pem)?;
+
+    Ok(vec![
+        ("data", data),
+        ("mess", mess),
+        ("timestamp", timestamp),
+        ("sign", sign),
+        ("sign_type", "rsa".to_string()),
+    ])
+}
+
+fn success<T: Serialize>(creds: &YzhCredentials, data: &T) -> HttpResponse {
+    let cipher = serde_json::to_vec(data)
+        .map_err(YzhError::from)
+        .and_then(|x| encrypt_3des(creds.des_key.as_bytes(), &x));
+    match cipher {
+        Ok(cipher) => HttpResponse::Ok().json(json!({
+            "code": CODE_SUCCESS,
+            "message": "操作成功",
+            "request_id": Uuid::new_v4().to_string(),
+            "data": cipher,
+        })),
+        Err(e) => failure(CODE_BAD_DATA, &e.to_string()),
+    }
+}
+
+fn failure(code: &str, message: &str) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "code": code,
+        "message": message,
+        "request_id": Uuid::new_v4().to_string(),
+    }))
+}
+
+/// 校验请求头、签名并解密业务参数，同时记录调用
+fn accept<T: for<'de> Deserialize<'de>>(
+    req: &HttpRequest,
+    body: &SignedRequest,
+    operation: &'static str,
+) -> Result<(&'static YzhCredentials, T), HttpResponse> {
+    let creds = secrets::load().map_err(|e| {
+        failure(CODE_BAD_DATA, &format!("模拟服务配置错误: {}", e))
+    })?;
+
+    let dealer_id = req
+        .headers()
+        .get("dealer-id")
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default();
+    if dealer_id != creds.dealer_id {
+        return Err(failure(CODE_DEALER_MISMATCH, "dealer-id 不匹配"));
+    }
+
+    let opened = public_key_pem_from_private(&creds.dealer_private_key_pem)
+        .and_then(|pem| {
+            open_request(body, &creds.des_key, &creds.app_key, &pem)
+        });
+    let payload = match opened {
+        Ok(payload) => payload,
+        Err(e) => {
+            state().record_request(
+                PROVIDER,
+                operation,
+                false,
+                serde_json::Value::Null,
+            );
+            return Err(match e {
+                YzhError::RsaVerify => failure(CODE_BAD_SIGNATURE, "签名错误"),
+                e => failure(CODE_BAD_DATA, &format!("数据解密失败: {}", e)),
+            });
+        }
+    };
+
+    state().record_request(PROVIDER, operation, true, payload.clone());
+
+    serde_json::from_value(payload)
+        .map(|x| (creds, x))
+        .map_err(|e| failure(CODE_BAD_DATA, &format!("参数错误: {}", e)))
+}
+
+/// 签名并异步投递回调
+fn send_notify(
+    event: &'static str,
+    url: String,
+    payload: serde_json::Value,
+    delivery: &DeliveryOptions,
+    creds: &YzhCredentials,
+) {
+    if !delivery.callback || url.is_empty() {
+        return;
+    }
+
+    let form = dotenvy::var("MOCK_YUNZHANGHU_PLATFORM_PRIVATE_KEY_PATH")
+        .map_err(|_| {
+            YzhError::MissingEnv("MOCK_YUNZHANGHU_PLATFORM_PRIVATE_KEY_PATH")
+        })
+        .and_then(|path| {
+            std::fs::read_to_string(&path)
+                .map_err(|source| YzhError::KeyFileRead { path, source })
+        })
+        .and_then(|pem| {
+            seal_notify(
+                &payload,
+                &creds.des_key,
+                &creds.app_key,
+                &pem,
+                delivery.bad_signature,
+            )
+        });
+
+    match form {
+        Ok(form) => {
+            deliver(PROVIDER, event, url, DeliveryBody::Form(form), delivery)
+        }
+        Err(e) => log::error!("模拟云账户回调签名失败 event={}: {}", event, e),
+    }
+}
+
+// ==================== H5 签约 ====================
+
+impl YzhSigner {
+    /// 结束处理中的签约或解约，返回需要投递的回调
+    fn finish(
+        &mut self,
+        creds: &YzhCredentials,
+    ) -> Option<(&'static str, String, serde_json::Value, DeliveryOptions)>
+    {
+        let pending = self.pending.take_if(|x| !x.progress.is_pending())?;
+        let succeeded = pending.progress == Progress::Succeeded;
+        if succeeded {
+            self.status = pending.target;
+            if pending.target == 1 {
+                self.signed_at = Some(Utc::now());
+            }
+        }
+
+        if pending.target == 2 {
+            // 解约只在成功时回调
+            return succeeded.then(|| {
+                (
+                    "unsign",
+                    pending.callback_url,
+                    json!({
+                        "dealer_id": creds.dealer_id,
+                        "broker_id": creds.broker_id,
+                        "real_name": self.real_name,
+                        "id_card": self.id_card,
+                        "release_type": "user",
+                        "release_reason": "",
+                        "release_time": format_app_tz(Utc::now()),
+                    }),
+                    pending.delivery,
+                )
+            });
+        }
+
+        Some((
+            "sign",
+            pending.callback_url,
+            json!({
+                "dealer_id": creds.dealer_id,
+                "broker_id": creds.broker_id,
+                "real_name": self.real_name,
+                "id_card": self.id_card,
+                "phone": "",
+                "status": self.status,
+                "event_type": "sign",
+                "event_status": if succeeded { "success" } else { "fail" },
+            }),
+            pending.delivery,
+        ))
+    }
+
+    /// 开始签约或解约，`Reject` 时返回错误信息
+    fn begin(
+        &mut self,
+        state: &MockState,
+        kind: ScenarioKind,
+        target: i32,
+        callback_url: String,
+    ) -> Result<(), (String, String)> {
+        let scenario = state.scenario(kind, &self.id_card);
+        if let super::Outcome::Reject { code, message } = &scenario.outcome {
+            return Err((
+                code.clone().unwrap_or_else(|| CODE_REJECTED.to_string()),
+                message.clone().unwrap_or_else(|| "模拟拒绝".to_string()),
+            ));
+        }
+
+        self.pending = Some(PendingSign {
+            target,
+            progress: Progress::start(&scenario.outcome),
+            callback_url,
+            delivery: scenario.delivery,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct PresignData {
+    real_name: String,
+    id_card: String,
+}
+
+#[post("api/sdk/v1/presign")]
+pub async fn presign(
+    req: HttpRequest,
+    body: web::Form<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) = match accept::<PresignData>(&req, &body, "presign") {
+        Ok(x) => x,
+        Err(resp) => return resp,
+    };
+
+    let token = Uuid::new_v4().simple().to_string();
+    let mut state = state();
+    let signer = state
+        .yunzhanghu
+        .signers
+        .entry(data.id_card.clone())
+        .or_insert_with(|| YzhSigner {
+            real_name: data.real_name.clone(),
+            id_card: data.id_card.clone(),
+            status: 0,
+            signed_at: None,
+            pending: None,
+        });
+    signer.real_name = data.real_name;
+    let status = signer.status;
+    state.yunzhanghu.tokens.insert(token.clone(), data.id_card);
+
+    success(creds, &json!({ "token": token, "status": status }))
+}
+
+#[derive(Deserialize)]
+struct SignApplyData {
+    token: String,
+    url: Option<String>,
+    event_callback_url: Option<String>,
+}
+
+#[get("api/sdk/v1/sign/h5")]
+pub async fn sign_h5(
+    req: HttpRequest,
+    body: web::Query<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) = match accept::<SignApplyData>(&req, &body, "sign_h5") {
+        Ok(x) => x,
+        Err(resp) => return resp,
+    };
+
+    let notify = {
+        let mut state = state();
+        let Some(id_card) = state.yunzhanghu.tokens.get(&data.token).cloned()
+        else {
+            return failure(CODE_NOT_FOUND, "token 无效或已过期");
+        };
+        let mut signer = match state.yunzhanghu.signers.remove(&id_card) {
+            Some(signer) => signer,
+            None => return failure(CODE_NOT_FOUND, "签约信息不存在"),
+        };
+        let callback_url =
+            data.event_callback_url.or(data.url).unwrap_or_default();
+        let result =
+            signer.begin(&state, ScenarioKind::YunzhanghuSign, 1, callback_url);
+        let notify = signer.finish(creds);
+        state.yunzhanghu.signers.insert(id_card, signer);
+        if let Err((code, message)) = result {
+            return failure(&code, &message);
+        }
+        notify
+    };
+
+    if let Some((event, url, payload, delivery)) = notify {
+        send_notify(event, url, payload, &delivery, creds);
+    }
+
+    success(
+        creds,
+        &json!({
+            "url": format!("{}/_mock/yunzhanghu/h5/sign/{}", self_addr(), data.token),
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+struct SignerQuery {
+    id_card: String,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+#[get("api/sdk/v1/sign/user/status")]
+pub async fn sign_status(
+    req: HttpRequest,
+    body: web::Query<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) = match accept::<SignerQuery>(&req, &body, "sign_status")
+    {
+        Ok(x) => x,
+        Err(resp) => return resp,
+    };
+
+    let (response, notify) = {
+        let mut state = state();
+        let Some(signer) = state.yunzhanghu.signers.get_mut(&data.id_card)
+        else {
+            return success(
+                creds,
+                &json!({ "status": 0, "signed_at": "", "event_type": "", "event_status": "" }),
+            );
+        };
+
+        if let Some(pending) = &mut signer.pending {
+            pending.progress.poll();
+        }
+        let notify = signer.finish(creds);
+        let event_status = match &notify {
+            Some((_, _, payload, _)) => payload["event_status"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            None if signer.pending.is_some() => "processing".to_string(),
+            None => String::new(),
+        };
+
+        (
+            json!({
+                "status": signer.status,
+                "signed_at": signer.signed_at.map(format_app_tz).unwrap_or_default(),
+                "event_type": "sign",
+                "event_status": event_status,
+            }),
+            notify,
+        )
+    };
+
+    if let Some((event, url, payload, delivery)) = notify {
+        send_notify(event, url, payload, &delivery, creds);
+    }
+
+    success(creds, &response)
+}
+
+#[get("api/sdk/v1/release/h5")]
+pub async fn release_h5(
+    req: HttpRequest,
+    body: web::Query<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) = match accept::<SignerQuery>(&req, &body, "release_h5") {
+        Ok(x) => x,
+        Err(resp) => return resp,
+    };
+
+    let (status, notify) = {
+        let mut state = state();
+        let Some(mut signer) = state.yunzhanghu.signers.remove(&data.id_card)
+        else {
+            return failure(CODE_NOT_FOUND, "用户未签约");
+        };
+
+        let mut notify = None;
+        let mut result = Ok(());
+        if signer.status == 1 {
+            let callback_url = data.url.unwrap_or_else(|| {
+                dotenvy::var("MOCK_YUNZHANGHU_UNSIGN_NOTIFY_URL")
+                    .unwrap_or_else(|_| {
+                        format!("{}/v3/yunzhanghu/_webhook/unsign", self_addr())
+                    })
+            });
+            result = signer.begin(
+                &state,
+                ScenarioKind::YunzhanghuRelease,
+                2,
+                callback_url,
+            );
+            notify = signer.finish(creds);
+        }
+        let status = signer.status;
+        state.yunzhanghu.signers.insert(data.id_card, signer);
+        if let Err((code, message)) = result {
+            return failure(&code, &message);
+        }
+        (status, notify)
+    };
+
+    if let Some((event, url, payload, delivery)) = notify {
+        send_notify(event, url, payload, &delivery, creds);
+    }
+
+    success(
+        creds,
+        &json!({
+            "status": status,
+            "url": format!("{}/_mock/yunzhanghu/h5/release", self_addr()),
+        }),
+    )
+}
+
+// ==================== 实时支付 ====================
+
+#[derive(Deserialize)]
+struct AlipayOrderData {
+    order_id: String,
+    real_name: String,
+    card_no: String,
+    id_card: String,
+    #[serde(default)]
+    phone_no: String,
+    pay: String,
+    #[serde(default)]
+    notify_url: Option<String>,
+    #[serde(default)]
+    dealer_id: String,
+    #[serde(default)]
+    broker_id: String,
+}
+
+#[post("api/payment/v1/order-alipay")]
+pub async fn order_alipay(
+    req: HttpRequest,
+    body: web::Form<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) =
+        match accept::<AlipayOrderData>(&req, &body, "order_alipay") {
+            Ok(x) => x,
+            Err(resp) => return resp,
+        };
+
+    if data.dealer_id != creds.dealer_id || data.broker_id != creds.broker_id {
+        return failure(CODE_DEALER_MISMATCH, "dealer_id 或 broker_id 不匹配");
+    }
+
+    let (response, notify) = {
+        let mut state = state();
+        if state.yunzhanghu.orders.contains_key(&data.order_id) {
+            return failure(CODE_DUPLICATE_ORDER, "订单号已存在，订单处理中");
+        }
+
+        let scenario =
+            state.scenario(ScenarioKind::YunzhanghuOrder, &data.order_id);
+        if let super::Outcome::Reject { code, message } = &scenario.outcome {
+            return failure(
+                code.as_deref().unwrap_or(CODE_REJECTED),
+                message.as_deref().unwrap_or("模拟拒绝"),
+            );
+        }
+
+        let mut order = YzhOrder {
+            order_id: data.order_id.clone(),
+            ref_id: format!("mock-{}", Uuid::new_v4().simple()),
+            pay: data.pay,
+            real_name: data.real_name,
+            card_no: data.card_no,
+            id_card: data.id_card,
+            phone_no: data.phone_no,
+            notify_url: data.notify_url.unwrap_or_default(),
+            status: String::new(),
+            status_detail_message: String::new(),
+            progress: Progress::start(&scenario.outcome),
+            delivery: scenario.delivery,
+        };
+        order.settle();
+
+        let response = json!({
+            "order_id": order.order_id,
+            "ref": order.ref_id,
+            "pay": order.pay,
+        });
+        let notify = (!order.progress.is_pending()).then(|| {
+            (
+                order.notify_url.clone(),
+                order.notify_payload(creds),
+                order.delivery.clone(),
+            )
+        });
+        state.yunzhanghu.orders.insert(data.order_id, order);
+        (response, notify)
+    };
+
+    if let Some((url, payload, delivery)) = notify {
+        send_notify("order", url, payload, &delivery, creds);
+    }
+
+    success(creds, &response)
+}
+
+#[derive(Deserialize)]
+struct CalcTaxData {
+    pay: String,
+}
+
+/// 税费试算，模拟服务不计税
+#[post("api/payment/v1/calc-tax")]
+pub async fn calc_tax(
+    req: HttpRequest,
+    body: web::Form<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) = match accept::<CalcTaxData>(&req, &body, "calc_tax") {
+        Ok(x) => x,
+        Err(resp) => return resp,
+    };
+
+    success(
+        creds,
+        &json!({
+            "pay": data.pay,
+            "before_tax_amount": data.pay,
+            "after_tax_amount": data.pay,
+            "user_real_excluding_vat_amount": data.pay,
+            "tax": "0.00",
+            "user_tax": "0.00",
+            "dealer_tax": "0.00",
+            "broker_tax": "0.00",
+            "user_fee": "0.00",
+            "status": "1",
+            "status_detail": "0",
+            "status_message": "",
+            "status_detail_message": "",
+            "tax_detail": {},
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+struct QueryOrderData {
+    order_id: String,
+}
+
+#[get("api/payment/v1/query-order")]
+pub async fn query_order(
+    req: HttpRequest,
+    body: web::Query<SignedRequest>,
+) -> HttpResponse {
+    let (creds, data) =
+        match accept::<QueryOrderData>(&req, &body, "query_order") {
+            Ok(x) => x,
+            Err(resp) => return resp,
+        };
+
+    let (response, notify) = {
+        let mut state = state();
+        let Some(order) = state.yunzhanghu.orders.get_mut(&data.order_id)
+        else {
+            return failure(CODE_NOT_FOUND, "订单不存在");
+        };
+
+        let settled = order.progress.poll();
+        order.settle();
+
+        let paid = if order.status == "1" {
+            order.pay.as_str()
+        } else {
+            ""
+        };
+        let response = json!({
+            "order_id": order.order_id,
+            "ref": order.ref_id,
+            "pay": order.pay,
+            "user_real_amount": paid,
+            "user_real_excluding_vat_amount": paid,
+            "user_fee": "0.00",
+            "received_user_fee": "0.00",
+            "tax": "0.00",
+            "received_tax_amount": "0.00",
+            "tax_detail": {},
+            "status": order.status,
+            "status_detail": if order.status == "2" { "1" } else { "0" },
+            "status_message": "",
+            "status_detail_message": order.status_detail_message,
+            "refund_origin": if order.status == "4" { "2" } else { "" },
+        });
+        let notify = settled.then(|| {
+            (
+                order.notify_url.clone(),
+                order.notify_payload(creds),
+                order.delivery.clone(),
+            )
+        });
+        (response, notify)
+    };
+
+    if let Some((url, payload, delivery)) = notify {
+        send_notify("order", url, payload, &delivery, creds);
+    }
+
+    success(creds, &response)
+}
+
+#[derive(Deserialize)]
+pub struct OrderNotifyRequest {
+    /// 订单主状态：`0` 处理中 / `1` 成功 / `2` 失败 / `3` 挂起 / `4` 退汇 / `5` 撤销
+    pub status: String,
+    pub status_detail_message: Option<String>,
+    #[serde(flatten)]
+    pub delivery: DeliveryOptions,
+}
+
+/// 修改模拟订单状态并投递订单回调
+#[post("orders/{order_id}/notify", guard = "admin_key_guard")]
+pub async fn order_notify(
+    path: web::Path<String>,
+    body: web::Json<OrderNotifyRequest>,
+) -> HttpResponse {
+    let creds = match secrets::load() {
+        Ok(creds) => creds,
+        Err(e) => {
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    };
+    let body = body.into_inner();
+
+    let (url, payload) = {
+        let mut state = state();
+        let Some(order) = state.yunzhanghu.orders.get_mut(&*path) else {
+            return HttpResponse::NotFound().finish();
+        };
+
+        order.progress = if body.status == "0" {
+            Progress::Pending { polls_left: None }
+        } else {
+            Progress::Succeeded
+        };
+        order.status = body.status;
+        order.status_detail_message =
+            body.status_detail_message.unwrap_or_default();
+        (order.notify_url.clone(), order.notify_payload(creds))
+    };
+
+    send_notify("order", url, payload, &body.delivery, creds);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+
+    const DES_KEY: &str = "123456788765432112345678";
+    const APP_KEY: &str = "78f9b4fad3481fbce1df0b30eee58577";
+
+    fn key_pair() -> (String, String) {
+        let mut rng = rand::thread_rng();
+        let private = RsaPrivateKey::new(&mut rng, 2048).unwrap();
+        let public = RsaPublicKey::from(&private);
+        (
+            private.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+            public.to_public_key_pem(LineEnding::LF).unwrap(),
+        )
+    }
+
+    fn to_request(form: Vec<(&'static str, String)>) -> SignedRequest {
+        let form: HashMap<_, _> = form.into_iter().collect();
+        SignedRequest {
+            data: form["data"].clone(),
+            mess: form["mess"].clone(),
+            timestamp: form["timestamp"].clone(),
+            sign: form["sign"].clone(),
+            sign_type: form["sign_type"].clone(),
+        }
+    }
+
+    #[test]
+    fn sealed_notify_opens_with_public_key() {
+        let (private, public) = key_pair();
+        assert_eq!(public_key_pem_from_private(&private).unwrap(), public);
+
+        let payload = json!({ "order_id": "bbsmc-1", "status": "1" });
+        let form =
+            seal_notify(&payload, DES_KEY, APP_KEY, &private, false).unwrap();
+        let opened =
+            open_request(&to_request(form), DES_KEY, APP_KEY, &public).unwrap();
+        assert_eq!(opened, payload);
+
+        let form =
+            seal_notify(&payload, DES_KEY, APP_KEY, &private, true).unwrap();
+        assert!(matches!(
+            open_request(&to_request(form), DES_KEY, APP_KEY, &public),
+            Err(YzhError::RsaVerify)
+        ));
+    }
+}
@@ -9,6 +9,8 @@ use actix_web::{HttpResponse, web};
 use futures::FutureExt;
 
 pub mod internal;
+#[cfg(any(test, feature = "mock-providers"))]
+pub mod mock_providers;
 pub mod v2;
 pub mod v3;
 
@@ -142,6 +144,8 @@ pub enum ApiError {
         "该资源正在被 {0} 修改百科页面，请等待其他用户修改完并且被审核完成后再进行提交修改"
     )]
     ISConflict(String),
+    #[error("百科修改与其他人的编辑存在冲突，已退回给提交者处理: {0}")]
+    WikiConflict(String),
     #[error("您的请求过于频繁，请等待 {0} 毫秒后重试。剩余配额: 0/{1}")]
     RateLimitError(u128, u32),
     #[error("与支付处理器交互时出错: {0}")]
@@ -186,6 +190,7 @@ impl ApiError {
                 ApiError::NotFound => "not_found",
                 ApiError::ISExists => "is_exists",
                 ApiError::ISConflict(..) => "is_conflict",
+                ApiError::WikiConflict(..) => "wiki_conflict",
                 ApiError::Zip(..) => "zip_error",
                 ApiError::Io(..) => "io_error",
                 ApiError::RateLimitError(..) => "ratelimit_error",
@@ -227,6 +232,7 @@ impl actix_web::ResponseError for ApiError {
             ApiError::NotFound => StatusCode::NOT_FOUND,
             ApiError::ISExists => StatusCode::BAD_REQUEST,
             ApiError::ISConflict(..) => StatusCode::BAD_REQUEST,
+            ApiError::WikiConflict(..) => StatusCode::CONFLICT,
             ApiError::Zip(..) => StatusCode::BAD_REQUEST,
             ApiError::Io(..) => StatusCode::BAD_REQUEST,
             ApiError::RateLimitError(..) => StatusCode::TOO_MANY_REQUESTS,
@@ -11,6 +11,7 @@ use crate::queue::session::AuthQueue;
 use crate::routes::v3::project_creation::default_project_type;
 use crate::routes::v3::project_creation::{CreateError, NewGalleryItem};
 use crate::routes::{v2_reroute, v3};
+use crate::util::ratelimit::{RateLimit, UPLOAD};
 use actix_multipart::Multipart;
 use actix_web::web::Data;
 use actix_web::{HttpRequest, HttpResponse, post};
@@ -135,7 +136,7 @@ struct ProjectCreateData {
     pub organization_id: Option<models::ids::OrganizationId>,
 }
 
-#[post("project")]
+#[post("project", wrap = "RateLimit(&UPLOAD)")]
 pub async fn project_create(
     req: HttpRequest,
     payload: Multipart,
@@ -204,6 +205,7 @@ pub async fn project_create(
                         fields,
                         disk_only: v.disk_only,
                         disk_urls: v.disk_urls,
+                        convert_to_mrpack: v.convert_to_mrpack,
                     }
                 })
                 .collect();
@@ -14,6 +14,7 @@ use crate::queue::session::AuthQueue;
 use crate::routes::v3::projects::ProjectIds;
 use crate::routes::{ApiError, v2_reroute, v3};
 use crate::search::{SearchConfig, SearchError, search_for_project};
+use crate::util::ratelimit::{RateLimit, SEARCH};
 use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -51,9 +52,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
-#[get("search")]
+#[get("search", wrap = "RateLimit(&SEARCH)")]
 pub async fn project_search(
     web::Query(info): web::Query<SearchRequest>,
+    pool: web::Data<PgPool>,
     config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, SearchError> {
     // 搜索现在使用 loader_fields 而不是显式的 'client_side' 和 'server_side' 字段
@@ -105,7 +107,7 @@ pub async fn project_search(
         ..info
     };
 
-    let results = search_for_project(&info, &config).await?;
+    let results = search_for_project(&info, &config, &pool).await?;
 
     let results = LegacySearchResults::from(results);
 
@@ -14,6 +14,7 @@ use crate::queue::session::AuthQueue;
 use crate::routes::v3::project_creation::CreateError;
 use crate::routes::v3::version_creation;
 use crate::routes::{v2_reroute, v3};
+use crate::util::ratelimit::{RateLimit, UPLOAD};
 use actix_multipart::Multipart;
 use actix_web::http::header::ContentDisposition;
 use actix_web::web::Data;
@@ -79,6 +80,8 @@ pub struct InitialVersionData {
     #[serde(default)]
     pub disk_only: bool,
     pub disk_urls: Option<Vec<QueryDisk>>,
+    #[serde(default)]
+    pub convert_to_mrpack: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -89,7 +92,7 @@ struct InitialFileData {
 
 // 在 `/api/v1/version` 下
 #[allow(clippy::too_many_arguments)]
-#[post("version")]
+#[post("version", wrap = "RateLimit(&UPLOAD)")]
 pub async fn version_create(
     req: HttpRequest,
     payload: Multipart,
@@ -257,6 +260,7 @@ pub async fn version_create(
                     fields,
                     disk_only: legacy_create.disk_only,
                     disk_urls: legacy_create.disk_urls,
+                    convert_to_mrpack: legacy_create.convert_to_mrpack,
                 })
             }
         },
@@ -316,7 +320,7 @@ async fn get_example_version_fields(
 
 // 在 /api/v1/version/{version_id} 下
 #[allow(clippy::too_many_arguments)]
-#[post("{version_id}/file")]
+#[post("{version_id}/file", wrap = "RateLimit(&UPLOAD)")]
 pub async fn upload_file_to_version(
     req: HttpRequest,
     url_data: web::Path<(VersionId,)>,
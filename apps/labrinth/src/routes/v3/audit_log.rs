@@ -0,0 +1,227 @@
+//! 特权操作审计日志 API
+//!
+//! 封禁、申诉处理、资料与图片审核、项目状态变更、提现审核、创作者申请
+//! 审核与管理员密钥接口都会写入统一的审计日志。日志只追加，并按 id 顺序
+//! 构成哈希链，可通过校验接口发现被篡改或删除的记录。
+//!
+//! 权限要求：管理员，SESSION_ACCESS
+
+use crate::auth::validate::check_is_admin_from_headers;
+use crate::database::models::{AuditLogEntry, AuditLogFilter};
+use crate::database::redis::RedisPool;
+use crate::models::ids::UserId;
+use crate::models::pats::Scopes;
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// 单次校验读取的日志条数
+const VERIFY_BATCH_SIZE: i64 = 1000;
+/// 单次请求最多校验的日志条数
+const MAX_VERIFY_ENTRIES: i64 = 100_000;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("audit_log")
+            .service(audit_log_list)
+            .service(audit_log_verify),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<UserId>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogEntryResponse {
+    pub id: i64,
+    pub actor_id: Option<UserId>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub prev_hash: Option<String>,
+    pub hash: String,
+}
+
+impl From<AuditLogEntry> for AuditLogEntryResponse {
+    fn from(x: AuditLogEntry) -> Self {
+        Self {
+            id: x.id,
+            actor_id: x.actor_id.map(UserId::from),
+            action: x.action,
+            target_type: x.target_type,
+            target_id: x.target_id,
+            before: x.before,
+            after: x.after,
+            reason: x.reason,
+            ip: x.ip,
+            created_at: x.created_at,
+            prev_hash: x.prev_hash,
+            hash: x.hash,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditLogResponse {
+    pub items: Vec<AuditLogEntryResponse>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+/// 审计日志列表，按时间倒序
+#[get("")]
+pub async fn audit_log_list(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::SESSION_ACCESS]),
+    )
+    .await?;
+
+    let query = query.into_inner();
+    let page = query.page.unwrap_or(1).clamp(1, 10_000);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 200);
+
+    let filter = AuditLogFilter {
+        actor_id: query.actor_id.map(Into::into),
+        action: query.action.filter(|x| !x.is_empty()),
+        target_type: query.target_type.filter(|x| !x.is_empty()),
+        target_id: query.target_id,
+        start: query.start,
+        end: query.end,
+    };
+
+    let total = AuditLogEntry::count(&filter, &**pool).await?;
+    let entries = AuditLogEntry::get_many(
+        &filter,
+        page_size,
+        (page - 1) * page_size,
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(AuditLogResponse {
+        items: entries
+            .into_iter()
+            .map(AuditLogEntryResponse::from)
+            .collect(),
+        total,
+        page,
+        page_size,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogVerifyQuery {
+    /// 从该 ID 之后开始校验，默认从第一条开始
+    pub after_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogVerifyResponse {
+    /// 本次校验的日志条数
+    pub checked: i64,
+    /// 最后一条校验通过的日志 ID，可作为下一次校验的 `after_id`
+    pub last_id: Option<i64>,
+    /// 第一条哈希不一致的日志 ID，为空表示本段哈希链完整
+    pub broken_at: Option<i64>,
+}
+
+/// 校验哈希链
+#[get("verify")]
+pub async fn audit_log_verify(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<AuditLogVerifyQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::SESSION_ACCESS]),
+    )
+    .await?;
+
+    let mut after_id = query.after_id.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(MAX_VERIFY_ENTRIES)
+        .clamp(1, MAX_VERIFY_ENTRIES);
+
+    let mut prev_hash = if after_id > 0 {
+        AuditLogEntry::get_prev_hash(after_id + 1, &**pool).await?
+    } else {
+        None
+    };
+
+    let mut checked = 0;
+    let mut last_id = None;
+    let mut broken_at = None;
+
+    while checked < limit {
+        let entries = AuditLogEntry::get_chain(
+            after_id,
+            VERIFY_BATCH_SIZE.min(limit - checked),
+            &**pool,
+        )
+        .await?;
+        let Some(last) = entries.last() else {
+            break;
+        };
+
+        if let Some(id) =
+            AuditLogEntry::verify_chain(prev_hash.as_deref(), &entries)
+        {
+            checked += entries.iter().take_while(|x| x.id < id).count() as i64;
+            broken_at = Some(id);
+            last_id = entries
+                .iter()
+                .take_while(|x| x.id < id)
+                .last()
+                .map(|x| x.id)
+                .or(last_id);
+            break;
+        }
+
+        checked += entries.len() as i64;
+        after_id = last.id;
+        last_id = Some(last.id);
+        prev_hash = Some(last.hash.clone());
+    }
+
+    Ok(HttpResponse::Ok().json(AuditLogVerifyResponse {
+        checked,
+        last_id,
+        broken_at,
+    }))
+}
@@ -8,6 +8,7 @@ use log::info;
 use sqlx::PgPool;
 
 use crate::auth::get_user_from_headers;
+use crate::database::models::AuditLogBuilder;
 use crate::database::models::ids::{
     generate_ban_appeal_id, generate_ban_history_id, generate_user_ban_id,
 };
@@ -29,6 +30,7 @@ use crate::models::v3::bans::{
 use crate::models::v3::notifications::NotificationBody;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
+use crate::util::ip::request_ip;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -128,6 +130,24 @@ pub async fn create_ban(
     )
     .await?;
 
+    let target_user_id = parse_user_id(&info.0)?;
+    let response =
+        issue_ban(&req, &user, target_user_id, &body, &pool, &redis).await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+/// 由管理员或版主封禁用户
+///
+/// 供封禁接口与举报处理共用，包含权限层级检查、历史、通知与审计日志。
+pub(crate) async fn issue_ban(
+    req: &HttpRequest,
+    user: &crate::models::users::User,
+    target_user_id: UserId,
+    body: &CreateBanRequest,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<crate::models::v3::bans::UserBan, ApiError> {
     if !is_admin_or_moderator(&user.role) {
         return Err(ApiError::CustomAuthentication(
             "您没有权限执行此操作".to_string(),
@@ -155,7 +175,6 @@ pub async fn create_ban(
         ));
     }
 
-    let target_user_id = parse_user_id(&info.0)?;
     let admin_user_id: UserId = user.id.into();
 
     // 不能封禁自己
@@ -165,7 +184,7 @@ pub async fn create_ban(
 
     // 检查目标用户是否存在
     let target_user =
-        crate::database::models::User::get_id(target_user_id, &**pool, &redis)
+        crate::database::models::User::get_id(target_user_id, pool, redis)
             .await?
             .ok_or_else(|| ApiError::NotFound)?;
 
@@ -187,7 +206,7 @@ pub async fn create_ban(
         crate::models::v3::bans::BanType::Resource => "资源",
         crate::models::v3::bans::BanType::Forum => "论坛",
     };
-    if UserBan::is_user_banned(target_user_id, ban_type.clone(), &pool, &redis)
+    if UserBan::is_user_banned(target_user_id, ban_type.clone(), pool, redis)
         .await?
     {
         return Err(ApiError::InvalidInput(format!(
@@ -255,7 +274,7 @@ pub async fn create_ban(
     .await?;
 
     // 发送通知
-    if body.notify_user {
+    let push_events = if body.notify_user {
         NotificationBuilder {
             body: NotificationBody::UserBanned {
                 ban_id: crate::models::v3::bans::UserBanId(ban_id.0 as u64),
@@ -264,17 +283,39 @@ pub async fn create_ban(
                 expires_at: body.expires_at,
             },
         }
-        .insert(target_user_id, &mut transaction, &redis)
-        .await?;
+        .insert(target_user_id, &mut transaction, redis)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    AuditLogBuilder {
+        actor_id: Some(admin_user_id),
+        action: "ban.create".to_string(),
+        target_type: "user_ban".to_string(),
+        target_id: Some(ban_id.0),
+        before: None,
+        after: Some(serde_json::json!({
+            "user_id": target_user_id.0,
+            "ban_type": ban.ban_type,
+            "reason": ban.reason,
+            "internal_reason": ban.internal_reason,
+            "expires_at": ban.expires_at,
+        })),
+        reason: Some(body.reason.clone()),
+        ip: request_ip(req),
     }
+    .insert(&mut transaction)
+    .await?;
 
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, redis).await;
 
     // 清除用户缓存（包含active_bans字段）并清理锁键
     // 使用clear_caches_with_locks防止封禁后立即访问时的锁超时问题
     crate::database::models::User::clear_caches_with_locks(
         &[(target_user_id, Some(target_user.username.clone()))],
-        &redis,
+        redis,
     )
     .await?;
 
@@ -285,22 +326,20 @@ pub async fn create_ban(
     );
 
     // 构建响应
-    let response = crate::models::v3::bans::UserBan {
+    Ok(crate::models::v3::bans::UserBan {
         id: crate::models::v3::bans::UserBanId(ban_id.0 as u64),
         user_id: crate::models::ids::UserId(target_user_id.0 as u64),
         ban_type: body.ban_type.clone(),
         reason: ban.reason,
         internal_reason: ban.internal_reason,
         banned_by: crate::models::ids::UserId(admin_user_id.0 as u64),
-        banned_by_username: Some(user.username),
+        banned_by_username: Some(user.username.clone()),
         banned_at: ban.banned_at,
         expires_at: ban.expires_at,
         is_active: true,
         can_appeal: Some(true),
         appeal: None,
-    };
-
-    Ok(HttpResponse::Created().json(response))
+    })
 }
 
 /// 获取封禁详情
@@ -481,6 +520,25 @@ pub async fn update_ban(
     )
     .await?;
 
+    let new_data = serde_json::json!({
+        "reason": body.reason,
+        "internal_reason": body.internal_reason,
+        "expires_at": body.expires_at,
+    });
+
+    AuditLogBuilder {
+        actor_id: Some(admin_user_id),
+        action: "ban.update".to_string(),
+        target_type: "user_ban".to_string(),
+        target_id: Some(ban_id.0),
+        before: Some(old_data.clone()),
+        after: Some(new_data.clone()),
+        reason: body.modification_reason.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut transaction)
+    .await?;
+
     // 记录历史
     let history_id = generate_ban_history_id(&mut transaction).await?;
     BanHistory::insert(
@@ -490,11 +548,7 @@ pub async fn update_ban(
             action: "modified".to_string(),
             operator_id: admin_user_id,
             old_data: Some(old_data),
-            new_data: serde_json::json!({
-                "reason": body.reason,
-                "internal_reason": body.internal_reason,
-                "expires_at": body.expires_at,
-            }),
+            new_data,
             reason: body
                 .modification_reason
                 .clone()
@@ -611,7 +665,7 @@ pub async fn revoke_ban(
     .await?;
 
     // 发送通知
-    if body.notify_user {
+    let push_events = if body.notify_user {
         NotificationBuilder {
             body: NotificationBody::UserUnbanned {
                 ban_id: crate::models::v3::bans::UserBanId(ban_id.0 as u64),
@@ -620,10 +674,30 @@ pub async fn revoke_ban(
             },
         }
         .insert(ban.user_id, &mut transaction, &redis)
-        .await?;
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    AuditLogBuilder {
+        actor_id: Some(admin_user_id),
+        action: "ban.revoke".to_string(),
+        target_type: "user_ban".to_string(),
+        target_id: Some(ban_id.0),
+        before: Some(serde_json::json!({
+            "user_id": ban.user_id.0,
+            "ban_type": ban.ban_type,
+            "is_active": true,
+        })),
+        after: Some(serde_json::json!({ "is_active": false })),
+        reason: Some(body.reason.clone()),
+        ip: request_ip(&req),
     }
+    .insert(&mut transaction)
+    .await?;
 
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     // 清除用户缓存（包含active_bans字段）并清理锁键
     crate::database::models::User::clear_caches_with_locks(
@@ -1174,7 +1248,7 @@ pub async fn review_appeal(
     };
 
     // 发送通知
-    if body.notify_user {
+    let push_events = if body.notify_user {
         NotificationBuilder {
             body: NotificationBody::AppealReviewed {
                 appeal_id: crate::models::v3::bans::BanAppealId(
@@ -1188,10 +1262,33 @@ pub async fn review_appeal(
             },
         }
         .insert(appeal.user_id, &mut transaction, &redis)
-        .await?;
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    AuditLogBuilder {
+        actor_id: Some(admin_user_id),
+        action: "ban_appeal.review".to_string(),
+        target_type: "ban_appeal".to_string(),
+        target_id: Some(appeal_id.0),
+        before: Some(serde_json::json!({
+            "ban_id": appeal.ban_id.0,
+            "user_id": appeal.user_id.0,
+            "status": appeal.status,
+        })),
+        after: Some(serde_json::json!({
+            "status": body.status.as_str(),
+            "ban_revoked": cache_to_clear.is_some(),
+        })),
+        reason: body.review_notes.clone(),
+        ip: request_ip(&req),
     }
+    .insert(&mut transaction)
+    .await?;
 
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
@@ -0,0 +1,755 @@
+//! 项目捆绑包 API
+//!
+//! 创作者或组织可以把自己的多个付费项目打包，以低于单独购买总价的价格出售。
+//! 买家购买捆绑包时，已拥有的项目按原价比例从捆绑包价格中扣除，只需为剩余项目付款。
+//! 支付款项进入单个卖家的商户账户，因此捆绑包中的项目必须属于同一卖家。
+//!
+//! 权限要求：
+//! - 用户捆绑包：只能包含自己作为所有者的项目
+//! - 组织捆绑包：只能包含组织下的项目，需要组织成员权限 EDIT_DETAILS
+
+use super::ApiError;
+use super::project_order::get_project_seller;
+use crate::auth::checks::is_visible_project;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ids::{
+    OrganizationId as DBOrganizationId, ProjectId as DBProjectId,
+    UserId as DBUserId,
+};
+use crate::database::models::project_bundle_item::split_amount;
+use crate::database::models::{
+    OrderItemPrice, ProjectBundle as DBProjectBundle, ProjectPricing,
+    UserPurchase, generate_project_bundle_id,
+};
+use crate::database::redis::RedisPool;
+use crate::models::bundles::ProjectBundle;
+use crate::models::ids::{ProjectBundleId, ProjectId};
+use crate::models::pats::Scopes;
+use crate::models::teams::OrganizationPermissions;
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// 单个捆绑包最多包含的项目数
+const MAX_BUNDLE_PROJECTS: usize = 20;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("bundle")
+            .route("", web::get().to(bundles_get))
+            .route("", web::post().to(bundle_create))
+            .route("{id}", web::get().to(bundle_get))
+            .route("{id}", web::patch().to(bundle_edit))
+            .route("{id}", web::delete().to(bundle_delete))
+            .route("{id}/quote", web::get().to(bundle_quote)),
+    );
+}
+
+/// 创建捆绑包请求
+#[derive(Deserialize, Validate)]
+pub struct BundleCreate {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "名称长度必须在 1-64 之间"
+    ))]
+    pub name: String,
+    #[validate(length(max = 2048, message = "简介不能超过 2048 个字符"))]
+    #[serde(default)]
+    pub description: String,
+    /// 捆绑包价格（元）
+    pub price: Decimal,
+    /// 项目 ID 或 slug
+    #[validate(length(
+        min = 2,
+        max = 20,
+        message = "捆绑包需要包含 2-20 个项目"
+    ))]
+    pub project_ids: Vec<String>,
+    /// 填写时创建组织捆绑包
+    pub organization_id: Option<String>,
+}
+
+/// 修改捆绑包请求
+#[derive(Deserialize, Validate)]
+pub struct BundleEdit {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "名称长度必须在 1-64 之间"
+    ))]
+    pub name: Option<String>,
+    #[validate(length(max = 2048, message = "简介不能超过 2048 个字符"))]
+    pub description: Option<String>,
+    pub price: Option<Decimal>,
+    #[validate(length(
+        min = 2,
+        max = 20,
+        message = "捆绑包需要包含 2-20 个项目"
+    ))]
+    pub project_ids: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+/// 捆绑包中单个项目的报价
+#[derive(Serialize)]
+pub struct BundleQuoteItem {
+    pub project_id: ProjectId,
+    /// 单独购买的原价（元）
+    pub original_amount: Decimal,
+    /// 分摊到该项目的支付金额（元）
+    pub amount: Decimal,
+}
+
+/// 捆绑包报价响应
+#[derive(Serialize)]
+pub struct BundleQuoteResponse {
+    /// 全部项目单独购买的原价总和（元）
+    pub list_price: Decimal,
+    /// 捆绑包价格（元）
+    pub bundle_price: Decimal,
+    /// 扣除已拥有项目后需支付的金额（元）
+    pub amount: Decimal,
+    /// 已拥有、不再收费的项目
+    pub owned_projects: Vec<ProjectId>,
+    pub items: Vec<BundleQuoteItem>,
+}
+
+/// 捆绑包报价
+pub struct BundleQuote {
+    pub list_price: Decimal,
+    pub bundle_price: Decimal,
+    pub owned_projects: Vec<DBProjectId>,
+    /// 需要付款的项目及分摊金额
+    pub items: Vec<OrderItemPrice>,
+    pub seller_id: DBUserId,
+}
+
+impl BundleQuote {
+    pub fn amount(&self) -> Decimal {
+        self.items.iter().map(|x| x.amount).sum()
+    }
+}
+
+/// 计算用户购买捆绑包的价格
+///
+/// 已拥有的项目按其原价在捆绑包原价总和中的占比，从捆绑包价格中扣除；
+/// 剩余金额按各项目原价比例分摊到订单明细，用于按项目结算创作者收入。
+/// 捆绑包中有项目对 `viewer` 不可见（私有、被拒绝、被扣留等）时不可购买。
+pub async fn quote_bundle(
+    bundle: &DBProjectBundle,
+    viewer: &Option<User>,
+    user_id: Option<DBUserId>,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<BundleQuote, ApiError> {
+    let projects =
+        database::models::Project::get_many_ids(&bundle.projects, pool, redis)
+            .await?;
+    let pricing = ProjectPricing::get_many(&bundle.projects, pool).await?;
+
+    let mut seller_id = None;
+    let mut prices = Vec::with_capacity(bundle.projects.len());
+    for project_id in &bundle.projects {
+        let project = projects
+            .iter()
+            .find(|x| x.inner.id == *project_id && x.inner.is_paid);
+        let pricing = pricing.iter().find(|x| x.project_id == *project_id);
+        let (Some(project), Some(pricing)) = (project, pricing) else {
+            return Err(ApiError::InvalidInput(
+                "捆绑包中有项目暂不可购买".to_string(),
+            ));
+        };
+        if !is_visible_project(&project.inner, viewer, pool, false).await? {
+            return Err(ApiError::InvalidInput(
+                "捆绑包中有项目暂不可购买".to_string(),
+            ));
+        }
+
+        let seller = get_project_seller(&project.inner, pool, redis).await?;
+        if seller_id.is_some_and(|x| x != seller) {
+            return Err(ApiError::InvalidInput(
+                "捆绑包中的项目属于不同卖家".to_string(),
+            ));
+        }
+        seller_id = Some(seller);
+        prices.push(pricing);
+    }
+
+    let seller_id = seller_id.ok_or_else(|| {
+        ApiError::InvalidInput("捆绑包中没有项目".to_string())
+    })?;
+
+    let list_price: Decimal = prices.iter().map(|x| x.price).sum();
+    let bundle_price = bundle.price.min(list_price);
+
+    let mut owned_projects = Vec::new();
+    let mut remaining = Vec::new();
+    for pricing in prices {
+        let owned = match user_id {
+            Some(user_id) => {
+                UserPurchase::check_access(user_id, pricing.project_id, pool)
+                    .await?
+            }
+            None => false,
+        };
+        if owned {
+            owned_projects.push(pricing.project_id);
+        } else {
+            remaining.push(pricing);
+        }
+    }
+
+    if remaining.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "您已拥有捆绑包中的全部项目".to_string(),
+        ));
+    }
+
+    let remaining_list: Decimal = remaining.iter().map(|x| x.price).sum();
+    let amount = if owned_projects.is_empty() || list_price.is_zero() {
+        bundle_price
+    } else {
+        (bundle_price * remaining_list / list_price)
+            .round_dp(2)
+            .max(Decimal::new(1, 2))
+    };
+
+    let weights: Vec<Decimal> = remaining.iter().map(|x| x.price).collect();
+    let items = remaining
+        .iter()
+        .zip(split_amount(amount, &weights))
+        .map(|(pricing, amount)| OrderItemPrice {
+            project_id: pricing.project_id,
+            original_amount: pricing.price,
+            amount,
+            validity_days: pricing.validity_days,
+            sale_id: None,
+        })
+        .collect();
+
+    Ok(BundleQuote {
+        list_price,
+        bundle_price,
+        owned_projects,
+        items,
+        seller_id,
+    })
+}
+
+/// 捆绑包内项目的原价总和
+async fn bundle_list_price(
+    bundle: &DBProjectBundle,
+    pool: &PgPool,
+) -> Result<Decimal, ApiError> {
+    Ok(ProjectPricing::get_many(&bundle.projects, pool)
+        .await?
+        .iter()
+        .map(|x| x.price)
+        .sum())
+}
+
+/// 获取当前用户的捆绑包
+///
+/// GET /v3/bundle
+pub async fn bundles_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let bundles = DBProjectBundle::get_user(user.id.into(), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(bundles_with_price(bundles, &pool).await?))
+}
+
+/// 获取包含项目的上架捆绑包
+///
+/// GET /v3/project/{id}/bundles
+pub async fn project_bundles_get(
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    let bundles =
+        DBProjectBundle::get_active_for_project(project.inner.id, &**pool)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(bundles_with_price(bundles, &pool).await?))
+}
+
+/// 获取组织的捆绑包
+///
+/// GET /v3/organization/{id}/bundles
+///
+/// 组织管理成员可以看到未上架的捆绑包，其他用户只能看到上架中的捆绑包。
+pub async fn organization_bundles_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user_option = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::ORGANIZATION_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    let organization = database::models::Organization::get(
+        &info.into_inner().0,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    let can_manage = match &user_option {
+        Some(user) => check_organization_permission(user, &organization, &pool)
+            .await
+            .is_ok(),
+        None => false,
+    };
+
+    let bundles = DBProjectBundle::get_organization(organization.id, &**pool)
+        .await?
+        .into_iter()
+        .filter(|x| x.active || can_manage)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(bundles_with_price(bundles, &pool).await?))
+}
+
+/// 获取捆绑包
+///
+/// GET /v3/bundle/{id}
+pub async fn bundle_get(
+    req: HttpRequest,
+    info: web::Path<(ProjectBundleId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let bundle = DBProjectBundle::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if !bundle.active {
+        let user = get_user_from_headers(
+            &req,
+            &**pool,
+            &redis,
+            &session_queue,
+            Some(&[Scopes::PROJECT_READ]),
+        )
+        .await
+        .map(|x| x.1)
+        .map_err(|_| ApiError::NotFound)?;
+
+        check_bundle_permission(&user, &bundle, &pool, &redis)
+            .await
+            .map_err(|_| ApiError::NotFound)?;
+    }
+
+    let list_price = bundle_list_price(&bundle, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(ProjectBundle::from(bundle, list_price)))
+}
+
+/// 捆绑包报价，登录用户会扣除已拥有的项目
+///
+/// GET /v3/bundle/{id}/quote
+pub async fn bundle_quote(
+    req: HttpRequest,
+    info: web::Path<(ProjectBundleId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user_option = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    let bundle = DBProjectBundle::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .filter(|x| x.active)
+        .ok_or(ApiError::NotFound)?;
+
+    let user_id = user_option.as_ref().map(|x| x.id.into());
+    let quote =
+        quote_bundle(&bundle, &user_option, user_id, &pool, &redis).await?;
+
+    Ok(HttpResponse::Ok().json(BundleQuoteResponse {
+        list_price: quote.list_price,
+        bundle_price: quote.bundle_price,
+        amount: quote.amount(),
+        owned_projects: quote
+            .owned_projects
+            .iter()
+            .map(|x| (*x).into())
+            .collect(),
+        items: quote
+            .items
+            .into_iter()
+            .map(|x| BundleQuoteItem {
+                project_id: x.project_id.into(),
+                original_amount: x.original_amount,
+                amount: x.amount,
+            })
+            .collect(),
+    }))
+}
+
+/// 创建捆绑包
+///
+/// POST /v3/bundle
+pub async fn bundle_create(
+    req: HttpRequest,
+    body: web::Json<BundleCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let organization_id = match &body.organization_id {
+        Some(id) => {
+            let organization =
+                database::models::Organization::get(id, &**pool, &redis)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::InvalidInput("组织不存在".to_string())
+                    })?;
+            check_organization_permission(&user, &organization, &pool).await?;
+            Some(organization.id)
+        }
+        None => None,
+    };
+
+    let user_id = if organization_id.is_some() {
+        None
+    } else {
+        Some(user.id.into())
+    };
+    let projects = resolve_bundle_projects(
+        user_id,
+        organization_id,
+        &body.project_ids,
+        &pool,
+        &redis,
+    )
+    .await?;
+    validate_bundle_price(body.price, &projects, &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+    let bundle = DBProjectBundle {
+        id: generate_project_bundle_id(&mut transaction).await?,
+        user_id,
+        organization_id,
+        name: body.name.trim().to_string(),
+        description: body.description.clone(),
+        price: body.price,
+        active: true,
+        projects,
+        created_by: user.id.into(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    bundle.insert(&mut transaction).await?;
+    transaction.commit().await?;
+
+    let list_price = bundle_list_price(&bundle, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(ProjectBundle::from(bundle, list_price)))
+}
+
+/// 修改捆绑包
+///
+/// PATCH /v3/bundle/{id}
+pub async fn bundle_edit(
+    req: HttpRequest,
+    info: web::Path<(ProjectBundleId,)>,
+    body: web::Json<BundleEdit>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let mut bundle =
+        DBProjectBundle::get_id(info.into_inner().0.into(), &**pool)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_bundle_permission(&user, &bundle, &pool, &redis).await?;
+
+    if let Some(name) = &body.name {
+        bundle.name = name.trim().to_string();
+    }
+    if let Some(description) = &body.description {
+        bundle.description.clone_from(description);
+    }
+    if let Some(price) = body.price {
+        bundle.price = price;
+    }
+    if let Some(active) = body.active {
+        bundle.active = active;
+    }
+    if let Some(project_ids) = &body.project_ids {
+        bundle.projects = resolve_bundle_projects(
+            bundle.user_id,
+            bundle.organization_id,
+            project_ids,
+            &pool,
+            &redis,
+        )
+        .await?;
+    }
+    validate_bundle_price(bundle.price, &bundle.projects, &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+    bundle.update(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 删除捆绑包，已创建的订单保留但不再关联捆绑包
+///
+/// DELETE /v3/bundle/{id}
+pub async fn bundle_delete(
+    req: HttpRequest,
+    info: web::Path<(ProjectBundleId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let bundle = DBProjectBundle::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    check_bundle_permission(&user, &bundle, &pool, &redis).await?;
+
+    let mut transaction = pool.begin().await?;
+    DBProjectBundle::remove(bundle.id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn bundles_with_price(
+    bundles: Vec<DBProjectBundle>,
+    pool: &PgPool,
+) -> Result<Vec<ProjectBundle>, ApiError> {
+    let mut result = Vec::with_capacity(bundles.len());
+    for bundle in bundles {
+        let list_price = bundle_list_price(&bundle, pool).await?;
+        result.push(ProjectBundle::from(bundle, list_price));
+    }
+    Ok(result)
+}
+
+/// 解析并校验捆绑包中的项目：必须是已定价的付费项目，且归属于捆绑包所有者
+async fn resolve_bundle_projects(
+    user_id: Option<DBUserId>,
+    organization_id: Option<DBOrganizationId>,
+    project_ids: &[String],
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<Vec<DBProjectId>, ApiError> {
+    let projects =
+        database::models::Project::get_many(project_ids, pool, redis).await?;
+
+    let mut ids: Vec<DBProjectId> =
+        projects.iter().map(|x| x.inner.id).collect();
+    ids.sort_by_key(|x| x.0);
+    ids.dedup();
+    if ids.len() < 2 || ids.len() > MAX_BUNDLE_PROJECTS {
+        return Err(ApiError::InvalidInput(format!(
+            "捆绑包需要包含 2-{} 个有效项目",
+            MAX_BUNDLE_PROJECTS
+        )));
+    }
+
+    let pricing = ProjectPricing::get_many(&ids, pool).await?;
+    let mut seller_id = None;
+    for project in &projects {
+        if !project.inner.is_paid
+            || !pricing.iter().any(|x| x.project_id == project.inner.id)
+        {
+            return Err(ApiError::InvalidInput(format!(
+                "项目 {} 不是已定价的付费项目",
+                project.inner.name
+            )));
+        }
+
+        let seller = get_project_seller(&project.inner, pool, redis).await?;
+        let owned = match (user_id, organization_id) {
+            (Some(user_id), _) => seller == user_id,
+            (None, Some(organization_id)) => {
+                project.inner.organization_id == Some(organization_id)
+            }
+            (None, None) => false,
+        };
+        if !owned {
+            return Err(ApiError::InvalidInput(format!(
+                "项目 {} 不属于捆绑包所有者",
+                project.inner.name
+            )));
+        }
+
+        if seller_id.is_some_and(|x| x != seller) {
+            return Err(ApiError::InvalidInput(
+                "捆绑包中的项目必须属于同一卖家".to_string(),
+            ));
+        }
+        seller_id = Some(seller);
+    }
+
+    Ok(ids)
+}
+
+/// 捆绑包价格必须大于 0 且不高于项目原价总和
+async fn validate_bundle_price(
+    price: Decimal,
+    projects: &[DBProjectId],
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    if price <= Decimal::ZERO || price.scale() > 2 {
+        return Err(ApiError::InvalidInput(
+            "捆绑包价格必须大于 0，且最多两位小数".to_string(),
+        ));
+    }
+
+    let list_price: Decimal = ProjectPricing::get_many(projects, pool)
+        .await?
+        .iter()
+        .map(|x| x.price)
+        .sum();
+    if price > list_price {
+        return Err(ApiError::InvalidInput(format!(
+            "捆绑包价格不能高于项目原价总和 {}",
+            list_price
+        )));
+    }
+
+    Ok(())
+}
+
+async fn check_organization_permission(
+    user: &User,
+    organization: &database::models::Organization,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    let team_member = database::models::TeamMember::get_from_user_id(
+        organization.team_id,
+        user.id.into(),
+        pool,
+    )
+    .await?;
+
+    let permissions = OrganizationPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(OrganizationPermissions::EDIT_DETAILS) {
+        return Err(ApiError::CustomAuthentication(
+            "您没有权限管理此组织的捆绑包".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_bundle_permission(
+    user: &User,
+    bundle: &DBProjectBundle,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    if let Some(organization_id) = bundle.organization_id {
+        let organization = database::models::Organization::get_id(
+            organization_id,
+            pool,
+            redis,
+        )
+        .await?
+        .ok_or(ApiError::NotFound)?;
+        check_organization_permission(user, &organization, pool).await
+    } else if bundle.user_id == Some(user.id.into()) || user.role.is_admin() {
+        Ok(())
+    } else {
+        Err(ApiError::CustomAuthentication(
+            "您没有权限管理此捆绑包".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,721 @@
+//! 优惠券 API
+//!
+//! 项目或组织成员可以为付费项目创建优惠码，买家下单时填写优惠码即可按百分比或固定金额减免。
+//! 组织优惠券适用于组织下的所有付费项目；同一优惠码同时存在于项目和组织时优先使用项目优惠券。
+//!
+//! 权限要求：
+//! - 项目优惠券：项目成员权限 EDIT_DETAILS
+//! - 组织优惠券：组织成员权限 EDIT_DETAILS
+
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ids::{
+    CouponId as DBCouponId, PaymentOrderId, UserId as DBUserId,
+};
+use crate::database::models::project_item::Project as DBProject;
+use crate::database::models::{
+    Coupon as DBCoupon, OrderPrice, ProjectPricing, ProjectSale,
+    generate_coupon_id,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::CouponId;
+use crate::models::pats::Scopes;
+use crate::models::promotions::{Coupon, DiscountType};
+use crate::models::teams::{OrganizationPermissions, ProjectPermissions};
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// 单张优惠券最多限定的用户数
+const MAX_ALLOWED_USERS: usize = 200;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("coupon")
+            .route("{id}", web::patch().to(coupon_edit))
+            .route("{id}", web::delete().to(coupon_delete)),
+    );
+}
+
+fn validate_coupon_code(code: &str) -> Result<(), validator::ValidationError> {
+    if code
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        let mut err = validator::ValidationError::new("invalid_coupon_code");
+        err.message = Some("优惠码只能包含字母、数字、- 和 _".into());
+        Err(err)
+    }
+}
+
+/// 创建优惠券请求
+#[derive(Deserialize, Validate)]
+pub struct CouponCreate {
+    #[validate(
+        length(min = 3, max = 32, message = "优惠码长度必须在 3-32 之间"),
+        custom(function = "validate_coupon_code")
+    )]
+    pub code: String,
+    pub discount_type: DiscountType,
+    /// 百分比减免时为 1-99，固定减免时为减免金额（元）
+    pub discount_value: Decimal,
+    #[validate(range(min = 1, message = "使用次数上限必须大于 0"))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1, message = "每人使用次数上限必须大于 0"))]
+    pub per_user_limit: Option<i32>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// 限定可使用的用户（用户名或 ID），不填表示所有用户可用
+    #[serde(default)]
+    pub allowed_users: Vec<String>,
+}
+
+/// 修改优惠券请求；优惠码与折扣创建后不可修改
+#[derive(Deserialize, Validate)]
+pub struct CouponEdit {
+    pub enabled: Option<bool>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[validate(range(min = 1, message = "使用次数上限必须大于 0"))]
+    pub max_uses: Option<Option<i32>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[validate(range(min = 1, message = "每人使用次数上限必须大于 0"))]
+    pub per_user_limit: Option<Option<i32>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub starts_at: Option<Option<DateTime<Utc>>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub ends_at: Option<Option<DateTime<Utc>>>,
+    pub allowed_users: Option<Vec<String>>,
+}
+
+/// 价格试算请求
+#[derive(Deserialize)]
+pub struct QuoteQuery {
+    pub coupon_code: Option<String>,
+}
+
+/// 价格试算响应
+#[derive(Serialize)]
+pub struct QuoteResponse {
+    /// 原价（元）
+    pub original_amount: Decimal,
+    /// 实际支付金额（元）
+    pub amount: Decimal,
+    /// 减免金额（元）
+    pub discount_amount: Decimal,
+    /// 是否处于限时促销
+    pub on_sale: bool,
+    /// 是否使用了优惠券
+    pub coupon_applied: bool,
+}
+
+/// 计算用户购买项目的价格：先取进行中的促销价，再叠加优惠券折扣
+///
+/// 优惠券行会被锁定到事务结束，调用方应在同一事务中创建订单，避免并发下单超出使用次数上限。
+/// `replacing_order` 为即将被替换的待支付订单，不计入优惠券使用次数。
+pub async fn quote_order_price(
+    project: &DBProject,
+    pricing: &ProjectPricing,
+    coupon_code: Option<&str>,
+    user_id: DBUserId,
+    replacing_order: Option<PaymentOrderId>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<OrderPrice, ApiError> {
+    let now = Utc::now();
+
+    let sales =
+        ProjectSale::get_project(pricing.project_id, &mut **transaction)
+            .await?;
+    let sale = ProjectSale::active(&sales, now);
+
+    let mut amount = sale
+        .map(|x| x.sale_price.min(pricing.price))
+        .unwrap_or(pricing.price);
+
+    let mut coupon_id = None;
+    if let Some(code) = coupon_code.map(str::trim).filter(|x| !x.is_empty()) {
+        let coupon = DBCoupon::get_by_code_for_update(
+            code,
+            project.id,
+            project.organization_id,
+            transaction,
+        )
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("优惠码不存在".to_string()))?;
+
+        if !coupon.enabled {
+            return Err(ApiError::InvalidInput("优惠码已停用".to_string()));
+        }
+        if coupon.starts_at.is_some_and(|x| x > now) {
+            return Err(ApiError::InvalidInput("优惠码尚未生效".to_string()));
+        }
+        if coupon.ends_at.is_some_and(|x| x <= now) {
+            return Err(ApiError::InvalidInput("优惠码已过期".to_string()));
+        }
+        if !coupon.allowed_users.is_empty()
+            && !coupon.allowed_users.contains(&user_id)
+        {
+            return Err(ApiError::InvalidInput(
+                "您不在该优惠码的适用范围内".to_string(),
+            ));
+        }
+
+        let usage = DBCoupon::get_usage(
+            coupon.id,
+            user_id,
+            replacing_order,
+            &mut **transaction,
+        )
+        .await?;
+        if coupon.max_uses.is_some_and(|x| usage.total >= x as i64) {
+            return Err(ApiError::InvalidInput(
+                "优惠码已达到使用次数上限".to_string(),
+            ));
+        }
+        if coupon
+            .per_user_limit
+            .is_some_and(|x| usage.user >= x as i64)
+        {
+            return Err(ApiError::InvalidInput(
+                "您已达到该优惠码的使用次数上限".to_string(),
+            ));
+        }
+
+        amount = coupon.discount_type.apply(amount, coupon.discount_value);
+        coupon_id = Some(coupon.id);
+    }
+
+    Ok(OrderPrice {
+        original_amount: pricing.price,
+        amount,
+        coupon_id,
+        sale_id: sale.map(|x| x.id),
+    })
+}
+
+/// 试算购买价格
+///
+/// GET /v3/project/{id}/pricing/quote?coupon_code=
+pub async fn pricing_quote(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    query: web::Query<QuoteQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or_else(|| ApiError::InvalidInput("项目不存在".to_string()))?;
+
+    if !project.inner.is_paid {
+        return Err(ApiError::InvalidInput("该项目不是付费资源".to_string()));
+    }
+
+    let pricing = ProjectPricing::get(project.inner.id, &**pool)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput("该项目尚未设置定价".to_string())
+        })?;
+
+    // 只读试算，事务不提交
+    let mut transaction = pool.begin().await?;
+    let price = quote_order_price(
+        &project.inner,
+        &pricing,
+        query.coupon_code.as_deref(),
+        user.id.into(),
+        None,
+        &mut transaction,
+    )
+    .await?;
+    transaction.rollback().await?;
+
+    Ok(HttpResponse::Ok().json(QuoteResponse {
+        original_amount: price.original_amount,
+        amount: price.amount,
+        discount_amount: price.discount_amount(),
+        on_sale: price.sale_id.is_some(),
+        coupon_applied: price.coupon_id.is_some(),
+    }))
+}
+
+/// 获取项目优惠券
+///
+/// GET /v3/project/{id}/coupons
+pub async fn project_coupons_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_project_permission(&user, &project.inner, &pool).await?;
+
+    let coupons =
+        DBCoupon::get_owned(Some(project.inner.id), None, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(coupons_with_uses(coupons, &pool).await?))
+}
+
+/// 创建项目优惠券
+///
+/// POST /v3/project/{id}/coupons
+pub async fn project_coupon_create(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    body: web::Json<CouponCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_project_permission(&user, &project.inner, &pool).await?;
+
+    if !project.inner.is_paid {
+        return Err(ApiError::InvalidInput(
+            "只有付费资源才能创建优惠券".to_string(),
+        ));
+    }
+
+    let coupon =
+        create_coupon(&user, Some(project.inner.id), None, body, &pool, &redis)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(coupon))
+}
+
+/// 获取组织优惠券
+///
+/// GET /v3/organization/{id}/coupons
+pub async fn organization_coupons_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::ORGANIZATION_READ]),
+    )
+    .await?
+    .1;
+
+    let organization = database::models::Organization::get(
+        &info.into_inner().0,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    check_organization_permission(&user, &organization, &pool).await?;
+
+    let coupons =
+        DBCoupon::get_owned(None, Some(organization.id), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(coupons_with_uses(coupons, &pool).await?))
+}
+
+/// 创建组织优惠券
+///
+/// POST /v3/organization/{id}/coupons
+pub async fn organization_coupon_create(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    body: web::Json<CouponCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::ORGANIZATION_WRITE]),
+    )
+    .await?
+    .1;
+
+    let organization = database::models::Organization::get(
+        &info.into_inner().0,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    check_organization_permission(&user, &organization, &pool).await?;
+
+    let coupon =
+        create_coupon(&user, None, Some(organization.id), body, &pool, &redis)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(coupon))
+}
+
+/// 修改优惠券
+///
+/// PATCH /v3/coupon/{id}
+pub async fn coupon_edit(
+    req: HttpRequest,
+    info: web::Path<(CouponId,)>,
+    body: web::Json<CouponEdit>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let mut coupon = DBCoupon::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    check_coupon_permission(&user, &coupon, &pool, &redis).await?;
+
+    if let Some(enabled) = body.enabled {
+        coupon.enabled = enabled;
+    }
+    if let Some(max_uses) = body.max_uses {
+        coupon.max_uses = max_uses;
+    }
+    if let Some(per_user_limit) = body.per_user_limit {
+        coupon.per_user_limit = per_user_limit;
+    }
+    if let Some(starts_at) = body.starts_at {
+        coupon.starts_at = starts_at;
+    }
+    if let Some(ends_at) = body.ends_at {
+        coupon.ends_at = ends_at;
+    }
+    validate_window(coupon.starts_at, coupon.ends_at)?;
+    if let Some(allowed_users) = &body.allowed_users {
+        coupon.allowed_users =
+            resolve_users(allowed_users, &pool, &redis).await?;
+    }
+
+    let mut transaction = pool.begin().await?;
+    coupon.update(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 删除优惠券；已使用该优惠券的订单保留减免金额
+///
+/// DELETE /v3/coupon/{id}
+pub async fn coupon_delete(
+    req: HttpRequest,
+    info: web::Path<(CouponId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let coupon = DBCoupon::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    check_coupon_permission(&user, &coupon, &pool, &redis).await?;
+
+    let mut transaction = pool.begin().await?;
+    DBCoupon::remove(coupon.id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+async fn create_coupon(
+    user: &User,
+    project_id: Option<database::models::ProjectId>,
+    organization_id: Option<database::models::OrganizationId>,
+    body: web::Json<CouponCreate>,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<Coupon, ApiError> {
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let discount_value = body.discount_value.round_dp(2);
+    match body.discount_type {
+        DiscountType::Percent => {
+            if discount_value < Decimal::ONE
+                || discount_value >= Decimal::ONE_HUNDRED
+            {
+                return Err(ApiError::InvalidInput(
+                    "百分比折扣必须在 1-99 之间".to_string(),
+                ));
+            }
+        }
+        DiscountType::Fixed => {
+            if discount_value <= Decimal::ZERO
+                || discount_value > Decimal::from(1000)
+            {
+                return Err(ApiError::InvalidInput(
+                    "减免金额必须在 0.01-1000 之间".to_string(),
+                ));
+            }
+        }
+    }
+    validate_window(body.starts_at, body.ends_at)?;
+
+    let allowed_users = resolve_users(&body.allowed_users, pool, redis).await?;
+
+    let existing = DBCoupon::get_owned(project_id, organization_id, pool)
+        .await?
+        .into_iter()
+        .any(|x| x.code.eq_ignore_ascii_case(&body.code));
+    if existing {
+        return Err(ApiError::InvalidInput("该优惠码已存在".to_string()));
+    }
+
+    let mut transaction = pool.begin().await?;
+    let coupon = DBCoupon {
+        id: generate_coupon_id(&mut transaction).await?,
+        code: body.code.clone(),
+        project_id,
+        organization_id,
+        discount_type: body.discount_type,
+        discount_value,
+        max_uses: body.max_uses,
+        per_user_limit: body.per_user_limit,
+        starts_at: body.starts_at,
+        ends_at: body.ends_at,
+        allowed_users,
+        enabled: true,
+        created_by: user.id.into(),
+        created_at: Utc::now(),
+    };
+    coupon.insert(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(Coupon::from(coupon, 0))
+}
+
+async fn coupons_with_uses(
+    coupons: Vec<DBCoupon>,
+    pool: &PgPool,
+) -> Result<Vec<Coupon>, ApiError> {
+    let uses = DBCoupon::get_uses_many(
+        &coupons.iter().map(|x| x.id).collect::<Vec<DBCouponId>>(),
+        pool,
+    )
+    .await?;
+
+    Ok(coupons
+        .into_iter()
+        .map(|x| {
+            let uses = uses.get(&x.id).copied().unwrap_or(0);
+            Coupon::from(x, uses)
+        })
+        .collect())
+}
+
+fn validate_window(
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+) -> Result<(), ApiError> {
+    if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at)
+        && ends_at <= starts_at
+    {
+        return Err(ApiError::InvalidInput(
+            "结束时间必须晚于开始时间".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn resolve_users(
+    users: &[String],
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<Vec<DBUserId>, ApiError> {
+    if users.len() > MAX_ALLOWED_USERS {
+        return Err(ApiError::InvalidInput(format!(
+            "限定用户不能超过 {MAX_ALLOWED_USERS} 个"
+        )));
+    }
+    if users.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let found = database::models::User::get_many(users, pool, redis).await?;
+    if found.len() != users.len() {
+        return Err(ApiError::InvalidInput(
+            "限定用户中存在不存在的用户".to_string(),
+        ));
+    }
+
+    Ok(found.into_iter().map(|x| x.id).collect())
+}
+
+async fn check_project_permission(
+    user: &User,
+    project: &DBProject,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    let (team_member, organization_team_member) =
+        database::models::TeamMember::get_for_project_permissions(
+            project,
+            user.id.into(),
+            pool,
+        )
+        .await?;
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(ProjectPermissions::EDIT_DETAILS) {
+        return Err(ApiError::CustomAuthentication(
+            "您没有权限管理此项目的优惠券".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_organization_permission(
+    user: &User,
+    organization: &database::models::Organization,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    let team_member = database::models::TeamMember::get_from_user_id(
+        organization.team_id,
+        user.id.into(),
+        pool,
+    )
+    .await?;
+
+    let permissions = OrganizationPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(OrganizationPermissions::EDIT_DETAILS) {
+        return Err(ApiError::CustomAuthentication(
+            "您没有权限管理此组织的优惠券".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_coupon_permission(
+    user: &User,
+    coupon: &DBCoupon,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    if let Some(project_id) = coupon.project_id {
+        let project =
+            database::models::Project::get_id(project_id, pool, redis)
+                .await?
+                .ok_or(ApiError::NotFound)?;
+        check_project_permission(user, &project.inner, pool).await
+    } else if let Some(organization_id) = coupon.organization_id {
+        let organization = database::models::Organization::get_id(
+            organization_id,
+            pool,
+            redis,
+        )
+        .await?
+        .ok_or(ApiError::NotFound)?;
+        check_organization_permission(user, &organization, pool).await
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
@@ -10,6 +10,8 @@ use crate::models::ids::base62_impl::parse_base62;
 use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
+use crate::search::SearchConfig;
+use crate::search::indexing::content::{ContentTarget, update_content_index};
 
 use crate::database::models::UserId;
 use crate::util::validate::validation_errors_to_string;
@@ -263,6 +265,7 @@ pub async fn forum_edit(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     body.validate().map_err(|err| {
         ApiError::Validation(validation_errors_to_string(err, None))
@@ -370,6 +373,12 @@ pub async fn forum_edit(
     }
 
     transaction.commit().await?;
+    update_content_index(
+        ContentTarget::Discussion(discussion_id),
+        &pool,
+        &search_config,
+    )
+    .await;
     Discussion::clear_cache(&[discussion_id], &redis).await?;
     Discussion::clear_cache_discussions(
         &[discussion.inner.category.clone(), "all".to_string()],
@@ -392,6 +401,7 @@ pub async fn forum_delete(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
         &req,
@@ -445,6 +455,12 @@ pub async fn forum_delete(
     discussion.inner.delete_discussion(&mut transaction).await?;
 
     transaction.commit().await?;
+    update_content_index(
+        ContentTarget::Discussion(discussion_id),
+        &pool,
+        &search_config,
+    )
+    .await;
     crate::database::models::forum::Discussion::clear_cache(
         &[discussion_id],
         &redis,
@@ -471,6 +487,7 @@ pub async fn forum_create(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     body.validate().map_err(|err| {
         ApiError::Validation(validation_errors_to_string(err, None))
@@ -589,6 +606,12 @@ pub async fn forum_create(
     };
     discussion.insert(&mut transaction).await?;
     transaction.commit().await?;
+    update_content_index(
+        ContentTarget::Discussion(discussion_id),
+        &pool,
+        &search_config,
+    )
+    .await;
     crate::database::models::forum::Discussion::clear_cache_discussions(
         &[discussion.category.clone(), "all".to_string()],
         &redis,
@@ -613,6 +636,7 @@ pub async fn posts_post(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     body.validate().map_err(|err| {
         ApiError::Validation(validation_errors_to_string(err, None))
@@ -732,11 +756,14 @@ pub async fn posts_post(
             sender: user_option.as_ref().unwrap().username.clone(),
         },
     };
-    notification
+    let push_events = notification
         .insert(discussion.user_id, &mut transaction, &redis)
         .await?;
 
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
+    update_content_index(ContentTarget::Post(post_id), &pool, &search_config)
+        .await;
 
     Discussion::clear_cache(&[discussion_id], &redis).await?;
     Discussion::clear_cache_discussions(
@@ -776,6 +803,7 @@ pub async fn post_delete(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let user_option = get_user_from_headers(
         &req,
@@ -836,12 +864,37 @@ pub async fn post_delete(
 
     transaction.commit().await?;
 
-    // 清理缓存
-    let discussion_id = DiscussionId(post_info.discussion_id);
-    Discussion::clear_cache(&[discussion_id], &redis).await?;
+    clear_post_caches(
+        post_id,
+        DiscussionId(post_info.discussion_id),
+        post_info.replied_to.map(PostId),
+        post_info.user_id,
+        &pool,
+        &redis,
+        &search_config,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 回复被删除或隐藏后更新搜索索引并清理相关缓存
+pub(crate) async fn clear_post_caches(
+    post_id: PostId,
+    discussion_id: DiscussionId,
+    replied_to: Option<PostId>,
+    author_id: i64,
+    pool: &PgPool,
+    redis: &RedisPool,
+    search_config: &SearchConfig,
+) -> Result<(), ApiError> {
+    update_content_index(ContentTarget::Post(post_id), pool, search_config)
+        .await;
+
+    Discussion::clear_cache(&[discussion_id], redis).await?;
 
     // 清理帖子缓存
-    crate::database::models::forum::PostQuery::clear_cache(&[post_id], &redis)
+    crate::database::models::forum::PostQuery::clear_cache(&[post_id], redis)
         .await?;
 
     // 清理回复了被删除帖子的所有帖子的缓存
@@ -849,33 +902,32 @@ pub async fn post_delete(
     let replies_to_deleted = sqlx::query!(
         "SELECT id FROM posts WHERE replied_to = $1 AND discussion_id = $2",
         post_id.0,
-        post_info.discussion_id
+        discussion_id.0
     )
-    .fetch_all(&**pool)
+    .fetch_all(pool)
     .await?;
 
     if !replies_to_deleted.is_empty() {
         let reply_ids: Vec<PostId> =
             replies_to_deleted.iter().map(|r| PostId(r.id)).collect();
         crate::database::models::forum::PostQuery::clear_cache(
-            &reply_ids, &redis,
+            &reply_ids, redis,
         )
         .await?;
     }
 
     // 清理被删除帖子所回复的帖子的缓存
     // 因为那个帖子的 replies 列表缓存了被删除帖子的信息
-    if let Some(replied_to_id) = post_info.replied_to {
+    if let Some(replied_to_id) = replied_to {
         crate::database::models::forum::PostQuery::clear_cache(
-            &[PostId(replied_to_id)],
-            &redis,
+            &[replied_to_id],
+            redis,
         )
         .await?;
     }
 
     // 清除回复作者的论坛内容缓存
-    let _ =
-        super::users::clear_user_forum_cache(post_info.user_id, &redis).await;
+    let _ = super::users::clear_user_forum_cache(author_id, redis).await;
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(())
 }
@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use super::ApiError;
 use crate::database::models::notification_item::NotificationBuilder;
-use crate::database::models::{self as db_models};
+use crate::database::models::{self as db_models, AuditLogBuilder};
 use crate::database::redis::RedisPool;
 use crate::file_hosting::FileHost;
 use crate::models::ids::random_base62;
@@ -13,6 +13,7 @@ use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
 use crate::util::img::delete_old_images;
+use crate::util::ip::request_ip;
 
 #[derive(Deserialize)]
 pub struct ImageReviewListQuery {
@@ -178,8 +179,9 @@ pub async fn approve_image_review(
 
     let mut transaction = pool.begin().await?;
 
-    let _review = sqlx::query!(
-        "SELECT id FROM image_content_reviews
+    let review = sqlx::query!(
+        "SELECT id, image_url, uploader_id, source_type, source_id, project_id
+         FROM image_content_reviews
          WHERE id = $1 AND status = 'pending'
          FOR UPDATE",
         review_id,
@@ -199,6 +201,26 @@ pub async fn approve_image_review(
     .execute(&mut *transaction)
     .await?;
 
+    AuditLogBuilder {
+        actor_id: Some(moderator.id.into()),
+        action: "image_review.approved".to_string(),
+        target_type: "image_review".to_string(),
+        target_id: Some(review_id),
+        before: Some(serde_json::json!({
+            "image_url": review.image_url,
+            "uploader_id": review.uploader_id,
+            "source_type": review.source_type,
+            "source_id": review.source_id,
+            "project_id": review.project_id,
+            "status": "pending",
+        })),
+        after: Some(serde_json::json!({ "status": "approved" })),
+        reason: body.notes.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
@@ -325,7 +347,7 @@ pub async fn reject_image_review(
             review_notes: body.notes.clone(),
         },
     };
-    notification
+    let push_events = notification
         .insert(
             crate::database::models::ids::UserId(review.uploader_id),
             &mut transaction,
@@ -333,7 +355,28 @@ pub async fn reject_image_review(
         )
         .await?;
 
+    AuditLogBuilder {
+        actor_id: Some(moderator.id.into()),
+        action: "image_review.rejected".to_string(),
+        target_type: "image_review".to_string(),
+        target_id: Some(review_id),
+        before: Some(serde_json::json!({
+            "image_url": review.image_url,
+            "uploader_id": review.uploader_id,
+            "source_type": review.source_type,
+            "source_id": review.source_id,
+            "project_id": review.project_id,
+            "status": review.status,
+        })),
+        after: Some(serde_json::json!({ "status": "rejected" })),
+        reason: body.notes.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
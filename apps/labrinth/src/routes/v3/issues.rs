@@ -1,4 +1,4 @@
-use crate::auth::email::send_email;
+use crate::auth::email::{DEFAULT_LOCALE, EmailTemplate, send_email};
 use crate::auth::{AuthenticationError, get_user_from_headers};
 use crate::database::models::ids::{
     IssuesCommentsId, IssuesId, generate_issues_comments_id, generate_issues_id,
@@ -13,6 +13,8 @@ use crate::models::ids::base62_impl::parse_base62;
 use crate::models::pats::Scopes;
 use crate::models::teams::ProjectPermissions;
 use crate::queue::session::AuthQueue;
+use crate::search::SearchConfig;
+use crate::search::indexing::content::{ContentTarget, update_content_index};
 use crate::{
     models::v3::issues::{
         CommentResponse, CommentsQueryParams, CreateCommentRequest,
@@ -122,6 +124,7 @@ pub async fn project_issue_create(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let project_id_str: String = info.into_inner().0;
     let project_id = ProjectId(parse_base62(&project_id_str)? as i64);
@@ -209,6 +212,8 @@ pub async fn project_issue_create(
 
     issue.insert(&mut transaction).await?;
     transaction.commit().await?;
+    update_content_index(ContentTarget::Issue(issue_id), &pool, &search_config)
+        .await;
 
     // 清除单个Issue的缓存
     Issue::clear_cache(&[issue_id], &redis).await?;
@@ -252,23 +257,27 @@ pub async fn project_issue_create(
     for user in users {
         if let Some(email) = &user.email {
             let _ = send_email(
+                &**pool,
                 email.clone(),
-                "新问题通知",
-                &format!(
-                    "{} 在 {} 创建了新问题：{}",
-                    user.username, project.inner.name, body.title
-                ),
-                &body.body,
-                Some((
-                    "查看问题",
-                    &format!(
-                        "{}/project/{}/issues/{}",
-                        dotenvy::var("SITE_URL")?,
-                        slug,
-                        id
+                EmailTemplate::IssueCreated,
+                DEFAULT_LOCALE,
+                &[
+                    ("username", &user.username),
+                    ("project", &project.inner.name),
+                    ("title", &body.title),
+                    ("body", &body.body),
+                    (
+                        "link",
+                        &format!(
+                            "{}/project/{}/issues/{}",
+                            dotenvy::var("SITE_URL")?,
+                            slug,
+                            id
+                        ),
                     ),
-                )),
-            );
+                ],
+            )
+            .await;
         }
     }
 
@@ -285,6 +294,7 @@ pub async fn issue_edit(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let issue_id_str: String = info.into_inner().0;
     let issue_id = IssuesId(parse_base62(&issue_id_str)? as i64);
@@ -392,31 +402,35 @@ pub async fn issue_edit(
             for user in users {
                 if let Some(email) = &user.email {
                     let _ = send_email(
+                        &mut *transaction,
                         email.clone(),
-                        "问题状态更新通知",
-                        &format!(
-                            "{} 更新了 {} 的问题状态：{}",
-                            user.username,
-                            issue.inner.title.clone(),
-                            if state == "open" {
-                                "重新打开"
-                            } else if state == "closed" {
-                                "关闭"
-                            } else {
-                                "未知"
-                            }
-                        ),
-                        "",
-                        Some((
-                            "查看问题",
-                            &format!(
-                                "{}/project/{}/issues/{}",
-                                dotenvy::var("SITE_URL")?,
-                                slug,
-                                &issue_id_str
+                        EmailTemplate::IssueStateChanged,
+                        DEFAULT_LOCALE,
+                        &[
+                            ("username", &user.username),
+                            ("title", &issue.inner.title),
+                            (
+                                "state",
+                                if state == "open" {
+                                    "重新打开"
+                                } else if state == "closed" {
+                                    "关闭"
+                                } else {
+                                    "未知"
+                                },
+                            ),
+                            (
+                                "link",
+                                &format!(
+                                    "{}/project/{}/issues/{}",
+                                    dotenvy::var("SITE_URL")?,
+                                    slug,
+                                    &issue_id_str
+                                ),
                             ),
-                        )),
-                    );
+                        ],
+                    )
+                    .await;
                 }
             }
         }
@@ -452,6 +466,8 @@ pub async fn issue_edit(
     }
 
     transaction.commit().await?;
+    update_content_index(ContentTarget::Issue(issue_id), &pool, &search_config)
+        .await;
 
     // 清除单个Issue的缓存
     Issue::clear_cache(&[issue_id], &redis).await?;
@@ -629,6 +645,7 @@ pub async fn comment_create(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let issue_id_str: String = info.into_inner().0;
     let issue_id = IssuesId(parse_base62(&issue_id_str)? as i64);
@@ -706,6 +723,12 @@ pub async fn comment_create(
 
     comment.insert(&mut transaction).await?;
     transaction.commit().await?;
+    update_content_index(
+        ContentTarget::IssueComment(comment_id),
+        &pool,
+        &search_config,
+    )
+    .await;
 
     Issue::clear_cache(&[issue_id], &redis).await?;
 
@@ -738,23 +761,26 @@ pub async fn comment_create(
     for user in users {
         if let Some(email) = &user.email {
             let _ = send_email(
+                &**pool,
                 email.clone(),
-                "问题收到新的回复通知",
-                &format!(
-                    "{} 在 {} 回复了你的消息：{}",
-                    user.username, project.inner.name, body.body
-                ),
-                &body.body,
-                Some((
-                    "查看问题",
-                    &format!(
-                        "{}/project/{}/issues/{}",
-                        dotenvy::var("SITE_URL")?,
-                        slug,
-                        &issue_id_str
+                EmailTemplate::IssueReply,
+                DEFAULT_LOCALE,
+                &[
+                    ("username", &user.username),
+                    ("project", &project.inner.name),
+                    ("body", &body.body),
+                    (
+                        "link",
+                        &format!(
+                            "{}/project/{}/issues/{}",
+                            dotenvy::var("SITE_URL")?,
+                            slug,
+                            &issue_id_str
+                        ),
                     ),
-                )),
-            );
+                ],
+            )
+            .await;
         }
     }
 
@@ -823,6 +849,7 @@ pub async fn comment_delete(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let comment_id_str: String = info.into_inner().0;
     let comment_id = IssuesCommentsId(parse_base62(&comment_id_str)? as i64);
@@ -904,8 +931,29 @@ pub async fn comment_delete(
     IssueCommentQuery::delete_comment(comment_id, &mut transaction).await?;
     transaction.commit().await?;
 
+    clear_comment_caches(comment_id, issue_id, &pool, &redis, &search_config)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 评论被删除或隐藏后更新搜索索引并清理相关缓存
+pub(crate) async fn clear_comment_caches(
+    comment_id: IssuesCommentsId,
+    issue_id: IssuesId,
+    pool: &PgPool,
+    redis: &RedisPool,
+    search_config: &SearchConfig,
+) -> Result<(), ApiError> {
+    update_content_index(
+        ContentTarget::IssueComment(comment_id),
+        pool,
+        search_config,
+    )
+    .await;
+
     // 清除相关缓存
-    Issue::clear_cache(&[issue_id], &redis).await?;
+    Issue::clear_cache(&[issue_id], redis).await?;
 
     // 清除评论相关的缓存 - 需要清除所有该Issue的评论缓存
     let mut redis_conn = redis.connect().await?;
@@ -915,10 +963,13 @@ pub async fn comment_delete(
         "SELECT id FROM issue_comments WHERE issue_id = $1 AND deleted = false",
         issue_id.0
     )
-    .fetch_all(&**pool)
+    .fetch_all(pool)
     .await?;
 
-    let mut cache_keys = vec![(ISSUE_NAMESPACE, Some(issue_id.0.to_string()))];
+    let mut cache_keys = vec![
+        (ISSUE_NAMESPACE, Some(issue_id.0.to_string())),
+        ("issue_comment", Some(comment_id.0.to_string())),
+    ];
 
     // 添加所有评论的缓存键
     for comment in all_comment_ids {
@@ -927,7 +978,7 @@ pub async fn comment_delete(
 
     redis_conn.delete_many(cache_keys).await?;
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(())
 }
 
 // 获取所有标签
@@ -0,0 +1,292 @@
+//! 创作者账本 API
+//!
+//! 创作者可以查看自己收益相关的账本明细；管理员可以查看与处理每日对账
+//! 发现的差异，也可以手动触发一次对账。
+//!
+//! 权限要求：
+//! - 查看账本明细：PAYOUTS_READ
+//! - 对账差异：管理员，查看需要 PAYOUTS_READ，处理与触发对账需要 PAYOUTS_WRITE
+
+use crate::auth::get_user_from_headers;
+use crate::auth::validate::check_is_admin_from_headers;
+use crate::database::models::{
+    LedgerAccount, LedgerEntry, LedgerEntryKind, ReconciliationIssue,
+    ReconciliationIssueKind,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::{ProjectId, UserId};
+use crate::models::pats::Scopes;
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// 单次查询账本明细的最大时间跨度
+const MAX_LEDGER_RANGE_DAYS: i64 = 366;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("ledger")
+            .service(user_ledger)
+            .service(reconciliation_issues)
+            .service(reconciliation_run)
+            .service(reconciliation_resolve),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct LedgerQuery {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// 账本明细行，金额以创作者视角表示：收入为正，支出为负
+#[derive(Serialize)]
+pub struct LedgerLineResponse {
+    pub entry_id: i64,
+    pub kind: LedgerEntryKind,
+    pub reversed_kind: Option<LedgerEntryKind>,
+    pub reference: Option<String>,
+    pub description: String,
+    pub account: LedgerAccount,
+    pub project_id: Option<ProjectId>,
+    pub amount: Decimal,
+    pub available_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct LedgerResponse {
+    pub available: Decimal,
+    pub pending: Decimal,
+    pub lines: Vec<LedgerLineResponse>,
+}
+
+/// 查看自己的账本明细，默认最近 30 天
+#[get("")]
+pub async fn user_ledger(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<LedgerQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+
+    let end = query.end.unwrap_or_else(Utc::now);
+    let start = query.start.unwrap_or(end - Duration::days(30));
+    if start >= end {
+        return Err(ApiError::InvalidInput(
+            "开始时间必须早于结束时间".to_string(),
+        ));
+    }
+    if end - start > Duration::days(MAX_LEDGER_RANGE_DAYS) {
+        return Err(ApiError::InvalidInput(format!(
+            "查询时间跨度不能超过 {} 天",
+            MAX_LEDGER_RANGE_DAYS
+        )));
+    }
+
+    let balance = LedgerEntry::get_balance(user.id.into(), &**pool).await?;
+    let lines = LedgerEntry::get_user_lines(
+        user.id.into(),
+        &[LedgerAccount::CreatorPayable, LedgerAccount::CreatorSales],
+        start,
+        end,
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(LedgerResponse {
+        available: balance.available.round_dp(16),
+        pending: balance.pending.round_dp(16),
+        lines: lines
+            .into_iter()
+            .map(|x| LedgerLineResponse {
+                entry_id: x.entry_id,
+                kind: x.kind,
+                reversed_kind: x.reversed_kind,
+                reference: x.reference,
+                description: x.description,
+                account: x.account,
+                project_id: x.project_id.map(ProjectId::from),
+                amount: (-x.amount).round_dp(16),
+                available_at: x.available_at,
+                created_at: x.created_at,
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ReconciliationQuery {
+    #[serde(default)]
+    pub include_resolved: bool,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ReconciliationIssueResponse {
+    pub id: i64,
+    pub kind: ReconciliationIssueKind,
+    pub reference: String,
+    pub user_id: Option<UserId>,
+    pub expected: Option<Decimal>,
+    pub actual: Option<Decimal>,
+    pub detail: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<UserId>,
+    pub resolution_note: Option<String>,
+}
+
+impl From<ReconciliationIssue> for ReconciliationIssueResponse {
+    fn from(x: ReconciliationIssue) -> Self {
+        Self {
+            id: x.id,
+            kind: x.kind,
+            reference: x.reference,
+            user_id: x.user_id.map(UserId::from),
+            expected: x.expected.map(|x| x.round_dp(16)),
+            actual: x.actual.map(|x| x.round_dp(16)),
+            detail: x.detail,
+            first_seen_at: x.first_seen_at,
+            last_seen_at: x.last_seen_at,
+            resolved_at: x.resolved_at,
+            resolved_by: x.resolved_by.map(UserId::from),
+            resolution_note: x.resolution_note,
+        }
+    }
+}
+
+/// 对账差异列表，默认只返回未处理的差异
+#[get("reconciliation")]
+pub async fn reconciliation_issues(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<ReconciliationQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?;
+
+    let page = query.page.unwrap_or(1).clamp(1, 10_000);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let issues = ReconciliationIssue::get_many(
+        query.include_resolved,
+        page_size,
+        (page - 1) * page_size,
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(
+        issues
+            .into_iter()
+            .map(ReconciliationIssueResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[derive(Serialize)]
+pub struct ReconciliationRunResponse {
+    pub issues: u64,
+}
+
+/// 立即执行一次对账
+#[post("reconciliation/run")]
+pub async fn reconciliation_run(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?;
+
+    let issues = crate::queue::ledger::reconcile(&pool).await?;
+    crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
+        .await;
+
+    Ok(HttpResponse::Ok().json(ReconciliationRunResponse { issues }))
+}
+
+#[derive(Deserialize)]
+pub struct ResolveIssue {
+    pub note: Option<String>,
+}
+
+/// 标记对账差异已处理
+#[post("reconciliation/{id}/resolve")]
+pub async fn reconciliation_resolve(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    body: web::Json<ResolveIssue>,
+) -> Result<HttpResponse, ApiError> {
+    let admin = check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?;
+
+    let id = path.into_inner();
+    let note = body
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.chars().take(1000).collect::<String>());
+
+    if ReconciliationIssue::get_id(id, &**pool).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    if !ReconciliationIssue::resolve(
+        id,
+        admin.id.into(),
+        note.as_deref(),
+        &**pool,
+    )
+    .await?
+    {
+        return Err(ApiError::InvalidInput("该差异已处理".to_string()));
+    }
+
+    crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
+        .await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,404 @@
+//! 付费项目许可证 API
+//!
+//! 买家为已购买的付费项目领取签名许可令牌，服务器插件可使用公钥离线验证令牌，
+//! 也可调用在线验证接口检查吊销状态并登记服务器实例。每个许可证可激活的实例数量有上限，
+//! 买家可以解除旧实例的激活以释放名额。退款或过期后许可证随购买记录一起失效。
+
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ids::{
+    ProjectLicenseId as DBProjectLicenseId, UserId as DBUserId,
+};
+use crate::database::models::{
+    ProjectLicense as DBProjectLicense, PurchaseStatus, UserPurchase,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::{ProjectId, ProjectLicenseId, UserId};
+use crate::models::licenses::ProjectLicense;
+use crate::models::pats::Scopes;
+use crate::queue::session::AuthQueue;
+use crate::util::license::{
+    LicenseClaims, license_public_key, sign_license_token, verify_license_token,
+};
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{HttpRequest, HttpResponse, web};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// 默认每个许可证可激活的服务器实例数量
+const DEFAULT_MAX_ACTIVATIONS: i32 = 3;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("license")
+            .route("", web::get().to(licenses_get))
+            .route("key", web::get().to(license_public_key_get))
+            .route("verify", web::post().to(license_verify))
+            .route("{id}/revoke", web::post().to(license_revoke))
+            .route(
+                "{id}/activations/{instance_id}",
+                web::delete().to(license_deactivate),
+            ),
+    );
+}
+
+fn max_activations() -> i32 {
+    dotenvy::var("LICENSE_MAX_ACTIVATIONS")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_MAX_ACTIVATIONS)
+}
+
+#[derive(Serialize)]
+pub struct LicensePublicKey {
+    pub algorithm: &'static str,
+    /// Base64 编码的 32 字节公钥
+    pub public_key: String,
+}
+
+/// 获取许可令牌签名公钥
+///
+/// GET /v3/license/key
+pub async fn license_public_key_get() -> Result<HttpResponse, ApiError> {
+    let public_key = license_public_key().ok_or_else(|| {
+        ApiError::InvalidInput("许可证功能未启用".to_string())
+    })?;
+
+    Ok(HttpResponse::Ok().json(LicensePublicKey {
+        algorithm: "Ed25519",
+        public_key: BASE64.encode(public_key),
+    }))
+}
+
+#[derive(Serialize)]
+pub struct LicenseTokenResponse {
+    pub token: String,
+    pub license: ProjectLicense,
+}
+
+/// 领取项目许可令牌
+///
+/// GET /v3/project/{id}/license
+///
+/// 每次调用都会按当前购买记录重新签发令牌，续费后重新领取即可获得新的过期时间。
+pub async fn project_license_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    let purchase = UserPurchase::get(user.id.into(), project.inner.id, &**pool)
+        .await?
+        .filter(|x| {
+            x.status == PurchaseStatus::Active
+                && x.expires_at.is_none_or(|exp| exp > Utc::now())
+        })
+        .ok_or_else(|| {
+            ApiError::CustomAuthentication(
+                "您尚未购买此项目或购买已过期".to_string(),
+            )
+        })?;
+
+    let mut transaction = pool.begin().await?;
+    let license = DBProjectLicense::get_or_create(
+        purchase.id,
+        max_activations(),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if license.revoked_at.is_some() {
+        return Err(ApiError::CustomAuthentication(
+            "此许可证已被吊销".to_string(),
+        ));
+    }
+
+    let token = sign_license_token(&LicenseClaims {
+        lid: license.id.into(),
+        uid: license.user_id.into(),
+        pid: license.project_id.into(),
+        iat: Utc::now().timestamp(),
+        exp: license.expires_at.map(|x| x.timestamp()),
+        max: license.max_activations,
+    })
+    .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+
+    let activations =
+        DBProjectLicense::get_activations(license.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(LicenseTokenResponse {
+        token,
+        license: ProjectLicense::from(license, activations),
+    }))
+}
+
+/// 获取当前用户的全部许可证
+///
+/// GET /v3/license
+pub async fn licenses_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let licenses = DBProjectLicense::get_user(user.id.into(), &**pool).await?;
+
+    let mut result = Vec::with_capacity(licenses.len());
+    for license in licenses {
+        let activations =
+            DBProjectLicense::get_activations(license.id, &**pool).await?;
+        result.push(ProjectLicense::from(license, activations));
+    }
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// 在线验证请求
+#[derive(Deserialize, Validate)]
+pub struct LicenseVerifyRequest {
+    #[validate(length(min = 1, max = 4096, message = "许可令牌无效"))]
+    pub token: String,
+    /// 服务器实例标识，由插件生成并持久保存
+    #[validate(length(
+        min = 1,
+        max = 128,
+        message = "实例标识长度必须在 1-128 之间"
+    ))]
+    pub instance_id: String,
+}
+
+#[derive(Serialize)]
+pub struct LicenseVerifyResponse {
+    pub valid: bool,
+    /// 验证失败的原因
+    pub reason: Option<String>,
+    pub license_id: Option<ProjectLicenseId>,
+    pub user_id: Option<UserId>,
+    pub project_id: Option<ProjectId>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_activations: Option<i32>,
+}
+
+impl LicenseVerifyResponse {
+    fn invalid(reason: &str) -> Self {
+        Self {
+            valid: false,
+            reason: Some(reason.to_string()),
+            license_id: None,
+            user_id: None,
+            project_id: None,
+            expires_at: None,
+            max_activations: None,
+        }
+    }
+}
+
+/// 在线验证许可令牌并登记服务器实例
+///
+/// POST /v3/license/verify
+///
+/// 无需登录。令牌无效、许可证已吊销或过期、实例数量超出上限时返回 `valid: false`。
+pub async fn license_verify(
+    req: HttpRequest,
+    body: web::Json<LicenseVerifyRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let claims = match verify_license_token(&body.token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            return Ok(HttpResponse::Ok()
+                .json(LicenseVerifyResponse::invalid(&e.to_string())));
+        }
+    };
+
+    let mut transaction = pool.begin().await?;
+
+    let license_id: DBProjectLicenseId = claims.lid.into();
+    if !DBProjectLicense::lock(license_id, &mut transaction).await? {
+        return Ok(HttpResponse::Ok()
+            .json(LicenseVerifyResponse::invalid("许可证不存在")));
+    }
+
+    let Some(license) =
+        DBProjectLicense::get_id(license_id, &mut *transaction).await?
+    else {
+        return Ok(HttpResponse::Ok()
+            .json(LicenseVerifyResponse::invalid("许可证不存在")));
+    };
+
+    let reason = if license.revoked_at.is_some() {
+        Some("许可证已被吊销")
+    } else if license.purchase_status == PurchaseStatus::Refunded {
+        Some("订单已退款")
+    } else if !license.is_valid() {
+        Some("许可证已过期")
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        return Ok(
+            HttpResponse::Ok().json(LicenseVerifyResponse::invalid(reason))
+        );
+    }
+
+    let ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|x| x.chars().take(64).collect::<String>());
+    if !license
+        .activate(&body.instance_id, ip.as_deref(), &mut transaction)
+        .await?
+    {
+        return Ok(HttpResponse::Ok().json(LicenseVerifyResponse::invalid(
+            "已达到许可证可激活的服务器数量上限",
+        )));
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(LicenseVerifyResponse {
+        valid: true,
+        reason: None,
+        license_id: Some(license.id.into()),
+        user_id: Some(license.user_id.into()),
+        project_id: Some(license.project_id.into()),
+        expires_at: license.expires_at,
+        max_activations: Some(license.max_activations),
+    }))
+}
+
+/// 解除服务器实例的激活
+///
+/// DELETE /v3/license/{id}/activations/{instance_id}
+pub async fn license_deactivate(
+    req: HttpRequest,
+    info: web::Path<(ProjectLicenseId, String)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let (id, instance_id) = info.into_inner();
+    let user_id: DBUserId = user.id.into();
+
+    let license = DBProjectLicense::get_id(id.into(), &**pool)
+        .await?
+        .filter(|x| x.user_id == user_id || user.role.is_admin())
+        .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+    let removed = DBProjectLicense::deactivate(
+        license.id,
+        &instance_id,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if !removed {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 吊销许可证请求
+#[derive(Deserialize, Validate)]
+pub struct LicenseRevoke {
+    #[validate(length(max = 2000, message = "吊销原因不能超过 2000 个字符"))]
+    pub reason: Option<String>,
+}
+
+/// 吊销许可证（仅管理员）
+///
+/// POST /v3/license/{id}/revoke
+pub async fn license_revoke(
+    req: HttpRequest,
+    info: web::Path<(ProjectLicenseId,)>,
+    body: web::Json<LicenseRevoke>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    if !user.role.is_admin() {
+        return Err(ApiError::CustomAuthentication(
+            "只有管理员可以吊销许可证".to_string(),
+        ));
+    }
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let mut transaction = pool.begin().await?;
+    let revoked = DBProjectLicense::revoke(
+        info.into_inner().0.into(),
+        body.reason.as_deref(),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if !revoked {
+        return Err(ApiError::InvalidInput(
+            "许可证不存在或已被吊销".to_string(),
+        ));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
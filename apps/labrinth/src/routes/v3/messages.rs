@@ -0,0 +1,670 @@
+//! 用户私信 API
+//!
+//! 私信会话复用 `direct_message` 类型的 thread，会话内发送消息仍走
+//! `POST /thread/{id}`；本模块负责发起会话、会话列表、未读数、隐私设置
+//! 与用户屏蔽。
+//!
+//! - 发起新会话受接收方隐私设置约束（管理员除外），已有会话不受影响
+//! - 任一方向的屏蔽关系都会阻止发起会话和在会话中发送消息
+//! - 消息内容需通过论坛封禁检查与敏感词检查
+
+use crate::auth::{check_forum_ban, get_user_from_headers};
+use crate::database;
+use crate::database::models::direct_message_item::DirectMessage;
+use crate::database::models::notification_item::{
+    Notification, NotificationBuilder,
+};
+use crate::database::models::thread_item::{
+    ThreadBuilder, ThreadMessageBuilder,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::{ThreadId, ThreadMessageId, UserId};
+use crate::models::messages::{
+    Conversation, DmPrivacy, DmSettings, MAX_CONVERSATION_MEMBERS, UnreadCount,
+    UserBlock,
+};
+use crate::models::notifications::NotificationBody;
+use crate::models::pats::Scopes;
+use crate::models::threads::{MessageBody, ThreadMessage, ThreadType};
+use crate::models::users::User;
+use crate::queue::push::{PushEventBody, PushEvents};
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("messages")
+            .service(conversations_list)
+            .service(conversation_create)
+            .service(unread_count)
+            .service(settings_get)
+            .service(settings_edit)
+            .service(blocks_list)
+            .service(user_block)
+            .service(user_unblock)
+            .service(conversation_read)
+            .service(conversation_leave),
+    );
+}
+
+/// 校验私信内容：长度与敏感词
+pub async fn check_message_text(
+    body: &str,
+    user: &User,
+    redis: &RedisPool,
+) -> Result<(), ApiError> {
+    if body.trim().is_empty() {
+        return Err(ApiError::InvalidInput("消息内容不能为空".to_string()));
+    }
+    if body.len() > 65536 {
+        return Err(ApiError::InvalidInput("输入内容过长!".to_string()));
+    }
+
+    let risk = crate::util::risk::check_text_risk(
+        body,
+        &user.username,
+        &format!("/user/{}", user.username),
+        "私信",
+        redis,
+    )
+    .await?;
+    if !risk {
+        return Err(ApiError::InvalidInput(
+            "私信内容包含敏感词，已被记录该次提交，请勿在本网站发送涉及敏感词的消息".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 通知会话中除发送者外的成员
+///
+/// 成员在该会话仍有未读的私信通知时不再重复通知，避免连续消息刷屏。
+/// 返回的推送事件由调用方在事务提交后发出。
+pub async fn notify_direct_message(
+    thread_id: database::models::ThreadId,
+    message_id: database::models::ThreadMessageId,
+    sender: &User,
+    members: &[database::models::UserId],
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<PushEvents, ApiError> {
+    let sender_id: database::models::UserId = sender.id.into();
+    let recipients = members
+        .iter()
+        .copied()
+        .filter(|x| *x != sender_id)
+        .collect::<Vec<_>>();
+    if recipients.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let notified = DirectMessage::get_users_with_unread_notification(
+        thread_id,
+        &recipients,
+        transaction,
+    )
+    .await?;
+    let recipients = recipients
+        .into_iter()
+        .filter(|x| !notified.contains(x))
+        .collect::<Vec<_>>();
+
+    if recipients.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(NotificationBuilder {
+        body: NotificationBody::DirectMessage {
+            thread_id: thread_id.into(),
+            message_id: message_id.into(),
+            sender_id: sender.id,
+            sender: sender.username.clone(),
+        },
+    }
+    .insert_many(recipients, transaction, redis)
+    .await?)
+}
+
+#[derive(Deserialize)]
+pub struct ConversationsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// 当前用户的私信会话列表
+#[get("")]
+pub async fn conversations_list(
+    req: HttpRequest,
+    web::Query(query): web::Query<ConversationsQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_READ]),
+    )
+    .await?
+    .1;
+
+    let page = query.page.unwrap_or(1).clamp(1, 10_000);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let summaries = DirectMessage::get_user_conversations(
+        user.id.into(),
+        page_size,
+        (page - 1) * page_size,
+        &**pool,
+    )
+    .await?;
+
+    let mut user_ids = summaries
+        .iter()
+        .flat_map(|x| x.members.clone())
+        .collect::<Vec<_>>();
+    user_ids.sort_by_key(|x| x.0);
+    user_ids.dedup();
+
+    let message_ids = summaries
+        .iter()
+        .filter_map(|x| x.last_message_id)
+        .collect::<Vec<_>>();
+
+    let users: Vec<User> =
+        database::models::User::get_many_ids(&user_ids, &**pool, &redis)
+            .await?
+            .into_iter()
+            .map(From::from)
+            .collect();
+    let mut messages =
+        database::models::ThreadMessage::get_many(&message_ids, &**pool)
+            .await?;
+
+    let conversations = summaries
+        .into_iter()
+        .map(|summary| {
+            let last_message = summary.last_message_id.and_then(|id| {
+                messages.iter().position(|x| x.id == id).map(|i| {
+                    ThreadMessage::from(messages.swap_remove(i), &user)
+                })
+            });
+
+            Conversation {
+                thread_id: summary.thread_id.into(),
+                members: users
+                    .iter()
+                    .filter(|x| summary.members.contains(&x.id.into()))
+                    .cloned()
+                    .collect(),
+                last_message,
+                last_message_at: summary.last_message_at,
+                unread: summary.unread,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(conversations))
+}
+
+#[derive(Deserialize)]
+pub struct NewConversation {
+    /// 除自己以外的会话成员
+    pub user_ids: Vec<UserId>,
+    pub body: String,
+}
+
+#[derive(Serialize)]
+pub struct ConversationCreated {
+    pub thread_id: ThreadId,
+    pub message_id: ThreadMessageId,
+}
+
+/// 发起私信会话并发送第一条消息
+///
+/// 与单个用户已有一对一会话时直接在原会话中发送。
+#[post("")]
+pub async fn conversation_create(
+    req: HttpRequest,
+    new_conversation: web::Json<NewConversation>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_WRITE]),
+    )
+    .await?
+    .1;
+
+    check_forum_ban(&user, &pool).await?;
+
+    let new_conversation = new_conversation.into_inner();
+    let sender_id: database::models::UserId = user.id.into();
+
+    let mut recipient_ids = new_conversation
+        .user_ids
+        .iter()
+        .map(|x| database::models::UserId::from(*x))
+        .filter(|x| *x != sender_id)
+        .collect::<Vec<_>>();
+    recipient_ids.sort_by_key(|x| x.0);
+    recipient_ids.dedup();
+
+    if recipient_ids.is_empty() {
+        return Err(ApiError::InvalidInput("不能给自己发送私信".to_string()));
+    }
+    if recipient_ids.len() + 1 > MAX_CONVERSATION_MEMBERS {
+        return Err(ApiError::InvalidInput(format!(
+            "私信会话最多 {} 人",
+            MAX_CONVERSATION_MEMBERS
+        )));
+    }
+
+    check_message_text(&new_conversation.body, &user, &redis).await?;
+
+    let recipients =
+        database::models::User::get_many_ids(&recipient_ids, &**pool, &redis)
+            .await?;
+    if recipients.len() != recipient_ids.len() {
+        return Err(ApiError::InvalidInput("用户不存在".to_string()));
+    }
+
+    if DirectMessage::is_blocked_between(sender_id, &recipient_ids, &**pool)
+        .await?
+    {
+        return Err(ApiError::InvalidInput("无法向该用户发送私信".to_string()));
+    }
+
+    let existing = if let [recipient_id] = recipient_ids[..] {
+        DirectMessage::find_direct(sender_id, recipient_id, &**pool).await?
+    } else {
+        None
+    };
+
+    if existing.is_none() && !user.role.is_mod() {
+        for recipient in &recipients {
+            let allowed = match DirectMessage::get_privacy(
+                recipient.id,
+                &**pool,
+            )
+            .await?
+            {
+                DmPrivacy::Everyone => true,
+                DmPrivacy::Followers => {
+                    DirectMessage::is_follower(sender_id, recipient.id, &**pool)
+                        .await?
+                }
+                DmPrivacy::Nobody => false,
+            };
+            if !allowed {
+                return Err(ApiError::InvalidInput(format!(
+                    "用户 {} 不接收您的私信",
+                    recipient.username
+                )));
+            }
+        }
+    }
+
+    let mut members = recipient_ids.clone();
+    members.push(sender_id);
+
+    let mut transaction = pool.begin().await?;
+
+    let thread_id = match existing {
+        Some(thread_id) => thread_id,
+        None => {
+            ThreadBuilder {
+                type_: ThreadType::DirectMessage,
+                members: members.clone(),
+                project_id: None,
+                report_id: None,
+                ban_appeal_id: None,
+                creator_application_id: None,
+            }
+            .insert(&mut transaction)
+            .await?
+        }
+    };
+
+    let message_id = ThreadMessageBuilder {
+        author_id: Some(sender_id),
+        body: MessageBody::Text {
+            body: new_conversation.body,
+            private: false,
+            replying_to: None,
+            associated_images: vec![],
+        },
+        thread_id,
+        hide_identity: false,
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    DirectMessage::mark_read(thread_id, sender_id, &mut transaction).await?;
+
+    let mut events = notify_direct_message(
+        thread_id,
+        message_id,
+        &user,
+        &members,
+        &mut transaction,
+        &redis,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    events.extend(recipient_ids.into_iter().map(|x| {
+        (
+            x,
+            PushEventBody::ThreadMessage {
+                thread_id: thread_id.into(),
+                message_id: message_id.into(),
+            },
+        )
+    }));
+    crate::queue::push::publish(events, &redis).await;
+
+    Ok(HttpResponse::Ok().json(ConversationCreated {
+        thread_id: thread_id.into(),
+        message_id: message_id.into(),
+    }))
+}
+
+/// 私信未读数
+#[get("unread")]
+pub async fn unread_count(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_READ]),
+    )
+    .await?
+    .1;
+
+    let (unread, conversations) =
+        DirectMessage::get_unread_count(user.id.into(), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(UnreadCount {
+        unread,
+        conversations,
+    }))
+}
+
+/// 将会话标记为已读，同时将该会话的私信通知标记为已读
+#[post("{id}/read")]
+pub async fn conversation_read(
+    req: HttpRequest,
+    info: web::Path<(ThreadId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_READ]),
+    )
+    .await?
+    .1;
+
+    let thread_id: database::models::ThreadId = info.into_inner().0.into();
+    let user_id: database::models::UserId = user.id.into();
+
+    let mut transaction = pool.begin().await?;
+
+    if !DirectMessage::mark_read(thread_id, user_id, &mut transaction).await? {
+        return Err(ApiError::NotFound);
+    }
+
+    let notification_ids = DirectMessage::get_unread_notifications(
+        thread_id,
+        user_id,
+        &mut transaction,
+    )
+    .await?;
+    if !notification_ids.is_empty() {
+        Notification::read_many(&notification_ids, &mut transaction, &redis)
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 退出私信会话
+///
+/// 一对一会话中任一方退出后，对方无法继续在该会话中发送消息。
+#[delete("{id}")]
+pub async fn conversation_leave(
+    req: HttpRequest,
+    info: web::Path<(ThreadId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_WRITE]),
+    )
+    .await?
+    .1;
+
+    let thread_id: database::models::ThreadId = info.into_inner().0.into();
+    let user_id: database::models::UserId = user.id.into();
+
+    let thread = database::models::Thread::get(thread_id, &**pool).await?;
+    if !thread.is_some_and(|x| x.type_ == ThreadType::DirectMessage) {
+        return Err(ApiError::NotFound);
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    if !DirectMessage::leave(thread_id, user_id, &mut transaction).await? {
+        return Err(ApiError::NotFound);
+    }
+
+    let notification_ids = DirectMessage::get_unread_notifications(
+        thread_id,
+        user_id,
+        &mut transaction,
+    )
+    .await?;
+    if !notification_ids.is_empty() {
+        Notification::read_many(&notification_ids, &mut transaction, &redis)
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[get("settings")]
+pub async fn settings_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_READ]),
+    )
+    .await?
+    .1;
+
+    let privacy = DirectMessage::get_privacy(user.id.into(), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(DmSettings { privacy }))
+}
+
+#[patch("settings")]
+pub async fn settings_edit(
+    req: HttpRequest,
+    settings: web::Json<DmSettings>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_WRITE]),
+    )
+    .await?
+    .1;
+
+    let mut transaction = pool.begin().await?;
+    DirectMessage::set_privacy(
+        user.id.into(),
+        settings.privacy,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 当前用户屏蔽的用户
+#[get("blocks")]
+pub async fn blocks_list(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_READ]),
+    )
+    .await?
+    .1;
+
+    let blocks = DirectMessage::get_blocks(user.id.into(), &**pool).await?;
+    let user_ids = blocks.iter().map(|x| x.blocked_user_id).collect::<Vec<_>>();
+    let users =
+        database::models::User::get_many_ids(&user_ids, &**pool, &redis)
+            .await?;
+
+    let blocks = blocks
+        .into_iter()
+        .filter_map(|block| {
+            users
+                .iter()
+                .find(|x| x.id == block.blocked_user_id)
+                .map(|x| UserBlock {
+                    user: User::from(x.clone()),
+                    created_at: block.created_at,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(blocks))
+}
+
+#[post("blocks/{user_id}")]
+pub async fn user_block(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_WRITE]),
+    )
+    .await?
+    .1;
+
+    let blocked_user_id: database::models::UserId = info.into_inner().0.into();
+    if blocked_user_id == user.id.into() {
+        return Err(ApiError::InvalidInput("不能屏蔽自己".to_string()));
+    }
+    if database::models::User::get_id(blocked_user_id, &**pool, &redis)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    let mut transaction = pool.begin().await?;
+    DirectMessage::block(user.id.into(), blocked_user_id, &mut transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[delete("blocks/{user_id}")]
+pub async fn user_unblock(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::THREAD_WRITE]),
+    )
+    .await?
+    .1;
+
+    let blocked_user_id: database::models::UserId = info.into_inner().0.into();
+
+    let mut transaction = pool.begin().await?;
+    let removed = DirectMessage::unblock(
+        user.id.into(),
+        blocked_user_id,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if removed {
+        Ok(HttpResponse::NoContent().body(""))
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
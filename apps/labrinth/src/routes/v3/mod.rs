@@ -4,16 +4,23 @@ use actix_web::{HttpResponse, web};
 use serde_json::json;
 
 pub mod analytics_get;
+pub mod audit_log;
 pub mod bans;
+pub mod bundles;
 pub mod collections;
+pub mod coupons;
 pub mod forum;
 pub mod images;
+pub mod messages;
 pub mod notifications;
 pub mod organizations;
 pub mod payouts;
 pub mod project_creation;
 pub mod projects;
+pub mod push;
 pub mod reports;
+pub mod search;
+pub mod statements;
 pub mod statistics;
 pub mod tags;
 pub mod teams;
@@ -27,11 +34,15 @@ pub mod creator;
 pub mod image_reviews;
 pub mod incentive;
 pub mod issues;
+pub mod ledger;
+pub mod licenses;
 pub mod oauth_clients;
 pub mod payment_merchant;
 pub mod profile_reviews;
 pub mod project_order;
 pub mod project_pricing;
+pub mod purchase_codes;
+pub mod refunds;
 pub mod user_purchase;
 #[allow(clippy::unnecessary_unwrap, clippy::explicit_auto_deref)]
 mod wikis;
@@ -42,14 +53,22 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("v3")
             .wrap(default_cors())
             .configure(analytics_get::config)
+            .configure(audit_log::config)
+            .configure(bundles::config)
             .configure(collections::config)
+            .configure(coupons::config)
             .configure(images::config)
+            .configure(messages::config)
             .configure(notifications::config)
             .configure(organizations::config)
             .configure(project_creation::config)
             .configure(projects::config)
+            .configure(push::config)
             .configure(project_pricing::config)
+            .configure(purchase_codes::config)
             .configure(reports::config)
+            .configure(search::config)
+            .configure(statements::config)
             .configure(statistics::config)
             .configure(tags::config)
             .configure(teams::config)
@@ -67,7 +86,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .configure(issues::config)
             .configure(bans::config)
             .configure(incentive::config)
+            .configure(ledger::config)
             .configure(project_order::config)
+            .configure(refunds::config)
+            .configure(licenses::config)
             .configure(yunzhanghu::config),
     );
 }
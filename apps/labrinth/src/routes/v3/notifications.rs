@@ -1,19 +1,35 @@
 use crate::auth::get_user_from_headers;
 use crate::database;
+use crate::database::models::notification_preference_item::NotificationPreference as DBNotificationPreference;
 use crate::database::redis::RedisPool;
 use crate::models::ids::NotificationId;
-use crate::models::notifications::Notification;
+use crate::models::notifications::{
+    Notification, NotificationBody, NotificationChannel, NotificationPreference,
+};
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
 use actix_web::{HttpRequest, HttpResponse, web};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("notifications", web::get().to(notifications_get));
     cfg.route("notifications", web::patch().to(notifications_read));
     cfg.route("notifications", web::delete().to(notifications_delete));
+    cfg.route(
+        "notifications/preferences",
+        web::get().to(notification_preferences_get),
+    );
+    cfg.route(
+        "notifications/preferences",
+        web::patch().to(notification_preferences_edit),
+    );
+    cfg.route(
+        "notifications/unsubscribe",
+        web::post().to(notifications_unsubscribe),
+    );
 
     cfg.service(
         web::scope("notification")
@@ -312,3 +328,134 @@ pub async fn notifications_delete(
 
     Ok(HttpResponse::NoContent().body(""))
 }
+
+/// 当前用户各通知类型的投递方式
+pub async fn notification_preferences_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::NOTIFICATION_READ]),
+    )
+    .await?
+    .1;
+
+    let saved =
+        DBNotificationPreference::get_user(user.id.into(), &**pool).await?;
+
+    let preferences = NotificationBody::CONFIGURABLE_TYPES
+        .iter()
+        .map(|x| NotificationPreference {
+            notification_type: x.to_string(),
+            channel: saved
+                .get(*x)
+                .copied()
+                .unwrap_or(NotificationChannel::InApp),
+            can_disable: !NotificationBody::is_required_type(x),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+#[derive(Deserialize)]
+pub struct EditNotificationPreferences {
+    /// 通知类型 -> 投递方式，只修改提交的类型
+    pub preferences: HashMap<String, NotificationChannel>,
+}
+
+pub async fn notification_preferences_edit(
+    req: HttpRequest,
+    body: web::Json<EditNotificationPreferences>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::NOTIFICATION_WRITE]),
+    )
+    .await?
+    .1;
+
+    let mut preferences = Vec::with_capacity(body.preferences.len());
+    for (notification_type, channel) in &body.preferences {
+        if !NotificationBody::CONFIGURABLE_TYPES
+            .contains(&notification_type.as_str())
+        {
+            return Err(ApiError::InvalidInput(format!(
+                "未知的通知类型: {notification_type}"
+            )));
+        }
+        if *channel == NotificationChannel::Off
+            && NotificationBody::is_required_type(notification_type)
+        {
+            return Err(ApiError::InvalidInput(format!(
+                "通知类型 {notification_type} 不能关闭"
+            )));
+        }
+        preferences.push((notification_type.clone(), *channel));
+    }
+
+    if preferences.is_empty() {
+        return Ok(HttpResponse::NoContent().body(""));
+    }
+
+    let mut transaction = pool.begin().await?;
+    DBNotificationPreference::set_many(
+        user.id.into(),
+        &preferences,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeRequest {
+    pub token: String,
+    /// 只退订该类型的通知邮件，省略时退订全部通知邮件与摘要
+    #[serde(rename = "type")]
+    pub notification_type: Option<String>,
+}
+
+/// 邮件中的一键退订，通过令牌识别用户，无需登录
+pub async fn notifications_unsubscribe(
+    body: web::Json<UnsubscribeRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    if let Some(notification_type) = &body.notification_type
+        && !NotificationBody::CONFIGURABLE_TYPES
+            .contains(&notification_type.as_str())
+    {
+        return Err(ApiError::InvalidInput(format!(
+            "未知的通知类型: {notification_type}"
+        )));
+    }
+
+    let mut transaction = pool.begin().await?;
+    let user_id = DBNotificationPreference::unsubscribe(
+        &body.token,
+        body.notification_type.as_deref(),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if user_id.is_none() {
+        return Err(ApiError::InvalidInput("退订链接无效".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().body(""))
+}
@@ -48,6 +48,18 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route(
                 "{id}/members",
                 web::get().to(super::teams::team_members_get_organization),
+            )
+            .route(
+                "{id}/coupons",
+                web::get().to(super::coupons::organization_coupons_get),
+            )
+            .route(
+                "{id}/coupons",
+                web::post().to(super::coupons::organization_coupon_create),
+            )
+            .route(
+                "{id}/bundles",
+                web::get().to(super::bundles::organization_bundles_get),
             ),
     );
 }
@@ -874,6 +886,12 @@ pub async fn organization_projects_add(
         )
         .execute(&mut *transaction)
         .await?;
+        // 项目的作者随组织变化，需要更新搜索索引
+        database::models::SearchIndexQueueEntry::enqueue(
+            &[project_item.inner.id],
+            &mut *transaction,
+        )
+        .await?;
 
         // 原来的所有者不再是所有者（因为它现在是组织的，'给予'给他们）
         // 原来的所有者仍然是项目的成员，但不再是所有者
@@ -1077,6 +1095,12 @@ pub async fn organization_projects_remove(
         )
         .execute(&mut *transaction)
         .await?;
+        // 项目的作者随组织变化，需要更新搜索索引
+        database::models::SearchIndexQueueEntry::enqueue(
+            &[project_item.inner.id],
+            &mut *transaction,
+        )
+        .await?;
 
         transaction.commit().await?;
         database::models::User::clear_project_cache(
@@ -2,10 +2,12 @@ use crate::auth::validate::{
     check_is_admin_from_headers, get_user_record_from_bearer_token,
 };
 use crate::auth::{AuthenticationError, get_user_from_headers};
-use crate::database::models::generate_payout_id;
 use crate::database::models::yunzhanghu_profile_item::{
     YunzhanghuProfile, YzhSignStatus,
 };
+use crate::database::models::{
+    AuditLogBuilder, DatabaseError, LedgerEntry, generate_payout_id,
+};
 use crate::database::redis::RedisPool;
 use crate::models::ids::PayoutId;
 use crate::models::pats::Scopes;
@@ -14,8 +16,10 @@ use crate::models::payouts::{
     PayoutStatus,
 };
 use crate::queue::payouts::{PayoutsQueue, make_aditude_request};
+use crate::queue::push::PushEventBody;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
+use crate::util::ip::request_ip;
 use crate::util::yunzhanghu::{NotifyEnvelope, YzhClient, api as yzh_api};
 use actix_web::{HttpRequest, HttpResponse, delete, get, post, web};
 use chrono::{DateTime, Datelike, Duration, TimeZone, Utc, Weekday};
@@ -702,6 +706,24 @@ pub async fn admin_confirm_payout(
                     update_err
                 );
             }
+            AuditLogBuilder {
+                actor_id: Some(admin_id),
+                action: "payout.confirm".to_string(),
+                target_type: "payout".to_string(),
+                target_id: Some(payout_id.0),
+                before: Some(serde_json::json!({
+                    "user_id": user_id.0,
+                    "amount": pay_amount,
+                    "status": PayoutStatus::InTransit.as_str(),
+                })),
+                after: Some(serde_json::json!({
+                    "submit_error": err.to_string(),
+                })),
+                reason: None,
+                ip: request_ip(&req),
+            }
+            .record(&pool)
+            .await?;
             return Err(err);
         }
     };
@@ -720,6 +742,26 @@ pub async fn admin_confirm_payout(
     .execute(&**pool)
     .await?;
 
+    AuditLogBuilder {
+        actor_id: Some(admin_id),
+        action: "payout.confirm".to_string(),
+        target_type: "payout".to_string(),
+        target_id: Some(payout_id.0),
+        before: Some(serde_json::json!({
+            "user_id": user_id.0,
+            "amount": pay_amount,
+            "status": PayoutStatus::InTransit.as_str(),
+        })),
+        after: Some(serde_json::json!({
+            "order_id": order_id,
+            "platform_id": resp.ref_id,
+        })),
+        reason: None,
+        ip: request_ip(&req),
+    }
+    .record(&pool)
+    .await?;
+
     crate::database::models::User::clear_caches(&[(user_id, None)], &redis)
         .await?;
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
@@ -812,11 +854,40 @@ pub async fn admin_reject_payout(
     )
     .execute(&mut *tx)
     .await?;
+    LedgerEntry::reverse_withdrawal(payout_id, &mut tx).await?;
+    AuditLogBuilder {
+        actor_id: Some(admin_id),
+        action: "payout.reject".to_string(),
+        target_type: "payout".to_string(),
+        target_id: Some(payout_id.0),
+        before: Some(serde_json::json!({
+            "user_id": payout.user_id,
+            "status": payout.status,
+        })),
+        after: Some(serde_json::json!({
+            "status": PayoutStatus::Cancelled.as_str(),
+        })),
+        reason: reason.clone(),
+        ip: request_ip(&req),
+    }
+    .insert(&mut tx)
+    .await?;
     tx.commit().await?;
 
     let user_id = crate::database::models::UserId(payout.user_id);
     crate::database::models::User::clear_caches(&[(user_id, None)], &redis)
         .await?;
+    crate::queue::push::publish(
+        vec![(
+            user_id,
+            PushEventBody::PayoutStatus {
+                payout_id: public_id,
+                status: PayoutStatus::Cancelled,
+            },
+        )],
+        &redis,
+    )
+    .await;
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
 
@@ -1160,7 +1231,7 @@ fn normalize_requested_payout_amount(
     ensure_supported_payout_amount(amount)
 }
 
-async fn quote_yunzhanghu_payout(
+pub(crate) async fn quote_yunzhanghu_payout(
     amount: Decimal,
     profile: &YunzhanghuProfile,
 ) -> Result<PayoutQuote, ApiError> {
@@ -1448,6 +1519,14 @@ pub async fn create_payout(
     };
 
     payout_item.insert(&mut transaction).await?;
+    LedgerEntry::record_withdrawal(
+        payout_id,
+        user.id,
+        amount,
+        quote.user_fee,
+        &mut transaction,
+    )
+    .await?;
     transaction.commit().await?;
 
     crate::database::models::User::clear_caches(&[(user.id, None)], &redis)
@@ -1560,70 +1639,16 @@ pub async fn get_balance(
     Ok(HttpResponse::Ok().json(balance))
 }
 
+/// 创作者余额由账本中可提现收益科目的明细汇总得出
 async fn get_user_balance(
     user_id: crate::database::models::ids::UserId,
     pool: &PgPool,
-) -> Result<UserBalance, sqlx::Error> {
-    let available = sqlx::query!(
-        "
-        SELECT SUM(amount)
-        FROM payouts_values
-        WHERE user_id = $1 AND date_available <= NOW()
-        ",
-        user_id.0
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    let pending = sqlx::query!(
-        "
-        SELECT SUM(amount)
-        FROM payouts_values
-        WHERE user_id = $1 AND date_available > NOW()
-        ",
-        user_id.0
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    let withdrawn = sqlx::query!(
-        "
-        SELECT
-            SUM(amount) amount,
-            SUM(
-                CASE
-                    WHEN method = 'yunzhanghu_alipay' THEN 0
-                    ELSE COALESCE(fee, 0)
-                END
-            ) fee
-        FROM payouts
-        WHERE user_id = $1 AND (status = 'success' OR status = 'in-transit')
-        ",
-        user_id.0
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    let available = available
-        .map(|x| x.sum.unwrap_or(Decimal::ZERO))
-        .unwrap_or(Decimal::ZERO);
-    let pending = pending
-        .map(|x| x.sum.unwrap_or(Decimal::ZERO))
-        .unwrap_or(Decimal::ZERO);
-    let (withdrawn, fees) = withdrawn
-        .map(|x| {
-            (
-                x.amount.unwrap_or(Decimal::ZERO),
-                x.fee.unwrap_or(Decimal::ZERO),
-            )
-        })
-        .unwrap_or((Decimal::ZERO, Decimal::ZERO));
+) -> Result<UserBalance, DatabaseError> {
+    let balance = LedgerEntry::get_balance(user_id, pool).await?;
 
     Ok(UserBalance {
-        available: available.round_dp(16)
-            - withdrawn.round_dp(16)
-            - fees.round_dp(16),
-        pending,
+        available: balance.available.round_dp(16),
+        pending: balance.pending.round_dp(16),
     })
 }
 
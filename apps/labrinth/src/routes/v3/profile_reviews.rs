@@ -4,14 +4,15 @@ use sqlx::PgPool;
 
 use super::ApiError;
 use crate::auth::get_user_from_headers;
-use crate::database::models::User;
 use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::{AuditLogBuilder, User};
 use crate::database::redis::RedisPool;
 use crate::file_hosting::FileHost;
 use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
 use crate::util::img::delete_old_images;
+use crate::util::ip::request_ip;
 use std::sync::Arc;
 
 /// 用户撤销资料审核
@@ -324,7 +325,7 @@ pub async fn approve_review(
                         ),
                     },
                 };
-                notification
+                let push_events = notification
                     .insert(
                         crate::database::models::ids::UserId(review.user_id),
                         &mut transaction,
@@ -332,6 +333,19 @@ pub async fn approve_review(
                     )
                     .await?;
 
+                review_audit_log(
+                    &req,
+                    moderator.id,
+                    review_id,
+                    review.user_id,
+                    &review.review_type,
+                    &review.new_value,
+                    "rejected",
+                    Some("用户名在审核期间已被其他用户占用".to_string()),
+                )
+                .insert(&mut transaction)
+                .await?;
+
                 transaction.commit().await?;
 
                 User::clear_caches(
@@ -339,6 +353,7 @@ pub async fn approve_review(
                     &redis,
                 )
                 .await?;
+                crate::queue::push::publish(push_events, &redis).await;
 
                 return Err(ApiError::InvalidInput(format!(
                     "用户名 '{}' 在审核期间已被其他用户占用",
@@ -421,7 +436,7 @@ pub async fn approve_review(
             review_notes: body.notes.clone(),
         },
     };
-    notification
+    let push_events = notification
         .insert(
             crate::database::models::ids::UserId(review.user_id),
             &mut transaction,
@@ -429,7 +444,21 @@ pub async fn approve_review(
         )
         .await?;
 
+    review_audit_log(
+        &req,
+        moderator.id,
+        review_id,
+        review.user_id,
+        &review.review_type,
+        &review.new_value,
+        "approved",
+        body.notes.clone(),
+    )
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
@@ -558,7 +587,7 @@ pub async fn reject_review(
             review_notes: body.notes.clone(),
         },
     };
-    notification
+    let push_events = notification
         .insert(
             crate::database::models::ids::UserId(review.user_id),
             &mut transaction,
@@ -566,7 +595,21 @@ pub async fn reject_review(
         )
         .await?;
 
+    review_audit_log(
+        &req,
+        moderator.id,
+        review_id,
+        review.user_id,
+        &review.review_type,
+        &review.new_value,
+        "rejected",
+        body.notes.clone(),
+    )
+    .insert(&mut transaction)
+    .await?;
+
     transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
@@ -696,6 +739,7 @@ pub async fn approve_all_pending(
     // 收集需要删除的旧头像
     let mut old_avatars_to_delete: Vec<(Option<String>, Option<String>)> =
         Vec::new();
+    let mut push_events = Vec::new();
 
     for review in &pending_reviews {
         let db_user_id = crate::database::models::ids::UserId(review.user_id);
@@ -724,19 +768,21 @@ pub async fn approve_all_pending(
                     .execute(&mut *transaction)
                     .await?;
 
-                    NotificationBuilder {
-                        body: NotificationBody::ProfileReviewResult {
-                            review_id: review.id,
-                            review_type: "username".to_string(),
-                            status: "rejected".to_string(),
-                            review_notes: Some(
-                                "批量审批：用户名在审核期间已被其他用户占用"
-                                    .to_string(),
-                            ),
-                        },
-                    }
-                    .insert(db_user_id, &mut transaction, &redis)
-                    .await?;
+                    push_events.extend(
+                        NotificationBuilder {
+                            body: NotificationBody::ProfileReviewResult {
+                                review_id: review.id,
+                                review_type: "username".to_string(),
+                                status: "rejected".to_string(),
+                                review_notes: Some(
+                                    "批量审批：用户名在审核期间已被其他用户占用"
+                                        .to_string(),
+                                ),
+                            },
+                        }
+                        .insert(db_user_id, &mut transaction, &redis)
+                        .await?,
+                        );
 
                     cache_entries
                         .push((db_user_id, Some(review.username.clone())));
@@ -855,19 +901,34 @@ pub async fn approve_all_pending(
             .iter()
             .filter(|r| approved_ids.contains(&r.id))
         {
-            NotificationBuilder {
-                body: NotificationBody::ProfileReviewResult {
-                    review_id: review.id,
-                    review_type: review.review_type.clone(),
-                    status: "approved".to_string(),
-                    review_notes: body.notes.clone(),
-                },
-            }
-            .insert(
-                crate::database::models::ids::UserId(review.user_id),
-                &mut transaction,
-                &redis,
+            push_events.extend(
+                NotificationBuilder {
+                    body: NotificationBody::ProfileReviewResult {
+                        review_id: review.id,
+                        review_type: review.review_type.clone(),
+                        status: "approved".to_string(),
+                        review_notes: body.notes.clone(),
+                    },
+                }
+                .insert(
+                    crate::database::models::ids::UserId(review.user_id),
+                    &mut transaction,
+                    &redis,
+                )
+                .await?,
+            );
+
+            review_audit_log(
+                &req,
+                moderator.id,
+                review.id,
+                review.user_id,
+                &review.review_type,
+                &review.new_value,
+                "approved",
+                body.notes.clone(),
             )
+            .insert(&mut transaction)
             .await?;
         }
     }
@@ -880,6 +941,7 @@ pub async fn approve_all_pending(
     if !cache_entries.is_empty() {
         let _ = User::clear_caches(&cache_entries, &redis).await;
     }
+    crate::queue::push::publish(push_events, &redis).await;
     crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
         .await;
 
@@ -897,6 +959,35 @@ pub async fn approve_all_pending(
     })))
 }
 
+/// 资料审核决定的审计日志，`before` 记录待审核的修改内容
+#[allow(clippy::too_many_arguments)]
+fn review_audit_log(
+    req: &HttpRequest,
+    moderator_id: crate::models::ids::UserId,
+    review_id: i64,
+    user_id: i64,
+    review_type: &str,
+    new_value: &str,
+    status: &str,
+    notes: Option<String>,
+) -> AuditLogBuilder {
+    AuditLogBuilder {
+        actor_id: Some(moderator_id.into()),
+        action: format!("profile_review.{status}"),
+        target_type: "profile_review".to_string(),
+        target_id: Some(review_id),
+        before: Some(serde_json::json!({
+            "user_id": user_id,
+            "review_type": review_type,
+            "new_value": new_value,
+            "status": "pending",
+        })),
+        after: Some(serde_json::json!({ "status": status })),
+        reason: notes,
+        ip: request_ip(req),
+    }
+}
+
 /// 发送飞书审核通知
 async fn send_feishu_review_notification(
     review_type: &str,
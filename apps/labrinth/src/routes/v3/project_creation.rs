@@ -22,15 +22,19 @@ use crate::models::projects::{
 use crate::models::teams::{OrganizationPermissions, ProjectPermissions};
 use crate::models::threads::ThreadType;
 use crate::models::users::UserId;
+use crate::queue::moderation::{
+    ModerationMessage, ModerationMessages, send_automod_message,
+};
 use crate::queue::session::AuthQueue;
 use crate::search::indexing::IndexingError;
 use crate::util::img::upload_image_optimized;
+use crate::util::ratelimit::{RateLimit, UPLOAD};
 use crate::util::routes::read_from_field;
 use crate::util::validate::validation_errors_to_string;
 use actix_multipart::{Field, Multipart};
 use actix_web::http::StatusCode;
 use actix_web::web::{self, Data};
-use actix_web::{HttpRequest, HttpResponse};
+use actix_web::{HttpRequest, HttpResponse, guard};
 use chrono::Utc;
 use futures::stream::StreamExt;
 use image::ImageError;
@@ -44,7 +48,12 @@ use thiserror::Error;
 use validator::Validate;
 
 pub fn config(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.route("project", web::post().to(project_create));
+    cfg.service(
+        web::resource("project")
+            .guard(guard::Post())
+            .wrap(RateLimit(&UPLOAD))
+            .route(web::post().to(project_create)),
+    );
 }
 
 #[derive(Error, Debug)]
@@ -418,6 +427,8 @@ async fn project_create_inner(
     let project_create_data: ProjectCreateData;
     let mut versions;
     let mut versions_map = std::collections::HashMap::new();
+    let mut suspicious_code: HashMap<String, Vec<ModerationMessage>> =
+        HashMap::new();
     let mut gallery_urls = Vec::new();
     {
         // 第一个 multipart 字段必须命名为 "data" 并包含一个 JSON `ProjectCreateData` 对象。
@@ -770,6 +781,7 @@ async fn project_create_inner(
                 .iter()
                 .map(|x| x.filename.clone())
                 .collect();
+            let mut messages = Vec::new();
             // 上传新的 jar 文件
             super::version_creation::upload_file(
                 &mut field,
@@ -779,10 +791,13 @@ async fn project_create_inner(
                 uploaded_files,
                 &mut created_version.files,
                 &mut created_version.dependencies,
+                &mut Vec::new(),
+                &mut messages,
                 &cdn_url,
                 &content_disposition,
                 project_id,
                 created_version.version_id.into(),
+                &created_version.version_number,
                 &created_version.version_fields,
                 version_data.loaders.clone(),
                 version_data.primary_file.is_some(),
@@ -793,9 +808,17 @@ async fn project_create_inner(
                 redis,
                 current_user.username.clone(),
                 project_create_data.is_paid,
+                version_data.convert_to_mrpack,
             )
             .await?;
 
+            if !messages.is_empty() {
+                suspicious_code
+                    .entry(created_version.version_number.clone())
+                    .or_default()
+                    .extend(messages);
+            }
+
             Ok(())
         }
         .await;
@@ -823,6 +846,17 @@ async fn project_create_inner(
             }
         }
 
+        // 包含可疑代码的版本暂缓发布，等待审核员复核
+        for version in versions.iter_mut() {
+            if suspicious_code
+                .get(&version.version_number)
+                .is_some_and(|x| x.iter().any(|x| x.withholds_version()))
+            {
+                version.requested_status = Some(version.status);
+                version.status = VersionStatus::Draft;
+            }
+        }
+
         // 将类别名称列表转换为实际类别
         let mut categories =
             Vec::with_capacity(project_create_data.categories.len());
@@ -1003,6 +1037,8 @@ async fn project_create_inner(
         let now = Utc::now();
 
         let id = project_builder_actual.insert(&mut *transaction).await?;
+        models::SearchIndexQueueEntry::enqueue(&[id], &mut **transaction)
+            .await?;
         User::clear_project_cache(&[current_user.id.into()], redis).await?;
 
         // 如果是付费资源，插入定价信息
@@ -1070,6 +1106,18 @@ async fn project_create_inner(
         .insert(&mut *transaction)
         .await?;
 
+        if !suspicious_code.is_empty() {
+            send_automod_message(
+                thread_id,
+                &ModerationMessages {
+                    messages: vec![],
+                    version_specific: suspicious_code,
+                },
+                transaction,
+            )
+            .await?;
+        }
+
         let loaders = project_builder
             .initial_versions
             .iter()
@@ -1,32 +1,44 @@
 //! 项目订单路由（买家端）
 //!
 //! 提供用户购买付费项目的功能。
-//! 包括创建订单、查询订单状态、获取支付二维码等。
+//! 包括创建订单、购买捆绑包或一次购买多个项目、赠送给其他用户、查询订单状态、获取支付二维码等。
 
-use actix_web::{HttpRequest, HttpResponse, web};
-use chrono::Utc;
+use actix_web::{HttpRequest, HttpResponse, guard, web};
+use chrono::{Duration, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::time::Duration as StdDuration;
 use validator::Validate;
 
+use crate::auth::checks::is_visible_project;
 use crate::auth::get_user_from_headers;
+use crate::database::models::DatabaseError;
+use crate::database::models::LedgerEntry;
+use crate::database::models::User as DBUser;
 use crate::database::models::UserId as DBUserId;
 use crate::database::models::ids::ProjectId as DbProjectId;
+use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::payment_merchant_item::PaymentMerchant;
 use crate::database::models::payment_order_item::{
-    OrderStatus, PaymentMethod, PaymentOrder,
+    OrderItemPrice, OrderStatus, PaymentMethod, PaymentOrder,
 };
+use crate::database::models::project_bundle_item::ProjectBundle;
 use crate::database::models::project_item::Project;
 use crate::database::models::project_pricing_item::ProjectPricing;
 use crate::database::models::team_item::TeamMember;
 use crate::database::models::user_purchase_item::UserPurchase;
 use crate::database::redis::RedisPool;
-use crate::models::ids::ProjectId;
+use crate::models::ids::{ProjectBundleId, ProjectId, UserId};
+use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
+use crate::models::users::User;
+use crate::queue::push::PushEvents;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
+use crate::routes::v3::bundles::quote_bundle;
+use crate::routes::v3::coupons::quote_order_price;
+use crate::util::ratelimit::{ORDER, RateLimit};
 use crate::util::validate::validation_errors_to_string;
 
 /// 支付平台 API 请求超时时间（秒）
@@ -35,8 +47,19 @@ const PAYMENT_API_TIMEOUT_SECS: u64 = 15;
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("order")
-            .route("", web::post().to(create_order))
+            .service(
+                web::resource("")
+                    .guard(guard::Post())
+                    .wrap(RateLimit(&ORDER))
+                    .route(web::post().to(create_order)),
+            )
             .route("", web::get().to(list_user_orders))
+            .service(
+                web::resource("/checkout")
+                    .guard(guard::Post())
+                    .wrap(RateLimit(&ORDER))
+                    .route(web::post().to(checkout)),
+            )
             .route("/{order_no}", web::get().to(get_order))
             .route("/{order_no}/status", web::get().to(query_order_status)),
     );
@@ -53,6 +76,12 @@ pub struct CreateOrderRequest {
     /// 支付方式: "alipay" 或 "wechat"
     #[validate(custom(function = "validate_payment_method"))]
     pub payment_method: String,
+    /// 优惠码（可选）
+    #[validate(length(max = 32, message = "优惠码无效"))]
+    pub coupon_code: Option<String>,
+    /// 赠送给指定用户（用户名），不填表示为自己购买
+    #[validate(length(min = 1, max = 64, message = "受赠用户名无效"))]
+    pub gift_to: Option<String>,
 }
 
 fn validate_payment_method(
@@ -69,6 +98,23 @@ fn validate_payment_method(
     }
 }
 
+/// 多项目结算请求，bundle_id 与 project_ids 二选一
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CheckoutRequest {
+    /// 购买捆绑包
+    pub bundle_id: Option<ProjectBundleId>,
+    /// 一次购买多个项目（slug 或 base62 ID）
+    #[serde(default)]
+    #[validate(length(max = 20, message = "一次最多购买 20 个项目"))]
+    pub project_ids: Vec<String>,
+    /// 支付方式: "alipay" 或 "wechat"
+    #[validate(custom(function = "validate_payment_method"))]
+    pub payment_method: String,
+    /// 赠送给指定用户（用户名），不填表示为自己购买
+    #[validate(length(min = 1, max = 64, message = "受赠用户名无效"))]
+    pub gift_to: Option<String>,
+}
+
 /// 订单创建响应
 #[derive(Debug, Clone, Serialize)]
 pub struct CreateOrderResponse {
@@ -76,6 +122,10 @@ pub struct CreateOrderResponse {
     pub order_no: String,
     /// 支付金额（元）
     pub amount: Decimal,
+    /// 原价（元）
+    pub original_amount: Decimal,
+    /// 促销与优惠券减免的金额（元）
+    pub discount_amount: Decimal,
     /// 支付二维码 URL（base64 图片或链接）
     pub qr_code_url: Option<String>,
     /// 订单过期时间
@@ -84,6 +134,32 @@ pub struct CreateOrderResponse {
     pub payment_method: String,
     /// 项目信息
     pub project: OrderProjectInfo,
+    /// 赠送订单的受赠用户
+    pub gift_recipient_id: Option<UserId>,
+}
+
+/// 多项目结算响应
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutResponse {
+    pub order_no: String,
+    pub amount: Decimal,
+    pub original_amount: Decimal,
+    pub discount_amount: Decimal,
+    pub qr_code_url: Option<String>,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+    pub payment_method: String,
+    pub bundle_id: Option<ProjectBundleId>,
+    pub items: Vec<OrderItemInfo>,
+    pub gift_recipient_id: Option<UserId>,
+}
+
+/// 订单明细中的单个项目
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderItemInfo {
+    pub project: OrderProjectInfo,
+    pub original_amount: Decimal,
+    /// 分摊到该项目的支付金额
+    pub amount: Decimal,
 }
 
 /// 订单中的项目信息
@@ -99,13 +175,20 @@ pub struct OrderProjectInfo {
 pub struct OrderDetailResponse {
     pub order_no: String,
     pub amount: Decimal,
+    pub original_amount: Decimal,
+    pub discount_amount: Decimal,
     pub status: String,
     pub payment_method: Option<String>,
     pub qr_code_url: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub paid_at: Option<chrono::DateTime<Utc>>,
     pub expires_at: Option<chrono::DateTime<Utc>>,
+    /// 订单的第一个项目
     pub project: OrderProjectInfo,
+    pub bundle_id: Option<ProjectBundleId>,
+    pub items: Vec<OrderItemInfo>,
+    /// 赠送订单的受赠用户
+    pub gift_recipient_id: Option<UserId>,
 }
 
 /// 订单状态查询响应
@@ -127,12 +210,18 @@ pub struct UserOrdersResponse {
 pub struct OrderSummary {
     pub order_no: String,
     pub amount: Decimal,
+    pub original_amount: Decimal,
+    pub discount_amount: Decimal,
     pub status: String,
     pub payment_method: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub paid_at: Option<chrono::DateTime<Utc>>,
     pub project_id: String,
     pub project_title: Option<String>,
+    /// 订单包含的项目数量
+    pub item_count: i32,
+    /// 赠送订单的受赠用户
+    pub gift_recipient_id: Option<UserId>,
 }
 
 /// 支付接口创建订单响应
@@ -174,7 +263,8 @@ struct PaymentApiQueryOrderData {
 ///
 /// POST /v3/order
 ///
-/// 用户发起购买请求，创建订单并返回支付二维码
+/// 用户发起购买请求，创建订单并返回支付二维码。
+/// 填写 gift_to 时为赠送订单，支付成功后购买记录授予受赠用户。
 pub async fn create_order(
     req: HttpRequest,
     body: web::Json<CreateOrderRequest>,
@@ -198,6 +288,13 @@ pub async fn create_order(
     .1;
 
     let db_user_id = DBUserId(user.id.0 as i64);
+    let gift_recipient_id = resolve_gift_recipient(
+        body.gift_to.as_deref(),
+        db_user_id,
+        &pool,
+        &redis,
+    )
+    .await?;
 
     // 2. 获取项目信息
     let project = Project::get(&body.project_id, &**pool, &redis)
@@ -206,25 +303,23 @@ pub async fn create_order(
 
     let project_id = project.inner.id.0;
 
-    // 3. 验证项目是否是付费项目
-    if !project.inner.is_paid {
-        return Err(ApiError::InvalidInput(
-            "该项目不是付费项目，无需购买".to_string(),
-        ));
-    }
+    // 3. 验证项目对用户可见且是付费项目
+    check_purchasable(&project.inner, &user, &pool).await?;
 
-    // 4. 检查用户是否已购买（且未过期）
+    // 4. 检查用户（赠送时为受赠用户）是否已购买（且未过期）
     let has_access = UserPurchase::check_access(
-        db_user_id,
+        gift_recipient_id.unwrap_or(db_user_id),
         DbProjectId(project_id),
         &**pool,
     )
     .await?;
 
     if has_access {
-        return Err(ApiError::InvalidInput(
-            "您已经购买过该项目，无需重复购买".to_string(),
-        ));
+        return Err(ApiError::InvalidInput(if gift_recipient_id.is_some() {
+            "对方已经拥有该项目，无需赠送".to_string()
+        } else {
+            "您已经购买过该项目，无需重复购买".to_string()
+        }));
     }
 
     // 5. 获取项目定价信息
@@ -235,30 +330,9 @@ pub async fn create_order(
         })?;
 
     // 6. 获取卖家的商户配置
-    // 获取团队拥有者作为卖家
-    let team_members =
-        TeamMember::get_from_team_full(project.inner.team_id, &**pool, &redis)
-            .await?;
-
-    let owner = team_members.iter().find(|m| m.is_owner).ok_or_else(|| {
-        ApiError::InvalidInput("无法找到项目所有者".to_string())
-    })?;
-
-    let seller_user_id = owner.user_id;
-
-    let merchant = PaymentMerchant::get_by_user(seller_user_id, &**pool)
-        .await?
-        .ok_or_else(|| {
-            ApiError::InvalidInput(
-                "卖家尚未配置收款账户，暂时无法购买".to_string(),
-            )
-        })?;
-
-    if !merchant.verified {
-        return Err(ApiError::InvalidInput(
-            "卖家收款账户尚未验证，暂时无法购买".to_string(),
-        ));
-    }
+    let seller_user_id =
+        get_project_seller(&project.inner, &pool, &redis).await?;
+    let merchant = get_seller_merchant(seller_user_id, &pool).await?;
 
     // 7. 解析支付方式
     let payment_method = match body.payment_method.as_str() {
@@ -286,76 +360,110 @@ pub async fn create_order(
         );
     }
 
-    // 9. 检查是否已有待支付订单（复用已有订单，避免重复创建）
+    // 9. 计算实际价格（促销价、优惠券），价格不变时复用已有的待支付订单
+    let mut transaction = pool.begin().await?;
+
     let existing_order = PaymentOrder::get_pending_by_user_project(
         db_user_id,
         DbProjectId(project_id),
-        &**pool,
+        gift_recipient_id,
+        &mut *transaction,
     )
     .await?;
 
-    let order = if let Some(existing) = existing_order {
-        log::info!(
-            "复用已有待支付订单: order_no={}, user_id={}, project_id={}",
-            existing.order_no,
-            db_user_id.0,
-            project_id
-        );
-        existing
-    } else {
-        // 创建新订单（处理竞态条件：如果唯一约束冲突，则获取已存在的订单）
-        let mut transaction = pool.begin().await?;
-        let create_result = PaymentOrder::create(
-            db_user_id,
-            DbProjectId(project_id),
-            seller_user_id,
-            pricing.price,
-            pricing.validity_days,
-            &mut transaction,
-        )
-        .await;
-
-        match create_result {
-            Ok(new_order) => {
-                // 更新订单的支付信息
-                let external_order_no = format!("7Y{}", new_order.order_no);
-                PaymentOrder::update_payment_info(
-                    &new_order.order_no,
-                    &external_order_no,
-                    payment_method.clone(),
-                    &mut transaction,
-                )
-                .await?;
+    let price = quote_order_price(
+        &project.inner,
+        &pricing,
+        body.coupon_code.as_deref(),
+        db_user_id,
+        existing_order.as_ref().map(|x| x.id),
+        &mut transaction,
+    )
+    .await?;
 
-                transaction.commit().await?;
-                new_order
+    let order = match existing_order {
+        Some(existing)
+            if existing.amount == price.amount
+                && existing.coupon_id == price.coupon_id
+                && existing.sale_id == price.sale_id =>
+        {
+            transaction.rollback().await?;
+            log::info!(
+                "复用已有待支付订单: order_no={}, user_id={}, project_id={}",
+                existing.order_no,
+                db_user_id.0,
+                project_id
+            );
+            existing
+        }
+        stale => {
+            // 价格发生变化时作废旧的待支付订单
+            if let Some(existing) = stale {
+                PaymentOrder::expire_pending(existing.id, &mut transaction)
+                    .await?;
+                log::info!(
+                    "订单价格变化，作废旧订单: order_no={}, user_id={}, project_id={}",
+                    existing.order_no,
+                    db_user_id.0,
+                    project_id
+                );
             }
-            Err(e) => {
-                // 检查是否是唯一约束冲突（竞态条件导致）
-                let error_str = format!("{:?}", e);
-                if error_str.contains("idx_payment_orders_pending_unique")
-                    || error_str.contains("duplicate key")
-                {
-                    // 回滚事务并获取已存在的订单
-                    drop(transaction);
-                    log::info!(
-                        "订单创建冲突，获取已存在订单: user_id={}, project_id={}",
-                        db_user_id.0,
-                        project_id
-                    );
-                    PaymentOrder::get_pending_by_user_project(
-                        db_user_id,
-                        DbProjectId(project_id),
-                        &**pool,
+
+            // 创建新订单（处理竞态条件：如果唯一约束冲突，则获取已存在的订单）
+            let create_result = PaymentOrder::create(
+                db_user_id,
+                DbProjectId(project_id),
+                seller_user_id,
+                &price,
+                pricing.validity_days,
+                gift_recipient_id,
+                &mut transaction,
+            )
+            .await;
+
+            match create_result {
+                Ok(new_order) => {
+                    // 更新订单的支付信息
+                    let external_order_no = format!("7Y{}", new_order.order_no);
+                    PaymentOrder::update_payment_info(
+                        &new_order.order_no,
+                        &external_order_no,
+                        payment_method.clone(),
+                        &mut transaction,
                     )
-                    .await?
-                    .ok_or_else(|| {
-                        ApiError::InvalidInput(
-                            "订单创建失败，请重试".to_string(),
+                    .await?;
+
+                    transaction.commit().await?;
+                    new_order
+                }
+                Err(e) => {
+                    // 检查是否是唯一约束冲突（竞态条件导致）
+                    let error_str = format!("{:?}", e);
+                    if error_str.contains("idx_payment_orders_pending_unique")
+                        || error_str.contains("duplicate key")
+                    {
+                        // 回滚事务并获取已存在的订单
+                        drop(transaction);
+                        log::info!(
+                            "订单创建冲突，获取已存在订单: user_id={}, project_id={}",
+                            db_user_id.0,
+                            project_id
+                        );
+                        PaymentOrder::get_pending_by_user_project(
+                            db_user_id,
+                            DbProjectId(project_id),
+                            gift_recipient_id,
+                            &**pool,
                         )
-                    })?
-                } else {
-                    return Err(e.into());
+                        .await?
+                        .ok_or_else(|| {
+                            ApiError::InvalidInput(
+                                "订单创建失败，请重试".to_string(),
+                            )
+                        })?
+                    } else {
+                        return Err(e.into());
+                    }
                 }
             }
         }
@@ -368,7 +476,7 @@ pub async fn create_order(
         &merchant.secret_key,
         &project.inner.name,
         &user.username,
-        pricing.price,
+        order.amount,
         &payment_method,
     )
     .await?;
@@ -376,7 +484,9 @@ pub async fn create_order(
     // 10. 构建响应
     let response = CreateOrderResponse {
         order_no: order.order_no,
-        amount: pricing.price,
+        amount: order.amount,
+        original_amount: order.original_amount,
+        discount_amount: order.discount_amount,
         qr_code_url: Some(qr_code_url),
         expires_at: order.expires_at,
         payment_method: payment_method.as_str().to_string(),
@@ -386,11 +496,237 @@ pub async fn create_order(
             title: project.inner.name,
             slug: project.inner.slug.clone().unwrap_or_default(),
         },
+        gift_recipient_id: order.gift_recipient_id.map(Into::into),
     };
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// 多项目结算
+///
+/// POST /v3/order/checkout
+///
+/// 购买捆绑包或一次购买多个项目，生成一个包含多个项目明细的订单。
+/// 捆绑包会扣除用户已拥有的项目；直接购买多个项目时按各项目当前促销价计算，不支持优惠券。
+/// 所有项目必须属于同一卖家，支付成功后为每个项目分别创建购买记录。
+/// 填写 gift_to 时为赠送订单，已拥有判断与捆绑包抵扣均以受赠用户计算。
+pub async fn checkout(
+    req: HttpRequest,
+    body: web::Json<CheckoutRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let db_user_id = DBUserId(user.id.0 as i64);
+    let gift_recipient_id = resolve_gift_recipient(
+        body.gift_to.as_deref(),
+        db_user_id,
+        &pool,
+        &redis,
+    )
+    .await?;
+    let grantee = gift_recipient_id.unwrap_or(db_user_id);
+
+    let payment_method = match body.payment_method.as_str() {
+        "alipay" => PaymentMethod::Alipay,
+        "wechat" => PaymentMethod::Wechat,
+        _ => {
+            return Err(ApiError::InvalidInput("不支持的支付方式".to_string()));
+        }
+    };
+
+    let mut transaction = pool.begin().await?;
+
+    let (bundle, seller_id, items) = match (&body.bundle_id, &body.project_ids)
+    {
+        (Some(bundle_id), project_ids) if project_ids.is_empty() => {
+            let bundle = ProjectBundle::get_id((*bundle_id).into(), &**pool)
+                .await?
+                .filter(|x| x.active)
+                .ok_or_else(|| {
+                    ApiError::InvalidInput("捆绑包不存在或已下架".to_string())
+                })?;
+            let quote = quote_bundle(
+                &bundle,
+                &Some(user.clone()),
+                Some(grantee),
+                &pool,
+                &redis,
+            )
+            .await?;
+            (Some(bundle), quote.seller_id, quote.items)
+        }
+        (None, project_ids) if !project_ids.is_empty() => {
+            let mut projects =
+                Project::get_many(project_ids, &**pool, &redis).await?;
+            projects.sort_by_key(|x| x.inner.id.0);
+            projects.dedup_by_key(|x| x.inner.id);
+
+            let mut seller_id = None;
+            let mut items = Vec::with_capacity(projects.len());
+            for project in &projects {
+                check_purchasable(&project.inner, &user, &pool).await?;
+
+                if UserPurchase::check_access(
+                    grantee,
+                    project.inner.id,
+                    &**pool,
+                )
+                .await?
+                {
+                    return Err(ApiError::InvalidInput(format!(
+                        "{}已经拥有项目 {}",
+                        if gift_recipient_id.is_some() {
+                            "对方"
+                        } else {
+                            "您"
+                        },
+                        project.inner.name
+                    )));
+                }
+
+                let pricing = ProjectPricing::get(project.inner.id, &**pool)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::InvalidInput(format!(
+                            "项目 {} 尚未设置定价",
+                            project.inner.name
+                        ))
+                    })?;
+
+                let seller =
+                    get_project_seller(&project.inner, &pool, &redis).await?;
+                if seller_id.is_some_and(|x| x != seller) {
+                    return Err(ApiError::InvalidInput(
+                        "不同卖家的项目需要分别下单".to_string(),
+                    ));
+                }
+                seller_id = Some(seller);
+
+                let price = quote_order_price(
+                    &project.inner,
+                    &pricing,
+                    None,
+                    db_user_id,
+                    None,
+                    &mut transaction,
+                )
+                .await?;
+                items.push(OrderItemPrice {
+                    project_id: project.inner.id,
+                    original_amount: price.original_amount,
+                    amount: price.amount,
+                    validity_days: pricing.validity_days,
+                    sale_id: price.sale_id,
+                });
+            }
+
+            let seller_id = seller_id.ok_or_else(|| {
+                ApiError::InvalidInput("项目不存在".to_string())
+            })?;
+            (None, seller_id, items)
+        }
+        _ => {
+            return Err(ApiError::InvalidInput(
+                "请选择捆绑包或要购买的项目".to_string(),
+            ));
+        }
+    };
+
+    let merchant = get_seller_merchant(seller_id, &pool).await?;
+
+    let order = PaymentOrder::create_with_items(
+        db_user_id,
+        seller_id,
+        bundle.as_ref().map(|x| x.id),
+        None,
+        gift_recipient_id,
+        &items,
+        &mut transaction,
+    )
+    .await?;
+
+    let external_order_no = format!("7Y{}", order.order_no);
+    PaymentOrder::update_payment_info(
+        &order.order_no,
+        &external_order_no,
+        payment_method.clone(),
+        &mut transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    let project_ids: Vec<DbProjectId> =
+        items.iter().map(|x| x.project_id).collect();
+    let projects = Project::get_many_ids(&project_ids, &**pool, &redis).await?;
+    let items: Vec<OrderItemInfo> = items
+        .iter()
+        .filter_map(|item| {
+            let project =
+                projects.iter().find(|p| p.inner.id == item.project_id)?;
+            Some(OrderItemInfo {
+                project: OrderProjectInfo {
+                    id: ProjectId::from(item.project_id).to_string(),
+                    title: project.inner.name.clone(),
+                    slug: project.inner.slug.clone().unwrap_or_default(),
+                },
+                original_amount: item.original_amount,
+                amount: item.amount,
+            })
+        })
+        .collect();
+
+    let title = match &bundle {
+        Some(bundle) => bundle.name.clone(),
+        None if items.len() > 1 => {
+            format!("{} 等 {} 个项目", items[0].project.title, items.len())
+        }
+        None => items
+            .first()
+            .map(|x| x.project.title.clone())
+            .unwrap_or_default(),
+    };
+
+    let qr_code_url = create_payment_order(
+        &order.order_no,
+        merchant.sid,
+        &merchant.secret_key,
+        &title,
+        &user.username,
+        order.amount,
+        &payment_method,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CheckoutResponse {
+        order_no: order.order_no,
+        amount: order.amount,
+        original_amount: order.original_amount,
+        discount_amount: order.discount_amount,
+        qr_code_url: Some(qr_code_url),
+        expires_at: order.expires_at,
+        payment_method: payment_method.as_str().to_string(),
+        bundle_id: order.bundle_id.map(Into::into),
+        items,
+        gift_recipient_id: order.gift_recipient_id.map(Into::into),
+    }))
+}
+
 /// 获取用户订单列表
 ///
 /// GET /v3/order
@@ -435,6 +771,8 @@ pub async fn list_user_orders(
         .map(|order| OrderSummary {
             order_no: order.order_no,
             amount: order.amount,
+            original_amount: order.original_amount,
+            discount_amount: order.discount_amount,
             status: order.status.as_str().to_string(),
             payment_method: order
                 .payment_method
@@ -443,6 +781,8 @@ pub async fn list_user_orders(
             paid_at: order.paid_at,
             project_id: ProjectId::from(order.project_id).to_string(),
             project_title: project_map.get(&order.project_id.0).cloned(),
+            item_count: order.item_count,
+            gift_recipient_id: order.gift_recipient_id.map(Into::into),
         })
         .collect();
 
@@ -486,25 +826,45 @@ pub async fn get_order(
         return Err(ApiError::InvalidInput("无权查看此订单".to_string()));
     }
 
-    // 获取项目信息
-    let project = Project::get_id(order.project_id, &**pool, &redis)
-        .await?
-        .ok_or_else(|| ApiError::InvalidInput("项目不存在".to_string()))?;
+    // 获取订单明细与项目信息
+    let order_items = PaymentOrder::get_items(order.id, &**pool).await?;
+    let project_ids: Vec<DbProjectId> =
+        order_items.iter().map(|x| x.project_id).collect();
+    let projects = Project::get_many_ids(&project_ids, &**pool, &redis).await?;
+
+    let project_info = |project_id: DbProjectId| {
+        let project = projects.iter().find(|p| p.inner.id == project_id);
+        OrderProjectInfo {
+            id: ProjectId::from(project_id).to_string(),
+            title: project.map(|p| p.inner.name.clone()).unwrap_or_default(),
+            slug: project
+                .and_then(|p| p.inner.slug.clone())
+                .unwrap_or_default(),
+        }
+    };
 
     let response = OrderDetailResponse {
         order_no: order.order_no,
         amount: order.amount,
+        original_amount: order.original_amount,
+        discount_amount: order.discount_amount,
         status: order.status.as_str().to_string(),
         payment_method: order.payment_method.map(|m| m.as_str().to_string()),
         qr_code_url: order.qr_code_url,
         created_at: order.created_at,
         paid_at: order.paid_at,
         expires_at: order.expires_at,
-        project: OrderProjectInfo {
-            id: ProjectId::from(order.project_id).to_string(),
-            title: project.inner.name,
-            slug: project.inner.slug.unwrap_or_default(),
-        },
+        project: project_info(order.project_id),
+        bundle_id: order.bundle_id.map(Into::into),
+        items: order_items
+            .iter()
+            .map(|x| OrderItemInfo {
+                project: project_info(x.project_id),
+                original_amount: x.original_amount,
+                amount: x.amount,
+            })
+            .collect(),
+        gift_recipient_id: order.gift_recipient_id.map(Into::into),
     };
 
     Ok(HttpResponse::Ok().json(response))
@@ -607,6 +967,89 @@ pub async fn query_order_status(
     Ok(HttpResponse::Ok().json(response))
 }
 
+// ==================== 卖家与商户 ====================
+
+/// 解析赠送订单的受赠用户，不赠送时返回 None
+async fn resolve_gift_recipient(
+    gift_to: Option<&str>,
+    buyer_id: DBUserId,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<Option<DBUserId>, ApiError> {
+    let Some(username) = gift_to.map(str::trim).filter(|x| !x.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let recipient = DBUser::get(username, pool, redis)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("受赠用户不存在".to_string()))?;
+
+    if recipient.id == buyer_id {
+        return Err(ApiError::InvalidInput("不能赠送给自己".to_string()));
+    }
+
+    Ok(Some(recipient.id))
+}
+
+/// 检查用户能否购买项目：对用户不可见的项目视为不存在，且必须是付费项目
+async fn check_purchasable(
+    project: &Project,
+    user: &User,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    if !is_visible_project(project, &Some(user.clone()), pool, false).await? {
+        return Err(ApiError::NotFound);
+    }
+
+    if !project.is_paid {
+        return Err(ApiError::InvalidInput(format!(
+            "项目 {} 不是付费项目，无需购买",
+            project.name
+        )));
+    }
+
+    Ok(())
+}
+
+/// 获取项目的卖家（项目团队所有者），订单款项进入卖家的商户账户
+pub async fn get_project_seller(
+    project: &Project,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<DBUserId, ApiError> {
+    let team_members =
+        TeamMember::get_from_team_full(project.team_id, pool, redis).await?;
+
+    team_members
+        .iter()
+        .find(|m| m.is_owner)
+        .map(|m| m.user_id)
+        .ok_or_else(|| ApiError::InvalidInput("无法找到项目所有者".to_string()))
+}
+
+/// 获取卖家已验证的商户配置
+pub async fn get_seller_merchant(
+    seller_id: DBUserId,
+    pool: &PgPool,
+) -> Result<PaymentMerchant, ApiError> {
+    let merchant = PaymentMerchant::get_by_user(seller_id, pool)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput(
+                "卖家尚未配置收款账户，暂时无法购买".to_string(),
+            )
+        })?;
+
+    if !merchant.verified {
+        return Err(ApiError::InvalidInput(
+            "卖家收款账户尚未验证，暂时无法购买".to_string(),
+        ));
+    }
+
+    Ok(merchant)
+}
+
 // ==================== 支付接口调用 ====================
 
 /// 调用支付接口创建支付订单
@@ -802,6 +1245,163 @@ async fn query_payment_order_status(
     Ok(body.data.and_then(|d| d.trade_state))
 }
 
+/// 调用支付接口为订单发起原路退款
+///
+/// 退款从卖家的商户账户原路退回买家，返回支付平台的退款单号
+pub async fn request_payment_refund(
+    order_no: &str,
+    sid: i32,
+    secret_key: &str,
+    amount: Decimal,
+) -> Result<Option<String>, ApiError> {
+    let api_url = dotenvy::var("SEVENPAY_API_URL").unwrap_or_default();
+    let refund_order_path =
+        dotenvy::var("SEVENPAY_REFUND_ORDER_PATH").unwrap_or_default();
+
+    if api_url.is_empty() || refund_order_path.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "支付平台退款接口未配置，请联系管理员".to_string(),
+        ));
+    }
+
+    // 将金额转换为分（整数）
+    let amount_fen: i64 = (amount * Decimal::from(100))
+        .round()
+        .try_into()
+        .map_err(|_| ApiError::InvalidInput("金额转换失败".to_string()))?;
+
+    // 签名原文：orderNo值 + sid值 + money值 + key
+    let sign_raw = format!("{}{}{}{}", order_no, sid, amount_fen, secret_key);
+    let sign = format!("{:x}", md5::compute(&sign_raw));
+
+    let request_url = format!(
+        "{}{}?orderNo={}&sid={}&money={}",
+        api_url, refund_order_path, order_no, sid, amount_fen
+    );
+
+    log::info!(
+        "调用支付接口退款: order_no={}, sid={}, money={}分",
+        order_no,
+        sid,
+        amount_fen
+    );
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(&request_url)
+        .header("Authorization", &sign)
+        .timeout(StdDuration::from_secs(PAYMENT_API_TIMEOUT_SECS))
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("调用支付接口退款失败: {}", e);
+            ApiError::InvalidInput("无法连接支付平台，请稍后重试".to_string())
+        })?;
+
+    if !response.status().is_success() {
+        log::error!("支付接口退款返回错误状态: {}", response.status());
+        return Err(ApiError::InvalidInput(format!(
+            "支付平台返回错误: {}",
+            response.status()
+        )));
+    }
+
+    let body: serde_json::Value = response.json().await.map_err(|e| {
+        log::error!("解析支付接口退款响应失败: {}", e);
+        ApiError::InvalidInput("支付平台响应格式错误".to_string())
+    })?;
+
+    if body.get("code").and_then(|c| c.as_i64()) != Some(200) {
+        let msg = body
+            .get("msg")
+            .and_then(|m| m.as_str())
+            .unwrap_or("未知错误");
+        log::error!("支付接口退款失败: order_no={}, msg={}", order_no, msg);
+        return Err(ApiError::InvalidInput(format!("退款失败: {}", msg)));
+    }
+
+    Ok(body
+        .get("data")
+        .and_then(|d| d.get("refundNo"))
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_string()))
+}
+
+/// 为已支付订单中的每个项目创建购买记录，返回授权的项目与待推送的通知
+///
+/// 有效期按各项目下单时的定价计算，从支付时起算。
+/// 赠送订单的购买记录授予受赠用户，并通知受赠用户。
+/// 同时在账本中记录销售收入与平台服务费
+pub async fn grant_order_purchases(
+    order: &PaymentOrder,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    redis: &RedisPool,
+) -> Result<(Vec<DbProjectId>, PushEvents), DatabaseError> {
+    let items = PaymentOrder::get_items(order.id, &mut **transaction).await?;
+
+    LedgerEntry::record_sale(
+        &order.order_no,
+        order.seller_id,
+        &items,
+        transaction,
+    )
+    .await?;
+
+    let mut project_ids = Vec::with_capacity(items.len());
+    for item in items {
+        let expires_at = item
+            .validity_days
+            .map(|days| Utc::now() + Duration::days(days as i64));
+
+        UserPurchase::create(
+            order.grantee(),
+            item.project_id,
+            Some(order.order_no.clone()),
+            item.amount,
+            expires_at,
+            transaction,
+        )
+        .await?;
+
+        project_ids.push(item.project_id);
+    }
+
+    let push_events = if let Some(recipient_id) = order.gift_recipient_id {
+        let buyer = DBUser::get_id(order.user_id, &mut **transaction, redis)
+            .await?
+            .map(|x| x.username)
+            .unwrap_or_default();
+        let project_b62 = ProjectId::from(order.project_id);
+
+        NotificationBuilder {
+            body: NotificationBody::LegacyMarkdown {
+                notification_type: Some("purchase_gift".to_string()),
+                name: "[赠送] 您收到了一份礼物".to_string(),
+                text: if project_ids.len() > 1 {
+                    format!(
+                        "{} 赠送给您 {} 个付费项目，现在可以下载使用了。",
+                        buyer,
+                        project_ids.len()
+                    )
+                } else {
+                    format!(
+                        "{} 赠送给您一个付费项目，现在可以下载使用了。",
+                        buyer
+                    )
+                },
+                link: format!("/project/{project_b62}"),
+                actions: vec![],
+            },
+        }
+        .insert(recipient_id, transaction, redis)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok((project_ids, push_events))
+}
+
 /// 处理支付成功，更新订单状态并创建购买记录
 ///
 /// 主动查询支付成功时调用，会同时通知支付平台订单已发货
@@ -812,9 +1412,6 @@ async fn handle_payment_success(
     sid: i32,
     secret_key: &str,
 ) -> Result<(), ApiError> {
-    use crate::database::models::user_purchase_item::UserPurchase;
-    use chrono::Duration;
-
     // 开始事务
     let mut transaction = pool.begin().await.map_err(|e| {
         log::error!("开始事务失败: {}", e);
@@ -839,49 +1436,40 @@ async fn handle_payment_success(
         }
     };
 
-    // 计算购买有效期
-    let expires_at = paid_order
-        .validity_days
-        .map(|days| Utc::now() + Duration::days(days as i64));
-
-    // 创建用户购买记录
-    let purchase = UserPurchase::create(
-        paid_order.user_id,
-        paid_order.project_id,
-        Some(order.order_no.clone()),
-        paid_order.amount,
-        expires_at,
-        &mut transaction,
-    )
-    .await
-    .map_err(|e| {
-        log::error!("创建购买记录失败: {}", e);
-        ApiError::InvalidInput("创建购买记录失败".to_string())
-    })?;
+    // 为订单中的每个项目创建用户购买记录
+    let (project_ids, push_events) =
+        grant_order_purchases(&paid_order, &mut transaction, redis)
+            .await
+            .map_err(|e| {
+                log::error!("创建购买记录失败: {}", e);
+                ApiError::InvalidInput("创建购买记录失败".to_string())
+            })?;
 
     // 提交事务
     transaction.commit().await.map_err(|e| {
         log::error!("提交事务失败: {}", e);
         ApiError::InvalidInput("处理支付失败".to_string())
     })?;
+    crate::queue::push::publish(push_events, redis).await;
 
     // 更新 Redis 缓存
-    if let Err(e) = UserPurchase::add_to_user_purchase_cache(
-        paid_order.user_id,
-        paid_order.project_id,
-        redis,
-    )
-    .await
-    {
-        log::warn!("更新购买缓存失败: {:?}", e);
+    for project_id in &project_ids {
+        if let Err(e) = UserPurchase::add_to_user_purchase_cache(
+            paid_order.grantee(),
+            *project_id,
+            redis,
+        )
+        .await
+        {
+            log::warn!("更新购买缓存失败: {:?}", e);
+        }
     }
 
     log::info!(
-        "主动查询支付成功处理完成: order_no={}, user_id={}, project_id={}, purchase_id={}",
+        "主动查询支付成功处理完成: order_no={}, user_id={}, project_ids={:?}",
         order.order_no,
-        paid_order.user_id.0,
-        paid_order.project_id.0,
-        purchase.id.0
+        paid_order.grantee().0,
+        project_ids.iter().map(|x| x.0).collect::<Vec<_>>()
     );
 
     // 通知支付平台订单已发货（主动查询时需要手动触发）
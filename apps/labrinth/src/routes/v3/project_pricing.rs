@@ -6,22 +6,28 @@
 //! 权限要求：
 //! - GET: 公开访问（定价信息是公开的，用户需要知道价格才能购买）
 //! - POST/PATCH: 需要 PROJECT_WRITE scope、项目成员权限 EDIT_DETAILS、且是高级创作者
+//! - 限时促销、退款政策: 需要 PROJECT_WRITE scope、项目成员权限 EDIT_DETAILS
 
 use super::ApiError;
 use crate::auth::get_user_from_headers;
 use crate::database::models::UserId as DBUserId;
 use crate::database::models::user_purchase_item::UserPurchase;
-use crate::database::models::{self, ProjectPricing};
+use crate::database::models::{
+    self, ProjectPricing, ProjectSale, generate_project_sale_id,
+};
 use crate::database::redis::RedisPool;
-use crate::models::ids::UserId;
+use crate::models::ids::{ProjectSaleId, UserId};
 use crate::models::pats::Scopes;
+use crate::models::promotions;
 use crate::models::teams::ProjectPermissions;
 use crate::queue::session::AuthQueue;
+use crate::util::validate::validation_errors_to_string;
 use actix_web::{HttpRequest, HttpResponse, web};
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use validator::Validate;
 
 /// 定价请求数据
 #[derive(Deserialize)]
@@ -39,6 +45,37 @@ pub struct PricingResponse {
     pub price: i32,
     pub validity_days: Option<i32>,
     pub is_permanent: bool,
+    /// 当前实际售价（元），处于促销时为促销价
+    pub current_price: Decimal,
+    /// 进行中与计划中的限时促销
+    pub sales: Vec<promotions::ProjectSale>,
+    /// 支付后可申请退款的天数，0 表示不接受退款申请
+    pub refund_window_days: i32,
+    /// 退款说明
+    pub refund_policy: Option<String>,
+}
+
+/// 退款政策请求
+#[derive(Deserialize, Validate)]
+pub struct RefundPolicyRequest {
+    /// 支付后可申请退款的天数（0-90），0 表示不接受退款申请
+    #[validate(range(
+        min = 0,
+        max = 90,
+        message = "退款期限必须在 0-90 天之间"
+    ))]
+    pub refund_window_days: i32,
+    #[validate(length(max = 2000, message = "退款说明不能超过 2000 个字符"))]
+    pub refund_policy: Option<String>,
+}
+
+/// 创建限时促销请求
+#[derive(Deserialize)]
+pub struct SaleRequest {
+    /// 促销价（元，最多两位小数）
+    pub sale_price: Decimal,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
 }
 
 /// 购买用户信息
@@ -93,11 +130,20 @@ pub async fn get_pricing(
     let price_i32 = decimal_to_i32(pricing.price)
         .map_err(|_| ApiError::InvalidInput("价格数据异常".to_string()))?;
 
+    let sales = ProjectSale::get_project(project.inner.id, &**pool).await?;
+    let current_price = ProjectSale::active(&sales, Utc::now())
+        .map(|x| x.sale_price.min(pricing.price))
+        .unwrap_or(pricing.price);
+
     Ok(HttpResponse::Ok().json(PricingResponse {
         project_id: project_id_str,
         price: price_i32,
         validity_days: pricing.validity_days,
         is_permanent: pricing.validity_days.is_none(),
+        current_price,
+        sales: sales.into_iter().map(Into::into).collect(),
+        refund_window_days: pricing.refund_window_days,
+        refund_policy: pricing.refund_policy,
     }))
 }
 
@@ -196,7 +242,7 @@ pub async fn set_pricing(
     let price_decimal = Decimal::from(body.price);
 
     // 设置定价
-    ProjectPricing::upsert(
+    let pricing = ProjectPricing::upsert(
         project.inner.id,
         price_decimal,
         body.validity_days,
@@ -206,11 +252,20 @@ pub async fn set_pricing(
 
     transaction.commit().await?;
 
+    let sales = ProjectSale::get_project(project.inner.id, &**pool).await?;
+    let current_price = ProjectSale::active(&sales, Utc::now())
+        .map(|x| x.sale_price.min(price_decimal))
+        .unwrap_or(price_decimal);
+
     Ok(HttpResponse::Ok().json(PricingResponse {
         project_id: project_id_str,
         price: body.price,
         validity_days: body.validity_days,
         is_permanent: body.validity_days.is_none(),
+        current_price,
+        sales: sales.into_iter().map(Into::into).collect(),
+        refund_window_days: pricing.refund_window_days,
+        refund_policy: pricing.refund_policy,
     }))
 }
 
@@ -442,6 +497,228 @@ pub async fn revoke_purchase(
     })))
 }
 
+/// 创建限时促销
+///
+/// POST /v3/project/{id}/pricing/sales
+///
+/// 权限要求：项目成员（EDIT_DETAILS 权限）
+pub async fn create_sale(
+    req: HttpRequest,
+    info: web::Path<String>,
+    body: web::Json<SaleRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let current_user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let project = models::Project::get(&info.into_inner(), &**pool, &redis)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("项目不存在".to_string()))?;
+
+    check_pricing_permission(&current_user, &project.inner, &pool).await?;
+
+    let pricing = ProjectPricing::get(project.inner.id, &**pool)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput("请先设置项目定价".to_string())
+        })?;
+
+    let sale_price = body.sale_price.round_dp(2);
+    if sale_price < promotions::min_order_amount()
+        || sale_price >= pricing.price
+    {
+        return Err(ApiError::InvalidInput(
+            "促销价必须大于 0 且低于原价".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    if body.ends_at <= body.starts_at || body.ends_at <= now {
+        return Err(ApiError::InvalidInput(
+            "促销结束时间必须晚于开始时间和当前时间".to_string(),
+        ));
+    }
+    if body.ends_at - body.starts_at > chrono::Duration::days(MAX_SALE_DAYS) {
+        return Err(ApiError::InvalidInput(format!(
+            "单次促销不能超过 {MAX_SALE_DAYS} 天"
+        )));
+    }
+
+    let sales = ProjectSale::get_project(project.inner.id, &**pool).await?;
+    if sales.len() >= MAX_SCHEDULED_SALES {
+        return Err(ApiError::InvalidInput(format!(
+            "最多只能安排 {MAX_SCHEDULED_SALES} 个促销"
+        )));
+    }
+    if sales
+        .iter()
+        .any(|x| x.starts_at < body.ends_at && body.starts_at < x.ends_at)
+    {
+        return Err(ApiError::InvalidInput(
+            "促销时间与已有促销重叠".to_string(),
+        ));
+    }
+
+    let mut transaction = pool.begin().await?;
+    let sale = ProjectSale {
+        id: generate_project_sale_id(&mut transaction).await?,
+        project_id: project.inner.id,
+        sale_price,
+        starts_at: body.starts_at,
+        ends_at: body.ends_at,
+        created_at: now,
+    };
+    sale.insert(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(promotions::ProjectSale::from(sale)))
+}
+
+/// 删除限时促销（可用于提前结束进行中的促销）
+///
+/// DELETE /v3/project/{id}/pricing/sales/{sale_id}
+///
+/// 权限要求：项目成员（EDIT_DETAILS 权限）
+pub async fn delete_sale(
+    req: HttpRequest,
+    info: web::Path<(String, ProjectSaleId)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let current_user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let (project_id_str, sale_id) = info.into_inner();
+
+    let project = models::Project::get(&project_id_str, &**pool, &redis)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("项目不存在".to_string()))?;
+
+    check_pricing_permission(&current_user, &project.inner, &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+    let deleted =
+        ProjectSale::remove(sale_id.into(), project.inner.id, &mut transaction)
+            .await?;
+    transaction.commit().await?;
+
+    if !deleted {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 设置退款政策
+///
+/// PATCH /v3/project/{id}/pricing/refund_policy
+///
+/// 权限要求：项目成员（EDIT_DETAILS 权限）
+pub async fn update_refund_policy(
+    req: HttpRequest,
+    info: web::Path<String>,
+    body: web::Json<RefundPolicyRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let current_user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let project = models::Project::get(&info.into_inner(), &**pool, &redis)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("项目不存在".to_string()))?;
+
+    check_pricing_permission(&current_user, &project.inner, &pool).await?;
+
+    let refund_policy = body
+        .refund_policy
+        .as_deref()
+        .map(str::trim)
+        .filter(|x| !x.is_empty());
+
+    let mut transaction = pool.begin().await?;
+    let updated = ProjectPricing::set_refund_policy(
+        project.inner.id,
+        body.refund_window_days,
+        refund_policy,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    if !updated {
+        return Err(ApiError::InvalidInput("请先设置项目定价".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 单次促销的最长天数
+const MAX_SALE_DAYS: i64 = 90;
+/// 同时安排的促销数量上限
+const MAX_SCHEDULED_SALES: usize = 10;
+
+async fn check_pricing_permission(
+    user: &crate::models::users::User,
+    project: &models::project_item::Project,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    if !project.is_paid {
+        return Err(ApiError::InvalidInput("该项目不是付费资源".to_string()));
+    }
+
+    let (team_member, organization_team_member) =
+        models::TeamMember::get_for_project_permissions(
+            project,
+            user.id.into(),
+            pool,
+        )
+        .await?;
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(ProjectPermissions::EDIT_DETAILS) {
+        return Err(ApiError::CustomAuthentication(
+            "您没有管理此项目定价的权限".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// 验证价格是否在有效范围内 (1-1000)
 pub fn validate_price(price: i32) -> Result<(), &'static str> {
     if price < 1 {
@@ -480,4 +757,8 @@ pub fn config(_cfg: &mut web::ServiceConfig) {
     // - POST   project/{id}/pricing
     // - PATCH  project/{id}/pricing
     // - GET    project/{id}/pricing/purchasers
+    // - GET    project/{id}/pricing/quote
+    // - POST   project/{id}/pricing/sales
+    // - DELETE project/{id}/pricing/sales/{sale_id}
+    // - PATCH  project/{id}/pricing/refund_policy
 }
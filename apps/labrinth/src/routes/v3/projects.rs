@@ -33,9 +33,11 @@ use crate::search::indexing::remove_documents;
 use crate::search::{SearchConfig, SearchError, search_for_project};
 use crate::util::img;
 use crate::util::img::{delete_old_images, upload_image_optimized};
+use crate::util::ip::request_ip;
+use crate::util::ratelimit::{RateLimit, SEARCH};
 use crate::util::routes::read_from_payload;
 use crate::util::validate::validation_errors_to_string;
-use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, guard, web};
 use chrono::Utc;
 use futures::TryStreamExt;
 use itertools::Itertools;
@@ -45,7 +47,12 @@ use sqlx::PgPool;
 use validator::Validate;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.route("search", web::get().to(project_search));
+    cfg.service(
+        web::resource("search")
+            .guard(guard::Get())
+            .wrap(RateLimit(&SEARCH))
+            .route(web::get().to(project_search)),
+    );
     cfg.route("projects", web::get().to(projects_get));
     cfg.route("projects", web::patch().to(projects_edit));
     cfg.route("projects_random", web::get().to(random_projects_get));
@@ -114,6 +121,26 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                 "{id}/wiki_delete",
                 web::delete().to(super::wikis::wiki_delete),
             )
+            .route(
+                "{id}/wiki_rollback",
+                web::post().to(super::wikis::wiki_rollback_all),
+            )
+            .route(
+                "{id}/wiki/{wiki_id}/revisions",
+                web::get().to(super::wikis::wiki_revisions),
+            )
+            .route(
+                "{id}/wiki/{wiki_id}/revision/{revision_id}",
+                web::get().to(super::wikis::wiki_revision_get),
+            )
+            .route(
+                "{id}/wiki/{wiki_id}/diff",
+                web::get().to(super::wikis::wiki_revision_diff),
+            )
+            .route(
+                "{id}/wiki/{wiki_id}/rollback",
+                web::post().to(super::wikis::wiki_rollback),
+            )
             .route("{id}/forum", web::post().to(project_forum_create))
             .service(
                 web::scope("{id}")
@@ -155,6 +182,56 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                         "pricing/purchasers/{user_id}",
                         web::delete()
                             .to(super::project_pricing::revoke_purchase),
+                    )
+                    .route(
+                        "pricing/quote",
+                        web::get().to(super::coupons::pricing_quote),
+                    )
+                    .route(
+                        "pricing/sales",
+                        web::post().to(super::project_pricing::create_sale),
+                    )
+                    .route(
+                        "pricing/sales/{sale_id}",
+                        web::delete().to(super::project_pricing::delete_sale),
+                    )
+                    .route(
+                        "pricing/refund_policy",
+                        web::patch()
+                            .to(super::project_pricing::update_refund_policy),
+                    )
+                    .route(
+                        "refunds",
+                        web::get().to(super::refunds::project_refunds_get),
+                    )
+                    .route(
+                        "license",
+                        web::get().to(super::licenses::project_license_get),
+                    )
+                    .route(
+                        "bundles",
+                        web::get().to(super::bundles::project_bundles_get),
+                    )
+                    // 优惠券路由
+                    .route(
+                        "coupons",
+                        web::get().to(super::coupons::project_coupons_get),
+                    )
+                    .route(
+                        "coupons",
+                        web::post().to(super::coupons::project_coupon_create),
+                    )
+                    // 兑换码路由
+                    .route(
+                        "purchase_codes",
+                        web::get()
+                            .to(super::purchase_codes::project_purchase_codes_get),
+                    )
+                    .route(
+                        "purchase_codes",
+                        web::post().to(
+                            super::purchase_codes::project_purchase_codes_create,
+                        ),
                     ),
             ),
     );
@@ -409,6 +486,7 @@ pub async fn project_edit(
 
         if let Some(perms) = permissions {
             let mut transaction = pool.begin().await?;
+            let mut push_events = Vec::new();
 
             // BBSMC 上游修复 97e4d8e13: 记录需要从搜索索引中删除的版本
             let mut versions_to_remove: Option<
@@ -652,15 +730,17 @@ pub async fn project_edit(
                     .try_collect::<Vec<_>>()
                     .await?;
 
-                    NotificationBuilder {
-                        body: NotificationBody::StatusChange {
-                            project_id: project_item.inner.id.into(),
-                            old_status: project_item.inner.status,
-                            new_status: *status,
-                        },
-                    }
-                    .insert_many(notified_members, &mut transaction, &redis)
-                    .await?;
+                    push_events.extend(
+                        NotificationBuilder {
+                            body: NotificationBody::StatusChange {
+                                project_id: project_item.inner.id.into(),
+                                old_status: project_item.inner.status,
+                                new_status: *status,
+                            },
+                        }
+                        .insert_many(notified_members, &mut transaction, &redis)
+                        .await?,
+                    );
                 }
 
                 ThreadMessageBuilder {
@@ -687,6 +767,23 @@ pub async fn project_edit(
                 .execute(&mut *transaction)
                 .await?;
 
+                if user.role.is_mod() {
+                    db_models::AuditLogBuilder {
+                        actor_id: Some(user.id.into()),
+                        action: "project.status_change".to_string(),
+                        target_type: "project".to_string(),
+                        target_id: Some(id.0),
+                        before: Some(json!({
+                            "status": project_item.inner.status.as_str(),
+                        })),
+                        after: Some(json!({ "status": status.as_str() })),
+                        reason: None,
+                        ip: request_ip(&req),
+                    }
+                    .insert(&mut transaction)
+                    .await?;
+                }
+
                 // 如果这是个汉化包项目且状态发生变化，清除所有目标版本的缓存
                 // 获取该项目的所有版本
                 let version_ids = sqlx::query!(
@@ -1242,6 +1339,12 @@ pub async fn project_edit(
             )
             .await?;
 
+            db_models::SearchIndexQueueEntry::enqueue(
+                &[project_item.inner.id],
+                &mut *transaction,
+            )
+            .await?;
+
             transaction.commit().await?;
 
             // 项目状态变更时清除待处理计数缓存
@@ -1264,11 +1367,12 @@ pub async fn project_edit(
                 &redis,
             )
             .await?;
+            crate::queue::push::publish(push_events, &redis).await;
 
             // BBSMC 上游修复 97e4d8e13: 确保版本在路由执行结束前从搜索索引中删除
             // 在事务提交和缓存清理后再删除搜索索引，确保任务完成后再返回响应
             if let Some(versions) = versions_to_remove {
-                remove_documents(&versions, &search_config).await?;
+                remove_documents(&versions, &search_config, &pool).await?;
             }
 
             // 仅在项目可搜索状态下通知 Bing IndexNow（内容编辑）
@@ -1343,9 +1447,10 @@ pub async fn edit_project_categories(
 
 pub async fn project_search(
     web::Query(info): web::Query<SearchRequest>,
+    pool: web::Data<PgPool>,
     config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, SearchError> {
-    let results = search_for_project(&info, &config).await?;
+    let results = search_for_project(&info, &config, &pool).await?;
 
     // TODO: 添加此内容
     // let results = ReturnSearchResults {
@@ -1705,6 +1810,12 @@ pub async fn projects_edit(
             }
         }
 
+        db_models::SearchIndexQueueEntry::enqueue(
+            &[project.inner.id],
+            &mut *transaction,
+        )
+        .await?;
+
         db_models::Project::clear_cache(
             project.inner.id,
             project.inner.slug,
@@ -1897,6 +2008,12 @@ pub async fn project_icon_edit(
     .execute(&mut *transaction)
     .await?;
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project_item.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
     db_models::Project::clear_cache(
         project_item.inner.id,
@@ -1983,6 +2100,12 @@ pub async fn delete_project_icon(
     .execute(&mut *transaction)
     .await?;
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project_item.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
     db_models::Project::clear_cache(
         project_item.inner.id,
@@ -2197,6 +2320,12 @@ pub async fn add_gallery_item(
     )
     .await?;
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project_item.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
     db_models::Project::clear_cache(
         project_item.inner.id,
@@ -2395,6 +2524,12 @@ pub async fn edit_gallery_item(
         .await?;
     }
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project_item.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
 
     db_models::Project::clear_cache(
@@ -2505,6 +2640,12 @@ pub async fn delete_gallery_item(
     .execute(&mut *transaction)
     .await?;
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project_item.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
 
     db_models::Project::clear_cache(
@@ -2601,6 +2742,12 @@ pub async fn project_delete(
         db_models::Project::remove(project.inner.id, &mut transaction, &redis)
             .await?;
 
+    db_models::SearchIndexQueueEntry::enqueue(
+        &[project.inner.id],
+        &mut *transaction,
+    )
+    .await?;
+
     transaction.commit().await?;
 
     // 如果被删除的项目之前是可搜索的，通知 Bing IndexNow 以加速下架
@@ -2618,6 +2765,7 @@ pub async fn project_delete(
             .map(|x| x.into())
             .collect::<Vec<_>>(),
         &search_config,
+        &pool,
     )
     .await?;
 
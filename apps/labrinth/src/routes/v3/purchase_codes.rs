@@ -0,0 +1,435 @@
+//! 兑换码 API
+//!
+//! 付费项目的作者可以批量生成一次性兑换码，发放给测试者或抽奖获奖者。
+//! 用户兑换后获得与正常购买相同有效期的购买记录，不产生订单与收入。
+//! 兑换接口需要人机验证，并按 IP 单独限流以防止暴力猜测兑换码。
+//!
+//! 权限要求：
+//! - 生成、查看与作废兑换码：项目成员权限 EDIT_DETAILS
+
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ids::UserId as DBUserId;
+use crate::database::models::project_item::Project as DBProject;
+use crate::database::models::{
+    ProjectPricing, PurchaseCode, PurchaseCodeBatch as DBPurchaseCodeBatch,
+    UserPurchase, generate_purchase_code_batch_id,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::{ProjectId, PurchaseCodeBatchId};
+use crate::models::pats::Scopes;
+use crate::models::purchase_codes::PurchaseCodeBatch;
+use crate::models::teams::ProjectPermissions;
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::util::captcha::check_hcaptcha;
+use crate::util::ratelimit::{REDEEM, RateLimit};
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// 单个项目同时可兑换（未兑换、未作废且未过期）的兑换码上限
+const MAX_OUTSTANDING_CODES: i64 = 2000;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("purchase_code")
+            .service(
+                web::resource("redeem")
+                    .wrap(RateLimit(&REDEEM))
+                    .route(web::post().to(purchase_code_redeem)),
+            )
+            .route("batch/{id}", web::get().to(purchase_code_batch_get))
+            .route("batch/{id}", web::delete().to(purchase_code_batch_revoke)),
+    );
+}
+
+/// 生成兑换码批次请求
+#[derive(Deserialize, Validate)]
+pub struct PurchaseCodeBatchCreate {
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "批次名称长度必须在 1-64 之间"
+    ))]
+    pub name: String,
+    #[validate(range(
+        min = 1,
+        max = 500,
+        message = "每批可生成 1-500 个兑换码"
+    ))]
+    pub count: i32,
+    /// 兑换截止时间，不填表示长期有效
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 兑换请求
+#[derive(Deserialize, Validate)]
+pub struct PurchaseCodeRedeem {
+    #[validate(length(min = 1, max = 64, message = "兑换码无效"))]
+    pub code: String,
+    /// 人机验证凭证
+    pub challenge: String,
+}
+
+/// 兑换成功响应
+#[derive(Serialize)]
+pub struct PurchaseCodeRedeemResponse {
+    pub project_id: ProjectId,
+    /// 购买记录的过期时间，None 表示永久有效
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 获取项目的兑换码批次及使用情况
+///
+/// GET /v3/project/{id}/purchase_codes
+pub async fn project_purchase_codes_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_project_permission(&user, &project.inner, &pool).await?;
+
+    let batches =
+        DBPurchaseCodeBatch::get_project(project.inner.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(
+        batches
+            .into_iter()
+            .map(|x| PurchaseCodeBatch::from(x, None))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 生成兑换码批次，响应中包含全部兑换码
+///
+/// POST /v3/project/{id}/purchase_codes
+pub async fn project_purchase_codes_create(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    body: web::Json<PurchaseCodeBatchCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_project_permission(&user, &project.inner, &pool).await?;
+
+    if !project.inner.is_paid {
+        return Err(ApiError::InvalidInput(
+            "只有付费资源才能生成兑换码".to_string(),
+        ));
+    }
+    if ProjectPricing::get(project.inner.id, &**pool)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::InvalidInput("该项目尚未设置定价".to_string()));
+    }
+    if body.expires_at.is_some_and(|x| x <= Utc::now()) {
+        return Err(ApiError::InvalidInput(
+            "兑换截止时间必须晚于当前时间".to_string(),
+        ));
+    }
+
+    let outstanding =
+        DBPurchaseCodeBatch::get_outstanding_count(project.inner.id, &**pool)
+            .await?;
+    if outstanding + body.count as i64 > MAX_OUTSTANDING_CODES {
+        return Err(ApiError::InvalidInput(format!(
+            "项目可兑换的兑换码不能超过 {MAX_OUTSTANDING_CODES} 个，当前还有 {outstanding} 个未兑换"
+        )));
+    }
+
+    let mut codes = Vec::with_capacity(body.count as usize);
+    while codes.len() < body.count as usize {
+        let code = PurchaseCode::generate();
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+
+    let mut transaction = pool.begin().await?;
+    let batch = DBPurchaseCodeBatch {
+        id: generate_purchase_code_batch_id(&mut transaction).await?,
+        project_id: project.inner.id,
+        name: body.name.clone(),
+        code_count: body.count,
+        redeemed_count: 0,
+        expires_at: body.expires_at,
+        revoked: false,
+        created_by: user.id.into(),
+        created_at: Utc::now(),
+    };
+    batch.insert(&codes, &mut transaction).await?;
+    transaction.commit().await?;
+
+    let codes = codes
+        .into_iter()
+        .map(|code| PurchaseCode {
+            code,
+            batch_id: batch.id,
+            redeemed_by: None,
+            redeemed_at: None,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(PurchaseCodeBatch::from(batch, Some(codes))))
+}
+
+/// 获取兑换码批次详情，包含每个兑换码的兑换情况
+///
+/// GET /v3/purchase_code/batch/{id}
+pub async fn purchase_code_batch_get(
+    req: HttpRequest,
+    info: web::Path<(PurchaseCodeBatchId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let batch =
+        get_managed_batch(&user, info.into_inner().0, &pool, &redis).await?;
+    let codes = DBPurchaseCodeBatch::get_codes(batch.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(PurchaseCodeBatch::from(batch, Some(codes))))
+}
+
+/// 作废兑换码批次；已兑换的购买记录保留
+///
+/// DELETE /v3/purchase_code/batch/{id}
+pub async fn purchase_code_batch_revoke(
+    req: HttpRequest,
+    info: web::Path<(PurchaseCodeBatchId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    let batch =
+        get_managed_batch(&user, info.into_inner().0, &pool, &redis).await?;
+
+    let mut transaction = pool.begin().await?;
+    DBPurchaseCodeBatch::revoke(batch.id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 兑换兑换码，按项目当前定价的有效期创建购买记录
+///
+/// POST /v3/purchase_code/redeem
+pub async fn purchase_code_redeem(
+    req: HttpRequest,
+    body: web::Json<PurchaseCodeRedeem>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    if !check_hcaptcha(&body.challenge).await? {
+        return Err(ApiError::Turnstile);
+    }
+
+    let code = PurchaseCode::normalize(&body.code)
+        .ok_or_else(|| ApiError::InvalidInput("兑换码无效".to_string()))?;
+    let user_id: DBUserId = user.id.into();
+
+    let mut transaction = pool.begin().await?;
+
+    let redeemable = PurchaseCode::get_for_redeem(&code, &mut transaction)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("兑换码无效".to_string()))?;
+
+    if redeemable.redeemed {
+        return Err(ApiError::InvalidInput("兑换码已被使用".to_string()));
+    }
+    if redeemable.revoked {
+        return Err(ApiError::InvalidInput("兑换码已作废".to_string()));
+    }
+    if redeemable.expires_at.is_some_and(|x| x <= Utc::now()) {
+        return Err(ApiError::InvalidInput("兑换码已过期".to_string()));
+    }
+
+    let project = database::models::Project::get_id(
+        redeemable.project_id,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .filter(|x| x.inner.is_paid)
+    .ok_or_else(|| {
+        ApiError::InvalidInput("兑换码对应的项目已不再出售".to_string())
+    })?;
+
+    let pricing = ProjectPricing::get(project.inner.id, &mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput("兑换码对应的项目已不再出售".to_string())
+        })?;
+
+    if UserPurchase::check_access(user_id, project.inner.id, &mut *transaction)
+        .await?
+    {
+        return Err(ApiError::InvalidInput(
+            "您已经拥有该项目，无需兑换".to_string(),
+        ));
+    }
+
+    if !PurchaseCode::redeem(&redeemable.code, user_id, &mut transaction)
+        .await?
+    {
+        return Err(ApiError::InvalidInput("兑换码已被使用".to_string()));
+    }
+
+    let expires_at = pricing
+        .validity_days
+        .map(|days| Utc::now() + Duration::days(days as i64));
+
+    UserPurchase::create(
+        user_id,
+        project.inner.id,
+        None,
+        Decimal::ZERO,
+        expires_at,
+        &mut transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    UserPurchase::add_to_user_purchase_cache(user_id, project.inner.id, &redis)
+        .await?;
+
+    log::info!(
+        "兑换码兑换成功: batch_id={}, user_id={}, project_id={}",
+        redeemable.batch_id.0,
+        user_id.0,
+        project.inner.id.0
+    );
+
+    Ok(HttpResponse::Ok().json(PurchaseCodeRedeemResponse {
+        project_id: project.inner.id.into(),
+        expires_at,
+    }))
+}
+
+/// 获取批次，并检查用户是否有权管理批次所属的项目
+async fn get_managed_batch(
+    user: &User,
+    id: PurchaseCodeBatchId,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<DBPurchaseCodeBatch, ApiError> {
+    let batch = DBPurchaseCodeBatch::get_id(id.into(), pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let project =
+        database::models::Project::get_id(batch.project_id, pool, redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    check_project_permission(user, &project.inner, pool).await?;
+
+    Ok(batch)
+}
+
+async fn check_project_permission(
+    user: &User,
+    project: &DBProject,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    let (team_member, organization_team_member) =
+        database::models::TeamMember::get_for_project_permissions(
+            project,
+            user.id.into(),
+            pool,
+        )
+        .await?;
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    )
+    .unwrap_or_default();
+
+    if !permissions.contains(ProjectPermissions::EDIT_DETAILS) {
+        return Err(ApiError::CustomAuthentication(
+            "您没有权限管理此项目的兑换码".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! 实时推送 WebSocket
+//!
+//! `GET /v3/push` 升级为 WebSocket 后持续推送当前用户的新通知、线程与私信
+//! 消息、提现状态变化等事件，每条事件为带有 `id` 与 `type` 字段的 JSON 文本。
+//! 令牌只能通过 `Authorization` 请求头传入，不接受查询参数，避免出现在访问日志中。
+//! 重连时传入最后收到的事件 ID `last_event_id`，服务端先补发之后的事件。
+
+use crate::auth::AuthenticationError;
+use crate::auth::validate::get_user_record_from_bearer_token;
+use crate::database::redis::RedisPool;
+use crate::models::pats::Scopes;
+use crate::queue::push::{PushFrame, PushHub, parse_event_id, replay};
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_ws::{Closed, Message, MessageStream, Session};
+use log::warn;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// 服务端发送心跳的间隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// 超过该时长未收到客户端任何消息时断开连接
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(90);
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("push", web::get().to(push_connect));
+}
+
+#[derive(Deserialize)]
+pub struct PushQuery {
+    pub last_event_id: Option<String>,
+}
+
+pub async fn push_connect(
+    req: HttpRequest,
+    web::Query(query): web::Query<PushQuery>,
+    body: web::Payload,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    hub: web::Data<PushHub>,
+) -> Result<HttpResponse, ApiError> {
+    let (scopes, user) = get_user_record_from_bearer_token(
+        &req,
+        None,
+        &**pool,
+        &redis,
+        &session_queue,
+    )
+    .await?
+    .ok_or(AuthenticationError::InvalidCredentials)?;
+
+    if !scopes.contains(Scopes::NOTIFICATION_READ) {
+        return Err(AuthenticationError::InvalidCredentials.into());
+    }
+
+    let (response, session, msg_stream) = actix_ws::handle(&req, body)
+        .map_err(|_| {
+            ApiError::InvalidInput("无效的 WebSocket 握手请求".to_string())
+        })?;
+
+    let user_id = user.id;
+    // 先登记连接再补发，避免补发期间产生的事件丢失
+    let (connection_id, receiver) = hub.subscribe(user_id);
+    let missed = match &query.last_event_id {
+        Some(last_event_id) => {
+            match replay(user_id, last_event_id, &redis).await {
+                Ok(missed) => missed,
+                Err(e) => {
+                    warn!("补发推送事件失败 (用户 {}): {:?}", user_id.0, e);
+                    Vec::new()
+                }
+            }
+        }
+        None => Vec::new(),
+    };
+
+    actix_web::rt::spawn(async move {
+        let _ = run_connection(session, msg_stream, receiver, missed).await;
+        hub.unsubscribe(user_id, connection_id);
+    });
+
+    Ok(response)
+}
+
+async fn run_connection(
+    mut session: Session,
+    mut msg_stream: MessageStream,
+    mut receiver: mpsc::Receiver<PushFrame>,
+    missed: Vec<PushFrame>,
+) -> Result<(), Closed> {
+    // 补发与实时推送可能重叠，只发送比已发送事件更新的事件
+    let mut last_sent = None;
+    for frame in missed {
+        last_sent = parse_event_id(&frame.id);
+        session.text(&*frame.text).await?;
+    }
+
+    let mut heartbeat = actix_web::rt::time::interval(HEARTBEAT_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    let reason = loop {
+        tokio::select! {
+            message = msg_stream.recv() => {
+                match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        last_seen = Instant::now();
+                        session.pong(&bytes).await?;
+                    }
+                    Some(Ok(Message::Close(reason))) => break reason,
+                    Some(Ok(_)) => last_seen = Instant::now(),
+                    Some(Err(_)) | None => break None,
+                }
+            }
+            frame = receiver.recv() => {
+                let Some(frame) = frame else {
+                    break None;
+                };
+                let id = parse_event_id(&frame.id);
+                if last_sent.is_some() && id <= last_sent {
+                    continue;
+                }
+                last_sent = id;
+                session.text(&*frame.text).await?;
+            }
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > CLIENT_TIMEOUT {
+                    break None;
+                }
+                session.ping(b"").await?;
+            }
+        }
+    };
+
+    session.close(reason).await
+}
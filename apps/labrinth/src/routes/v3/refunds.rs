@@ -0,0 +1,763 @@
+//! 订单退款 API
+//!
+//! 买家在项目退款政策规定的期限内可对已支付订单申请退款，并通过退款线程与作者沟通。
+//! 作者（项目成员权限 EDIT_DETAILS）或管理员审核通过后，通过卖家商户原路退款，
+//! 同时撤销买家的购买授权。卖家商户无法退款时，管理员可由平台垫付退款，
+//! 垫付的金额从创作者可提现收益中扣回（见 `database::models::ledger_item`）。
+//!
+//! 支付平台直接发起的退款（拒付）由支付回调处理，见 `routes::internal::payment`。
+
+use super::ApiError;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ids::{
+    PaymentRefundId as DBPaymentRefundId, ProjectId as DBProjectId,
+    UserId as DBUserId,
+};
+use crate::database::models::notification_item::NotificationBuilder;
+use crate::database::models::thread_item::{
+    ThreadBuilder, ThreadMessageBuilder,
+};
+use crate::database::models::{
+    LedgerEntry, OrderStatus, PaymentMerchant, PaymentOrder,
+    PaymentRefund as DBPaymentRefund, ProjectPricing, UserPurchase,
+    generate_payment_refund_id,
+};
+use crate::database::redis::RedisPool;
+use crate::models::ids::PaymentRefundId;
+use crate::models::ids::base62_impl::to_base62;
+use crate::models::notifications::NotificationBody;
+use crate::models::pats::Scopes;
+use crate::models::refunds::{PaymentRefund, RefundSource, RefundStatus};
+use crate::models::teams::ProjectPermissions;
+use crate::models::threads::{MessageBody, ThreadType};
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::routes::v3::project_order::request_payment_refund;
+use crate::util::validate::validation_errors_to_string;
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use validator::Validate;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("refund")
+            .route("", web::post().to(refund_create))
+            .route("", web::get().to(refunds_get))
+            .route("pending", web::get().to(refunds_pending))
+            .route("{id}", web::get().to(refund_get))
+            .route("{id}", web::delete().to(refund_cancel))
+            .route("{id}/approve", web::post().to(refund_approve))
+            .route("{id}/reject", web::post().to(refund_reject)),
+    );
+}
+
+/// 申请退款请求
+#[derive(Deserialize, Validate)]
+pub struct RefundCreate {
+    /// 订单号
+    #[validate(length(min = 1, max = 64, message = "订单号无效"))]
+    pub order_no: String,
+    #[validate(length(
+        min = 5,
+        max = 2000,
+        message = "退款理由长度必须在 5-2000 之间"
+    ))]
+    pub reason: String,
+}
+
+/// 审核退款请求
+#[derive(Deserialize, Validate)]
+pub struct RefundReview {
+    #[validate(length(max = 2000, message = "审核备注不能超过 2000 个字符"))]
+    pub note: Option<String>,
+    /// 由平台垫付退款并从创作者收益中扣回（仅管理员，卖家商户无法原路退款时使用）
+    #[serde(default)]
+    pub platform_funded: bool,
+}
+
+/// 申请退款
+///
+/// POST /v3/refund
+pub async fn refund_create(
+    req: HttpRequest,
+    body: web::Json<RefundCreate>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let user_id: DBUserId = user.id.into();
+
+    let order = PaymentOrder::get_by_order_no(&body.order_no, &**pool)
+        .await?
+        .filter(|x| x.user_id == user_id)
+        .ok_or_else(|| ApiError::InvalidInput("订单不存在".to_string()))?;
+
+    match order.status {
+        OrderStatus::Paid => {}
+        OrderStatus::Refunded => {
+            return Err(ApiError::InvalidInput("该订单已退款".to_string()));
+        }
+        _ => {
+            return Err(ApiError::InvalidInput(
+                "只有已支付的订单才能申请退款".to_string(),
+            ));
+        }
+    }
+
+    // 多项目订单（捆绑包等）按各项目中最短的退款期限计算，
+    // 任一项目不接受退款时整单不可退款
+    let mut project_ids = PaymentOrder::get_items(order.id, &**pool)
+        .await?
+        .into_iter()
+        .map(|x| x.project_id)
+        .collect::<Vec<_>>();
+    if project_ids.is_empty() {
+        project_ids.push(order.project_id);
+    }
+    let pricings = ProjectPricing::get_many(&project_ids, &**pool).await?;
+    let refund_window_days = project_ids
+        .iter()
+        .map(|id| {
+            pricings
+                .iter()
+                .find(|x| x.project_id == *id)
+                .map_or(0, |x| x.refund_window_days)
+        })
+        .min()
+        .filter(|x| *x > 0)
+        .ok_or_else(|| {
+            ApiError::InvalidInput("该项目不接受退款申请".to_string())
+        })?;
+
+    let paid_at = order.paid_at.unwrap_or(order.created_at);
+    if paid_at + Duration::days(refund_window_days as i64) < Utc::now() {
+        return Err(ApiError::InvalidInput(format!(
+            "已超过退款期限（支付后 {refund_window_days} 天内）"
+        )));
+    }
+
+    if DBPaymentRefund::get_by_order(order.id, &**pool)
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::InvalidInput("该订单已有退款申请".to_string()));
+    }
+
+    let project =
+        database::models::Project::get_id(order.project_id, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+
+    // 1. 创建退款线程，申请理由作为首条消息
+    let thread_id = ThreadBuilder {
+        type_: ThreadType::Refund,
+        members: vec![user_id],
+        project_id: None,
+        report_id: None,
+        ban_appeal_id: None,
+        creator_application_id: None,
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    ThreadMessageBuilder {
+        author_id: Some(user_id),
+        body: MessageBody::Text {
+            body: body.reason.clone(),
+            private: false,
+            replying_to: None,
+            associated_images: vec![],
+        },
+        thread_id,
+        hide_identity: false,
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    // 2. 写入退款申请
+    let refund = DBPaymentRefund {
+        id: generate_payment_refund_id(&mut transaction).await?,
+        order_id: order.id,
+        order_no: order.order_no.clone(),
+        user_id,
+        project_id: order.project_id,
+        seller_id: order.seller_id,
+        amount: order.amount,
+        reason: body.reason.clone(),
+        status: RefundStatus::Pending,
+        source: RefundSource::Buyer,
+        thread_id: Some(thread_id),
+        reviewer_id: None,
+        review_note: None,
+        external_refund_no: None,
+        clawback_amount: Decimal::ZERO,
+        created_at: Utc::now(),
+        reviewed_at: None,
+        completed_at: None,
+    };
+    refund.insert(&mut transaction).await?;
+
+    // 3. 通知有编辑权限的项目成员
+    let reviewers = database::models::TeamMember::get_from_team_full(
+        project.inner.team_id,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .into_iter()
+    .filter(|x| {
+        x.accepted
+            && x.user_id != user_id
+            && x.permissions.contains(ProjectPermissions::EDIT_DETAILS)
+    })
+    .map(|x| x.user_id)
+    .collect::<Vec<_>>();
+
+    let push_events = if !reviewers.is_empty() {
+        let project_b62 = to_base62(project.inner.id.0 as u64);
+        NotificationBuilder {
+            body: NotificationBody::LegacyMarkdown {
+                notification_type: Some("refund_request".to_string()),
+                name: format!("[退款申请] {}", project.inner.name),
+                text: format!(
+                    "买家申请退款 {} 元，请在退款线程中沟通并审核。",
+                    order.amount
+                ),
+                link: format!("/project/{project_b62}/settings/refunds"),
+                actions: vec![],
+            },
+        }
+        .insert_many(reviewers, &mut transaction, &redis)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
+
+    Ok(HttpResponse::Ok().json(PaymentRefund::from(refund)))
+}
+
+/// 获取当前用户的退款记录
+///
+/// GET /v3/refund
+pub async fn refunds_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let refunds = DBPaymentRefund::get_user(user.id.into(), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(
+        refunds
+            .into_iter()
+            .map(PaymentRefund::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 获取所有待审核的退款申请（管理员）
+///
+/// GET /v3/refund/pending
+pub async fn refunds_pending(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    if !user.role.is_admin() {
+        return Err(ApiError::CustomAuthentication(
+            "只有管理员可以查看所有退款申请".to_string(),
+        ));
+    }
+
+    let refunds = DBPaymentRefund::get_pending(&**pool).await?;
+
+    Ok(HttpResponse::Ok().json(
+        refunds
+            .into_iter()
+            .map(PaymentRefund::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 获取项目的退款记录
+///
+/// GET /v3/project/{id}/refunds
+pub async fn project_refunds_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let project =
+        database::models::Project::get(&info.into_inner().0, &**pool, &redis)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+    if !can_review(&user, project.inner.id, &pool, &redis).await? {
+        return Err(ApiError::CustomAuthentication(
+            "您没有权限查看此项目的退款记录".to_string(),
+        ));
+    }
+
+    let refunds =
+        DBPaymentRefund::get_project(project.inner.id, &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(
+        refunds
+            .into_iter()
+            .map(PaymentRefund::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 获取退款详情
+///
+/// GET /v3/refund/{id}
+pub async fn refund_get(
+    req: HttpRequest,
+    info: web::Path<(PaymentRefundId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let refund = DBPaymentRefund::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if refund.user_id != user.id.into()
+        && !can_review(&user, refund.project_id, &pool, &redis).await?
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(PaymentRefund::from(refund)))
+}
+
+/// 撤回退款申请（买家）
+///
+/// DELETE /v3/refund/{id}
+pub async fn refund_cancel(
+    req: HttpRequest,
+    info: web::Path<(PaymentRefundId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await?
+    .1;
+
+    let refund = DBPaymentRefund::get_id(info.into_inner().0.into(), &**pool)
+        .await?
+        .filter(|x| x.user_id == user.id.into())
+        .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+    let cancelled = DBPaymentRefund::close(
+        refund.id,
+        RefundStatus::Cancelled,
+        None,
+        None,
+        &mut transaction,
+    )
+    .await?;
+    if !cancelled {
+        return Err(ApiError::InvalidInput(
+            "只能撤回待审核的退款申请".to_string(),
+        ));
+    }
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 同意退款
+///
+/// POST /v3/refund/{id}/approve
+pub async fn refund_approve(
+    req: HttpRequest,
+    info: web::Path<(PaymentRefundId,)>,
+    body: web::Json<RefundReview>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let refund =
+        get_reviewable(&user, info.into_inner().0, &pool, &redis).await?;
+
+    if body.platform_funded && !user.role.is_admin() {
+        return Err(ApiError::CustomAuthentication(
+            "只有管理员可以由平台垫付退款".to_string(),
+        ));
+    }
+
+    let order = PaymentOrder::get_by_order_no(&refund.order_no, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+
+    // 与退款回调相同，先锁定订单再锁定退款记录，避免并发审核或回调重复退款
+    if PaymentOrder::lock_status(order.id, &mut transaction).await?
+        != Some(OrderStatus::Paid)
+    {
+        return Err(ApiError::InvalidInput(
+            "订单状态已变化，无法退款".to_string(),
+        ));
+    }
+    if !DBPaymentRefund::lock_pending(refund.id, &mut transaction).await? {
+        return Err(ApiError::InvalidInput("该退款申请已处理".to_string()));
+    }
+
+    let (mut transaction, external_refund_no, clawback_amount) = if body
+        .platform_funded
+    {
+        (transaction, None, order.seller_amount)
+    } else {
+        let merchant = PaymentMerchant::get_by_user(refund.seller_id, &**pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(
+                    "卖家未配置支付商户，无法原路退款，请联系管理员处理"
+                        .to_string(),
+                )
+            })?;
+
+        // 调用支付平台前先提交处理中状态，其他审核请求不会再次发起退款
+        DBPaymentRefund::mark_processing(
+            refund.id,
+            user.id.into(),
+            body.note.as_deref(),
+            &mut transaction,
+        )
+        .await?;
+        transaction.commit().await?;
+
+        let refund_no = match request_payment_refund(
+            &refund.order_no,
+            merchant.sid,
+            &merchant.secret_key,
+            refund.amount,
+        )
+        .await
+        {
+            Ok(refund_no) => refund_no,
+            Err(err) => {
+                let mut transaction = pool.begin().await?;
+                DBPaymentRefund::revert_processing(refund.id, &mut transaction)
+                    .await?;
+                transaction.commit().await?;
+                return Err(err);
+            }
+        };
+
+        let mut transaction = pool.begin().await?;
+
+        // 支付平台的退款回调可能已先完成了该退款
+        if PaymentOrder::lock_status(order.id, &mut transaction).await?
+            != Some(OrderStatus::Paid)
+        {
+            return Ok(HttpResponse::NoContent().body(""));
+        }
+
+        (transaction, refund_no, Decimal::ZERO)
+    };
+
+    let refunded_projects = complete_refund(
+        &refund,
+        Some(user.id.into()),
+        body.note.as_deref(),
+        external_refund_no.as_deref(),
+        clawback_amount,
+        &mut transaction,
+    )
+    .await?;
+
+    let project_b62 = to_base62(refund.project_id.0 as u64);
+    let push_events = NotificationBuilder {
+        body: NotificationBody::LegacyMarkdown {
+            notification_type: Some("refund_result".to_string()),
+            name: "[退款] 退款申请已通过".to_string(),
+            text: format!(
+                "订单 {} 的 {} 元已退回原支付账户。",
+                refund.order_no, refund.amount
+            ),
+            link: format!("/project/{project_b62}"),
+            actions: vec![],
+        },
+    }
+    .insert(refund.user_id, &mut transaction, &redis)
+    .await?;
+
+    transaction.commit().await?;
+
+    for (user_id, project_id) in refunded_projects {
+        UserPurchase::remove_from_user_purchase_cache(
+            user_id, project_id, &redis,
+        )
+        .await?;
+    }
+    crate::queue::push::publish(push_events, &redis).await;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 拒绝退款
+///
+/// POST /v3/refund/{id}/reject
+pub async fn refund_reject(
+    req: HttpRequest,
+    info: web::Path<(PaymentRefundId,)>,
+    body: web::Json<RefundReview>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+
+    let refund =
+        get_reviewable(&user, info.into_inner().0, &pool, &redis).await?;
+
+    let mut transaction = pool.begin().await?;
+
+    let rejected = DBPaymentRefund::close(
+        refund.id,
+        RefundStatus::Rejected,
+        Some(user.id.into()),
+        body.note.as_deref(),
+        &mut transaction,
+    )
+    .await?;
+    if !rejected {
+        return Err(ApiError::InvalidInput("该退款申请已处理".to_string()));
+    }
+
+    let project_b62 = to_base62(refund.project_id.0 as u64);
+    let push_events = NotificationBuilder {
+        body: NotificationBody::LegacyMarkdown {
+            notification_type: Some("refund_result".to_string()),
+            name: "[退款] 退款申请被拒绝".to_string(),
+            text: body.note.clone().unwrap_or_else(|| {
+                format!("订单 {} 的退款申请未通过审核。", refund.order_no)
+            }),
+            link: format!("/project/{project_b62}"),
+            actions: vec![],
+        },
+    }
+    .insert(refund.user_id, &mut transaction, &redis)
+    .await?;
+
+    transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// 完成退款：订单标记为已退款、退款记录标记为已完成、在账本中记录退款、
+/// 撤销对应的购买授权
+///
+/// 调用方负责提交事务并清除返回的用户与项目的购买缓存
+pub async fn complete_refund(
+    refund: &DBPaymentRefund,
+    reviewer_id: Option<DBUserId>,
+    review_note: Option<&str>,
+    external_refund_no: Option<&str>,
+    clawback_amount: Decimal,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<(DBUserId, DBProjectId)>, ApiError> {
+    if !PaymentOrder::mark_as_refunded(refund.order_id, transaction).await? {
+        return Err(ApiError::InvalidInput(
+            "订单状态已变化，无法退款".to_string(),
+        ));
+    }
+
+    let items =
+        PaymentOrder::get_items(refund.order_id, &mut **transaction).await?;
+
+    if !refund
+        .complete(
+            reviewer_id,
+            review_note,
+            external_refund_no,
+            &items,
+            clawback_amount,
+            transaction,
+        )
+        .await?
+    {
+        return Err(ApiError::InvalidInput("该退款申请已处理".to_string()));
+    }
+
+    LedgerEntry::record_refund(
+        &refund.order_no,
+        refund.seller_id,
+        &items,
+        clawback_amount,
+        transaction,
+    )
+    .await?;
+
+    Ok(UserPurchase::refund(&refund.order_no, transaction).await?)
+}
+
+/// 获取待审核的退款申请，并检查用户是否有权审核
+async fn get_reviewable(
+    user: &User,
+    id: PaymentRefundId,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<DBPaymentRefund, ApiError> {
+    let refund = DBPaymentRefund::get_id(DBPaymentRefundId::from(id), pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if !can_review(user, refund.project_id, pool, redis).await? {
+        return Err(ApiError::NotFound);
+    }
+
+    if refund.user_id == user.id.into() && !user.role.is_admin() {
+        return Err(ApiError::CustomAuthentication(
+            "不能审核自己的退款申请".to_string(),
+        ));
+    }
+
+    match refund.status {
+        RefundStatus::Pending => Ok(refund),
+        RefundStatus::Processing => Err(ApiError::InvalidInput(
+            "退款正在处理中，请等待支付平台确认".to_string(),
+        )),
+        _ => Err(ApiError::InvalidInput("该退款申请已处理".to_string())),
+    }
+}
+
+/// 管理员或有编辑权限的项目成员可以查看和审核项目的退款
+async fn can_review(
+    user: &User,
+    project_id: DBProjectId,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<bool, ApiError> {
+    if user.role.is_admin() {
+        return Ok(true);
+    }
+
+    let Some(project) =
+        database::models::Project::get_id(project_id, pool, redis).await?
+    else {
+        return Ok(false);
+    };
+
+    let (team_member, organization_team_member) =
+        database::models::TeamMember::get_for_project_permissions(
+            &project.inner,
+            user.id.into(),
+            pool,
+        )
+        .await?;
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    )
+    .unwrap_or_default();
+
+    Ok(permissions.contains(ProjectPermissions::EDIT_DETAILS))
+}
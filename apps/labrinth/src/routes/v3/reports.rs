@@ -1,3 +1,4 @@
+use crate::auth::checks::is_visible_project;
 use crate::auth::{
     check_forum_ban, check_is_moderator_from_headers, get_user_from_headers,
 };
@@ -6,22 +7,38 @@ use crate::database::models::image_item;
 use crate::database::models::thread_item::{
     ThreadBuilder, ThreadMessageBuilder,
 };
+use crate::database::models::wiki_item::WIKI_NAMESPACE;
+use crate::database::models::wiki_revision_item::{
+    WikiRevisionAction, WikiRevisionContext,
+};
+use crate::database::models::{AuditLogBuilder, WikiRevision};
 use crate::database::redis::RedisPool;
 use crate::models::ids::ImageId;
+use crate::models::ids::base62_impl::to_base62;
 use crate::models::ids::{
-    ProjectId, UserId, VersionId, base62_impl::parse_base62,
+    IssuesCommentsId, PostId, ProjectId, ThreadMessageId, UserId, VersionId,
+    WikiId, base62_impl::parse_base62,
 };
 use crate::models::images::{Image, ImageContext};
 use crate::models::pats::Scopes;
-use crate::models::reports::{ItemType, Report};
+use crate::models::reports::{
+    AggregatedReport, ContentAction, ItemType, Report, ReportId,
+    ReportedContent,
+};
 use crate::models::threads::{MessageBody, ThreadType};
+use crate::models::users::User;
+use crate::models::v3::bans::{CreateBanRequest, UserBan};
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
+use crate::routes::v3::wikis::check_wiki_paid_access;
+use crate::search::SearchConfig;
+use crate::search::indexing::content::{ContentTarget, update_content_index};
 use crate::util::img;
+use crate::util::ip::request_ip;
 use actix_web::{HttpRequest, HttpResponse, web};
 use chrono::Utc;
 use futures::StreamExt;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use validator::Validate;
 
@@ -29,9 +46,11 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.route("report", web::post().to(report_create));
     cfg.route("report", web::get().to(reports));
     cfg.route("reports", web::get().to(reports_get));
+    cfg.route("report/items", web::get().to(report_items));
     cfg.route("report/{id}", web::get().to(report_get));
     cfg.route("report/{id}", web::patch().to(report_edit));
     cfg.route("report/{id}", web::delete().to(report_delete));
+    cfg.route("report/{id}/moderate", web::post().to(report_moderate));
 }
 
 #[derive(Deserialize, Validate)]
@@ -90,12 +109,19 @@ pub async fn report_create(
         ))
     })?;
 
+    let mut content = None;
     let mut report = crate::database::models::report_item::Report {
         id,
         report_type_id: report_type,
         project_id: None,
         version_id: None,
         user_id: None,
+        post_id: None,
+        issue_comment_id: None,
+        wiki_id: None,
+        thread_message_id: None,
+        content_author_id: None,
+        content_snapshot: None,
         body: new_report.body.clone(),
         reporter: current_user.id.into(),
         created: Utc::now(),
@@ -162,6 +188,209 @@ pub async fn report_create(
 
             report.user_id = Some(user_id.into())
         }
+        ItemType::ForumPost => {
+            let post_id = PostId(parse_base62(new_report.item_id.as_str())?);
+
+            let post = sqlx::query!(
+                "
+                SELECT p.discussion_id, p.user_id, p.content, p.created_at, d.title,
+                    (SELECT m.id FROM mods m WHERE m.forum = d.id LIMIT 1) AS project_id
+                FROM posts p
+                INNER JOIN discussions d ON d.id = p.discussion_id
+                WHERE p.id = $1 AND p.deleted = FALSE AND d.deleted = FALSE
+                ",
+                post_id.0 as i64
+            )
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(format!(
+                    "回复未找到: {}",
+                    new_report.item_id
+                ))
+            })?;
+
+            if let Some(project_id) = post.project_id
+                && !can_view_reported_project(
+                    project_id,
+                    &current_user,
+                    false,
+                    &pool,
+                    &redis,
+                )
+                .await?
+            {
+                return Err(ApiError::InvalidInput(format!(
+                    "回复未找到: {}",
+                    new_report.item_id
+                )));
+            }
+
+            report.post_id = Some(post_id.into());
+            report.content_author_id =
+                Some(database::models::UserId(post.user_id));
+            content = Some(ReportedContent {
+                title: Some(post.title),
+                body: post.content,
+                parent_id: Some(to_base62(post.discussion_id as u64)),
+                created: post.created_at,
+            });
+        }
+        ItemType::IssueComment => {
+            let comment_id =
+                IssuesCommentsId(parse_base62(new_report.item_id.as_str())?);
+
+            let comment = sqlx::query!(
+                "
+                SELECT c.issue_id, c.author_id, c.body, c.created_at, i.title, i.mod_id
+                FROM issue_comments c
+                INNER JOIN issues i ON i.id = c.issue_id
+                WHERE c.id = $1 AND c.deleted = FALSE AND i.deleted = FALSE
+                ",
+                comment_id.0 as i64
+            )
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(format!(
+                    "评论未找到: {}",
+                    new_report.item_id
+                ))
+            })?;
+
+            if !can_view_reported_project(
+                comment.mod_id,
+                &current_user,
+                false,
+                &pool,
+                &redis,
+            )
+            .await?
+            {
+                return Err(ApiError::InvalidInput(format!(
+                    "评论未找到: {}",
+                    new_report.item_id
+                )));
+            }
+
+            report.issue_comment_id = Some(comment_id.into());
+            report.content_author_id =
+                Some(database::models::UserId(comment.author_id));
+            content = Some(ReportedContent {
+                title: Some(comment.title),
+                body: comment.body,
+                parent_id: Some(to_base62(comment.issue_id as u64)),
+                created: comment.created_at,
+            });
+        }
+        ItemType::WikiPage => {
+            let wiki_id = WikiId(parse_base62(new_report.item_id.as_str())?);
+
+            // 百科由多人编辑，以最后一次修订的作者作为内容作者
+            let wiki = sqlx::query!(
+                "
+                SELECT w.mod_id, w.title, w.body, w.updated,
+                    (
+                        SELECT r.author_id FROM wiki_revisions r
+                        WHERE r.wiki_id = w.id
+                        ORDER BY r.revision_number DESC
+                        LIMIT 1
+                    ) AS last_author_id
+                FROM wikis w
+                WHERE w.id = $1 AND w.draft = FALSE
+                ",
+                wiki_id.0 as i64
+            )
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(format!(
+                    "百科页面未找到: {}",
+                    new_report.item_id
+                ))
+            })?;
+
+            // 付费项目的百科正文只对已购买的用户可见
+            if !can_view_reported_project(
+                wiki.mod_id,
+                &current_user,
+                true,
+                &pool,
+                &redis,
+            )
+            .await?
+            {
+                return Err(ApiError::InvalidInput(format!(
+                    "百科页面未找到: {}",
+                    new_report.item_id
+                )));
+            }
+
+            report.wiki_id = Some(wiki_id.into());
+            report.content_author_id =
+                wiki.last_author_id.map(database::models::UserId);
+            content = Some(ReportedContent {
+                title: Some(wiki.title),
+                body: wiki.body,
+                parent_id: Some(to_base62(wiki.mod_id as u64)),
+                created: wiki.updated,
+            });
+        }
+        ItemType::ThreadMessage => {
+            let message_id =
+                ThreadMessageId(parse_base62(new_report.item_id.as_str())?);
+
+            let message = database::models::ThreadMessage::get(
+                message_id.into(),
+                &mut *transaction,
+            )
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(format!(
+                    "消息未找到: {}",
+                    new_report.item_id
+                ))
+            })?;
+
+            // 只能举报自己所在讨论串中的消息
+            let is_member = sqlx::query!(
+                "
+                SELECT EXISTS(
+                    SELECT 1 FROM threads_members
+                    WHERE thread_id = $1 AND user_id = $2
+                )
+                ",
+                message.thread_id.0,
+                current_user.id.0 as i64
+            )
+            .fetch_one(&mut *transaction)
+            .await?
+            .exists
+            .unwrap_or(false);
+
+            let body = match message.body {
+                MessageBody::Text {
+                    body,
+                    private: false,
+                    ..
+                } if is_member && message.author_id.is_some() => body,
+                _ => {
+                    return Err(ApiError::InvalidInput(format!(
+                        "消息未找到: {}",
+                        new_report.item_id
+                    )));
+                }
+            };
+
+            report.thread_message_id = Some(message_id.into());
+            report.content_author_id = message.author_id;
+            content = Some(ReportedContent {
+                title: None,
+                body,
+                parent_id: Some(to_base62(message.thread_id.0 as u64)),
+                created: message.created,
+            });
+        }
         ItemType::Unknown => {
             return Err(ApiError::InvalidInput(format!(
                 "无效的举报项目类型: {}",
@@ -170,6 +399,27 @@ pub async fn report_create(
         }
     }
 
+    if report.content_author_id == Some(current_user.id.into()) {
+        return Err(ApiError::InvalidInput("不能举报自己的内容".to_string()));
+    }
+
+    // 同一用户对同一对象只保留一条未关闭的举报，其他用户的举报会被聚合
+    let item_id = parse_base62(new_report.item_id.as_str())? as i64;
+    if crate::database::models::report_item::Report::has_open_report(
+        report.reporter,
+        new_report.item_type.as_str(),
+        item_id,
+        &mut *transaction,
+    )
+    .await?
+    {
+        return Err(ApiError::InvalidInput(
+            "您已举报过该内容，请等待管理员处理".to_string(),
+        ));
+    }
+
+    report.content_snapshot =
+        content.as_ref().map(serde_json::to_value).transpose()?;
     report.insert(&mut transaction).await?;
 
     for image_id in new_report.uploaded_images {
@@ -234,9 +484,58 @@ pub async fn report_create(
         created: Utc::now(),
         closed: false,
         thread_id: thread_id.into(),
+        content_author: report.content_author_id.map(Into::into),
+        content: content.filter(|_| current_user.role.is_mod()),
     }))
 }
 
+/// 被举报内容所属的项目须对举报人可见，`paid_content` 为真时付费项目
+/// 还须已购买，避免通过举报读取无权查看的内容
+async fn can_view_reported_project(
+    project_id: i64,
+    user: &User,
+    paid_content: bool,
+    pool: &PgPool,
+    redis: &RedisPool,
+) -> Result<bool, ApiError> {
+    let Some(project) = database::models::Project::get_id(
+        database::models::ProjectId(project_id),
+        pool,
+        redis,
+    )
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    if !is_visible_project(&project.inner, &Some(user.clone()), pool, false)
+        .await?
+    {
+        return Ok(false);
+    }
+
+    if paid_content
+        && project.inner.is_paid
+        && !check_wiki_paid_access(user, &project.inner, pool).await?
+    {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// 内容快照只返回给版主，举报人只能看到自己的举报说明
+fn report_for_user(
+    report: database::models::report_item::Report,
+    user: &User,
+) -> Report {
+    let mut report: Report = report.into();
+    if !user.role.is_mod() {
+        report.content = None;
+    }
+    report
+}
+
 #[derive(Deserialize)]
 pub struct ReportsRequestOptions {
     #[serde(default = "default_count")]
@@ -311,7 +610,7 @@ pub async fn reports(
     let mut reports: Vec<Report> = Vec::new();
 
     for x in query_reports {
-        reports.push(x.into());
+        reports.push(report_for_user(x, &user));
     }
 
     Ok(HttpResponse::Ok().json(reports))
@@ -354,7 +653,7 @@ pub async fn reports_get(
     let all_reports = reports_data
         .into_iter()
         .filter(|x| user.role.is_mod() || x.reporter == user.id.into())
-        .map(|x| x.into())
+        .map(|x| report_for_user(x, &user))
         .collect::<Vec<Report>>();
 
     Ok(HttpResponse::Ok().json(all_reports))
@@ -364,7 +663,7 @@ pub async fn report_get(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
-    info: web::Path<(crate::models::reports::ReportId,)>,
+    info: web::Path<(ReportId,)>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
@@ -386,8 +685,7 @@ pub async fn report_get(
             return Err(ApiError::NotFound);
         }
 
-        let report: Report = report.into();
-        Ok(HttpResponse::Ok().json(report))
+        Ok(HttpResponse::Ok().json(report_for_user(report, &user)))
     } else {
         Err(ApiError::NotFound)
     }
@@ -404,7 +702,7 @@ pub async fn report_edit(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
-    info: web::Path<(crate::models::reports::ReportId,)>,
+    info: web::Path<(ReportId,)>,
     session_queue: web::Data<AuthQueue>,
     edit_report: web::Json<EditReport>,
 ) -> Result<HttpResponse, ApiError> {
@@ -506,7 +804,7 @@ pub async fn report_edit(
 pub async fn report_delete(
     req: HttpRequest,
     pool: web::Data<PgPool>,
-    info: web::Path<(crate::models::reports::ReportId,)>,
+    info: web::Path<(ReportId,)>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
 ) -> Result<HttpResponse, ApiError> {
@@ -548,3 +846,461 @@ pub async fn report_delete(
         Err(ApiError::NotFound)
     }
 }
+
+#[derive(Deserialize)]
+pub struct ReportItemsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ReportItemsResponse {
+    pub items: Vec<AggregatedReport>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+/// 按被举报对象聚合的未关闭举报，仅版主可见
+pub async fn report_items(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<ReportItemsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_moderator_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::REPORT_READ]),
+    )
+    .await?;
+
+    let page = query.page.unwrap_or(1).clamp(1, 10_000);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let total =
+        crate::database::models::report_item::Report::count_open_groups(
+            &**pool,
+        )
+        .await?;
+    let groups = crate::database::models::report_item::Report::get_open_groups(
+        page_size,
+        (page - 1) * page_size,
+        &**pool,
+    )
+    .await?;
+
+    // 每组取最近一条举报的内容快照
+    let latest_ids = groups
+        .iter()
+        .filter_map(|x| x.report_ids.last().copied())
+        .collect::<Vec<_>>();
+    let latest = crate::database::models::report_item::Report::get_many(
+        &latest_ids,
+        &**pool,
+    )
+    .await?
+    .into_iter()
+    .map(|x| (x.id.0, Report::from(x)))
+    .collect::<std::collections::HashMap<_, _>>();
+
+    let items = groups
+        .into_iter()
+        .map(|group| {
+            let report = group.report_ids.last().and_then(|x| latest.get(&x.0));
+
+            AggregatedReport {
+                item_type: ItemType::from_string(&group.item_type),
+                item_id: to_base62(group.item_id as u64),
+                report_count: group.report_count,
+                reporter_count: group.reporter_count,
+                report_types: group.report_types,
+                report_ids: group
+                    .report_ids
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                first_reported: group.first_reported,
+                last_reported: group.last_reported,
+                content_author: report.and_then(|x| x.content_author),
+                content: report.and_then(|x| x.content.clone()),
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ReportItemsResponse {
+        items,
+        total,
+        page,
+        page_size,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ModerateReport {
+    /// 隐藏或删除被举报的内容
+    pub content_action: Option<ContentAction>,
+    /// 封禁被举报内容的作者
+    pub ban: Option<CreateBanRequest>,
+    /// 关闭同一对象的全部未关闭举报
+    #[serde(default = "default_close")]
+    pub close: bool,
+}
+
+fn default_close() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+pub struct ModerateReportResponse {
+    pub closed_reports: Vec<ReportId>,
+    pub ban: Option<UserBan>,
+}
+
+/// 内容处理提交后需要清理的缓存
+enum ContentCleanup {
+    Post {
+        post_id: crate::database::models::ids::PostId,
+        discussion_id: crate::database::models::ids::DiscussionId,
+        replied_to: Option<crate::database::models::ids::PostId>,
+        author_id: i64,
+    },
+    IssueComment {
+        comment_id: crate::database::models::ids::IssuesCommentsId,
+        issue_id: crate::database::models::ids::IssuesId,
+    },
+    Wiki {
+        wiki_id: i64,
+        project_id: crate::database::models::ids::ProjectId,
+    },
+}
+
+/// 直接处理举报：隐藏或删除内容、封禁作者并关闭同一对象的举报
+pub async fn report_moderate(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    info: web::Path<(ReportId,)>,
+    session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
+    body: web::Json<ModerateReport>,
+) -> Result<HttpResponse, ApiError> {
+    let user = check_is_moderator_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::REPORT_WRITE]),
+    )
+    .await?;
+    let id = info.into_inner().0.into();
+
+    let report: Report =
+        crate::database::models::report_item::Report::get(id, &**pool)
+            .await?
+            .ok_or(ApiError::NotFound)?
+            .into();
+    let item_id = parse_base62(&report.item_id)? as i64;
+
+    if body.content_action.is_some() && !report.item_type.is_content() {
+        return Err(ApiError::InvalidInput(
+            "该类型的举报不支持直接处理内容".to_string(),
+        ));
+    }
+
+    // 先封禁：封禁校验失败时不改动内容和举报状态
+    let ban = if let Some(ban) = &body.ban {
+        let target = match report.item_type {
+            ItemType::User => Some(UserId(item_id as u64)),
+            _ => report.content_author,
+        }
+        .ok_or_else(|| {
+            ApiError::InvalidInput("无法确定被举报内容的作者".to_string())
+        })?;
+
+        Some(
+            super::bans::issue_ban(
+                &req,
+                &user,
+                target.into(),
+                ban,
+                &pool,
+                &redis,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let mut transaction = pool.begin().await?;
+
+    let mut cleanup = None;
+    if let Some(action) = body.content_action {
+        cleanup = moderate_content(
+            report.item_type,
+            item_id,
+            action,
+            &user,
+            &mut transaction,
+        )
+        .await?;
+
+        AuditLogBuilder {
+            actor_id: Some(user.id.into()),
+            action: match action {
+                ContentAction::Hide => "report.content_hide",
+                ContentAction::Delete => "report.content_delete",
+            }
+            .to_string(),
+            target_type: report.item_type.as_str().to_string(),
+            target_id: Some(item_id),
+            before: report
+                .content
+                .as_ref()
+                .map(serde_json::to_value)
+                .transpose()?,
+            after: None,
+            reason: Some(report.report_type.clone()),
+            ip: request_ip(&req),
+        }
+        .insert(&mut transaction)
+        .await?;
+    }
+
+    let mut closed_reports = Vec::new();
+    if body.close {
+        let closed =
+            crate::database::models::report_item::Report::close_for_item(
+                report.item_type.as_str(),
+                item_id,
+                &mut transaction,
+            )
+            .await?;
+
+        for (report_id, thread_id) in closed {
+            ThreadMessageBuilder {
+                author_id: Some(user.id.into()),
+                body: MessageBody::ThreadClosure,
+                thread_id,
+                hide_identity: true,
+            }
+            .insert(&mut transaction)
+            .await?;
+            closed_reports.push(report_id.into());
+        }
+    }
+
+    transaction.commit().await?;
+
+    match cleanup {
+        Some(ContentCleanup::Post {
+            post_id,
+            discussion_id,
+            replied_to,
+            author_id,
+        }) => {
+            super::forum::clear_post_caches(
+                post_id,
+                discussion_id,
+                replied_to,
+                author_id,
+                &pool,
+                &redis,
+                &search_config,
+            )
+            .await?;
+        }
+        Some(ContentCleanup::IssueComment {
+            comment_id,
+            issue_id,
+        }) => {
+            super::issues::clear_comment_caches(
+                comment_id,
+                issue_id,
+                &pool,
+                &redis,
+                &search_config,
+            )
+            .await?;
+        }
+        Some(ContentCleanup::Wiki {
+            wiki_id,
+            project_id,
+        }) => {
+            let mut redis_conn = redis.connect().await?;
+            redis_conn
+                .delete_many([(WIKI_NAMESPACE, Some(wiki_id.to_string()))])
+                .await?;
+            database::models::Project::clear_cache(
+                project_id, None, None, &redis,
+            )
+            .await?;
+            update_content_index(
+                ContentTarget::ProjectWikis(project_id),
+                &pool,
+                &search_config,
+            )
+            .await;
+        }
+        None => {}
+    }
+
+    crate::routes::internal::moderation::clear_pending_counts_cache(&redis)
+        .await;
+
+    Ok(HttpResponse::Ok().json(ModerateReportResponse {
+        closed_reports,
+        ban,
+    }))
+}
+
+/// 隐藏或删除被举报的内容，内容已不存在时不做处理
+async fn moderate_content(
+    item_type: ItemType,
+    item_id: i64,
+    action: ContentAction,
+    user: &User,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Option<ContentCleanup>, ApiError> {
+    use crate::database::models::ids as db_ids;
+
+    let delete = action == ContentAction::Delete;
+
+    match item_type {
+        ItemType::ForumPost => {
+            let post = sqlx::query!(
+                "
+                UPDATE posts
+                SET deleted = TRUE,
+                    deleted_at = COALESCE(deleted_at, NOW()),
+                    content = CASE WHEN $2 THEN '' ELSE content END
+                WHERE id = $1
+                RETURNING discussion_id, user_id, replied_to
+                ",
+                item_id,
+                delete,
+            )
+            .fetch_optional(&mut **transaction)
+            .await?;
+
+            Ok(post.map(|x| ContentCleanup::Post {
+                post_id: db_ids::PostId(item_id),
+                discussion_id: db_ids::DiscussionId(x.discussion_id),
+                replied_to: x.replied_to.map(db_ids::PostId),
+                author_id: x.user_id,
+            }))
+        }
+        ItemType::IssueComment => {
+            let comment = sqlx::query!(
+                "
+                UPDATE issue_comments
+                SET deleted = TRUE,
+                    deleted_at = COALESCE(deleted_at, NOW()),
+                    body = CASE WHEN $2 THEN '' ELSE body END
+                WHERE id = $1
+                RETURNING issue_id
+                ",
+                item_id,
+                delete,
+            )
+            .fetch_optional(&mut **transaction)
+            .await?;
+
+            Ok(comment.map(|x| ContentCleanup::IssueComment {
+                comment_id: db_ids::IssuesCommentsId(item_id),
+                issue_id: db_ids::IssuesId(x.issue_id),
+            }))
+        }
+        ItemType::WikiPage => {
+            let Some(wiki) = sqlx::query!(
+                r#"
+                SELECT mod_id, EXISTS(
+                    SELECT 1 FROM wikis c
+                    WHERE c.parent_wiki_id = w.id AND c.id <> w.id
+                ) AS "has_children!"
+                FROM wikis w
+                WHERE w.id = $1
+                "#,
+                item_id,
+            )
+            .fetch_optional(&mut **transaction)
+            .await?
+            else {
+                return Ok(None);
+            };
+
+            if delete {
+                if wiki.has_children {
+                    return Err(ApiError::InvalidInput(
+                        "该百科页面包含子页面，请先处理子页面".to_string(),
+                    ));
+                }
+
+                // 删除前写入修订，页面可通过整体回滚恢复
+                let page =
+                    database::models::Wiki::get(item_id, &mut **transaction)
+                        .await?;
+                let user_id = user.id.into();
+                WikiRevision::record(
+                    &page,
+                    WikiRevisionAction::Delete,
+                    &WikiRevisionContext {
+                        wiki_cache_id: None,
+                        author_id: Some(user_id),
+                        reviewer_id: Some(user_id),
+                        message: "举报处理：删除页面",
+                    },
+                    transaction,
+                )
+                .await?;
+                page.delete(transaction).await?;
+            } else {
+                // 草稿状态的页面不对外显示，页面再次通过编辑后恢复
+                sqlx::query!(
+                    "UPDATE wikis SET draft = TRUE WHERE id = $1",
+                    item_id,
+                )
+                .execute(&mut **transaction)
+                .await?;
+            }
+
+            Ok(Some(ContentCleanup::Wiki {
+                wiki_id: item_id,
+                project_id: db_ids::ProjectId(wiki.mod_id),
+            }))
+        }
+        ItemType::ThreadMessage => {
+            // 消息没有隐藏状态，隐藏与删除都替换为已删除消息
+            if let Some(message) = database::models::ThreadMessage::get(
+                db_ids::ThreadMessageId(item_id),
+                &mut **transaction,
+            )
+            .await?
+            {
+                let private = matches!(
+                    message.body,
+                    MessageBody::Text { private: true, .. }
+                        | MessageBody::Deleted { private: true }
+                );
+                database::models::ThreadMessage::remove_full(
+                    message.id,
+                    private,
+                    transaction,
+                )
+                .await?;
+            }
+
+            Ok(None)
+        }
+        ItemType::Project
+        | ItemType::Version
+        | ItemType::User
+        | ItemType::Unknown => Err(ApiError::InvalidInput(
+            "该类型的举报不支持直接处理内容".to_string(),
+        )),
+    }
+}
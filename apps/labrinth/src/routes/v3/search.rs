@@ -0,0 +1,190 @@
+use crate::auth::checks::is_visible_project;
+use crate::auth::get_user_from_headers;
+use crate::database;
+use crate::database::models::ProjectId;
+use crate::database::redis::RedisPool;
+use crate::models::ids::base62_impl::parse_base62;
+use crate::models::pats::Scopes;
+use crate::models::projects::ProjectStatus;
+use crate::models::users::User;
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use crate::search::{
+    ContentSearchRequest, SearchConfig, SearchError, search_for_content,
+};
+use crate::util::ratelimit::{RateLimit, SEARCH};
+use actix_web::{HttpRequest, HttpResponse, guard, web};
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("search/content")
+            .guard(guard::Get())
+            .wrap(RateLimit(&SEARCH))
+            .route(web::get().to(content_search)),
+    );
+}
+
+fn parse_project_id(id: &str) -> Option<ProjectId> {
+    parse_base62(id).ok().map(|x| ProjectId(x as i64))
+}
+
+/// 搜索百科、论坛帖子与问题
+///
+/// 不可见项目下的内容在搜索时即被排除，不计入命中数；付费项目的百科在
+/// 未购买时只返回标题。
+pub async fn content_search(
+    req: HttpRequest,
+    web::Query(info): web::Query<ContentSearchRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let user_option = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    let project_id = match &info.project {
+        Some(project) => {
+            let project =
+                database::models::Project::get(project, &**pool, &redis)
+                    .await?
+                    .ok_or(ApiError::NotFound)?;
+            if !is_visible_project(&project.inner, &user_option, &pool, false)
+                .await?
+            {
+                return Err(ApiError::NotFound);
+            }
+            Some(
+                crate::models::ids::ProjectId::from(project.inner.id)
+                    .to_string(),
+            )
+        }
+        None => None,
+    };
+
+    // 指定项目时已确认可见，无需再排除
+    let hidden_projects = match project_id {
+        Some(_) => Vec::new(),
+        None => hidden_content_projects(&user_option, &pool).await?,
+    };
+
+    let mut results = search_for_content(
+        &info,
+        project_id.as_deref(),
+        &hidden_projects,
+        &config,
+        &pool,
+    )
+    .await
+    .map_err(|e| match e {
+        SearchError::MeiliSearch(e) => ApiError::Search(e),
+        SearchError::Database(e) => ApiError::SqlxDatabase(e),
+        e => ApiError::InvalidInput(e.to_string()),
+    })?;
+
+    let project_ids = results
+        .hits
+        .iter()
+        .filter_map(|x| x.project_id.as_deref().and_then(parse_project_id))
+        .unique()
+        .collect_vec();
+    let projects =
+        database::models::Project::get_many_ids(&project_ids, &**pool, &redis)
+            .await?;
+
+    // 付费项目逐个检查购买状态
+    let mut paid_access = HashMap::new();
+    for project in &projects {
+        if project.inner.is_paid {
+            let has_access = match &user_option {
+                Some(user) => {
+                    super::wikis::check_wiki_paid_access(
+                        user,
+                        &project.inner,
+                        &pool,
+                    )
+                    .await?
+                }
+                None => false,
+            };
+            paid_access.insert(project.inner.id, has_access);
+        }
+    }
+
+    for hit in &mut results.hits {
+        if hit.content_type == "wiki" {
+            hit.requires_purchase = hit
+                .project_id
+                .as_deref()
+                .and_then(parse_project_id)
+                .and_then(|x| paid_access.get(&x))
+                .is_some_and(|x| !x);
+            if hit.requires_purchase {
+                hit.snippet = String::new();
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// 对用户不可见、且有可搜索内容的项目（base62 ID）
+///
+/// 与 [`is_visible_project`] 一致：版主可见全部项目，其他用户可见未隐藏的
+/// 项目以及自己所在团队或组织的项目。
+async fn hidden_content_projects(
+    user_option: &Option<User>,
+    pool: &PgPool,
+) -> Result<Vec<String>, ApiError> {
+    if user_option.as_ref().is_some_and(|x| x.role.is_mod()) {
+        return Ok(Vec::new());
+    }
+
+    let statuses = ProjectStatus::iterator()
+        .filter(|x| x.is_hidden())
+        .map(|x| x.to_string())
+        .collect_vec();
+    let user_id = user_option.as_ref().map(|x| x.id.0 as i64);
+
+    let ids = sqlx::query!(
+        "
+        SELECT m.id
+        FROM mods m
+        WHERE m.status = ANY($1)
+            AND (
+                m.forum IS NOT NULL
+                OR EXISTS (SELECT 1 FROM wikis w WHERE w.mod_id = m.id)
+                OR EXISTS (SELECT 1 FROM issues i WHERE i.mod_id = m.id)
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM team_members tm
+                WHERE tm.team_id = m.team_id AND tm.user_id = $2
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM organizations o
+                INNER JOIN team_members tm ON tm.team_id = o.team_id
+                WHERE o.id = m.organization_id AND tm.user_id = $2
+            )
+        ",
+        &statuses,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|x| crate::models::ids::ProjectId::from(ProjectId(x.id)).to_string())
+    .collect();
+
+    Ok(ids)
+}
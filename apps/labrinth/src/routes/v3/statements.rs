@@ -0,0 +1,250 @@
+//! 创作者月度收益对账单 API
+//!
+//! 对账单按自然月归档，可以 JSON、CSV 或 PDF 格式下载。已结束的月份
+//! 首次查看时若尚未归档会立即生成；归档后内容不再变化，只有管理员批量
+//! 重新生成时才会覆盖。
+//!
+//! 权限要求：
+//! - 查看与下载自己的对账单：PAYOUTS_READ
+//! - 批量重新生成：管理员，PAYOUTS_WRITE
+
+use crate::auth::get_user_from_headers;
+use crate::auth::validate::check_is_admin_from_headers;
+use crate::database::models::CreatorStatement;
+use crate::database::models::ids::UserId as DBUserId;
+use crate::database::redis::RedisPool;
+use crate::models::ids::UserId;
+use crate::models::pats::Scopes;
+use crate::models::statements::EarningsStatement;
+use crate::queue::session::AuthQueue;
+use crate::queue::statements;
+use crate::routes::ApiError;
+use actix_web::http::header::{
+    ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// 单次指定重新生成的用户数上限
+const MAX_REGENERATE_USERS: usize = 500;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("statement")
+            .service(statements_list)
+            .service(statements_regenerate)
+            .service(statement_get)
+            .service(statement_csv)
+            .service(statement_pdf),
+    );
+}
+
+#[derive(Serialize)]
+pub struct StatementPeriod {
+    pub period: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// 已归档的对账单月份
+#[get("")]
+pub async fn statements_list(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+
+    let periods =
+        CreatorStatement::get_user_periods(user.id.into(), &**pool).await?;
+
+    Ok(HttpResponse::Ok().json(
+        periods
+            .into_iter()
+            .map(|(period, generated_at)| StatementPeriod {
+                period: period.format("%Y-%m").to_string(),
+                generated_at,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 获取归档的对账单，已结束但尚未归档的月份立即生成
+async fn get_statement(
+    req: &HttpRequest,
+    period: &str,
+    pool: &PgPool,
+    redis: &RedisPool,
+    session_queue: &AuthQueue,
+) -> Result<EarningsStatement, ApiError> {
+    let user = get_user_from_headers(
+        req,
+        pool,
+        redis,
+        session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+    let user_id: DBUserId = user.id.into();
+
+    let period = statements::parse_period(period).ok_or_else(|| {
+        ApiError::InvalidInput("对账月份格式应为 YYYY-MM".to_string())
+    })?;
+    if !statements::is_closed_period(period, Utc::now()) {
+        return Err(ApiError::InvalidInput(
+            "该月份尚未结束，暂不能生成对账单".to_string(),
+        ));
+    }
+
+    let statement = match CreatorStatement::get(user_id, period, pool).await? {
+        Some(statement) => statement,
+        None => {
+            statements::generate(user_id, period, false, pool).await?;
+            CreatorStatement::get(user_id, period, pool)
+                .await?
+                .ok_or(ApiError::NotFound)?
+        }
+    };
+
+    Ok(serde_json::from_value(statement.data)?)
+}
+
+#[get("{period}")]
+pub async fn statement_get(
+    req: HttpRequest,
+    path: web::Path<String>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let statement =
+        get_statement(&req, &path, &pool, &redis, &session_queue).await?;
+
+    Ok(HttpResponse::Ok().json(statement))
+}
+
+fn attachment(filename: String) -> ContentDisposition {
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(filename)],
+    }
+}
+
+#[get("{period}/csv")]
+pub async fn statement_csv(
+    req: HttpRequest,
+    path: web::Path<String>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let statement =
+        get_statement(&req, &path, &pool, &redis, &session_queue).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(attachment(format!(
+            "statement-{}.csv",
+            statement.period
+        )))
+        .body(statement.to_csv()))
+}
+
+#[get("{period}/pdf")]
+pub async fn statement_pdf(
+    req: HttpRequest,
+    path: web::Path<String>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let statement =
+        get_statement(&req, &path, &pool, &redis, &session_queue).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(attachment(format!(
+            "statement-{}.pdf",
+            statement.period
+        )))
+        .body(statement.to_pdf()))
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateStatements {
+    /// 对账月份，格式 `YYYY-MM`
+    pub period: String,
+    /// 只重新生成这些用户的对账单，为空时处理当月全部有收益变动的创作者
+    pub user_ids: Option<Vec<UserId>>,
+}
+
+#[derive(Serialize)]
+pub struct RegenerateStatementsResponse {
+    pub generated: u64,
+}
+
+/// 批量重新生成对账单，覆盖已归档的版本
+#[post("regenerate")]
+pub async fn statements_regenerate(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    body: web::Json<RegenerateStatements>,
+) -> Result<HttpResponse, ApiError> {
+    let admin = check_is_admin_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?;
+
+    let period = statements::parse_period(&body.period).ok_or_else(|| {
+        ApiError::InvalidInput("对账月份格式应为 YYYY-MM".to_string())
+    })?;
+    if !statements::is_closed_period(period, Utc::now()) {
+        return Err(ApiError::InvalidInput(
+            "该月份尚未结束，暂不能生成对账单".to_string(),
+        ));
+    }
+
+    let user_ids = body
+        .user_ids
+        .as_ref()
+        .map(|ids| ids.iter().map(|&x| DBUserId::from(x)).collect::<Vec<_>>());
+    if user_ids
+        .as_ref()
+        .is_some_and(|ids| ids.is_empty() || ids.len() > MAX_REGENERATE_USERS)
+    {
+        return Err(ApiError::InvalidInput(format!(
+            "指定的用户数应在 1 到 {} 之间",
+            MAX_REGENERATE_USERS
+        )));
+    }
+
+    let generated =
+        statements::generate_period(period, user_ids.as_deref(), true, &pool)
+            .await?;
+
+    log::info!(
+        "管理员 {} 重新生成 {} 对账单 {} 份",
+        admin.id,
+        body.period,
+        generated
+    );
+
+    Ok(HttpResponse::Ok().json(RegenerateStatementsResponse { generated }))
+}
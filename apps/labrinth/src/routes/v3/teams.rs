@@ -623,7 +623,7 @@ pub async fn add_team_member(
     .await?;
 
     // 如果用户有机会接受邀请，发送通知
-    if !force_accepted {
+    let push_events = if !force_accepted {
         match team_association {
             TeamAssociationId::Project(pid) => {
                 NotificationBuilder {
@@ -635,7 +635,7 @@ pub async fn add_team_member(
                     },
                 }
                 .insert(new_member.user_id.into(), &mut transaction, &redis)
-                .await?;
+                .await?
             }
             TeamAssociationId::Organization(oid) => {
                 NotificationBuilder {
@@ -647,14 +647,17 @@ pub async fn add_team_member(
                     },
                 }
                 .insert(new_member.user_id.into(), &mut transaction, &redis)
-                .await?;
+                .await?
             }
         }
-    }
+    } else {
+        Vec::new()
+    };
 
     transaction.commit().await?;
     TeamMember::clear_cache(team_id, &redis).await?;
     User::clear_project_cache(&[new_member.user_id.into()], &redis).await?;
+    crate::queue::push::publish(push_events, &redis).await;
 
     Ok(HttpResponse::NoContent().body(""))
 }
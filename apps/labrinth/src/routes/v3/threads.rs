@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::auth::{check_forum_ban, get_user_from_headers};
 use crate::database;
+use crate::database::models::direct_message_item::DirectMessage;
 use crate::database::models::image_item;
 use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::thread_item::ThreadMessageBuilder;
@@ -15,8 +16,10 @@ use crate::models::projects::ProjectStatus;
 use crate::models::teams::ProjectPermissions;
 use crate::models::threads::{MessageBody, Thread, ThreadId, ThreadType};
 use crate::models::users::User;
+use crate::queue::push::PushEventBody;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
+use crate::routes::v3::messages;
 use actix_web::{HttpRequest, HttpResponse, web};
 use futures::TryStreamExt;
 use serde::Deserialize;
@@ -203,6 +206,28 @@ pub async fn is_authorized_thread(
             .await?
             .exists
         }
+        ThreadType::Refund => {
+            // 退款线程：买家或有编辑权限的项目成员可以访问
+            sqlx::query!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM payment_refunds r
+                    JOIN mods m ON m.id = r.project_id
+                    LEFT JOIN team_members tm ON tm.team_id = m.team_id
+                        AND tm.user_id = $2
+                        AND tm.accepted = TRUE
+                        AND (tm.permissions & $3) = $3
+                    WHERE r.thread_id = $1 AND (r.user_id = $2 OR tm.user_id IS NOT NULL)
+                ) as "exists!"
+                "#,
+                thread.id.0,
+                user_id.0,
+                ProjectPermissions::EDIT_DETAILS.bits() as i64,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
     })
 }
 
@@ -439,6 +464,44 @@ pub async fn filter_authorized_threads(
             .try_collect::<Vec<()>>()
             .await?;
         }
+
+        // 处理 Refund 类型的线程：通过 thread_id 反查 + 买家或项目成员可读
+        let refund_thread_ids = check_threads
+            .iter()
+            .filter(|x| x.type_ == ThreadType::Refund)
+            .map(|x| x.id.0)
+            .collect::<Vec<_>>();
+
+        if !refund_thread_ids.is_empty() {
+            sqlx::query!(
+                r#"
+                SELECT r.thread_id AS "thread_id!"
+                FROM payment_refunds r
+                JOIN mods m ON m.id = r.project_id
+                LEFT JOIN team_members tm ON tm.team_id = m.team_id
+                    AND tm.user_id = $2
+                    AND tm.accepted = TRUE
+                    AND (tm.permissions & $3) = $3
+                WHERE r.thread_id = ANY($1)
+                  AND (r.user_id = $2 OR tm.user_id IS NOT NULL)
+                "#,
+                &refund_thread_ids[..],
+                user_id.0,
+                ProjectPermissions::EDIT_DETAILS.bits() as i64,
+            )
+            .fetch(&***pool)
+            .map_ok(|row| {
+                check_threads.retain(|x| {
+                    let matched = x.id.0 == row.thread_id;
+                    if matched {
+                        return_threads.push(x.clone());
+                    }
+                    !matched
+                });
+            })
+            .try_collect::<Vec<()>>()
+            .await?;
+        }
     }
 
     let mut user_ids = return_threads
@@ -680,6 +743,39 @@ pub async fn thread_send_message(
             return Err(ApiError::NotFound);
         }
 
+        // 私信会话：仅成员可发送，且受屏蔽关系与敏感词检查约束
+        if thread.type_ == ThreadType::DirectMessage {
+            let user_id: database::models::UserId = user.id.into();
+            if !thread.members.contains(&user_id) {
+                return Err(ApiError::InvalidInput(
+                    "您不是该私信会话的成员".to_string(),
+                ));
+            }
+
+            let others = thread
+                .members
+                .iter()
+                .copied()
+                .filter(|x| *x != user_id)
+                .collect::<Vec<_>>();
+            if others.is_empty() {
+                return Err(ApiError::InvalidInput(
+                    "会话中的其他成员已退出".to_string(),
+                ));
+            }
+            if DirectMessage::is_blocked_between(user_id, &others, &**pool)
+                .await?
+            {
+                return Err(ApiError::InvalidInput(
+                    "无法向该用户发送私信".to_string(),
+                ));
+            }
+
+            if let MessageBody::Text { body, .. } = &new_message.body {
+                messages::check_message_text(body, &user, &redis).await?;
+            }
+        }
+
         let mut transaction = pool.begin().await?;
 
         let id = ThreadMessageBuilder {
@@ -690,6 +786,7 @@ pub async fn thread_send_message(
         }
         .insert(&mut transaction)
         .await?;
+        let mut push_events = Vec::new();
 
         if let Some(project_id) = thread.project_id {
             let project =
@@ -707,20 +804,22 @@ pub async fn thread_send_message(
                 )
                 .await?;
 
-                NotificationBuilder {
-                    body: NotificationBody::ModeratorMessage {
-                        thread_id: thread.id.into(),
-                        message_id: id.into(),
-                        project_id: Some(project.inner.id.into()),
-                        report_id: None,
-                    },
-                }
-                .insert_many(
-                    members.into_iter().map(|x| x.user_id).collect(),
-                    &mut transaction,
-                    &redis,
-                )
-                .await?;
+                push_events.extend(
+                    NotificationBuilder {
+                        body: NotificationBody::ModeratorMessage {
+                            thread_id: thread.id.into(),
+                            message_id: id.into(),
+                            project_id: Some(project.inner.id.into()),
+                            report_id: None,
+                        },
+                    }
+                    .insert_many(
+                        members.into_iter().map(|x| x.user_id).collect(),
+                        &mut transaction,
+                        &redis,
+                    )
+                    .await?,
+                );
             }
         } else if let Some(report_id) = thread.report_id {
             let report =
@@ -735,16 +834,18 @@ pub async fn thread_send_message(
                 }
 
                 if user.id != report.reporter.into() {
-                    NotificationBuilder {
-                        body: NotificationBody::ModeratorMessage {
-                            thread_id: thread.id.into(),
-                            message_id: id.into(),
-                            project_id: None,
-                            report_id: Some(report.id.into()),
-                        },
-                    }
-                    .insert(report.reporter, &mut transaction, &redis)
-                    .await?;
+                    push_events.extend(
+                        NotificationBuilder {
+                            body: NotificationBody::ModeratorMessage {
+                                thread_id: thread.id.into(),
+                                message_id: id.into(),
+                                project_id: None,
+                                report_id: Some(report.id.into()),
+                            },
+                        }
+                        .insert(report.reporter, &mut transaction, &redis)
+                        .await?,
+                    );
                 }
             }
         } else if let Some(ban_appeal_id) = thread.ban_appeal_id {
@@ -763,17 +864,20 @@ pub async fn thread_send_message(
                         database::models::ids::UserId(appeal.user_id);
                     // 只有当发送者不是申诉用户本人时才发送通知
                     if user.id != appeal_user_id.into() {
-                        NotificationBuilder {
-                            body: NotificationBody::BanAppealMessage {
-                                appeal_id: crate::models::v3::bans::BanAppealId(
-                                    ban_appeal_id.0 as u64,
-                                ),
-                                thread_id: thread.id.into(),
-                                message_id: id.into(),
-                            },
-                        }
-                        .insert(appeal_user_id, &mut transaction, &redis)
-                        .await?;
+                        push_events.extend(
+                            NotificationBuilder {
+                                body: NotificationBody::BanAppealMessage {
+                                    appeal_id:
+                                        crate::models::v3::bans::BanAppealId(
+                                            ban_appeal_id.0 as u64,
+                                        ),
+                                    thread_id: thread.id.into(),
+                                    message_id: id.into(),
+                                },
+                            }
+                            .insert(appeal_user_id, &mut transaction, &redis)
+                            .await?,
+                        );
                     }
                 }
             }
@@ -794,15 +898,17 @@ pub async fn thread_send_message(
                 if user.role.is_mod() {
                     // 管理员回复时：通知申请用户
                     if user.id != app_user_id.into() {
-                        NotificationBuilder {
-                            body: NotificationBody::CreatorApplicationMessage {
-                                application_id: creator_application_id.0,
-                                thread_id: thread.id.into(),
-                                message_id: id.into(),
-                            },
-                        }
-                        .insert(app_user_id, &mut transaction, &redis)
-                        .await?;
+                        push_events.extend(
+                            NotificationBuilder {
+                                body: NotificationBody::CreatorApplicationMessage {
+                                    application_id: creator_application_id.0,
+                                    thread_id: thread.id.into(),
+                                    message_id: id.into(),
+                                },
+                            }
+                            .insert(app_user_id, &mut transaction, &redis)
+                            .await?,
+                        );
                     }
                 } else {
                     // 用户回复时：通知所有管理员（限制 100 人，避免性能问题）
@@ -818,15 +924,17 @@ pub async fn thread_send_message(
                     .collect::<Vec<_>>();
 
                     if !mod_ids.is_empty() {
-                        NotificationBuilder {
-                            body: NotificationBody::CreatorApplicationMessage {
-                                application_id: creator_application_id.0,
-                                thread_id: thread.id.into(),
-                                message_id: id.into(),
-                            },
-                        }
-                        .insert_many(mod_ids, &mut transaction, &redis)
-                        .await?;
+                        push_events.extend(
+                            NotificationBuilder {
+                                body: NotificationBody::CreatorApplicationMessage {
+                                    application_id: creator_application_id.0,
+                                    thread_id: thread.id.into(),
+                                    message_id: id.into(),
+                                },
+                            }
+                            .insert_many(mod_ids, &mut transaction, &redis)
+                            .await?,
+                        );
                     }
                 }
             }
@@ -857,27 +965,47 @@ pub async fn thread_send_message(
                         crate::models::ids::base62_impl::to_base62(
                             appl.project_id as u64,
                         );
-                    NotificationBuilder {
-                        body: NotificationBody::LegacyMarkdown {
-                            notification_type: Some(
-                                "incentive_application_message".to_string(),
-                            ),
-                            name: format!(
-                                "[激励申请] 管理员回复：{project_title}"
-                            ),
-                            text:
-                                "管理员在你的激励申请中回复了消息，请前往查看。"
-                                    .to_string(),
-                            link: format!(
-                                "/project/{project_b62}/settings/incentive"
-                            ),
-                            actions: vec![],
-                        },
-                    }
-                    .insert(applicant_user_id, &mut transaction, &redis)
-                    .await?;
+                    push_events.extend(
+                        NotificationBuilder {
+                            body: NotificationBody::LegacyMarkdown {
+                                notification_type: Some(
+                                    "incentive_application_message".to_string(),
+                                ),
+                                name: format!(
+                                    "[激励申请] 管理员回复：{project_title}"
+                                ),
+                                text:
+                                    "管理员在你的激励申请中回复了消息，请前往查看。"
+                                        .to_string(),
+                                link: format!(
+                                    "/project/{project_b62}/settings/incentive"
+                                ),
+                                actions: vec![],
+                            },
+                        }
+                        .insert(applicant_user_id, &mut transaction, &redis)
+                        .await?,
+                    );
                 }
             }
+        } else if thread.type_ == ThreadType::DirectMessage {
+            push_events.extend(
+                messages::notify_direct_message(
+                    thread.id,
+                    id,
+                    &user,
+                    &thread.members,
+                    &mut transaction,
+                    &redis,
+                )
+                .await?,
+            );
+            DirectMessage::mark_read(
+                thread.id,
+                user.id.into(),
+                &mut transaction,
+            )
+            .await?;
         }
 
         if let MessageBody::Text {
@@ -929,6 +1057,29 @@ pub async fn thread_send_message(
 
         transaction.commit().await?;
 
+        // 私密消息仅管理员可见，不推送给线程成员
+        if !matches!(new_message.body, MessageBody::Text { private: true, .. })
+        {
+            let author_id: database::models::UserId = user.id.into();
+            push_events.extend(
+                thread
+                    .members
+                    .iter()
+                    .copied()
+                    .filter(|x| *x != author_id)
+                    .map(|x| {
+                        (
+                            x,
+                            PushEventBody::ThreadMessage {
+                                thread_id: thread.id.into(),
+                                message_id: id.into(),
+                            },
+                        )
+                    }),
+            );
+        }
+        crate::queue::push::publish(push_events, &redis).await;
+
         Ok(HttpResponse::NoContent().body(""))
     } else {
         Err(ApiError::NotFound)
@@ -390,6 +390,7 @@ pub async fn user_edit(
             }
             let mut transaction = pool.begin().await?;
             let mut pending_fields = Vec::new();
+            let mut push_events = Vec::new();
 
             if let Some(username) = &new_user.username {
                 let existing_user_id_option =
@@ -448,9 +449,11 @@ pub async fn user_edit(
                                 review_type: "username".to_string(),
                             },
                         };
-                        notification
-                            .insert(id, &mut transaction, &redis)
-                            .await?;
+                        push_events.extend(
+                            notification
+                                .insert(id, &mut transaction, &redis)
+                                .await?,
+                        );
 
                         pending_fields.push("username");
                     }
@@ -511,9 +514,11 @@ pub async fn user_edit(
                                 review_type: "bio".to_string(),
                             },
                         };
-                        notification
-                            .insert(id, &mut transaction, &redis)
-                            .await?;
+                        push_events.extend(
+                            notification
+                                .insert(id, &mut transaction, &redis)
+                                .await?,
+                        );
 
                         pending_fields.push("bio");
                     }
@@ -573,6 +578,7 @@ pub async fn user_edit(
             transaction.commit().await?;
             User::clear_caches(&[(id, Some(actual_user.username))], &redis)
                 .await?;
+            crate::queue::push::publish(push_events, &redis).await;
 
             if !pending_fields.is_empty() {
                 crate::routes::internal::moderation::clear_pending_counts_cache(&redis).await;
@@ -761,11 +767,12 @@ pub async fn user_icon_edit(
                     review_type: "avatar".to_string(),
                 },
             };
-            notification
+            let push_events = notification
                 .insert(actual_user.id, &mut transaction, &redis)
                 .await?;
 
             transaction.commit().await?;
+            crate::queue::push::publish(push_events, &redis).await;
 
             crate::routes::internal::moderation::clear_pending_counts_cache(
                 &redis,
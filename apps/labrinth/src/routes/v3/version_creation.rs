@@ -1,5 +1,6 @@
 use super::project_creation::{CreateError, UploadedFile};
 use crate::auth::{check_resource_ban, get_user_from_headers};
+use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
 use crate::database::models::loader_fields::{
     LoaderField, LoaderFieldEnumValue, VersionField,
 };
@@ -12,7 +13,7 @@ use crate::database::redis::RedisPool;
 use crate::file_hosting::{FileHost, S3PrivateHost};
 use crate::models::images::{Image, ImageContext, ImageId};
 use crate::models::notifications::NotificationBody;
-use crate::models::pack::PackFileHash;
+use crate::models::pack::{PackFileHash, PackFormat};
 use crate::models::pats::Scopes;
 use crate::models::projects::{
     Dependency, FileType, Loader, ProjectId, Version, VersionFile, VersionId,
@@ -20,11 +21,18 @@ use crate::models::projects::{
 };
 use crate::models::projects::{DependencyType, ProjectStatus, skip_nulls};
 use crate::models::teams::ProjectPermissions;
-use crate::queue::moderation::AutomatedModerationQueue;
+use crate::queue::moderation::{
+    AutomatedModerationQueue, ModerationMessage, ModerationMessages,
+    send_automod_message, withhold_version,
+};
+use crate::queue::push::PushEvents;
 use crate::queue::session::AuthQueue;
 use crate::util::routes::read_from_field;
 use crate::util::validate::validation_errors_to_string;
-use crate::validate::{ValidationResult, validate_file};
+use crate::validate::curseforge;
+use crate::validate::metadata::{ModMetadata, check_version_metadata};
+use crate::validate::structure::check_structure_metadata;
+use crate::validate::{FileValidation, ValidationResult, validate_file};
 use actix_multipart::{Field, Multipart};
 use actix_web::web::Data;
 use actix_web::{HttpRequest, HttpResponse, web};
@@ -95,6 +103,18 @@ pub struct InitialVersionData {
 
     pub disk_only: bool,
     pub disk_urls: Option<Vec<QueryDisk>>,
+    /// 上传 CurseForge 整合包时转换为 mrpack 保存
+    #[serde(default)]
+    pub convert_to_mrpack: bool,
+}
+
+/// 创建版本的响应，附带描述文件与填写内容不一致时的提示
+#[derive(Serialize)]
+struct VersionCreateResponse {
+    #[serde(flatten)]
+    version: Version,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metadata_warnings: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -132,23 +152,28 @@ pub async fn version_create(
     )
     .await;
 
-    if result.is_err() {
-        let undo_result = super::project_creation::undo_uploads(
-            &***file_host,
-            &uploaded_files,
-        )
-        .await;
-        let rollback_result = transaction.rollback().await;
+    let (response, push_events) = match result {
+        Ok(x) => x,
+        Err(err) => {
+            let undo_result = super::project_creation::undo_uploads(
+                &***file_host,
+                &uploaded_files,
+            )
+            .await;
+            let rollback_result = transaction.rollback().await;
 
-        undo_result?;
-        if let Err(e) = rollback_result {
-            return Err(e.into());
+            undo_result?;
+            if let Err(e) = rollback_result {
+                return Err(e.into());
+            }
+            return Err(err);
         }
-    } else {
-        transaction.commit().await?;
-    }
+    };
 
-    result
+    transaction.commit().await?;
+    crate::queue::push::publish(push_events, &redis).await;
+
+    Ok(response)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -163,7 +188,7 @@ async fn version_create_inner(
     pool: &PgPool,
     session_queue: &AuthQueue,
     moderation_queue: &AutomatedModerationQueue,
-) -> Result<HttpResponse, CreateError> {
+) -> Result<(HttpResponse, PushEvents), CreateError> {
     let cdn_url = dotenvy::var("CDN_URL")?;
 
     let mut initial_version_data = None;
@@ -171,6 +196,9 @@ async fn version_create_inner(
     let mut selected_loaders = None;
     let mut project_is_paid = false;
     let mut project_slug: Option<String> = None;
+    let mut project_thread_id = None;
+    let mut metadata_warnings = Vec::new();
+    let mut suspicious_code = Vec::new();
 
     let user = get_user_from_headers(
         &req,
@@ -245,6 +273,7 @@ async fn version_create_inner(
                 // 保存项目的付费状态，用于后续设置文件的 is_private
                 project_is_paid = project.inner.is_paid;
                 project_slug = project.inner.slug.clone();
+                project_thread_id = Some(project.thread_id);
 
                 // 检查创建此版本的用户是否是项目团队成员
                 // 项目版本正在添加。
@@ -500,10 +529,13 @@ async fn version_create_inner(
                 uploaded_files,
                 &mut version.files,
                 &mut version.dependencies,
+                &mut metadata_warnings,
+                &mut suspicious_code,
                 &cdn_url,
                 &content_disposition,
                 version.project_id.into(),
                 version.version_id.into(),
+                &version.version_number,
                 &version.version_fields,
                 loaders,
                 version_data.primary_file.is_some(),
@@ -514,6 +546,7 @@ async fn version_create_inner(
                 redis,
                 user.username.clone(),
                 project_is_paid,
+                version_data.convert_to_mrpack,
             )
             .await?;
 
@@ -533,7 +566,7 @@ async fn version_create_inner(
     let version_data = initial_version_data.ok_or_else(|| {
         CreateError::InvalidInput("`data` field 是必需的".to_string())
     })?;
-    let builder = version_builder.ok_or_else(|| {
+    let mut builder = version_builder.ok_or_else(|| {
         CreateError::InvalidInput("`data` field 是必需的".to_string())
     })?;
 
@@ -546,6 +579,33 @@ async fn version_create_inner(
     if version_data.disk_only && version_data.disk_urls.is_none() {
         return Err(CreateError::InvalidInput("未填写网盘地址".to_string()));
     }
+
+    // 包含可疑代码的版本暂缓发布，等待审核员复核
+    if suspicious_code.iter().any(|x| x.withholds_version()) {
+        builder.requested_status = Some(builder.status);
+        builder.status = VersionStatus::Draft;
+        metadata_warnings.push(
+            "版本中检测到可疑代码，已暂缓发布，审核员复核后会恢复为所选的状态"
+                .to_string(),
+        );
+    }
+    if let Some(thread_id) = project_thread_id
+        && !suspicious_code.is_empty()
+    {
+        send_automod_message(
+            thread_id,
+            &ModerationMessages {
+                messages: vec![],
+                version_specific: HashMap::from([(
+                    builder.version_number.clone(),
+                    suspicious_code,
+                )]),
+            },
+            transaction,
+        )
+        .await?;
+    }
+
     use futures::stream::TryStreamExt;
 
     let users = sqlx::query!(
@@ -564,7 +624,7 @@ async fn version_create_inner(
     let project_id: ProjectId = builder.project_id.into();
     let version_id: VersionId = builder.version_id.into();
 
-    NotificationBuilder {
+    let push_events = NotificationBuilder {
         body: NotificationBody::ProjectUpdate {
             project_id,
             version_id,
@@ -602,6 +662,7 @@ async fn version_create_inner(
             primary: file.primary,
             size: file.size,
             file_type: file.file_type,
+            structure_metadata: file.structure_metadata.clone(),
         })
         .collect::<Vec<_>>();
     let disk_urls: Vec<QueryDisk> =
@@ -619,6 +680,7 @@ async fn version_create_inner(
             primary: false,
             size: 0,
             file_type: None,
+            structure_metadata: None,
         });
     }
 
@@ -662,6 +724,8 @@ async fn version_create_inner(
 
     let project_id = builder.project_id;
     builder.insert(transaction).await?;
+    models::SearchIndexQueueEntry::enqueue(&[project_id], &mut **transaction)
+        .await?;
 
     // 清除版本链接目标版本的缓存（新建版本时）
     for target_version_id in target_version_ids_to_clear {
@@ -737,7 +801,13 @@ async fn version_create_inner(
         );
     }
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok((
+        HttpResponse::Ok().json(VersionCreateResponse {
+            version: response,
+            metadata_warnings,
+        }),
+        push_events,
+    ))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -851,6 +921,7 @@ async fn upload_file_to_version_inner(
     .ok_or_else(|| CreateError::InvalidInput("提供的项目id无效".to_string()))?;
 
     let project_is_paid = project.inner.is_paid;
+    let mut suspicious_code = Vec::new();
 
     if !user.role.is_admin() {
         let team_member = models::TeamMember::get_from_user_id_project(
@@ -955,10 +1026,13 @@ async fn upload_file_to_version_inner(
                 uploaded_files,
                 &mut file_builders,
                 &mut dependencies,
+                &mut Vec::new(),
+                &mut suspicious_code,
                 &cdn_url,
                 &content_disposition,
                 project_id,
                 version_id.into(),
+                &version.inner.version_number,
                 &version.version_fields,
                 loaders,
                 true,
@@ -969,6 +1043,7 @@ async fn upload_file_to_version_inner(
                 &redis,
                 user.username.clone(),
                 project_is_paid,
+                false,
             )
             .await?;
 
@@ -995,6 +1070,34 @@ async fn upload_file_to_version_inner(
         }
     }
 
+    if !suspicious_code.is_empty() {
+        let withheld = version.inner.status == VersionStatus::Draft
+            && version.inner.requested_status.is_some();
+        if !withheld && suspicious_code.iter().any(|x| x.withholds_version()) {
+            withhold_version(version_id, &mut **transaction).await?;
+            models::Project::clear_cache(
+                version.inner.project_id,
+                None,
+                Some(true),
+                &redis,
+            )
+            .await?;
+        }
+
+        send_automod_message(
+            project.thread_id,
+            &ModerationMessages {
+                messages: vec![],
+                version_specific: HashMap::from([(
+                    version.inner.version_number.clone(),
+                    suspicious_code,
+                )]),
+            },
+            transaction,
+        )
+        .await?;
+    }
+
     // 清除版本缓存
     models::Version::clear_cache(&version, &redis).await?;
 
@@ -1011,10 +1114,13 @@ pub async fn upload_file(
     uploaded_files: &mut Vec<UploadedFile>,
     version_files: &mut Vec<VersionFileBuilder>,
     dependencies: &mut Vec<DependencyBuilder>,
+    metadata_warnings: &mut Vec<String>,
+    suspicious_code: &mut Vec<ModerationMessage>,
     cdn_url: &str,
     content_disposition: &actix_web::http::header::ContentDisposition,
     project_id: ProjectId,
     version_id: VersionId,
+    version_number: &str,
     version_fields: &[VersionField],
     loaders: Vec<Loader>,
     ignore_primary: bool,
@@ -1025,6 +1131,7 @@ pub async fn upload_file(
     redis: &RedisPool,
     username: String,
     is_paid_project: bool,
+    convert_to_mrpack: bool,
 ) -> Result<(), CreateError> {
     let (file_name, file_extension) = get_name_ext(content_disposition)?;
 
@@ -1045,11 +1152,30 @@ pub async fn upload_file(
             CreateError::InvalidFileType(file_extension.to_string())
         })?;
 
-    let data = read_from_field(
+    let mut data = read_from_field(
         field, 1024 * (1 << 20),
         "项目文件超出了 1GB 的上限。请联系版主或管理员以请求上传更大文件的权限。"
     ).await?;
 
+    // 作者选择转换时，CurseForge 整合包以 mrpack 的形式保存
+    let converted_name = if convert_to_mrpack && file_extension == "zip" {
+        match curseforge::convert_to_mrpack(data.clone().freeze()).await? {
+            Some(converted) => {
+                data = bytes::BytesMut::from(&converted[..]);
+                Some(format!("{}.mrpack", file_name.trim_end_matches(".zip")))
+            }
+            None => None,
+        }
+    } else {
+        None
+    };
+    let (file_name, file_extension) = match &converted_name {
+        Some(name) => (name.as_str(), "mrpack"),
+        None => (file_name, file_extension),
+    };
+    let content_type = crate::util::ext::project_file_type(file_extension)
+        .unwrap_or(content_type);
+
     let hash = format!("{:x}", sha1::Sha1::digest(&data));
     let exists = sqlx::query!(
         "
@@ -1073,7 +1199,12 @@ pub async fn upload_file(
         ));
     }
 
-    let validation_result = validate_file(
+    let FileValidation {
+        result: validation_result,
+        metadata,
+        structure,
+        findings,
+    } = validate_file(
         data.clone().into(),
         file_extension.to_string(),
         loaders.clone(),
@@ -1084,70 +1215,18 @@ pub async fn upload_file(
     )
     .await?;
 
-    if let ValidationResult::PassWithPackDataAndFiles {
-        ref format,
-        ref files,
-    } = validation_result
-        && dependencies.is_empty()
-    {
-        let hashes: Vec<Vec<u8>> = format
-            .files
-            .iter()
-            .filter_map(|x| x.hashes.get(&PackFileHash::Sha1))
-            .map(|x| x.as_bytes().to_vec())
-            .collect();
-
-        let res = sqlx::query!(
-                "
-                    SELECT v.id version_id, v.mod_id project_id, h.hash hash FROM hashes h
-                    INNER JOIN files f on h.file_id = f.id
-                    INNER JOIN versions v on f.version_id = v.id
-                    WHERE h.algorithm = 'sha1' AND h.hash = ANY($1)
-                    ",
-                &*hashes
-            )
-            .fetch_all(&mut **transaction)
-            .await?;
-
-        for file in &format.files {
-            if let Some(dep) = res.iter().find(|x| {
-                Some(&*x.hash)
-                    == file
-                        .hashes
-                        .get(&PackFileHash::Sha1)
-                        .map(|x| x.as_bytes())
-            }) {
-                dependencies.push(DependencyBuilder {
-                    project_id: Some(models::ProjectId(dep.project_id)),
-                    version_id: Some(models::VersionId(dep.version_id)),
-                    file_name: None,
-                    dependency_type: DependencyType::Embedded.to_string(),
-                });
-            } else if let Some(first_download) = file.downloads.first() {
-                dependencies.push(DependencyBuilder {
-                    project_id: None,
-                    version_id: None,
-                    file_name: Some(
-                        first_download
-                            .rsplit('/')
-                            .next()
-                            .unwrap_or(first_download)
-                            .to_string(),
-                    ),
-                    dependency_type: DependencyType::Embedded.to_string(),
-                });
+    if dependencies.is_empty() {
+        match &validation_result {
+            ValidationResult::PassWithPackDataAndFiles { format, files } => {
+                add_pack_dependencies(format, files, dependencies, transaction)
+                    .await?;
             }
-        }
-
-        for file in files {
-            if !file.is_empty() {
-                dependencies.push(DependencyBuilder {
-                    project_id: None,
-                    version_id: None,
-                    file_name: Some(file.to_string()),
-                    dependency_type: DependencyType::Embedded.to_string(),
-                });
+            // 清单中引用的 CurseForge 文件无法对应到站内项目，只匹配直接打包的文件
+            ValidationResult::PassWithCurseForgeData { format, .. } => {
+                add_pack_dependencies(format, &[], dependencies, transaction)
+                    .await?;
             }
+            _ => {}
         }
     }
 
@@ -1216,6 +1295,47 @@ pub async fn upload_file(
         return Err(CreateError::InvalidInput(msg.to_string()));
     }
 
+    if let Some(findings) = findings.as_ref().filter(|x| !x.is_empty()) {
+        suspicious_code.push(ModerationMessage::SuspiciousCode {
+            file_name: file_name.to_string(),
+            findings: findings.clone(),
+        });
+    }
+
+    if primary && (!metadata.is_empty() || structure.is_some()) {
+        let game_versions = version_fields
+            .iter()
+            .find_map(|x| MinecraftGameVersion::try_from_version_field(x).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.version)
+            .collect::<Vec<_>>();
+        let environment = version_fields
+            .iter()
+            .find(|x| x.field_name == "environment")
+            .and_then(|x| {
+                x.value.serialize_internal().as_str().map(String::from)
+            });
+
+        metadata_warnings.extend(check_version_metadata(
+            &metadata,
+            version_number,
+            &loaders.iter().map(|x| x.0.clone()).collect::<Vec<_>>(),
+            &game_versions,
+            environment.as_deref(),
+        ));
+
+        if let Some(structure) = &structure {
+            metadata_warnings
+                .extend(check_structure_metadata(structure, &game_versions));
+        }
+
+        if dependencies.is_empty() {
+            fill_dependencies(&metadata, project_id, dependencies, transaction)
+                .await?;
+        }
+    }
+
     version_files.push(VersionFileBuilder {
         filename: file_name.to_string(),
         url: file_url,
@@ -1235,11 +1355,142 @@ pub async fn upload_file(
         size: upload_data.content_length,
         file_type,
         is_private: use_private,
+        structure_metadata: structure,
+        malware_findings: findings,
     });
 
     Ok(())
 }
 
+/// 整合包中能在站内找到的文件记为内嵌的项目版本，其余的按文件名记录
+async fn add_pack_dependencies(
+    format: &PackFormat,
+    files: &[String],
+    dependencies: &mut Vec<DependencyBuilder>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), CreateError> {
+    let hashes: Vec<Vec<u8>> = format
+        .files
+        .iter()
+        .filter_map(|x| x.hashes.get(&PackFileHash::Sha1))
+        .map(|x| x.as_bytes().to_vec())
+        .collect();
+
+    let res = sqlx::query!(
+        "
+        SELECT v.id version_id, v.mod_id project_id, h.hash hash FROM hashes h
+        INNER JOIN files f on h.file_id = f.id
+        INNER JOIN versions v on f.version_id = v.id
+        WHERE h.algorithm = 'sha1' AND h.hash = ANY($1)
+        ",
+        &*hashes
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    for file in &format.files {
+        if let Some(dep) = res.iter().find(|x| {
+            Some(&*x.hash)
+                == file.hashes.get(&PackFileHash::Sha1).map(|x| x.as_bytes())
+        }) {
+            dependencies.push(DependencyBuilder {
+                project_id: Some(models::ProjectId(dep.project_id)),
+                version_id: Some(models::VersionId(dep.version_id)),
+                file_name: None,
+                dependency_type: DependencyType::Embedded.to_string(),
+            });
+        } else {
+            // CurseForge 整合包中直接打包的文件没有下载地址，使用路径中的文件名
+            let source = file
+                .downloads
+                .first()
+                .map(String::as_str)
+                .unwrap_or(file.path.as_str());
+            dependencies.push(DependencyBuilder {
+                project_id: None,
+                version_id: None,
+                file_name: Some(
+                    source.rsplit('/').next().unwrap_or(source).to_string(),
+                ),
+                dependency_type: DependencyType::Embedded.to_string(),
+            });
+        }
+    }
+
+    for file in files {
+        if !file.is_empty() {
+            dependencies.push(DependencyBuilder {
+                project_id: None,
+                version_id: None,
+                file_name: Some(file.to_string()),
+                dependency_type: DependencyType::Embedded.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// 作者未填写依赖时，按描述文件声明的模组 ID 匹配站内项目的 slug 补全依赖
+async fn fill_dependencies(
+    metadata: &[ModMetadata],
+    project_id: ProjectId,
+    dependencies: &mut Vec<DependencyBuilder>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), CreateError> {
+    // 模组 ID 通常使用下划线，slug 使用连字符；过短的 ID 容易误配，不参与匹配
+    let candidates = |mod_id: &str| {
+        let mod_id = mod_id.to_lowercase();
+        let slug = mod_id.replace('_', "-");
+        [mod_id, slug].into_iter().filter(|x| x.len() >= 3)
+    };
+
+    let slugs = metadata
+        .iter()
+        .flat_map(|x| &x.dependencies)
+        .flat_map(|x| candidates(&x.mod_id))
+        .unique()
+        .collect::<Vec<_>>();
+    if slugs.is_empty() {
+        return Ok(());
+    }
+
+    let projects = sqlx::query!(
+        "
+        SELECT id, LOWER(slug) slug FROM mods
+        WHERE LOWER(slug) = ANY($1) AND id != $2
+        ",
+        &slugs,
+        project_id.0 as i64,
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    for dependency in metadata.iter().flat_map(|x| &x.dependencies) {
+        let Some(project) = projects.iter().find(|project| {
+            candidates(&dependency.mod_id)
+                .any(|x| project.slug.as_deref() == Some(x.as_str()))
+        }) else {
+            continue;
+        };
+        let project_id = models::ProjectId(project.id);
+        if dependencies
+            .iter()
+            .any(|x| x.project_id == Some(project_id))
+        {
+            continue;
+        }
+        dependencies.push(DependencyBuilder {
+            project_id: Some(project_id),
+            version_id: None,
+            file_name: None,
+            dependency_type: dependency.dependency_type.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
 pub fn get_name_ext(
     content_disposition: &actix_web::http::header::ContentDisposition,
 ) -> Result<(&str, &str), CreateError> {
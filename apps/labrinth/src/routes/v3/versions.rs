@@ -14,7 +14,7 @@ use crate::database::models::version_item::{
 use crate::database::models::{Organization, image_item};
 use crate::database::redis::RedisPool;
 use crate::models;
-use crate::models::analytics::Download;
+use crate::models::analytics::{Download, new_event_id};
 use crate::models::ids::base62_impl::parse_base62;
 use crate::models::ids::{ProjectId, VersionId};
 use crate::models::images::ImageContext;
@@ -31,8 +31,9 @@ use crate::search::SearchConfig;
 use crate::search::indexing::remove_documents;
 use crate::util::date::get_current_tenths_of_ms;
 use crate::util::img;
+use crate::util::ratelimit::{RateLimit, UPLOAD};
 use crate::util::validate::validation_errors_to_string;
-use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, guard, web};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -44,9 +45,11 @@ use validator::Validate;
 pub mod version_link_thread;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.route(
-        "version",
-        web::post().to(super::version_creation::version_create),
+    cfg.service(
+        web::resource("version")
+            .guard(guard::Post())
+            .wrap(RateLimit(&UPLOAD))
+            .route(web::post().to(super::version_creation::version_create)),
     );
     cfg.route("versions", web::get().to(versions_get));
 
@@ -56,9 +59,15 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("{id}", web::patch().to(version_edit))
             .route("{id}/download", web::patch().to(version_download))
             .route("{id}", web::delete().to(version_delete))
-            .route(
-                "{version_id}/file",
-                web::post().to(super::version_creation::upload_file_to_version),
+            .service(
+                web::resource("{version_id}/file")
+                    .guard(guard::Post())
+                    .wrap(RateLimit(&UPLOAD))
+                    .route(
+                        web::post().to(
+                            super::version_creation::upload_file_to_version,
+                        ),
+                    ),
             )
             .route(
                 "{version_id}/link/{target_version_id}/approve",
@@ -350,27 +359,27 @@ pub async fn version_download(
             let url = url::Url::parse(&url).map_err(|_| {
                 ApiError::InvalidInput("无效的下载URL!".to_string())
             })?;
-            analytics_queue.add_download(Download {
-                recorded: get_current_tenths_of_ms(),
-                domain: url.host_str().unwrap_or_default().to_string(),
-                site_path: url.path().to_string(),
-                user_id,
-                project_id: id.0,
-                version_id: version_id.0,
-                ip,
-                country: "".to_string(),
-                user_agent: headers
-                    .get("user-agent")
-                    .cloned()
-                    .unwrap_or_default(),
-                headers: Vec::new(),
-            });
-            incentive_queue.add(
-                id.0,
-                user_id,
-                ip,
-                chrono::Utc::now().timestamp(),
-            );
+            analytics_queue
+                .add_download(Download {
+                    event_id: new_event_id(),
+                    recorded: get_current_tenths_of_ms(),
+                    domain: url.host_str().unwrap_or_default().to_string(),
+                    site_path: url.path().to_string(),
+                    user_id,
+                    project_id: id.0,
+                    version_id: version_id.0,
+                    ip,
+                    country: "".to_string(),
+                    user_agent: headers
+                        .get("user-agent")
+                        .cloned()
+                        .unwrap_or_default(),
+                    headers: Vec::new(),
+                })
+                .await;
+            incentive_queue
+                .add(id.0, user_id, ip, chrono::Utc::now().timestamp())
+                .await;
         } else {
             let url = version_item.disks.first().unwrap().url.clone();
 
@@ -378,27 +387,27 @@ pub async fn version_download(
                 ApiError::InvalidInput("无效的下载URL!".to_string())
             })?;
 
-            analytics_queue.add_download(Download {
-                recorded: get_current_tenths_of_ms(),
-                domain: url.host_str().unwrap_or_default().to_string(),
-                site_path: url.path().to_string(),
-                user_id,
-                project_id: id.0,
-                version_id: version_id.0,
-                ip,
-                country: "".to_string(),
-                user_agent: headers
-                    .get("user-agent")
-                    .cloned()
-                    .unwrap_or_default(),
-                headers: Vec::new(),
-            });
-            incentive_queue.add(
-                id.0,
-                user_id,
-                ip,
-                chrono::Utc::now().timestamp(),
-            );
+            analytics_queue
+                .add_download(Download {
+                    event_id: new_event_id(),
+                    recorded: get_current_tenths_of_ms(),
+                    domain: url.host_str().unwrap_or_default().to_string(),
+                    site_path: url.path().to_string(),
+                    user_id,
+                    project_id: id.0,
+                    version_id: version_id.0,
+                    ip,
+                    country: "".to_string(),
+                    user_agent: headers
+                        .get("user-agent")
+                        .cloned()
+                        .unwrap_or_default(),
+                    headers: Vec::new(),
+                })
+                .await;
+            incentive_queue
+                .add(id.0, user_id, ip, chrono::Utc::now().timestamp())
+                .await;
         }
         Ok(HttpResponse::NoContent().body(""))
     } else {
@@ -1034,10 +1043,21 @@ pub async fn version_edit_helper(
                     ));
                 }
 
+                // 因可疑代码暂缓发布的版本只能由审核员恢复
+                if version_item.inner.status == VersionStatus::Draft
+                    && version_item.inner.requested_status.is_some()
+                    && !user.role.is_mod()
+                {
+                    return Err(ApiError::CustomAuthentication(
+                        "此版本中检测到可疑代码，需等待审核员复核后才能更改状态！"
+                            .to_string(),
+                    ));
+                }
+
                 sqlx::query!(
                     "
                     UPDATE versions
-                    SET status = $1
+                    SET status = $1, requested_status = NULL
                     WHERE (id = $2)
                     ",
                     status.as_str(),
@@ -1142,6 +1162,12 @@ pub async fn version_edit_helper(
             )
             .await?;
 
+            database::models::SearchIndexQueueEntry::enqueue(
+                &[version_item.inner.project_id],
+                &mut *transaction,
+            )
+            .await?;
+
             transaction.commit().await?;
             database::models::Version::clear_cache(&version_item, &redis)
                 .await?;
@@ -1420,6 +1446,11 @@ pub async fn version_delete(
         &mut transaction,
     )
     .await?;
+    database::models::SearchIndexQueueEntry::enqueue(
+        &[version.inner.project_id],
+        &mut *transaction,
+    )
+    .await?;
     transaction.commit().await?;
 
     database::models::Project::clear_cache(
@@ -1431,7 +1462,7 @@ pub async fn version_delete(
     .await?;
     // Modrinth 上游修复 97e4d8e13: 确保版本在路由执行结束前从搜索索引中删除
     // 将搜索索引删除移到缓存清理之后，确保任务完成后再返回响应
-    remove_documents(&[version.inner.id.into()], &search_config).await?;
+    remove_documents(&[version.inner.id.into()], &search_config, &pool).await?;
 
     if result.is_some() {
         Ok(HttpResponse::NoContent().body(""))
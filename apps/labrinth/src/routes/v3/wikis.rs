@@ -3,10 +3,16 @@ use crate::auth::{AuthenticationError, get_user_from_headers};
 use crate::database;
 use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::user_purchase_item::UserPurchase;
+use crate::database::models::wiki_cache_item::{
+    WIKI_OVERTAKE_LIMIT, WikiConflict,
+};
 use crate::database::models::wiki_item::{WikiDisplays, Wikis};
+use crate::database::models::wiki_revision_item::{
+    WikiRevisionAction, WikiRevisionContext, WikiRevisionSummary,
+};
 use crate::database::models::{
-    User, UserId, Wiki, WikiCache, WikiCacheId, WikiId, generate_wiki_cache_id,
-    generate_wiki_id, user_item,
+    UserId, Wiki, WikiCache, WikiCacheId, WikiId, WikiRevision,
+    generate_wiki_cache_id, generate_wiki_id,
 };
 use crate::database::redis::RedisPool;
 use crate::models::ids::ProjectId;
@@ -16,6 +22,11 @@ use crate::models::teams::ProjectPermissions;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
 use crate::routes::v3::users::user_get_;
+use crate::search::SearchConfig;
+use crate::search::indexing::content::{ContentTarget, update_content_index};
+use crate::util::diff::{
+    DiffLine, diff_lines, diff_stats, merge3, unified_diff,
+};
 use crate::util::validate::validation_errors_to_string;
 use actix_web::{HttpRequest, HttpResponse, web};
 use chrono::Utc;
@@ -63,6 +74,18 @@ pub struct EditWiki {
     pub sort_order: i32,
 }
 
+#[derive(Deserialize)]
+pub struct WikiEditStartQuery {
+    /// 要编辑的页面
+    pub wiki_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct WikiCacheQuery {
+    /// 要审核的草稿，不传时取最早提交的待审核编辑
+    pub id: Option<WikiCacheId>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct WikiDelete {
     pub id: i64,
@@ -112,9 +135,11 @@ pub async fn wiki_delete(
         {
             return Err(ApiError::NotFound);
         }
+        let wiki_delete: WikiDelete = serde_json::from_slice(bytes.as_ref())?;
         let wiki_cache = database::models::WikiCache::get_draft(
             project.inner.id,
             UserId::from(user_option.as_ref().unwrap().id),
+            WikiId(wiki_delete.id),
             &**pool,
         )
         .await?;
@@ -122,9 +147,44 @@ pub async fn wiki_delete(
             return Err(ApiError::NotFound);
         }
         let mut cache = wiki_cache.unwrap();
-        let cache_json = cache.cache.as_array_mut().unwrap();
-        let wiki_delete: WikiDelete = serde_json::from_slice(bytes.as_ref())?;
 
+        if cache.wiki_id.is_some() {
+            // 页面草稿：删除页面时连同其子页面，子页面也记入草稿的基线，
+            // 合并时他人在此之后新增的子页面会作为冲突报告
+            let children = database::models::Wiki::get_many(
+                &project.wikis,
+                false,
+                &**pool,
+                &redis,
+            )
+            .await?
+            .into_iter()
+            .filter(|x| {
+                x.parent_wiki_id.0 == wiki_delete.id && x.id != x.parent_wiki_id
+            })
+            .collect::<Vec<_>>();
+            let old = cache.old.as_array_mut().unwrap();
+            for child in &children {
+                if !old.iter().any(|x| x["id"] == child.id.0) {
+                    old.push(serde_json::json!(child));
+                }
+            }
+            let wiki_ids = old
+                .iter()
+                .filter_map(|x| x["id"].as_i64().map(WikiId))
+                .collect::<Vec<_>>();
+            cache.base_revisions =
+                base_revisions_json(project.inner.id, Some(&wiki_ids), &**pool)
+                    .await?;
+            cache.cache = serde_json::json!([]);
+
+            let mut transaction = pool.begin().await?;
+            cache.update_cache(&mut transaction).await?;
+            transaction.commit().await?;
+            return Ok(HttpResponse::Ok().finish());
+        }
+
+        let cache_json = cache.cache.as_array_mut().unwrap();
         for i in 0..cache_json.len() {
             if cache_json[i]["id"] == wiki_delete.id {
                 cache_json.remove(i);
@@ -200,6 +260,7 @@ pub async fn wiki_edit(
         let wiki_cache = database::models::WikiCache::get_draft(
             project.inner.id,
             UserId::from(user_option.as_ref().unwrap().id),
+            WikiId(new_wiki.id),
             &**pool,
         )
         .await?;
@@ -281,18 +342,20 @@ pub async fn wiki_star(
         {
             return Err(ApiError::NotFound);
         }
+        let new_wiki: WikiStar = serde_json::from_slice(bytes.as_ref())?;
         let wiki_cache = database::models::WikiCache::get_draft(
             project.inner.id,
             UserId::from(user_option.as_ref().unwrap().id),
+            WikiId(new_wiki.id),
             &**pool,
         )
         .await?;
         if wiki_cache.is_none() {
             return Err(ApiError::NotFound);
         }
+        // 页面草稿只设置本页为推荐，通过时再取消其他页面的推荐
         let mut cache = wiki_cache.unwrap();
         let cache_json = cache.cache.as_array_mut().unwrap();
-        let new_wiki: WikiStar = serde_json::from_slice(bytes.as_ref())?;
 
         for i in cache_json {
             if i["id"] == new_wiki.id {
@@ -358,90 +421,93 @@ pub async fn wiki_create(
                 AuthenticationError::InvalidCredentials,
             ));
         }
-
-        // 检查论坛封禁
-        check_forum_ban(user_option.as_ref().unwrap(), &**pool).await?;
-
         if !is_visible_project(&project.inner, &user_option, &pool, false)
             .await?
         {
             return Err(ApiError::NotFound);
         }
-        let wiki_cache = database::models::WikiCache::get_draft(
-            project.inner.id,
-            UserId::from(user_option.as_ref().unwrap().id),
+        let user = user_option.as_ref().unwrap();
+        check_wiki_edit_access(&project, user, &**pool).await?;
+
+        let mut wikis = database::models::Wiki::get_many(
+            &project.wikis,
+            false,
             &**pool,
+            &redis,
         )
-        .await?;
-        if wiki_cache.is_none() {
-            return Err(ApiError::NotFound);
+        .await?
+        .into_iter()
+        .collect::<Vec<_>>();
+
+        let father_id = new_wiki.father_id.map(WikiId);
+        if let Some(father_id) = father_id
+            && !wikis
+                .iter()
+                .any(|x| x.id == father_id && x.id == x.parent_wiki_id)
+        {
+            return Err(ApiError::InvalidInput("父页面不存在".to_string()));
         }
 
         let mut transaction = pool.begin().await?;
 
         let wiki_id = generate_wiki_id(&mut transaction).await?;
-        let father_id = new_wiki.father_id;
 
-        let mut wiki = Wiki {
+        // 新页面在草稿通过前不会发布
+        let wiki = Wiki {
             id: wiki_id,
             project_id: project.inner.id,
             sort_order: new_wiki.sort_order,
             title: new_wiki.title.clone(),
             body: "".to_string(),
-            parent_wiki_id: wiki_id,
+            parent_wiki_id: father_id.unwrap_or(wiki_id),
             featured: false,
             created: Default::default(),
             updated: Default::default(),
             slug: new_wiki.slug.clone(),
-        };
-        if father_id.is_some() {
-            wiki.parent_wiki_id = WikiId(father_id.unwrap());
         }
-        let wiki_ = wiki.insert(&mut transaction).await?;
+        .insert(&mut transaction)
+        .await?;
 
-        let mut cache = wiki_cache.unwrap();
-        let cache_json = cache.cache.as_array_mut().unwrap();
-        if wiki_.parent_wiki_id == wiki.id {
-            cache_json.push(serde_json::json!(wiki_));
-        } else {
-            for i in &mut *cache_json {
-                if i["id"] == wiki_.parent_wiki_id.0 {
-                    if i["child"].is_null() {
-                        i["child"] = serde_json::json!([]);
-                    }
-                    i["child"]
-                        .as_array_mut()
-                        .unwrap()
-                        .push(serde_json::json!(wiki_));
-                    break;
-                }
-            }
+        let wiki_cache_id = generate_wiki_cache_id(&mut transaction).await?;
+        let cache = WikiCache {
+            id: wiki_cache_id,
+            project_id: project.inner.id,
+            user_id: UserId::from(user.id),
+            created: Default::default(),
+            status: "".to_string(),
+            cache: serde_json::json!([wiki]),
+            old: serde_json::json!([]),
+            message: serde_json::json!([]),
+            again_count: 0,
+            again_time: Default::default(),
+            base_revisions: serde_json::json!({}),
+            conflicts: serde_json::json!([]),
+            wiki_id: Some(wiki.id),
         }
-        cache.cache = cache_json.clone().into();
-        // println!("{:?}",cache);
-        cache = cache.update_cache(&mut transaction).await?;
+        .insert(&mut transaction)
+        .await?;
 
         transaction.commit().await?;
 
-        let mut wikis = database::models::Wiki::get_many(
-            &project.wikis,
-            false,
+        let drafts = database::models::WikiCache::get_user_actives(
+            project.inner.id,
+            UserId::from(user.id),
             &**pool,
-            &redis,
         )
-        .await?
-        .into_iter()
-        .collect::<Vec<_>>();
+        .await?;
+
         wikis.sort_by_key(|x| x.sort_order);
         let wikis_array = wiki_format(wikis);
         let wikis = Wikis {
             wikis: wikis_array,
             is_editor: true,
             cache: Option::from(cache),
+            drafts,
             is_editor_user: true,
             editor_user: user_option,
             is_visitors: false,
             requires_purchase: false,
+            reviews: vec![],
         };
 
         Ok(HttpResponse::Ok().json(wikis))
@@ -453,6 +519,7 @@ pub async fn wiki_create(
 pub async fn wiki_edit_start(
     req: HttpRequest,
     info: web::Path<(String,)>,
+    query: web::Query<WikiEditStartQuery>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
@@ -476,128 +543,70 @@ pub async fn wiki_edit_start(
                 AuthenticationError::InvalidCredentials,
             ));
         }
-        // 检查用户是否被论坛类封禁
-        let user = user_option.as_ref().unwrap();
-        check_forum_ban(user, &**pool).await?;
-
-        if Utc::now() < user_option.as_ref().unwrap().wiki_ban_time {
-            return Err(ApiError::WikiBan(
-                user_option
-                    .as_ref()
-                    .unwrap()
-                    .wiki_ban_time
-                    .with_timezone(&crate::util::date::app_tz())
-                    .format("%Y-%m-%d %H:%M:%S")
-                    .to_string(),
-            ));
-        }
         if !is_visible_project(&project.inner, &user_option, &pool, false)
             .await?
         {
             return Err(ApiError::NotFound);
         }
-        // let wiki_cache = database::models::WikiCache::get_draft(
-        //     project.inner.id,
-        //     UserId::from(user_option.as_ref().unwrap().id),
-        //     &**pool,
-        // )
-        // .await?;
-        // if wiki_cache.is_some() {
-        //     return Ok(HttpResponse::Ok().json(wiki_cache.unwrap()));
-        //     // return return Err(ApiError::ISExists);
-        // }
-
-        let has_draft_or_review =
-            database::models::WikiCache::get_has_draft_or_review(
-                project.inner.id,
-                &**pool,
-            )
-            .await?;
+        let user = user_option.as_ref().unwrap();
+        check_wiki_edit_access(&project, user, &**pool).await?;
 
-        if has_draft_or_review.is_some() {
-            let user = user_item::User::get_id(
-                has_draft_or_review.unwrap().user_id,
-                &**pool,
-                &redis,
-            )
-            .await?;
-            return Err(ApiError::ISConflict(user.unwrap().username));
+        let wiki_id = WikiId(query.wiki_id);
+        if !project.wikis.contains(&wiki_id) {
+            return Err(ApiError::NotFound);
         }
-        let (team_member, organization_team_member) =
-            crate::database::models::TeamMember::get_for_project_permissions(
-                &project.inner,
-                UserId::from(user_option.as_ref().unwrap().id),
-                &**pool,
-            )
-            .await?;
-
-        let permissions = ProjectPermissions::get_permissions_by_role(
-            &user_option.as_ref().unwrap().role,
-            &team_member,
-            &organization_team_member,
-        );
 
-        if permissions.is_none() && !project.inner.wiki_open {
+        // 草稿按页面划分，不同页面的草稿互不阻塞；该页面已有草稿时直接继续编辑
+        if let Some(active) = database::models::WikiCache::get_user_active(
+            project.inner.id,
+            UserId::from(user.id),
+            wiki_id,
+            &**pool,
+        )
+        .await?
+        {
+            if active.status == "draft" {
+                return Ok(HttpResponse::Ok().json(active));
+            }
             return Err(ApiError::Validation(
-                "你没有权限编辑百科页面".to_string(),
+                "您已有该页面待审核的修改，请等待审核完成后再发起新的编辑"
+                    .to_string(),
             ));
         }
 
-        if permissions.is_some() {
-            let perms = permissions.unwrap();
-            if !perms.contains(ProjectPermissions::WIKI_EDIT)
-                && !project.inner.wiki_open
-            {
-                return Err(ApiError::Validation(
-                    "你没有权限编辑百科页面".to_string(),
-                ));
-            }
-        }
-
-        // 付费资源检查：未购买用户不能编辑百科
-        if project.inner.is_paid {
-            let has_access = check_wiki_paid_access(
-                user_option.as_ref().unwrap(),
-                &project.inner,
-                &**pool,
-            )
-            .await?;
-            if !has_access {
-                return Err(ApiError::Validation(
-                    "您需要购买此资源后才能编辑百科".to_string(),
-                ));
-            }
-        }
-
-        let mut transaction = pool.begin().await?;
-
-        let id = user_option.unwrap().id;
-
-        let wiki_cache_id = generate_wiki_cache_id(&mut transaction).await?;
-
-        let mut wikis = database::models::Wiki::get_many(
-            &project.wikis,
+        let Some(wiki) = database::models::Wiki::get_many(
+            &[wiki_id],
             false,
             &**pool,
             &redis,
         )
         .await?
         .into_iter()
-        .collect::<Vec<_>>();
-        wikis.sort_by_key(|x| x.sort_order);
-        let wikis_array = wiki_format(wikis);
+        .next() else {
+            return Err(ApiError::NotFound);
+        };
+
+        let mut transaction = pool.begin().await?;
+
+        let wiki_cache_id = generate_wiki_cache_id(&mut transaction).await?;
+        let base_revisions =
+            base_revisions_json(project.inner.id, Some(&[wiki_id]), &**pool)
+                .await?;
 
         let wiki_cache = WikiCache {
             id: wiki_cache_id,
             project_id: project.inner.id,
-            user_id: UserId(id.0 as i64),
+            user_id: UserId::from(user.id),
             created: Default::default(),
             status: "".to_string(),
-            cache: serde_json::json!(wikis_array),
-            old: serde_json::json!(wikis_array),
+            cache: serde_json::json!([wiki]),
+            old: serde_json::json!([wiki]),
             message: serde_json::json!([]),
             again_count: 0,
             again_time: Default::default(),
+            base_revisions,
+            conflicts: serde_json::json!([]),
+            wiki_id: Some(wiki_id),
         }
         .insert(&mut transaction)
         .await?;
@@ -608,6 +617,58 @@ pub async fn wiki_edit_start(
     Err(ApiError::NotFound)
 }
 
+/// 检查用户能否编辑该项目的百科：未被禁止编辑、有编辑权限或百科对外开放，
+/// 付费资源还需已购买
+async fn check_wiki_edit_access(
+    project: &database::models::project_item::QueryProject,
+    user: &crate::models::v3::users::User,
+    pool: &PgPool,
+) -> Result<(), ApiError> {
+    // 检查用户是否被论坛类封禁
+    check_forum_ban(user, pool).await?;
+
+    if Utc::now() < user.wiki_ban_time {
+        return Err(ApiError::WikiBan(
+            user.wiki_ban_time
+                .with_timezone(&crate::util::date::app_tz())
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        ));
+    }
+
+    let (team_member, organization_team_member) =
+        crate::database::models::TeamMember::get_for_project_permissions(
+            &project.inner,
+            UserId::from(user.id),
+            pool,
+        )
+        .await?;
+
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    );
+
+    if !project.inner.wiki_open
+        && !permissions
+            .is_some_and(|x| x.contains(ProjectPermissions::WIKI_EDIT))
+    {
+        return Err(ApiError::Validation("你没有权限编辑百科页面".to_string()));
+    }
+
+    // 付费资源检查：未购买用户不能编辑百科
+    if project.inner.is_paid
+        && !check_wiki_paid_access(user, &project.inner, pool).await?
+    {
+        return Err(ApiError::Validation(
+            "您需要购买此资源后才能编辑百科".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
 pub async fn wiki_list(
     req: HttpRequest,
     info: web::Path<(String,)>,
@@ -673,82 +734,71 @@ pub async fn wiki_list(
             wikis: wikis_array,
             is_editor: false,
             cache: None,
+            drafts: vec![],
             is_editor_user: false,
             editor_user: None,
             is_visitors: true,
             requires_purchase,
+            reviews: vec![],
         };
 
         // 缓存，正在编辑的wiki缓存（付费未购买时跳过，不返回缓存内容）
         if user_option.is_some() && !requires_purchase {
-            let wiki_cache = database::models::WikiCache::get_draft_or_review(
-                project.inner.id,
-                &**pool,
-            )
-            .await?;
-            // wiki_cache.sort_by_key(|x| x.sort_order);
+            let user_id = UserId::from(user_option.as_ref().unwrap().id);
+
+            let (team_member, organization_team_member) =
+                crate::database::models::TeamMember::get_for_project_permissions(
+                    &project.inner,
+                    user_id,
+                    &**pool,
+                )
+                    .await?;
 
-            if wiki_cache.is_some() {
-                let mut wiki_cache_ = wiki_cache.unwrap();
+            let permissions = ProjectPermissions::get_permissions_by_role(
+                &user_option.as_ref().unwrap().role,
+                &team_member,
+                &organization_team_member,
+            );
+            let can_review = permissions
+                .is_some_and(|x| x.contains(ProjectPermissions::WIKI_EDIT));
 
-                let (team_member, organization_team_member) =
-                    crate::database::models::TeamMember::get_for_project_permissions(
-                        &project.inner,
-                        UserId::from(user_option.as_ref().unwrap().id),
+            if can_review {
+                wikis.reviews =
+                    database::models::WikiCache::get_project_reviews(
+                        project.inner.id,
                         &**pool,
                     )
-                        .await?;
-
-                let permissions = ProjectPermissions::get_permissions_by_role(
-                    &user_option.as_ref().unwrap().role,
-                    &team_member,
-                    &organization_team_member,
-                );
-
-                // 如果 有编辑权限 或者 缓存的用户id和当前用户id相同则返回所有正在编辑的wiki
-                if permissions.is_some()
-                    && permissions
-                        .unwrap()
-                        .contains(ProjectPermissions::WIKI_EDIT)
-                    || wiki_cache_.user_id
-                        == UserId(user_option.as_ref().unwrap().id.0 as i64)
-                {
-                    wiki_cache_
-                        .cache
-                        .as_array_mut()
-                        .unwrap()
-                        .sort_by_key(|x| x["sort_order"].as_i64().unwrap());
-                    for x in wiki_cache_.cache.as_array_mut().unwrap() {
-                        if !x["child"].is_null() {
-                            x["child"].as_array_mut().unwrap().sort_by_key(
-                                |x| x["sort_order"].as_i64().unwrap(),
-                            );
-                        }
-                    }
-                    let u = user_get_(wiki_cache_.user_id, pool, redis).await?;
-
-                    wikis.is_visitors = false;
-                    wikis.is_editor = true;
-                    wikis.editor_user = u;
-                    if wiki_cache_.user_id
-                        == UserId(user_option.as_ref().unwrap().id.0 as i64)
-                    {
-                        wikis.is_editor_user = true;
-                        wikis.cache = Option::from(wiki_cache_);
-                    } else {
-                        wikis.is_editor_user = false;
-                        if wiki_cache_.status == *"review" {
-                            wikis.cache = Option::from(wiki_cache_);
-                        }
-                    }
-                } else {
-                    let u = user_get_(wiki_cache_.user_id, pool, redis).await?;
-                    wikis.is_editor = true;
-                    wikis.is_visitors = true;
-                    wikis.is_editor_user = false;
-                    wikis.editor_user = u;
+                    .await?;
+                for review in &mut wikis.reviews {
+                    sort_wiki_cache(review);
                 }
             }
+
+            // 自己的草稿优先；有审核权限时其次展示最早的待审核编辑
+            wikis.drafts = database::models::WikiCache::get_user_actives(
+                project.inner.id,
+                user_id,
+                &**pool,
+            )
+            .await?;
+            for draft in &mut wikis.drafts {
+                sort_wiki_cache(draft);
+            }
+            let wiki_cache = match wikis.drafts.first() {
+                Some(own) => Some(own.clone()),
+                None => wikis.reviews.first().cloned(),
+            };
+
+            if let Some(mut wiki_cache_) = wiki_cache {
+                sort_wiki_cache(&mut wiki_cache_);
+                let u = user_get_(wiki_cache_.user_id, pool, redis).await?;
+
+                wikis.is_visitors = false;
+                wikis.is_editor = true;
+                wikis.editor_user = u;
+                wikis.is_editor_user = wiki_cache_.user_id == user_id;
+                wikis.cache = Option::from(wiki_cache_);
+            }
         }
 
         Ok(HttpResponse::Ok().json(wikis))
@@ -759,9 +809,11 @@ pub async fn wiki_list(
 pub async fn wiki_accept(
     req: HttpRequest,
     info: web::Path<(String,)>,
+    query: web::Query<WikiCacheQuery>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let string = info.into_inner().0;
     let result =
@@ -791,32 +843,8 @@ pub async fn wiki_accept(
             return Err(ApiError::NotFound);
         }
 
-        let mut wikis = database::models::Wiki::get_many(
-            &project.wikis,
-            false,
-            &**pool,
-            &redis,
-        )
-        .await?
-        .into_iter()
-        .collect::<Vec<_>>();
-        wikis.sort_by_key(|x| x.sort_order);
-        let wikis_array = wiki_format(wikis);
-        let wikis = Wikis {
-            wikis: wikis_array,
-            is_editor: false,
-            cache: None,
-            is_editor_user: true,
-            editor_user: user_option.clone(),
-            is_visitors: false,
-            requires_purchase: false,
-        };
-
-        let wiki_cache = database::models::WikiCache::get_draft_or_review(
-            project.inner.id,
-            &**pool,
-        )
-        .await?;
+        let wiki_cache =
+            get_review_target(project.inner.id, query.id, &**pool).await?;
 
         let (team_member, organization_team_member) =
             crate::database::models::TeamMember::get_for_project_permissions(
@@ -846,208 +874,17 @@ pub async fn wiki_accept(
             // 只有编辑权限的用户才能跳过审核部分
 
             let mut wiki_cache_ = wiki_cache.unwrap();
+            let edit_message = wiki_cache_.latest_message();
 
-            wiki_cache_
-                .cache
-                .as_array_mut()
-                .unwrap()
-                .sort_by_key(|x| x["sort_order"].as_i64().unwrap());
-            for x in wiki_cache_.cache.as_array_mut().unwrap() {
-                if !x["child"].is_null() {
-                    x["child"]
-                        .as_array_mut()
-                        .unwrap()
-                        .sort_by_key(|x| x["sort_order"].as_i64().unwrap());
-                }
-            }
-
-            let mut old_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            let mut new_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            for wiki in wikis.wikis {
-                old_wikis.insert(
-                    wiki.id,
-                    Wiki {
-                        id: wiki.id,
-                        project_id: wiki.project_id,
-                        parent_wiki_id: wiki.parent_wiki_id,
-                        title: wiki.title,
-                        body: wiki.body,
-                        sort_order: wiki.sort_order,
-                        featured: wiki.featured,
-                        created: wiki.created,
-                        updated: wiki.updated,
-                        slug: wiki.slug,
-                    },
-                );
-                if !wiki.child.is_empty() {
-                    for child in wiki.child {
-                        old_wikis.insert(
-                            child.id,
-                            Wiki {
-                                id: child.id,
-                                project_id: child.project_id,
-                                parent_wiki_id: child.parent_wiki_id,
-                                title: child.title,
-                                body: child.body,
-                                sort_order: child.sort_order,
-                                featured: child.featured,
-                                created: child.created,
-                                updated: child.updated,
-                                slug: child.slug,
-                            },
-                        );
-                    }
-                }
-            }
-            for wiki in wiki_cache_.cache.as_array_mut().unwrap() {
-                let id = WikiId(wiki["id"].as_i64().unwrap());
-                new_wikis.insert(
-                    id,
-                    Wiki {
-                        id,
-                        project_id: project.inner.id,
-                        parent_wiki_id: WikiId(
-                            wiki["parent_wiki_id"].as_i64().unwrap(),
-                        ),
-                        title: wiki["title"].as_str().unwrap().to_string(),
-                        body: wiki["body"].as_str().unwrap().to_string(),
-                        sort_order: wiki["sort_order"].as_i64().unwrap() as i32,
-                        featured: wiki["featured"].as_bool().unwrap(),
-                        created: wiki["created"]
-                            .as_str()
-                            .unwrap()
-                            .to_string()
-                            .parse()
-                            .unwrap(),
-                        updated: wiki["updated"]
-                            .as_str()
-                            .unwrap()
-                            .to_string()
-                            .parse()
-                            .unwrap(),
-                        slug: wiki["slug"].as_str().unwrap().to_string(),
-                    },
-                );
-
-                if !wiki["child"].is_null() {
-                    for child in wiki["child"].as_array_mut().unwrap() {
-                        let id = WikiId(child["id"].as_i64().unwrap());
-                        new_wikis.insert(
-                            id,
-                            Wiki {
-                                id,
-                                project_id: project.inner.id,
-                                parent_wiki_id: WikiId(
-                                    child["parent_wiki_id"].as_i64().unwrap(),
-                                ),
-                                title: child["title"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                                body: child["body"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                                sort_order: child["sort_order"]
-                                    .as_i64()
-                                    .unwrap()
-                                    as i32,
-                                featured: child["featured"].as_bool().unwrap(),
-                                created: child["created"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string()
-                                    .parse()
-                                    .unwrap(),
-                                updated: child["updated"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string()
-                                    .parse()
-                                    .unwrap(),
-                                slug: child["slug"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                            },
-                        );
-                    }
-                }
-            }
-
-            let mut common_wikis: HashMap<WikiId, (Wiki, Wiki)> =
-                HashMap::new();
-            for (key, old_wiki) in &old_wikis {
-                if let Some(new_wiki) = new_wikis.get(key) {
-                    common_wikis
-                        .insert(*key, (old_wiki.clone(), new_wiki.clone()));
-                }
-            }
-            let mut added_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            let mut removed_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-
-            for (key, new_wiki) in &new_wikis {
-                if !old_wikis.contains_key(key) {
-                    added_wikis.insert(*key, new_wiki.clone());
-                }
-            }
-
-            for (key, old_wiki) in &old_wikis {
-                if !new_wikis.contains_key(key) {
-                    removed_wikis.insert(*key, old_wiki.clone());
-                }
-            }
-
-            let mut transaction = pool.begin().await?;
-            for (old_wiki, new_wiki) in common_wikis.values() {
-                if old_wiki.body != new_wiki.body
-                    || old_wiki.sort_order != new_wiki.sort_order
-                    || old_wiki.featured != new_wiki.featured
-                    || old_wiki.title != new_wiki.title
-                {
-                    let mut wiki = new_wiki.clone();
-                    wiki.updated = chrono::Utc::now();
-                    wiki.update(&mut transaction).await?;
-                    wiki.clear_cache(&redis).await?;
-                }
-            }
-            for new_wiki in added_wikis.values() {
-                let wiki = new_wiki.clone();
-                wiki.update(&mut transaction).await?;
-                wiki.clear_cache(&redis).await?;
-            }
-            for old_wiki in removed_wikis.values() {
-                if old_wiki.id != old_wiki.parent_wiki_id {
-                    old_wiki.delete(&mut transaction).await?;
-                }
-            }
-            for old_wiki in removed_wikis.values() {
-                if old_wiki.id == old_wiki.parent_wiki_id {
-                    old_wiki.delete(&mut transaction).await?;
-                }
-            }
-            wiki_cache_
-                .message_add(user_option.as_ref().unwrap(), "通过")
-                .await;
-            wiki_cache_.finish_cache(&mut transaction).await?;
-            NotificationBuilder {
-                body: NotificationBody::WikiCache {
-                    project_id: ProjectId::from(project.inner.id),
-                    project_title: project.inner.name.clone(),
-                    wiki_cache_id: wiki_cache_.id,
-                    type_: "accept".to_string(),
-                    msg: "通过".to_string(),
-                },
-            }
-            .insert(wiki_cache_.user_id, &mut transaction, &redis)
-            .await?;
-
-            transaction.commit().await?;
-            crate::database::models::Project::clear_cache(
-                project.inner.id,
-                None,
-                None,
+            apply_wiki_cache(
+                &project,
+                &mut wiki_cache_,
+                user_option.as_ref().unwrap(),
+                &edit_message,
+                "通过",
+                &pool,
                 &redis,
+                &search_config,
             )
             .await?;
         } else {
@@ -1063,6 +900,7 @@ pub async fn wiki_accept(
 pub async fn wiki_reject(
     req: HttpRequest,
     info: web::Path<(String,)>,
+    query: web::Query<WikiCacheQuery>,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
@@ -1099,22 +937,8 @@ pub async fn wiki_reject(
             return Err(ApiError::NotFound);
         }
 
-        let mut wikis = database::models::Wiki::get_many(
-            &project.wikis,
-            false,
-            &**pool,
-            &redis,
-        )
-        .await?
-        .into_iter()
-        .collect::<Vec<_>>();
-        wikis.sort_by_key(|x| x.sort_order);
-
-        let wiki_cache = database::models::WikiCache::get_draft_or_review(
-            project.inner.id,
-            &**pool,
-        )
-        .await?;
+        let wiki_cache =
+            get_review_target(project.inner.id, query.id, &**pool).await?;
 
         let (team_member, organization_team_member) =
             crate::database::models::TeamMember::get_for_project_permissions(
@@ -1150,7 +974,7 @@ pub async fn wiki_reject(
                 .message_add(user_option.as_ref().unwrap(), &body.msg)
                 .await;
             wiki_cache_.reject_cache(&mut transaction).await?;
-            NotificationBuilder {
+            let push_events = NotificationBuilder {
                 body: NotificationBody::WikiCache {
                     project_id: ProjectId::from(project.inner.id),
                     project_title: project.inner.name.clone(),
@@ -1162,6 +986,7 @@ pub async fn wiki_reject(
             .insert(wiki_cache_.user_id, &mut transaction, &redis)
             .await?;
             transaction.commit().await?;
+            crate::queue::push::publish(push_events, &redis).await;
             Ok(HttpResponse::Ok().finish())
         } else {
             Err(ApiError::NotFound)
@@ -1176,6 +1001,8 @@ pub async fn wiki_submit(
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
     session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
+    query: web::Query<WikiCacheQuery>,
     body: web::Json<MsgWiki>,
 ) -> Result<HttpResponse, ApiError> {
     body.validate().map_err(|err| {
@@ -1213,33 +1040,20 @@ pub async fn wiki_submit(
             return Err(ApiError::NotFound);
         }
 
-        let mut wikis = database::models::Wiki::get_many(
-            &project.wikis,
-            false,
+        // 指定 id 时只提交该草稿，否则提交自己在该项目下的所有草稿
+        let user_id = UserId::from(user_option.as_ref().unwrap().id);
+        let drafts = database::models::WikiCache::get_user_actives(
+            project.inner.id,
+            user_id,
             &**pool,
-            &redis,
         )
         .await?
         .into_iter()
+        .filter(|x| x.status == "draft" && query.id.is_none_or(|id| id == x.id))
         .collect::<Vec<_>>();
-        wikis.sort_by_key(|x| x.sort_order);
-        let wikis_array = wiki_format(wikis);
-        let wikis = Wikis {
-            wikis: wikis_array,
-            is_editor: false,
-            cache: None,
-            is_editor_user: true,
-            editor_user: user_option.clone(),
-            is_visitors: false,
-            requires_purchase: false,
-        };
-
-        let wiki_cache = database::models::WikiCache::get_draft(
-            project.inner.id,
-            UserId::from(user_option.as_ref().unwrap().id),
-            &**pool,
-        )
-        .await?;
+        if drafts.is_empty() {
+            return Err(ApiError::NotFound);
+        }
 
         // wiki_cache.sort_by_key(|x| x.sort_order);
         let (team_member, organization_team_member) =
@@ -1264,18 +1078,10 @@ pub async fn wiki_submit(
          *
          */
 
-        if wiki_cache.is_some()
-            && (permissions.is_none()
-                || !permissions
-                    .unwrap()
-                    .contains(ProjectPermissions::WIKI_EDIT))
+        if permissions.is_none()
+            || !permissions.unwrap().contains(ProjectPermissions::WIKI_EDIT)
         {
             let mut transaction = pool.begin().await?;
-            let mut cache = wiki_cache.unwrap();
-            cache
-                .message_add(user_option.as_ref().unwrap(), &body.msg)
-                .await;
-            cache.review_cache(&mut transaction).await?;
 
             let mut new_member =
                 database::models::TeamMember::get_from_team_full(
@@ -1330,227 +1136,57 @@ pub async fn wiki_submit(
 
             // println!("new_member: {:?}", new_member);
 
-            for member in new_member {
-                println!("member: {:?}", member);
-                NotificationBuilder {
-                    body: NotificationBody::WikiCache {
-                        project_id: ProjectId::from(project.inner.id),
-                        project_title: project.inner.name.clone(),
-                        wiki_cache_id: cache.id,
-                        type_: "review".to_string(),
-                        msg: body.msg.clone(),
-                    },
+            let mut push_events = Vec::new();
+            for mut cache in drafts {
+                cache
+                    .message_add(user_option.as_ref().unwrap(), &body.msg)
+                    .await;
+                cache.review_cache(&mut transaction).await?;
+
+                for member in &new_member {
+                    push_events.extend(
+                        NotificationBuilder {
+                            body: NotificationBody::WikiCache {
+                                project_id: ProjectId::from(project.inner.id),
+                                project_title: project.inner.name.clone(),
+                                wiki_cache_id: cache.id,
+                                type_: "review".to_string(),
+                                msg: body.msg.clone(),
+                            },
+                        }
+                        .insert(member.user_id, &mut transaction, &redis)
+                        .await?,
+                    );
                 }
-                .insert(member.user_id, &mut transaction, &redis)
-                .await?;
             }
             transaction.commit().await?;
+            crate::queue::push::publish(push_events, &redis).await;
             return Ok(HttpResponse::Ok().finish());
         }
 
-        if wiki_cache.is_some() {
-            // 只有编辑权限的用户才能跳过审核部分
-
-            let mut wiki_cache_ = wiki_cache.unwrap();
-
-            wiki_cache_
-                .cache
-                .as_array_mut()
-                .unwrap()
-                .sort_by_key(|x| x["sort_order"].as_i64().unwrap());
-            for x in wiki_cache_.cache.as_array_mut().unwrap() {
-                if !x["child"].is_null() {
-                    x["child"]
-                        .as_array_mut()
-                        .unwrap()
-                        .sort_by_key(|x| x["sort_order"].as_i64().unwrap());
-                }
-            }
-            // wikis.cache = Option::from(wiki_cache_);
-            // wikis.is_editor = true;
-
-            let mut old_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            let mut new_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            for wiki in wikis.wikis {
-                old_wikis.insert(
-                    wiki.id,
-                    Wiki {
-                        id: wiki.id,
-                        project_id: wiki.project_id,
-                        parent_wiki_id: wiki.parent_wiki_id,
-                        title: wiki.title,
-                        body: wiki.body,
-                        sort_order: wiki.sort_order,
-                        featured: wiki.featured,
-                        created: wiki.created,
-                        updated: wiki.updated,
-                        slug: wiki.slug,
-                    },
-                );
-                if !wiki.child.is_empty() {
-                    for child in wiki.child {
-                        old_wikis.insert(
-                            child.id,
-                            Wiki {
-                                id: child.id,
-                                project_id: child.project_id,
-                                parent_wiki_id: child.parent_wiki_id,
-                                title: child.title,
-                                body: child.body,
-                                sort_order: child.sort_order,
-                                featured: child.featured,
-                                created: child.created,
-                                updated: child.updated,
-                                slug: child.slug,
-                            },
-                        );
-                    }
-                }
-            }
-            for wiki in wiki_cache_.cache.as_array_mut().unwrap() {
-                let id = WikiId(wiki["id"].as_i64().unwrap());
-                new_wikis.insert(
-                    id,
-                    Wiki {
-                        id,
-                        project_id: project.inner.id,
-                        parent_wiki_id: WikiId(
-                            wiki["parent_wiki_id"].as_i64().unwrap(),
-                        ),
-                        title: wiki["title"].as_str().unwrap().to_string(),
-                        body: wiki["body"].as_str().unwrap().to_string(),
-                        sort_order: wiki["sort_order"].as_i64().unwrap() as i32,
-                        featured: wiki["featured"].as_bool().unwrap(),
-                        created: wiki["created"]
-                            .as_str()
-                            .unwrap()
-                            .to_string()
-                            .parse()
-                            .unwrap(),
-                        updated: wiki["updated"]
-                            .as_str()
-                            .unwrap()
-                            .to_string()
-                            .parse()
-                            .unwrap(),
-                        slug: wiki["slug"].as_str().unwrap().to_string(),
-                    },
-                );
-
-                if !wiki["child"].is_null() {
-                    for child in wiki["child"].as_array_mut().unwrap() {
-                        let id = WikiId(child["id"].as_i64().unwrap());
-                        new_wikis.insert(
-                            id,
-                            Wiki {
-                                id,
-                                project_id: project.inner.id,
-                                parent_wiki_id: WikiId(
-                                    child["parent_wiki_id"].as_i64().unwrap(),
-                                ),
-                                title: child["title"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                                body: child["body"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                                sort_order: child["sort_order"]
-                                    .as_i64()
-                                    .unwrap()
-                                    as i32,
-                                featured: child["featured"].as_bool().unwrap(),
-                                created: child["created"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string()
-                                    .parse()
-                                    .unwrap(),
-                                updated: child["updated"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string()
-                                    .parse()
-                                    .unwrap(),
-                                slug: child["slug"]
-                                    .as_str()
-                                    .unwrap()
-                                    .to_string(),
-                            },
-                        );
-                    }
-                }
-            }
-
-            let mut common_wikis: HashMap<WikiId, (Wiki, Wiki)> =
-                HashMap::new();
-            for (key, old_wiki) in &old_wikis {
-                if let Some(new_wiki) = new_wikis.get(key) {
-                    common_wikis
-                        .insert(*key, (old_wiki.clone(), new_wiki.clone()));
-                }
-            }
-
-            let mut added_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-            let mut removed_wikis: HashMap<WikiId, Wiki> = HashMap::new();
-
-            for (key, new_wiki) in &new_wikis {
-                if !old_wikis.contains_key(key) {
-                    added_wikis.insert(*key, new_wiki.clone());
-                }
-            }
-
-            for (key, old_wiki) in &old_wikis {
-                if !new_wikis.contains_key(key) {
-                    removed_wikis.insert(*key, old_wiki.clone());
-                }
-            }
-
-            let mut transaction = pool.begin().await?;
-
-            for wiki in common_wikis.values() {
-                let (old_wiki, new_wiki) = wiki;
-                if old_wiki.body != new_wiki.body
-                    || old_wiki.sort_order != new_wiki.sort_order
-                    || old_wiki.featured != new_wiki.featured
-                    || old_wiki.title != new_wiki.title
-                {
-                    let mut wiki = new_wiki.clone();
-                    wiki.updated = chrono::Utc::now();
-                    wiki.update(&mut transaction).await?;
-                    wiki.clear_cache(&redis).await?;
-                }
-            }
-            for new_wiki in &added_wikis {
-                let wiki = new_wiki.1.clone();
-                wiki.update(&mut transaction).await?;
-                wiki.clear_cache(&redis).await?;
-            }
-            for old_wiki in &removed_wikis {
-                if old_wiki.1.id != old_wiki.1.parent_wiki_id {
-                    old_wiki.1.delete(&mut transaction).await?;
-                }
-            }
-            for old_wiki in &removed_wikis {
-                if old_wiki.1.id == old_wiki.1.parent_wiki_id {
-                    old_wiki.1.delete(&mut transaction).await?;
-                }
-            }
-            wiki_cache_
-                .message_add(user_option.as_ref().unwrap(), &body.msg)
-                .await;
-            wiki_cache_.finish_cache(&mut transaction).await?;
-            transaction.commit().await?;
-            crate::database::models::Project::clear_cache(
-                project.inner.id,
-                None,
-                None,
+        // 只有编辑权限的用户才能跳过审核部分，提交者即审核者；
+        // 各草稿分别合并，有冲突的草稿退回，其余照常发布
+        let mut conflicts = vec![];
+        for mut wiki_cache_ in drafts {
+            match apply_wiki_cache(
+                &project,
+                &mut wiki_cache_,
+                user_option.as_ref().unwrap(),
+                &body.msg,
+                &body.msg,
+                &pool,
                 &redis,
+                &search_config,
             )
-            .await?;
-        } else {
-            return Err(ApiError::NotFound);
+            .await
+            {
+                Ok(()) => {}
+                Err(ApiError::WikiConflict(titles)) => conflicts.push(titles),
+                Err(e) => return Err(e),
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(ApiError::WikiConflict(conflicts.join("、")));
         }
 
         Ok(HttpResponse::Ok().finish())
@@ -1598,18 +1234,6 @@ pub async fn wiki_submit_again(
             return Err(ApiError::NotFound);
         }
 
-        let more = database::models::WikiCache::get_draft_or_review(
-            project.inner.id,
-            &**pool,
-        )
-        .await?;
-        if more.is_some()
-            && more.unwrap().user_id
-                != UserId::from(user_option.as_ref().unwrap().id)
-        {
-            return Err(ApiError::Validation("其他用户正在编辑中".to_string()));
-        }
-
         let cache_id_parsed: i64 = cache_id
             .parse()
             .map_err(|_| ApiError::InvalidInput("无效的缓存 ID".to_string()))?;
@@ -1623,6 +1247,27 @@ pub async fn wiki_submit_again(
         if wiki_cache.is_some() {
             let mut cache = wiki_cache.unwrap();
 
+            // 同一用户在同一页面上只能有一份进行中的编辑
+            let user_id = UserId::from(user_option.as_ref().unwrap().id);
+            let active = database::models::WikiCache::get_user_actives(
+                project.inner.id,
+                user_id,
+                &**pool,
+            )
+            .await?
+            .into_iter()
+            .filter(|x| {
+                x.wiki_id.is_none()
+                    || cache.wiki_id.is_none()
+                    || x.wiki_id == cache.wiki_id
+            })
+            .collect::<Vec<_>>();
+            if active.iter().any(|x| x.id != cache.id) {
+                return Err(ApiError::Validation(
+                    "您已有进行中的百科编辑，请先提交或放弃".to_string(),
+                ));
+            }
+
             if cache.again_count >= 5 {
                 return Err(ApiError::Validation(
                     "已重复编辑过5次，本申请已无法再次编辑".to_string(),
@@ -1700,14 +1345,14 @@ pub async fn wiki_given_up(
             cache
                 .user_overtake_count(cache.user_id, 1, &mut transaction)
                 .await?;
-            if user.wiki_overtake_count + 1 > 3 {
+            if user.wiki_overtake_count + 1 > WIKI_OVERTAKE_LIMIT {
                 cache
                     .user_overtake_count_set(cache.user_id, 0, &mut transaction)
                     .await?;
                 cache.user_ban(cache.user_id, 72, &mut transaction).await?;
             }
             transaction.commit().await?;
-            User::clear_caches(
+            database::models::User::clear_caches(
                 &[(UserId::from(user.id), Some(user.username.clone()))],
                 &redis,
             )
@@ -1721,8 +1366,940 @@ pub async fn wiki_given_up(
     }
 }
 
+#[derive(Deserialize)]
+pub struct WikiDiffQuery {
+    pub from: i64,
+    pub to: i64,
+}
+
+#[derive(Deserialize, Serialize, Validate)]
+pub struct WikiRollback {
+    pub revision_id: i64,
+    #[validate(length(max = 500))]
+    pub msg: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WikiRevisionDiff {
+    pub from: WikiRevisionSummary,
+    pub to: WikiRevisionSummary,
+    pub title_changed: bool,
+    pub added: usize,
+    pub removed: usize,
+    pub lines: Vec<DiffLine>,
+    pub unified: String,
+}
+
+/// 获取百科页面的修订列表
+pub async fn wiki_revisions(
+    req: HttpRequest,
+    info: web::Path<(String, i64)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (string, wiki_id) = info.into_inner();
+    let project =
+        get_wiki_history_project(&req, &string, &pool, &redis, &session_queue)
+            .await?;
+
+    let revisions = WikiRevision::get_summaries_for_wiki(
+        WikiId(wiki_id),
+        project.inner.id,
+        &**pool,
+    )
+    .await?;
+    if revisions.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+/// 获取某一条修订的完整内容
+pub async fn wiki_revision_get(
+    req: HttpRequest,
+    info: web::Path<(String, i64, i64)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (string, wiki_id, revision_id) = info.into_inner();
+    let project =
+        get_wiki_history_project(&req, &string, &pool, &redis, &session_queue)
+            .await?;
+
+    let revision = WikiRevision::get(revision_id, &**pool)
+        .await?
+        .filter(|x| {
+            x.wiki_id == WikiId(wiki_id) && x.project_id == project.inner.id
+        })
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(revision))
+}
+
+/// 对比同一页面的两条修订
+pub async fn wiki_revision_diff(
+    req: HttpRequest,
+    info: web::Path<(String, i64)>,
+    query: web::Query<WikiDiffQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (string, wiki_id) = info.into_inner();
+    let project =
+        get_wiki_history_project(&req, &string, &pool, &redis, &session_queue)
+            .await?;
+
+    let mut revisions = Vec::with_capacity(2);
+    for id in [query.from, query.to] {
+        let revision = WikiRevision::get(id, &**pool)
+            .await?
+            .filter(|x| {
+                x.wiki_id == WikiId(wiki_id) && x.project_id == project.inner.id
+            })
+            .ok_or(ApiError::NotFound)?;
+        revisions.push(revision);
+    }
+    let to = revisions.pop().unwrap();
+    let from = revisions.pop().unwrap();
+
+    let lines = diff_lines(&from.body, &to.body);
+    let (added, removed) = diff_stats(&lines);
+    let unified = unified_diff(
+        &lines,
+        &format!("{} (#{})", from.title, from.revision_number),
+        &format!("{} (#{})", to.title, to.revision_number),
+    );
+
+    Ok(HttpResponse::Ok().json(WikiRevisionDiff {
+        title_changed: from.title != to.title,
+        from: revision_summary(&from),
+        to: revision_summary(&to),
+        added,
+        removed,
+        lines,
+        unified,
+    }))
+}
+
+/// 将单个百科页面回滚到指定修订
+pub async fn wiki_rollback(
+    req: HttpRequest,
+    info: web::Path<(String, i64)>,
+    body: web::Json<WikiRollback>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+    let (string, wiki_id) = info.into_inner();
+    let (project, user) =
+        get_wiki_rollback_project(&req, &string, &pool, &redis, &session_queue)
+            .await?;
+
+    let revision = WikiRevision::get(body.revision_id, &**pool)
+        .await?
+        .filter(|x| {
+            x.wiki_id == WikiId(wiki_id) && x.project_id == project.inner.id
+        })
+        .ok_or(ApiError::NotFound)?;
+    if revision.action == WikiRevisionAction::Delete {
+        return Err(ApiError::Validation(
+            "该版本为页面删除记录，无法回滚到此版本".to_string(),
+        ));
+    }
+
+    let current_wikis = database::models::Wiki::get_many(
+        &project.wikis,
+        false,
+        &**pool,
+        &redis,
+    )
+    .await?;
+    let current = current_wikis.iter().find(|x| x.id == revision.wiki_id);
+    if current.is_none()
+        && revision.parent_wiki_id != revision.wiki_id
+        && !current_wikis
+            .iter()
+            .any(|x| x.id == revision.parent_wiki_id)
+    {
+        return Err(ApiError::Validation(
+            "该页面的父页面已被删除，请先回滚父页面".to_string(),
+        ));
+    }
+
+    let message = body
+        .msg
+        .clone()
+        .unwrap_or_else(|| format!("回滚到版本 #{}", revision.revision_number));
+    let user_id = UserId::from(user.id);
+    let revision_context = WikiRevisionContext {
+        wiki_cache_id: None,
+        author_id: Some(user_id),
+        reviewer_id: Some(user_id),
+        message: &message,
+    };
+
+    let mut transaction = pool.begin().await?;
+    let wiki = match current {
+        Some(current) => {
+            let mut wiki = revision.to_wiki(current.created);
+            wiki.slug = current.slug.clone();
+            wiki.parent_wiki_id = current.parent_wiki_id;
+            wiki.update(&mut transaction).await?;
+            wiki
+        }
+        None => restore_wiki(&revision, &mut transaction).await?,
+    };
+    WikiRevision::record(
+        &wiki,
+        WikiRevisionAction::Rollback,
+        &revision_context,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    wiki.clear_cache(&redis).await?;
+    database::models::Project::clear_cache(
+        project.inner.id,
+        None,
+        None,
+        &redis,
+    )
+    .await?;
+    update_content_index(
+        ContentTarget::ProjectWikis(project.inner.id),
+        &pool,
+        &search_config,
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(wiki))
+}
+
+/// 将整个项目百科回滚到指定修订所在时刻的状态
+///
+/// 当时不存在的页面会被删除，之后被删除的页面会被恢复。
+pub async fn wiki_rollback_all(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    body: web::Json<WikiRollback>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    search_config: web::Data<SearchConfig>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(|err| {
+        ApiError::Validation(validation_errors_to_string(err, None))
+    })?;
+    let string = info.into_inner().0;
+    let (project, user) =
+        get_wiki_rollback_project(&req, &string, &pool, &redis, &session_queue)
+            .await?;
+
+    let revision = WikiRevision::get(body.revision_id, &**pool)
+        .await?
+        .filter(|x| x.project_id == project.inner.id)
+        .ok_or(ApiError::NotFound)?;
+
+    let target: HashMap<WikiId, WikiRevision> =
+        WikiRevision::get_project_state_at(
+            project.inner.id,
+            revision.created,
+            &**pool,
+        )
+        .await?
+        .into_iter()
+        .filter(|x| x.action != WikiRevisionAction::Delete)
+        .map(|x| (x.wiki_id, x))
+        .collect();
+    let current: HashMap<WikiId, Wiki> = database::models::Wiki::get_many(
+        &project.wikis,
+        false,
+        &**pool,
+        &redis,
+    )
+    .await?
+    .into_iter()
+    .map(|x| (x.id, x))
+    .collect();
+
+    let message = body.msg.clone().unwrap_or_else(|| {
+        format!(
+            "整体回滚到 {}",
+            crate::util::date::format_app_tz(revision.created)
+        )
+    });
+    let user_id = UserId::from(user.id);
+    let revision_context = WikiRevisionContext {
+        wiki_cache_id: None,
+        author_id: Some(user_id),
+        reviewer_id: Some(user_id),
+        message: &message,
+    };
+
+    let mut changed: Vec<Wiki> = Vec::new();
+    let mut transaction = pool.begin().await?;
+
+    // 当时不存在的页面：先删子页面，再删父页面
+    let mut removed = current
+        .values()
+        .filter(|x| !target.contains_key(&x.id))
+        .collect::<Vec<_>>();
+    removed.sort_by_key(|x| x.id == x.parent_wiki_id);
+    for wiki in removed {
+        WikiRevision::record(
+            wiki,
+            WikiRevisionAction::Delete,
+            &revision_context,
+            &mut transaction,
+        )
+        .await?;
+        wiki.delete(&mut transaction).await?;
+        changed.push(wiki.clone());
+    }
+
+    // 仍然存在或需要恢复的页面：先处理父页面，再处理子页面
+    let mut restored = target.values().collect::<Vec<_>>();
+    restored.sort_by_key(|x| x.wiki_id != x.parent_wiki_id);
+    for target_revision in restored {
+        let wiki = match current.get(&target_revision.wiki_id) {
+            Some(current_wiki) => {
+                if current_wiki.title == target_revision.title
+                    && current_wiki.body == target_revision.body
+                    && current_wiki.sort_order == target_revision.sort_order
+                    && current_wiki.featured == target_revision.featured
+                {
+                    continue;
+                }
+                let mut wiki = target_revision.to_wiki(current_wiki.created);
+                wiki.slug = current_wiki.slug.clone();
+                wiki.parent_wiki_id = current_wiki.parent_wiki_id;
+                wiki.update(&mut transaction).await?;
+                wiki
+            }
+            None => {
+                if target_revision.parent_wiki_id != target_revision.wiki_id
+                    && !target.contains_key(&target_revision.parent_wiki_id)
+                {
+                    continue;
+                }
+                restore_wiki(target_revision, &mut transaction).await?
+            }
+        };
+        WikiRevision::record(
+            &wiki,
+            WikiRevisionAction::Rollback,
+            &revision_context,
+            &mut transaction,
+        )
+        .await?;
+        changed.push(wiki);
+    }
+    transaction.commit().await?;
+
+    for wiki in &changed {
+        wiki.clear_cache(&redis).await?;
+    }
+    database::models::Project::clear_cache(
+        project.inner.id,
+        None,
+        None,
+        &redis,
+    )
+    .await?;
+    update_content_index(
+        ContentTarget::ProjectWikis(project.inner.id),
+        &pool,
+        &search_config,
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "changed": changed.len(),
+    })))
+}
+
+/// 审核目标：指定 id 的进行中编辑，或最早提交的待审核编辑
+async fn get_review_target(
+    project_id: database::models::ProjectId,
+    id: Option<WikiCacheId>,
+    pool: &PgPool,
+) -> Result<Option<WikiCache>, ApiError> {
+    Ok(match id {
+        Some(id) => WikiCache::get_active(id, project_id, pool).await?,
+        None => WikiCache::get_project_reviews(project_id, pool)
+            .await?
+            .into_iter()
+            .next(),
+    })
+}
+
+fn sort_wiki_cache(wiki_cache: &mut WikiCache) {
+    if let Some(cache) = wiki_cache.cache.as_array_mut() {
+        cache.sort_by_key(|x| x["sort_order"].as_i64().unwrap_or_default());
+        for x in cache {
+            if let Some(child) = x["child"].as_array_mut() {
+                child.sort_by_key(|x| {
+                    x["sort_order"].as_i64().unwrap_or_default()
+                });
+            }
+        }
+    }
+}
+
+/// 草稿的基线版本：开始编辑时各页面最新修订的 id；`wiki_ids` 为空时取所有页面
+async fn base_revisions_json(
+    project_id: database::models::ProjectId,
+    wiki_ids: Option<&[WikiId]>,
+    pool: &PgPool,
+) -> Result<Value, ApiError> {
+    let revisions = WikiRevision::get_latest_ids(project_id, pool).await?;
+    Ok(Value::Object(
+        revisions
+            .into_iter()
+            .filter(|(wiki_id, _)| wiki_ids.is_none_or(|x| x.contains(wiki_id)))
+            .map(|(wiki_id, id)| (wiki_id.0.to_string(), Value::from(id)))
+            .collect(),
+    ))
+}
+
+fn parse_wiki(
+    wiki: &Value,
+    project_id: database::models::ProjectId,
+) -> Option<Wiki> {
+    Some(Wiki {
+        id: WikiId(wiki["id"].as_i64()?),
+        project_id,
+        parent_wiki_id: WikiId(wiki["parent_wiki_id"].as_i64()?),
+        title: wiki["title"].as_str()?.to_string(),
+        body: wiki["body"].as_str()?.to_string(),
+        sort_order: wiki["sort_order"].as_i64()? as i32,
+        featured: wiki["featured"].as_bool()?,
+        created: wiki["created"].as_str()?.parse().ok()?,
+        updated: wiki["updated"].as_str()?.parse().ok()?,
+        slug: wiki["slug"].as_str()?.to_string(),
+    })
+}
+
+/// 将草稿中保存的页面树展开为 id -> 页面
+fn parse_wiki_tree(
+    tree: &Value,
+    project_id: database::models::ProjectId,
+) -> HashMap<WikiId, Wiki> {
+    let mut wikis = HashMap::new();
+    for wiki in tree.as_array().into_iter().flatten() {
+        if let Some(parsed) = parse_wiki(wiki, project_id) {
+            wikis.insert(parsed.id, parsed);
+        }
+        for child in wiki["child"].as_array().into_iter().flatten() {
+            if let Some(parsed) = parse_wiki(child, project_id) {
+                wikis.insert(parsed.id, parsed);
+            }
+        }
+    }
+    wikis
+}
+
+enum WikiChange {
+    Create(Wiki),
+    Edit(Wiki),
+    Delete(Wiki),
+}
+
+struct WikiMerge {
+    changes: Vec<WikiChange>,
+    /// 合并后的完整页面；有冲突时作为变基后的草稿内容
+    merged: Vec<Wiki>,
+    conflicts: Vec<(Wiki, String)>,
+}
+
+fn wiki_unchanged(a: &Wiki, b: &Wiki) -> bool {
+    a.title == b.title
+        && a.body == b.body
+        && a.sort_order == b.sort_order
+        && a.featured == b.featured
+        && a.parent_wiki_id == b.parent_wiki_id
+}
+
+/// 单个字段的三方合并：只有一侧改动时取改动的一侧，两侧改成不同值时返回 None
+fn merge_field<T: PartialEq + Clone>(
+    base: &T,
+    ours: &T,
+    theirs: &T,
+) -> Option<T> {
+    if ours == base || ours == theirs {
+        Some(theirs.clone())
+    } else if theirs == base {
+        Some(ours.clone())
+    } else {
+        None
+    }
+}
+
+/// 以草稿开始时的内容为共同祖先，逐页合并草稿与当前已发布的百科
+///
+/// `base` 为草稿开始编辑时的页面，`ours` 为草稿内容，`theirs` 为当前已发布的页面。
+/// 页面草稿的 `base` 与 `ours` 只包含草稿涉及的页面，其余页面原样保留，
+/// 只有双方都改动过的页面才可能冲突。
+fn merge_wiki_tree(
+    base: &HashMap<WikiId, Wiki>,
+    ours: &HashMap<WikiId, Wiki>,
+    theirs: &HashMap<WikiId, Wiki>,
+) -> WikiMerge {
+    let mut ids = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    ids.sort_by_key(|x| x.0);
+    ids.dedup();
+
+    let mut merge = WikiMerge {
+        changes: vec![],
+        merged: vec![],
+        conflicts: vec![],
+    };
+    // 草稿新设为推荐的页面
+    let mut featured = None;
+    for id in ids {
+        match (base.get(&id), ours.get(&id), theirs.get(&id)) {
+            (None, Some(o), None) => {
+                let parent = o.parent_wiki_id;
+                let parent_exists = parent == o.id
+                    || theirs.contains_key(&parent)
+                    || (ours.contains_key(&parent)
+                        && !base.contains_key(&parent));
+                if !parent_exists {
+                    merge
+                        .conflicts
+                        .push((o.clone(), "父页面已被他人删除".to_string()));
+                } else {
+                    merge.changes.push(WikiChange::Create(o.clone()));
+                    if o.featured {
+                        featured = Some(o.id);
+                    }
+                }
+                merge.merged.push(o.clone());
+            }
+            (None, _, Some(t)) => {
+                merge.merged.push(t.clone());
+            }
+            (Some(b), None, Some(t)) => {
+                // 他人在该页面下新增的子页面会随删除一起丢失
+                let new_child = theirs.values().any(|x| {
+                    x.parent_wiki_id == id
+                        && x.id != id
+                        && !base.contains_key(&x.id)
+                });
+                if !wiki_unchanged(b, t) {
+                    merge.conflicts.push((
+                        t.clone(),
+                        "页面已被他人修改，无法删除".to_string(),
+                    ));
+                    merge.merged.push(t.clone());
+                } else if new_child {
+                    merge.conflicts.push((
+                        t.clone(),
+                        "他人在该页面下新增了子页面，无法删除".to_string(),
+                    ));
+                    merge.merged.push(t.clone());
+                } else {
+                    merge.changes.push(WikiChange::Delete(t.clone()));
+                }
+            }
+            (Some(b), Some(o), None) => {
+                if !wiki_unchanged(b, o) {
+                    merge
+                        .conflicts
+                        .push((o.clone(), "页面已被他人删除".to_string()));
+                }
+            }
+            (Some(b), Some(o), Some(t)) => {
+                let mut reasons = vec![];
+                let title = merge_field(&b.title, &o.title, &t.title)
+                    .unwrap_or_else(|| {
+                        reasons.push("标题");
+                        t.title.clone()
+                    });
+                let body =
+                    merge3(&b.body, &o.body, &t.body, "我的修改", "当前版本");
+                if body.conflicts > 0 {
+                    reasons.push("正文");
+                }
+                let mut wiki = t.clone();
+                wiki.title = title;
+                wiki.body = body.text;
+                // 排序与推荐状态以草稿为准
+                if o.sort_order != b.sort_order {
+                    wiki.sort_order = o.sort_order;
+                }
+                if o.featured != b.featured {
+                    wiki.featured = o.featured;
+                    if o.featured {
+                        featured = Some(id);
+                    }
+                }
+
+                if !reasons.is_empty() {
+                    merge.conflicts.push((
+                        wiki.clone(),
+                        format!("{}与他人的修改冲突", reasons.join("、")),
+                    ));
+                } else if !wiki_unchanged(&wiki, t) {
+                    merge.changes.push(WikiChange::Edit(wiki.clone()));
+                }
+                merge.merged.push(wiki);
+            }
+            _ => {}
+        }
+    }
+
+    // 只能有一个推荐页面：取消其他页面的推荐，这不算作改动了这些页面
+    if let Some(featured) = featured
+        && merge.conflicts.is_empty()
+    {
+        for wiki in &mut merge.merged {
+            if wiki.id == featured || !wiki.featured {
+                continue;
+            }
+            wiki.featured = false;
+            let change = merge.changes.iter_mut().find_map(|x| match x {
+                WikiChange::Edit(x) | WikiChange::Create(x)
+                    if x.id == wiki.id =>
+                {
+                    Some(x)
+                }
+                _ => None,
+            });
+            match change {
+                Some(change) => change.featured = false,
+                None => merge.changes.push(WikiChange::Edit(wiki.clone())),
+            }
+        }
+    }
+    merge
+}
+
+/// 将草稿合并到当前百科并发布
+///
+/// 草稿与期间他人已发布的修改逐页三方合并；存在冲突时不做任何发布，
+/// 而是把草稿变基到当前内容（冲突处写入标记）并退回给提交者。
+async fn apply_wiki_cache(
+    project: &database::models::project_item::QueryProject,
+    wiki_cache: &mut WikiCache,
+    reviewer: &crate::models::v3::users::User,
+    revision_message: &str,
+    accept_msg: &str,
+    pool: &web::Data<PgPool>,
+    redis: &RedisPool,
+    search_config: &SearchConfig,
+) -> Result<(), ApiError> {
+    let project_id = project.inner.id;
+    let mut transaction = pool.begin().await?;
+
+    // 先锁定草稿，再锁定项目的全部页面并在事务内读取当前内容：并发的审核
+    // 依次基于前一次发布的结果合并，重复提交的审核也只会处理一次
+    *wiki_cache =
+        WikiCache::lock_active(wiki_cache.id, project_id, &mut transaction)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInput("该编辑已被处理".to_string())
+            })?;
+    let current = database::models::Wiki::get_project_for_update(
+        project_id,
+        &mut transaction,
+    )
+    .await?;
+
+    let base = parse_wiki_tree(&wiki_cache.old, project_id);
+    let ours = parse_wiki_tree(&wiki_cache.cache, project_id);
+    let theirs = current
+        .iter()
+        .map(|x| (x.id, x.clone()))
+        .collect::<HashMap<_, _>>();
+    let merge = merge_wiki_tree(&base, &ours, &theirs);
+
+    if !merge.conflicts.is_empty() {
+        let conflicts = merge
+            .conflicts
+            .iter()
+            .map(|(wiki, reason)| WikiConflict {
+                wiki_id: wiki.id,
+                title: wiki.title.clone(),
+                reason: reason.clone(),
+                base_revision_id: wiki_cache.base_revisions
+                    [wiki.id.0.to_string()]
+                .as_i64(),
+            })
+            .collect::<Vec<_>>();
+        let titles = conflicts
+            .iter()
+            .map(|x| x.title.clone())
+            .collect::<Vec<_>>()
+            .join("、");
+
+        let mut merged = merge.merged;
+        merged.sort_by_key(|x| x.sort_order);
+        let mut current = current;
+        current.sort_by_key(|x| x.sort_order);
+        if wiki_cache.wiki_id.is_some() {
+            // 页面草稿只变基草稿涉及的页面
+            let touched =
+                base.keys().chain(ours.keys()).copied().collect::<Vec<_>>();
+            merged.retain(|x| touched.contains(&x.id));
+            current.retain(|x| touched.contains(&x.id));
+            wiki_cache.cache = serde_json::json!(merged);
+            wiki_cache.old = serde_json::json!(current);
+            wiki_cache.base_revisions =
+                base_revisions_json(project_id, Some(&touched), &***pool)
+                    .await?;
+        } else {
+            wiki_cache.cache = serde_json::json!(wiki_format(merged));
+            wiki_cache.old = serde_json::json!(wiki_format(current));
+            wiki_cache.base_revisions =
+                base_revisions_json(project_id, None, &***pool).await?;
+        }
+        wiki_cache.conflicts = serde_json::json!(conflicts);
+        wiki_cache
+            .message_add(reviewer, &format!("与其他编辑冲突: {titles}"))
+            .await;
+        wiki_cache.rebase(&mut transaction).await?;
+        let push_events = NotificationBuilder {
+            body: NotificationBody::WikiCache {
+                project_id: ProjectId::from(project_id),
+                project_title: project.inner.name.clone(),
+                wiki_cache_id: wiki_cache.id,
+                type_: "conflict".to_string(),
+                msg: titles.clone(),
+            },
+        }
+        .insert(wiki_cache.user_id, &mut transaction, redis)
+        .await?;
+        transaction.commit().await?;
+        crate::queue::push::publish(push_events, redis).await;
+        return Err(ApiError::WikiConflict(titles));
+    }
+
+    let revision_context = WikiRevisionContext {
+        wiki_cache_id: Some(wiki_cache.id),
+        author_id: Some(wiki_cache.user_id),
+        reviewer_id: Some(UserId::from(reviewer.id)),
+        message: revision_message,
+    };
+
+    let mut deleted = vec![];
+    for change in merge.changes {
+        match change {
+            WikiChange::Create(wiki) => {
+                wiki.update(&mut transaction).await?;
+                WikiRevision::record(
+                    &wiki,
+                    WikiRevisionAction::Create,
+                    &revision_context,
+                    &mut transaction,
+                )
+                .await?;
+                wiki.clear_cache(redis).await?;
+            }
+            WikiChange::Edit(mut wiki) => {
+                wiki.updated = Utc::now();
+                wiki.update(&mut transaction).await?;
+                WikiRevision::record(
+                    &wiki,
+                    WikiRevisionAction::Edit,
+                    &revision_context,
+                    &mut transaction,
+                )
+                .await?;
+                wiki.clear_cache(redis).await?;
+            }
+            WikiChange::Delete(wiki) => {
+                WikiRevision::record(
+                    &wiki,
+                    WikiRevisionAction::Delete,
+                    &revision_context,
+                    &mut transaction,
+                )
+                .await?;
+                deleted.push(wiki);
+            }
+        }
+    }
+    // 先删子页面，再删父页面
+    deleted.sort_by_key(|x| x.id == x.parent_wiki_id);
+    for wiki in &deleted {
+        wiki.delete(&mut transaction).await?;
+    }
+
+    wiki_cache.conflicts = serde_json::json!([]);
+    wiki_cache.message_add(reviewer, accept_msg).await;
+    wiki_cache.finish_cache(&mut transaction).await?;
+    let push_events = if wiki_cache.user_id != UserId::from(reviewer.id) {
+        NotificationBuilder {
+            body: NotificationBody::WikiCache {
+                project_id: ProjectId::from(project_id),
+                project_title: project.inner.name.clone(),
+                wiki_cache_id: wiki_cache.id,
+                type_: "accept".to_string(),
+                msg: accept_msg.to_string(),
+            },
+        }
+        .insert(wiki_cache.user_id, &mut transaction, redis)
+        .await?
+    } else {
+        Vec::new()
+    };
+    transaction.commit().await?;
+    crate::queue::push::publish(push_events, redis).await;
+
+    for wiki in &deleted {
+        wiki.clear_cache(redis).await?;
+    }
+    database::models::Project::clear_cache(project_id, None, None, redis)
+        .await?;
+    update_content_index(
+        ContentTarget::ProjectWikis(project_id),
+        pool,
+        search_config,
+    )
+    .await;
+    Ok(())
+}
+
+/// 按修订内容重新创建已被删除的页面
+async fn restore_wiki(
+    revision: &WikiRevision,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Wiki, ApiError> {
+    let mut wiki = revision.to_wiki(Utc::now()).insert(transaction).await?;
+    // insert 写入的是草稿行，需再 update 一次才会发布
+    wiki.updated = Utc::now();
+    wiki.update(transaction).await?;
+    Ok(wiki)
+}
+
+fn revision_summary(revision: &WikiRevision) -> WikiRevisionSummary {
+    WikiRevisionSummary {
+        id: revision.id,
+        wiki_id: revision.wiki_id,
+        wiki_cache_id: revision.wiki_cache_id,
+        revision_number: revision.revision_number,
+        action: revision.action,
+        author_id: revision.author_id,
+        reviewer_id: revision.reviewer_id,
+        message: revision.message.clone(),
+        title: revision.title.clone(),
+        body_length: revision.body.chars().count() as i32,
+        created: revision.created,
+    }
+}
+
+/// 查看修订历史前的检查：项目可见，且付费项目需已购买
+async fn get_wiki_history_project(
+    req: &HttpRequest,
+    string: &str,
+    pool: &web::Data<PgPool>,
+    redis: &RedisPool,
+    session_queue: &AuthQueue,
+) -> Result<database::models::project_item::QueryProject, ApiError> {
+    let project = database::models::Project::get(string, &***pool, redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let user_option = get_user_from_headers(
+        req,
+        &***pool,
+        redis,
+        session_queue,
+        Some(&[Scopes::PROJECT_READ, Scopes::VERSION_READ]),
+    )
+    .await
+    .map(|x| x.1)
+    .ok();
+
+    if !is_visible_project(&project.inner, &user_option, pool, false).await? {
+        return Err(ApiError::NotFound);
+    }
+
+    if project.inner.is_paid {
+        let has_access = match &user_option {
+            Some(user) => {
+                check_wiki_paid_access(user, &project.inner, pool).await?
+            }
+            None => false,
+        };
+        if !has_access {
+            return Err(ApiError::Validation(
+                "您需要购买此资源后才能查看百科历史".to_string(),
+            ));
+        }
+    }
+
+    Ok(project)
+}
+
+/// 回滚前的检查：需要 WIKI_EDIT 权限
+async fn get_wiki_rollback_project(
+    req: &HttpRequest,
+    string: &str,
+    pool: &web::Data<PgPool>,
+    redis: &RedisPool,
+    session_queue: &AuthQueue,
+) -> Result<
+    (
+        database::models::project_item::QueryProject,
+        crate::models::v3::users::User,
+    ),
+    ApiError,
+> {
+    let user = get_user_from_headers(
+        req,
+        &***pool,
+        redis,
+        session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+    let project = database::models::Project::get(string, &***pool, redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if !is_visible_project(&project.inner, &Some(user.clone()), pool, false)
+        .await?
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    let (team_member, organization_team_member) =
+        database::models::TeamMember::get_for_project_permissions(
+            &project.inner,
+            UserId::from(user.id),
+            &***pool,
+        )
+        .await?;
+    let permissions = ProjectPermissions::get_permissions_by_role(
+        &user.role,
+        &team_member,
+        &organization_team_member,
+    );
+    if !permissions.is_some_and(|x| x.contains(ProjectPermissions::WIKI_EDIT)) {
+        return Err(ApiError::Validation("你没有权限回滚百科页面".to_string()));
+    }
+
+    Ok((project, user))
+}
+
 /// 检查用户是否有权访问付费项目的 Wiki
-async fn check_wiki_paid_access(
+pub(crate) async fn check_wiki_paid_access(
     user: &crate::models::v3::users::User,
     project: &database::models::project_item::Project,
     pool: &PgPool,
@@ -1805,3 +2382,76 @@ pub fn wiki_format(wikis: Vec<Wiki>) -> Vec<WikiDisplays> {
     }
     wikis_.into_values().collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(id: i64, parent: i64, body: &str, featured: bool) -> Wiki {
+        Wiki {
+            id: WikiId(id),
+            project_id: database::models::ProjectId(1),
+            sort_order: 0,
+            title: format!("页面 {id}"),
+            body: body.to_string(),
+            parent_wiki_id: WikiId(parent),
+            featured,
+            created: Default::default(),
+            updated: Default::default(),
+            slug: format!("page-{id}"),
+        }
+    }
+
+    fn pages(wikis: &[Wiki]) -> HashMap<WikiId, Wiki> {
+        wikis.iter().map(|x| (x.id, x.clone())).collect()
+    }
+
+    #[test]
+    fn page_drafts_only_conflict_on_pages_both_sides_touched() {
+        // 草稿只编辑了页面 1，他人改动了页面 2 并删除了页面 3
+        let base = pages(&[page(1, 1, "a", false)]);
+        let ours = pages(&[page(1, 1, "b", false)]);
+        let theirs = pages(&[page(1, 1, "a", false), page(2, 2, "c", false)]);
+        let merge = merge_wiki_tree(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.changes.len(), 1);
+        assert!(
+            matches!(&merge.changes[0], WikiChange::Edit(x) if x.body == "b")
+        );
+
+        // 双方都改动了页面 1
+        let theirs = pages(&[page(1, 1, "c", false)]);
+        let merge = merge_wiki_tree(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn deleting_a_page_conflicts_with_new_children() {
+        let base = pages(&[page(1, 1, "a", false), page(2, 1, "b", false)]);
+        let ours = HashMap::new();
+        let theirs = pages(&[page(1, 1, "a", false), page(2, 1, "b", false)]);
+        let merge = merge_wiki_tree(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.changes.len(), 2);
+
+        let mut theirs = theirs;
+        theirs.insert(WikiId(3), page(3, 1, "c", false));
+        let merge = merge_wiki_tree(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn featuring_a_page_unfeatures_the_others() {
+        let base = pages(&[page(1, 1, "a", false)]);
+        let ours = pages(&[page(1, 1, "a", true)]);
+        let theirs = pages(&[page(1, 1, "a", false), page(2, 2, "b", true)]);
+        let merge = merge_wiki_tree(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert!(merge.changes.iter().any(
+            |x| matches!(x, WikiChange::Edit(x) if x.id == WikiId(2) && !x.featured)
+        ));
+        assert!(merge.changes.iter().any(
+            |x| matches!(x, WikiChange::Edit(x) if x.id == WikiId(1) && x.featured)
+        ));
+    }
+}
@@ -5,10 +5,12 @@
 
 use crate::auth::get_user_from_headers;
 use crate::database::models::UserId;
+use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::yunzhanghu_profile_item::{
     YunzhanghuProfile, YzhSignStatus,
 };
 use crate::database::redis::RedisPool;
+use crate::models::notifications::NotificationBody;
 use crate::models::pats::Scopes;
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
@@ -1186,6 +1188,19 @@ pub async fn apply_order_status(
     .execute(&mut *tx)
     .await?;
 
+    // 打款失败或被撤销，冲正提现分录，余额回到创作者账户
+    if matches!(
+        new_status,
+        crate::models::payouts::PayoutStatus::Failed
+            | crate::models::payouts::PayoutStatus::Cancelled
+    ) {
+        crate::database::models::LedgerEntry::reverse_withdrawal(
+            crate::database::models::PayoutId(payout_db_id),
+            &mut tx,
+        )
+        .await?;
+    }
+
     tx.commit().await?;
 
     // 用户余额可能因为 payout 被改为非 in-transit 而回退，清缓存让前端刷新
@@ -1195,6 +1210,22 @@ pub async fn apply_order_status(
     )
     .await?;
 
+    if current != new_status {
+        crate::queue::push::publish(
+            vec![(
+                crate::database::models::UserId(row.user_id),
+                crate::queue::push::PushEventBody::PayoutStatus {
+                    payout_id: crate::models::ids::PayoutId(
+                        payout_db_id as u64,
+                    ),
+                    status: new_status,
+                },
+            )],
+            redis,
+        )
+        .await;
+    }
+
     if should_notify_success
         && let Err(e) = insert_payout_success_notification(
             pool,
@@ -1232,8 +1263,8 @@ async fn insert_payout_success_notification(
     amount: rust_decimal::Decimal,
 ) -> Result<(), crate::database::models::DatabaseError> {
     let mut tx = pool.begin().await?;
-    crate::database::models::notification_item::NotificationBuilder {
-        body: crate::models::notifications::NotificationBody::LegacyMarkdown {
+    let push_events = NotificationBuilder {
+        body: NotificationBody::LegacyMarkdown {
             notification_type: Some("payout_success".to_string()),
             name: "提现已到账".to_string(),
             text: format!(
@@ -1247,6 +1278,7 @@ async fn insert_payout_success_notification(
     .insert(user_id, &mut tx, redis)
     .await?;
     tx.commit().await?;
+    crate::queue::push::publish(push_events, redis).await;
 
     Ok(())
 }
@@ -3,11 +3,11 @@
 //! 每 5 分钟执行一次，检查启用了汉化追踪的项目，
 //! 同步上游更新并更新汉化内容。
 
-use crate::database::models::DatabaseError;
 use crate::database::models::ids::{ProjectId, generate_project_id};
 use crate::database::models::project_item::{Project, ProjectBuilder};
 use crate::database::models::team_item::TeamBuilder;
 use crate::database::models::thread_item::ThreadBuilder;
+use crate::database::models::{DatabaseError, SearchIndexQueueEntry};
 use crate::database::redis::RedisPool;
 use crate::models::projects::{MonetizationStatus, ProjectStatus};
 use crate::models::threads::ThreadType;
@@ -326,6 +326,7 @@ async fn sync_cn_project_icon(
     )
     .execute(pool)
     .await?;
+    SearchIndexQueueEntry::enqueue(&[ProjectId(cn_project_id)], pool).await?;
 
     Ok(())
 }
@@ -552,6 +553,8 @@ async fn process_tracked_project(
     .execute(&mut *transaction)
     .await?;
 
+    SearchIndexQueueEntry::enqueue(&[project_id], &mut *transaction).await?;
+
     transaction.commit().await?;
 
     // 清除原项目缓存（translation_tracker 已更新）
@@ -0,0 +1,402 @@
+//! MeiliSearch 过滤表达式的解析
+//!
+//! 数据库搜索后端需要理解与 MeiliSearch 相同的过滤语法（`facets`、`filters`、
+//! `new_filters` 最终都会拼成这种表达式），这里把表达式解析为语法树，
+//! 再由后端翻译为 SQL 条件。
+
+use crate::search::SearchError;
+
+/// 表达式的最大长度，按字节计
+const MAX_FILTER_LEN: usize = 16 * 1024;
+/// 括号与 NOT 的最大嵌套层数；解析与翻译都是递归的，不限制时
+/// 深层嵌套的表达式会耗尽线程栈
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Condition { field: String, condition: Condition },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Compare(Operator, String),
+    In(Vec<String>),
+    /// `field a TO b`，包含两端
+    Range(String, String),
+    Exists,
+    IsNull,
+    IsEmpty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Op(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+fn invalid(message: impl Into<String>) -> SearchError {
+    SearchError::InvalidFilter(message.into())
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, SearchError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '[' | ']' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '!' | '>' | '<' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                tokens.push(Token::Op(match (c, eq) {
+                    ('=', false) => Operator::Eq,
+                    ('!', true) => Operator::Ne,
+                    ('>', false) => Operator::Gt,
+                    ('>', true) => Operator::Ge,
+                    ('<', false) => Operator::Lt,
+                    ('<', true) => Operator::Le,
+                    _ => return Err(invalid(format!("无法识别的运算符 {c}"))),
+                }));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.extend(chars.next()),
+                        Some(x) if x == c => break,
+                        Some(x) => value.push(x),
+                        None => return Err(invalid("引号未闭合")),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&x) = chars.peek() {
+                    if x.is_whitespace() || "()[],=!<>\"'".contains(x) {
+                        break;
+                    }
+                    word.push(x);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(x)) if x.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self.peek_keyword(keyword);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), SearchError> {
+        match self.next() {
+            Some(x) if x == token => Ok(()),
+            x => Err(invalid(format!("期望 {token:?}，实际为 {x:?}"))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, SearchError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.eat_keyword("OR") {
+            filters.push(self.parse_and()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, SearchError> {
+        let mut filters = vec![self.parse_unary()?];
+        while self.eat_keyword("AND") {
+            filters.push(self.parse_unary()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::And(filters)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, SearchError> {
+        if self.peek_keyword("NOT") || self.peek() == Some(&Token::LParen) {
+            if self.depth >= MAX_DEPTH {
+                return Err(invalid(format!(
+                    "嵌套层数不能超过 {MAX_DEPTH} 层"
+                )));
+            }
+            self.depth += 1;
+            let filter = if self.eat_keyword("NOT") {
+                self.parse_unary().map(|x| Filter::Not(Box::new(x)))
+            } else {
+                self.position += 1;
+                self.parse_or()
+                    .and_then(|x| self.expect(Token::RParen).map(|_| x))
+            };
+            self.depth -= 1;
+            return filter;
+        }
+        self.parse_condition()
+    }
+
+    fn parse_value(&mut self) -> Result<String, SearchError> {
+        match self.next() {
+            Some(Token::Word(x)) | Some(Token::Quoted(x)) => Ok(x),
+            x => Err(invalid(format!("期望值，实际为 {x:?}"))),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<String>, SearchError> {
+        self.expect(Token::LBracket)?;
+        let mut values = Vec::new();
+        if self.peek() == Some(&Token::RBracket) {
+            self.position += 1;
+            return Ok(values);
+        }
+        loop {
+            values.push(self.parse_value()?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RBracket) => break,
+                x => return Err(invalid(format!("列表格式错误: {x:?}"))),
+            }
+        }
+        Ok(values)
+    }
+
+    fn parse_condition(&mut self) -> Result<Filter, SearchError> {
+        let field = self.parse_value()?;
+        let condition = |condition| Filter::Condition {
+            field: field.clone(),
+            condition,
+        };
+
+        if let Some(Token::Op(operator)) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            let value = self.parse_value()?;
+            return Ok(condition(Condition::Compare(operator, value)));
+        }
+        if self.eat_keyword("IN") {
+            return Ok(condition(Condition::In(self.parse_list()?)));
+        }
+        if self.eat_keyword("EXISTS") {
+            return Ok(condition(Condition::Exists));
+        }
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            let inner = if self.eat_keyword("NULL") {
+                condition(Condition::IsNull)
+            } else if self.eat_keyword("EMPTY") {
+                condition(Condition::IsEmpty)
+            } else {
+                return Err(invalid("IS 之后应为 NULL 或 EMPTY"));
+            };
+            return Ok(if negated {
+                Filter::Not(Box::new(inner))
+            } else {
+                inner
+            });
+        }
+        if self.eat_keyword("NOT") {
+            let inner = if self.eat_keyword("IN") {
+                condition(Condition::In(self.parse_list()?))
+            } else if self.eat_keyword("EXISTS") {
+                condition(Condition::Exists)
+            } else {
+                return Err(invalid("NOT 之后应为 IN 或 EXISTS"));
+            };
+            return Ok(Filter::Not(Box::new(inner)));
+        }
+
+        let from = self.parse_value()?;
+        if !self.eat_keyword("TO") {
+            return Err(invalid(format!("无法解析条件 {field} {from}")));
+        }
+        let to = self.parse_value()?;
+        Ok(condition(Condition::Range(from, to)))
+    }
+}
+
+/// 解析过滤表达式；空表达式返回 None
+pub fn parse_filter(input: &str) -> Result<Option<Filter>, SearchError> {
+    if input.len() > MAX_FILTER_LEN {
+        return Err(invalid(format!(
+            "过滤表达式不能超过 {MAX_FILTER_LEN} 字节"
+        )));
+    }
+    let tokens = lex(input)?;
+    // 空括号由空的 facets 拼接而来，等同于没有条件
+    if tokens
+        .iter()
+        .all(|x| matches!(x, Token::LParen | Token::RParen))
+    {
+        return Ok(None);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+    let filter = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(invalid(format!("多余的内容: {token:?}")));
+    }
+    Ok(Some(filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eq(field: &str, value: &str) -> Filter {
+        Filter::Condition {
+            field: field.to_string(),
+            condition: Condition::Compare(Operator::Eq, value.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_facet_filter() {
+        let filter = parse_filter(
+            "((categories = forge OR categories = fabric) AND (game_versions = 1.20.1)) AND (downloads>=100)",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(vec![
+                Filter::And(vec![
+                    Filter::Or(vec![
+                        eq("categories", "forge"),
+                        eq("categories", "fabric"),
+                    ]),
+                    eq("game_versions", "1.20.1"),
+                ]),
+                Filter::Condition {
+                    field: "downloads".to_string(),
+                    condition: Condition::Compare(
+                        Operator::Ge,
+                        "100".to_string()
+                    ),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_keywords() {
+        assert_eq!(
+            parse_filter(
+                "NOT license IN [\"mit\", 'apache-2.0'] AND color EXISTS"
+            )
+            .unwrap()
+            .unwrap(),
+            Filter::And(vec![
+                Filter::Not(Box::new(Filter::Condition {
+                    field: "license".to_string(),
+                    condition: Condition::In(vec![
+                        "mit".to_string(),
+                        "apache-2.0".to_string(),
+                    ]),
+                })),
+                Filter::Condition {
+                    field: "color".to_string(),
+                    condition: Condition::Exists,
+                },
+            ])
+        );
+        assert_eq!(
+            parse_filter("downloads 10 TO 20").unwrap().unwrap(),
+            Filter::Condition {
+                field: "downloads".to_string(),
+                condition: Condition::Range("10".to_string(), "20".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_filter() {
+        assert_eq!(parse_filter("()").unwrap(), None);
+        assert!(parse_filter("categories =").is_err());
+        assert!(parse_filter("(categories = forge").is_err());
+        assert!(parse_filter("categories forge").is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let nested = |depth: usize| {
+            format!("{}a = 1{}", "(".repeat(depth), ")".repeat(depth))
+        };
+        assert!(parse_filter(&nested(MAX_DEPTH)).unwrap().is_some());
+        assert!(parse_filter(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(
+            parse_filter(&format!("{}a = 1", "NOT ".repeat(10_000))).is_err()
+        );
+        assert!(
+            parse_filter(&format!("{}a = 1", "(".repeat(100_000))).is_err()
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use log::info;
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentDeletionQuery;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use meilisearch_sdk::settings::{PaginationSetting, Settings};
+
+use super::SearchBackend;
+use crate::models::ids::VersionId;
+use crate::models::ids::base62_impl::to_base62;
+use crate::models::projects::SearchRequest;
+use crate::search::indexing::content::{CONTENT_INDEX, ContentScope};
+use crate::search::indexing::{
+    IndexingError, TIMEOUT, add_projects, get_indexes_for_indexing, swap_index,
+};
+use crate::search::{
+    ContentSearchRequest, ContentSearchResults, ResultSearchProject,
+    SearchConfig, SearchError, SearchResults, UploadSearchContent,
+    UploadSearchProject, content_types, get_sort_index, project_filter,
+};
+
+// 内容文档的正文较长，每批只上传少量文档，避免请求超过 10MiB
+const CONTENT_CHUNK_SIZE: usize = 100;
+// 搜索结果摘要截取的词数
+const CONTENT_CROP_LENGTH: usize = 40;
+
+pub struct MeilisearchBackend {
+    config: SearchConfig,
+}
+
+impl MeilisearchBackend {
+    pub fn new(config: SearchConfig) -> Self {
+        Self { config }
+    }
+
+    async fn content_index(&self) -> Result<(Client, Index), IndexingError> {
+        let client = self.config.make_client()?;
+        let index = client
+            .get_index(self.config.get_index_name(CONTENT_INDEX, false))
+            .await?;
+        Ok((client, index))
+    }
+}
+
+#[async_trait]
+impl SearchBackend for MeilisearchBackend {
+    async fn search_projects(
+        &self,
+        info: &SearchRequest,
+    ) -> Result<SearchResults, SearchError> {
+        let client = self.config.make_client()?;
+
+        let offset: usize = info.offset.as_deref().unwrap_or("0").parse()?;
+        let index = info.index.as_deref().unwrap_or("relevance");
+        let limit = info
+            .limit
+            .as_deref()
+            .unwrap_or("10")
+            .parse::<usize>()?
+            .clamp(1, 100);
+
+        let sort = get_sort_index(&self.config, index)?;
+        let meilisearch_index = client.get_index(sort.0).await?;
+
+        // 将 offset 和 limit 转换为 page 和 hits_per_page
+        let hits_per_page = limit;
+        let page = offset / limit + 1;
+
+        let filter = project_filter(info)?;
+
+        let results = {
+            let mut query = meilisearch_index.search();
+            query
+                .with_page(page)
+                .with_hits_per_page(hits_per_page)
+                .with_query(info.query.as_deref().unwrap_or_default())
+                .with_sort(&sort.1);
+
+            if let Some(filter) = filter.as_deref() {
+                query.with_filter(filter);
+            }
+
+            query.execute::<ResultSearchProject>().await?
+        };
+
+        Ok(SearchResults {
+            hits: results.hits.into_iter().map(|r| r.result).collect(),
+            page: results.page.unwrap_or_default(),
+            hits_per_page: results.hits_per_page.unwrap_or_default(),
+            total_hits: results.total_hits.unwrap_or_default(),
+        })
+    }
+
+    async fn replace_projects(
+        &self,
+        projects: &[UploadSearchProject],
+        additional_fields: &[String],
+    ) -> Result<(), IndexingError> {
+        let config = &self.config;
+
+        // 首先，确保当前索引存在（这样不会发生错误- 当前索引应该是空的最坏情况，而不是缺失）
+        get_indexes_for_indexing(config, false).await?;
+
+        // 然后，如果存在，删除下一个索引
+        let indices = get_indexes_for_indexing(config, true).await?;
+        for index in indices {
+            index.delete().await?;
+        }
+        // 重新创建下一个索引进行索引
+        let indices = get_indexes_for_indexing(config, true).await?;
+
+        add_projects(&indices, projects, additional_fields.to_vec(), config)
+            .await?;
+
+        // 交换索引
+        swap_index(config, "projects").await?;
+        swap_index(config, "projects_filtered").await?;
+
+        // 删除现在已过时的索引
+        for index in indices {
+            index.delete().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_projects(
+        &self,
+        project_ids: &[String],
+        projects: &[UploadSearchProject],
+    ) -> Result<(), IndexingError> {
+        let config = &self.config;
+        let client = config.make_client()?;
+
+        // 直接使用当前索引，不重新提交索引设置，避免触发整个索引的重建
+        let mut indexes = Vec::new();
+        for name in ["projects", "projects_filtered"] {
+            indexes.push(
+                client.get_index(config.get_index_name(name, false)).await?,
+            );
+        }
+
+        if !project_ids.is_empty() {
+            let filter = format!(
+                "project_id IN [{}]",
+                project_ids
+                    .iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            for index in &indexes {
+                index
+                    .delete_documents_with(
+                        DocumentDeletionQuery::new(index).with_filter(&filter),
+                    )
+                    .await?
+                    .wait_for_completion(&client, None, Some(TIMEOUT))
+                    .await?;
+            }
+        }
+
+        if !projects.is_empty() {
+            add_projects(&indexes, projects, Vec::new(), config).await?;
+        }
+
+        Ok(())
+    }
+
+    // Modrinth 上游修复 97e4d8e13: 确保版本在路由执行结束前从搜索索引中删除
+    // 改进删除逻辑，等待 Meilisearch 任务完成后再返回
+    async fn remove_versions(
+        &self,
+        ids: &[VersionId],
+    ) -> Result<(), IndexingError> {
+        let mut indexes = get_indexes_for_indexing(&self.config, false).await?;
+        let mut indexes_next =
+            get_indexes_for_indexing(&self.config, true).await?;
+        indexes.append(&mut indexes_next);
+
+        let client = self.config.make_client()?;
+        let client = &client;
+        let mut deletion_tasks = FuturesUnordered::new();
+
+        for index in &indexes {
+            deletion_tasks.push(async move {
+                // Meilisearch 任务提交后异步执行，需要等待一定时间确保完成
+                index
+                    .delete_documents(
+                        &ids.iter().map(|x| to_base62(x.0)).collect::<Vec<_>>(),
+                    )
+                    .await?
+                    .wait_for_completion(
+                        client,
+                        None,
+                        Some(Duration::from_secs(15)),
+                    )
+                    .await
+            });
+        }
+
+        while let Some(result) = deletion_tasks.next().await {
+            result?;
+        }
+
+        Ok(())
+    }
+
+    async fn search_content(
+        &self,
+        info: &ContentSearchRequest,
+        project_id: Option<&str>,
+        hidden_projects: &[String],
+    ) -> Result<ContentSearchResults, SearchError> {
+        let types = content_types(info)?;
+
+        let client = self.config.make_client()?;
+        let index = client
+            .get_index(self.config.get_index_name(CONTENT_INDEX, false))
+            .await?;
+
+        let limit = info.limit.unwrap_or(10).clamp(1, 100);
+        let page = info.offset.unwrap_or_default() / limit + 1;
+
+        let mut filters = Vec::new();
+        if !types.is_empty() {
+            filters.push(format!("content_type IN [{}]", types.join(", ")));
+        }
+        if let Some(project_id) = project_id {
+            filters.push(format!("project_id = \"{project_id}\""));
+        }
+        if !hidden_projects.is_empty() {
+            filters.push(format!(
+                "NOT project_id IN [{}]",
+                hidden_projects
+                    .iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let filter = filters.join(" AND ");
+
+        let mut query = index.search();
+        query
+            .with_page(page)
+            .with_hits_per_page(limit)
+            .with_query(info.query.as_deref().unwrap_or_default())
+            .with_facets(Selectors::Some(&["content_type"]))
+            .with_attributes_to_crop(Selectors::Some(&[(
+                "body",
+                Some(CONTENT_CROP_LENGTH),
+            )]))
+            .with_attributes_to_highlight(Selectors::Some(&["title", "body"]));
+        if !filter.is_empty() {
+            query.with_filter(&filter);
+        }
+        let results = query.execute::<UploadSearchContent>().await?;
+
+        let types = results
+            .facet_distribution
+            .and_then(|mut x| x.remove("content_type"))
+            .unwrap_or_default();
+
+        Ok(ContentSearchResults {
+            hits: results
+                .hits
+                .into_iter()
+                .map(|hit| {
+                    let snippet = hit
+                        .formatted_result
+                        .as_ref()
+                        .and_then(|x| x.get("body"))
+                        .and_then(|x| x.as_str())
+                        .map(|x| x.to_string())
+                        .unwrap_or_default();
+                    hit.result.into_result(snippet)
+                })
+                .collect(),
+            page: results.page.unwrap_or_default(),
+            hits_per_page: results.hits_per_page.unwrap_or_default(),
+            total_hits: results.total_hits.unwrap_or_default(),
+            types,
+        })
+    }
+
+    async fn replace_content(
+        &self,
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError> {
+        let config = &self.config;
+        let client = config.make_client()?;
+
+        // 确保当前索引存在，搜索不会因为索引缺失而报错
+        create_or_update_content_index(
+            &client,
+            &config.get_index_name(CONTENT_INDEX, false),
+        )
+        .await?;
+
+        let next_name = config.get_index_name(CONTENT_INDEX, true);
+        if let Ok(index) = client.get_index(&next_name).await {
+            index.delete().await?;
+        }
+        let index = create_or_update_content_index(&client, &next_name).await?;
+
+        add_content(&client, &index, documents).await?;
+
+        swap_index(config, CONTENT_INDEX).await?;
+        index.delete().await?;
+
+        Ok(())
+    }
+
+    async fn update_content(
+        &self,
+        scopes: &[ContentScope],
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError> {
+        let (client, index) = self.content_index().await?;
+
+        let filter = scopes
+            .iter()
+            .map(|scope| {
+                let (content_type, field, id) = match scope {
+                    ContentScope::Content(content_type, id) => {
+                        (content_type, "content_id", id)
+                    }
+                    ContentScope::Project(content_type, id) => {
+                        (content_type, "project_id", id)
+                    }
+                    ContentScope::Parent(content_type, id) => {
+                        (content_type, "parent_id", id)
+                    }
+                };
+                format!(
+                    "(content_type = {content_type} AND {field} = \"{id}\")"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ");
+
+        // 先按条件删除旧文档，再写入最新内容，这样已删除的页面也会从索引中移除
+        if !filter.is_empty() {
+            index
+                .delete_documents_with(
+                    DocumentDeletionQuery::new(&index).with_filter(&filter),
+                )
+                .await?
+                .wait_for_completion(&client, None, Some(TIMEOUT))
+                .await?;
+        }
+        add_content(&client, &index, documents).await?;
+
+        Ok(())
+    }
+}
+
+async fn add_content(
+    client: &Client,
+    index: &Index,
+    documents: &[UploadSearchContent],
+) -> Result<(), IndexingError> {
+    for chunk in documents.chunks(CONTENT_CHUNK_SIZE) {
+        index
+            .add_or_replace(chunk, Some("id"))
+            .await?
+            .wait_for_completion(
+                client,
+                None,
+                Some(std::time::Duration::from_secs(3600)),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+async fn create_or_update_content_index(
+    client: &Client,
+    name: &str,
+) -> Result<Index, meilisearch_sdk::errors::Error> {
+    let index = match client.get_index(name).await {
+        Ok(index) => index,
+        _ => {
+            info!("创建索引 {}", name);
+            client
+                .create_index(name, Some("id"))
+                .await?
+                .wait_for_completion(client, None, Some(TIMEOUT))
+                .await?
+                .try_make_index(client)
+                .map_err(|x| x.unwrap_failure())?
+        }
+    };
+
+    index
+        .set_settings(&content_settings())
+        .await?
+        .wait_for_completion(client, None, Some(TIMEOUT))
+        .await?;
+
+    Ok(index)
+}
+
+fn content_settings() -> Settings {
+    Settings::new()
+        .with_searchable_attributes(CONTENT_SEARCHABLE_ATTRIBUTES)
+        .with_filterable_attributes(CONTENT_FILTERABLE_ATTRIBUTES)
+        .with_sortable_attributes(CONTENT_SORTABLE_ATTRIBUTES)
+        .with_pagination(PaginationSetting {
+            max_total_hits: 10000,
+        })
+}
+
+const CONTENT_SEARCHABLE_ATTRIBUTES: &[&str] = &["title", "body", "author"];
+
+const CONTENT_FILTERABLE_ATTRIBUTES: &[&str] = &[
+    "content_type",
+    "content_id",
+    "parent_id",
+    "project_id",
+    "category",
+    "state",
+    "requires_purchase",
+];
+
+const CONTENT_SORTABLE_ATTRIBUTES: &[&str] =
+    &["created_timestamp", "modified_timestamp"];
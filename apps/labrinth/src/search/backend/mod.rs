@@ -0,0 +1,91 @@
+//! 搜索后端
+//!
+//! 默认使用 MeiliSearch；设置 `SEARCH_BACKEND=postgres` 时改用数据库全文搜索，
+//! 本地开发、CI 与小规模部署不再依赖外部搜索服务。
+
+use async_trait::async_trait;
+
+use crate::models::ids::VersionId;
+use crate::models::projects::SearchRequest;
+use crate::search::indexing::IndexingError;
+use crate::search::indexing::content::ContentScope;
+use crate::search::{
+    ContentSearchRequest, ContentSearchResults, SearchError, SearchResults,
+    UploadSearchContent, UploadSearchProject,
+};
+
+mod filter;
+mod meilisearch;
+mod postgres;
+mod tokenizer;
+
+pub use meilisearch::MeilisearchBackend;
+pub use postgres::PostgresBackend;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchBackendKind {
+    Meilisearch,
+    Postgres,
+}
+
+impl SearchBackendKind {
+    /// 读取 `SEARCH_BACKEND`，未设置时使用 MeiliSearch；值无效时返回 None
+    pub fn from_env() -> Option<Self> {
+        match dotenvy::var("SEARCH_BACKEND").ok().as_deref() {
+            None | Some("meilisearch") => Some(Self::Meilisearch),
+            Some("postgres") => Some(Self::Postgres),
+            Some(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait SearchBackend {
+    /// 搜索项目，支持与 MeiliSearch 相同的 facets、过滤表达式与排序索引
+    async fn search_projects(
+        &self,
+        info: &SearchRequest,
+    ) -> Result<SearchResults, SearchError>;
+
+    /// 用新的文档整体替换项目索引
+    async fn replace_projects(
+        &self,
+        projects: &[UploadSearchProject],
+        additional_fields: &[String],
+    ) -> Result<(), IndexingError>;
+
+    /// 删除 `project_ids`（base62）的全部旧文档，再写入 `projects`
+    async fn update_projects(
+        &self,
+        project_ids: &[String],
+        projects: &[UploadSearchProject],
+    ) -> Result<(), IndexingError>;
+
+    /// 从项目索引中删除版本
+    async fn remove_versions(
+        &self,
+        ids: &[VersionId],
+    ) -> Result<(), IndexingError>;
+
+    /// 搜索站内内容；`project_id` 为 base62 的项目 ID，`hidden_projects`
+    /// 中项目下的内容从结果与计数中排除
+    async fn search_content(
+        &self,
+        info: &ContentSearchRequest,
+        project_id: Option<&str>,
+        hidden_projects: &[String],
+    ) -> Result<ContentSearchResults, SearchError>;
+
+    /// 用新的文档整体替换内容索引
+    async fn replace_content(
+        &self,
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError>;
+
+    /// 删除 `scopes` 范围内的旧文档，再写入 `documents`
+    async fn update_content(
+        &self,
+        scopes: &[ContentScope],
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError>;
+}
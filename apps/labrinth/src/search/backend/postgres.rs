@@ -0,0 +1,735 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
+
+use super::SearchBackend;
+use super::filter::{Condition, Filter, Operator, parse_filter};
+use super::tokenizer::{highlight_words, query_terms, to_tsvector};
+use crate::models::ids::VersionId;
+use crate::models::ids::base62_impl::to_base62;
+use crate::models::projects::SearchRequest;
+use crate::search::indexing::content::ContentScope;
+use crate::search::indexing::{DEFAULT_DISPLAYED_ATTRIBUTES, IndexingError};
+use crate::search::{
+    ContentSearchRequest, ContentSearchResults, ResultSearchProject,
+    SearchError, SearchResults, UploadSearchContent, UploadSearchProject,
+    content_types, project_filter,
+};
+
+// 每条 INSERT 写入的文档数
+const INSERT_CHUNK_SIZE: usize = 1000;
+// 内容搜索摘要的字符数
+const SNIPPET_CHARS: usize = 120;
+// 只有形如数字的字段值参与大小比较
+const NUMERIC_PATTERN: &str = r"'^-?[0-9]+(\.[0-9]+)?([eE][-+]?[0-9]+)?$'";
+
+/// 基于 PostgreSQL 全文搜索的后端
+///
+/// 文档保存在 `search_projects` 与 `search_content` 表中，
+/// 过滤表达式按 MeiliSearch 的语法解析后转换为对 `document` 的条件。
+pub struct PostgresBackend {
+    pool: PgPool,
+}
+
+impl PostgresBackend {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// 与 MeiliSearch 排序索引对应的排序方式
+///
+/// `downloads` 索引的排序规则排在相关度之前，其余索引都是先比较命中的词条数。
+fn sort_order(index: &str) -> Result<&'static str, SearchError> {
+    Ok(match index {
+        "relevance" => "matched_terms DESC, text_rank DESC, downloads DESC",
+        "downloads" => "downloads DESC, matched_terms DESC, text_rank DESC",
+        "follows" => "matched_terms DESC, follows DESC, text_rank DESC",
+        "updated" => "matched_terms DESC, date_modified DESC",
+        "newest" => "matched_terms DESC, date_created DESC",
+        i => return Err(SearchError::InvalidIndex(i.to_string())),
+    })
+}
+
+fn project_vector(project: &UploadSearchProject) -> String {
+    to_tsvector(&[
+        (&project.name, 'A'),
+        (&project.summary, 'B'),
+        (&project.author, 'C'),
+        (project.slug.as_deref().unwrap_or_default(), 'D'),
+    ])
+}
+
+fn content_vector(content: &UploadSearchContent) -> String {
+    to_tsvector(&[
+        (&content.title, 'A'),
+        (&content.body, 'B'),
+        (content.author.as_deref().unwrap_or_default(), 'C'),
+    ])
+}
+
+/// 只保留 MeiliSearch 会返回的字段，其余字段仅用于过滤
+fn project_result(
+    mut document: Value,
+) -> Result<ResultSearchProject, serde_json::Error> {
+    if let Value::Object(fields) = &mut document {
+        fields.retain(|key, _| DEFAULT_DISPLAYED_ATTRIBUTES.contains(&&**key));
+    }
+    serde_json::from_value(document)
+}
+
+/// 命中查询且满足过滤条件的项目，每个项目只保留最匹配的一个版本
+fn push_project_matches(
+    query: &mut QueryBuilder<'_, Postgres>,
+    terms: &[String],
+    filter: Option<&Filter>,
+) {
+    query.push(
+        "SELECT DISTINCT ON (project_id) project_id, document, downloads, \
+         follows, date_created, date_modified, ",
+    );
+    if terms.is_empty() {
+        query.push(
+            "0::bigint AS matched_terms, 0::real AS text_rank \
+             FROM search_projects WHERE true",
+        );
+    } else {
+        let any = terms.join(" | ");
+        query
+            .push("(SELECT COUNT(*) FROM UNNEST(")
+            .push_bind(terms.to_vec())
+            .push(
+                "::text[]) AS q(term) WHERE search_vector @@ q.term::tsquery) \
+                 AS matched_terms, ts_rank(search_vector, ",
+            )
+            .push_bind(any.clone())
+            .push(
+                "::tsquery) AS text_rank FROM search_projects \
+                 WHERE search_vector @@ ",
+            )
+            .push_bind(any)
+            .push("::tsquery");
+    }
+    if let Some(filter) = filter {
+        query.push(" AND ");
+        push_filter(query, filter);
+    }
+    query.push(
+        " ORDER BY project_id, matched_terms DESC, text_rank DESC, \
+         version_id DESC",
+    );
+}
+
+fn push_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &Filter) {
+    match filter {
+        Filter::And(filters) | Filter::Or(filters) => {
+            let separator = if matches!(filter, Filter::And(_)) {
+                " AND "
+            } else {
+                " OR "
+            };
+            query.push("(");
+            for (i, filter) in filters.iter().enumerate() {
+                if i > 0 {
+                    query.push(separator);
+                }
+                push_filter(query, filter);
+            }
+            query.push(")");
+        }
+        Filter::Not(filter) => {
+            query.push("NOT (");
+            push_filter(query, filter);
+            query.push(")");
+        }
+        Filter::Condition { field, condition } => {
+            push_condition(query, field, condition)
+        }
+    }
+}
+
+/// 字段值统一展开为文本集合：数组逐个比较，单值视为只有一个元素
+fn push_values(query: &mut QueryBuilder<'_, Postgres>, field: &str) {
+    query
+        .push(
+            "EXISTS (SELECT 1 FROM jsonb_array_elements_text(\
+             CASE jsonb_typeof(document -> ",
+        )
+        .push_bind(field.to_string())
+        .push(") WHEN 'array' THEN document -> ")
+        .push_bind(field.to_string())
+        .push(" ELSE jsonb_build_array(document -> ")
+        .push_bind(field.to_string())
+        .push(") END) AS v(value) WHERE ");
+}
+
+fn push_numeric(
+    query: &mut QueryBuilder<'_, Postgres>,
+    field: &str,
+    operator: &str,
+    value: &str,
+) {
+    push_values(query, field);
+    query
+        .push("CASE WHEN v.value ~ ")
+        .push(NUMERIC_PATTERN)
+        .push(" THEN v.value::double precision ")
+        .push(operator)
+        .push(" ")
+        .push_bind(value.parse::<f64>().unwrap_or(f64::NAN))
+        .push(" ELSE false END)");
+}
+
+fn push_condition(
+    query: &mut QueryBuilder<'_, Postgres>,
+    field: &str,
+    condition: &Condition,
+) {
+    match condition {
+        Condition::Compare(Operator::Eq, value) => {
+            push_values(query, field);
+            query
+                .push("lower(v.value) = lower(")
+                .push_bind(value.clone())
+                .push("))");
+        }
+        Condition::Compare(Operator::Ne, value) => {
+            query.push("NOT ");
+            push_condition(
+                query,
+                field,
+                &Condition::Compare(Operator::Eq, value.clone()),
+            );
+        }
+        Condition::Compare(operator, value) => {
+            let operator = match operator {
+                Operator::Gt => ">",
+                Operator::Ge => ">=",
+                Operator::Lt => "<",
+                _ => "<=",
+            };
+            push_numeric(query, field, operator, value);
+        }
+        Condition::In(values) => {
+            push_values(query, field);
+            query
+                .push("lower(v.value) = ANY(")
+                .push_bind(
+                    values.iter().map(|x| x.to_lowercase()).collect::<Vec<_>>(),
+                )
+                .push("::text[]))");
+        }
+        Condition::Range(from, to) => {
+            query.push("(");
+            push_numeric(query, field, ">=", from);
+            query.push(" AND ");
+            push_numeric(query, field, "<=", to);
+            query.push(")");
+        }
+        Condition::Exists => {
+            query
+                .push("(document ? ")
+                .push_bind(field.to_string())
+                .push(")");
+        }
+        Condition::IsNull => {
+            query
+                .push("COALESCE(document -> ")
+                .push_bind(field.to_string())
+                .push(" = 'null'::jsonb, false)");
+        }
+        Condition::IsEmpty => {
+            query
+                .push("COALESCE(document -> ")
+                .push_bind(field.to_string())
+                .push(" IN ('\"\"'::jsonb, '[]'::jsonb, '{}'::jsonb), false)");
+        }
+    }
+}
+
+/// 大小比较的值必须是数字，与 MeiliSearch 的报错保持一致
+fn validate_filter(filter: &Filter) -> Result<(), SearchError> {
+    match filter {
+        Filter::And(filters) | Filter::Or(filters) => {
+            filters.iter().try_for_each(validate_filter)
+        }
+        Filter::Not(filter) => validate_filter(filter),
+        Filter::Condition { field, condition } => {
+            let values = match condition {
+                Condition::Compare(Operator::Eq | Operator::Ne, _) => vec![],
+                Condition::Compare(_, value) => vec![value],
+                Condition::Range(from, to) => vec![from, to],
+                _ => vec![],
+            };
+            match values.into_iter().find(|x| x.parse::<f64>().is_err()) {
+                Some(value) => Err(SearchError::InvalidFilter(format!(
+                    "{field} 只能与数字比较，实际为 {value}"
+                ))),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// 截取正文中第一个关键词附近的片段，关键词以 `<em>` 标记
+fn snippet(body: &str, words: &[String]) -> String {
+    let chars = body.chars().collect::<Vec<_>>();
+    let lower = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<_>>();
+    let mut words = words
+        .iter()
+        .map(|x| x.chars().collect::<Vec<_>>())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    words.sort_by_key(|x| std::cmp::Reverse(x.len()));
+
+    let match_at = |i: usize| {
+        words
+            .iter()
+            .find(|word| lower[i..].starts_with(word))
+            .map(|word| word.len())
+    };
+
+    let first = (0..chars.len())
+        .find(|&i| match_at(i).is_some())
+        .unwrap_or_default();
+    let start = first
+        .saturating_sub(SNIPPET_CHARS / 4)
+        .min(chars.len().saturating_sub(SNIPPET_CHARS));
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut i = start;
+    while i < end {
+        match match_at(i) {
+            Some(len) => {
+                snippet.push_str("<em>");
+                snippet.extend(&chars[i..i + len]);
+                snippet.push_str("</em>");
+                i += len;
+            }
+            None => {
+                snippet.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if i < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+async fn insert_projects(
+    projects: &[&UploadSearchProject],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), IndexingError> {
+    let mut version_ids = Vec::new();
+    let mut project_ids = Vec::new();
+    let mut documents = Vec::new();
+    let mut vectors = Vec::new();
+    let mut downloads = Vec::new();
+    let mut follows = Vec::new();
+    let mut created: Vec<DateTime<Utc>> = Vec::new();
+    let mut modified: Vec<DateTime<Utc>> = Vec::new();
+    for project in projects {
+        version_ids.push(project.version_id.clone());
+        project_ids.push(project.project_id.clone());
+        documents.push(serde_json::to_value(project)?);
+        vectors.push(project_vector(project));
+        downloads.push(project.downloads);
+        follows.push(project.follows);
+        created.push(project.date_created);
+        modified.push(project.date_modified);
+    }
+
+    sqlx::query!(
+        "
+        INSERT INTO search_projects (
+            version_id, project_id, document, search_vector,
+            downloads, follows, date_created, date_modified
+        )
+        SELECT version_id, project_id, document, search_vector::tsvector,
+            downloads, follows, date_created, date_modified
+        FROM UNNEST(
+            $1::varchar[], $2::varchar[], $3::jsonb[], $4::text[],
+            $5::int[], $6::int[], $7::timestamptz[], $8::timestamptz[]
+        ) AS t(
+            version_id, project_id, document, search_vector,
+            downloads, follows, date_created, date_modified
+        )
+        ",
+        &version_ids[..],
+        &project_ids[..],
+        &documents[..],
+        &vectors[..],
+        &downloads[..],
+        &follows[..],
+        &created[..],
+        &modified[..],
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_content(
+    documents: &[UploadSearchContent],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), IndexingError> {
+    for chunk in documents.chunks(INSERT_CHUNK_SIZE) {
+        let mut ids = Vec::new();
+        let mut content_types = Vec::new();
+        let mut content_ids = Vec::new();
+        let mut parent_ids = Vec::new();
+        let mut project_ids = Vec::new();
+        let mut values = Vec::new();
+        let mut vectors = Vec::new();
+        let mut modified = Vec::new();
+        for content in chunk {
+            ids.push(content.id.clone());
+            content_types.push(content.content_type.clone());
+            content_ids.push(content.content_id.clone());
+            parent_ids.push(content.parent_id.clone());
+            project_ids.push(content.project_id.clone());
+            values.push(serde_json::to_value(content)?);
+            vectors.push(content_vector(content));
+            modified.push(content.modified_timestamp);
+        }
+
+        sqlx::query!(
+            "
+            INSERT INTO search_content (
+                id, content_type, content_id, parent_id, project_id,
+                document, search_vector, modified_timestamp
+            )
+            SELECT id, content_type, content_id, parent_id, project_id,
+                document, search_vector::tsvector, modified_timestamp
+            FROM UNNEST(
+                $1::varchar[], $2::varchar[], $3::varchar[], $4::varchar[],
+                $5::varchar[], $6::jsonb[], $7::text[], $8::bigint[]
+            ) AS t(
+                id, content_type, content_id, parent_id, project_id,
+                document, search_vector, modified_timestamp
+            )
+            ON CONFLICT (id) DO UPDATE SET
+                content_type = EXCLUDED.content_type,
+                content_id = EXCLUDED.content_id,
+                parent_id = EXCLUDED.parent_id,
+                project_id = EXCLUDED.project_id,
+                document = EXCLUDED.document,
+                search_vector = EXCLUDED.search_vector,
+                modified_timestamp = EXCLUDED.modified_timestamp
+            ",
+            &ids[..],
+            &content_types[..],
+            &content_ids[..],
+            &parent_ids[..] as &[Option<String>],
+            &project_ids[..] as &[Option<String>],
+            &values[..],
+            &vectors[..],
+            &modified[..],
+        )
+        .execute(&mut **transaction)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl SearchBackend for PostgresBackend {
+    async fn search_projects(
+        &self,
+        info: &SearchRequest,
+    ) -> Result<SearchResults, SearchError> {
+        let offset: usize = info.offset.as_deref().unwrap_or("0").parse()?;
+        let index = info.index.as_deref().unwrap_or("relevance");
+        let limit = info
+            .limit
+            .as_deref()
+            .unwrap_or("10")
+            .parse::<usize>()?
+            .clamp(1, 100);
+        let order = sort_order(index)?;
+
+        // 与 MeiliSearch 一样按页返回
+        let hits_per_page = limit;
+        let page = offset / limit + 1;
+
+        let filter = match project_filter(info)? {
+            Some(filter) => parse_filter(&filter)?,
+            None => None,
+        };
+        if let Some(filter) = &filter {
+            validate_filter(filter)?;
+        }
+        let terms = query_terms(info.query.as_deref().unwrap_or_default());
+
+        let mut query = QueryBuilder::new(
+            "SELECT document, COUNT(*) OVER () AS total_hits FROM (",
+        );
+        push_project_matches(&mut query, &terms, filter.as_ref());
+        query
+            .push(") matches ORDER BY ")
+            .push(order)
+            .push(", project_id LIMIT ")
+            .push_bind(hits_per_page as i64)
+            .push(" OFFSET ")
+            .push_bind(((page - 1) * hits_per_page) as i64);
+        let rows = query.build().fetch_all(&self.pool).await?;
+
+        let mut total_hits = match rows.first() {
+            Some(row) => row.try_get::<i64, _>("total_hits")? as usize,
+            None => 0,
+        };
+        // 页码超出范围时仍然返回总数
+        if rows.is_empty() && page > 1 {
+            let mut query = QueryBuilder::new("SELECT COUNT(*) FROM (");
+            push_project_matches(&mut query, &terms, filter.as_ref());
+            query.push(") matches");
+            total_hits = query
+                .build_query_scalar::<i64>()
+                .fetch_one(&self.pool)
+                .await? as usize;
+        }
+
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            hits.push(project_result(row.try_get("document")?)?);
+        }
+
+        Ok(SearchResults {
+            hits,
+            page,
+            hits_per_page,
+            total_hits,
+        })
+    }
+
+    async fn replace_projects(
+        &self,
+        projects: &[UploadSearchProject],
+        _additional_fields: &[String],
+    ) -> Result<(), IndexingError> {
+        let mut seen = HashSet::new();
+        let projects = projects
+            .iter()
+            .filter(|x| seen.insert(&x.version_id))
+            .collect::<Vec<_>>();
+
+        // 在同一个事务中清空并重建，重建期间的搜索仍然读取旧数据
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM search_projects")
+            .execute(&mut *transaction)
+            .await?;
+        for chunk in projects.chunks(INSERT_CHUNK_SIZE) {
+            insert_projects(chunk, &mut transaction).await?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_projects(
+        &self,
+        project_ids: &[String],
+        projects: &[UploadSearchProject],
+    ) -> Result<(), IndexingError> {
+        let projects = projects.iter().collect::<Vec<_>>();
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "
+            DELETE FROM search_projects
+            WHERE project_id = ANY($1)
+            ",
+            project_ids,
+        )
+        .execute(&mut *transaction)
+        .await?;
+        for chunk in projects.chunks(INSERT_CHUNK_SIZE) {
+            insert_projects(chunk, &mut transaction).await?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn remove_versions(
+        &self,
+        ids: &[VersionId],
+    ) -> Result<(), IndexingError> {
+        let ids = ids.iter().map(|x| to_base62(x.0)).collect::<Vec<_>>();
+        sqlx::query!(
+            "
+            DELETE FROM search_projects
+            WHERE version_id = ANY($1)
+            ",
+            &ids[..],
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn search_content(
+        &self,
+        info: &ContentSearchRequest,
+        project_id: Option<&str>,
+        hidden_projects: &[String],
+    ) -> Result<ContentSearchResults, SearchError> {
+        let types = content_types(info)?;
+
+        let limit = info.limit.unwrap_or(10).clamp(1, 100);
+        let page = info.offset.unwrap_or_default() / limit + 1;
+
+        let query = info.query.as_deref().unwrap_or_default();
+        let terms = query_terms(query);
+        let any = (!terms.is_empty()).then(|| terms.join(" | "));
+
+        let counts = sqlx::query!(
+            "
+            SELECT content_type, COUNT(*) AS \"count!\"
+            FROM search_content
+            WHERE (cardinality($1::text[]) = 0 OR content_type = ANY($1))
+                AND ($2::text IS NULL OR project_id = $2)
+                AND ($3::text IS NULL OR search_vector @@ $3::text::tsquery)
+                AND (project_id IS NULL OR NOT project_id = ANY($4))
+            GROUP BY content_type
+            ",
+            &types[..],
+            project_id,
+            any.as_deref(),
+            hidden_projects,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let rows = sqlx::query!(
+            "
+            SELECT document
+            FROM search_content
+            WHERE (cardinality($1::text[]) = 0 OR content_type = ANY($1))
+                AND ($2::text IS NULL OR project_id = $2)
+                AND ($3::text IS NULL OR search_vector @@ $3::text::tsquery)
+                AND (project_id IS NULL OR NOT project_id = ANY($7))
+            ORDER BY
+                (
+                    SELECT COUNT(*) FROM UNNEST($4::text[]) AS q(term)
+                    WHERE search_vector @@ q.term::tsquery
+                ) DESC,
+                COALESCE(ts_rank(search_vector, $3::text::tsquery), 0) DESC,
+                modified_timestamp DESC
+            LIMIT $5 OFFSET $6
+            ",
+            &types[..],
+            project_id,
+            any.as_deref(),
+            &terms[..],
+            limit as i64,
+            ((page - 1) * limit) as i64,
+            hidden_projects,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let words = highlight_words(query);
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let content: UploadSearchContent =
+                serde_json::from_value(row.document)?;
+            let snippet = snippet(&content.body, &words);
+            hits.push(content.into_result(snippet));
+        }
+
+        let types = counts
+            .into_iter()
+            .map(|x| (x.content_type, x.count as usize))
+            .collect::<HashMap<_, _>>();
+
+        Ok(ContentSearchResults {
+            hits,
+            page,
+            hits_per_page: limit,
+            total_hits: types.values().sum(),
+            types,
+        })
+    }
+
+    async fn replace_content(
+        &self,
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM search_content")
+            .execute(&mut *transaction)
+            .await?;
+        insert_content(documents, &mut transaction).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_content(
+        &self,
+        scopes: &[ContentScope],
+        documents: &[UploadSearchContent],
+    ) -> Result<(), IndexingError> {
+        let mut transaction = self.pool.begin().await?;
+        for scope in scopes {
+            match scope {
+                ContentScope::Content(content_type, id) => {
+                    sqlx::query!(
+                        "
+                        DELETE FROM search_content
+                        WHERE content_type = $1 AND content_id = $2
+                        ",
+                        content_type,
+                        id,
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+                ContentScope::Project(content_type, id) => {
+                    sqlx::query!(
+                        "
+                        DELETE FROM search_content
+                        WHERE content_type = $1 AND project_id = $2
+                        ",
+                        content_type,
+                        id,
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+                ContentScope::Parent(content_type, id) => {
+                    sqlx::query!(
+                        "
+                        DELETE FROM search_content
+                        WHERE content_type = $1 AND parent_id = $2
+                        ",
+                        content_type,
+                        id,
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+            }
+        }
+        insert_content(documents, &mut transaction).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
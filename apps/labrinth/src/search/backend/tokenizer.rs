@@ -0,0 +1,174 @@
+//! 数据库搜索使用的分词
+//!
+//! PostgreSQL 自带的分词配置不会切分中文，这里在应用内完成切分：
+//! 中日韩文字按单字与相邻双字生成词项，其他文字按连续的字母数字切分并转为小写。
+//! 生成的结果直接写成 `tsvector` / `tsquery` 字面量，与数据库的区域设置无关。
+
+/// 文本中的一段：中日韩文字连续段或其他文字的单词
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Word(String),
+    Cjk(Vec<char>),
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}' // 韩文音节
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}')
+}
+
+pub fn segments(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current: Option<Segment> = None;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            match &mut current {
+                Some(Segment::Cjk(chars)) => chars.push(c),
+                _ => {
+                    segments.extend(current.take());
+                    current = Some(Segment::Cjk(vec![c]));
+                }
+            }
+        } else if c.is_alphanumeric() {
+            match &mut current {
+                Some(Segment::Word(word)) => word.extend(c.to_lowercase()),
+                _ => {
+                    segments.extend(current.take());
+                    current = Some(Segment::Word(c.to_lowercase().collect()));
+                }
+            }
+        } else {
+            segments.extend(current.take());
+        }
+    }
+    segments.extend(current);
+    segments
+}
+
+/// 索引用的词项，按出现顺序排列
+///
+/// 中文连续段同时生成单字与双字，单字查询和多字查询都能命中。
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for segment in segments(text) {
+        match segment {
+            Segment::Word(word) => tokens.push(word),
+            Segment::Cjk(chars) => {
+                for (i, c) in chars.iter().enumerate() {
+                    tokens.push(c.to_string());
+                    if let Some(next) = chars.get(i + 1) {
+                        tokens.push([*c, *next].iter().collect());
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn quote_lexeme(lexeme: &str) -> String {
+    format!("'{}'", lexeme.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// 生成带权重的 `tsvector` 字面量
+///
+/// `fields` 为 (文本, 权重) 列表，权重为 `A`~`D`；位置在各字段间连续递增。
+pub fn to_tsvector(fields: &[(&str, char)]) -> String {
+    let mut position = 0usize;
+    let mut lexemes = Vec::new();
+    for (text, weight) in fields {
+        for token in tokenize(text) {
+            position += 1;
+            lexemes.push(format!(
+                "{}:{}{}",
+                quote_lexeme(&token),
+                position,
+                weight
+            ));
+        }
+    }
+    lexemes.join(" ")
+}
+
+/// 把查询拆成若干词条，每个词条是一个 `tsquery` 字面量
+///
+/// 中文连续段要求其中的双字全部命中；最后一个单词按前缀匹配，便于边输入边搜索。
+pub fn query_terms(query: &str) -> Vec<String> {
+    let segments = segments(query);
+    let prefix_last = query.chars().last().is_some_and(|c| !c.is_whitespace());
+
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| match segment {
+            Segment::Word(word) => {
+                if prefix_last && i == segments.len() - 1 {
+                    format!("{}:*", quote_lexeme(word))
+                } else {
+                    quote_lexeme(word)
+                }
+            }
+            Segment::Cjk(chars) if chars.len() == 1 => {
+                quote_lexeme(&chars[0].to_string())
+            }
+            Segment::Cjk(chars) => format!(
+                "({})",
+                chars
+                    .windows(2)
+                    .map(|x| quote_lexeme(&x.iter().collect::<String>()))
+                    .collect::<Vec<_>>()
+                    .join(" & ")
+            ),
+        })
+        .collect()
+}
+
+/// 用于高亮摘要的关键词：单词与中文连续段
+pub fn highlight_words(query: &str) -> Vec<String> {
+    segments(query)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Word(word) => word,
+            Segment::Cjk(chars) => chars.into_iter().collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_cjk_into_unigrams_and_bigrams() {
+        assert_eq!(
+            tokenize("我的世界 Fabric-API"),
+            vec![
+                "我", "我的", "的", "的世", "世", "世界", "界", "fabric", "api"
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_weighted_tsvector() {
+        assert_eq!(
+            to_tsvector(&[("机械", 'A'), ("it's", 'B')]),
+            "'机':1A '机械':2A '械':3A 'it':4B 's':5B"
+        );
+    }
+
+    #[test]
+    fn builds_query_terms() {
+        assert_eq!(query_terms("科技 create"), vec!["('科技')", "'create':*"]);
+        assert_eq!(
+            query_terms("我的世界 "),
+            vec!["('我的' & '的世' & '世界')"]
+        );
+        assert_eq!(query_terms("龙"), vec!["'龙'"]);
+        assert!(query_terms("  ").is_empty());
+    }
+}
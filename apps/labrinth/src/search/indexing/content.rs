@@ -0,0 +1,323 @@
+//! 百科、论坛与问题等站内内容的索引
+
+use futures::TryStreamExt;
+use log::{info, warn};
+use sqlx::postgres::PgPool;
+
+use super::IndexingError;
+use crate::database::models::{
+    DiscussionId, IssuesCommentsId, IssuesId, PostId, ProjectId,
+};
+use crate::models::ids::base62_impl::to_base62;
+use crate::search::{SearchConfig, UploadSearchContent};
+
+pub const CONTENT_INDEX: &str = "content";
+
+// 写入索引的正文最大字符数，超出部分不参与搜索
+const MAX_BODY_CHARS: usize = 20000;
+
+/// 需要更新索引的内容
+#[derive(Debug, Clone, Copy)]
+pub enum ContentTarget {
+    /// 项目的全部百科页面
+    ProjectWikis(ProjectId),
+    /// 论坛帖子本身；帖子被删除时其下的回复一并移除
+    Discussion(DiscussionId),
+    Post(PostId),
+    Issue(IssuesId),
+    IssueComment(IssuesCommentsId),
+}
+
+/// 索引中需要移除的文档范围，第一个字段为内容类型
+#[derive(Debug, Clone)]
+pub enum ContentScope {
+    /// 单个内容
+    Content(&'static str, String),
+    /// 项目下的全部内容
+    Project(&'static str, String),
+    /// 帖子或问题下的全部回复
+    Parent(&'static str, String),
+}
+
+fn content_document_id(content_type: &str, id: i64) -> String {
+    format!("{}_{}", content_type, to_base62(id as u64))
+}
+
+fn truncate_body(body: String) -> String {
+    match body.char_indices().nth(MAX_BODY_CHARS) {
+        Some((index, _)) => body[..index].to_string(),
+        None => body,
+    }
+}
+
+/// 内容变更后更新索引
+///
+/// 索引失败只记录日志，不影响已经提交的修改；定时的全量索引会修正遗漏。
+pub async fn update_content_index(
+    target: ContentTarget,
+    pool: &PgPool,
+    config: &SearchConfig,
+) {
+    if let Err(e) = update_content_index_inner(target, pool, config).await {
+        warn!("更新内容索引失败 {:?}：{:?}", target, e);
+    }
+}
+
+async fn update_content_index_inner(
+    target: ContentTarget,
+    pool: &PgPool,
+    config: &SearchConfig,
+) -> Result<(), IndexingError> {
+    let (scopes, documents) = match target {
+        ContentTarget::ProjectWikis(project_id) => (
+            vec![ContentScope::Project(
+                "wiki",
+                to_base62(project_id.0 as u64),
+            )],
+            load_wikis(pool, Some(project_id)).await?,
+        ),
+        ContentTarget::Discussion(discussion_id) => {
+            let id = to_base62(discussion_id.0 as u64);
+            let documents = load_discussions(pool, Some(discussion_id)).await?;
+            let mut scopes = vec![ContentScope::Content("forum", id.clone())];
+            if documents.is_empty() {
+                scopes.push(ContentScope::Parent("post", id));
+            }
+            (scopes, documents)
+        }
+        ContentTarget::Post(post_id) => (
+            vec![ContentScope::Content("post", to_base62(post_id.0 as u64))],
+            load_posts(pool, Some(post_id)).await?,
+        ),
+        ContentTarget::Issue(issue_id) => (
+            vec![ContentScope::Content("issue", to_base62(issue_id.0 as u64))],
+            load_issues(pool, Some(issue_id)).await?,
+        ),
+        ContentTarget::IssueComment(comment_id) => (
+            vec![ContentScope::Content(
+                "issue_comment",
+                to_base62(comment_id.0 as u64),
+            )],
+            load_issue_comments(pool, Some(comment_id)).await?,
+        ),
+    };
+
+    // 先按范围删除旧文档，再写入最新内容，这样已删除的页面也会从索引中移除
+    config
+        .make_backend(pool)
+        .update_content(&scopes, &documents)
+        .await
+}
+
+/// 全量重建内容索引
+pub async fn index_content(
+    pool: &PgPool,
+    config: &SearchConfig,
+) -> Result<(), IndexingError> {
+    info!("索引站内内容。");
+
+    let mut documents = load_wikis(pool, None).await?;
+    documents.extend(load_discussions(pool, None).await?);
+    documents.extend(load_posts(pool, None).await?);
+    documents.extend(load_issues(pool, None).await?);
+    documents.extend(load_issue_comments(pool, None).await?);
+    config
+        .make_backend(pool)
+        .replace_content(&documents)
+        .await?;
+
+    info!("完成索引 {} 条站内内容。", documents.len());
+    Ok(())
+}
+
+/// 已发布的百科页面；付费项目的页面标记为需要购买
+async fn load_wikis(
+    pool: &PgPool,
+    project_id: Option<ProjectId>,
+) -> Result<Vec<UploadSearchContent>, IndexingError> {
+    let documents = sqlx::query!(
+        "
+        SELECT w.id, w.mod_id, w.title, w.body, w.slug, w.created, w.updated, m.is_paid
+        FROM wikis w
+        INNER JOIN mods m ON m.id = w.mod_id
+        WHERE w.draft = false AND ($1::bigint IS NULL OR w.mod_id = $1)
+        ",
+        project_id.map(|x| x.0),
+    )
+    .fetch(pool)
+    .map_ok(|m| UploadSearchContent {
+        id: content_document_id("wiki", m.id),
+        content_type: "wiki".to_string(),
+        content_id: to_base62(m.id as u64),
+        parent_id: None,
+        project_id: Some(to_base62(m.mod_id as u64)),
+        title: m.title,
+        body: truncate_body(m.body),
+        slug: Some(m.slug),
+        author: None,
+        category: None,
+        state: None,
+        requires_purchase: m.is_paid,
+        created_timestamp: m.created.timestamp(),
+        modified_timestamp: m.updated.timestamp(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(documents)
+}
+
+async fn load_discussions(
+    pool: &PgPool,
+    discussion_id: Option<DiscussionId>,
+) -> Result<Vec<UploadSearchContent>, IndexingError> {
+    let documents = sqlx::query!(
+        "
+        SELECT d.id, d.title, d.content, d.category, d.state, d.created_at, d.updated_at,
+               u.username AS \"username?\",
+               (SELECT m.id FROM mods m WHERE m.forum = d.id LIMIT 1) AS project_id
+        FROM discussions d
+        LEFT JOIN users u ON u.id = d.user_id
+        WHERE d.deleted = false AND ($1::bigint IS NULL OR d.id = $1)
+        ",
+        discussion_id.map(|x| x.0),
+    )
+    .fetch(pool)
+    .map_ok(|m| UploadSearchContent {
+        id: content_document_id("forum", m.id),
+        content_type: "forum".to_string(),
+        content_id: to_base62(m.id as u64),
+        parent_id: None,
+        project_id: m.project_id.map(|x| to_base62(x as u64)),
+        title: m.title,
+        body: truncate_body(m.content),
+        slug: None,
+        author: m.username,
+        category: Some(m.category),
+        state: Some(m.state),
+        requires_purchase: false,
+        created_timestamp: m.created_at.timestamp(),
+        modified_timestamp: m.updated_at.unwrap_or(m.created_at).timestamp(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(documents)
+}
+
+/// 论坛回复，标题使用所属帖子的标题
+async fn load_posts(
+    pool: &PgPool,
+    post_id: Option<PostId>,
+) -> Result<Vec<UploadSearchContent>, IndexingError> {
+    let documents = sqlx::query!(
+        "
+        SELECT p.id, p.discussion_id, p.content, p.created_at, p.updated_at,
+               d.title, d.category, u.username AS \"username?\",
+               (SELECT m.id FROM mods m WHERE m.forum = d.id LIMIT 1) AS project_id
+        FROM posts p
+        INNER JOIN discussions d ON d.id = p.discussion_id
+        LEFT JOIN users u ON u.id = p.user_id
+        WHERE p.deleted = false AND d.deleted = false AND ($1::bigint IS NULL OR p.id = $1)
+        ",
+        post_id.map(|x| x.0),
+    )
+    .fetch(pool)
+    .map_ok(|m| UploadSearchContent {
+        id: content_document_id("post", m.id),
+        content_type: "post".to_string(),
+        content_id: to_base62(m.id as u64),
+        parent_id: Some(to_base62(m.discussion_id as u64)),
+        project_id: m.project_id.map(|x| to_base62(x as u64)),
+        title: m.title,
+        body: truncate_body(m.content),
+        slug: None,
+        author: m.username,
+        category: Some(m.category),
+        state: None,
+        requires_purchase: false,
+        created_timestamp: m.created_at.timestamp(),
+        modified_timestamp: m.updated_at.unwrap_or(m.created_at).timestamp(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(documents)
+}
+
+async fn load_issues(
+    pool: &PgPool,
+    issue_id: Option<IssuesId>,
+) -> Result<Vec<UploadSearchContent>, IndexingError> {
+    let documents = sqlx::query!(
+        "
+        SELECT i.id, i.mod_id, i.title, i.body, i.state, i.created_at, i.updated_at,
+               u.username AS \"username?\"
+        FROM issues i
+        LEFT JOIN users u ON u.id = i.author_id
+        WHERE i.deleted = false AND ($1::bigint IS NULL OR i.id = $1)
+        ",
+        issue_id.map(|x| x.0),
+    )
+    .fetch(pool)
+    .map_ok(|m| UploadSearchContent {
+        id: content_document_id("issue", m.id),
+        content_type: "issue".to_string(),
+        content_id: to_base62(m.id as u64),
+        parent_id: None,
+        project_id: Some(to_base62(m.mod_id as u64)),
+        title: m.title,
+        body: truncate_body(m.body),
+        slug: None,
+        author: m.username,
+        category: None,
+        state: Some(m.state),
+        requires_purchase: false,
+        created_timestamp: m.created_at.timestamp(),
+        modified_timestamp: m.updated_at.timestamp(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(documents)
+}
+
+/// 问题下的回复评论，系统生成的状态变更通知不参与搜索
+async fn load_issue_comments(
+    pool: &PgPool,
+    comment_id: Option<IssuesCommentsId>,
+) -> Result<Vec<UploadSearchContent>, IndexingError> {
+    let documents = sqlx::query!(
+        "
+        SELECT ic.id, ic.issue_id, ic.body, ic.created_at, ic.updated_at,
+               i.mod_id, i.title, u.username AS \"username?\"
+        FROM issue_comments ic
+        INNER JOIN issues i ON i.id = ic.issue_id
+        LEFT JOIN users u ON u.id = ic.author_id
+        WHERE ic.deleted = false AND i.deleted = false AND ic.comment_type <> 'notification'
+              AND ($1::bigint IS NULL OR ic.id = $1)
+        ",
+        comment_id.map(|x| x.0),
+    )
+    .fetch(pool)
+    .map_ok(|m| UploadSearchContent {
+        id: content_document_id("issue_comment", m.id),
+        content_type: "issue_comment".to_string(),
+        content_id: to_base62(m.id as u64),
+        parent_id: Some(to_base62(m.issue_id as u64)),
+        project_id: Some(to_base62(m.mod_id as u64)),
+        title: m.title,
+        body: truncate_body(m.body),
+        slug: None,
+        author: m.username,
+        category: None,
+        state: None,
+        requires_purchase: false,
+        created_timestamp: m.created_at.timestamp(),
+        modified_timestamp: m.updated_at.timestamp(),
+    })
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(documents)
+}
@@ -23,8 +23,13 @@ use sqlx::postgres::PgPool;
 /// 每批处理的项目数量，避免大查询一次性占满数据库连接导致 API 请求超时
 const INDEX_BATCH_SIZE: usize = 500;
 
+/// 生成项目的搜索文档
+///
+/// `project_ids` 为 None 时处理全部可搜索项目；否则只处理其中仍可搜索的项目，
+/// 不可搜索或已删除的项目不会生成文档。
 pub async fn index_local(
     pool: &PgPool,
+    project_ids: Option<&[ProjectId]>,
 ) -> Result<Vec<UploadSearchProject>, IndexingError> {
     info!("索引本地项目");
 
@@ -48,13 +53,14 @@ pub async fn index_local(
         SELECT m.id id, m.name name, m.summary summary, m.downloads downloads, m.follows follows,
         m.icon_url icon_url, m.updated updated, m.approved approved, m.published, m.license license, m.slug slug, m.color
         FROM mods m
-        WHERE m.status = ANY($1)
+        WHERE m.status = ANY($1) AND ($2::bigint[] IS NULL OR m.id = ANY($2))
         GROUP BY m.id;
         ",
         &*crate::models::projects::ProjectStatus::iterator()
         .filter(|x| x.is_searchable())
         .map(|x| x.to_string())
         .collect::<Vec<String>>(),
+        project_ids.map(|x| x.iter().map(|x| x.0).collect::<Vec<_>>()),
     )
         .fetch(pool)
         .map_ok(|m| {
@@ -1,14 +1,12 @@
 /// 此模块用于从任何来源进行索引。
+pub mod content;
 pub mod local_import;
 
 use std::error::Error;
-use std::time::Duration;
 
+use crate::database::models::SearchIndexQueueEntry;
 use crate::database::redis::RedisPool;
-use crate::models::ids::base62_impl::to_base62;
 use crate::search::{SearchConfig, UploadSearchProject};
-use futures::StreamExt;
-use futures::stream::FuturesUnordered;
 use local_import::index_local;
 use log::info;
 use meilisearch_sdk::client::{Client, SwapIndexes};
@@ -37,44 +35,22 @@ pub enum IndexingError {
 // 太大 (>10MiB) 则请求失败。这个块大小
 // 假设每个项目平均大小为 4KiB 以避免这个限制。
 const MEILISEARCH_CHUNK_SIZE: usize = 10000000;
-const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+pub(crate) const TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(60);
+// 每批从索引队列中取出的项目数量
+const QUEUE_BATCH_SIZE: i64 = 100;
 
-// Modrinth 上游修复 97e4d8e13: 确保版本在路由执行结束前从搜索索引中删除
-// 改进删除逻辑，等待 Meilisearch 任务完成后再返回
+// 全量重建与增量更新互斥，避免 MeiliSearch 交换索引时丢掉重建期间的增量写入
+static INDEXING_LOCK: tokio::sync::Mutex<()> =
+    tokio::sync::Mutex::const_new(());
+
+/// 从搜索索引中删除版本
 pub async fn remove_documents(
     ids: &[crate::models::ids::VersionId],
     config: &SearchConfig,
-) -> Result<(), meilisearch_sdk::errors::Error> {
-    let mut indexes = get_indexes_for_indexing(config, false).await?;
-    let mut indexes_next = get_indexes_for_indexing(config, true).await?;
-    indexes.append(&mut indexes_next);
-
-    let client = config.make_client()?;
-    let client = &client;
-    let mut deletion_tasks = FuturesUnordered::new();
-
-    for index in &indexes {
-        deletion_tasks.push(async move {
-            // Meilisearch 任务提交后异步执行，需要等待一定时间确保完成
-            index
-                .delete_documents(
-                    &ids.iter().map(|x| to_base62(x.0)).collect::<Vec<_>>(),
-                )
-                .await?
-                .wait_for_completion(
-                    client,
-                    None,
-                    Some(Duration::from_secs(15)),
-                )
-                .await
-        });
-    }
-
-    while let Some(result) = deletion_tasks.next().await {
-        result?;
-    }
-
-    Ok(())
+    pool: &PgPool,
+) -> Result<(), IndexingError> {
+    config.make_backend(pool).remove_versions(ids).await
 }
 
 pub async fn index_projects(
@@ -84,16 +60,7 @@ pub async fn index_projects(
 ) -> Result<(), IndexingError> {
     info!("索引项目。");
 
-    // 首先，确保当前索引存在（这样不会发生错误- 当前索引应该是空的最坏情况，而不是缺失）
-    get_indexes_for_indexing(config, false).await?;
-
-    // 然后，如果存在，删除下一个索引
-    let indices = get_indexes_for_indexing(config, true).await?;
-    for index in indices {
-        index.delete().await?;
-    }
-    // 重新创建下一个索引进行索引
-    let indices = get_indexes_for_indexing(config, true).await?;
+    let _guard = INDEXING_LOCK.lock().await;
 
     let all_loader_fields =
         crate::database::models::loader_fields::LoaderField::get_fields_all(
@@ -104,17 +71,11 @@ pub async fn index_projects(
         .map(|x| x.field)
         .collect::<Vec<_>>();
 
-    let uploads = index_local(&pool).await?;
-    add_projects(&indices, &uploads, all_loader_fields.clone(), config).await?;
-
-    // 交换索引
-    swap_index(config, "projects").await?;
-    swap_index(config, "projects_filtered").await?;
-
-    // 删除现在已过时的索引
-    for index in indices {
-        index.delete().await?;
-    }
+    let uploads = index_local(&pool, None).await?;
+    config
+        .make_backend(&pool)
+        .replace_projects(&uploads, &all_loader_fields)
+        .await?;
     // let ups = uploads.clone();
     // 初始化一个不重复数值的Set数组
 
@@ -155,6 +116,49 @@ pub async fn index_projects(
     Ok(())
 }
 
+/// 处理索引队列，只重建队列中项目的文档，返回处理的项目数量
+///
+/// 项目已删除或不再可搜索时，它的文档会被移除。
+pub async fn index_queued_projects(
+    pool: &PgPool,
+    config: &SearchConfig,
+) -> Result<usize, IndexingError> {
+    let _guard = INDEXING_LOCK.lock().await;
+    let backend = config.make_backend(pool);
+
+    let mut processed = 0;
+    loop {
+        let entries =
+            SearchIndexQueueEntry::get_batch(QUEUE_BATCH_SIZE, pool).await?;
+        if entries.is_empty() {
+            break;
+        }
+
+        let project_ids =
+            entries.iter().map(|x| x.project_id).collect::<Vec<_>>();
+        let uploads = index_local(pool, Some(&project_ids)).await?;
+        backend
+            .update_projects(
+                &project_ids
+                    .iter()
+                    .map(|x| {
+                        crate::models::ids::ProjectId::from(*x).to_string()
+                    })
+                    .collect::<Vec<_>>(),
+                &uploads,
+            )
+            .await?;
+        SearchIndexQueueEntry::remove(&entries, pool).await?;
+
+        processed += entries.len();
+        if entries.len() < QUEUE_BATCH_SIZE as usize {
+            break;
+        }
+    }
+
+    Ok(processed)
+}
+
 async fn _submit_urls(urls: Vec<String>) -> Result<(), Box<dyn Error>> {
     // let urls = vec![
     //     "https://bbsmc.net/modpack/snk",
@@ -386,7 +390,7 @@ fn default_settings() -> Settings {
         })
 }
 
-const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
+pub(crate) const DEFAULT_DISPLAYED_ATTRIBUTES: &[&str] = &[
     "project_id",
     "version_id",
     "project_types",
@@ -2,16 +2,21 @@ use crate::models::error::ApiError;
 use crate::models::projects::SearchRequest;
 use actix_web::HttpResponse;
 use actix_web::http::StatusCode;
+use backend::{
+    MeilisearchBackend, PostgresBackend, SearchBackend, SearchBackendKind,
+};
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use meilisearch_sdk::client::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sqlx::PgPool;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Write;
 use thiserror::Error;
 
+pub mod backend;
 pub mod indexing;
 
 #[derive(Error, Debug)]
@@ -28,6 +33,12 @@ pub enum SearchError {
     Env(#[from] dotenvy::Error),
     #[error("无效的排序索引: {0}")]
     InvalidIndex(String),
+    #[error("无效的内容类型: {0}")]
+    InvalidContentType(String),
+    #[error("无效的过滤条件: {0}")]
+    InvalidFilter(String),
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlx::Error),
 }
 
 impl actix_web::ResponseError for SearchError {
@@ -38,7 +49,10 @@ impl actix_web::ResponseError for SearchError {
             SearchError::Serde(..) => StatusCode::BAD_REQUEST,
             SearchError::IntParsing(..) => StatusCode::BAD_REQUEST,
             SearchError::InvalidIndex(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidContentType(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidFilter(..) => StatusCode::BAD_REQUEST,
             SearchError::FormatError(..) => StatusCode::BAD_REQUEST,
+            SearchError::Database(..) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
@@ -50,7 +64,10 @@ impl actix_web::ResponseError for SearchError {
                 SearchError::Serde(..) => "invalid_input",
                 SearchError::IntParsing(..) => "invalid_input",
                 SearchError::InvalidIndex(..) => "invalid_input",
+                SearchError::InvalidContentType(..) => "invalid_input",
+                SearchError::InvalidFilter(..) => "invalid_input",
                 SearchError::FormatError(..) => "invalid_input",
+                SearchError::Database(..) => "database_error",
             },
             description: self.to_string(),
         })
@@ -59,6 +76,7 @@ impl actix_web::ResponseError for SearchError {
 
 #[derive(Debug, Clone)]
 pub struct SearchConfig {
+    pub backend: SearchBackendKind,
     pub address: String,
     pub key: String,
     pub meta_namespace: String,
@@ -68,18 +86,46 @@ impl SearchConfig {
     // 如果环境变量未设置，则抛出错误，
     // 但这些错误已经在启动时检查过了。
     pub fn new(meta_namespace: Option<String>) -> Self {
-        let address =
-            dotenvy::var("MEILISEARCH_ADDR").expect("MEILISEARCH_ADDR 未设置");
-        let key =
-            dotenvy::var("MEILISEARCH_KEY").expect("MEILISEARCH_KEY 未设置");
+        let backend = SearchBackendKind::from_env()
+            .expect("指定了无效的搜索后端。启动中止！");
+
+        // 数据库搜索不需要 MeiliSearch 的连接信息
+        let (address, key) = match backend {
+            SearchBackendKind::Meilisearch => (
+                dotenvy::var("MEILISEARCH_ADDR")
+                    .expect("MEILISEARCH_ADDR 未设置"),
+                dotenvy::var("MEILISEARCH_KEY")
+                    .expect("MEILISEARCH_KEY 未设置"),
+            ),
+            SearchBackendKind::Postgres => (
+                dotenvy::var("MEILISEARCH_ADDR").unwrap_or_default(),
+                dotenvy::var("MEILISEARCH_KEY").unwrap_or_default(),
+            ),
+        };
 
         Self {
+            backend,
             address,
             key,
             meta_namespace: meta_namespace.unwrap_or_default(),
         }
     }
 
+    /// 按配置创建搜索后端
+    pub fn make_backend(
+        &self,
+        pool: &PgPool,
+    ) -> Box<dyn SearchBackend + Send + Sync> {
+        match self.backend {
+            SearchBackendKind::Meilisearch => {
+                Box::new(MeilisearchBackend::new(self.clone()))
+            }
+            SearchBackendKind::Postgres => {
+                Box::new(PostgresBackend::new(pool.clone()))
+            }
+        }
+    }
+
     pub fn make_client(
         &self,
     ) -> Result<Client, meilisearch_sdk::errors::Error> {
@@ -188,130 +234,230 @@ pub fn get_sort_index(
     })
 }
 
-pub async fn search_for_project(
-    info: &SearchRequest,
-    config: &SearchConfig,
-) -> Result<SearchResults, SearchError> {
-    let client = Client::new(&*config.address, Some(&*config.key))?;
-
-    let offset: usize = info.offset.as_deref().unwrap_or("0").parse()?;
-    let index = info.index.as_deref().unwrap_or("relevance");
-    let limit = info
-        .limit
-        .as_deref()
-        .unwrap_or("10")
-        .parse::<usize>()?
-        .clamp(1, 100);
-
-    let sort = get_sort_index(config, index)?;
-    let meilisearch_index = client.get_index(sort.0).await?;
+/// 把请求中的 `new_filters`，或旧版的 `facets`、`filters`、`version`
+/// 合并为 MeiliSearch 过滤表达式；没有任何条件时返回 None
+fn project_filter(info: &SearchRequest) -> Result<Option<String>, SearchError> {
+    if let Some(new_filters) = info.new_filters.as_deref() {
+        return Ok(Some(new_filters.to_string()));
+    }
 
     let mut filter_string = String::new();
 
-    // 将 offset 和 limit 转换为 page 和 hits_per_page
-    let hits_per_page = limit;
-    let page = offset / limit + 1;
-
-    let results = {
-        let mut query = meilisearch_index.search();
-        query
-            .with_page(page)
-            .with_hits_per_page(hits_per_page)
-            .with_query(info.query.as_deref().unwrap_or_default())
-            .with_sort(&sort.1);
-
-        if let Some(new_filters) = info.new_filters.as_deref() {
-            query.with_filter(new_filters);
-        } else {
-            let facets = if let Some(facets) = &info.facets {
-                Some(serde_json::from_str::<Vec<Vec<Value>>>(facets)?)
-            } else {
-                None
-            };
-
-            let filters: Cow<_> =
-                match (info.filters.as_deref(), info.version.as_deref()) {
-                    (Some(f), Some(v)) => format!("({f}) AND ({v})").into(),
-                    (Some(f), None) => f.into(),
-                    (None, Some(v)) => v.into(),
-                    (None, None) => "".into(),
-                };
-
-            if let Some(facets) = facets {
-                // Search 现在可以 *可选地* 有第三个内部数组：So Vec(AND)<Vec(OR)<Vec(AND)< _ >>>
-                // 对于每个内部 facet，我们将检查它是否可以被反序列化为 Vec<&str>，如果是，则进行反序列化。
-                // 如果不是，我们假设它是一个单一的 facet 并将其包装在 Vec 中。
-                let facets: Vec<Vec<Vec<String>>> = facets
-                    .into_iter()
-                    .map(|facets| {
-                        facets
-                            .into_iter()
-                            .map(|facet| {
-                                if facet.is_array() {
-                                    serde_json::from_value::<Vec<String>>(facet)
-                                        .unwrap_or_default()
-                                } else {
-                                    vec![
-                                        serde_json::from_value::<String>(facet)
-                                            .unwrap_or_default(),
-                                    ]
-                                }
-                            })
-                            .collect_vec()
-                    })
-                    .collect_vec();
-
-                filter_string.push('(');
-                for (index, facet_outer_list) in facets.iter().enumerate() {
-                    filter_string.push('(');
-
-                    for (facet_outer_index, facet_inner_list) in
-                        facet_outer_list.iter().enumerate()
-                    {
-                        filter_string.push('(');
-                        for (facet_inner_index, facet) in
-                            facet_inner_list.iter().enumerate()
-                        {
-                            filter_string.push_str(&facet.replace(':', " = "));
-                            if facet_inner_index != (facet_inner_list.len() - 1)
-                            {
-                                filter_string.push_str(" AND ")
-                            }
-                        }
-                        filter_string.push(')');
+    let facets = if let Some(facets) = &info.facets {
+        Some(serde_json::from_str::<Vec<Vec<Value>>>(facets)?)
+    } else {
+        None
+    };
 
-                        if facet_outer_index != (facet_outer_list.len() - 1) {
-                            filter_string.push_str(" OR ")
+    let filters: Cow<_> =
+        match (info.filters.as_deref(), info.version.as_deref()) {
+            (Some(f), Some(v)) => format!("({f}) AND ({v})").into(),
+            (Some(f), None) => f.into(),
+            (None, Some(v)) => v.into(),
+            (None, None) => "".into(),
+        };
+
+    if let Some(facets) = facets {
+        // Search 现在可以 *可选地* 有第三个内部数组：So Vec(AND)<Vec(OR)<Vec(AND)< _ >>>
+        // 对于每个内部 facet，我们将检查它是否可以被反序列化为 Vec<&str>，如果是，则进行反序列化。
+        // 如果不是，我们假设它是一个单一的 facet 并将其包装在 Vec 中。
+        let facets: Vec<Vec<Vec<String>>> = facets
+            .into_iter()
+            .map(|facets| {
+                facets
+                    .into_iter()
+                    .map(|facet| {
+                        if facet.is_array() {
+                            serde_json::from_value::<Vec<String>>(facet)
+                                .unwrap_or_default()
+                        } else {
+                            vec![
+                                serde_json::from_value::<String>(facet)
+                                    .unwrap_or_default(),
+                            ]
                         }
-                    }
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
 
-                    filter_string.push(')');
+        filter_string.push('(');
+        for (index, facet_outer_list) in facets.iter().enumerate() {
+            filter_string.push('(');
 
-                    if index != (facets.len() - 1) {
+            for (facet_outer_index, facet_inner_list) in
+                facet_outer_list.iter().enumerate()
+            {
+                filter_string.push('(');
+                for (facet_inner_index, facet) in
+                    facet_inner_list.iter().enumerate()
+                {
+                    filter_string.push_str(&facet.replace(':', " = "));
+                    if facet_inner_index != (facet_inner_list.len() - 1) {
                         filter_string.push_str(" AND ")
                     }
                 }
                 filter_string.push(')');
 
-                if !filters.is_empty() {
-                    write!(filter_string, " AND ({filters})")?;
+                if facet_outer_index != (facet_outer_list.len() - 1) {
+                    filter_string.push_str(" OR ")
                 }
-            } else {
-                filter_string.push_str(&filters);
             }
 
-            if !filter_string.is_empty() {
-                query.with_filter(&filter_string);
+            filter_string.push(')');
+
+            if index != (facets.len() - 1) {
+                filter_string.push_str(" AND ")
             }
         }
+        filter_string.push(')');
 
-        query.execute::<ResultSearchProject>().await?
-    };
+        if !filters.is_empty() {
+            write!(filter_string, " AND ({filters})")?;
+        }
+    } else {
+        filter_string.push_str(&filters);
+    }
 
-    Ok(SearchResults {
-        hits: results.hits.into_iter().map(|r| r.result).collect(),
-        page: results.page.unwrap_or_default(),
-        hits_per_page: results.hits_per_page.unwrap_or_default(),
-        total_hits: results.total_hits.unwrap_or_default(),
+    Ok(if filter_string.is_empty() {
+        None
+    } else {
+        Some(filter_string)
     })
 }
+
+pub async fn search_for_project(
+    info: &SearchRequest,
+    config: &SearchConfig,
+    pool: &PgPool,
+) -> Result<SearchResults, SearchError> {
+    config.make_backend(pool).search_projects(info).await
+}
+
+/// 内容索引中可搜索的类型
+pub const CONTENT_TYPES: &[&str] =
+    &["wiki", "forum", "post", "issue", "issue_comment"];
+
+/// 上传到内容索引的文档：百科页面、论坛帖子与回复、问题与评论
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadSearchContent {
+    /// `{content_type}_{content_id}`，索引主键
+    pub id: String,
+    pub content_type: String,
+    pub content_id: String,
+    /// 回复所属的帖子或问题
+    pub parent_id: Option<String>,
+    pub project_id: Option<String>,
+    pub title: String,
+    pub body: String,
+    /// 百科页面的 slug
+    pub slug: Option<String>,
+    pub author: Option<String>,
+    /// 论坛分类
+    pub category: Option<String>,
+    /// 论坛帖子或问题的状态
+    pub state: Option<String>,
+    /// 是否为付费项目的百科页面
+    pub requires_purchase: bool,
+    pub created_timestamp: i64,
+    pub modified_timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContentSearchRequest {
+    pub query: Option<String>,
+    /// 逗号分隔的内容类型，如 `wiki,issue`
+    pub types: Option<String>,
+    /// 项目 ID 或 slug，仅搜索该项目下的内容
+    pub project: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResultSearchContent {
+    pub id: String,
+    pub content_type: String,
+    pub content_id: String,
+    pub parent_id: Option<String>,
+    pub project_id: Option<String>,
+    pub title: String,
+    pub slug: Option<String>,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    pub state: Option<String>,
+    pub requires_purchase: bool,
+    /// 命中位置附近的正文摘要，关键词以 `<em>` 标记
+    pub snippet: String,
+    pub created_timestamp: i64,
+    pub modified_timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContentSearchResults {
+    pub hits: Vec<ResultSearchContent>,
+    pub page: usize,
+    pub hits_per_page: usize,
+    pub total_hits: usize,
+    /// 各内容类型的命中数
+    pub types: HashMap<String, usize>,
+}
+
+/// 解析逗号分隔的内容类型；为空时表示全部类型
+fn content_types(
+    info: &ContentSearchRequest,
+) -> Result<Vec<String>, SearchError> {
+    let types = info
+        .types
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect_vec();
+    if let Some(invalid) =
+        types.iter().find(|x| !CONTENT_TYPES.contains(&x.as_str()))
+    {
+        return Err(SearchError::InvalidContentType(invalid.clone()));
+    }
+    Ok(types)
+}
+
+impl UploadSearchContent {
+    fn into_result(self, snippet: String) -> ResultSearchContent {
+        ResultSearchContent {
+            id: self.id,
+            content_type: self.content_type,
+            content_id: self.content_id,
+            parent_id: self.parent_id,
+            project_id: self.project_id,
+            title: self.title,
+            slug: self.slug,
+            author: self.author,
+            category: self.category,
+            state: self.state,
+            requires_purchase: self.requires_purchase,
+            snippet,
+            created_timestamp: self.created_timestamp,
+            modified_timestamp: self.modified_timestamp,
+        }
+    }
+}
+
+/// 搜索站内内容
+///
+/// `project_id` 为 base62 的项目 ID；`hidden_projects` 中项目下的内容不会
+/// 出现在结果与计数中。付费限制由调用方在结果上处理。
+pub async fn search_for_content(
+    info: &ContentSearchRequest,
+    project_id: Option<&str>,
+    hidden_projects: &[String],
+    config: &SearchConfig,
+    pool: &PgPool,
+) -> Result<ContentSearchResults, SearchError> {
+    config
+        .make_backend(pool)
+        .search_content(info, project_id, hidden_projects)
+        .await
+}
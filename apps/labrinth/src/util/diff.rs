@@ -0,0 +1,396 @@
+//! 基于行的文本差异比较与三方合并，用于百科版本对比、并发编辑合并等场景。
+//!
+//! 先裁掉公共前后缀，再对剩余部分做 LCS；剩余部分过大时退化为
+//! “整段删除 + 整段插入”，避免超长文本占用过多内存。
+
+use serde::Serialize;
+
+/// LCS 动态规划表允许的最大单元数
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// unified diff 中每个变更块前后保留的上下文行数
+const UNIFIED_CONTEXT: usize = 3;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    /// 在旧文本中的行号（从 1 开始），插入行为 None
+    pub old_line: Option<usize>,
+    /// 在新文本中的行号（从 1 开始），删除行为 None
+    pub new_line: Option<usize>,
+    pub content: String,
+}
+
+/// 逐行比较两段文本
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    let (mut i, mut j) = (0usize, 0usize);
+    ops.into_iter()
+        .map(|op| match op {
+            DiffOp::Equal => {
+                let line = DiffLine {
+                    op,
+                    old_line: Some(i + 1),
+                    new_line: Some(j + 1),
+                    content: old_lines[i].to_string(),
+                };
+                i += 1;
+                j += 1;
+                line
+            }
+            DiffOp::Delete => {
+                let line = DiffLine {
+                    op,
+                    old_line: Some(i + 1),
+                    new_line: None,
+                    content: old_lines[i].to_string(),
+                };
+                i += 1;
+                line
+            }
+            DiffOp::Insert => {
+                let line = DiffLine {
+                    op,
+                    old_line: None,
+                    new_line: Some(j + 1),
+                    content: new_lines[j].to_string(),
+                };
+                j += 1;
+                line
+            }
+        })
+        .collect()
+}
+
+fn diff_ops(old_lines: &[&str], new_lines: &[&str]) -> Vec<DiffOp> {
+    let prefix = old_lines
+        .iter()
+        .zip(new_lines.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old_lines[prefix..old_lines.len() - suffix];
+    let new_mid = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut ops = Vec::with_capacity(old_lines.len() + new_lines.len());
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, prefix));
+    ops.extend(diff_middle(old_mid, new_mid));
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, suffix));
+    ops
+}
+
+fn diff_middle(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let (n, m) = (old.len(), new.len());
+    if n == 0 || m == 0 || (n + 1) * (m + 1) > MAX_LCS_CELLS {
+        let mut ops = vec![DiffOp::Delete; n];
+        ops.extend(std::iter::repeat_n(DiffOp::Insert, m));
+        return ops;
+    }
+
+    // table[i][j] = old[i..] 与 new[j..] 的 LCS 长度
+    let width = m + 1;
+    let mut table = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Equal);
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            ops.push(DiffOp::Delete);
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(DiffOp::Delete, n - i));
+    ops.extend(std::iter::repeat_n(DiffOp::Insert, m - j));
+    ops
+}
+
+/// 统计新增与删除的行数
+pub fn diff_stats(lines: &[DiffLine]) -> (usize, usize) {
+    lines
+        .iter()
+        .fold((0, 0), |(added, removed), line| match line.op {
+            DiffOp::Insert => (added + 1, removed),
+            DiffOp::Delete => (added, removed + 1),
+            DiffOp::Equal => (added, removed),
+        })
+}
+
+/// 将逐行差异渲染为 unified diff 文本，两段文本相同时返回空字符串
+pub fn unified_diff(
+    lines: &[DiffLine],
+    old_label: &str,
+    new_label: &str,
+) -> String {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.op != DiffOp::Equal)
+        .map(|(idx, _)| idx)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // 把相距不超过 2 * 上下文 的变更合并到同一个块
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for idx in changed {
+        let start = idx.saturating_sub(UNIFIED_CONTEXT);
+        let end = (idx + UNIFIED_CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|l| l.op != DiffOp::Insert).count();
+        let new_count = hunk.iter().filter(|l| l.op != DiffOp::Delete).count();
+        let old_start = hunk_start(lines, start, |l| l.old_line, old_count);
+        let new_start = hunk_start(lines, start, |l| l.new_line, new_count);
+
+        out.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+        for line in hunk {
+            let prefix = match line.op {
+                DiffOp::Equal => ' ',
+                DiffOp::Insert => '+',
+                DiffOp::Delete => '-',
+            };
+            out.push(prefix);
+            out.push_str(&line.content);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// 计算块的起始行号；块内没有对应侧的行时，按惯例取前一行的行号
+fn hunk_start(
+    lines: &[DiffLine],
+    start: usize,
+    side: impl Fn(&DiffLine) -> Option<usize>,
+    count: usize,
+) -> usize {
+    if count > 0 {
+        lines[start..].iter().find_map(&side).unwrap_or(0)
+    } else {
+        lines[..start].iter().rev().find_map(&side).unwrap_or(0)
+    }
+}
+
+/// 三方合并的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    /// 合并后的文本；有冲突时冲突处以 `<<<<<<<` / `=======` / `>>>>>>>` 标记
+    pub text: String,
+    /// 冲突块数量
+    pub conflicts: usize,
+}
+
+/// 以 `base` 为共同祖先，对 `ours` 与 `theirs` 做逐行三方合并
+///
+/// 两侧修改了 `base` 中互不重叠的部分时可以自动合并；
+/// 同一处被两侧改成不同内容时记为冲突，并在文本中写入冲突标记。
+pub fn merge3(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    ours_label: &str,
+    theirs_label: &str,
+) -> MergeResult {
+    if ours == base || ours == theirs {
+        return MergeResult {
+            text: theirs.to_string(),
+            conflicts: 0,
+        };
+    }
+    if theirs == base {
+        return MergeResult {
+            text: ours.to_string(),
+            conflicts: 0,
+        };
+    }
+
+    let base_lines: Vec<&str> = base.lines().collect();
+    let ours_lines: Vec<&str> = ours.lines().collect();
+    let theirs_lines: Vec<&str> = theirs.lines().collect();
+    let ours_match = line_matches(&base_lines, &ours_lines);
+    let theirs_match = line_matches(&base_lines, &theirs_lines);
+
+    let mut out: Vec<String> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut o, mut t) = (0usize, 0usize, 0usize);
+    loop {
+        // 下一个在两侧都未被改动的 base 行，作为稳定锚点
+        let anchor = (b..base_lines.len())
+            .find_map(|i| Some((i, ours_match[i]?, theirs_match[i]?)));
+        let (b1, o1, t1) = anchor.unwrap_or((
+            base_lines.len(),
+            ours_lines.len(),
+            theirs_lines.len(),
+        ));
+
+        let base_seg = &base_lines[b..b1];
+        let ours_seg = &ours_lines[o..o1];
+        let theirs_seg = &theirs_lines[t..t1];
+        if ours_seg == base_seg || ours_seg == theirs_seg {
+            out.extend(theirs_seg.iter().map(|x| x.to_string()));
+        } else if theirs_seg == base_seg {
+            out.extend(ours_seg.iter().map(|x| x.to_string()));
+        } else {
+            conflicts += 1;
+            out.push(format!("<<<<<<< {ours_label}"));
+            out.extend(ours_seg.iter().map(|x| x.to_string()));
+            out.push("=======".to_string());
+            out.extend(theirs_seg.iter().map(|x| x.to_string()));
+            out.push(format!(">>>>>>> {theirs_label}"));
+        }
+
+        match anchor {
+            Some((i, j, k)) => {
+                out.push(base_lines[i].to_string());
+                b = i + 1;
+                o = j + 1;
+                t = k + 1;
+            }
+            None => break,
+        }
+    }
+
+    let mut text = out.join("\n");
+    if !out.is_empty() && ours.ends_with('\n') && theirs.ends_with('\n') {
+        text.push('\n');
+    }
+    MergeResult { text, conflicts }
+}
+
+/// 对 `base` 的每一行，给出它在 `other` 中对应的行号；被删除或修改的行为 None
+fn line_matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    let (mut i, mut j) = (0usize, 0usize);
+    for op in diff_ops(base, other) {
+        match op {
+            DiffOp::Equal => {
+                matches[i] = Some(j);
+                i += 1;
+                j += 1;
+            }
+            DiffOp::Delete => i += 1,
+            DiffOp::Insert => j += 1,
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_text_has_no_changes() {
+        let lines = diff_lines("a\nb\nc", "a\nb\nc");
+        assert!(lines.iter().all(|l| l.op == DiffOp::Equal));
+        assert_eq!(unified_diff(&lines, "a", "b"), "");
+    }
+
+    #[test]
+    fn detects_insert_and_delete() {
+        let lines = diff_lines("a\nb\nc\nd", "a\nc\nd\ne");
+        let ops: Vec<DiffOp> = lines.iter().map(|l| l.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::Equal,
+                DiffOp::Delete,
+                DiffOp::Equal,
+                DiffOp::Equal,
+                DiffOp::Insert,
+            ]
+        );
+        assert_eq!(diff_stats(&lines), (1, 1));
+    }
+
+    #[test]
+    fn renders_unified_hunk() {
+        let lines = diff_lines("a\nb\nc", "a\nB\nc");
+        assert_eq!(
+            unified_diff(&lines, "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn empty_side_uses_previous_line_number() {
+        let lines = diff_lines("", "x\ny");
+        assert_eq!(
+            unified_diff(&lines, "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+x\n+y\n"
+        );
+    }
+
+    #[test]
+    fn merges_non_overlapping_changes() {
+        let base = "title\n\nintro\n\nbody\n\nfooter\n";
+        let ours = "title\n\nintro changed\n\nbody\n\nfooter\n";
+        let theirs = "title\n\nintro\n\nbody\n\nfooter changed\n";
+        let result = merge3(base, ours, theirs, "ours", "theirs");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(
+            result.text,
+            "title\n\nintro changed\n\nbody\n\nfooter changed\n"
+        );
+    }
+
+    #[test]
+    fn reports_overlapping_changes() {
+        let result = merge3("a\nb\nc", "a\nB\nc", "a\nb2\nc", "ours", "theirs");
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.text,
+            "a\n<<<<<<< ours\nB\n=======\nb2\n>>>>>>> theirs\nc"
+        );
+    }
+
+    #[test]
+    fn identical_changes_merge_cleanly() {
+        let result = merge3("a\nb", "a\nc\nb", "a\nc\nb", "ours", "theirs");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.text, "a\nc\nb");
+    }
+}
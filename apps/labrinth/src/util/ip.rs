@@ -1,3 +1,5 @@
+use crate::util::env::parse_var;
+use actix_web::HttpRequest;
 use std::net::{AddrParseError, IpAddr, Ipv6Addr};
 
 pub fn convert_to_ip_v6(src: &str) -> Result<Ipv6Addr, AddrParseError> {
@@ -35,3 +37,19 @@ pub fn ip_to_identity_64(ip: Ipv6Addr) -> String {
         format!("ip6_{:x}:{:x}:{:x}:{:x}", s[0], s[1], s[2], s[3])
     }
 }
+
+/// 请求方 IP：开启 Cloudflare 集成时优先取 `x-real-ip`，否则取对端地址
+pub fn request_ip(req: &HttpRequest) -> Option<String> {
+    let conn_info = req.connection_info().clone();
+    let ip = if parse_var("CLOUDFLARE_INTEGRATION").unwrap_or(false) {
+        if let Some(header) = req.headers().get("x-real-ip") {
+            header.to_str().ok()
+        } else {
+            conn_info.peer_addr()
+        }
+    } else {
+        conn_info.peer_addr()
+    };
+
+    ip.map(|x| x.chars().take(64).collect())
+}
@@ -0,0 +1,200 @@
+//! 付费项目许可令牌
+//!
+//! 令牌格式：`Base64Url(payload JSON).Base64Url(Ed25519 签名)`，签名覆盖第一段原文。
+//! 服务器插件可通过公钥接口获取公钥后离线验证，也可调用在线验证接口检查吊销状态。
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use thiserror::Error;
+
+use crate::models::ids::{ProjectId, ProjectLicenseId, UserId};
+
+/// 签名私钥环境变量名
+pub const LICENSE_SIGNING_KEY_ENV: &str = "LICENSE_SIGNING_KEY";
+
+/// 签名密钥对
+/// 格式：Base64 编码的 32 字节 Ed25519 种子
+static LICENSE_KEY: LazyLock<Option<Ed25519KeyPair>> = LazyLock::new(|| {
+    let seed_base64 = match std::env::var(LICENSE_SIGNING_KEY_ENV) {
+        Ok(x) => x,
+        Err(_) => {
+            log::warn!(
+                "{} 未设置，许可令牌签发功能将不可用",
+                LICENSE_SIGNING_KEY_ENV
+            );
+            return None;
+        }
+    };
+
+    let seed = match BASE64.decode(&seed_base64) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("{} Base64 解码失败: {}", LICENSE_SIGNING_KEY_ENV, e);
+            return None;
+        }
+    };
+
+    match Ed25519KeyPair::from_seed_unchecked(&seed) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            log::error!(
+                "{} 必须是 32 字节的 Ed25519 种子: {}",
+                LICENSE_SIGNING_KEY_ENV,
+                e
+            );
+            None
+        }
+    }
+});
+
+#[derive(Debug, Error)]
+pub enum LicenseError {
+    #[error("许可签名密钥未配置，请设置 {} 环境变量", LICENSE_SIGNING_KEY_ENV)]
+    KeyNotConfigured,
+
+    #[error("许可令牌格式错误")]
+    InvalidFormat,
+
+    #[error("许可令牌签名无效")]
+    InvalidSignature,
+}
+
+/// 令牌内容
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LicenseClaims {
+    /// 许可证 ID
+    pub lid: ProjectLicenseId,
+    /// 购买者
+    pub uid: UserId,
+    /// 项目
+    pub pid: ProjectId,
+    /// 签发时间（Unix 秒）
+    pub iat: i64,
+    /// 过期时间（Unix 秒），永久授权为空
+    pub exp: Option<i64>,
+    /// 允许激活的服务器实例数量
+    pub max: i32,
+}
+
+impl LicenseClaims {
+    /// 令牌是否已过期（不检查吊销状态）
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.exp.is_some_and(|exp| exp <= now)
+    }
+}
+
+/// 获取签名公钥（32 字节）
+pub fn license_public_key() -> Option<&'static [u8]> {
+    LICENSE_KEY.as_ref().map(|key| key.public_key().as_ref())
+}
+
+/// 签发许可令牌
+pub fn sign_license_token(
+    claims: &LicenseClaims,
+) -> Result<String, LicenseError> {
+    let key = LICENSE_KEY.as_ref().ok_or(LicenseError::KeyNotConfigured)?;
+    Ok(sign_with_key(key, claims))
+}
+
+/// 使用服务端公钥验证令牌签名并解析内容
+pub fn verify_license_token(
+    token: &str,
+) -> Result<LicenseClaims, LicenseError> {
+    let public_key =
+        license_public_key().ok_or(LicenseError::KeyNotConfigured)?;
+    verify_with_key(public_key, token)
+}
+
+fn sign_with_key(key: &Ed25519KeyPair, claims: &LicenseClaims) -> String {
+    // LicenseClaims 只包含基本类型，序列化不会失败
+    let payload = serde_json::to_vec(claims).unwrap_or_default();
+    let payload = BASE64_URL.encode(payload);
+    let signature = key.sign(payload.as_bytes());
+
+    format!("{}.{}", payload, BASE64_URL.encode(signature.as_ref()))
+}
+
+fn verify_with_key(
+    public_key: &[u8],
+    token: &str,
+) -> Result<LicenseClaims, LicenseError> {
+    let (payload, signature) =
+        token.split_once('.').ok_or(LicenseError::InvalidFormat)?;
+    let signature = BASE64_URL
+        .decode(signature)
+        .map_err(|_| LicenseError::InvalidFormat)?;
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(payload.as_bytes(), &signature)
+        .map_err(|_| LicenseError::InvalidSignature)?;
+
+    let payload = BASE64_URL
+        .decode(payload)
+        .map_err(|_| LicenseError::InvalidFormat)?;
+    serde_json::from_slice(&payload).map_err(|_| LicenseError::InvalidFormat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> LicenseClaims {
+        LicenseClaims {
+            lid: ProjectLicenseId(1),
+            uid: UserId(2),
+            pid: ProjectId(3),
+            iat: 1_700_000_000,
+            exp: Some(1_800_000_000),
+            max: 3,
+        }
+    }
+
+    #[test]
+    fn token_round_trip() {
+        let key = Ed25519KeyPair::from_seed_unchecked(&[7u8; 32]).unwrap();
+        let token = sign_with_key(&key, &claims());
+
+        let parsed =
+            verify_with_key(key.public_key().as_ref(), &token).unwrap();
+        assert_eq!(parsed, claims());
+        assert!(!parsed.is_expired(1_700_000_001));
+        assert!(parsed.is_expired(1_800_000_000));
+    }
+
+    #[test]
+    fn rejects_tampered_token() {
+        let key = Ed25519KeyPair::from_seed_unchecked(&[7u8; 32]).unwrap();
+        let token = sign_with_key(&key, &claims());
+
+        let mut other = claims();
+        other.max = 100;
+        let forged_payload =
+            BASE64_URL.encode(serde_json::to_vec(&other).unwrap());
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", forged_payload, signature);
+
+        assert!(matches!(
+            verify_with_key(key.public_key().as_ref(), &forged),
+            Err(LicenseError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_with_key(key.public_key().as_ref(), "abc"),
+            Err(LicenseError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn rejects_other_key() {
+        let key = Ed25519KeyPair::from_seed_unchecked(&[7u8; 32]).unwrap();
+        let other = Ed25519KeyPair::from_seed_unchecked(&[8u8; 32]).unwrap();
+        let token = sign_with_key(&key, &claims());
+
+        assert!(matches!(
+            verify_with_key(other.public_key().as_ref(), &token),
+            Err(LicenseError::InvalidSignature)
+        ));
+    }
+}
@@ -4,6 +4,7 @@ pub mod bitflag;
 pub mod captcha;
 pub mod cors;
 pub mod date;
+pub mod diff;
 pub mod encrypt;
 pub mod env;
 pub mod ext;
@@ -11,6 +12,8 @@ pub mod guards;
 pub mod img;
 pub mod indexnow;
 pub mod ip;
+pub mod license;
+pub mod pdf;
 pub mod phone;
 pub mod ratelimit;
 pub mod redis;
@@ -0,0 +1,214 @@
+//! 纯文本 PDF 生成
+//!
+//! 只支持按行排版的文本，足够输出对账单一类的表格。中文使用 PDF 阅读器
+//! 内置的 Adobe-GB1 字体 `STSong-Light`（`UniGB-UCS2-H` 编码），不需要
+//! 嵌入字体文件；基本多文种平面以外的字符以 `?` 代替。
+
+use std::fmt::Write;
+
+/// A4 纸张尺寸，单位 pt
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 48.0;
+/// 行高与字号的比例
+const LINE_HEIGHT: f32 = 1.5;
+
+/// 一行中的一段文本，`x` 为相对左边距的偏移
+pub struct TextCell<'a> {
+    pub x: f32,
+    pub text: &'a str,
+}
+
+#[derive(Default)]
+pub struct TextPdf {
+    pages: Vec<String>,
+    /// 当前行基线距页面顶部的距离
+    cursor: f32,
+}
+
+impl TextPdf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输出一行文本
+    pub fn line(&mut self, text: &str, size: f32) {
+        self.row(&[TextCell { x: 0.0, text }], size);
+    }
+
+    /// 输出一行多列文本，用于表格
+    pub fn row(&mut self, cells: &[TextCell<'_>], size: f32) {
+        let height = size * LINE_HEIGHT;
+        if self.pages.is_empty() || self.cursor + height > PAGE_HEIGHT - MARGIN
+        {
+            self.pages.push(String::new());
+            self.cursor = MARGIN;
+        }
+        self.cursor += height;
+
+        let y = PAGE_HEIGHT - self.cursor;
+        let page = self.pages.last_mut().expect("已创建页面");
+        for cell in cells {
+            let _ = writeln!(
+                page,
+                "BT /F1 {} Tf {} {} Td <{}> Tj ET",
+                fmt_num(size),
+                fmt_num(MARGIN + cell.x),
+                fmt_num(y),
+                encode_text(cell.text)
+            );
+        }
+    }
+
+    /// 空出指定高度
+    pub fn gap(&mut self, height: f32) {
+        self.cursor += height;
+    }
+
+    /// 输出一条横线
+    pub fn rule(&mut self) {
+        if self.pages.is_empty() {
+            self.row(&[], 0.0);
+        }
+        self.cursor += 4.0;
+        let y = PAGE_HEIGHT - self.cursor;
+        let page = self.pages.last_mut().expect("已创建页面");
+        let _ = writeln!(
+            page,
+            "0.5 w {} {} m {} {} l S",
+            fmt_num(MARGIN),
+            fmt_num(y),
+            fmt_num(PAGE_WIDTH - MARGIN),
+            fmt_num(y)
+        );
+        self.cursor += 4.0;
+    }
+
+    /// 生成 PDF 文件内容
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.pages.is_empty() {
+            self.pages.push(String::new());
+        }
+
+        // 对象编号：1 目录，2 页面树，3 字体，4 CID 字体，5 字体描述，
+        // 之后每页依次为页面对象与内容流
+        let page_count = self.pages.len();
+        let kids = (0..page_count)
+            .map(|i| format!("{} 0 R", 6 + i * 2))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!("<< /Type /Pages /Kids [{kids}] /Count {page_count} >>"),
+            "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light \
+             /Encoding /UniGB-UCS2-H /DescendantFonts [4 0 R] >>"
+                .to_string(),
+            "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 4 >> \
+             /FontDescriptor 5 0 R /DW 1000 /W [1 95 500] >>"
+                .to_string(),
+            "<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 \
+             /FontBBox [-25 -254 1000 880] /ItalicAngle 0 /Ascent 880 \
+             /Descent -120 /CapHeight 880 /StemV 93 >>"
+                .to_string(),
+        ];
+        for (i, content) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                fmt_num(PAGE_WIDTH),
+                fmt_num(PAGE_HEIGHT),
+                7 + i * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+
+        let xref = out.len();
+        let _ =
+            write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = write!(out, "{offset:010} 00000 n \n");
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+
+        out.into_bytes()
+    }
+}
+
+/// 按 UCS-2 大端序编码为十六进制字符串
+fn encode_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 4);
+    for c in text.chars() {
+        let code = u16::try_from(u32::from(c)).unwrap_or(u16::from(b'?'));
+        let _ = write!(out, "{code:04X}");
+    }
+    out
+}
+
+fn fmt_num(value: f32) -> String {
+    let value = (value * 100.0).round() / 100.0;
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{value}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_text_uses_ucs2() {
+        assert_eq!(encode_text("A中"), "00414E2D");
+        // 基本多文种平面以外的字符无法用 UCS-2 表示
+        assert_eq!(encode_text("😀"), "003F");
+    }
+
+    #[test]
+    fn xref_offsets_point_to_objects() {
+        let mut pdf = TextPdf::new();
+        pdf.line("对账单", 16.0);
+        pdf.rule();
+        pdf.line("合计 12.34", 10.0);
+        let bytes = pdf.finish();
+        let text = String::from_utf8(bytes).unwrap();
+
+        let xref = text.rfind("startxref\n").unwrap();
+        let start: usize =
+            text[xref + 10..].lines().next().unwrap().parse().unwrap();
+        assert!(text[start..].starts_with("xref\n"));
+
+        for (i, line) in text[start..].lines().skip(3).take(7).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    fn long_content_is_paginated() {
+        let mut pdf = TextPdf::new();
+        for _ in 0..200 {
+            pdf.line("行", 10.0);
+        }
+        let text = String::from_utf8(pdf.finish()).unwrap();
+        assert!(text.contains("/Count 5 "));
+    }
+}
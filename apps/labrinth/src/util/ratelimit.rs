@@ -1,17 +1,47 @@
-use governor::clock::{Clock, DefaultClock};
-use governor::{RateLimiter, middleware, state};
-use std::str::FromStr;
-use std::sync::Arc;
+//! 请求限流
+//!
+//! 限流按策略（[`RateLimitPolicy`]）划分额度，策略通过 [`RateLimit`] 中间件
+//! 挂在整个应用或某个路由上，多个策略叠加时分别计数。请求方按令牌识别：
+//! 会话与个人访问令牌按所属用户、OAuth 令牌按应用计数，并使用各自的
+//! 额度；个人访问令牌不单独计数，与所属用户的会话共享用户额度。没有有效
+//! 令牌时按 IP 计数。令牌对应的计数对象缓存在 Redis 中，缓存中没有时先按
+//! IP 检查未登录额度，通过后才查询数据库。
+//!
+//! 计数保存在 Redis 中，多个实例共享同一额度，算法为 GCRA（与令牌桶等价，
+//! 额度在一个周期内均匀恢复）。Redis 不可用时退回到进程内限流。
+//! 带有 `RATE_LIMIT_IGNORE_KEY` 的请求不受限流。
 
+use crate::auth::validate::extract_authorization_header;
+use crate::database::models::oauth_token_item::OAuthAccessToken;
+use crate::database::models::pat_item::PersonalAccessToken;
+use crate::database::models::session_item::Session;
+use crate::database::models::{DatabaseError, OAuthClientId, UserId};
+use crate::database::redis::RedisPool;
+use crate::models::ids::base62_impl::{parse_base62, to_base62};
 use crate::routes::ApiError;
-use crate::util::env::parse_var;
+use crate::util::ip::request_ip;
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use actix_web::{
-    Error, ResponseError,
+    Error, HttpMessage, HttpRequest, ResponseError,
     body::EitherBody,
     dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    web,
 };
+use chrono::Utc;
+use dashmap::DashMap;
 use futures_util::future::LocalBoxFuture;
 use futures_util::future::{Ready, ready};
+use governor::clock::{Clock, DefaultClock};
+use governor::{RateLimiter, middleware, state};
+use log::warn;
+use serde::Serialize;
+use sha2::Digest;
+use sqlx::PgPool;
+use std::fmt::Display;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 pub type KeyedRateLimiter<
     K = String,
@@ -20,11 +50,567 @@ pub type KeyedRateLimiter<
     RateLimiter<K, state::keyed::DefaultKeyedStateStore<K>, DefaultClock, MW>,
 >;
 
-pub struct RateLimit(pub KeyedRateLimiter);
+/// 一个周期内允许的请求数
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub const fn per_minute(limit: u32) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(60),
+        }
+    }
+
+    pub const fn per_hour(limit: u32) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// 每恢复一次额度所需的毫秒数
+    fn interval_ms(&self) -> f64 {
+        self.period.as_millis() as f64 / self.limit.max(1) as f64
+    }
+
+    fn governor(&self) -> governor::Quota {
+        let limit = NonZeroU32::new(self.limit.max(1)).unwrap();
+        governor::Quota::with_period(self.period / limit.get())
+            .unwrap_or_else(|| governor::Quota::per_second(limit))
+            .allow_burst(limit)
+    }
+}
+
+/// 请求方的类别，不同类别使用不同的额度
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitTier {
+    Anonymous,
+    User,
+    OAuth,
+}
+
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    /// 按 IP 计数的未登录请求
+    pub anonymous: Quota,
+    /// 会话与个人访问令牌；个人访问令牌按所属用户计数，同一用户的会话与
+    /// 所有令牌共享这一额度
+    pub user: Quota,
+    /// OAuth 应用，同一应用的所有用户共享
+    pub oauth: Quota,
+}
+
+impl RateLimitPolicy {
+    pub fn quota(&self, tier: RateLimitTier) -> Quota {
+        match tier {
+            RateLimitTier::Anonymous => self.anonymous,
+            RateLimitTier::User => self.user,
+            RateLimitTier::OAuth => self.oauth,
+        }
+    }
+}
+
+/// 所有请求
+pub static GLOBAL: RateLimitPolicy = RateLimitPolicy {
+    name: "global",
+    anonymous: Quota::per_minute(500),
+    user: Quota::per_minute(1000),
+    oauth: Quota::per_minute(3000),
+};
+
+/// 项目与内容搜索
+pub static SEARCH: RateLimitPolicy = RateLimitPolicy {
+    name: "search",
+    anonymous: Quota::per_minute(60),
+    user: Quota::per_minute(120),
+    oauth: Quota::per_minute(600),
+};
+
+/// 创建项目、上传版本与文件
+pub static UPLOAD: RateLimitPolicy = RateLimitPolicy {
+    name: "upload",
+    anonymous: Quota::per_minute(5),
+    user: Quota::per_minute(20),
+    oauth: Quota::per_minute(60),
+};
+
+/// 登录、注册、两步验证与重置密码
+pub static LOGIN: RateLimitPolicy = RateLimitPolicy {
+    name: "login",
+    anonymous: Quota::per_minute(10),
+    user: Quota::per_minute(10),
+    oauth: Quota::per_minute(10),
+};
+
+/// 发送短信与验证邮件
+pub static SMS: RateLimitPolicy = RateLimitPolicy {
+    name: "sms",
+    anonymous: Quota::per_hour(10),
+    user: Quota::per_hour(10),
+    oauth: Quota::per_hour(10),
+};
+
+/// 创建支付订单
+pub static ORDER: RateLimitPolicy = RateLimitPolicy {
+    name: "order",
+    anonymous: Quota::per_minute(10),
+    user: Quota::per_minute(10),
+    oauth: Quota::per_minute(30),
+};
+
+/// 兑换码兑换
+pub static REDEEM: RateLimitPolicy = RateLimitPolicy {
+    name: "redeem",
+    anonymous: Quota::per_minute(10),
+    user: Quota::per_minute(10),
+    oauth: Quota::per_minute(10),
+};
+
+pub static POLICIES: [&RateLimitPolicy; 7] =
+    [&GLOBAL, &SEARCH, &UPLOAD, &LOGIN, &SMS, &ORDER, &REDEEM];
+
+pub fn find_policy(name: &str) -> Option<&'static RateLimitPolicy> {
+    POLICIES.iter().copied().find(|x| x.name == name)
+}
+
+/// 计数对象，字符串形式为 `ip:{ip}`、`user:{id}` 或 `oauth:{id}`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitSubject {
+    Ip(String),
+    User(UserId),
+    OAuthClient(OAuthClientId),
+}
+
+impl RateLimitSubject {
+    pub fn tier(&self) -> RateLimitTier {
+        match self {
+            RateLimitSubject::Ip(_) => RateLimitTier::Anonymous,
+            RateLimitSubject::User(_) => RateLimitTier::User,
+            RateLimitSubject::OAuthClient(_) => RateLimitTier::OAuth,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, id) = value.split_once(':')?;
+        if id.is_empty() {
+            return None;
+        }
+        let parse_id = || parse_base62(id).ok().map(|x| x as i64);
+
+        match kind {
+            "ip" if id.len() <= 64 => {
+                Some(RateLimitSubject::Ip(id.to_string()))
+            }
+            "user" => Some(RateLimitSubject::User(UserId(parse_id()?))),
+            "oauth" => {
+                Some(RateLimitSubject::OAuthClient(OAuthClientId(parse_id()?)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for RateLimitSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitSubject::Ip(ip) => write!(f, "ip:{ip}"),
+            RateLimitSubject::User(id) => {
+                write!(f, "user:{}", to_base62(id.0 as u64))
+            }
+            RateLimitSubject::OAuthClient(id) => {
+                write!(f, "oauth:{}", to_base62(id.0 as u64))
+            }
+        }
+    }
+}
+
+/// 一次限流检查的结果
+#[derive(Clone, Debug)]
+pub struct RateLimitDecision {
+    pub policy: &'static str,
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// 额度完全恢复所需的时间
+    pub reset_after: Duration,
+    /// 被拒绝时距离下一次可以请求的时间
+    pub retry_after: Duration,
+}
+
+impl RateLimitDecision {
+    fn new(policy: &'static RateLimitPolicy, quota: Quota) -> Self {
+        Self {
+            policy: policy.name,
+            allowed: true,
+            limit: quota.limit,
+            remaining: quota.limit,
+            reset_after: Duration::ZERO,
+            retry_after: Duration::ZERO,
+        }
+    }
+
+    /// 写入限流响应头；多个策略叠加时保留剩余次数最少的一个
+    fn insert_headers(&self, headers: &mut HeaderMap) {
+        let current = headers
+            .get(REMAINING_HEADER)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u32>().ok());
+        if current.is_some_and(|x| x <= self.remaining) {
+            return;
+        }
+
+        headers.insert(
+            HeaderName::from_static(POLICY_HEADER),
+            HeaderValue::from_static(self.policy),
+        );
+        headers
+            .insert(HeaderName::from_static(LIMIT_HEADER), self.limit.into());
+        headers.insert(
+            HeaderName::from_static(REMAINING_HEADER),
+            self.remaining.into(),
+        );
+        headers.insert(
+            HeaderName::from_static(RESET_HEADER),
+            ceil_secs(self.reset_after).into(),
+        );
+        if !self.allowed {
+            headers.insert(
+                header::RETRY_AFTER,
+                ceil_secs(self.retry_after).max(1).into(),
+            );
+        }
+    }
+}
+
+const POLICY_HEADER: &str = "x-ratelimit-policy";
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RESET_HEADER: &str = "x-ratelimit-reset";
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+/// 某个计数对象在一个策略下的当前状态
+#[derive(Serialize, Clone, Debug)]
+pub struct RateLimitBucket {
+    pub policy: &'static str,
+    pub limit: u32,
+    pub period_secs: u64,
+    pub remaining: u32,
+    pub reset_after_secs: u64,
+}
+
+/// 按 GCRA 计算的剩余次数与完全恢复所需的毫秒数；`tat` 为理论到达时间
+fn remaining_capacity(tat: Option<f64>, now: f64, quota: Quota) -> (u32, f64) {
+    let tat = tat.unwrap_or(now).max(now);
+    let free = now + quota.period.as_millis() as f64 - tat;
+    let remaining = (free / quota.interval_ms()).floor().max(0.0) as u32;
+    (remaining.min(quota.limit), tat - now)
+}
+
+fn bucket_key(policy: &RateLimitPolicy, subject: &RateLimitSubject) -> String {
+    format!("ratelimit:{}:{}", policy.name, subject)
+}
+
+const SUBJECTS_NAMESPACE: &str = "ratelimit_subjects";
+const SUBJECTS_EXPIRY: i64 = 60 * 10;
+
+/// 缓存键使用令牌的哈希，避免令牌原文写入 Redis
+fn token_hash(token: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(token.as_bytes()))
+}
+
+/// 原子地检查并消耗一次额度。返回 {是否允许, 剩余次数, 完全恢复毫秒数,
+/// 重试等待毫秒数}；时间取 Redis 服务器时间，避免各实例时钟不一致
+static GCRA_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+        local period = tonumber(ARGV[1])
+        local interval = period / tonumber(ARGV[2])
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + tonumber(time[2]) / 1000
+        local tat = tonumber(redis.call('GET', KEYS[1]) or now)
+        if tat < now then
+            tat = now
+        end
+        local new_tat = tat + interval
+        local allow_at = new_tat - period
+        if allow_at > now then
+            return {0, 0, math.ceil(tat - now), math.ceil(allow_at - now)}
+        end
+        redis.call('SET', KEYS[1], string.format('%.3f', new_tat),
+            'PX', math.ceil(new_tat - now))
+        return {1, math.floor((now - allow_at) / interval), math.ceil(new_tat - now), 0}
+        ",
+    )
+});
+
+pub struct RateLimits {
+    pool: PgPool,
+    redis: RedisPool,
+    /// Redis 不可用时使用的进程内限流器，键为策略名称与请求方类别
+    local: DashMap<(&'static str, RateLimitTier), KeyedRateLimiter>,
+}
+
+impl RateLimits {
+    pub fn new(pool: PgPool, redis: RedisPool) -> Self {
+        Self {
+            pool,
+            redis,
+            local: DashMap::new(),
+        }
+    }
+
+    /// 识别请求方，同时返回识别过程中已对该请求方做过的检查。
+    ///
+    /// 令牌先从缓存中查找；缓存中没有时先按 IP 检查 `policy` 的未登录额度，
+    /// 通过后才查询数据库，避免大量无效令牌绕过限流直接查询数据库。
+    /// 令牌无效时按 IP 计数，沿用这次检查的结果。
+    pub async fn identify(
+        &self,
+        policy: &'static RateLimitPolicy,
+        req: &HttpRequest,
+    ) -> Option<(RateLimitSubject, Option<RateLimitDecision>)> {
+        let token = extract_authorization_header(req).ok();
+        if let Some(token) = token {
+            match self.cached_subject(token).await {
+                Ok(Some(subject)) => return Some((subject, None)),
+                Ok(None) => {}
+                Err(e) => warn!("读取限流识别缓存失败: {:?}", e),
+            }
+        }
+
+        let ip = RateLimitSubject::Ip(request_ip(req)?);
+        let decision = self.check(policy, &ip).await;
+        if !decision.allowed {
+            return Some((ip, Some(decision)));
+        }
+
+        if let Some(token) = token {
+            match self.token_subject(token).await {
+                Ok(Some(subject)) => return Some((subject, None)),
+                Ok(None) => {}
+                Err(e) => warn!("限流识别令牌失败: {:?}", e),
+            }
+        }
+
+        Some((ip, Some(decision)))
+    }
+
+    async fn cached_subject(
+        &self,
+        token: &str,
+    ) -> Result<Option<RateLimitSubject>, DatabaseError> {
+        let mut redis = self.redis.connect().await?;
+        let subject = redis
+            .get(SUBJECTS_NAMESPACE, &token_hash(token))
+            .await?
+            .and_then(|x| RateLimitSubject::parse(&x));
+
+        Ok(subject)
+    }
+
+    /// 从数据库查询令牌对应的计数对象，并缓存一段时间
+    async fn token_subject(
+        &self,
+        token: &str,
+    ) -> Result<Option<RateLimitSubject>, DatabaseError> {
+        let now = Utc::now();
+        let subject = match token.split_once('_') {
+            Some(("mra", _)) => Session::get(token, &self.pool, &self.redis)
+                .await?
+                .filter(|x| x.expires > now)
+                .map(|x| RateLimitSubject::User(x.user_id)),
+            Some(("mrp", _)) => {
+                PersonalAccessToken::get(token, &self.pool, &self.redis)
+                    .await?
+                    .filter(|x| x.expires > now)
+                    .map(|x| RateLimitSubject::User(x.user_id))
+            }
+            Some(("mro", _)) => OAuthAccessToken::get(
+                OAuthAccessToken::hash_token(token),
+                &self.pool,
+            )
+            .await?
+            .filter(|x| x.expires > now)
+            .map(|x| RateLimitSubject::OAuthClient(x.client_id)),
+            _ => None,
+        };
+
+        if let Some(subject) = &subject {
+            let mut redis = self.redis.connect().await?;
+            redis
+                .set(
+                    SUBJECTS_NAMESPACE,
+                    &token_hash(token),
+                    &subject.to_string(),
+                    Some(SUBJECTS_EXPIRY),
+                )
+                .await?;
+        }
+
+        Ok(subject)
+    }
+
+    /// 检查并消耗一次额度
+    pub async fn check(
+        &self,
+        policy: &'static RateLimitPolicy,
+        subject: &RateLimitSubject,
+    ) -> RateLimitDecision {
+        match self.check_redis(policy, subject).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                warn!("Redis 限流失败，使用进程内限流: {:?}", e);
+                self.check_local(policy, subject)
+            }
+        }
+    }
+
+    async fn check_redis(
+        &self,
+        policy: &'static RateLimitPolicy,
+        subject: &RateLimitSubject,
+    ) -> Result<RateLimitDecision, DatabaseError> {
+        let quota = policy.quota(subject.tier());
+        let mut redis = self.redis.pool.get().await?;
+
+        let (allowed, remaining, reset_after, retry_after): (
+            i64,
+            i64,
+            i64,
+            i64,
+        ) = GCRA_SCRIPT
+            .key(bucket_key(policy, subject))
+            .arg(quota.period.as_millis() as u64)
+            .arg(quota.limit.max(1))
+            .invoke_async(&mut redis)
+            .await?;
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            remaining: remaining.clamp(0, quota.limit as i64) as u32,
+            reset_after: Duration::from_millis(reset_after.max(0) as u64),
+            retry_after: Duration::from_millis(retry_after.max(0) as u64),
+            ..RateLimitDecision::new(policy, quota)
+        })
+    }
+
+    fn check_local(
+        &self,
+        policy: &'static RateLimitPolicy,
+        subject: &RateLimitSubject,
+    ) -> RateLimitDecision {
+        let quota = policy.quota(subject.tier());
+        let limiter = self
+            .local
+            .entry((policy.name, subject.tier()))
+            .or_insert_with(|| {
+                Arc::new(
+                    RateLimiter::keyed(quota.governor())
+                        .with_middleware::<middleware::StateInformationMiddleware>(),
+                )
+            })
+            .clone();
+
+        match limiter.check_key(&subject.to_string()) {
+            Ok(snapshot) => RateLimitDecision {
+                remaining: snapshot.remaining_burst_capacity(),
+                reset_after: snapshot.quota().burst_size_replenished_in(),
+                ..RateLimitDecision::new(policy, quota)
+            },
+            Err(negative) => RateLimitDecision {
+                allowed: false,
+                remaining: 0,
+                reset_after: quota.period,
+                retry_after: negative
+                    .wait_time_from(DefaultClock::default().now()),
+                ..RateLimitDecision::new(policy, quota)
+            },
+        }
+    }
+
+    /// 清理进程内限流器中已恢复满额的计数
+    pub fn retain_recent(&self) {
+        for limiter in self.local.iter() {
+            limiter.retain_recent();
+        }
+    }
+
+    /// 计数对象在各个策略下的状态
+    pub async fn inspect(
+        &self,
+        subject: &RateLimitSubject,
+    ) -> Result<Vec<RateLimitBucket>, DatabaseError> {
+        let mut redis = self.redis.pool.get().await?;
+        let keys = POLICIES
+            .iter()
+            .map(|x| bucket_key(x, subject))
+            .collect::<Vec<_>>();
+        // 与限流脚本一样取 Redis 服务器时间，避免本机时钟偏差影响结果
+        let (tats, (secs, micros)): (Vec<Option<f64>>, (u64, u64)) =
+            redis::pipe()
+                .atomic()
+                .cmd("MGET")
+                .arg(&keys)
+                .cmd("TIME")
+                .query_async(&mut redis)
+                .await?;
+
+        let now = secs as f64 * 1000.0 + micros as f64 / 1000.0;
+        let buckets = POLICIES
+            .iter()
+            .zip(tats)
+            .map(|(policy, tat)| {
+                let quota = policy.quota(subject.tier());
+                let (remaining, reset_after) =
+                    remaining_capacity(tat, now, quota);
+                RateLimitBucket {
+                    policy: policy.name,
+                    limit: quota.limit,
+                    period_secs: quota.period.as_secs(),
+                    remaining,
+                    reset_after_secs: (reset_after / 1000.0).ceil() as u64,
+                }
+            })
+            .collect();
+
+        Ok(buckets)
+    }
+
+    /// 清空计数；未指定策略时清空所有策略
+    pub async fn reset(
+        &self,
+        subject: &RateLimitSubject,
+        policy: Option<&'static RateLimitPolicy>,
+    ) -> Result<(), DatabaseError> {
+        let keys = match policy {
+            Some(policy) => vec![bucket_key(policy, subject)],
+            None => POLICIES.iter().map(|x| bucket_key(x, subject)).collect(),
+        };
+
+        let mut redis = self.redis.pool.get().await?;
+        redis::cmd("DEL")
+            .arg(&keys)
+            .query_async::<()>(&mut redis)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// 为路由或整个应用加上限流策略，需要在应用数据中注册 [`RateLimits`]
+pub struct RateLimit(pub &'static RateLimitPolicy);
 
 impl<S, B> Transform<S, ServiceRequest> for RateLimit
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -36,21 +622,22 @@ where
 
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(RateLimitService {
-            service,
-            rate_limiter: Arc::clone(&self.0),
+            service: Rc::new(service),
+            policy: self.0,
         }))
     }
 }
 
 #[doc(hidden)]
 pub struct RateLimitService<S> {
-    service: S,
-    rate_limiter: KeyedRateLimiter,
+    service: Rc<S>,
+    policy: &'static RateLimitPolicy,
 }
 
 impl<S, B> Service<ServiceRequest> for RateLimitService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+        + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -61,135 +648,125 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        if let Some(key) = req.headers().get("x-ratelimit-key")
-            && key.to_str().ok()
-                == dotenvy::var("RATE_LIMIT_IGNORE_KEY").ok().as_deref()
-        {
-            let res = self.service.call(req);
-
-            return Box::pin(async move {
-                let service_response = res.await?;
-                Ok(service_response.map_into_left_body())
-            });
-        }
-
-        let conn_info = req.connection_info().clone();
-        let ip = if parse_var("CLOUDFLARE_INTEGRATION").unwrap_or(false) {
-            if let Some(header) = req.headers().get("x-real-ip") {
-                header.to_str().ok()
-            } else {
-                conn_info.peer_addr()
-            }
-        } else {
-            conn_info.peer_addr()
-        };
+        let service = Rc::clone(&self.service);
+        let policy = self.policy;
 
-        if let Some(ip) = ip {
-            let ip = ip.to_string();
-
-            match self.rate_limiter.check_key(&ip) {
-                Ok(snapshot) => {
-                    let fut = self.service.call(req);
-
-                    Box::pin(async move {
-                        match fut.await {
-                            Ok(mut service_response) => {
-                                // 现在你有一个可变引用 ServiceResponse，所以你可以修改它的头部。
-                                let headers = service_response.headers_mut();
-                                headers.insert(
-                                    actix_web::http::header::HeaderName::from_str(
-                                        "x-ratelimit-limit",
-                                    )
-                                    .unwrap(),
-                                    snapshot.quota().burst_size().get().into(),
-                                );
-                                headers.insert(
-                                    actix_web::http::header::HeaderName::from_str(
-                                        "x-ratelimit-remaining",
-                                    )
-                                    .unwrap(),
-                                    snapshot.remaining_burst_capacity().into(),
-                                );
-
-                                headers.insert(
-                                    actix_web::http::header::HeaderName::from_str(
-                                        "x-ratelimit-reset",
-                                    )
-                                    .unwrap(),
-                                    snapshot
-                                        .quota()
-                                        .burst_size_replenished_in()
-                                        .as_secs()
-                                        .into(),
-                                );
-
-                                // 返回修改后的响应作为 Ok。
-                                Ok(service_response.map_into_left_body())
-                            }
-                            Err(e) => {
-                                // 处理错误情况
-                                Err(e)
-                            }
-                        }
-                    })
-                }
-                Err(negative) => {
-                    let wait_time =
-                        negative.wait_time_from(DefaultClock::default().now());
+        Box::pin(async move {
+            let ignored = req
+                .headers()
+                .get("x-ratelimit-key")
+                .and_then(|x| x.to_str().ok())
+                .is_some_and(|x| {
+                    dotenvy::var("RATE_LIMIT_IGNORE_KEY").ok().as_deref()
+                        == Some(x)
+                });
+            let limits = req.app_data::<web::Data<RateLimits>>().cloned();
 
-                    let mut response = ApiError::RateLimitError(
-                        wait_time.as_millis(),
-                        negative.quota().burst_size().get(),
-                    )
-                    .error_response();
+            let Some(limits) = limits.filter(|_| !ignored) else {
+                let response = service.call(req).await?;
+                return Ok(response.map_into_left_body());
+            };
 
-                    let headers = response.headers_mut();
-
-                    headers.insert(
-                        actix_web::http::header::HeaderName::from_str(
-                            "x-ratelimit-limit",
-                        )
-                        .unwrap(),
-                        negative.quota().burst_size().get().into(),
-                    );
-                    headers.insert(
-                        actix_web::http::header::HeaderName::from_str(
-                            "x-ratelimit-remaining",
+            // 同一请求经过多个策略时只识别一次
+            let subject = req.extensions().get::<RateLimitSubject>().cloned();
+            let (subject, decision) = match subject {
+                Some(subject) => (subject, None),
+                None => match limits.identify(policy, req.request()).await {
+                    Some((subject, decision)) => {
+                        req.extensions_mut().insert(subject.clone());
+                        (subject, decision)
+                    }
+                    None => {
+                        let response = ApiError::CustomAuthentication(
+                            "无法获取用户 IP 地址！".to_string(),
                         )
-                        .unwrap(),
-                        0.into(),
-                    );
-                    headers.insert(
-                        actix_web::http::header::HeaderName::from_str(
-                            "x-ratelimit-reset",
-                        )
-                        .unwrap(),
-                        wait_time.as_secs().into(),
-                    );
-
-                    // 上游修复: a5427f728 - CORS headers on ratelimited
-                    headers.insert(
-                        actix_web::http::header::HeaderName::from_str(
-                            "Access-Control-Allow-Origin",
-                        )
-                        .unwrap(),
-                        "*".parse().unwrap(),
-                    );
+                        .error_response();
+                        return Ok(
+                            req.into_response(response.map_into_right_body())
+                        );
+                    }
+                },
+            };
 
-                    Box::pin(async {
-                        Ok(req.into_response(response.map_into_right_body()))
-                    })
-                }
+            let decision = match decision {
+                Some(decision) => decision,
+                None => limits.check(policy, &subject).await,
+            };
+            if decision.allowed {
+                let mut response = service.call(req).await?;
+                decision.insert_headers(response.headers_mut());
+                return Ok(response.map_into_left_body());
             }
-        } else {
-            let response = ApiError::CustomAuthentication(
-                "无法获取用户 IP 地址！".to_string(),
+
+            let mut response = ApiError::RateLimitError(
+                decision.retry_after.as_millis(),
+                decision.limit,
             )
             .error_response();
+            let headers = response.headers_mut();
+            decision.insert_headers(headers);
+            // 上游修复: a5427f728 - CORS headers on ratelimited
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
 
-            Box::pin(async {
-                Ok(req.into_response(response.map_into_right_body()))
-            })
+            Ok(req.into_response(response.map_into_right_body()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subjects_round_trip() {
+        for value in ["ip:127.0.0.1", "ip:::1", "user:abc", "oauth:z"] {
+            let subject = RateLimitSubject::parse(value).unwrap();
+            assert_eq!(subject.to_string(), value);
+        }
+        assert_eq!(
+            RateLimitSubject::parse("user:abc").unwrap().tier(),
+            RateLimitTier::User
+        );
+        assert_eq!(
+            RateLimitSubject::parse("oauth:abc").unwrap().tier(),
+            RateLimitTier::OAuth
+        );
+        assert!(RateLimitSubject::parse("user:").is_none());
+        assert!(RateLimitSubject::parse("user:!!").is_none());
+        assert!(RateLimitSubject::parse("team:abc").is_none());
+        assert!(RateLimitSubject::parse("pat:abc").is_none());
+        assert!(RateLimitSubject::parse("127.0.0.1").is_none());
+    }
+
+    #[test]
+    fn remaining_capacity_follows_gcra() {
+        let quota = Quota::per_minute(60);
+        let now = 1_000_000.0;
+        assert_eq!(remaining_capacity(None, now, quota), (60, 0.0));
+        assert_eq!(remaining_capacity(Some(now - 5000.0), now, quota).0, 60);
+        assert_eq!(remaining_capacity(Some(now + 1000.0), now, quota).0, 59);
+        assert_eq!(
+            remaining_capacity(Some(now + 30_000.0), now, quota),
+            (30, 30_000.0)
+        );
+        assert_eq!(remaining_capacity(Some(now + 60_000.0), now, quota).0, 0);
+    }
+
+    #[test]
+    fn policies_have_unique_names() {
+        for policy in POLICIES {
+            assert_eq!(
+                POLICIES.iter().filter(|x| x.name == policy.name).count(),
+                1
+            );
+            assert!(policy.anonymous.limit > 0);
+            assert!(policy.user.limit > 0);
+            assert!(policy.oauth.limit > 0);
         }
+        assert!(find_policy("search").is_some());
+        assert!(find_policy("unknown").is_none());
     }
 }
@@ -96,6 +96,20 @@ pub fn rsa_verify_sha256(
         .map_err(|_| YzhError::RsaVerify)
 }
 
+/// 从 PKCS#8 私钥推导 X.509 公钥 PEM，供本地模拟服务验证平台企业的请求签名。
+pub fn public_key_pem_from_private(
+    private_key_pem: &str,
+) -> Result<String, YzhError> {
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+
+    let key = RsaPrivateKey::from_pkcs8_pem(private_key_pem).map_err(|e| {
+        YzhError::KeyParse(format!("私钥 PKCS8 PEM 解析失败: {}", e))
+    })?;
+    RsaPublicKey::from(&key)
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| YzhError::KeyParse(format!("公钥 PEM 编码失败: {}", e)))
+}
+
 // ============================================================================
 // 测试
 // ============================================================================
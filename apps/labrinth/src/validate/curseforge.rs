@@ -0,0 +1,279 @@
+//! CurseForge 格式的整合包
+//!
+//! CurseForge 整合包是带有 `manifest.json` 的 zip：清单只记录 CurseForge 上的项目与文件 ID，
+//! 其余文件直接打包在覆盖目录（通常为 `overrides/`）中。
+
+use crate::models::pack::{
+    CurseForgeManifest, PackFile, PackFileHash, PackFormat,
+};
+use crate::queue::moderation::{FlameFile, FlameProject, FlameResponse};
+use crate::util::safe_path::SafeRelativePath;
+use crate::util::validate::validation_errors_to_string;
+use crate::validate::ValidationError;
+use futures::{StreamExt, TryStreamExt};
+use sha2::Digest;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use validator::Validate;
+use zip::ZipArchive;
+use zip::write::{SimpleFileOptions, ZipWriter};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// 转换时同时下载的文件数
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// CurseForge 的项目分类 ID 与其在游戏目录中的位置
+const CLASS_DIRECTORIES: &[(u32, &str)] =
+    &[(6, "mods"), (12, "resourcepacks"), (6552, "shaderpacks")];
+
+/// 读取 CurseForge 清单；文件中没有清单时返回 None
+pub fn read_manifest(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Result<Option<CurseForgeManifest>, ValidationError> {
+    let Ok(mut file) = archive.by_name(MANIFEST_FILE) else {
+        return Ok(None);
+    };
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let manifest: CurseForgeManifest = serde_json::from_str(&contents)?;
+    if manifest.manifest_type != "minecraftModpack" {
+        return Err(ValidationError::InvalidInput(
+            "manifest.json 不是 CurseForge 整合包清单！".into(),
+        ));
+    }
+
+    Ok(Some(manifest))
+}
+
+/// 覆盖目录下直接打包的模组、资源包与光影包
+fn is_bundled_file(path: &str) -> bool {
+    (path.ends_with(".jar") || path.ends_with(".zip"))
+        && path.matches('/').count() == 1
+        && CLASS_DIRECTORIES.iter().any(|(_, dir)| {
+            path.strip_prefix(dir).is_some_and(|x| x.starts_with('/'))
+        })
+}
+
+fn hash_file(contents: &[u8]) -> HashMap<PackFileHash, String> {
+    HashMap::from([
+        (
+            PackFileHash::Sha1,
+            format!("{:x}", sha1::Sha1::digest(contents)),
+        ),
+        (
+            PackFileHash::Sha512,
+            format!("{:x}", sha2::Sha512::digest(contents)),
+        ),
+    ])
+}
+
+fn pack_format(
+    manifest: &CurseForgeManifest,
+    files: Vec<PackFile>,
+) -> PackFormat {
+    PackFormat {
+        game: "minecraft".to_string(),
+        format_version: 1,
+        version_id: manifest
+            .version
+            .clone()
+            .filter(|x| !x.is_empty())
+            .unwrap_or_else(|| "1.0.0".to_string()),
+        name: manifest.name.clone(),
+        summary: None,
+        files,
+        dependencies: manifest.pack_dependencies(),
+    }
+}
+
+/// 把覆盖目录中打包的文件整理为 mrpack 格式，便于与站内文件的哈希匹配；
+/// 清单中引用的 CurseForge 文件没有哈希，不包含在内
+pub fn bundled_pack_format(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    manifest: &CurseForgeManifest,
+) -> Result<PackFormat, ValidationError> {
+    let prefix = manifest.overrides_prefix();
+    let mut files = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(path) = file.name().strip_prefix(&prefix) else {
+            continue;
+        };
+        if file.is_dir() || !is_bundled_file(path) {
+            continue;
+        }
+        let path = SafeRelativePath::new(path.to_string())
+            .map_err(|err| ValidationError::InvalidInput(err.into()))?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        files.push(PackFile {
+            path,
+            hashes: hash_file(&contents),
+            env: None,
+            downloads: Vec::new(),
+            file_size: contents.len() as u32,
+        });
+    }
+
+    Ok(pack_format(manifest, files))
+}
+
+/// 将 CurseForge 整合包转换为 `.mrpack`：通过 CurseForge API 解析清单中引用的文件，
+/// 覆盖目录原样保留在 `overrides/` 下
+pub async fn convert_to_mrpack(
+    data: bytes::Bytes,
+) -> Result<Option<bytes::Bytes>, ValidationError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let Some(manifest) = read_manifest(&mut archive)? else {
+        return Ok(None);
+    };
+
+    let flame_anvil_url = dotenvy::var("FLAME_ANVIL_URL").unwrap_or_default();
+    if flame_anvil_url == "none" || flame_anvil_url.is_empty() {
+        return Err(ValidationError::InvalidInput(
+            "未配置 CurseForge API，无法转换为 mrpack".into(),
+        ));
+    }
+    let flame_anvil_url = flame_anvil_url.trim_end_matches('/');
+
+    let client = reqwest::Client::new();
+    let flame_files = if manifest.files.is_empty() {
+        Vec::new()
+    } else {
+        client
+            .post(format!("{flame_anvil_url}/v1/mods/files"))
+            .json(&serde_json::json!({
+                "fileIds": manifest.files.iter().map(|x| x.file_id).collect::<Vec<_>>()
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<FlameResponse<Vec<FlameFile>>>()
+            .await?
+            .data
+    };
+    let flame_projects = if flame_files.is_empty() {
+        Vec::new()
+    } else {
+        client
+            .post(format!("{flame_anvil_url}/v1/mods"))
+            .json(&serde_json::json!({
+                "modIds": flame_files.iter().map(|x| x.mod_id).collect::<Vec<_>>()
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<FlameResponse<Vec<FlameProject>>>()
+            .await?
+            .data
+    };
+
+    let mut targets = Vec::new();
+    for file in &manifest.files {
+        let flame_file = flame_files
+            .iter()
+            .find(|x| x.id == file.file_id)
+            .ok_or_else(|| {
+                ValidationError::InvalidInput(
+                    format!("CurseForge 上找不到文件 {}", file.file_id).into(),
+                )
+            })?;
+        // 作者关闭了第三方分发的文件没有下载地址，只能在 CurseForge 启动器中下载
+        let url = flame_file.download_url.clone().ok_or_else(|| {
+            ValidationError::InvalidInput(
+                format!(
+                    "文件 {} 不允许第三方下载，无法转换为 mrpack",
+                    flame_file.file_name
+                )
+                .into(),
+            )
+        })?;
+        let directory = flame_projects
+            .iter()
+            .find(|x| x.id == flame_file.mod_id)
+            .and_then(|x| x.class_id)
+            .and_then(|class| {
+                CLASS_DIRECTORIES.iter().find(|(id, _)| *id == class)
+            })
+            .map(|(_, dir)| *dir)
+            .unwrap_or("mods");
+        let path = SafeRelativePath::new(format!(
+            "{directory}/{}",
+            flame_file.file_name
+        ))
+        .map_err(|err| ValidationError::InvalidInput(err.into()))?;
+        let sha1 = flame_file
+            .hashes
+            .iter()
+            .find(|x| x.algo == 1)
+            .map(|x| x.value.clone());
+
+        targets.push((url, path, sha1));
+    }
+
+    // mrpack 要求 SHA512，CurseForge 只提供 SHA1，需要下载文件自行计算
+    let files = futures::stream::iter(targets)
+        .map(|(url, path, sha1)| {
+            let client = &client;
+            async move {
+                let contents = client
+                    .get(&url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?;
+                let hashes = hash_file(&contents);
+                if sha1.is_some_and(|x| {
+                    Some(&x) != hashes.get(&PackFileHash::Sha1)
+                }) {
+                    return Err(ValidationError::InvalidInput(
+                        format!(
+                            "下载的文件 {path} 与 CurseForge 记录的哈希不一致"
+                        )
+                        .into(),
+                    ));
+                }
+                Ok(PackFile {
+                    path,
+                    hashes,
+                    env: None,
+                    downloads: vec![url],
+                    file_size: contents.len() as u32,
+                })
+            }
+        })
+        .buffered(DOWNLOAD_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let pack = pack_format(&manifest, files);
+    pack.validate().map_err(|err| {
+        ValidationError::InvalidInput(
+            validation_errors_to_string(err, None).into(),
+        )
+    })?;
+
+    let prefix = manifest.overrides_prefix();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("modrinth.index.json", SimpleFileOptions::default())?;
+    writer.write_all(&serde_json::to_vec_pretty(&pack)?)?;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let Some(path) = file.name().strip_prefix(&prefix) else {
+            continue;
+        };
+        if path.is_empty() {
+            continue;
+        }
+        let name = format!("overrides/{path}");
+        writer.raw_copy_file_rename(file, name)?;
+    }
+
+    Ok(Some(writer.finish()?.into_inner().into()))
+}
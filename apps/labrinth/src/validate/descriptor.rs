@@ -0,0 +1,477 @@
+//! 模组描述文件的简易读取
+//!
+//! `mods.toml` 与 `plugin.yml` 只需要读取少数几个顶层字段与表，
+//! 这里按描述文件的常见写法解析 TOML 与 YAML 的子集：
+//! 无法识别的值（数组、内联表、嵌套结构等）会被跳过，不会导致失败。
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TomlValue {
+    String(String),
+    Bool(bool),
+    /// 数字、日期、数组、内联表等不需要读取的值
+    Other,
+}
+
+/// TOML 中的一张表；`name` 为表头（根表为空字符串），`[[x]]` 的每个元素各为一张表
+#[derive(Debug, Clone, Default)]
+pub struct TomlTable {
+    pub name: String,
+    pub entries: HashMap<String, TomlValue>,
+}
+
+impl TomlTable {
+    pub fn string(&self, key: &str) -> Option<&str> {
+        match self.entries.get(key) {
+            Some(TomlValue::String(x)) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        match self.entries.get(key) {
+            Some(TomlValue::Bool(x)) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+struct Cursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            rest: text,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        self.rest.starts_with(pattern)
+    }
+
+    fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.next();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    /// 跳过空白、换行与注释
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                self.skip_line();
+            } else if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// 跳过同一行内的空格
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+    }
+
+    fn read_until(&mut self, pattern: &str) -> String {
+        let mut value = String::new();
+        while !self.rest.is_empty() && !self.starts_with(pattern) {
+            value.extend(self.next());
+        }
+        self.skip(pattern.chars().count());
+        value
+    }
+
+    fn read_basic_string(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '"' => break,
+                '\\' => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(x) => value.push(x),
+                    None => break,
+                },
+                x => value.push(x),
+            }
+        }
+        value
+    }
+
+    /// 跳过数组或内联表，考虑其中的字符串与嵌套
+    fn skip_nested(&mut self) {
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                '[' | '{' => {
+                    depth += 1;
+                    self.next();
+                }
+                ']' | '}' => {
+                    self.next();
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                '"' => {
+                    self.next();
+                    if self.starts_with("\"\"") {
+                        self.skip(2);
+                        self.read_until("\"\"\"");
+                    } else {
+                        self.read_basic_string();
+                    }
+                }
+                '\'' => {
+                    self.next();
+                    self.read_until("'");
+                }
+                '#' => self.skip_line(),
+                _ => {
+                    self.next();
+                }
+            }
+        }
+    }
+
+    fn read_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.next();
+                    key.push_str(&self.read_basic_string());
+                }
+                '\'' => {
+                    self.next();
+                    key.push_str(&self.read_until("'"));
+                }
+                '=' | ']' | '\n' => break,
+                c if c.is_whitespace() => {
+                    self.next();
+                }
+                c => {
+                    key.push(c);
+                    self.next();
+                }
+            }
+        }
+        key
+    }
+
+    fn read_toml_value(&mut self) -> TomlValue {
+        self.skip_spaces();
+        if self.starts_with("\"\"\"") {
+            self.skip(3);
+            // 紧跟在开头引号后的换行不属于字符串内容
+            if self.peek() == Some('\n') {
+                self.next();
+            }
+            return TomlValue::String(self.read_until("\"\"\""));
+        }
+        if self.starts_with("'''") {
+            self.skip(3);
+            if self.peek() == Some('\n') {
+                self.next();
+            }
+            return TomlValue::String(self.read_until("'''"));
+        }
+
+        match self.peek() {
+            Some('"') => {
+                self.next();
+                TomlValue::String(self.read_basic_string())
+            }
+            Some('\'') => {
+                self.next();
+                TomlValue::String(self.read_until("'"))
+            }
+            Some('[' | '{') => {
+                self.skip_nested();
+                TomlValue::Other
+            }
+            _ => {
+                let mut raw = String::new();
+                while let Some(c) = self.peek() {
+                    if c == '\n' || c == '#' {
+                        break;
+                    }
+                    raw.push(c);
+                    self.next();
+                }
+                match raw.trim() {
+                    "true" => TomlValue::Bool(true),
+                    "false" => TomlValue::Bool(false),
+                    _ => TomlValue::Other,
+                }
+            }
+        }
+    }
+}
+
+/// 解析 TOML 文本，按出现顺序返回所有表，第一张为根表
+pub fn parse_toml(text: &str) -> Vec<TomlTable> {
+    let mut tables = vec![TomlTable::default()];
+    let mut cursor = Cursor::new(text);
+
+    loop {
+        cursor.skip_blank();
+        let Some(c) = cursor.peek() else {
+            break;
+        };
+
+        if c == '[' {
+            // `[x]` 与 `[[x]]` 都作为一张新表处理
+            cursor.next();
+            if cursor.peek() == Some('[') {
+                cursor.next();
+            }
+            let name = cursor.read_key();
+            cursor.skip_line();
+            tables.push(TomlTable {
+                name,
+                entries: HashMap::new(),
+            });
+            continue;
+        }
+
+        let key = cursor.read_key();
+        if cursor.peek() != Some('=') {
+            cursor.skip_line();
+            continue;
+        }
+        cursor.next();
+        let value = cursor.read_toml_value();
+        if let Some(table) = tables.last_mut() {
+            table.entries.insert(key, value);
+        }
+    }
+
+    tables
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum YamlValue {
+    String(String),
+    List(Vec<String>),
+    /// 嵌套的映射等不需要读取的值
+    Other,
+}
+
+fn unquote_yaml(value: &str) -> String {
+    let value = value.trim();
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')));
+    if quoted {
+        value[1..value.len() - 1].to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn strip_yaml_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if i == 0 || line[..i].ends_with(' ') => {
+                return &line[..i];
+            }
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 解析 YAML 文本的顶层键；只识别标量、行内列表与块列表
+pub fn parse_yaml(text: &str) -> HashMap<String, YamlValue> {
+    let mut values = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in text.lines() {
+        let line = strip_yaml_comment(line).trim_end();
+        if line.trim().is_empty() || line.starts_with("---") {
+            continue;
+        }
+
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        let trimmed = line.trim_start();
+
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| if trimmed == "-" { Some("") } else { None })
+        {
+            if let Some(key) = &current {
+                match values.get_mut(key) {
+                    Some(YamlValue::List(list)) => {
+                        list.push(unquote_yaml(item))
+                    }
+                    Some(value @ YamlValue::Other) => {
+                        *value = YamlValue::List(vec![unquote_yaml(item)]);
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        if indented {
+            // 嵌套映射的内容
+            if let Some(key) = &current
+                && let Some(value) = values.get_mut(key)
+                && matches!(value, YamlValue::List(list) if list.is_empty())
+            {
+                *value = YamlValue::Other;
+            }
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            current = None;
+            continue;
+        };
+        let key = unquote_yaml(key);
+        let value = value.trim();
+
+        let parsed = if value.is_empty() {
+            // 之后可能是块列表，也可能是嵌套映射
+            YamlValue::Other
+        } else if let Some(inner) =
+            value.strip_prefix('[').and_then(|x| x.strip_suffix(']'))
+        {
+            YamlValue::List(
+                inner
+                    .split(',')
+                    .map(unquote_yaml)
+                    .filter(|x| !x.is_empty())
+                    .collect(),
+            )
+        } else if value.starts_with('{') {
+            YamlValue::Other
+        } else {
+            YamlValue::String(unquote_yaml(value))
+        };
+
+        values.insert(key.clone(), parsed);
+        current = Some(key);
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mods_toml() {
+        let tables = parse_toml(
+            r#"
+modLoader="javafml" # 注释
+loaderVersion="[47,)"
+license='MIT'
+clientSideOnly=true
+
+[[mods]]
+modId="examplemod"
+version="${file.jarVersion}"
+description='''
+多行描述，包含 "引号" 与 [方括号]
+'''
+authors=["a", "b"]
+
+[[dependencies.examplemod]]
+    modId="minecraft"
+    mandatory=true
+    versionRange="[1.20.1,1.21)"
+    ordering="NONE"
+    side="BOTH"
+"#,
+        );
+
+        assert_eq!(tables.len(), 3);
+        assert_eq!(tables[0].string("license"), Some("MIT"));
+        assert_eq!(tables[0].bool("clientSideOnly"), Some(true));
+        assert_eq!(tables[1].name, "mods");
+        assert_eq!(tables[1].string("modId"), Some("examplemod"));
+        assert_eq!(tables[1].entries.get("authors"), Some(&TomlValue::Other));
+        assert_eq!(tables[2].name, "dependencies.examplemod");
+        assert_eq!(tables[2].string("versionRange"), Some("[1.20.1,1.21)"));
+        assert_eq!(tables[2].bool("mandatory"), Some(true));
+    }
+
+    #[test]
+    fn parses_plugin_yml() {
+        let values = parse_yaml(
+            r#"
+name: "ExamplePlugin"
+version: 1.2.0 # 注释
+api-version: '1.20'
+depend: [Vault, ProtocolLib]
+softdepend:
+  - PlaceholderAPI
+  - 'LuckPerms'
+commands:
+  example:
+    description: test
+"#,
+        );
+
+        assert_eq!(
+            values.get("name"),
+            Some(&YamlValue::String("ExamplePlugin".to_string()))
+        );
+        assert_eq!(
+            values.get("version"),
+            Some(&YamlValue::String("1.2.0".to_string()))
+        );
+        assert_eq!(
+            values.get("api-version"),
+            Some(&YamlValue::String("1.20".to_string()))
+        );
+        assert_eq!(
+            values.get("depend"),
+            Some(&YamlValue::List(vec![
+                "Vault".to_string(),
+                "ProtocolLib".to_string()
+            ]))
+        );
+        assert_eq!(
+            values.get("softdepend"),
+            Some(&YamlValue::List(vec![
+                "PlaceholderAPI".to_string(),
+                "LuckPerms".to_string()
+            ]))
+        );
+        assert_eq!(values.get("commands"), Some(&YamlValue::Other));
+    }
+}
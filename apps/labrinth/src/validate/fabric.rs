@@ -1,5 +1,6 @@
 use crate::validate::{
-    SupportedGameVersions, ValidationError, ValidationResult, filter_out_packs,
+    ModMetadata, SupportedGameVersions, ValidationError, ValidationResult,
+    filter_out_packs, metadata,
 };
 use std::io::Cursor;
 use zip::ZipArchive;
@@ -46,4 +47,11 @@ impl super::Validator for FabricValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_fabric(archive)
+    }
 }
@@ -1,5 +1,6 @@
 use crate::validate::{
-    SupportedGameVersions, ValidationError, ValidationResult, filter_out_packs,
+    ModMetadata, SupportedGameVersions, ValidationError, ValidationResult,
+    filter_out_packs, metadata,
 };
 use chrono::DateTime;
 use std::io::Cursor;
@@ -50,6 +51,13 @@ impl super::Validator for ForgeValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_forge(archive)
+    }
 }
 
 pub struct LegacyForgeValidator;
@@ -95,4 +103,11 @@ impl super::Validator for LegacyForgeValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_legacy_forge(archive)
+    }
 }
@@ -0,0 +1,580 @@
+//! 上传的 jar 中可疑代码的扫描
+//!
+//! 读取每个类文件的常量池，按引用的类、方法与字符串常量匹配已知的恶意软件特征与可疑行为。
+//! 这只是启发式的检查：结果交由审核员判断，超过阈值的版本会暂缓发布。
+
+use crate::models::malware::{ScanFinding, ScanRule, ScanSeverity};
+use crate::util::env::parse_var;
+use crate::validate::ValidationError;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// 扫描的文件后缀
+pub const SCANNED_EXTENSIONS: &[&str] = &["jar"];
+
+/// 单个条目解压后的最大大小，更大的条目跳过
+const MAX_ENTRY_SIZE: u64 = 64 * (1 << 20);
+
+/// 内嵌 jar（jar-in-jar）的最大嵌套层数
+const MAX_NESTED_DEPTH: usize = 2;
+
+/// 每个文件最多保留的结果数
+const MAX_FINDINGS: usize = 50;
+
+/// 已知恶意软件（fractureiser / Skyrage 等）的类名前缀
+const MALWARE_CLASS_PREFIXES: &[&str] = &[
+    "dev/neko/nekoclient",
+    "dev/neko/nekoinjector",
+    "dev/neko/nekoloader",
+];
+
+/// 已知恶意软件使用的服务器与落地文件
+const MALWARE_STRINGS: &[&str] = &[
+    "85.217.144.130",
+    "107.189.3.101",
+    "files-8ie.pages.dev",
+    "skyrage.de",
+    "libwebgl64.jar",
+    "nekoclient",
+    "nekoinjector",
+];
+
+/// 账户、浏览器与 Discord 凭据的存放位置
+const CREDENTIAL_PATHS: &[&str] = &[
+    "local storage/leveldb",
+    "local storage\\leveldb",
+    "discordcanary",
+    "discordptb",
+    "google/chrome/user data",
+    "google\\chrome\\user data",
+    "login data",
+    "launcher_accounts.json",
+    "launcher_accounts_microsoft_store.json",
+    "microsoft_accounts.json",
+    ".lunarclient/settings/game/accounts.json",
+    ".feather/accounts.json",
+];
+
+const WEBHOOK_HOSTS: &[&str] =
+    &["discord.com/api/webhooks", "discordapp.com/api/webhooks"];
+
+/// 读取会话令牌的方法（官方名与各映射表中的混淆名）
+const SESSION_TOKEN_METHODS: &[&str] = &[
+    "getAccessToken",
+    "getSessionID",
+    "func_111286_b",
+    "func_148254_d",
+    "m_92547_",
+    "method_1674",
+];
+
+const NETWORK_CLASSES: &[&str] = &[
+    "java/net/HttpURLConnection",
+    "java/net/URLConnection",
+    "java/net/http/HttpClient",
+    "java/net/Socket",
+    "okhttp3/OkHttpClient",
+];
+
+const DEFINE_CLASS_METHODS: &[&str] = &[
+    "java/lang/ClassLoader.defineClass",
+    "java/security/SecureClassLoader.defineClass",
+    "java/lang/invoke/MethodHandles$Lookup.defineClass",
+    "sun/misc/Unsafe.defineClass",
+];
+
+/// 常见于加密或压缩后再加载的类
+const PAYLOAD_DECODING_CLASSES: &[&str] = &[
+    "java/util/Base64",
+    "javax/crypto/Cipher",
+    "java/util/zip/Inflater",
+    "java/util/zip/GZIPInputStream",
+];
+
+const PROCESS_METHODS: &[&str] =
+    &["java/lang/Runtime.exec", "java/lang/ProcessBuilder.start"];
+
+const NATIVE_LOAD_METHODS: &[&str] = &[
+    "java/lang/System.load",
+    "java/lang/System.loadLibrary",
+    "java/lang/Runtime.load",
+    "java/lang/Runtime.loadLibrary",
+];
+
+const FILE_WRITE_METHODS: &[&str] = &[
+    "java/io/FileOutputStream.<init>",
+    "java/nio/file/Files.copy",
+    "java/nio/file/Files.write",
+    "java/nio/file/Files.newOutputStream",
+];
+
+const NATIVE_EXTENSIONS: &[&str] = &[".dll", ".so", ".dylib", ".exe"];
+
+/// 达到此严重程度的版本暂缓发布，由 `MALWARE_SCAN_WITHHOLD_SEVERITY` 配置
+pub fn withhold_severity() -> ScanSeverity {
+    parse_var("MALWARE_SCAN_WITHHOLD_SEVERITY").unwrap_or(ScanSeverity::High)
+}
+
+pub fn should_withhold(findings: &[ScanFinding]) -> bool {
+    let threshold = withhold_severity();
+    findings.iter().any(|x| x.severity >= threshold)
+}
+
+/// 类文件常量池中与检查有关的内容
+#[derive(Default)]
+struct ClassInfo {
+    classes: HashSet<String>,
+    /// `类名.方法名`
+    methods: HashSet<String>,
+    strings: Vec<String>,
+}
+
+impl ClassInfo {
+    fn has_class(&self, names: &[&str]) -> Option<String> {
+        names
+            .iter()
+            .find(|x| self.classes.contains(**x))
+            .map(|x| x.to_string())
+    }
+
+    fn has_method(&self, names: &[&str]) -> Option<String> {
+        names
+            .iter()
+            .find(|x| self.methods.contains(**x))
+            .map(|x| x.to_string())
+    }
+
+    fn has_string(&self, patterns: &[&str]) -> Option<String> {
+        self.strings.iter().find_map(|x| {
+            let lower = x.to_lowercase();
+            patterns
+                .iter()
+                .any(|pattern| lower.contains(pattern))
+                .then(|| x.clone())
+        })
+    }
+}
+
+/// 解析类文件的常量池；不是有效的类文件时返回 None
+fn parse_class(data: &[u8]) -> Option<ClassInfo> {
+    struct Reader<'a>(&'a [u8]);
+    impl Reader<'_> {
+        fn take(&mut self, count: usize) -> Option<&[u8]> {
+            if count > self.0.len() {
+                return None;
+            }
+            let (value, rest) = self.0.split_at(count);
+            self.0 = rest;
+            Some(value)
+        }
+        fn u16(&mut self) -> Option<u16> {
+            self.take(2).map(|x| u16::from_be_bytes([x[0], x[1]]))
+        }
+    }
+
+    enum Constant {
+        Utf8(String),
+        Class(u16),
+        String(u16),
+        Member(u16, u16),
+        NameAndType(u16),
+        Other,
+    }
+
+    let mut reader = Reader(data);
+    if reader.take(4)? != [0xca, 0xfe, 0xba, 0xbe] {
+        return None;
+    }
+    reader.take(4)?;
+    let count = reader.u16()? as usize;
+
+    let mut pool = Vec::with_capacity(count);
+    pool.push(Constant::Other);
+    while pool.len() < count {
+        let tag = reader.take(1)?[0];
+        let constant = match tag {
+            1 => {
+                let length = reader.u16()? as usize;
+                // 类文件使用修改过的 UTF-8，类名与常见字符串不受影响
+                Constant::Utf8(
+                    String::from_utf8_lossy(reader.take(length)?).into_owned(),
+                )
+            }
+            7 => Constant::Class(reader.u16()?),
+            8 => Constant::String(reader.u16()?),
+            9..=11 => Constant::Member(reader.u16()?, reader.u16()?),
+            12 => {
+                let name = reader.u16()?;
+                reader.u16()?;
+                Constant::NameAndType(name)
+            }
+            3 | 4 | 17 | 18 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            5 | 6 => {
+                // long 与 double 占用两个位置
+                reader.take(8)?;
+                pool.push(Constant::Other);
+                Constant::Other
+            }
+            15 => {
+                reader.take(3)?;
+                Constant::Other
+            }
+            16 | 19 | 20 => {
+                reader.take(2)?;
+                Constant::Other
+            }
+            _ => return None,
+        };
+        pool.push(constant);
+    }
+
+    let utf8 = |index: u16| match pool.get(index as usize) {
+        Some(Constant::Utf8(x)) => Some(x.as_str()),
+        _ => None,
+    };
+    let class_name = |index: u16| match pool.get(index as usize) {
+        Some(Constant::Class(name)) => utf8(*name),
+        _ => None,
+    };
+
+    let mut info = ClassInfo::default();
+    for constant in &pool {
+        match constant {
+            Constant::Class(name) => {
+                if let Some(name) = utf8(*name) {
+                    info.classes.insert(name.to_string());
+                }
+            }
+            Constant::String(value) => {
+                if let Some(value) = utf8(*value) {
+                    info.strings.push(value.to_string());
+                }
+            }
+            Constant::Member(class, name_and_type) => {
+                if let Some(class) = class_name(*class)
+                    && let Some(Constant::NameAndType(name)) =
+                        pool.get(*name_and_type as usize)
+                    && let Some(name) = utf8(*name)
+                {
+                    info.methods.insert(format!("{class}.{name}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    info.strings.extend(byte_array_strings(data));
+
+    Some(info)
+}
+
+/// 还原用 `new byte[]{...}` 拼出的字符串：fractureiser 等用这种方式隐藏地址，
+/// 它们在字节码中是一连串的 `bipush <值>; bastore`
+fn byte_array_strings(code: &[u8]) -> Vec<String> {
+    const BIPUSH: u8 = 0x10;
+    const BASTORE: u8 = 0x54;
+    // 两次写入之间是 `dup` 与数组下标的压栈指令
+    const MAX_GAP: usize = 4;
+
+    let mut strings = Vec::new();
+    let mut current = Vec::new();
+    let mut last_end = 0;
+    let mut i = 0;
+    while i + 2 < code.len() {
+        if code[i] == BIPUSH
+            && code[i + 2] == BASTORE
+            && (0x20..0x7f).contains(&code[i + 1])
+        {
+            if !current.is_empty() && i > last_end + MAX_GAP {
+                strings.push(String::from_utf8_lossy(&current).into_owned());
+                current.clear();
+            }
+            current.push(code[i + 1]);
+            last_end = i + 3;
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    strings.push(String::from_utf8_lossy(&current).into_owned());
+    strings.retain(|x| x.len() >= 6);
+    strings
+}
+
+fn is_remote_url(value: &str) -> bool {
+    let lower = value.to_lowercase();
+    let Some(host) = lower
+        .strip_prefix("http://")
+        .or_else(|| lower.strip_prefix("https://"))
+    else {
+        return false;
+    };
+    !host.starts_with("localhost") && !host.starts_with("127.")
+}
+
+fn check_class(path: &str, info: &ClassInfo, findings: &mut Vec<ScanFinding>) {
+    let mut push = |severity, rule, detail: String| {
+        findings.push(ScanFinding {
+            severity,
+            rule,
+            path: path.to_string(),
+            detail,
+        });
+    };
+
+    if let Some(class) = info.classes.iter().find(|x| {
+        MALWARE_CLASS_PREFIXES
+            .iter()
+            .any(|prefix| x.starts_with(prefix))
+    }) {
+        push(
+            ScanSeverity::Critical,
+            ScanRule::KnownMalware,
+            class.clone(),
+        );
+    } else if let Some(value) = info.has_string(MALWARE_STRINGS) {
+        push(ScanSeverity::Critical, ScanRule::KnownMalware, value);
+    }
+
+    let credential = info.has_string(CREDENTIAL_PATHS);
+    let webhook = info.has_string(WEBHOOK_HOSTS);
+    let session_token = info.methods.iter().find(|x| {
+        x.rsplit_once('.')
+            .is_some_and(|(_, name)| SESSION_TOKEN_METHODS.contains(&name))
+    });
+    let network = info.has_class(NETWORK_CLASSES);
+    match (credential, webhook) {
+        (Some(credential), Some(webhook)) => push(
+            ScanSeverity::Critical,
+            ScanRule::TokenStealing,
+            format!("{credential} → {webhook}"),
+        ),
+        (Some(value), None) | (None, Some(value)) => {
+            push(ScanSeverity::High, ScanRule::TokenStealing, value)
+        }
+        (None, None) => {}
+    }
+    if let Some(method) = session_token
+        && let Some(network) = network
+    {
+        push(
+            ScanSeverity::High,
+            ScanRule::TokenStealing,
+            format!("{method} + {network}"),
+        );
+    }
+
+    if let Some(define) = info.has_method(DEFINE_CLASS_METHODS)
+        && let Some(decoding) = info.has_class(PAYLOAD_DECODING_CLASSES)
+    {
+        push(
+            ScanSeverity::High,
+            ScanRule::ObfuscatedLoader,
+            format!("{define} + {decoding}"),
+        );
+    }
+
+    if info.classes.contains("java/net/URLClassLoader")
+        && let Some(url) = info.strings.iter().find(|x| is_remote_url(x))
+    {
+        push(
+            ScanSeverity::High,
+            ScanRule::RemoteClassLoading,
+            format!("java/net/URLClassLoader + {url}"),
+        );
+    }
+
+    if let Some(load) = info.has_method(NATIVE_LOAD_METHODS)
+        && let Some(write) = info.has_method(FILE_WRITE_METHODS)
+    {
+        push(
+            ScanSeverity::Medium,
+            ScanRule::NativeLibraryDrop,
+            format!("{write} + {load}"),
+        );
+    }
+
+    if let Some(method) = info.has_method(PROCESS_METHODS) {
+        push(ScanSeverity::Medium, ScanRule::ProcessExecution, method);
+    }
+}
+
+fn scan_archive(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    prefix: &str,
+    depth: usize,
+    findings: &mut Vec<ScanFinding>,
+) -> Result<(), ValidationError> {
+    let mut native_libraries = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        if file.is_dir() || file.size() > MAX_ENTRY_SIZE {
+            continue;
+        }
+
+        let lower = name.to_lowercase();
+        if NATIVE_EXTENSIONS.iter().any(|x| lower.ends_with(x)) {
+            native_libraries.push(name);
+            continue;
+        }
+
+        let is_class = lower.ends_with(".class");
+        let is_jar = lower.ends_with(".jar") && depth < MAX_NESTED_DEPTH;
+        if !is_class && !is_jar {
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        drop(file);
+
+        let path = format!("{prefix}{name}");
+        if is_class {
+            if let Some(info) = parse_class(&contents) {
+                check_class(&path, &info, findings);
+            }
+        } else if let Ok(mut nested) =
+            ZipArchive::new(Cursor::new(bytes::Bytes::from(contents)))
+        {
+            scan_archive(
+                &mut nested,
+                &format!("{path}!/"),
+                depth + 1,
+                findings,
+            )?;
+        }
+    }
+
+    // 本地库本身不可疑，只在有代码释放并加载本地库时一并列出
+    if findings.iter().any(|x| {
+        x.rule == ScanRule::NativeLibraryDrop && x.path.starts_with(prefix)
+    }) {
+        for library in native_libraries {
+            findings.push(ScanFinding {
+                severity: ScanSeverity::Medium,
+                rule: ScanRule::NativeLibraryDrop,
+                path: format!("{prefix}{library}"),
+                detail: library,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// 扫描 jar 中的类文件与内嵌 jar，结果按严重程度降序排列
+pub fn scan_jar(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Result<Vec<ScanFinding>, ValidationError> {
+    let mut findings = Vec::new();
+    scan_archive(archive, "", 0, &mut findings)?;
+
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    findings.truncate(MAX_FINDINGS);
+    Ok(findings)
+}
+
+/// 在阻塞线程池中扫描已经上传的 jar 文件
+pub async fn scan_jar_bytes(
+    data: bytes::Bytes,
+) -> Result<Vec<ScanFinding>, ValidationError> {
+    actix_web::web::block(move || {
+        scan_jar(&mut ZipArchive::new(Cursor::new(data))?)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造只有常量池的类文件
+    fn class_file(constants: &[&[u8]], code: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+        data.extend_from_slice(&(constants.len() as u16 + 1).to_be_bytes());
+        for constant in constants {
+            data.extend_from_slice(constant);
+        }
+        data.extend_from_slice(code);
+        data
+    }
+
+    fn utf8(value: &str) -> Vec<u8> {
+        let mut data = vec![1];
+        data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        data.extend_from_slice(value.as_bytes());
+        data
+    }
+
+    #[test]
+    fn detects_remote_class_loading() {
+        let constants = [
+            utf8("java/net/URLClassLoader"),
+            vec![7, 0, 1],
+            utf8("https://example.com/payload.jar"),
+            vec![8, 0, 3],
+            utf8("java/lang/Runtime"),
+            vec![7, 0, 5],
+            utf8("exec"),
+            utf8("(Ljava/lang/String;)Ljava/lang/Process;"),
+            vec![12, 0, 7, 0, 8],
+            vec![10, 0, 6, 0, 9],
+        ];
+        let data = class_file(
+            &constants.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            &[],
+        );
+
+        let info = parse_class(&data).unwrap();
+        let mut findings = Vec::new();
+        check_class("a/B.class", &info, &mut findings);
+
+        assert_eq!(
+            findings.iter().map(|x| x.rule).collect::<Vec<_>>(),
+            vec![ScanRule::RemoteClassLoading, ScanRule::ProcessExecution]
+        );
+        assert_eq!(findings[0].severity, ScanSeverity::High);
+    }
+
+    #[test]
+    fn recovers_byte_array_strings() {
+        // new byte[]{'8','5','.', ...}：dup; iconst/bipush 下标; bipush 值; bastore
+        let mut code = Vec::new();
+        for (index, byte) in "85.217.144.130".bytes().enumerate() {
+            code.extend_from_slice(&[
+                0x59,
+                0x10,
+                index as u8,
+                0x10,
+                byte,
+                0x54,
+            ]);
+        }
+        let data = class_file(&[], &code);
+
+        let info = parse_class(&data).unwrap();
+        assert_eq!(info.strings, vec!["85.217.144.130".to_string()]);
+
+        let mut findings = Vec::new();
+        check_class("a/B.class", &info, &mut findings);
+        assert_eq!(findings[0].rule, ScanRule::KnownMalware);
+        assert_eq!(findings[0].severity, ScanSeverity::Critical);
+    }
+
+    #[test]
+    fn ignores_invalid_classes() {
+        assert!(parse_class(b"not a class").is_none());
+        assert!(
+            parse_class(&[0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52, 0, 2, 1])
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,857 @@
+//! 从上传文件的描述文件中提取模组元数据
+//!
+//! 各加载器的描述文件（`fabric.mod.json`、`mods.toml`、`plugin.yml` 等）
+//! 会声明模组 ID、版本号、支持的游戏版本范围、依赖与运行环境，
+//! 创建版本时据此检查作者填写的字段并补全依赖。
+
+use crate::models::projects::DependencyType;
+use crate::validate::descriptor::{YamlValue, parse_toml, parse_yaml};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// 描述文件大小上限，超过时不再解析
+const MAX_DESCRIPTOR_SIZE: u64 = 1 << 20;
+
+/// 游戏本体、加载器与运行时的 ID，不作为模组依赖
+const PLATFORM_IDS: &[&str] = &[
+    "minecraft",
+    "java",
+    "forge",
+    "neoforge",
+    "fabricloader",
+    "fabric-loader",
+    "quilt_loader",
+    "javafml",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModEnvironment {
+    Client,
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModDependency {
+    pub mod_id: String,
+    pub dependency_type: DependencyType,
+    pub version_range: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModMetadata {
+    /// 描述文件可被哪些加载器读取
+    pub loaders: Vec<&'static str>,
+    pub mod_id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// 支持的游戏版本范围，满足其中任意一个即可
+    pub game_versions: Vec<String>,
+    pub dependencies: Vec<ModDependency>,
+    pub environment: Option<ModEnvironment>,
+}
+
+impl ModMetadata {
+    fn new(loaders: &[&'static str], mod_id: &str) -> Self {
+        Self {
+            loaders: loaders.to_vec(),
+            mod_id: mod_id.to_string(),
+            name: None,
+            version: None,
+            game_versions: Vec::new(),
+            dependencies: Vec::new(),
+            environment: None,
+        }
+    }
+
+    fn push_dependency(
+        &mut self,
+        mod_id: &str,
+        dependency_type: DependencyType,
+        version_range: Option<String>,
+    ) {
+        let mod_id = mod_id.trim();
+        if mod_id.is_empty()
+            || mod_id == self.mod_id
+            || PLATFORM_IDS.contains(&mod_id)
+            || self.dependencies.iter().any(|x| x.mod_id == mod_id)
+        {
+            return;
+        }
+        self.dependencies.push(ModDependency {
+            mod_id: mod_id.to_string(),
+            dependency_type,
+            version_range: version_range.filter(|x| !x.is_empty()),
+        });
+    }
+
+    /// 游戏版本是否在声明的范围内；未声明或无法判断时返回 None
+    pub fn supports_game_version(&self, version: &str) -> Option<bool> {
+        let mut unknown = self.game_versions.is_empty();
+        for range in &self.game_versions {
+            match matches_game_version(range, version) {
+                Some(true) => return Some(true),
+                Some(false) => {}
+                None => unknown = true,
+            }
+        }
+        if unknown { None } else { Some(false) }
+    }
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    name: &str,
+) -> Option<String> {
+    let file = archive.by_name(name).ok()?;
+    if file.size() > MAX_DESCRIPTOR_SIZE {
+        return None;
+    }
+    let mut text = String::new();
+    file.take(MAX_DESCRIPTOR_SIZE)
+        .read_to_string(&mut text)
+        .ok()?;
+    Some(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// 带有未替换占位符（如 `${version}`）的值视为未声明
+fn declared(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.contains("${"))
+        .map(|x| x.to_string())
+}
+
+fn json_str(value: &Value, key: &str) -> Option<String> {
+    declared(value.get(key).and_then(Value::as_str))
+}
+
+/// 读取单个字符串或字符串数组
+fn json_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(x) => vec![x.clone()],
+        Value::Array(x) => x
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|x| x.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn read_fabric(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    parse_fabric(&read_entry(archive, "fabric.mod.json")?)
+}
+
+pub fn read_quilt(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    parse_quilt(&read_entry(archive, "quilt.mod.json")?)
+}
+
+pub fn read_forge(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    let text = read_entry(archive, "META-INF/mods.toml")?;
+    let manifest = read_entry(archive, "META-INF/MANIFEST.MF");
+    parse_mods_toml(&text, false, manifest.as_deref())
+}
+
+pub fn read_neoforge(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    let text = read_entry(archive, "META-INF/neoforge.mods.toml")?;
+    let manifest = read_entry(archive, "META-INF/MANIFEST.MF");
+    parse_mods_toml(&text, true, manifest.as_deref())
+}
+
+pub fn read_legacy_forge(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    parse_mcmod_info(&read_entry(archive, "mcmod.info")?)
+}
+
+pub fn read_plugin_yml(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    if let Some(text) = read_entry(archive, "paper-plugin.yml") {
+        return parse_plugin_yml(&text, &["paper", "purpur", "folia"]);
+    }
+    parse_plugin_yml(
+        &read_entry(archive, "plugin.yml")?,
+        &["bukkit", "spigot", "paper", "purpur", "folia"],
+    )
+}
+
+pub fn read_bungee_yml(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    parse_plugin_yml(
+        &read_entry(archive, "bungee.yml")?,
+        &["bungeecord", "waterfall"],
+    )
+}
+
+pub fn read_velocity(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Option<ModMetadata> {
+    parse_velocity(&read_entry(archive, "velocity-plugin.json")?)
+}
+
+fn parse_fabric(text: &str) -> Option<ModMetadata> {
+    let json: Value = serde_json::from_str(text).ok()?;
+    let mut metadata =
+        ModMetadata::new(&["fabric", "quilt"], &json_str(&json, "id")?);
+    metadata.name = json_str(&json, "name");
+    metadata.version = json_str(&json, "version");
+
+    for (key, dependency_type) in [
+        ("depends", DependencyType::Required),
+        ("recommends", DependencyType::Optional),
+        ("suggests", DependencyType::Optional),
+        ("breaks", DependencyType::Incompatible),
+        ("conflicts", DependencyType::Incompatible),
+    ] {
+        let Some(Value::Object(map)) = json.get(key) else {
+            continue;
+        };
+        for (id, range) in map {
+            let ranges = json_strings(range);
+            if id == "minecraft" && dependency_type == DependencyType::Required
+            {
+                metadata.game_versions.extend(ranges);
+                continue;
+            }
+            metadata.push_dependency(
+                id,
+                dependency_type,
+                Some(ranges.join(" || ")),
+            );
+        }
+    }
+
+    metadata.environment = match json.get("environment").and_then(Value::as_str)
+    {
+        Some("client") => Some(ModEnvironment::Client),
+        Some("server") => Some(ModEnvironment::Server),
+        _ => None,
+    };
+
+    Some(metadata)
+}
+
+fn parse_quilt(text: &str) -> Option<ModMetadata> {
+    let json: Value = serde_json::from_str(text).ok()?;
+    let loader = json.get("quilt_loader")?;
+    let mut metadata = ModMetadata::new(&["quilt"], &json_str(loader, "id")?);
+    metadata.version = json_str(loader, "version");
+    metadata.name = loader.get("metadata").and_then(|x| json_str(x, "name"));
+
+    for (key, dependency_type) in [
+        ("depends", DependencyType::Required),
+        ("breaks", DependencyType::Incompatible),
+    ] {
+        let Some(Value::Array(entries)) = loader.get(key) else {
+            continue;
+        };
+        for entry in entries {
+            let (id, ranges, optional) = match entry {
+                Value::String(id) => (id.clone(), Vec::new(), false),
+                Value::Object(_) => {
+                    let Some(id) = json_str(entry, "id") else {
+                        continue;
+                    };
+                    let ranges = entry
+                        .get("versions")
+                        .map(json_strings)
+                        .unwrap_or_default();
+                    let optional = entry
+                        .get("optional")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                    (id, ranges, optional)
+                }
+                _ => continue,
+            };
+
+            if id == "minecraft" && dependency_type == DependencyType::Required
+            {
+                metadata.game_versions.extend(ranges);
+                continue;
+            }
+            let dependency_type =
+                if optional && dependency_type == DependencyType::Required {
+                    DependencyType::Optional
+                } else {
+                    dependency_type
+                };
+            metadata.push_dependency(
+                &id,
+                dependency_type,
+                Some(ranges.join(" || ")),
+            );
+        }
+    }
+
+    metadata.environment = match json
+        .get("minecraft")
+        .and_then(|x| x.get("environment"))
+        .and_then(Value::as_str)
+    {
+        Some("client") => Some(ModEnvironment::Client),
+        Some("dedicated_server") => Some(ModEnvironment::Server),
+        _ => None,
+    };
+
+    Some(metadata)
+}
+
+/// Maven 规范中单独的版本号表示“推荐版本”，按不低于该版本处理
+fn maven_range(range: &str) -> String {
+    let range = range.trim();
+    if range.starts_with('[') || range.starts_with('(') || range == "*" {
+        range.to_string()
+    } else {
+        format!("[{range},)")
+    }
+}
+
+fn manifest_value(manifest: &str, key: &str) -> Option<String> {
+    manifest.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
+}
+
+fn parse_mods_toml(
+    text: &str,
+    neoforge: bool,
+    manifest: Option<&str>,
+) -> Option<ModMetadata> {
+    let tables = parse_toml(text);
+    let root = tables.first()?;
+    let mods = tables.iter().find(|x| x.name == "mods")?;
+    let mod_id = mods.string("modId")?;
+
+    let mut metadata = ModMetadata::new(&[], mod_id);
+    metadata.name = declared(mods.string("displayName"));
+    metadata.version = match mods.string("version") {
+        Some("${file.jarVersion}") => {
+            manifest.and_then(|x| manifest_value(x, "Implementation-Version"))
+        }
+        version => declared(version),
+    };
+
+    let mut requires_neoforge = neoforge;
+    let dependency_table = format!("dependencies.{mod_id}");
+    for table in tables.iter().filter(|x| x.name == dependency_table) {
+        let Some(id) = table.string("modId") else {
+            continue;
+        };
+        // Forge 使用 `mandatory`，NeoForge 使用 `type`
+        let dependency_type =
+            match (table.string("type"), table.bool("mandatory")) {
+                (Some("optional"), _) | (None, Some(false)) => {
+                    DependencyType::Optional
+                }
+                (Some("incompatible" | "discouraged"), _) => {
+                    DependencyType::Incompatible
+                }
+                _ => DependencyType::Required,
+            };
+        let range = table.string("versionRange").map(maven_range);
+
+        match id {
+            "minecraft" if dependency_type == DependencyType::Required => {
+                metadata.game_versions.extend(range);
+            }
+            "neoforge" => requires_neoforge = true,
+            _ => metadata.push_dependency(id, dependency_type, range),
+        }
+    }
+
+    metadata.loaders = if requires_neoforge {
+        vec!["neoforge"]
+    } else {
+        // 1.20.1 的 NeoForge 仍可加载 Forge 模组
+        vec!["forge", "neoforge"]
+    };
+    if root.bool("clientSideOnly") == Some(true) {
+        metadata.environment = Some(ModEnvironment::Client);
+    }
+
+    Some(metadata)
+}
+
+fn parse_mcmod_info(text: &str) -> Option<ModMetadata> {
+    let json: Value = serde_json::from_str(text).ok()?;
+    // 旧版为数组，modListVersion 2 为 { "modList": [...] }
+    let entry = match &json {
+        Value::Array(x) => x.first()?,
+        Value::Object(_) => json.get("modList")?.as_array()?.first()?,
+        _ => return None,
+    };
+
+    let mut metadata = ModMetadata::new(&["forge"], &json_str(entry, "modid")?);
+    metadata.name = json_str(entry, "name");
+    metadata.version = json_str(entry, "version");
+    if let Some(version) = json_str(entry, "mcversion") {
+        metadata.game_versions.push(format!("[{version}]"));
+    }
+    for id in entry
+        .get("requiredMods")
+        .map(json_strings)
+        .unwrap_or_default()
+    {
+        // 形如 `Forge@[10.13,)`
+        let (id, range) = match id.split_once('@') {
+            Some((id, range)) => (id.to_string(), Some(maven_range(range))),
+            None => (id, None),
+        };
+        metadata.push_dependency(&id, DependencyType::Required, range);
+    }
+
+    Some(metadata)
+}
+
+fn parse_plugin_yml(
+    text: &str,
+    loaders: &[&'static str],
+) -> Option<ModMetadata> {
+    let values = parse_yaml(text);
+    let string = |key: &str| match values.get(key) {
+        Some(YamlValue::String(x)) => declared(Some(x)),
+        _ => None,
+    };
+    let list = |key: &str| match values.get(key) {
+        Some(YamlValue::List(x)) => x.clone(),
+        Some(YamlValue::String(x)) => vec![x.clone()],
+        _ => Vec::new(),
+    };
+
+    let name = string("name")?;
+    let mut metadata = ModMetadata::new(loaders, &name);
+    metadata.name = Some(name);
+    metadata.version = string("version");
+    metadata.environment = Some(ModEnvironment::Server);
+    // api-version 表示插件可运行的最低服务端版本
+    if let Some(api_version) = string("api-version") {
+        metadata.game_versions.push(format!(">={api_version}"));
+    }
+
+    // bungee.yml 使用 depends / softDepends
+    for key in ["depend", "depends"] {
+        for id in list(key) {
+            metadata.push_dependency(&id, DependencyType::Required, None);
+        }
+    }
+    for key in ["softdepend", "softDepends"] {
+        for id in list(key) {
+            metadata.push_dependency(&id, DependencyType::Optional, None);
+        }
+    }
+
+    Some(metadata)
+}
+
+fn parse_velocity(text: &str) -> Option<ModMetadata> {
+    let json: Value = serde_json::from_str(text).ok()?;
+    let mut metadata = ModMetadata::new(&["velocity"], &json_str(&json, "id")?);
+    metadata.name = json_str(&json, "name");
+    metadata.version = json_str(&json, "version");
+    metadata.environment = Some(ModEnvironment::Server);
+
+    if let Some(Value::Array(dependencies)) = json.get("dependencies") {
+        for dependency in dependencies {
+            let Some(id) = json_str(dependency, "id") else {
+                continue;
+            };
+            let optional = dependency
+                .get("optional")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            metadata.push_dependency(
+                &id,
+                if optional {
+                    DependencyType::Optional
+                } else {
+                    DependencyType::Required
+                },
+                None,
+            );
+        }
+    }
+
+    Some(metadata)
+}
+
+/// 解析正式版版本号，如 `1.20.1`；快照与预发布版本返回 None
+fn parse_release(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .split('.')
+        .map(|x| x.parse::<u64>().ok())
+        .collect()
+}
+
+/// 解析范围边界中的版本号，忽略 `-` 与 `+` 之后的后缀
+fn parse_bound(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim();
+    let end = version.find(['-', '+']).unwrap_or(version.len());
+    parse_release(&version[..end])
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a
+            .get(i)
+            .copied()
+            .unwrap_or(0)
+            .cmp(&b.get(i).copied().unwrap_or(0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Maven 版本范围：`[1.20,1.21)`、`[1.20.1]`、`(,1.19]`，多个区间以逗号连接
+fn matches_maven_range(range: &str, version: &[u64]) -> Option<bool> {
+    let mut rest = range.trim();
+    let mut matched = false;
+
+    while !rest.is_empty() {
+        let inclusive_start = rest.starts_with('[');
+        if !inclusive_start && !rest.starts_with('(') {
+            return None;
+        }
+        let end = rest.find([']', ')'])?;
+        let inclusive_end = rest[end..].starts_with(']');
+        let inner = &rest[1..end];
+        rest = rest[end + 1..].trim_start_matches([',', ' ']);
+
+        let in_range = match inner.split_once(',') {
+            None => {
+                compare_versions(version, &parse_bound(inner)?)
+                    == Ordering::Equal
+            }
+            Some((lower, upper)) => {
+                let lower_ok = if lower.trim().is_empty() {
+                    true
+                } else {
+                    match compare_versions(version, &parse_bound(lower)?) {
+                        Ordering::Greater => true,
+                        Ordering::Equal => inclusive_start,
+                        Ordering::Less => false,
+                    }
+                };
+                let upper_ok = if upper.trim().is_empty() {
+                    true
+                } else {
+                    match compare_versions(version, &parse_bound(upper)?) {
+                        Ordering::Less => true,
+                        Ordering::Equal => inclusive_end,
+                        Ordering::Greater => false,
+                    }
+                };
+                lower_ok && upper_ok
+            }
+        };
+        matched |= in_range;
+    }
+
+    Some(matched)
+}
+
+/// Fabric / Quilt 版本谓词，如 `>=1.20`、`~1.20.1`、`1.20.x`
+fn matches_predicate(predicate: &str, version: &[u64]) -> Option<bool> {
+    if predicate == "*" {
+        return Some(true);
+    }
+    let (operator, target) = ["^", "~", ">=", "<=", ">", "<", "="]
+        .iter()
+        .find_map(|op| predicate.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("=", predicate));
+    let target = target.trim();
+
+    // `1.20.x` 与 `1.20.*` 只比较通配符之前的部分
+    let parts = target.split('.').collect::<Vec<_>>();
+    if let Some(wildcard) =
+        parts.iter().position(|x| matches!(*x, "x" | "X" | "*"))
+    {
+        let prefix = parse_release(&parts[..wildcard].join("."))?;
+        return Some(
+            version.len() >= prefix.len()
+                && version[..prefix.len()] == prefix[..],
+        );
+    }
+
+    let target = parse_bound(target)?;
+    let ordering = compare_versions(version, &target);
+    Some(match operator {
+        ">=" => ordering != Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        "<" => ordering == Ordering::Less,
+        // `~1.20.1` 允许修订号变化，`^1.20.1` 允许次版本号变化
+        "~" | "^" => {
+            let fixed = if operator == "~" { 2 } else { 1 }.min(target.len());
+            ordering != Ordering::Less
+                && version.len() >= fixed
+                && version[..fixed] == target[..fixed]
+        }
+        _ => ordering == Ordering::Equal,
+    })
+}
+
+/// 判断游戏版本是否满足声明的范围；范围无法解析或版本不是正式版时返回 None
+pub fn matches_game_version(range: &str, version: &str) -> Option<bool> {
+    let version = parse_release(version)?;
+    let range = range.trim();
+    if range.starts_with('[') || range.starts_with('(') {
+        return matches_maven_range(range, &version);
+    }
+
+    // `||` 分隔的任一组满足即可，组内以空格分隔的谓词需全部满足
+    let mut matched = false;
+    for group in range.split("||") {
+        let mut group_matched = true;
+        for predicate in group.split_whitespace() {
+            group_matched &= matches_predicate(predicate, &version)?;
+        }
+        matched |= group_matched;
+    }
+    Some(matched)
+}
+
+/// 检查描述文件与作者填写的版本信息是否矛盾，返回需要提示作者的警告
+pub fn check_version_metadata(
+    metadata: &[ModMetadata],
+    version_number: &str,
+    loaders: &[String],
+    game_versions: &[String],
+    environment: Option<&str>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if metadata.is_empty() {
+        return warnings;
+    }
+
+    let declared_loaders = metadata
+        .iter()
+        .flat_map(|x| x.loaders.iter().copied())
+        .collect::<Vec<_>>();
+    if !loaders.is_empty()
+        && !loaders
+            .iter()
+            .any(|x| declared_loaders.contains(&x.as_str()))
+    {
+        let mut declared_loaders = declared_loaders;
+        declared_loaders.dedup();
+        warnings.push(format!(
+            "文件的描述文件适用于 {}，与所选的加载器 {} 不符",
+            declared_loaders.join(", "),
+            loaders.join(", ")
+        ));
+    }
+
+    let unsupported = game_versions
+        .iter()
+        .filter(|version| {
+            metadata
+                .iter()
+                .all(|x| x.supports_game_version(version) == Some(false))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unsupported.is_empty() {
+        let ranges = metadata
+            .iter()
+            .flat_map(|x| x.game_versions.iter().cloned())
+            .collect::<Vec<_>>();
+        warnings.push(format!(
+            "所选的游戏版本 {} 不在文件声明支持的范围（{}）内",
+            unsupported.join(", "),
+            ranges.join(", ")
+        ));
+    }
+
+    if let Some(environment) = environment {
+        for declared in metadata.iter().filter_map(|x| x.environment) {
+            let contradicts = match declared {
+                ModEnvironment::Client => matches!(
+                    environment,
+                    "server_only"
+                        | "dedicated_server_only"
+                        | "server_only_client_optional"
+                ),
+                ModEnvironment::Server => matches!(
+                    environment,
+                    "client_only" | "client_only_server_optional"
+                ),
+            };
+            if contradicts {
+                warnings.push(format!(
+                    "文件声明仅在{}运行，与所填写的运行环境 {} 不符",
+                    match declared {
+                        ModEnvironment::Client => "客户端",
+                        ModEnvironment::Server => "服务端",
+                    },
+                    environment
+                ));
+                break;
+            }
+        }
+    }
+
+    let version_number = version_number.trim();
+    if !version_number.is_empty()
+        && let Some(declared) = metadata.iter().find_map(|x| x.version.as_ref())
+        && !version_number.contains(declared.as_str())
+        && !declared.contains(version_number)
+    {
+        warnings.push(format!(
+            "文件声明的版本号 {declared} 与填写的版本号 {version_number} 不一致"
+        ));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fabric_mod_json() {
+        let metadata = parse_fabric(
+            r#"{
+                "schemaVersion": 1,
+                "id": "examplemod",
+                "version": "1.2.0",
+                "name": "Example Mod",
+                "environment": "client",
+                "depends": {
+                    "fabricloader": ">=0.15",
+                    "minecraft": ["~1.20.1", "1.20.2"],
+                    "fabric-api": "*"
+                },
+                "suggests": { "modmenu": ">=7" },
+                "breaks": { "optifabric": "*" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(metadata.mod_id, "examplemod");
+        assert_eq!(metadata.version.as_deref(), Some("1.2.0"));
+        assert_eq!(metadata.game_versions, vec!["~1.20.1", "1.20.2"]);
+        assert_eq!(metadata.environment, Some(ModEnvironment::Client));
+        assert_eq!(
+            metadata
+                .dependencies
+                .iter()
+                .map(|x| (x.mod_id.as_str(), x.dependency_type))
+                .collect::<Vec<_>>(),
+            vec![
+                ("fabric-api", DependencyType::Required),
+                ("modmenu", DependencyType::Optional),
+                ("optifabric", DependencyType::Incompatible),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_mods_toml() {
+        let metadata = parse_mods_toml(
+            r#"
+modLoader="javafml"
+loaderVersion="[1,)"
+
+[[mods]]
+modId="examplemod"
+version="${file.jarVersion}"
+
+[[dependencies.examplemod]]
+type="required"
+modId="neoforge"
+versionRange="[20.4,)"
+
+[[dependencies.examplemod]]
+type="optional"
+modId="jei"
+versionRange="[17,)"
+
+[[dependencies.examplemod]]
+type="required"
+modId="minecraft"
+versionRange="[1.20.4,1.21)"
+"#,
+            false,
+            Some("Manifest-Version: 1.0\nImplementation-Version: 3.1.0\n"),
+        )
+        .unwrap();
+
+        assert_eq!(metadata.loaders, vec!["neoforge"]);
+        assert_eq!(metadata.version.as_deref(), Some("3.1.0"));
+        assert_eq!(metadata.game_versions, vec!["[1.20.4,1.21)"]);
+        assert_eq!(metadata.dependencies.len(), 1);
+        assert_eq!(
+            metadata.dependencies[0].dependency_type,
+            DependencyType::Optional
+        );
+    }
+
+    #[test]
+    fn matches_version_ranges() {
+        assert_eq!(matches_game_version(">=1.20 <1.21", "1.20.4"), Some(true));
+        assert_eq!(matches_game_version(">=1.20 <1.21", "1.21"), Some(false));
+        assert_eq!(matches_game_version("1.20.x", "1.20.6"), Some(true));
+        assert_eq!(matches_game_version("~1.20.1", "1.20.4"), Some(true));
+        assert_eq!(matches_game_version("~1.20.1", "1.21"), Some(false));
+        assert_eq!(matches_game_version("1.20.1", "1.20.1"), Some(true));
+        assert_eq!(matches_game_version(">=1.20-", "1.20"), Some(true));
+        assert_eq!(matches_game_version("[1.20.1,1.21)", "1.20.6"), Some(true));
+        assert_eq!(matches_game_version("[1.20.1,1.21)", "1.21"), Some(false));
+        assert_eq!(
+            matches_game_version("(,1.12.2],[1.16.5]", "1.16.5"),
+            Some(true)
+        );
+        assert_eq!(matches_game_version(">=1.20", "24w14a"), None);
+    }
+
+    #[test]
+    fn warns_on_contradictions() {
+        let metadata = parse_fabric(
+            r#"{
+                "id": "examplemod",
+                "version": "2.0.0",
+                "environment": "client",
+                "depends": { "minecraft": ">=1.20" }
+            }"#,
+        )
+        .unwrap();
+
+        let warnings = check_version_metadata(
+            std::slice::from_ref(&metadata),
+            "2.0.0+1.20",
+            &["fabric".to_string()],
+            &["1.20.1".to_string()],
+            Some("client_only"),
+        );
+        assert!(warnings.is_empty());
+
+        let warnings = check_version_metadata(
+            &[metadata],
+            "1.0.0",
+            &["forge".to_string()],
+            &["1.19.2".to_string(), "1.20.1".to_string()],
+            Some("server_only"),
+        );
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[1].contains("1.19.2"));
+        assert!(!warnings[1].contains("1.20.1"));
+    }
+}
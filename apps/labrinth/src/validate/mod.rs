@@ -2,12 +2,15 @@ use crate::database::models::DatabaseError;
 use crate::database::models::legacy_loader_fields::MinecraftGameVersion;
 use crate::database::models::loader_fields::VersionField;
 use crate::database::redis::RedisPool;
-use crate::models::pack::PackFormat;
+use crate::models::malware::ScanFinding;
+use crate::models::pack::{CurseForgeManifest, PackFormat};
 use crate::models::projects::{FileType, Loader};
+use crate::models::structure::StructureMetadata;
 use crate::validate::datapack::DataPackValidator;
 use crate::validate::fabric::FabricValidator;
 use crate::validate::forge::{ForgeValidator, LegacyForgeValidator};
 use crate::validate::liteloader::LiteLoaderValidator;
+use crate::validate::metadata::ModMetadata;
 use crate::validate::modpack::ModpackValidator;
 use crate::validate::neoforge::NeoForgeValidator;
 use crate::validate::plugin::*;
@@ -22,17 +25,23 @@ use std::io::Cursor;
 use thiserror::Error;
 use zip::ZipArchive;
 
+pub mod curseforge;
 mod datapack;
+mod descriptor;
 mod fabric;
 mod forge;
 mod liteloader;
+pub mod malware;
+pub mod metadata;
 mod modpack;
+mod nbt;
 mod neoforge;
 pub mod plugin;
 mod quilt;
 mod resourcepack;
 mod rift;
 mod shader;
+pub mod structure;
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
@@ -48,6 +57,8 @@ pub enum ValidationError {
     Blocking(#[from] actix_web::error::BlockingError),
     #[error("查询数据库时出错")]
     Database(#[from] DatabaseError),
+    #[error("请求 CurseForge 时出错: {0}")]
+    CurseForge(#[from] reqwest::Error),
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -57,6 +68,11 @@ pub enum ValidationResult {
         format: PackFormat,
         files: Vec<String>,
     },
+    /// CurseForge 整合包，应标记为主要文件；`format` 只包含覆盖目录中打包的文件
+    PassWithCurseForgeData {
+        manifest: CurseForgeManifest,
+        format: PackFormat,
+    },
     /// 文件应标记为主要文件
     Pass,
     /// 文件不应标记为主要文件，原因在字符串中
@@ -67,6 +83,7 @@ impl ValidationResult {
     pub fn is_passed(&self) -> bool {
         match self {
             ValidationResult::PassWithPackDataAndFiles { .. } => true,
+            ValidationResult::PassWithCurseForgeData { .. } => true,
             ValidationResult::Pass => true,
             ValidationResult::Warning(_) => false,
         }
@@ -89,6 +106,24 @@ pub trait Validator: Sync {
         &self,
         archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
     ) -> Result<ValidationResult, ValidationError>;
+
+    /// 解析文件中的描述文件，没有可识别的描述文件时返回 None
+    fn metadata(
+        &self,
+        _archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        None
+    }
+}
+
+pub struct FileValidation {
+    pub result: ValidationResult,
+    /// 从文件的描述文件中读取到的元数据，可能来自多个加载器
+    pub metadata: Vec<ModMetadata>,
+    /// 结构文件与存档中读取到的信息
+    pub structure: Option<StructureMetadata>,
+    /// 可疑代码扫描的结果，只扫描 jar 文件，其他文件为 None
+    pub findings: Option<Vec<ScanFinding>>,
 }
 
 static ALWAYS_ALLOWED_EXT: &[&str] = &[
@@ -104,7 +139,7 @@ static ALWAYS_ALLOWED_EXT: &[&str] = &[
     "mctemplate",
 ];
 
-/// 这些后缀的文件不是 zip 容器，跳过 ZipArchive 解析直接放过
+/// 这些后缀的文件不是 zip 容器，跳过 ZipArchive 解析
 static NON_ZIP_ALLOWED_EXT: &[&str] = &[
     "txt",
     "schem",
@@ -163,9 +198,10 @@ pub async fn validate_file_for_pack(
         file_type,
     )
     .await
+    .map(|x| x.result)
 }
 
-/// 返回值是此文件是否应标记为主要文件，基于文件的分析
+/// 返回值包含此文件是否应标记为主要文件，以及从描述文件中读取到的元数据
 #[allow(clippy::too_many_arguments)]
 pub async fn validate_file(
     data: bytes::Bytes,
@@ -175,7 +211,7 @@ pub async fn validate_file(
     version_fields: Vec<VersionField>,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     redis: &RedisPool,
-) -> Result<ValidationResult, ValidationError> {
+) -> Result<FileValidation, ValidationError> {
     let game_versions = version_fields
         .into_iter()
         .find_map(|v| MinecraftGameVersion::try_from_version_field(&v).ok())
@@ -212,79 +248,127 @@ async fn validate_minecraft_file(
     game_versions: Vec<MinecraftGameVersion>,
     all_game_versions: Vec<MinecraftGameVersion>,
     file_type: Option<FileType>,
-) -> Result<ValidationResult, ValidationError> {
+) -> Result<FileValidation, ValidationError> {
     actix_web::web::block(move || {
-        // 单文件二进制格式（litematic / nbt / schem 等）不是 zip 容器，跳过解析直接放过
+        // 单文件二进制格式（litematic / nbt / schem 等）不是 zip 容器，只解析结构信息
         if NON_ZIP_ALLOWED_EXT.contains(&&*file_extension) {
-            return Ok(ValidationResult::Pass);
+            return Ok(FileValidation {
+                result: ValidationResult::Pass,
+                metadata: Vec::new(),
+                structure: structure::read_structure(&data, &file_extension)?,
+                findings: None,
+            });
         }
 
         let reader = Cursor::new(data);
         let mut zip = ZipArchive::new(reader)?;
 
-        if let Some(file_type) = file_type {
-            match file_type {
-                FileType::RequiredResourcePack
-                | FileType::OptionalResourcePack => {
-                    return PackValidator.validate(&mut zip);
-                }
-                FileType::Unknown => {}
+        let structure =
+            if structure::WORLD_EXTENSIONS.contains(&&*file_extension) {
+                Some(structure::read_world(&mut zip)?)
+            } else {
+                None
+            };
+
+        // 描述文件与所选加载器无关地读取，这样才能发现加载器选错的情况
+        let metadata = VALIDATORS
+            .iter()
+            .filter(|x| x.get_file_extensions().contains(&&*file_extension))
+            .filter_map(|x| x.metadata(&mut zip))
+            .collect::<Vec<_>>();
+
+        let result = validate_archive(
+            &mut zip,
+            &file_extension,
+            &loaders,
+            &game_versions,
+            &all_game_versions,
+            file_type,
+        )?;
+
+        let findings =
+            if malware::SCANNED_EXTENSIONS.contains(&&*file_extension) {
+                Some(malware::scan_jar(&mut zip)?)
+            } else {
+                None
+            };
+
+        Ok(FileValidation {
+            result,
+            metadata,
+            structure,
+            findings,
+        })
+    })
+    .await?
+}
+
+fn validate_archive(
+    zip: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    file_extension: &str,
+    loaders: &[Loader],
+    game_versions: &[MinecraftGameVersion],
+    all_game_versions: &[MinecraftGameVersion],
+    file_type: Option<FileType>,
+) -> Result<ValidationResult, ValidationError> {
+    if let Some(file_type) = file_type {
+        match file_type {
+            FileType::RequiredResourcePack | FileType::OptionalResourcePack => {
+                return PackValidator.validate(zip);
             }
+            FileType::Unknown => {}
         }
+    }
 
-        let mut visited = false;
-        let mut saved_result = None;
-        for validator in VALIDATORS {
-            if loaders
-                .iter()
-                .any(|x| validator.get_supported_loaders().contains(&&*x.0))
-                && game_version_supported(
-                    &game_versions,
-                    &all_game_versions,
-                    validator.get_supported_game_versions(),
-                )
-            {
-                if validator.get_file_extensions().contains(&&*file_extension) {
-                    let result = validator.validate(&mut zip)?;
-                    match result {
-                        ValidationResult::PassWithPackDataAndFiles {
-                            ..
-                        } => {
+    let mut visited = false;
+    let mut saved_result = None;
+    for validator in VALIDATORS {
+        if loaders
+            .iter()
+            .any(|x| validator.get_supported_loaders().contains(&&*x.0))
+            && game_version_supported(
+                game_versions,
+                all_game_versions,
+                validator.get_supported_game_versions(),
+            )
+        {
+            if validator.get_file_extensions().contains(&file_extension) {
+                let result = validator.validate(zip)?;
+                match result {
+                    ValidationResult::PassWithPackDataAndFiles { .. }
+                    | ValidationResult::PassWithCurseForgeData { .. } => {
+                        saved_result = Some(result);
+                    }
+                    ValidationResult::Pass => {
+                        if saved_result.is_none() {
                             saved_result = Some(result);
                         }
-                        ValidationResult::Pass => {
-                            if saved_result.is_none() {
-                                saved_result = Some(result);
-                            }
-                        }
-                        ValidationResult::Warning(_) => {
-                            return Ok(result);
-                        }
                     }
-                } else {
-                    visited = true;
+                    ValidationResult::Warning(_) => {
+                        return Ok(result);
+                    }
                 }
+            } else {
+                visited = true;
             }
         }
+    }
 
-        if let Some(result) = saved_result {
-            return Ok(result);
-        }
+    if let Some(result) = saved_result {
+        return Ok(result);
+    }
 
-        if visited {
-            if ALWAYS_ALLOWED_EXT.contains(&&*file_extension) {
-                Ok(ValidationResult::Warning("文件扩展名对输入文件无效"))
-            } else {
-                Err(ValidationError::InvalidInput(
-                    format!("文件扩展名 {file_extension} 对输入文件无效")
-                        .into(),
-                ))
-            }
+    if visited {
+        if ALWAYS_ALLOWED_EXT.contains(&file_extension) {
+            Ok(ValidationResult::Warning("文件扩展名对输入文件无效"))
         } else {
-            Ok(ValidationResult::Pass)
+            Err(ValidationError::InvalidInput(
+                format!("文件扩展名 {file_extension} 对输入文件无效").into(),
+            ))
         }
-    })
-    .await?
+    } else {
+        Ok(ValidationResult::Pass)
+    }
 }
 
 // 为此编写测试
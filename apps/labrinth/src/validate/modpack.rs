@@ -1,7 +1,7 @@
 use crate::models::pack::{PackFileHash, PackFormat};
 use crate::util::validate::validation_errors_to_string;
 use crate::validate::{
-    SupportedGameVersions, ValidationError, ValidationResult,
+    SupportedGameVersions, ValidationError, ValidationResult, curseforge,
 };
 use std::io::{Cursor, Read};
 // 注意：路径验证已迁移到 SafeRelativePath 类型中，在反序列化时自动执行
@@ -35,38 +35,22 @@ impl super::Validator for ModpackValidator {
         {
             return Ok(ValidationResult::Pass);
         }
-        if archive.by_name("manifest.json").is_ok() {
-            return Ok(ValidationResult::Pass);
-            // return Ok(ValidationResult::PassWithPackDataAndFiles {
-            //     format: {
-            //         let mut file = if let Ok(file) =
-            //             archive.by_name("manifest.json")
-            //         {
-            //             file
-            //         } else {
-            //             return Ok(ValidationResult::Warning("文件中缺少 manifest.json 文件！"));
-            //         };
-            //
-            //         let mut contents = String::new();
-            //         file.read_to_string(&mut contents)?;
-            //
-            //         serde_json::from_str(&contents)?
-            //     },
-            //     files: archive
-            //         .file_names()
-            //         .filter(|x| {
-            //             (x.ends_with("jar") || x.ends_with("zip"))
-            //                 && (x.starts_with("overrides/mods")
-            //                 || x.starts_with("client-overrides/mods")
-            //                 || x.starts_with("server-overrides/mods")
-            //                 || x.starts_with("overrides/resourcepacks")
-            //                 || x.starts_with("server-overrides/resourcepacks")
-            //                 || x.starts_with("overrides/shaderpacks")
-            //                 || x.starts_with("client-overrides/shaderpacks"))
-            //         })
-            //         .flat_map(|x| x.rsplit('/').next().map(|x| x.to_string()))
-            //         .collect::<Vec<String>>(),
-            // })
+        if let Some(manifest) = curseforge::read_manifest(archive)? {
+            let format = curseforge::bundled_pack_format(archive, &manifest)?;
+            let overrides = manifest.overrides_prefix();
+
+            if manifest.files.is_empty()
+                && !archive.file_names().any(|x| x.starts_with(&overrides))
+            {
+                return Err(ValidationError::InvalidInput(
+                    "包中没有文件！".into(),
+                ));
+            }
+
+            return Ok(ValidationResult::PassWithCurseForgeData {
+                manifest,
+                format,
+            });
         }
 
         let pack: PackFormat = {
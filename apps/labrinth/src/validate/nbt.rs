@@ -0,0 +1,295 @@
+//! NBT（Named Binary Tag）的读取
+//!
+//! Java 版的结构文件是 gzip 压缩的大端序 NBT，基岩版的结构与存档使用未压缩的小端序 NBT。
+//! 读取时校验所有长度，损坏或伪装成结构文件的其他文件会返回错误。
+
+use crate::validate::ValidationError;
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// 解压后的最大大小，防止压缩炸弹
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * (1 << 20);
+
+/// 最大嵌套深度，防止恶意文件导致栈溢出
+const MAX_DEPTH: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+pub type Compound = HashMap<String, Tag>;
+
+impl Tag {
+    /// 任意整数类型的值
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(x) => Some(*x as i64),
+            Tag::Short(x) => Some(*x as i64),
+            Tag::Int(x) => Some(*x as i64),
+            Tag::Long(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: &'static str) -> ValidationError {
+    ValidationError::InvalidInput(message.into())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    endian: Endian,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ValidationError> {
+        if count > self.data.len() {
+            return Err(invalid("NBT 数据不完整，文件可能已损坏"));
+        }
+        let (value, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ValidationError> {
+        let mut value = [0; N];
+        value.copy_from_slice(self.take(N)?);
+        if self.endian == Endian::Little {
+            value.reverse();
+        }
+        Ok(value)
+    }
+
+    fn u8(&mut self) -> Result<u8, ValidationError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, ValidationError> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, ValidationError> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, ValidationError> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    /// 读取数组或列表的长度，并确认剩余数据足够容纳这么多元素
+    fn length(
+        &mut self,
+        element_size: usize,
+    ) -> Result<usize, ValidationError> {
+        let length = self.i32()?;
+        let length = usize::try_from(length)
+            .map_err(|_| invalid("NBT 中的长度为负数，文件可能已损坏"))?;
+        if length.saturating_mul(element_size) > self.data.len() {
+            return Err(invalid("NBT 数据不完整，文件可能已损坏"));
+        }
+        Ok(length)
+    }
+
+    fn string(&mut self) -> Result<String, ValidationError> {
+        let length = u16::from_be_bytes(self.array()?) as usize;
+        // Java 使用修改过的 UTF-8，方块名等 ASCII 内容不受影响
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn payload(
+        &mut self,
+        tag_type: u8,
+        depth: usize,
+    ) -> Result<Tag, ValidationError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("NBT 嵌套层数过多"));
+        }
+
+        Ok(match tag_type {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let length = self.length(1)?;
+                Tag::ByteArray(self.take(length)?.to_vec())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element_type = self.u8()?;
+                let length = self.length(min_size(element_type))?;
+                if element_type == 0 {
+                    // 空列表的元素类型为 TAG_End
+                    Tag::List(Vec::new())
+                } else {
+                    let mut list = Vec::with_capacity(length);
+                    for _ in 0..length {
+                        list.push(self.payload(element_type, depth + 1)?);
+                    }
+                    Tag::List(list)
+                }
+            }
+            10 => Tag::Compound(self.compound(depth + 1)?),
+            11 => {
+                let length = self.length(4)?;
+                Tag::IntArray(
+                    (0..length)
+                        .map(|_| self.i32())
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            12 => {
+                let length = self.length(8)?;
+                Tag::LongArray(
+                    (0..length)
+                        .map(|_| self.i64())
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            _ => return Err(invalid("NBT 中存在未知的标签类型")),
+        })
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<Compound, ValidationError> {
+        let mut compound = HashMap::new();
+        loop {
+            let tag_type = self.u8()?;
+            if tag_type == 0 {
+                break;
+            }
+            let name = self.string()?;
+            let value = self.payload(tag_type, depth)?;
+            compound.insert(name, value);
+        }
+        Ok(compound)
+    }
+}
+
+/// 每种标签至少占用的字节数，用于在分配内存前检查列表长度
+fn min_size(tag_type: u8) -> usize {
+    match tag_type {
+        1 | 10 => 1,
+        2 | 8 => 2,
+        3 | 5 | 7 | 11 | 12 => 4,
+        4 | 6 => 8,
+        9 => 5,
+        _ => 0,
+    }
+}
+
+/// 读取以复合标签为根的 NBT，返回根标签的名称与内容
+pub fn read(
+    data: &[u8],
+    endian: Endian,
+) -> Result<(String, Compound), ValidationError> {
+    let mut reader = Reader { data, endian };
+    if reader.u8()? != 10 {
+        return Err(invalid("文件不是有效的 NBT 格式"));
+    }
+    let name = reader.string()?;
+    let root = reader.compound(0)?;
+    Ok((name, root))
+}
+
+/// 读取 Java 版的 NBT 文件；gzip 压缩与未压缩的文件都可以读取
+pub fn read_java(data: &[u8]) -> Result<(String, Compound), ValidationError> {
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return read(data, Endian::Big);
+    }
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data)
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| invalid("无法解压文件，文件可能已损坏"))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(invalid("文件解压后过大"));
+    }
+
+    read(&decompressed, Endian::Big)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_both_endians() {
+        // 根复合标签 "root" { Int "x" = 7, List<String> "l" = ["a"] }
+        let big = [
+            10, 0, 4, b'r', b'o', b'o', b't', //
+            3, 0, 1, b'x', 0, 0, 0, 7, //
+            9, 0, 1, b'l', 8, 0, 0, 0, 1, 0, 1, b'a', //
+            0,
+        ];
+        let little = [
+            10, 4, 0, b'r', b'o', b'o', b't', //
+            3, 1, 0, b'x', 7, 0, 0, 0, //
+            9, 1, 0, b'l', 8, 1, 0, 0, 0, 1, 0, b'a', //
+            0,
+        ];
+
+        for (data, endian) in [(&big, Endian::Big), (&little, Endian::Little)] {
+            let (name, root) = read(data, endian).unwrap();
+            assert_eq!(name, "root");
+            assert_eq!(root.get("x").and_then(Tag::as_i64), Some(7));
+            assert_eq!(
+                root.get("l").and_then(Tag::as_list),
+                Some(&[Tag::String("a".to_string())][..])
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_and_oversized_lengths() {
+        assert!(read(&[10, 0, 0, 3, 0, 1, b'x', 0, 0], Endian::Big).is_err());
+        // 声明了 2^31-1 个元素的字节数组
+        assert!(
+            read(&[10, 0, 0, 7, 0, 0, 0x7f, 0xff, 0xff, 0xff], Endian::Big)
+                .is_err()
+        );
+        assert!(read(b"PK\x03\x04", Endian::Big).is_err());
+    }
+}
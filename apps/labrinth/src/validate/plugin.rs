@@ -1,5 +1,6 @@
 use crate::validate::{
-    SupportedGameVersions, ValidationError, ValidationResult,
+    ModMetadata, SupportedGameVersions, ValidationError, ValidationResult,
+    metadata,
 };
 use std::io::Cursor;
 use zip::ZipArchive;
@@ -41,6 +42,13 @@ impl super::Validator for PluginYmlValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_plugin_yml(archive)
+    }
 }
 
 pub struct BungeeCordValidator;
@@ -80,6 +88,13 @@ impl super::Validator for BungeeCordValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_bungee_yml(archive)
+    }
 }
 
 pub struct VelocityValidator;
@@ -116,6 +131,13 @@ impl super::Validator for VelocityValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_velocity(archive)
+    }
 }
 
 pub struct SpongeValidator;
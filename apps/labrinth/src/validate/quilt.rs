@@ -1,5 +1,6 @@
 use crate::validate::{
-    SupportedGameVersions, ValidationError, ValidationResult, filter_out_packs,
+    ModMetadata, SupportedGameVersions, ValidationError, ValidationResult,
+    filter_out_packs, metadata,
 };
 use chrono::DateTime;
 use std::io::Cursor;
@@ -48,4 +49,11 @@ impl super::Validator for QuiltValidator {
 
         Ok(ValidationResult::Pass)
     }
+
+    fn metadata(
+        &self,
+        archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    ) -> Option<ModMetadata> {
+        metadata::read_quilt(archive)
+    }
 }
@@ -0,0 +1,782 @@
+//! 地图板块的结构文件与存档的解析
+//!
+//! 支持 Sponge 与 MCEdit 的 schematic、Litematica 投影、原版结构方块导出的 `.nbt`、
+//! 基岩版的 `.mcstructure` 以及基岩版存档。解析失败说明文件已损坏或只是改了后缀，
+//! 上传会被拒绝。
+
+use crate::models::structure::{
+    MaterialCount, StructureFormat, StructureMetadata, StructureSize,
+};
+use crate::validate::ValidationError;
+use crate::validate::nbt::{self, Compound, Endian, Tag};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// 以 zip 打包的基岩版存档
+pub const WORLD_EXTENSIONS: &[&str] = &["mcworld", "mctemplate"];
+
+/// 结果中保留的方块种类数
+const TOP_MATERIALS: usize = 10;
+
+/// 调色板索引的上限，防止按索引分配过大的计数数组
+const MAX_PALETTE_INDEX: usize = 1 << 20;
+
+/// 不计入方块数量的方块
+const AIR_BLOCKS: &[&str] = &[
+    "minecraft:air",
+    "minecraft:cave_air",
+    "minecraft:void_air",
+    "minecraft:structure_void",
+];
+
+/// 各正式版的数据版本（DataVersion），按升序排列
+const DATA_VERSIONS: &[(i32, &str)] = &[
+    (169, "1.9"),
+    (175, "1.9.1"),
+    (176, "1.9.2"),
+    (183, "1.9.3"),
+    (184, "1.9.4"),
+    (510, "1.10"),
+    (511, "1.10.1"),
+    (512, "1.10.2"),
+    (819, "1.11"),
+    (921, "1.11.1"),
+    (922, "1.11.2"),
+    (1139, "1.12"),
+    (1241, "1.12.1"),
+    (1343, "1.12.2"),
+    (1519, "1.13"),
+    (1628, "1.13.1"),
+    (1631, "1.13.2"),
+    (1952, "1.14"),
+    (1957, "1.14.1"),
+    (1963, "1.14.2"),
+    (1968, "1.14.3"),
+    (1976, "1.14.4"),
+    (2225, "1.15"),
+    (2227, "1.15.1"),
+    (2230, "1.15.2"),
+    (2566, "1.16"),
+    (2567, "1.16.1"),
+    (2578, "1.16.2"),
+    (2580, "1.16.3"),
+    (2584, "1.16.4"),
+    (2586, "1.16.5"),
+    (2724, "1.17"),
+    (2730, "1.17.1"),
+    (2860, "1.18"),
+    (2865, "1.18.1"),
+    (2975, "1.18.2"),
+    (3105, "1.19"),
+    (3117, "1.19.1"),
+    (3120, "1.19.2"),
+    (3218, "1.19.3"),
+    (3337, "1.19.4"),
+    (3463, "1.20"),
+    (3465, "1.20.1"),
+    (3578, "1.20.2"),
+    (3698, "1.20.3"),
+    (3700, "1.20.4"),
+    (3837, "1.20.5"),
+    (3839, "1.20.6"),
+    (3953, "1.21"),
+    (3955, "1.21.1"),
+    (4080, "1.21.2"),
+    (4082, "1.21.3"),
+    (4189, "1.21.4"),
+    (4325, "1.21.5"),
+    (4435, "1.21.6"),
+    (4438, "1.21.7"),
+    (4440, "1.21.8"),
+];
+
+/// 数据版本对应的游戏版本；快照等非正式版的数据版本取不高于它的最近正式版
+pub fn game_version(data_version: i32) -> Option<&'static str> {
+    DATA_VERSIONS
+        .iter()
+        .rev()
+        .find(|(x, _)| *x <= data_version)
+        .map(|(_, version)| *version)
+}
+
+fn invalid(message: &'static str) -> ValidationError {
+    ValidationError::InvalidInput(message.into())
+}
+
+fn int(compound: &Compound, key: &str) -> Option<i64> {
+    compound.get(key).and_then(Tag::as_i64)
+}
+
+fn list<'a>(compound: &'a Compound, key: &str) -> &'a [Tag] {
+    compound.get(key).and_then(Tag::as_list).unwrap_or_default()
+}
+
+fn child<'a>(compound: &'a Compound, key: &str) -> Option<&'a Compound> {
+    compound.get(key).and_then(Tag::as_compound)
+}
+
+/// 尺寸字段；schematic 的尺寸是无符号的 short
+fn dimension(compound: &Compound, key: &str) -> Result<u32, ValidationError> {
+    match compound.get(key) {
+        Some(Tag::Short(x)) => Ok(*x as u16 as u32),
+        Some(tag) => tag
+            .as_i64()
+            .and_then(|x| u32::try_from(x.unsigned_abs()).ok())
+            .ok_or_else(|| invalid("结构文件的尺寸无效")),
+        None => Err(invalid("结构文件缺少尺寸信息")),
+    }
+}
+
+/// `[x, y, z]` 形式的尺寸
+fn size_list(tags: &[Tag]) -> Result<StructureSize, ValidationError> {
+    let [width, height, length] = tags else {
+        return Err(invalid("结构文件缺少尺寸信息"));
+    };
+    let value = |tag: &Tag| {
+        tag.as_i64()
+            .and_then(|x| u32::try_from(x.unsigned_abs()).ok())
+            .ok_or_else(|| invalid("结构文件的尺寸无效"))
+    };
+    Ok(StructureSize {
+        width: value(width)?,
+        height: value(height)?,
+        length: value(length)?,
+    })
+}
+
+fn volume(size: &StructureSize) -> u64 {
+    (size.width as u64)
+        .saturating_mul(size.height as u64)
+        .saturating_mul(size.length as u64)
+}
+
+fn empty_metadata(format: StructureFormat) -> StructureMetadata {
+    StructureMetadata {
+        format,
+        size: None,
+        block_count: 0,
+        block_entity_count: 0,
+        entity_count: 0,
+        block_types: 0,
+        top_materials: Vec::new(),
+        data_version: None,
+        game_version: None,
+        name: None,
+        regions: Vec::new(),
+    }
+}
+
+fn set_data_version(
+    metadata: &mut StructureMetadata,
+    data_version: Option<i64>,
+) {
+    metadata.data_version = data_version.and_then(|x| i32::try_from(x).ok());
+    metadata.game_version = metadata
+        .data_version
+        .and_then(game_version)
+        .map(String::from);
+}
+
+/// 按方块名（不含方块状态）统计数量
+#[derive(Default)]
+struct Materials(HashMap<String, u64>);
+
+impl Materials {
+    fn add(&mut self, state: &str, count: u64) {
+        let name = state.split('[').next().unwrap_or(state);
+        if count == 0 || AIR_BLOCKS.contains(&name) {
+            return;
+        }
+        *self.0.entry(name.to_string()).or_default() += count;
+    }
+
+    /// 按调色板索引统计的数量合并到方块名上
+    fn add_indexed(
+        &mut self,
+        counts: &[u64],
+        palette: &[Option<String>],
+    ) -> Result<(), ValidationError> {
+        for (index, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let Some(Some(name)) = palette.get(index) else {
+                return Err(invalid("方块索引超出调色板范围，文件可能已损坏"));
+            };
+            self.add(name, *count);
+        }
+        Ok(())
+    }
+
+    fn finish(self, metadata: &mut StructureMetadata) {
+        let mut materials = self
+            .0
+            .into_iter()
+            .map(|(name, count)| MaterialCount { name, count })
+            .collect::<Vec<_>>();
+        materials
+            .sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+        metadata.block_count = materials.iter().map(|x| x.count).sum();
+        metadata.block_types = materials.len() as u32;
+        materials.truncate(TOP_MATERIALS);
+        metadata.top_materials = materials;
+    }
+}
+
+/// 解析单文件的结构格式；不是结构文件的后缀返回 None
+pub fn read_structure(
+    data: &[u8],
+    file_extension: &str,
+) -> Result<Option<StructureMetadata>, ValidationError> {
+    let metadata = match file_extension {
+        "schem" | "schematic" => read_schematic(&nbt::read_java(data)?.1)?,
+        "litematic" => read_litematic(&nbt::read_java(data)?.1)?,
+        "nbt" => read_vanilla_structure(&nbt::read_java(data)?.1)?,
+        "mcstructure" => {
+            read_bedrock_structure(&nbt::read(data, Endian::Little)?.1)?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(metadata))
+}
+
+/// `.schem` 与 `.schematic` 两种后缀的文件都可能是 Sponge 或 MCEdit 格式，按内容区分
+fn read_schematic(
+    root: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    // Sponge 第三版把内容放在根标签下的 Schematic 中
+    let schematic = child(root, "Schematic").unwrap_or(root);
+    match schematic.get("Blocks") {
+        Some(Tag::ByteArray(_)) => read_mcedit(schematic),
+        _ if schematic.contains_key("Palette")
+            || child(schematic, "Blocks").is_some() =>
+        {
+            read_sponge(schematic)
+        }
+        _ => Err(invalid("文件不是有效的 schematic 格式")),
+    }
+}
+
+fn read_sponge(
+    schematic: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    let mut metadata = empty_metadata(StructureFormat::SpongeSchematic);
+    let size = StructureSize {
+        width: dimension(schematic, "Width")?,
+        height: dimension(schematic, "Height")?,
+        length: dimension(schematic, "Length")?,
+    };
+
+    // 第三版的方块在 Blocks 中，旧版直接位于根标签
+    let (blocks, data_key, block_entities) = match child(schematic, "Blocks") {
+        Some(blocks) => (blocks, "Data", list(blocks, "BlockEntities")),
+        None => (
+            schematic,
+            "BlockData",
+            match schematic.get("BlockEntities") {
+                Some(_) => list(schematic, "BlockEntities"),
+                None => list(schematic, "TileEntities"),
+            },
+        ),
+    };
+
+    let mut palette = Vec::new();
+    for (name, index) in child(blocks, "Palette")
+        .ok_or_else(|| invalid("schematic 文件缺少方块调色板"))?
+    {
+        let index = index
+            .as_i64()
+            .and_then(|x| usize::try_from(x).ok())
+            .filter(|x| *x < MAX_PALETTE_INDEX)
+            .ok_or_else(|| invalid("schematic 文件的调色板无效"))?;
+        if palette.len() <= index {
+            palette.resize(index + 1, None);
+        }
+        palette[index] = Some(name.clone());
+    }
+
+    let Some(Tag::ByteArray(data)) = blocks.get(data_key) else {
+        return Err(invalid("schematic 文件缺少方块数据"));
+    };
+    // 方块数据是一串 varint 编码的调色板索引
+    let mut counts = vec![0u64; palette.len()];
+    let mut total = 0u64;
+    let mut value = 0usize;
+    let mut shift = 0;
+    for byte in data {
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 != 0 {
+            shift += 7;
+            if shift > 28 {
+                return Err(invalid("schematic 文件的方块数据无效"));
+            }
+            continue;
+        }
+        *counts.get_mut(value).ok_or_else(|| {
+            invalid("方块索引超出调色板范围，文件可能已损坏")
+        })? += 1;
+        total += 1;
+        value = 0;
+        shift = 0;
+    }
+    if shift != 0 || total != volume(&size) {
+        return Err(invalid("schematic 文件的方块数据与尺寸不符"));
+    }
+
+    let mut materials = Materials::default();
+    materials.add_indexed(&counts, &palette)?;
+    materials.finish(&mut metadata);
+
+    metadata.size = Some(size);
+    metadata.block_entity_count = block_entities.len() as u32;
+    metadata.entity_count = list(schematic, "Entities").len() as u32;
+    metadata.name = child(schematic, "Metadata")
+        .and_then(|x| x.get("Name"))
+        .and_then(Tag::as_str)
+        .map(String::from);
+    set_data_version(&mut metadata, int(schematic, "DataVersion"));
+    Ok(metadata)
+}
+
+/// MCEdit 格式使用 1.13 之前的数字方块 ID；Schematica 导出的文件带有 ID 与方块名的映射
+fn read_mcedit(
+    schematic: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    let mut metadata = empty_metadata(StructureFormat::McEditSchematic);
+    let size = StructureSize {
+        width: dimension(schematic, "Width")?,
+        height: dimension(schematic, "Height")?,
+        length: dimension(schematic, "Length")?,
+    };
+
+    let Some(Tag::ByteArray(blocks)) = schematic.get("Blocks") else {
+        return Err(invalid("schematic 文件缺少方块数据"));
+    };
+    if blocks.len() as u64 != volume(&size) {
+        return Err(invalid("schematic 文件的方块数据与尺寸不符"));
+    }
+    // 超过 255 的 ID 的高 4 位保存在 AddBlocks 中，每个字节对应两个方块
+    let add_blocks = match schematic.get("AddBlocks") {
+        Some(Tag::ByteArray(x)) => x.as_slice(),
+        _ => &[],
+    };
+
+    let mut counts = vec![0u64; 1 << 12];
+    for (index, block) in blocks.iter().enumerate() {
+        let add = add_blocks
+            .get(index >> 1)
+            .map(|x| if index & 1 == 0 { x >> 4 } else { x & 0x0f })
+            .unwrap_or(0);
+        counts[((add as usize) << 8) | *block as usize] += 1;
+    }
+
+    let mut palette = (0..counts.len())
+        .map(|id| Some(format!("legacy:{id}")))
+        .collect::<Vec<_>>();
+    palette[0] = Some("minecraft:air".to_string());
+    if let Some(mapping) = child(schematic, "SchematicaMapping") {
+        for (name, id) in mapping {
+            if let Some(slot) = id
+                .as_i64()
+                .and_then(|x| usize::try_from(x).ok())
+                .and_then(|x| palette.get_mut(x))
+            {
+                *slot = Some(name.clone());
+            }
+        }
+    }
+
+    let mut materials = Materials::default();
+    materials.add_indexed(&counts, &palette)?;
+    materials.finish(&mut metadata);
+
+    metadata.size = Some(size);
+    metadata.block_entity_count = list(schematic, "TileEntities").len() as u32;
+    metadata.entity_count = list(schematic, "Entities").len() as u32;
+    Ok(metadata)
+}
+
+fn read_litematic(
+    root: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    let mut metadata = empty_metadata(StructureFormat::Litematic);
+    let regions = child(root, "Regions")
+        .ok_or_else(|| invalid("文件不是有效的 Litematica 投影"))?;
+
+    let mut materials = Materials::default();
+    let mut names = regions.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    for name in &names {
+        let Some(region) = child(regions, name) else {
+            continue;
+        };
+        let size = child(region, "Size")
+            .ok_or_else(|| invalid("投影选区缺少尺寸信息"))
+            .and_then(|x| {
+                Ok(StructureSize {
+                    width: dimension(x, "x")?,
+                    height: dimension(x, "y")?,
+                    length: dimension(x, "z")?,
+                })
+            })?;
+
+        let palette = list(region, "BlockStatePalette")
+            .iter()
+            .map(|x| {
+                x.as_compound()
+                    .and_then(|x| x.get("Name"))
+                    .and_then(Tag::as_str)
+                    .map(String::from)
+            })
+            .collect::<Vec<_>>();
+        if palette.is_empty() || palette.len() > MAX_PALETTE_INDEX {
+            return Err(invalid("投影选区的方块调色板无效"));
+        }
+        let Some(Tag::LongArray(states)) = region.get("BlockStates") else {
+            return Err(invalid("投影选区缺少方块数据"));
+        };
+
+        let counts = count_packed_states(states, palette.len(), volume(&size))?;
+        materials.add_indexed(&counts, &palette)?;
+
+        metadata.block_entity_count +=
+            list(region, "TileEntities").len() as u32;
+        metadata.entity_count += list(region, "Entities").len() as u32;
+    }
+    materials.finish(&mut metadata);
+
+    let info = child(root, "Metadata");
+    metadata.size =
+        info.and_then(|x| child(x, "EnclosingSize")).and_then(|x| {
+            Some(StructureSize {
+                width: dimension(x, "x").ok()?,
+                height: dimension(x, "y").ok()?,
+                length: dimension(x, "z").ok()?,
+            })
+        });
+    metadata.name = info
+        .and_then(|x| x.get("Name"))
+        .and_then(Tag::as_str)
+        .map(String::from);
+    metadata.regions = names;
+    set_data_version(&mut metadata, int(root, "MinecraftDataVersion"));
+    Ok(metadata)
+}
+
+/// Litematica 的方块按固定位数紧密排列在 long 数组中，一个值可能跨越两个 long
+fn count_packed_states(
+    states: &[i64],
+    palette_len: usize,
+    volume: u64,
+) -> Result<Vec<u64>, ValidationError> {
+    let bits = (usize::BITS - (palette_len - 1).leading_zeros()).max(2) as u64;
+    if (states.len() as u64).saturating_mul(64) < volume.saturating_mul(bits) {
+        return Err(invalid("投影选区的方块数据与尺寸不符"));
+    }
+
+    let mask = (1u64 << bits) - 1;
+    let mut counts = vec![0u64; 1 << bits];
+    for index in 0..volume {
+        let start = index * bits;
+        let (long, offset) = ((start / 64) as usize, start % 64);
+        let mut value = (states[long] as u64) >> offset;
+        if offset + bits > 64 {
+            value |= (states[long + 1] as u64) << (64 - offset);
+        }
+        counts[(value & mask) as usize] += 1;
+    }
+    Ok(counts)
+}
+
+fn read_vanilla_structure(
+    root: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    let mut metadata = empty_metadata(StructureFormat::Structure);
+    if !root.contains_key("size") || !root.contains_key("blocks") {
+        return Err(invalid("文件不是有效的结构文件"));
+    }
+    let size = size_list(list(root, "size"))?;
+
+    // 带有多套调色板的结构（如沉船）统计第一套
+    let palette = match root.get("palette") {
+        Some(Tag::List(x)) => x.as_slice(),
+        _ => list(root, "palettes")
+            .first()
+            .and_then(Tag::as_list)
+            .unwrap_or_default(),
+    };
+    let palette = palette
+        .iter()
+        .map(|x| {
+            x.as_compound()
+                .and_then(|x| x.get("Name"))
+                .and_then(Tag::as_str)
+                .map(String::from)
+        })
+        .collect::<Vec<_>>();
+
+    let mut counts = vec![0u64; palette.len()];
+    for block in list(root, "blocks") {
+        let block = block
+            .as_compound()
+            .ok_or_else(|| invalid("结构文件的方块数据无效"))?;
+        let state = int(block, "state")
+            .and_then(|x| usize::try_from(x).ok())
+            .and_then(|x| counts.get_mut(x))
+            .ok_or_else(|| invalid("方块索引超出调色板范围，文件可能已损坏"))?;
+        *state += 1;
+        if block.contains_key("nbt") {
+            metadata.block_entity_count += 1;
+        }
+    }
+
+    let mut materials = Materials::default();
+    materials.add_indexed(&counts, &palette)?;
+    materials.finish(&mut metadata);
+
+    metadata.size = Some(size);
+    metadata.entity_count = list(root, "entities").len() as u32;
+    set_data_version(&mut metadata, int(root, "DataVersion"));
+    Ok(metadata)
+}
+
+fn read_bedrock_structure(
+    root: &Compound,
+) -> Result<StructureMetadata, ValidationError> {
+    let mut metadata = empty_metadata(StructureFormat::BedrockStructure);
+    let structure = child(root, "structure")
+        .ok_or_else(|| invalid("文件不是有效的基岩版结构"))?;
+    let size = size_list(list(root, "size"))?;
+
+    let palette = child(structure, "palette").and_then(|x| child(x, "default"));
+    let block_palette = palette
+        .map(|x| list(x, "block_palette"))
+        .unwrap_or_default()
+        .iter()
+        .map(|x| {
+            x.as_compound()
+                .and_then(|x| x.get("name"))
+                .and_then(Tag::as_str)
+                .map(String::from)
+        })
+        .collect::<Vec<_>>();
+
+    // 第一层为方块，第二层为含水等附加方块；-1 表示此处没有方块
+    let Some(Tag::List(indices)) = list(structure, "block_indices").first()
+    else {
+        return Err(invalid("基岩版结构缺少方块数据"));
+    };
+    if indices.len() as u64 != volume(&size) {
+        return Err(invalid("基岩版结构的方块数据与尺寸不符"));
+    }
+    let mut counts = vec![0u64; block_palette.len()];
+    for index in indices {
+        let index = index
+            .as_i64()
+            .ok_or_else(|| invalid("基岩版结构的方块数据无效"))?;
+        if index < 0 {
+            continue;
+        }
+        *usize::try_from(index)
+            .ok()
+            .and_then(|x| counts.get_mut(x))
+            .ok_or_else(|| {
+                invalid("方块索引超出调色板范围，文件可能已损坏")
+            })? += 1;
+    }
+
+    let mut materials = Materials::default();
+    materials.add_indexed(&counts, &block_palette)?;
+    materials.finish(&mut metadata);
+
+    metadata.size = Some(size);
+    metadata.block_entity_count = palette
+        .and_then(|x| child(x, "block_position_data"))
+        .map(|x| {
+            x.values()
+                .filter(|x| {
+                    x.as_compound()
+                        .is_some_and(|x| x.contains_key("block_entity_data"))
+                })
+                .count()
+        })
+        .unwrap_or(0) as u32;
+    metadata.entity_count = list(structure, "entities").len() as u32;
+    Ok(metadata)
+}
+
+/// 读取基岩版存档的 `level.dat`；存档可能直接位于压缩包根目录，也可能在一层文件夹中
+pub fn read_world(
+    archive: &mut ZipArchive<Cursor<bytes::Bytes>>,
+) -> Result<StructureMetadata, ValidationError> {
+    let level_dat = archive
+        .file_names()
+        .filter(|x| {
+            *x == "level.dat"
+                || (x.ends_with("/level.dat") && x.matches('/').count() == 1)
+        })
+        .min_by_key(|x| x.len())
+        .map(String::from)
+        .ok_or_else(|| invalid("存档中没有 level.dat"))?;
+
+    let mut data = Vec::new();
+    archive.by_name(&level_dat)?.read_to_end(&mut data)?;
+    // level.dat 以 8 字节的文件头开始：存储版本与 NBT 数据的长度
+    if data.len() < 8 {
+        return Err(invalid("存档的 level.dat 已损坏"));
+    }
+    let (_, root) = nbt::read(&data[8..], Endian::Little)?;
+
+    let mut metadata = empty_metadata(StructureFormat::BedrockWorld);
+    metadata.name = root
+        .get("LevelName")
+        .and_then(Tag::as_str)
+        .map(String::from);
+    metadata.game_version =
+        ["lastOpenedWithVersion", "MinimumCompatibleClientVersion"]
+            .iter()
+            .map(|key| list(&root, key))
+            .find(|x| !x.is_empty())
+            .map(|version| {
+                // 版本号如 [1, 21, 50, 7, 0]，只保留前三段
+                version
+                    .iter()
+                    .take(3)
+                    .filter_map(Tag::as_i64)
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            });
+    Ok(metadata)
+}
+
+/// 结构文件的数据版本高于所选的全部游戏版本时，在这些版本中无法加载，提示作者
+pub fn check_structure_metadata(
+    structure: &StructureMetadata,
+    game_versions: &[String],
+) -> Option<String> {
+    let data_version = structure.data_version?;
+    let selected = game_versions
+        .iter()
+        .filter_map(|version| {
+            DATA_VERSIONS
+                .iter()
+                .find(|(_, x)| x == version)
+                .map(|x| x.0)
+        })
+        .collect::<Vec<_>>();
+    if selected.is_empty() || selected.iter().any(|x| *x >= data_version) {
+        return None;
+    }
+
+    Some(format!(
+        "文件保存于 {}（数据版本 {data_version}），高于所选的游戏版本 {}，在这些版本中可能无法加载",
+        structure.game_version.as_deref().unwrap_or("未知版本"),
+        game_versions.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_data_versions() {
+        assert_eq!(game_version(3465), Some("1.20.1"));
+        // 1.20.2 的快照
+        assert_eq!(game_version(3567), Some("1.20.1"));
+        assert_eq!(game_version(100), None);
+    }
+
+    #[test]
+    fn counts_sponge_blocks() {
+        let palette = HashMap::from([
+            ("minecraft:air".to_string(), Tag::Int(0)),
+            ("minecraft:stone".to_string(), Tag::Int(1)),
+            (
+                "minecraft:oak_stairs[facing=north]".to_string(),
+                Tag::Int(2),
+            ),
+            (
+                "minecraft:oak_stairs[facing=south]".to_string(),
+                Tag::Int(130),
+            ),
+        ]);
+        let schematic = HashMap::from([
+            ("Version".to_string(), Tag::Int(2)),
+            ("DataVersion".to_string(), Tag::Int(3465)),
+            ("Width".to_string(), Tag::Short(2)),
+            ("Height".to_string(), Tag::Short(1)),
+            ("Length".to_string(), Tag::Short(3)),
+            ("Palette".to_string(), Tag::Compound(palette)),
+            // 索引 130 需要两个字节的 varint
+            (
+                "BlockData".to_string(),
+                Tag::ByteArray(vec![0, 1, 1, 2, 0x82, 0x01, 1]),
+            ),
+            ("BlockEntities".to_string(), Tag::List(vec![])),
+        ]);
+
+        let metadata = read_schematic(&schematic).unwrap();
+        assert_eq!(metadata.format, StructureFormat::SpongeSchematic);
+        assert_eq!(metadata.block_count, 5);
+        assert_eq!(metadata.block_types, 2);
+        assert_eq!(
+            metadata.top_materials,
+            vec![
+                MaterialCount {
+                    name: "minecraft:stone".to_string(),
+                    count: 3
+                },
+                MaterialCount {
+                    name: "minecraft:oak_stairs".to_string(),
+                    count: 2
+                },
+            ]
+        );
+        assert_eq!(metadata.game_version.as_deref(), Some("1.20.1"));
+
+        // 方块数据与尺寸不符
+        let mut truncated = schematic.clone();
+        truncated.insert("BlockData".to_string(), Tag::ByteArray(vec![0, 1]));
+        assert!(read_schematic(&truncated).is_err());
+    }
+
+    #[test]
+    fn unpacks_litematica_states() {
+        // 5 种方块需要 3 位，22 个值跨越了第一个 long 的边界
+        let values = (0..22u64).map(|x| x % 5).collect::<Vec<_>>();
+        let mut states = vec![0u64; 2];
+        for (index, value) in values.iter().enumerate() {
+            let start = index as u64 * 3;
+            states[(start / 64) as usize] |= value << (start % 64);
+            if start % 64 + 3 > 64 {
+                states[(start / 64) as usize + 1] |= value >> (64 - start % 64);
+            }
+        }
+        let states = states.into_iter().map(|x| x as i64).collect::<Vec<_>>();
+
+        let counts = count_packed_states(&states, 5, 22).unwrap();
+        assert_eq!(counts, vec![5, 5, 4, 4, 4, 0, 0, 0]);
+        assert!(count_packed_states(&states, 5, 43).is_err());
+    }
+
+    #[test]
+    fn warns_about_newer_structures() {
+        let mut metadata = empty_metadata(StructureFormat::Structure);
+        set_data_version(&mut metadata, Some(3953));
+        assert!(
+            check_structure_metadata(&metadata, &["1.20.1".to_string()])
+                .is_some()
+        );
+        assert!(
+            check_structure_metadata(
+                &metadata,
+                &["1.20.1".to_string(), "1.21".to_string()]
+            )
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! 集成测试公共设施
+//!
+//! 测试使用 `.env` 中的 PostgreSQL（`DATABASE_URL`）与 Redis（`REDIS_URL`），
+//! 需要启用 `mock-providers` feature：
+//!
+//! ```sh
+//! cargo test -p labrinth --features mock-providers --test payment_callback
+//! ```
+//!
+//! [`TestEnv::start`] 在随机端口启动挂载 v3、内部接口与 `/_mock` 的服务，
+//! 支付平台与云账户的接口地址指向模拟服务，模拟服务再把回调经 HTTP 投递回来。
+//! 凭据在进程内缓存，每个测试文件只启动一次服务。
+#![allow(dead_code)]
+
+use actix_web::dev::ServerHandle;
+use actix_web::{App, HttpServer, web};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use labrinth::database::models::{PayoutId, ProjectId, UserId};
+use labrinth::database::redis::RedisPool;
+use labrinth::models::pats::Scopes;
+use labrinth::queue::session::AuthQueue;
+use labrinth::routes::mock_providers::sevenpay::request_sign;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use std::future::Future;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 控制接口使用的管理员密钥
+pub const ADMIN_KEY: &str = "mock-admin-key";
+/// 模拟支付平台商户
+pub const SEVENPAY_SID: &str = "1001";
+pub const SEVENPAY_SECRET: &str = "mock-merchant-secret";
+
+pub struct TestEnv {
+    pub pool: PgPool,
+    pub redis: RedisPool,
+    pub base_url: String,
+    pub client: reqwest::Client,
+    server: ServerHandle,
+}
+
+impl TestEnv {
+    pub async fn start() -> Self {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").expect("绑定测试端口失败");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        configure_env(&base_url);
+
+        labrinth::database::check_for_migrations()
+            .await
+            .expect("运行数据库迁移失败");
+        let pool = labrinth::database::connect().await.expect("连接数据库失败");
+        let redis = RedisPool::new(Some(format!("test_{}", random_string(8))));
+        let session_queue = web::Data::new(AuthQueue::new());
+
+        let server = {
+            let pool = pool.clone();
+            let redis = redis.clone();
+            HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(pool.clone()))
+                    .app_data(web::Data::new(redis.clone()))
+                    .app_data(session_queue.clone())
+                    .configure(labrinth::routes::v3::config)
+                    .configure(labrinth::routes::internal::config)
+                    .configure(labrinth::routes::mock_providers::config)
+            })
+            .workers(1)
+            .listen(listener)
+            .expect("启动测试服务失败")
+            .run()
+        };
+        let server_handle = server.handle();
+        actix_rt::spawn(server);
+
+        Self {
+            pool,
+            redis,
+            base_url,
+            client: reqwest::Client::new(),
+            server: server_handle,
+        }
+    }
+
+    pub async fn stop(self) {
+        self.server.stop(true).await;
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    // ==================== 模拟服务 ====================
+
+    /// 调用模拟服务的控制接口，`admin` 为是否携带管理员密钥
+    pub async fn mock_post(
+        &self,
+        path: &str,
+        body: Value,
+        admin: bool,
+    ) -> reqwest::Response {
+        let mut request = self.client.post(self.url(path)).json(&body);
+        if admin {
+            request = request.header("Modrinth-Admin", ADMIN_KEY);
+        }
+        request.send().await.expect("请求模拟服务失败")
+    }
+
+    /// 为某类操作预设结果
+    pub async fn set_scenario(&self, scenario: Value) {
+        let resp = self.mock_post("/_mock/scenarios", scenario, true).await;
+        assert!(
+            resp.status().is_success(),
+            "预设结果失败: {}",
+            resp.status()
+        );
+    }
+
+    /// 模拟服务的回调投递记录
+    pub async fn deliveries(&self) -> Vec<Value> {
+        self.client
+            .get(self.url("/_mock/deliveries"))
+            .header("Modrinth-Admin", ADMIN_KEY)
+            .send()
+            .await
+            .expect("查询投递记录失败")
+            .json()
+            .await
+            .expect("解析投递记录失败")
+    }
+
+    /// 以商户身份在模拟支付平台下单，金额单位为分
+    pub async fn sevenpay_create_order(
+        &self,
+        order_no: &str,
+        money: &str,
+    ) -> Value {
+        let title = "测试订单";
+        let pay_type = "2";
+        let display_name = "测试用户";
+        let sign = request_sign(
+            &[order_no, SEVENPAY_SID, title, pay_type, display_name, money],
+            SEVENPAY_SECRET,
+        );
+        self.client
+            .get(self.url("/_mock/sevenpay/createOrder"))
+            .header("Authorization", sign)
+            .query(&[
+                ("orderNo", order_no),
+                ("sid", SEVENPAY_SID),
+                ("title", title),
+                ("payType", pay_type),
+                ("userDisplayName", display_name),
+                ("money", money),
+            ])
+            .send()
+            .await
+            .expect("模拟支付平台下单失败")
+            .json()
+            .await
+            .expect("解析下单结果失败")
+    }
+
+    // ==================== 测试数据 ====================
+
+    pub async fn insert_user(&self, role: &str) -> UserId {
+        let id = random_id();
+        sqlx::query(
+            "INSERT INTO users (id, username, role) VALUES ($1, $2, $3)",
+        )
+        .bind(id)
+        .bind(format!("test_{}", random_string(12)))
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .expect("创建测试用户失败");
+        UserId(id)
+    }
+
+    /// 为用户创建拥有全部权限的个人访问令牌
+    pub async fn insert_pat(&self, user_id: UserId) -> String {
+        let token = format!("mrp_{}", random_string(32));
+        sqlx::query(
+            "
+            INSERT INTO pats (id, name, user_id, access_token, scopes, expires)
+            VALUES ($1, $2, $3, $4, $5, NOW() + INTERVAL '1 day')
+            ",
+        )
+        .bind(random_id())
+        .bind("集成测试")
+        .bind(user_id.0)
+        .bind(&token)
+        .bind(Scopes::all().bits() as i64)
+        .execute(&self.pool)
+        .await
+        .expect("创建测试令牌失败");
+        token
+    }
+
+    /// 创建由 `owner` 所有的付费项目
+    pub async fn insert_paid_project(&self, owner: UserId) -> ProjectId {
+        let team_id = random_id();
+        let project_id = random_id();
+        sqlx::query("INSERT INTO teams (id) VALUES ($1)")
+            .bind(team_id)
+            .execute(&self.pool)
+            .await
+            .expect("创建测试团队失败");
+        sqlx::query(
+            "
+            INSERT INTO team_members (id, team_id, user_id, role, is_owner, accepted)
+            VALUES ($1, $2, $3, 'Owner', TRUE, TRUE)
+            ",
+        )
+        .bind(random_id())
+        .bind(team_id)
+        .bind(owner.0)
+        .execute(&self.pool)
+        .await
+        .expect("创建测试团队成员失败");
+        sqlx::query(
+            "
+            INSERT INTO mods (id, team_id, name, summary, status, is_paid)
+            VALUES ($1, $2, $3, '集成测试项目', 'approved', TRUE)
+            ",
+        )
+        .bind(project_id)
+        .bind(team_id)
+        .bind(format!("test_{}", random_string(12)))
+        .execute(&self.pool)
+        .await
+        .expect("创建测试项目失败");
+        ProjectId(project_id)
+    }
+
+    /// 创建一笔待支付的单项目订单，金额单位为元，平台抽成 10%
+    pub async fn insert_pending_order(
+        &self,
+        buyer: UserId,
+        seller: UserId,
+        project_id: ProjectId,
+        amount: Decimal,
+    ) -> String {
+        let order_no = format!("TEST{}", random_string(16).to_uppercase());
+        let platform_fee = (amount * Decimal::new(10, 2)).round_dp(2);
+        let seller_amount = amount - platform_fee;
+
+        let order_id: i64 = sqlx::query_scalar(
+            "
+            INSERT INTO payment_orders (
+                order_no, user_id, project_id, seller_id, amount,
+                platform_fee, seller_amount, original_amount
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $5)
+            RETURNING id
+            ",
+        )
+        .bind(&order_no)
+        .bind(buyer.0)
+        .bind(project_id.0)
+        .bind(seller.0)
+        .bind(amount)
+        .bind(platform_fee)
+        .bind(seller_amount)
+        .fetch_one(&self.pool)
+        .await
+        .expect("创建测试订单失败");
+
+        sqlx::query(
+            "
+            INSERT INTO payment_order_items (
+                order_id, project_id, original_amount, amount,
+                platform_fee, seller_amount
+            )
+            VALUES ($1, $2, $3, $3, $4, $5)
+            ",
+        )
+        .bind(order_id)
+        .bind(project_id.0)
+        .bind(amount)
+        .bind(platform_fee)
+        .bind(seller_amount)
+        .execute(&self.pool)
+        .await
+        .expect("创建测试订单明细失败");
+
+        order_no
+    }
+
+    /// 创建一笔等待管理员确认的云账户支付宝提现，并记录提现分录
+    pub async fn insert_yunzhanghu_payout(
+        &self,
+        user_id: UserId,
+        alipay_account: &str,
+        amount: Decimal,
+    ) -> PayoutId {
+        let payout_id = PayoutId(random_id());
+        let mut transaction = self.pool.begin().await.unwrap();
+        sqlx::query(
+            "
+            INSERT INTO payouts (id, user_id, amount, fee, status, method, method_address)
+            VALUES ($1, $2, $3, 0, 'in-transit', 'yunzhanghu_alipay', $4)
+            ",
+        )
+        .bind(payout_id.0)
+        .bind(user_id.0)
+        .bind(amount)
+        .bind(alipay_account)
+        .execute(&mut *transaction)
+        .await
+        .expect("创建测试提现失败");
+        labrinth::database::models::LedgerEntry::record_withdrawal(
+            payout_id,
+            user_id,
+            amount,
+            Decimal::ZERO,
+            &mut transaction,
+        )
+        .await
+        .expect("记录提现分录失败");
+        transaction.commit().await.unwrap();
+        payout_id
+    }
+
+    pub async fn order_status(&self, order_no: &str) -> String {
+        sqlx::query_scalar(
+            "SELECT status FROM payment_orders WHERE order_no = $1",
+        )
+        .bind(order_no)
+        .fetch_one(&self.pool)
+        .await
+        .expect("查询订单状态失败")
+    }
+
+    pub async fn payout_status(&self, payout_id: PayoutId) -> String {
+        sqlx::query_scalar("SELECT status FROM payouts WHERE id = $1")
+            .bind(payout_id.0)
+            .fetch_one(&self.pool)
+            .await
+            .expect("查询提现状态失败")
+    }
+}
+
+/// 设置服务与模拟服务需要的环境变量，必须在首次请求前调用
+fn configure_env(base_url: &str) {
+    let key_dir = std::env::temp_dir()
+        .join(format!("labrinth-test-{}", random_string(8)));
+    std::fs::create_dir_all(&key_dir).expect("创建密钥目录失败");
+    let (dealer_private, _) = write_key_pair(&key_dir, "dealer");
+    let (platform_private, platform_public) =
+        write_key_pair(&key_dir, "platform");
+
+    let encryption_key = BASE64.encode(rand::thread_rng().r#gen::<[u8; 32]>());
+
+    let vars = [
+        ("MOCK_PAYMENT_PROVIDERS", "true".to_string()),
+        ("LABRINTH_ADMIN_KEY", ADMIN_KEY.to_string()),
+        ("SELF_ADDR", base_url.to_string()),
+        ("ENCRYPTION_KEY", encryption_key),
+        ("SEVENPAY_API_URL", format!("{base_url}/_mock/sevenpay")),
+        ("SEVENPAY_CREATE_ORDER_PATH", "/createOrder".to_string()),
+        ("SEVENPAY_QUERY_ORDER_PATH", "/queryOrder".to_string()),
+        ("SEVENPAY_REFUND_ORDER_PATH", "/refundOrder".to_string()),
+        ("SEVENPAY_SHIP_ORDER_PATH", "/shipOrder".to_string()),
+        (
+            "SEVENPAY_VERIFY_MERCHANT_PATH",
+            "/verifyMerchant".to_string(),
+        ),
+        ("SEVENPAY_KEYCODE", "mock-keycode".to_string()),
+        ("SEVENPAY_ALLOWED_IPS", "127.0.0.1".to_string()),
+        (
+            "MOCK_SEVENPAY_MERCHANTS",
+            format!("{SEVENPAY_SID}:{SEVENPAY_SECRET}"),
+        ),
+        ("YUNZHANGHU_API_URL", format!("{base_url}/_mock/yunzhanghu")),
+        ("YUNZHANGHU_DEALER_ID", "mock-dealer".to_string()),
+        ("YUNZHANGHU_BROKER_ID", "mock-broker".to_string()),
+        ("YUNZHANGHU_APP_KEY", random_string(32)),
+        ("YUNZHANGHU_3DES_KEY", random_string(24)),
+        (
+            "YUNZHANGHU_DEALER_PRIVATE_KEY_PATH",
+            path_string(dealer_private),
+        ),
+        (
+            "YUNZHANGHU_PLATFORM_PUBLIC_KEY_PATH",
+            path_string(platform_public),
+        ),
+        (
+            "MOCK_YUNZHANGHU_PLATFORM_PRIVATE_KEY_PATH",
+            path_string(platform_private),
+        ),
+    ];
+
+    for (name, value) in vars {
+        // SAFETY: 在启动服务与任何后台任务之前调用，此时只有测试线程读写环境变量
+        unsafe { std::env::set_var(name, value) };
+    }
+}
+
+/// 生成 RSA 密钥对，返回私钥与公钥文件路径
+fn write_key_pair(dir: &std::path::Path, name: &str) -> (PathBuf, PathBuf) {
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+        .expect("生成 RSA 密钥失败");
+    let public_key = RsaPublicKey::from(&private_key);
+
+    let private_path = dir.join(format!("{name}_private.pem"));
+    let public_path = dir.join(format!("{name}_public.pem"));
+    std::fs::write(
+        &private_path,
+        private_key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes(),
+    )
+    .expect("写入私钥失败");
+    std::fs::write(
+        &public_path,
+        public_key.to_public_key_pem(LineEnding::LF).unwrap(),
+    )
+    .expect("写入公钥失败");
+
+    (private_path, public_path)
+}
+
+fn path_string(path: PathBuf) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+pub fn random_id() -> i64 {
+    rand::thread_rng().gen_range(1_000_000_000..(1_i64 << 52))
+}
+
+pub fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// 轮询直到 `check` 返回 `Some`，回调异步投递，需要等待一段时间
+pub async fn eventually<T, F, Fut>(what: &str, mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    for _ in 0..100 {
+        if let Some(value) = check().await {
+            return value;
+        }
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("等待超时: {what}");
+}
+
+/// 等待一段时间，确认异步回调没有产生预期之外的变化
+pub async fn settle() {
+    actix_rt::time::sleep(Duration::from_millis(1500)).await;
+}
+
+pub fn scenario(kind: &str, key: &str, outcome: Value) -> Value {
+    json!({
+        "kind": kind,
+        "key": key,
+        "outcome": outcome,
+        "callback_delay_ms": 0,
+    })
+}
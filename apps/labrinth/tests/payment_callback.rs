@@ -0,0 +1,164 @@
+//! 支付平台回调端到端测试：模拟支付平台下单后把回调投递到
+//! `/_internal/payment/callback`，验证订单支付、重复回调与拒付退款。
+
+mod common;
+
+use common::{TestEnv, eventually, scenario, settle};
+use rust_decimal::Decimal;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn payment_callback_drives_order_state() {
+    let env = TestEnv::start().await;
+
+    control_routes_require_admin_key(&env).await;
+    paid_callback_grants_purchase_once(&env).await;
+    bad_signature_is_ignored(&env).await;
+
+    env.stop().await;
+}
+
+async fn control_routes_require_admin_key(env: &TestEnv) {
+    let resp = env
+        .mock_post(
+            "/_mock/scenarios",
+            json!({ "kind": "sevenpay_order", "outcome": { "type": "reject" } }),
+            false,
+        )
+        .await;
+    assert_eq!(resp.status(), 404);
+
+    let resp = env
+        .mock_post(
+            "/_mock/sevenpay/orders/unknown/notify",
+            json!({ "trade_state": "REFUND" }),
+            false,
+        )
+        .await;
+    assert_eq!(resp.status(), 404);
+}
+
+async fn paid_callback_grants_purchase_once(env: &TestEnv) {
+    let buyer = env.insert_user("developer").await;
+    let seller = env.insert_user("developer").await;
+    let project_id = env.insert_paid_project(seller).await;
+    let order_no = env
+        .insert_pending_order(buyer, seller, project_id, Decimal::new(990, 2))
+        .await;
+
+    // 回调重复投递一次，购买记录只能创建一条
+    let mut success =
+        scenario("sevenpay_order", &order_no, json!({ "type": "success" }));
+    success["duplicates"] = json!(1);
+    env.set_scenario(success).await;
+
+    let resp = env.sevenpay_create_order(&order_no, "990").await;
+    assert_eq!(resp["code"], 200, "下单失败: {resp}");
+
+    eventually("订单变为已支付", || async {
+        (env.order_status(&order_no).await == "paid").then_some(())
+    })
+    .await;
+
+    let deliveries = eventually("回调投递两次", || async {
+        let deliveries = env
+            .deliveries()
+            .await
+            .into_iter()
+            .filter(|x| {
+                x["url"]
+                    .as_str()
+                    .is_some_and(|x| x.ends_with("/_internal/payment/callback"))
+            })
+            .filter(|x| !x["status"].is_null())
+            .collect::<Vec<_>>();
+        (deliveries.len() >= 2).then_some(deliveries)
+    })
+    .await;
+    assert!(deliveries.iter().all(|x| x["status"] == 200));
+
+    let purchases: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM user_purchases WHERE user_id = $1 AND project_id = $2",
+    )
+    .bind(buyer.0)
+    .bind(project_id.0)
+    .fetch_one(&env.pool)
+    .await
+    .unwrap();
+    assert_eq!(purchases, 1);
+
+    // 买家拒付：支付平台推送 REFUND，订单退款并记录拒付
+    let resp = env
+        .mock_post(
+            &format!("/_mock/sevenpay/orders/{order_no}/notify"),
+            json!({ "trade_state": "REFUND", "callback_delay_ms": 0 }),
+            true,
+        )
+        .await;
+    assert_eq!(resp.status(), 204);
+
+    eventually("订单变为已退款", || async {
+        (env.order_status(&order_no).await == "refunded").then_some(())
+    })
+    .await;
+
+    let (status, source): (String, String) = sqlx::query_as(
+        "
+        SELECT r.status, r.source
+        FROM payment_refunds r
+        INNER JOIN payment_orders o ON o.id = r.order_id
+        WHERE o.order_no = $1
+        ",
+    )
+    .bind(&order_no)
+    .fetch_one(&env.pool)
+    .await
+    .unwrap();
+    assert_eq!(status, "refunded");
+    assert_eq!(source, "chargeback");
+
+    let purchase_status: String = sqlx::query_scalar(
+        "SELECT status FROM user_purchases WHERE user_id = $1 AND project_id = $2",
+    )
+    .bind(buyer.0)
+    .bind(project_id.0)
+    .fetch_one(&env.pool)
+    .await
+    .unwrap();
+    assert_eq!(purchase_status, "refunded");
+}
+
+async fn bad_signature_is_ignored(env: &TestEnv) {
+    let buyer = env.insert_user("developer").await;
+    let seller = env.insert_user("developer").await;
+    let project_id = env.insert_paid_project(seller).await;
+    let order_no = env
+        .insert_pending_order(buyer, seller, project_id, Decimal::new(500, 2))
+        .await;
+
+    let mut forged =
+        scenario("sevenpay_order", &order_no, json!({ "type": "success" }));
+    forged["bad_signature"] = json!(true);
+    env.set_scenario(forged).await;
+
+    let resp = env.sevenpay_create_order(&order_no, "500").await;
+    assert_eq!(resp["code"], 200, "下单失败: {resp}");
+
+    eventually("伪造签名的回调已投递", || async {
+        env.deliveries()
+            .await
+            .into_iter()
+            .any(|x| {
+                x["bad_signature"] == true
+                    && !x["status"].is_null()
+                    && x["response"]
+                        .as_str()
+                        .is_some_and(|x| x.contains("签名验证失败"))
+            })
+            .then_some(())
+    })
+    .await;
+    settle().await;
+
+    assert_eq!(env.order_status(&order_no).await, "pending");
+}
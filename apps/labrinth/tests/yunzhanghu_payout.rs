@@ -0,0 +1,127 @@
+//! 云账户提现端到端测试：管理员确认提现后由模拟云账户受理，再通过订单回调
+//! 把提现改为失败或撤销，验证提现分录只冲正一次、余额回到创作者账户。
+
+mod common;
+
+use common::{TestEnv, eventually, scenario, settle};
+use labrinth::database::models::UserId;
+use labrinth::database::models::yunzhanghu_profile_item::{
+    YunzhanghuProfile, YzhSignStatus,
+};
+use labrinth::models::ids::PayoutId;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+const ALIPAY_ACCOUNT: &str = "creator@example.com";
+
+#[actix_rt::test]
+async fn order_callback_reverses_failed_payouts() {
+    let env = TestEnv::start().await;
+
+    let admin = env.insert_user("admin").await;
+    let admin_token = env.insert_pat(admin).await;
+    let creator = env.insert_user("developer").await;
+    YunzhanghuProfile::upsert_kyc(
+        &env.pool,
+        creator,
+        "张三",
+        "110101199003074514",
+        "13800138000",
+        ALIPAY_ACCOUNT,
+    )
+    .await
+    .unwrap();
+    YunzhanghuProfile::update_sign_status(
+        &env.pool,
+        creator,
+        YzhSignStatus::Signed,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // 2：打款失败；5：订单被撤销
+    for (status, expected) in [("2", "failed"), ("5", "cancelled")] {
+        let payout_id = env
+            .insert_yunzhanghu_payout(
+                creator,
+                ALIPAY_ACCOUNT,
+                Decimal::new(10_000, 2),
+            )
+            .await;
+        let public_id = PayoutId(payout_id.0 as u64);
+        let order_id = format!("bbsmc-{public_id}");
+
+        env.set_scenario(scenario(
+            "yunzhanghu_order",
+            &order_id,
+            json!({ "type": "pending" }),
+        ))
+        .await;
+
+        let resp = env
+            .client
+            .post(env.url(&format!("/v3/payout/admin/{public_id}/confirm")))
+            .header("Authorization", &admin_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.status(),
+            200,
+            "确认提现失败: {}",
+            resp.text().await.unwrap()
+        );
+        assert_eq!(env.payout_status(payout_id).await, "in-transit");
+
+        // 回调重复投递一次，提现分录只能冲正一次
+        let resp = env
+            .mock_post(
+                &format!("/_mock/yunzhanghu/orders/{order_id}/notify"),
+                json!({
+                    "status": status,
+                    "status_detail_message": "模拟打款失败",
+                    "duplicates": 1,
+                    "callback_delay_ms": 0,
+                }),
+                true,
+            )
+            .await;
+        assert_eq!(resp.status(), 204);
+
+        eventually("提现状态更新", || async {
+            (env.payout_status(payout_id).await == expected).then_some(())
+        })
+        .await;
+        settle().await;
+
+        let reversals: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM ledger_entries WHERE kind = 'reversal' AND reference = $1",
+        )
+        .bind(payout_id.0.to_string())
+        .fetch_one(&env.pool)
+        .await
+        .unwrap();
+        assert_eq!(reversals, 1, "状态 {status} 的冲正分录数量不正确");
+
+        assert_eq!(creator_payable(&env, creator).await, Decimal::ZERO);
+    }
+
+    env.stop().await;
+}
+
+/// 创作者应付余额
+async fn creator_payable(env: &TestEnv, user_id: UserId) -> Decimal {
+    sqlx::query_scalar(
+        "
+        SELECT COALESCE(SUM(amount), 0)
+        FROM ledger_lines
+        WHERE user_id = $1 AND account = 'creator_payable'
+        ",
+    )
+    .bind(user_id.0)
+    .fetch_one(&env.pool)
+    .await
+    .unwrap()
+}